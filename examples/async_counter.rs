@@ -0,0 +1,66 @@
+//! Runs the application's logic as an async task on a thread of its own through
+//! `AsyncWindowAdapter`. Left clicking increments a counter and right clicking decrements it. The
+//! counter is drawn on the next frame, which here means printing it, and the cursor shows whether
+//! it's even or odd. Press Escape to close the window from the async task.
+
+use std::thread;
+
+use baseview::keyboard_types::{Key, KeyState};
+use baseview::{
+    AsyncWindowAdapter, Event, EventContext, FrameStream, MouseButton, MouseCursor, MouseEvent,
+    Size, WindowCommander, WindowEventStream, WindowScalePolicy,
+};
+use futures::executor::block_on;
+use futures::{select, StreamExt};
+
+async fn run(events: WindowEventStream, frames: FrameStream, commander: WindowCommander) {
+    let mut events = events.fuse();
+    let mut frames = frames.fuse();
+    let mut counter = 0i64;
+    let mut drawn_counter = None;
+
+    loop {
+        select! {
+            event = events.next() => match event {
+                Some(Event::Mouse(MouseEvent::ButtonPressed { button, .. })) => {
+                    match button {
+                        MouseButton::Left => counter += 1,
+                        MouseButton::Right => counter -= 1,
+                        _ => continue,
+                    }
+                    let cursor =
+                        if counter % 2 == 0 { MouseCursor::Default } else { MouseCursor::Hand };
+                    commander.set_mouse_cursor(cursor);
+                }
+                Some(Event::Keyboard(event))
+                    if event.state == KeyState::Down && event.key == Key::Escape =>
+                {
+                    commander.close();
+                }
+                Some(_) => (),
+                None => break,
+            },
+            frame = frames.next() => {
+                let Some(frame) = frame else { continue };
+                if drawn_counter != Some(counter) {
+                    println!("Frame {}: the counter is {}", frame, counter);
+                    drawn_counter = Some(counter);
+                }
+            },
+        }
+    }
+
+    println!("The window closed, {} events were dropped", events.get_ref().dropped_events());
+}
+
+fn main() {
+    let (adapter, events, frames, commander) = AsyncWindowAdapter::new(256);
+    let task = thread::spawn(move || block_on(run(events, frames, commander)));
+
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Async counter", Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+    EventContext::open_blocking(window_open_options, |_| adapter);
+
+    task.join().unwrap();
+}
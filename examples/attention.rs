@@ -0,0 +1,61 @@
+//! Requests the user's attention a while after the window has opened. Switch to another window
+//! right after starting the example to see the effect. Clicking the window or pressing any key
+//! cancels the request.
+
+use std::time::{Duration, Instant};
+
+use baseview::{
+    AttentionType, Event, EventContext, EventStatus, MouseEvent, WindowHandler, WindowScalePolicy,
+};
+
+/// The informational request is made after this much time, and the critical one after twice as
+/// long.
+const DELAY: Duration = Duration::from_secs(5);
+
+struct AttentionExample {
+    opened_at: Instant,
+    /// The requests that haven't been made yet, in order.
+    pending_requests: Vec<(Duration, AttentionType)>,
+}
+
+impl WindowHandler for AttentionExample {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let elapsed = self.opened_at.elapsed();
+        while let Some(&(time, attention)) = self.pending_requests.first() {
+            if elapsed < time {
+                break;
+            }
+
+            println!("Requesting attention: {:?}", attention);
+            window.request_attention(attention);
+            self.pending_requests.remove(0);
+        }
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Mouse(MouseEvent::ButtonPressed { .. }) | Event::Keyboard(_) => {
+                println!("Stopping the attention request");
+                window.stop_attention();
+            }
+            _ => (),
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("baseview", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true);
+
+    EventContext::open_blocking(window_open_options, |_| AttentionExample {
+        opened_at: Instant::now(),
+        pending_requests: vec![
+            (DELAY, AttentionType::Informational),
+            (DELAY * 2, AttentionType::Critical),
+        ],
+    });
+}
@@ -0,0 +1,69 @@
+//! A tiny standalone tool that hands its result back to `main()`. Press a number key to pick a
+//! value and Enter to confirm it, or close the window to pick nothing.
+//!
+//! On macOS the tool runs as a regular app with a Dock icon and a main menu. Pass `--accessory` to
+//! run it as an accessory app instead, which has neither, like a helper utility.
+
+use baseview::keyboard_types::{Key, KeyState};
+use baseview::{
+    ActivationPolicy, Event, EventContext, EventStatus, WindowHandler, WindowHandlerWithResult,
+    WindowScalePolicy,
+};
+
+#[derive(Default)]
+struct PickNumber {
+    picked: Option<u32>,
+    confirmed: bool,
+}
+
+impl WindowHandler for PickNumber {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Keyboard(event) = event else { return EventStatus::Ignored };
+        if event.state != KeyState::Down {
+            return EventStatus::Ignored;
+        }
+
+        match &event.key {
+            Key::Character(c) if c.parse::<u32>().is_ok() => {
+                self.picked = c.parse().ok();
+                println!("Picked {}, press Enter to confirm", c);
+            }
+            Key::Enter if self.picked.is_some() => {
+                self.confirmed = true;
+                window.close();
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+impl WindowHandlerWithResult for PickNumber {
+    type Output = Option<u32>;
+
+    fn on_close(self: Box<Self>) -> Option<u32> {
+        self.picked.filter(|_| self.confirmed)
+    }
+}
+
+fn main() {
+    let accessory = std::env::args().any(|arg| arg == "--accessory");
+    let activation_policy =
+        if accessory { ActivationPolicy::Accessory } else { ActivationPolicy::Regular };
+
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Pick a number", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true)
+            .macos_activation_policy(Some(activation_policy))
+            .macos_install_menu(!accessory);
+
+    match EventContext::open_blocking_with_result(window_open_options, |_| PickNumber::default()) {
+        Some(Some(number)) => println!("The window returned {}", number),
+        Some(None) => println!("The window was closed without picking a number"),
+        None => println!("The window's handler panicked"),
+    }
+}
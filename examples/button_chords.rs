@@ -0,0 +1,128 @@
+//! Picks a tool based on the full chord of mouse buttons and keys that are held down, like a
+//! drawing program would. Dragging with the left button draws, dragging with the middle button or
+//! with the left button while holding Space pans, and dragging with both the left and the right
+//! button zooms. The examples don't have a renderer, so instead of drawing this prints what every
+//! drag did once its buttons are released. Press and release buttons in different orders to check
+//! that the chord always matches the buttons that are physically held down.
+
+use baseview::keyboard_types::Code;
+use baseview::{
+    Event, EventContext, EventStatus, MouseButtonSet, MouseEvent, Point, WindowHandler,
+    WindowScalePolicy,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tool {
+    Draw,
+    Pan,
+    Zoom,
+}
+
+impl Tool {
+    /// The tool for a chord, if there is one.
+    fn for_chord(buttons: MouseButtonSet, pressed_keys: &[Code]) -> Option<Tool> {
+        let left_and_right = MouseButtonSet::LEFT | MouseButtonSet::RIGHT;
+        if buttons.contains(left_and_right) {
+            Some(Tool::Zoom)
+        } else if buttons.contains(MouseButtonSet::MIDDLE)
+            || (buttons.contains(MouseButtonSet::LEFT) && pressed_keys.contains(&Code::Space))
+        {
+            Some(Tool::Pan)
+        } else if buttons.contains(MouseButtonSet::LEFT) {
+            Some(Tool::Draw)
+        } else {
+            None
+        }
+    }
+}
+
+struct ButtonChordsExample {
+    last_position: Option<Point>,
+    /// The tool of the current drag, and how far the cursor moved with it.
+    drag: Option<(Tool, f64)>,
+    stroke_points: usize,
+    pan_offset: (f64, f64),
+    zoom: f64,
+}
+
+impl ButtonChordsExample {
+    fn finish_drag(&mut self) {
+        let Some((tool, distance)) = self.drag.take() else { return };
+        match tool {
+            Tool::Draw => println!(
+                "Drew a stroke {:.0} points long, {} stroke points were drawn in total",
+                distance, self.stroke_points
+            ),
+            Tool::Pan => println!(
+                "Panned {:.0} points, the view is at {:.0},{:.0}",
+                distance, self.pan_offset.0, self.pan_offset.1
+            ),
+            Tool::Zoom => println!("Zoomed to {:.0}%", self.zoom * 100.0),
+        }
+    }
+
+    fn cursor_moved(&mut self, position: Point, tool: Option<Tool>) {
+        let (dx, dy) = match self.last_position.replace(position) {
+            Some(last) => (position.x - last.x, position.y - last.y),
+            None => (0.0, 0.0),
+        };
+
+        // Changing the chord in the middle of a drag switches to a different tool
+        if self.drag.map(|(drag_tool, _)| drag_tool) != tool {
+            self.finish_drag();
+            self.drag = tool.map(|tool| (tool, 0.0));
+        }
+        let Some((tool, distance)) = &mut self.drag else { return };
+
+        *distance += dx.hypot(dy);
+        match tool {
+            Tool::Draw => self.stroke_points += 1,
+            Tool::Pan => {
+                self.pan_offset.0 += dx;
+                self.pan_offset.1 += dy;
+            }
+            Tool::Zoom => self.zoom = (self.zoom * (1.0 - dy / 200.0)).clamp(0.1, 10.0),
+        }
+    }
+}
+
+impl WindowHandler for ButtonChordsExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Mouse(event) = event else {
+            return EventStatus::Ignored;
+        };
+
+        match event {
+            MouseEvent::CursorMoved { position, buttons, .. } => {
+                let tool = Tool::for_chord(buttons, &window.pressed_keys());
+                self.cursor_moved(position, tool);
+            }
+            MouseEvent::ButtonPressed { button, buttons, .. }
+            | MouseEvent::ButtonReleased { button, buttons, .. } => {
+                println!("{:?} changed, the chord is now {:?}", button, buttons);
+                if buttons.is_empty() {
+                    self.finish_drag();
+                }
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Button chords", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| ButtonChordsExample {
+        last_position: None,
+        drag: None,
+        stroke_points: 0,
+        pan_offset: (0.0, 0.0),
+        zoom: 1.0,
+    });
+}
@@ -0,0 +1,95 @@
+use std::collections::vec_deque::Drain;
+use std::time::Duration;
+
+use keyboard_types::{Code, KeyState};
+
+use baseview::{
+    ChannelHandler, Event, EventContext, MessageHandler, MouseButton, MouseCursor, MouseEvent,
+    WindowScalePolicy,
+};
+
+const MOUSE_CURSORS: &[MouseCursor] = &[
+    MouseCursor::Default,
+    MouseCursor::Hand,
+    MouseCursor::Text,
+    MouseCursor::Crosshair,
+    MouseCursor::NotAllowed,
+];
+
+/// Everything the application reacts to. Events from the window and messages from the background
+/// thread end up in the same queue.
+#[derive(Debug)]
+enum Message {
+    Event(Event),
+    Hello,
+}
+
+impl From<Event> for Message {
+    fn from(event: Event) -> Self {
+        Message::Event(event)
+    }
+}
+
+/// The same as the `open_window` example, but all of the logic runs once per frame in `update()`.
+struct ChannelExample {
+    cursor_index: usize,
+    slow_frames: bool,
+}
+
+impl MessageHandler<Message> for ChannelExample {
+    fn update(&mut self, window: &mut EventContext, messages: Drain<'_, Message>) {
+        for message in messages {
+            match message {
+                Message::Event(event) => {
+                    println!("{}", event);
+
+                    match event {
+                        // Left clicking cycles through some of the mouse cursors
+                        Event::Mouse(MouseEvent::ButtonPressed {
+                            button: MouseButton::Left,
+                            ..
+                        }) => {
+                            self.cursor_index = (self.cursor_index + 1) % MOUSE_CURSORS.len();
+                            window.set_mouse_cursor(MOUSE_CURSORS[self.cursor_index]);
+                        }
+                        // The F key switches between 60 and 10 frames per second. Messages are
+                        // only handled once per frame, so input lags behind at the lower rate.
+                        Event::Keyboard(e)
+                            if e.state == KeyState::Down && !e.repeat && e.code == Code::KeyF =>
+                        {
+                            self.slow_frames = !self.slow_frames;
+                            let fps = if self.slow_frames { 10 } else { 60 };
+                            println!("Frame rate: {} fps", fps);
+                            window.set_frame_interval(Duration::from_secs(1) / fps);
+                        }
+                        _ => (),
+                    }
+                }
+                Message::Hello => println!("Message: Hello"),
+            }
+        }
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("baseview", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true);
+
+    EventContext::open_blocking(window_open_options, |_| {
+        let handler = ChannelHandler::new(ChannelExample { cursor_index: 0, slow_frames: false });
+
+        let sender = handler.sender();
+        ::std::thread::spawn(move || loop {
+            ::std::thread::sleep(Duration::from_secs(5));
+
+            if sender.send(Message::Hello).is_err() {
+                // The window has been closed
+                break;
+            }
+        });
+
+        handler
+    });
+}
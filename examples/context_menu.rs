@@ -0,0 +1,195 @@
+//! Simulates a plugin host that opens a native context menu while an editor is animating a level
+//! meter. The host is a plain Win32 window with its own message loop, and the editor is opened
+//! inside of it. Right-click the strip below the meter to open the host's context menu. The meter
+//! should keep moving smoothly while the menu is open, and the longest gap between two frames
+//! during that time is printed once the menu has been closed. This example only does something on
+//! Windows, since that's where modal loops used to starve the frame timer.
+
+#[cfg(target_os = "windows")]
+fn main() {
+    platform::run();
+}
+
+#[cfg(not(target_os = "windows"))]
+fn main() {
+    println!("This example only runs on Windows");
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    use baseview::{Event, EventContext, EventStatus, Size, WindowHandler, WindowScalePolicy};
+    use rwh_05::{HasRawWindowHandle, RawWindowHandle, Win32WindowHandle};
+    use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::{HWND, POINT, RECT};
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::wingdi::{CreateSolidBrush, DeleteObject, RGB};
+    use winapi::um::winuser::{
+        AppendMenuW, ClientToScreen, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu,
+        DispatchMessageW, FillRect, GetDC, GetMessageW, PostQuitMessage, RegisterClassW, ReleaseDC,
+        TrackPopupMenu, TranslateMessage, CW_USEDEFAULT, MF_STRING, MSG, TPM_RIGHTBUTTON,
+        WM_DESTROY, WM_RBUTTONUP, WNDCLASSW, WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+    };
+
+    /// The editor's size in physical pixels. The host's client area has a strip below it that
+    /// opens the context menu.
+    const EDITOR_SIZE: (u32, u32) = (400, 60);
+    const HOST_SIZE: (i32, i32) = (420, 160);
+    /// How long the meter takes to go from empty to full and back.
+    const METER_PERIOD: Duration = Duration::from_secs(2);
+
+    /// Set by the host while its context menu is open.
+    static MENU_OPEN: AtomicBool = AtomicBool::new(false);
+
+    /// Draws a bar that moves back and forth on every frame, using GDI to keep the example short.
+    struct Meter {
+        opened_at: Instant,
+        last_frame: Option<Instant>,
+        /// The longest gap between two frames while the menu was open, or `None` if the menu isn't
+        /// open.
+        longest_gap_in_menu: Option<Duration>,
+    }
+
+    impl WindowHandler for Meter {
+        fn on_frame(&mut self, window: &mut EventContext) {
+            let now = Instant::now();
+            let gap = self.last_frame.replace(now).map(|last_frame| now - last_frame);
+            match (MENU_OPEN.load(Ordering::Relaxed), self.longest_gap_in_menu) {
+                (true, longest_gap) => {
+                    self.longest_gap_in_menu =
+                        Some(longest_gap.unwrap_or_default().max(gap.unwrap_or_default()));
+                }
+                (false, Some(longest_gap)) => {
+                    println!(
+                        "The longest gap between frames while the menu was open was {:?}",
+                        longest_gap
+                    );
+                    self.longest_gap_in_menu = None;
+                }
+                (false, None) => (),
+            }
+
+            let phase = self.opened_at.elapsed().as_secs_f64() / METER_PERIOD.as_secs_f64();
+            let level = 1.0 - (phase.fract() * 2.0 - 1.0).abs();
+            let filled = (level * EDITOR_SIZE.0 as f64) as i32;
+            window.with_hwnd(|hwnd| unsafe { draw_meter(hwnd as HWND, filled) });
+        }
+
+        fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+            EventStatus::Ignored
+        }
+    }
+
+    unsafe fn draw_meter(hwnd: HWND, filled: i32) {
+        let (width, height) = (EDITOR_SIZE.0 as i32, EDITOR_SIZE.1 as i32);
+        let dc = GetDC(hwnd);
+        let meter = CreateSolidBrush(RGB(64, 192, 96));
+        let background = CreateSolidBrush(RGB(32, 32, 32));
+        FillRect(dc, &RECT { left: 0, top: 0, right: filled, bottom: height }, meter);
+        FillRect(dc, &RECT { left: filled, top: 0, right: width, bottom: height }, background);
+        DeleteObject(meter as _);
+        DeleteObject(background as _);
+        ReleaseDC(hwnd, dc);
+    }
+
+    /// The host's window, which the editor is opened in.
+    struct Host {
+        hwnd: HWND,
+    }
+
+    unsafe impl HasRawWindowHandle for Host {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            let mut handle = Win32WindowHandle::empty();
+            handle.hwnd = self.hwnd as *mut _;
+
+            RawWindowHandle::Win32(handle)
+        }
+    }
+
+    unsafe extern "system" fn host_wnd_proc(
+        hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM,
+    ) -> LRESULT {
+        match msg {
+            WM_RBUTTONUP => {
+                let mut point =
+                    POINT { x: (lparam & 0xFFFF) as i16 as i32, y: (lparam >> 16) as i16 as i32 };
+                ClientToScreen(hwnd, &mut point);
+
+                let menu = CreatePopupMenu();
+                for (id, label) in [(1, "Copy"), (2, "Paste"), (3, "Reset")] {
+                    let label: Vec<u16> = label.encode_utf16().chain([0]).collect();
+                    AppendMenuW(menu, MF_STRING, id, label.as_ptr());
+                }
+
+                // This runs a modal loop until the menu is closed
+                MENU_OPEN.store(true, Ordering::Relaxed);
+                TrackPopupMenu(menu, TPM_RIGHTBUTTON, point.x, point.y, 0, hwnd, ptr::null());
+                MENU_OPEN.store(false, Ordering::Relaxed);
+                DestroyMenu(menu);
+
+                0
+            }
+            WM_DESTROY => {
+                PostQuitMessage(0);
+
+                0
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+
+    pub fn run() {
+        let class_name: Vec<u16> = "baseview-context-menu-host".encode_utf16().chain([0]).collect();
+        let title: Vec<u16> = "Right-click below the meter".encode_utf16().chain([0]).collect();
+
+        let hwnd = unsafe {
+            let instance = GetModuleHandleW(ptr::null());
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(host_wnd_proc),
+                hInstance: instance,
+                lpszClassName: class_name.as_ptr(),
+                ..std::mem::zeroed()
+            };
+            RegisterClassW(&class);
+
+            CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                title.as_ptr(),
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                HOST_SIZE.0,
+                HOST_SIZE.1,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            )
+        };
+        assert!(!hwnd.is_null(), "Couldn't create the host window");
+
+        let editor_options = baseview::WindowOpenOptions::new(
+            "Meter",
+            Size::new(EDITOR_SIZE.0 as f64, EDITOR_SIZE.1 as f64),
+        )
+        .scale(WindowScalePolicy::ScaleFactor(1.0));
+        let _editor = EventContext::open_parented(&Host { hwnd }, editor_options, |_| Meter {
+            opened_at: Instant::now(),
+            last_frame: None,
+            longest_gap_in_menu: None,
+        });
+
+        // Like a real host, the host runs the message loop that the editor's messages go through
+        unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+            while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+}
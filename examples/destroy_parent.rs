@@ -0,0 +1,249 @@
+//! Simulates a plugin host that tears down an editor by destroying the editor's parent window
+//! directly, without closing the editor first. The host window embeds a container window that's
+//! created through the platform's own API, like a host's editor frame would be, and the editor is
+//! opened inside of that container. Press any key in the host window to destroy the container.
+//! The host then prints how long it took for the editor's `WindowHandle::is_open()` to return
+//! `false`, which should be at most a frame or two.
+
+use std::time::Instant;
+
+use baseview::keyboard_types::KeyState;
+use baseview::{
+    Event, EventContext, EventStatus, Size, WindowEvent, WindowHandle, WindowHandler,
+    WindowScalePolicy,
+};
+use rwh_05::HasRawWindowHandle;
+
+/// The container's size in physical pixels. The editor uses a fixed scale factor so it fills the
+/// container exactly.
+const CONTAINER_SIZE: (u32, u32) = (320, 240);
+
+struct Editor;
+
+impl WindowHandler for Editor {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        if let Event::Window(WindowEvent::WillClose) = event {
+            println!("The editor received WillClose");
+        }
+
+        EventStatus::Ignored
+    }
+}
+
+struct Host {
+    container: Option<platform::Container>,
+    editor: WindowHandle,
+    /// When the container was destroyed, while waiting for the editor's handle to notice.
+    destroyed_at: Option<Instant>,
+}
+
+impl WindowHandler for Host {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let Some(destroyed_at) = self.destroyed_at else { return };
+
+        if !self.editor.is_open() {
+            println!(
+                "The editor's handle reported it as closed after {:?}",
+                destroyed_at.elapsed()
+            );
+            self.destroyed_at = None;
+            window.set_animating(false);
+        }
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Keyboard(event) = event else { return EventStatus::Ignored };
+        if event.state != KeyState::Down {
+            return EventStatus::Ignored;
+        }
+        let Some(container) = self.container.take() else { return EventStatus::Ignored };
+
+        println!("Destroying the editor's parent window");
+        container.destroy();
+        self.destroyed_at = Some(Instant::now());
+        // The editor's handle is checked on every frame until it reports the editor as closed
+        window.set_animating(true);
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let host_options = baseview::WindowOpenOptions::new("Host", Size::new(512.0, 512.0))
+        .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(host_options, |window| {
+        let container = platform::Container::new(window.raw_window_handle(), CONTAINER_SIZE);
+
+        let editor_options = baseview::WindowOpenOptions::new(
+            "Editor",
+            Size::new(CONTAINER_SIZE.0 as f64, CONTAINER_SIZE.1 as f64),
+        )
+        .scale(WindowScalePolicy::ScaleFactor(1.0));
+        let editor = EventContext::open_parented(&container, editor_options, |_| Editor);
+
+        println!("Press any key to destroy the editor's parent window");
+
+        Host { container: Some(container), editor, destroyed_at: None }
+    });
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ptr;
+
+    use rwh_05::{HasRawWindowHandle, RawWindowHandle, XlibWindowHandle};
+    use x11::xlib;
+
+    /// A plain X11 window, created through a connection of its own like a host's would be.
+    pub struct Container {
+        display: *mut xlib::Display,
+        window: xlib::Window,
+    }
+
+    impl Container {
+        pub fn new(parent: RawWindowHandle, (width, height): (u32, u32)) -> Self {
+            let RawWindowHandle::Xlib(parent) = parent else {
+                panic!("The host window isn't an X11 window");
+            };
+
+            unsafe {
+                let display = xlib::XOpenDisplay(ptr::null());
+                assert!(!display.is_null(), "Couldn't connect to the X server");
+
+                let window =
+                    xlib::XCreateSimpleWindow(display, parent.window, 0, 0, width, height, 0, 0, 0);
+                xlib::XMapWindow(display, window);
+                // The editor creates its window through another connection, so the container
+                // needs to exist by then
+                xlib::XSync(display, xlib::False);
+
+                Self { display, window }
+            }
+        }
+
+        pub fn destroy(self) {
+            unsafe {
+                xlib::XDestroyWindow(self.display, self.window);
+                xlib::XCloseDisplay(self.display);
+            }
+        }
+    }
+
+    unsafe impl HasRawWindowHandle for Container {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            let mut handle = XlibWindowHandle::empty();
+            handle.window = self.window;
+
+            RawWindowHandle::Xlib(handle)
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ptr;
+
+    use rwh_05::{HasRawWindowHandle, RawWindowHandle, Win32WindowHandle};
+    use winapi::shared::windef::HWND;
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::winuser::{CreateWindowExW, DestroyWindow, WS_CHILD, WS_VISIBLE};
+
+    /// A static control, which is about the simplest child window there is.
+    pub struct Container {
+        hwnd: HWND,
+    }
+
+    impl Container {
+        pub fn new(parent: RawWindowHandle, (width, height): (u32, u32)) -> Self {
+            let RawWindowHandle::Win32(parent) = parent else {
+                panic!("The host window isn't a Win32 window");
+            };
+
+            let class: Vec<u16> = "STATIC".encode_utf16().chain([0]).collect();
+            let hwnd = unsafe {
+                CreateWindowExW(
+                    0,
+                    class.as_ptr(),
+                    ptr::null(),
+                    WS_CHILD | WS_VISIBLE,
+                    0,
+                    0,
+                    width as i32,
+                    height as i32,
+                    parent.hwnd as HWND,
+                    ptr::null_mut(),
+                    GetModuleHandleW(ptr::null()),
+                    ptr::null_mut(),
+                )
+            };
+            assert!(!hwnd.is_null(), "Couldn't create the container window");
+
+            Self { hwnd }
+        }
+
+        pub fn destroy(self) {
+            unsafe { DestroyWindow(self.hwnd) };
+        }
+    }
+
+    unsafe impl HasRawWindowHandle for Container {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            let mut handle = Win32WindowHandle::empty();
+            handle.hwnd = self.hwnd as *mut _;
+
+            RawWindowHandle::Win32(handle)
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use cocoa::appkit::NSView;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSPoint, NSRect, NSSize};
+    use objc::{msg_send, sel, sel_impl};
+    use rwh_05::{AppKitWindowHandle, HasRawWindowHandle, RawWindowHandle};
+
+    /// A plain `NSView` added to the host's view.
+    pub struct Container {
+        ns_view: id,
+    }
+
+    impl Container {
+        pub fn new(parent: RawWindowHandle, (width, height): (u32, u32)) -> Self {
+            let RawWindowHandle::AppKit(parent) = parent else {
+                panic!("The host window isn't an AppKit window");
+            };
+
+            unsafe {
+                let frame =
+                    NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(width as f64, height as f64));
+                let ns_view = NSView::alloc(nil).initWithFrame_(frame);
+                let () = msg_send![parent.ns_view as id, addSubview: ns_view];
+
+                Self { ns_view }
+            }
+        }
+
+        /// Hosts usually remove the view before releasing it, which detaches the editor's view
+        /// from the window before it's deallocated.
+        pub fn destroy(self) {
+            unsafe {
+                let () = msg_send![self.ns_view, removeFromSuperview];
+                let () = msg_send![self.ns_view, release];
+            }
+        }
+    }
+
+    unsafe impl HasRawWindowHandle for Container {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            let mut handle = AppKitWindowHandle::empty();
+            handle.ns_view = self.ns_view as *mut _;
+
+            RawWindowHandle::AppKit(handle)
+        }
+    }
+}
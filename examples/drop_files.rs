@@ -0,0 +1,102 @@
+//! Accepts files dropped onto the window and prints how many allocations were made while the drop
+//! was delivered. All of the dropped files arrive in a single `WindowEvent::Drop`, so dropping a
+//! few hundred files from a file manager should only show about one allocation per file.
+//!
+//! The example also prints where the drop came from. A plugin's window lives in the host's
+//! process, so drops that were started by the current executable come from the host, and can be
+//! handled differently from drops that came from a file manager.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use baseview::{
+    DragSourceInfo, DropEffect, DropPayload, Event, EventContext, EventStatus, Size, WindowEvent,
+    WindowHandler, WindowScalePolicy,
+};
+
+/// Counts every allocation made by the process.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+struct DropFilesExample {
+    /// The allocation count at the last drag event before the drop.
+    allocations_before_drop: usize,
+}
+
+impl WindowHandler for DropFilesExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Window(WindowEvent::DragEnter { .. }) | Event::Window(WindowEvent::Dragging) => {
+                window.set_drop_effect(Some(DropEffect::Copy));
+                self.allocations_before_drop = ALLOCATIONS.load(Ordering::Relaxed);
+            }
+            Event::Window(WindowEvent::Drop(DropPayload { items, position, source })) => {
+                let allocations =
+                    ALLOCATIONS.load(Ordering::Relaxed) - self.allocations_before_drop;
+                println!(
+                    "Dropped {} items at {} from {}, delivering them took {} allocations",
+                    items.len(),
+                    position,
+                    describe_source(source.as_ref()),
+                    allocations
+                );
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+/// Executables of common file managers, in lowercase.
+const FILE_MANAGERS: &[&str] = &["explorer", "finder", "nautilus", "dolphin", "thunar", "nemo"];
+
+/// Tell drops from the host apart from drops from a file manager. The source is only a hint, so
+/// unknown sources must still be handled like any other drop.
+fn describe_source(source: Option<&DragSourceInfo>) -> String {
+    let Some(application) = source.and_then(|source| source.application.as_deref()) else {
+        return String::from("an unknown application");
+    };
+
+    let current_exe = std::env::current_exe().ok();
+    let host = current_exe.as_deref().and_then(|path| path.file_stem()).and_then(|s| s.to_str());
+    if host == Some(application) {
+        format!("the host ({})", application)
+    } else if FILE_MANAGERS.contains(&application.to_lowercase().as_str()) {
+        format!("a file manager ({})", application)
+    } else {
+        format!("another application ({})", application)
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Drop files", Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| DropFilesExample {
+        allocations_before_drop: 0,
+    });
+}
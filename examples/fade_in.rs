@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use baseview::{
+    Event, EventContext, EventStatus, MouseButton, MouseEvent, WindowHandler, WindowScalePolicy,
+};
+
+/// How long the window takes to fade in after opening.
+const FADE_IN_DURATION: Duration = Duration::from_secs(1);
+/// The opacity used while the left mouse button is held down.
+const GHOST_OPACITY: f64 = 0.5;
+
+/// Fades the window in from `on_frame()`, and makes it transparent while the left mouse button is
+/// held down. The longest gap between two frames is printed once the fade has finished, which
+/// should stay close to the frame interval if changing the opacity doesn't affect frame pacing.
+struct FadeInExample {
+    opened_at: Instant,
+    fade_finished: bool,
+    last_frame: Option<Instant>,
+    longest_frame_gap: Duration,
+    ghosted: bool,
+}
+
+impl WindowHandler for FadeInExample {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            self.longest_frame_gap = self.longest_frame_gap.max(now - last_frame);
+        }
+
+        if self.fade_finished || self.ghosted {
+            return;
+        }
+
+        let progress = (now - self.opened_at).as_secs_f64() / FADE_IN_DURATION.as_secs_f64();
+        if let Err(err) = window.set_opacity(progress) {
+            println!("Could not change the window's opacity: {}", err);
+            self.fade_finished = true;
+        } else if progress >= 1.0 {
+            println!("Faded in, the longest gap between frames was {:?}", self.longest_frame_gap);
+            self.fade_finished = true;
+        }
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, .. }) => {
+                self.ghosted = true;
+                let _ = window.set_opacity(GHOST_OPACITY);
+            }
+            Event::Mouse(MouseEvent::ButtonReleased { button: MouseButton::Left, .. }) => {
+                self.ghosted = false;
+                if self.fade_finished {
+                    let _ = window.set_opacity(1.0);
+                }
+            }
+            _ => (),
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options = baseview::WindowOpenOptions::new(
+        "Hold the left mouse button to make the window transparent",
+        baseview::Size::new(512.0, 512.0),
+    )
+    .scale(WindowScalePolicy::SystemScaleFactor)
+    .resizable(true);
+
+    EventContext::open_blocking(window_open_options, |window| {
+        // Start out fully transparent so the window doesn't flash before the first frame
+        let _ = window.set_opacity(0.0);
+
+        FadeInExample {
+            opened_at: Instant::now(),
+            fade_finished: false,
+            last_frame: None,
+            longest_frame_gap: Duration::ZERO,
+            ghosted: false,
+        }
+    });
+}
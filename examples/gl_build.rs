@@ -0,0 +1,113 @@
+//! Checks that the OpenGL context is current while the handler is built. The handler compiles a
+//! shader and reads back the viewport in its constructor without calling `make_current()` itself.
+//! The viewport should match the window's physical size.
+
+use std::ffi::CString;
+use std::mem;
+use std::ptr;
+
+use baseview::gl::{GlConfig, GlContext};
+use baseview::{Event, EventContext, EventStatus, Size, WindowHandler, WindowScalePolicy};
+
+const GL_NO_ERROR: u32 = 0;
+const GL_VIEWPORT: u32 = 0x0BA2;
+const GL_VERTEX_SHADER: u32 = 0x8B31;
+const GL_COMPILE_STATUS: u32 = 0x8B81;
+
+const VERTEX_SHADER: &str = "#version 150
+void main() {
+    gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+}
+";
+
+/// The handful of OpenGL functions this example needs, loaded through the window's context.
+struct Gl {
+    get_error: extern "system" fn() -> u32,
+    get_integerv: extern "system" fn(u32, *mut i32),
+    create_shader: extern "system" fn(u32) -> u32,
+    shader_source: extern "system" fn(u32, i32, *const *const i8, *const i32),
+    compile_shader: extern "system" fn(u32),
+    get_shaderiv: extern "system" fn(u32, u32, *mut i32),
+    delete_shader: extern "system" fn(u32),
+}
+
+impl Gl {
+    fn load(context: &GlContext) -> Self {
+        let load = |symbol: &str| {
+            let address = context.get_proc_address(symbol);
+            assert!(!address.is_null(), "Couldn't load {}", symbol);
+            address
+        };
+
+        unsafe {
+            Gl {
+                get_error: mem::transmute_copy(&load("glGetError")),
+                get_integerv: mem::transmute_copy(&load("glGetIntegerv")),
+                create_shader: mem::transmute_copy(&load("glCreateShader")),
+                shader_source: mem::transmute_copy(&load("glShaderSource")),
+                compile_shader: mem::transmute_copy(&load("glCompileShader")),
+                get_shaderiv: mem::transmute_copy(&load("glGetShaderiv")),
+                delete_shader: mem::transmute_copy(&load("glDeleteShader")),
+            }
+        }
+    }
+}
+
+struct GlBuildExample;
+
+impl GlBuildExample {
+    fn new(window: &mut EventContext) -> Self {
+        let context = window.gl_context().expect("The window doesn't have an OpenGL context");
+        let gl = Gl::load(context);
+
+        // `glCreateShader()` returns 0 when no context is current
+        let shader = (gl.create_shader)(GL_VERTEX_SHADER);
+        if shader == 0 {
+            println!("FAIL: the context isn't current during build");
+            return Self;
+        }
+
+        let source = CString::new(VERTEX_SHADER).unwrap();
+        (gl.shader_source)(shader, 1, &source.as_ptr(), ptr::null());
+        (gl.compile_shader)(shader);
+
+        let mut status = 0;
+        (gl.get_shaderiv)(shader, GL_COMPILE_STATUS, &mut status);
+        (gl.delete_shader)(shader);
+        match (status, (gl.get_error)()) {
+            (0, _) => println!("FAIL: the shader didn't compile"),
+            (_, GL_NO_ERROR) => println!("Compiled a shader during build without errors"),
+            (_, error) => println!("FAIL: compiling the shader caused error {:#x}", error),
+        }
+
+        let mut viewport = [0; 4];
+        (gl.get_integerv)(GL_VIEWPORT, viewport.as_mut_ptr());
+        if viewport[2] > 0 && viewport[3] > 0 {
+            println!(
+                "The viewport is {}x{} at ({}, {})",
+                viewport[2], viewport[3], viewport[0], viewport[1]
+            );
+        } else {
+            println!("FAIL: the viewport is empty: {:?}", viewport);
+        }
+
+        Self
+    }
+}
+
+impl WindowHandler for GlBuildExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("OpenGL build", Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .gl_config(GlConfig::default());
+
+    EventContext::open_blocking(window_open_options, GlBuildExample::new);
+}
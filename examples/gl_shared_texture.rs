@@ -0,0 +1,193 @@
+//! Uploads a texture on a worker thread through an `OffscreenGlContext` that shares objects with
+//! the window's context. The worker waits for the upload with a fence before handing the texture
+//! to the window, which then checks that its own context sees the texture with the right size.
+
+use std::ffi::c_void;
+use std::mem;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use baseview::gl::{GlConfig, OffscreenGlContext};
+use baseview::{Event, EventContext, EventStatus, Size, WindowHandler, WindowScalePolicy};
+
+const GL_NO_ERROR: u32 = 0;
+const GL_TEXTURE_2D: u32 = 0x0DE1;
+const GL_TEXTURE_WIDTH: u32 = 0x1000;
+const GL_TEXTURE_HEIGHT: u32 = 0x1001;
+const GL_TEXTURE_MIN_FILTER: u32 = 0x2801;
+const GL_LINEAR: i32 = 0x2601;
+const GL_RGBA: u32 = 0x1908;
+const GL_RGBA8: i32 = 0x8058;
+const GL_UNSIGNED_BYTE: u32 = 0x1401;
+const GL_COLOR_BUFFER_BIT: u32 = 0x4000;
+const GL_SYNC_GPU_COMMANDS_COMPLETE: u32 = 0x9117;
+const GL_TIMEOUT_IGNORED: u64 = u64::MAX;
+
+const TEXTURE_SIZE: i32 = 256;
+
+/// The handful of OpenGL functions this example needs.
+struct Gl {
+    get_error: extern "system" fn() -> u32,
+    gen_textures: extern "system" fn(i32, *mut u32),
+    bind_texture: extern "system" fn(u32, u32),
+    tex_parameteri: extern "system" fn(u32, u32, i32),
+    tex_image_2d: extern "system" fn(u32, i32, i32, i32, i32, i32, u32, u32, *const c_void),
+    get_tex_level_parameteriv: extern "system" fn(u32, i32, u32, *mut i32),
+    clear_color: extern "system" fn(f32, f32, f32, f32),
+    clear: extern "system" fn(u32),
+    flush: extern "system" fn(),
+    fence_sync: extern "system" fn(u32, u32) -> *const c_void,
+    wait_sync: extern "system" fn(*const c_void, u32, u64),
+    delete_sync: extern "system" fn(*const c_void),
+}
+
+impl Gl {
+    fn load(get_proc_address: impl Fn(&str) -> *const c_void) -> Self {
+        let load = |symbol: &str| {
+            let address = get_proc_address(symbol);
+            assert!(!address.is_null(), "Couldn't load {}", symbol);
+            address
+        };
+
+        unsafe {
+            Gl {
+                get_error: mem::transmute_copy(&load("glGetError")),
+                gen_textures: mem::transmute_copy(&load("glGenTextures")),
+                bind_texture: mem::transmute_copy(&load("glBindTexture")),
+                tex_parameteri: mem::transmute_copy(&load("glTexParameteri")),
+                tex_image_2d: mem::transmute_copy(&load("glTexImage2D")),
+                get_tex_level_parameteriv: mem::transmute_copy(&load("glGetTexLevelParameteriv")),
+                clear_color: mem::transmute_copy(&load("glClearColor")),
+                clear: mem::transmute_copy(&load("glClear")),
+                flush: mem::transmute_copy(&load("glFlush")),
+                fence_sync: mem::transmute_copy(&load("glFenceSync")),
+                wait_sync: mem::transmute_copy(&load("glWaitSync")),
+                delete_sync: mem::transmute_copy(&load("glDeleteSync")),
+            }
+        }
+    }
+}
+
+/// A texture that was uploaded on the worker thread, along with the fence that signals when the
+/// upload has completed. The fence is a pointer, which can't be sent between threads as is.
+struct UploadedTexture {
+    texture: u32,
+    fence: usize,
+}
+
+/// Create a checkerboard texture on the worker thread's context.
+fn upload_texture(context: OffscreenGlContext) -> UploadedTexture {
+    unsafe { context.make_current() };
+    let gl = Gl::load(|symbol| context.get_proc_address(symbol));
+
+    let pixels: Vec<u8> = (0..TEXTURE_SIZE * TEXTURE_SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % TEXTURE_SIZE, i / TEXTURE_SIZE);
+            let value = if (x / 32 + y / 32) % 2 == 0 { 255 } else { 64 };
+            [value, value, value, 255]
+        })
+        .collect();
+
+    let mut texture = 0;
+    (gl.gen_textures)(1, &mut texture);
+    (gl.bind_texture)(GL_TEXTURE_2D, texture);
+    (gl.tex_parameteri)(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR);
+    (gl.tex_image_2d)(
+        GL_TEXTURE_2D,
+        0,
+        GL_RGBA8,
+        TEXTURE_SIZE,
+        TEXTURE_SIZE,
+        0,
+        GL_RGBA,
+        GL_UNSIGNED_BYTE,
+        pixels.as_ptr().cast(),
+    );
+    (gl.bind_texture)(GL_TEXTURE_2D, 0);
+
+    // The fence only becomes visible to the window's context once it has been flushed
+    let fence = (gl.fence_sync)(GL_SYNC_GPU_COMMANDS_COMPLETE, 0);
+    (gl.flush)();
+    match (gl.get_error)() {
+        GL_NO_ERROR => println!("Uploaded texture {} on the worker thread", texture),
+        error => println!("FAIL: uploading the texture caused error {:#x}", error),
+    }
+
+    unsafe { context.make_not_current() };
+    UploadedTexture { texture, fence: fence as usize }
+}
+
+struct GlSharedTextureExample {
+    uploads: Receiver<UploadedTexture>,
+    texture: Option<u32>,
+}
+
+impl GlSharedTextureExample {
+    fn new(window: &mut EventContext) -> Self {
+        let context = window.gl_context().expect("The window doesn't have an OpenGL context");
+        let shared_context = context.create_shared().expect("Couldn't create a shared context");
+
+        let (sender, uploads) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(upload_texture(shared_context));
+        });
+
+        Self { uploads, texture: None }
+    }
+
+    /// Wait for the worker's upload on the window's context, and check that the texture is there.
+    fn receive_texture(&mut self, gl: &Gl, upload: UploadedTexture) {
+        let fence = upload.fence as *const c_void;
+        (gl.wait_sync)(fence, 0, GL_TIMEOUT_IGNORED);
+        (gl.delete_sync)(fence);
+
+        let (mut width, mut height) = (0, 0);
+        (gl.bind_texture)(GL_TEXTURE_2D, upload.texture);
+        (gl.get_tex_level_parameteriv)(GL_TEXTURE_2D, 0, GL_TEXTURE_WIDTH, &mut width);
+        (gl.get_tex_level_parameteriv)(GL_TEXTURE_2D, 0, GL_TEXTURE_HEIGHT, &mut height);
+        (gl.bind_texture)(GL_TEXTURE_2D, 0);
+
+        if (width, height) == (TEXTURE_SIZE, TEXTURE_SIZE) {
+            println!("The window's context sees the {}x{} texture", width, height);
+        } else {
+            println!("FAIL: the window's context sees a {}x{} texture", width, height);
+        }
+        self.texture = Some(upload.texture);
+    }
+}
+
+impl WindowHandler for GlSharedTextureExample {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let context = window.gl_context().unwrap();
+        unsafe { context.make_current() };
+        let gl = Gl::load(|symbol| context.get_proc_address(symbol));
+
+        if let Ok(upload) = self.uploads.try_recv() {
+            self.receive_texture(&gl, upload);
+        }
+
+        // The window turns green once the texture has arrived
+        if self.texture.is_some() {
+            (gl.clear_color)(0.2, 0.6, 0.3, 1.0);
+        } else {
+            (gl.clear_color)(0.3, 0.3, 0.3, 1.0);
+        }
+        (gl.clear)(GL_COLOR_BUFFER_BIT);
+
+        context.swap_buffers();
+        unsafe { context.make_not_current() };
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("OpenGL shared texture", Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .gl_config(GlConfig::default());
+
+    EventContext::open_blocking(window_open_options, GlSharedTextureExample::new);
+}
@@ -0,0 +1,109 @@
+//! Checks that the OpenGL context can still be used to free GPU resources when the handler receives
+//! `WindowEvent::WillClose`. The handler creates a texture when it's created, and deletes it again
+//! once the window closes. Close the window in any way to see the result.
+
+use std::ffi::CStr;
+use std::mem;
+
+use baseview::gl::{GlConfig, GlContext};
+use baseview::{
+    Event, EventContext, EventStatus, Size, WindowEvent, WindowHandler, WindowScalePolicy,
+};
+
+const GL_NO_ERROR: u32 = 0;
+const GL_VERSION: u32 = 0x1F02;
+
+/// The handful of OpenGL functions this example needs, loaded through the window's context.
+struct Gl {
+    get_error: extern "system" fn() -> u32,
+    get_string: extern "system" fn(u32) -> *const u8,
+    gen_textures: extern "system" fn(i32, *mut u32),
+    delete_textures: extern "system" fn(i32, *const u32),
+    is_texture: extern "system" fn(u32) -> u8,
+}
+
+impl Gl {
+    fn load(context: &GlContext) -> Self {
+        let load = |symbol: &str| {
+            let address = context.get_proc_address(symbol);
+            assert!(!address.is_null(), "Couldn't load {}", symbol);
+            address
+        };
+
+        unsafe {
+            Gl {
+                get_error: mem::transmute_copy(&load("glGetError")),
+                get_string: mem::transmute_copy(&load("glGetString")),
+                gen_textures: mem::transmute_copy(&load("glGenTextures")),
+                delete_textures: mem::transmute_copy(&load("glDeleteTextures")),
+                is_texture: mem::transmute_copy(&load("glIsTexture")),
+            }
+        }
+    }
+}
+
+struct GlTeardownExample {
+    gl: Gl,
+    texture: u32,
+}
+
+impl GlTeardownExample {
+    fn new(window: &mut EventContext) -> Self {
+        let context = window.gl_context().expect("The window doesn't have an OpenGL context");
+        let gl = Gl::load(context);
+
+        let mut texture = 0;
+        unsafe {
+            context.make_current();
+            (gl.gen_textures)(1, &mut texture);
+            context.make_not_current();
+        }
+        println!("Created texture {}, close the window to delete it again", texture);
+
+        Self { gl, texture }
+    }
+
+    fn delete_texture(&mut self) {
+        let gl = &self.gl;
+
+        // `glGetString()` returns null when no context is current
+        let version = (gl.get_string)(GL_VERSION);
+        if version.is_null() {
+            println!("FAIL: the context isn't current during WillClose");
+            return;
+        }
+        let version = unsafe { CStr::from_ptr(version as *const _) };
+        println!("The context is current during WillClose, version {}", version.to_string_lossy());
+
+        if (gl.is_texture)(self.texture) == 0 {
+            println!("FAIL: texture {} no longer exists", self.texture);
+        }
+        (gl.delete_textures)(1, &self.texture);
+
+        match (gl.get_error)() {
+            GL_NO_ERROR => println!("Deleted texture {} without errors", self.texture),
+            error => println!("FAIL: deleting the texture caused error {:#x}", error),
+        }
+    }
+}
+
+impl WindowHandler for GlTeardownExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        if let Event::Window(WindowEvent::WillClose) = event {
+            self.delete_texture();
+        }
+
+        EventStatus::Ignored
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("OpenGL teardown", Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .gl_config(GlConfig::default());
+
+    EventContext::open_blocking(window_open_options, GlTeardownExample::new);
+}
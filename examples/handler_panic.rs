@@ -0,0 +1,37 @@
+use baseview::{
+    Event, EventContext, EventStatus, PanicPolicy, WindowEvent, WindowHandler, WindowScalePolicy,
+};
+
+/// Panics as soon as a key is pressed. With `PanicPolicy::CloseWindow` the window should receive a
+/// `WillClose` event and close, after which `open_blocking()` returns normally.
+struct PanickingHandler;
+
+impl WindowHandler for PanickingHandler {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Keyboard(e) if e.state == keyboard_types::KeyState::Down => {
+                panic!("Key pressed: {:?}", e.key)
+            }
+            Event::Window(WindowEvent::WillClose) => println!("Window will close"),
+            _ => (),
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options = baseview::WindowOpenOptions::new(
+        "Press any key to panic",
+        baseview::Size::new(512.0, 512.0),
+    )
+    .scale(WindowScalePolicy::SystemScaleFactor)
+    .on_handler_panic(PanicPolicy::CloseWindow)
+    .resizable(true);
+
+    EventContext::open_blocking(window_open_options, |_| PanickingHandler);
+
+    println!("The window was closed, but the application is still running");
+}
@@ -0,0 +1,84 @@
+//! A knob that's turned by dragging up and down with the left mouse button, like the parameters in
+//! a plugin's editor. Holding Shift turns it a hundred times slower for fine adjustments. The knob
+//! follows the physical `delta` of the `CursorMoved` events instead of the difference between
+//! logical positions, so every pixel of movement turns it by the same amount regardless of the
+//! scale factor. The examples don't have a renderer, so the knob's value is printed instead. Once
+//! the button is released, this also prints how many of the drag's events had a delta while their
+//! position stayed the same. On macOS that happens when dragging while the cursor is pushed
+//! against the edge of the screen, where the knob keeps turning.
+
+use baseview::keyboard_types::Modifiers;
+use baseview::{
+    Event, EventContext, EventStatus, MouseButton, MouseEvent, Point, WindowHandler,
+    WindowScalePolicy,
+};
+
+/// How much the value changes per physical pixel of vertical movement.
+const SENSITIVITY: f64 = 0.005;
+/// Divides the sensitivity while Shift is held down.
+const FINE_FACTOR: f64 = 100.0;
+
+struct KnobExample {
+    /// Between 0 and 1.
+    value: f64,
+    dragging: bool,
+    last_position: Option<Point>,
+    /// Events during the current drag that moved the cursor without changing its logical position.
+    hidden_movements: usize,
+}
+
+impl WindowHandler for KnobExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Mouse(event) = event else { return EventStatus::Ignored };
+
+        match event {
+            MouseEvent::ButtonPressed { button: MouseButton::Left, .. } => {
+                self.dragging = true;
+                self.hidden_movements = 0;
+            }
+            MouseEvent::ButtonReleased { button: MouseButton::Left, .. } => {
+                self.dragging = false;
+                println!(
+                    "{} of the drag's movements didn't change the logical position",
+                    self.hidden_movements
+                );
+            }
+            MouseEvent::CursorMoved { position, delta, modifiers, .. } => {
+                let last_position = self.last_position.replace(position);
+                if !self.dragging || delta.y == 0 {
+                    return EventStatus::Captured;
+                }
+
+                if last_position == Some(position) {
+                    self.hidden_movements += 1;
+                }
+
+                let mut sensitivity = SENSITIVITY;
+                if modifiers.contains(Modifiers::SHIFT) {
+                    sensitivity /= FINE_FACTOR;
+                }
+                // Moving up turns the knob up
+                self.value = (self.value - delta.y as f64 * sensitivity).clamp(0.0, 1.0);
+                println!("Value: {:.5}", self.value);
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Knob", baseview::Size::new(256.0, 256.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| KnobExample {
+        value: 0.5,
+        dragging: false,
+        last_position: None,
+        hidden_movements: 0,
+    });
+}
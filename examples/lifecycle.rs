@@ -0,0 +1,470 @@
+//! Opens and closes windows in quick succession, closing each one at a different point in its
+//! lifecycle, and checks the order in which the handler was called. Every window has to receive a
+//! `Resized` event before its first frame, and `WillClose` has to be the very last call. The windows
+//! close by themselves, and the result for each window is printed once it has closed.
+//!
+//! Afterwards a window that the user can't resize resizes itself a few times. Every resize has to
+//! result in exactly one `Resized` event with the requested size.
+//!
+//! Finally, a host window opens a window with `open_parented()` and one with
+//! `open_as_if_parented()`, which both close themselves after a few frames. Their handles have to
+//! report them as open until then, and as closed afterwards. The windows opened with
+//! `open_blocking()` above don't have a handle, so for those returning is all there is to check.
+//!
+//! On Windows, opening a window in a parent that was destroyed already also has to fail with an
+//! error, without ever calling the `build` closure.
+
+use std::time::{Duration, Instant};
+
+use baseview::{
+    Event, EventContext, EventStatus, RemoteWindowHandle, Size, WindowEvent, WindowHandle,
+    WindowHandler, WindowHandlerWithResult, WindowOpenOptions, WindowScalePolicy,
+};
+
+const ROUNDS: usize = 10;
+
+/// The sizes the non-resizable window resizes itself to, in order.
+const RESIZES: [Size; 3] = [
+    Size { width: 320.0, height: 200.0 },
+    Size { width: 200.0, height: 320.0 },
+    Size { width: 256.0, height: 256.0 },
+];
+
+/// How many frames to wait for the `Resized` events that belong to a resize.
+const RESIZE_FRAMES: usize = 60;
+
+/// How long the windows opened by the host may take to close themselves.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The point at which a window closes itself.
+#[derive(Debug, Clone, Copy)]
+enum CloseAt {
+    Build,
+    InitialResized,
+    FirstEvent,
+    FirstFrame,
+    TenthFrame,
+}
+
+const CLOSE_AT: [CloseAt; 5] = [
+    CloseAt::Build,
+    CloseAt::InitialResized,
+    CloseAt::FirstEvent,
+    CloseAt::FirstFrame,
+    CloseAt::TenthFrame,
+];
+
+#[derive(Debug, PartialEq)]
+enum Call {
+    Resized,
+    WillClose,
+    OtherEvent,
+    Frame,
+}
+
+struct LifecycleRecorder {
+    close_at: CloseAt,
+    calls: Vec<Call>,
+}
+
+impl LifecycleRecorder {
+    fn frames(&self) -> usize {
+        self.calls.iter().filter(|call| **call == Call::Frame).count()
+    }
+}
+
+impl WindowHandler for LifecycleRecorder {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        self.calls.push(Call::Frame);
+
+        match self.close_at {
+            CloseAt::FirstFrame => window.close(),
+            CloseAt::TenthFrame if self.frames() == 10 => window.close(),
+            _ => (),
+        }
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let call = match event {
+            Event::Window(WindowEvent::Resized { .. }) => Call::Resized,
+            Event::Window(WindowEvent::WillClose) => Call::WillClose,
+            _ => Call::OtherEvent,
+        };
+
+        let is_first_event = self.calls.is_empty();
+        match (self.close_at, &call) {
+            (CloseAt::InitialResized, Call::Resized) if is_first_event => window.close(),
+            (CloseAt::FirstEvent, _) if is_first_event => window.close(),
+            _ => (),
+        }
+        self.calls.push(call);
+
+        EventStatus::Captured
+    }
+}
+
+impl WindowHandlerWithResult for LifecycleRecorder {
+    type Output = Vec<Call>;
+
+    fn on_close(self: Box<Self>) -> Vec<Call> {
+        self.calls
+    }
+}
+
+/// Resizes its window to each size in [`RESIZES`], and records the sizes of the `Resized` events
+/// that followed each resize.
+struct ResizeRecorder {
+    frames_waited: usize,
+    resized: Vec<Vec<Size>>,
+}
+
+impl WindowHandler for ResizeRecorder {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        if !self.resized.is_empty() && self.frames_waited < RESIZE_FRAMES {
+            self.frames_waited += 1;
+        } else if let Some(&size) = RESIZES.get(self.resized.len()) {
+            window.resize(size);
+            self.resized.push(Vec::new());
+            self.frames_waited = 0;
+        } else {
+            window.close();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        // The window's initial `Resized` event arrives before the first resize
+        if let (Event::Window(WindowEvent::Resized { new, .. }), Some(sizes)) =
+            (event, self.resized.last_mut())
+        {
+            sizes.push(new.logical_size());
+        }
+
+        EventStatus::Captured
+    }
+}
+
+impl WindowHandlerWithResult for ResizeRecorder {
+    type Output = Vec<Vec<Size>>;
+
+    fn on_close(self: Box<Self>) -> Vec<Vec<Size>> {
+        self.resized
+    }
+}
+
+/// Closes its window on the tenth frame.
+struct SelfClosing {
+    frames: usize,
+}
+
+impl WindowHandler for SelfClosing {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        self.frames += 1;
+        if self.frames == 10 {
+            window.close();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+/// A window opened by [`OpenStateChecker`] that hasn't closed yet.
+struct OpenedWindow {
+    mode: &'static str,
+    handle: WindowHandle,
+    remote: RemoteWindowHandle,
+}
+
+/// Watches the handles of the windows it opened until they report that their window closed.
+struct OpenStateChecker {
+    windows: Vec<OpenedWindow>,
+    results: Vec<(&'static str, Result<(), String>)>,
+    opened_at: Instant,
+}
+
+impl OpenStateChecker {
+    fn opened(&mut self, mode: &'static str, handle: WindowHandle) {
+        if handle.is_open() {
+            let remote = handle.remote();
+            self.windows.push(OpenedWindow { mode, handle, remote });
+        } else {
+            self.results.push((mode, Err("the handle was closed right after opening".to_string())));
+        }
+    }
+}
+
+impl WindowHandler for OpenStateChecker {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let timed_out = self.opened_at.elapsed() > CLOSE_TIMEOUT;
+        let results = &mut self.results;
+        self.windows.retain(|opened| {
+            let result = if !opened.handle.is_open() {
+                if opened.remote.is_open() {
+                    Err("the remote handle still reports the window as open".to_string())
+                } else {
+                    Ok(())
+                }
+            } else if timed_out {
+                Err(format!("the window was still open after {:?}", CLOSE_TIMEOUT))
+            } else {
+                return true;
+            };
+            results.push((opened.mode, result));
+
+            false
+        });
+
+        if self.windows.is_empty() {
+            window.close();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+impl WindowHandlerWithResult for OpenStateChecker {
+    type Output = Vec<(&'static str, Result<(), String>)>;
+
+    fn on_close(self: Box<Self>) -> Self::Output {
+        self.results
+    }
+}
+
+/// Try to open a window in a parent window that no longer exists. That has to fail with
+/// `Error::WindowCreationFailed` before the `build` closure is called.
+#[cfg(target_os = "windows")]
+fn check_destroyed_parent() -> Result<(), String> {
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use baseview::Error;
+    use rwh_05::{HasRawWindowHandle, RawWindowHandle, Win32WindowHandle};
+    use winapi::um::winuser::{CreateWindowExW, DestroyWindow};
+
+    struct DestroyedParent(Win32WindowHandle);
+
+    unsafe impl HasRawWindowHandle for DestroyedParent {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            RawWindowHandle::Win32(self.0)
+        }
+    }
+
+    // A static control is about the simplest window there is
+    let class: Vec<u16> = "STATIC".encode_utf16().chain([0]).collect();
+    let mut handle = Win32WindowHandle::empty();
+    unsafe {
+        let hwnd = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            16,
+            16,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        assert!(!hwnd.is_null(), "Could not create the parent window");
+        DestroyWindow(hwnd);
+        handle.hwnd = hwnd as *mut _;
+    }
+
+    let built = Arc::new(AtomicBool::new(false));
+    let built_flag = built.clone();
+    let options = WindowOpenOptions::new("Destroyed parent", Size::new(256.0, 256.0));
+    let result = EventContext::try_open_parented(&DestroyedParent(handle), options, move |_| {
+        built_flag.store(true, Ordering::Relaxed);
+        SelfClosing { frames: 0 }
+    });
+
+    match result {
+        _ if built.load(Ordering::Relaxed) => Err("the build closure was called".to_string()),
+        Err(Error::WindowCreationFailed(_)) => Ok(()),
+        Err(error) => Err(format!("the wrong error was returned: {}", error)),
+        Ok(_) => Err("the window was opened".to_string()),
+    }
+}
+
+/// Check that a resize resulted in a single `Resized` event with the requested size.
+fn check_resize(requested: Size, resized: &[Size]) -> Result<(), String> {
+    match resized {
+        [] => Err("no Resized event was received".to_string()),
+        [size]
+            if (size.width - requested.width).abs() > 1.0
+                || (size.height - requested.height).abs() > 1.0 =>
+        {
+            Err(format!("the window was resized to {}x{}", size.width, size.height))
+        }
+        [_] => Ok(()),
+        _ => Err(format!("{} Resized events were received", resized.len())),
+    }
+}
+
+/// Check the order of the handler's calls, returning the first violation.
+fn check(calls: &[Call]) -> Result<(), String> {
+    let first_resized = calls.iter().position(|call| *call == Call::Resized);
+    let first_frame = calls.iter().position(|call| *call == Call::Frame);
+    match (first_resized, first_frame) {
+        (None, _) => return Err("no Resized event was received".to_string()),
+        (Some(resized), Some(frame)) if frame < resized => {
+            return Err(format!("call {} was a frame before the first Resized event", frame));
+        }
+        _ => (),
+    }
+
+    match calls.iter().position(|call| *call == Call::WillClose) {
+        None => Err("no WillClose event was received".to_string()),
+        Some(will_close) if will_close != calls.len() - 1 => {
+            Err(format!("{:?} was called after WillClose", &calls[will_close + 1..]))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+fn main() {
+    let mut failures = 0;
+    for round in 0..ROUNDS {
+        for close_at in CLOSE_AT {
+            let options = WindowOpenOptions::new("Lifecycle", Size::new(256.0, 256.0))
+                .scale(WindowScalePolicy::SystemScaleFactor);
+            let calls = EventContext::open_blocking_with_result(options, move |window| {
+                if let CloseAt::Build = close_at {
+                    window.close();
+                }
+                window.set_animating(true);
+
+                LifecycleRecorder { close_at, calls: Vec::new() }
+            });
+
+            let result = match &calls {
+                Some(calls) => check(calls),
+                None => Err("the handler panicked".to_string()),
+            };
+            match result {
+                Ok(()) => println!("PASS: round {}, closed at {:?}", round + 1, close_at),
+                Err(error) => {
+                    failures += 1;
+                    println!("FAIL: round {}, closed at {:?}: {}", round + 1, close_at, error);
+                }
+            }
+        }
+    }
+
+    let options = WindowOpenOptions::new("Fixed size", Size::new(256.0, 256.0))
+        .scale(WindowScalePolicy::SystemScaleFactor)
+        .resizable(false);
+    let resized = EventContext::open_blocking_with_result(options, |window| {
+        window.set_animating(true);
+
+        ResizeRecorder { frames_waited: 0, resized: Vec::new() }
+    });
+
+    let mut resize_failures = 0;
+    match resized {
+        Some(resized) if resized.len() == RESIZES.len() => {
+            for (requested, resized) in RESIZES.into_iter().zip(&resized) {
+                let (width, height) = (requested.width, requested.height);
+                match check_resize(requested, resized) {
+                    Ok(()) => println!("PASS: resized to {}x{}", width, height),
+                    Err(error) => {
+                        resize_failures += 1;
+                        println!("FAIL: resized to {}x{}: {}", width, height, error);
+                    }
+                }
+            }
+        }
+        Some(_) => {
+            resize_failures = 1;
+            println!("FAIL: the non-resizable window closed before it was resized");
+        }
+        None => {
+            resize_failures = 1;
+            println!("FAIL: the non-resizable window's handler panicked");
+        }
+    }
+
+    let options = WindowOpenOptions::new("Host", Size::new(512.0, 512.0))
+        .scale(WindowScalePolicy::SystemScaleFactor);
+    let open_states = EventContext::open_blocking_with_result(options, |window| {
+        window.set_animating(true);
+
+        let mut checker = OpenStateChecker {
+            windows: Vec::new(),
+            results: Vec::new(),
+            opened_at: Instant::now(),
+        };
+        let options = WindowOpenOptions::new("Parented", Size::new(256.0, 256.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+        let handle = EventContext::open_parented(window, options, |_| SelfClosing { frames: 0 });
+        checker.opened("open_parented()", handle);
+
+        // On macOS these windows only close once the host releases their view, which this
+        // example doesn't have access to
+        if cfg!(target_os = "macos") {
+            println!("SKIP: open_as_if_parented() windows can't close themselves on macOS");
+        } else {
+            let options = WindowOpenOptions::new("As if parented", Size::new(256.0, 256.0))
+                .scale(WindowScalePolicy::SystemScaleFactor);
+            let handle = EventContext::open_as_if_parented(options, |_| SelfClosing { frames: 0 });
+            checker.opened("open_as_if_parented()", handle);
+        }
+
+        checker
+    });
+
+    let mut open_state_failures = 0;
+    match open_states {
+        Some(results) => {
+            for (mode, result) in results {
+                match result {
+                    Ok(()) => println!("PASS: is_open() followed the window from {}", mode),
+                    Err(error) => {
+                        open_state_failures += 1;
+                        println!("FAIL: is_open() for the window from {}: {}", mode, error);
+                    }
+                }
+            }
+        }
+        None => {
+            open_state_failures = 1;
+            println!("FAIL: the host window's handler panicked");
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    let destroyed_parent_failures = match check_destroyed_parent() {
+        Ok(()) => {
+            println!("PASS: opening a window in a destroyed parent failed");
+            0
+        }
+        Err(error) => {
+            println!("FAIL: opening a window in a destroyed parent: {}", error);
+            1
+        }
+    };
+    #[cfg(not(target_os = "windows"))]
+    let destroyed_parent_failures = 0;
+
+    if failures == 0 {
+        println!("All {} windows were closed correctly", ROUNDS * CLOSE_AT.len());
+    } else {
+        println!("{} of {} windows failed", failures, ROUNDS * CLOSE_AT.len());
+    }
+    if resize_failures == 0 {
+        println!("The non-resizable window was resized correctly");
+    }
+    if failures > 0
+        || resize_failures > 0
+        || open_state_failures > 0
+        || destroyed_parent_failures > 0
+    {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,72 @@
+//! Repeatedly opens and closes a batch of windows, like a plugin host would when opening and
+//! closing plugin editors, and prints the process' number of open file descriptors and its memory
+//! usage after every round. Neither should keep growing. These statistics are only available on
+//! Linux.
+
+use std::thread;
+use std::time::Duration;
+
+use baseview::{Event, EventContext, EventStatus, WindowHandler, WindowScalePolicy};
+
+const WINDOWS_PER_ROUND: usize = 8;
+const ROUNDS: usize = 5;
+
+struct EmptyHandler;
+
+impl WindowHandler for EmptyHandler {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+fn main() {
+    print_stats("before opening any windows");
+
+    for round in 1..=ROUNDS {
+        let mut handles: Vec<_> = (0..WINDOWS_PER_ROUND)
+            .map(|i| {
+                let window_open_options = baseview::WindowOpenOptions::new(
+                    format!("baseview {}", i + 1),
+                    baseview::Size::new(256.0, 256.0),
+                )
+                .scale(WindowScalePolicy::SystemScaleFactor)
+                .resizable(true);
+
+                EventContext::open_as_if_parented(window_open_options, |_| EmptyHandler)
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(500));
+        print_stats(&format!("round {}: {} windows open", round, WINDOWS_PER_ROUND));
+
+        for handle in &mut handles {
+            handle.close();
+        }
+
+        // The windows are closed asynchronously by their own threads
+        while handles.iter().any(|handle| handle.is_open()) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        thread::sleep(Duration::from_millis(200));
+        print_stats(&format!("round {}: all windows closed", round));
+    }
+}
+
+fn print_stats(label: &str) {
+    let open_fds = std::fs::read_dir("/proc/self/fd").map(|entries| entries.count()).ok();
+    let resident_memory = std::fs::read_to_string("/proc/self/status").ok().and_then(|status| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .map(|value| value.trim().to_string())
+    });
+
+    match (open_fds, resident_memory) {
+        (Some(open_fds), Some(resident_memory)) => {
+            println!("{}: {} open file descriptors, {} resident", label, open_fds, resident_memory)
+        }
+        _ => println!("{}: statistics are not available on this platform", label),
+    }
+}
@@ -0,0 +1,116 @@
+//! Reads an embedded window's metrics from its host. The host window opens an editor inside of
+//! itself whose frames take a varying amount of time, and every so often one of them stalls. The
+//! host resets the editor's metrics once it has warmed up, and prints what the editor's
+//! `WindowHandle` reported once the editor closes itself. Then the host closes too.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use baseview::{
+    Event, EventContext, EventStatus, Size, WindowHandle, WindowHandler, WindowMetrics,
+    WindowOpenOptions, WindowScalePolicy,
+};
+
+const WARM_UP: Duration = Duration::from_millis(500);
+const EDITOR_FRAMES: u64 = 120;
+const STALL_EVERY: u64 = 40;
+const STALL: Duration = Duration::from_millis(50);
+
+/// Pretends to do some drawing, and closes itself after [`EDITOR_FRAMES`] frames.
+struct Editor {
+    frames: u64,
+}
+
+impl WindowHandler for Editor {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        self.frames += 1;
+        if self.frames.is_multiple_of(STALL_EVERY) {
+            thread::sleep(STALL);
+        } else {
+            thread::sleep(Duration::from_millis(self.frames % 4));
+        }
+
+        if self.frames == EDITOR_FRAMES {
+            window.close();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+struct Host {
+    editor: WindowHandle,
+    opened_at: Instant,
+    warmed_up: bool,
+    last_metrics: WindowMetrics,
+}
+
+impl WindowHandler for Host {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        if !self.editor.is_open() {
+            print_metrics(&self.last_metrics);
+            window.close();
+            return;
+        }
+
+        if !self.warmed_up && self.opened_at.elapsed() > WARM_UP {
+            println!("Resetting the editor's metrics after {:?}", WARM_UP);
+            self.editor.reset_metrics();
+            self.warmed_up = true;
+        }
+
+        // The handle keeps working after the editor closes, but the time since its last frame
+        // would keep growing from then on
+        self.last_metrics = self.editor.metrics();
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+fn print_metrics(metrics: &WindowMetrics) {
+    println!("The editor closed. Since the reset it has:");
+    println!("  drawn {} frames", metrics.frames);
+    println!("  taken {:?} per frame on average", metrics.average_frame_duration);
+    println!("  taken {:?} for its slowest frame", metrics.max_frame_duration);
+    println!(
+        "  received {} events ({} mouse, {} keyboard, {} window, {} dialog, {} accessibility)",
+        metrics.events.total(),
+        metrics.events.mouse,
+        metrics.events.keyboard,
+        metrics.events.window,
+        metrics.events.dialog,
+        metrics.events.accessibility
+    );
+    println!("  stalled for at most {:?}", metrics.longest_stall);
+    match metrics.since_last_frame {
+        Some(since_last_frame) => println!("  drawn its last frame {:?} ago", since_last_frame),
+        None => println!("  never drawn a frame"),
+    }
+}
+
+fn main() {
+    let host_options = WindowOpenOptions::new("Host", Size::new(512.0, 512.0))
+        .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(host_options, |window| {
+        window.set_animating(true);
+
+        let editor_options = WindowOpenOptions::new("Editor", Size::new(256.0, 256.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+        let editor = EventContext::open_parented(window, editor_options, |window| {
+            window.set_animating(true);
+            Editor { frames: 0 }
+        });
+
+        Host {
+            editor,
+            opened_at: Instant::now(),
+            warmed_up: false,
+            last_metrics: WindowMetrics::default(),
+        }
+    });
+}
@@ -0,0 +1,89 @@
+//! Nests one baseview window inside of another. Type in either window after clicking on it and
+//! check that only the window that was clicked last prints the key presses, no matter where the
+//! cursor is. Press Escape in the outer window to close or reopen the inner one. The outer window
+//! closes the inner window while handling `WillClose`, as nested windows need to be closed before
+//! the window they're nested in.
+
+use baseview::{
+    Event, EventContext, EventStatus, Point, Size, WindowEvent, WindowHandle, WindowHandler,
+    WindowOpenOptions,
+};
+use keyboard_types::{Key, KeyState};
+
+fn print_event(name: &str, event: &Event) {
+    match event {
+        Event::Keyboard(event) if event.state == KeyState::Down => {
+            println!("{}: {:?}", name, event.key);
+        }
+        Event::Window(
+            event @ (WindowEvent::Focused | WindowEvent::Unfocused | WindowEvent::WillClose),
+        ) => {
+            println!("{}: {}", name, event);
+        }
+        _ => {}
+    }
+}
+
+struct Inner;
+
+impl WindowHandler for Inner {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        print_event("inner", &event);
+
+        match event {
+            Event::Keyboard(_) => EventStatus::Captured,
+            _ => EventStatus::Ignored,
+        }
+    }
+}
+
+struct Outer {
+    inner: Option<WindowHandle>,
+}
+
+impl Outer {
+    fn open_inner(window: &mut EventContext) -> WindowHandle {
+        let options = WindowOpenOptions::new("Inner", Size::new(240.0, 160.0));
+        EventContext::open_parented(window, options, |window| {
+            window.set_position(Point::new(40.0, 40.0));
+            Inner
+        })
+    }
+}
+
+impl WindowHandler for Outer {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        print_event("outer", &event);
+
+        match event {
+            Event::Keyboard(event) if event.state == KeyState::Down => {
+                if event.key == Key::Escape {
+                    match self.inner.take() {
+                        Some(mut inner) => inner.close(),
+                        None => self.inner = Some(Outer::open_inner(window)),
+                    }
+                }
+
+                EventStatus::Captured
+            }
+            Event::Window(WindowEvent::WillClose) => {
+                if let Some(mut inner) = self.inner.take() {
+                    inner.close();
+                }
+
+                EventStatus::Ignored
+            }
+            _ => EventStatus::Ignored,
+        }
+    }
+}
+
+fn main() {
+    let options = WindowOpenOptions::new("Nested windows", Size::new(480.0, 320.0));
+
+    EventContext::open_blocking(options, |window| Outer { inner: Some(Outer::open_inner(window)) });
+}
@@ -1,70 +1,306 @@
 use std::time::Duration;
 
+use keyboard_types::{Code, Key, KeyState};
 use rtrb::{Consumer, RingBuffer};
 
 #[cfg(target_os = "macos")]
 use baseview::copy_to_clipboard;
-use baseview::{Event, EventStatus, Window, WindowHandler, WindowScalePolicy};
+use baseview::{
+    CursorIcon, CursorId, Data, DragImage, DropEffect, Event, EventContext, EventStatus,
+    FileDialogKind, FileDialogOptions, MouseButton, MouseCursor, MouseEvent, Point, Shortcut,
+    VirtualFile, WindowEvent, WindowHandler, WindowScalePolicy, X11PropertyValue,
+};
+
+/// Every cursor, so the platform mappings can be checked by clicking through them
+const MOUSE_CURSORS: &[MouseCursor] = &[
+    MouseCursor::Default,
+    MouseCursor::Hand,
+    MouseCursor::HandGrabbing,
+    MouseCursor::PointingHand,
+    MouseCursor::Help,
+    MouseCursor::Hidden,
+    MouseCursor::Text,
+    MouseCursor::VerticalText,
+    MouseCursor::Working,
+    MouseCursor::PtrWorking,
+    MouseCursor::NotAllowed,
+    MouseCursor::PtrNotAllowed,
+    MouseCursor::ZoomIn,
+    MouseCursor::ZoomOut,
+    MouseCursor::Alias,
+    MouseCursor::Copy,
+    MouseCursor::Move,
+    MouseCursor::AllScroll,
+    MouseCursor::Cell,
+    MouseCursor::Crosshair,
+    MouseCursor::EResize,
+    MouseCursor::NResize,
+    MouseCursor::NeResize,
+    MouseCursor::NwResize,
+    MouseCursor::SResize,
+    MouseCursor::SeResize,
+    MouseCursor::SwResize,
+    MouseCursor::WResize,
+    MouseCursor::EwResize,
+    MouseCursor::NsResize,
+    MouseCursor::NwseResize,
+    MouseCursor::NeswResize,
+    MouseCursor::ColResize,
+    MouseCursor::RowResize,
+];
 
 #[derive(Debug, Clone)]
 enum Message {
     Hello,
 }
 
+/// Attached to the window as user data when it's created.
+struct WindowTitle(&'static str);
+
 struct OpenWindowExample {
     rx: Consumer<Message>,
+    cursor_index: usize,
+    /// A red ring with the hotspot in its center, shown when pressing the C key.
+    ring_cursor: CursorId,
+    /// Every platform should send exactly one `Resized` event before the first frame
+    initial_resized_events: usize,
+    first_frame_drawn: bool,
+    /// Toggled with the P key. No frames should be drawn while paused, and the frame that was
+    /// pending when pausing should be drawn right after unpausing.
+    frames_paused: bool,
+    /// Toggled with the F key, switches between 60 and 10 frames per second.
+    slow_frames: bool,
+    /// Used to print where files are being dragged. This should match the mouse cursor's position
+    /// within the window, and it should have a negative y coordinate over the title bar.
+    cursor_position: Point,
+    /// Where the right click that started the current drag was. Keep the mouse still after right
+    /// clicking, and the drag's first position in this window should be the same. Drag positions
+    /// that are scaled differently from mouse events end up in the wrong place on HiDPI displays.
+    drag_start_position: Option<Point>,
+}
+
+impl OpenWindowExample {
+    fn next_mouse_cursor(&mut self, window: &mut EventContext) {
+        self.cursor_index = (self.cursor_index + 1) % MOUSE_CURSORS.len();
+        println!("Mouse cursor: {:?}", MOUSE_CURSORS[self.cursor_index]);
+        window.set_mouse_cursor(MOUSE_CURSORS[self.cursor_index]);
+    }
 }
 
 impl WindowHandler for OpenWindowExample {
-    fn on_frame(&mut self, _window: &mut Window) {
+    fn on_frame(&mut self, _window: &mut EventContext) {
+        if !self.first_frame_drawn {
+            assert_eq!(
+                self.initial_resized_events, 1,
+                "Expected one Resized event before on_frame"
+            );
+            self.first_frame_drawn = true;
+        }
+        assert!(!self.frames_paused, "on_frame was called while frames were paused");
+
         while let Ok(message) = self.rx.pop() {
             println!("Message: {:?}", message);
         }
     }
 
-    fn on_event(&mut self, _window: &mut Window, event: Event) -> EventStatus {
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
         match event {
             Event::Mouse(e) => {
                 println!("Mouse event: {:?}", e);
 
+                if let MouseEvent::CursorMoved { position, .. } = e {
+                    self.cursor_position = position;
+                }
+
+                // Left clicking cycles through all of the mouse cursors
+                if let MouseEvent::ButtonPressed { button: MouseButton::Left, .. } = e {
+                    self.next_mouse_cursor(window);
+                }
+
+                // Right clicking drags a text file that's only created once it's dropped
+                if let MouseEvent::ButtonPressed { button: MouseButton::Right, .. } = e {
+                    let file = VirtualFile::new("baseview.txt", || {
+                        println!("Generating the dragged file's contents");
+                        b"Dragged out of baseview".to_vec()
+                    });
+                    self.drag_start_position = Some(self.cursor_position);
+                    window.start_drag(
+                        Data::VirtualFile(file),
+                        DropEffect::Copy,
+                        Some(file_thumbnail()),
+                    );
+                }
+
                 #[cfg(target_os = "macos")]
                 match e {
-                    baseview::MouseEvent::ButtonPressed { button, modifiers } => {
+                    baseview::MouseEvent::ButtonPressed { button, modifiers, .. } => {
                         copy_to_clipboard(&"This is a test!")
                     }
                     _ => (),
                 }
             }
-            Event::Keyboard(e) => println!("Keyboard event: {:?}", e),
-            Event::Window(e) => println!("Window event: {:?}", e),
+            Event::Keyboard(e) => {
+                println!("Keyboard event: {:?}", e);
+
+                let close_shortcut = Shortcut::command_or_control(Key::Character("w".into()));
+                if close_shortcut.matches(&e) {
+                    println!("{} pressed, closing the window", close_shortcut.display_string());
+                    window.close();
+                }
+
+                if e.state == KeyState::Down && !e.repeat {
+                    match e.code {
+                        Code::KeyP => {
+                            self.frames_paused = !self.frames_paused;
+                            println!("Frames paused: {}", self.frames_paused);
+                            window.pause_frames(self.frames_paused);
+                        }
+                        Code::KeyF => {
+                            self.slow_frames = !self.slow_frames;
+                            let fps = if self.slow_frames { 10 } else { 60 };
+                            println!("Frame rate: {} fps", fps);
+                            window.set_frame_interval(Duration::from_secs(1) / fps);
+                        }
+                        Code::KeyO => {
+                            let options = FileDialogOptions::new(FileDialogKind::Open)
+                                .title("Open some files")
+                                .filter("Text files", &["txt", "md"])
+                                .multiple(true);
+                            match window.open_file_dialog(options) {
+                                Ok(id) => println!("Opened file dialog {:?}", id),
+                                Err(err) => println!("Could not open a file dialog: {}", err),
+                            }
+                        }
+                        // Unlike clicking, this doesn't move the mouse at all, so it shows whether
+                        // the cursor changes while the pointer is standing still
+                        Code::KeyM => self.next_mouse_cursor(window),
+                        Code::KeyC => {
+                            println!("Mouse cursor: ring");
+                            window.set_mouse_cursor(MouseCursor::Custom(self.ring_cursor));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Event::Window(e) => {
+                println!("Window event: {:?}", e);
+
+                match e {
+                    WindowEvent::Resized { .. } if !self.first_frame_drawn => {
+                        self.initial_resized_events += 1;
+                    }
+                    WindowEvent::DragEnter { .. } => {
+                        // The drag's position was sent right before this event
+                        if let Some(start) = self.drag_start_position.take() {
+                            println!(
+                                "Drag entered at {}, started at {}",
+                                self.cursor_position, start
+                            );
+                            assert!(
+                                (self.cursor_position.x - start.x).abs() <= 1.0
+                                    && (self.cursor_position.y - start.y).abs() <= 1.0,
+                                "The drag's position doesn't match the mouse cursor's position"
+                            );
+                        }
+                    }
+                    WindowEvent::Dragging => {
+                        println!("Dragging at {}", self.cursor_position);
+                    }
+                    WindowEvent::DragSourceEnded { .. } => {
+                        self.drag_start_position = None;
+                    }
+                    WindowEvent::WillClose => {
+                        // User data only gets dropped after the handler has received this event
+                        let title = window.user_data::<WindowTitle>();
+                        assert_eq!(
+                            title.map(|title| title.0),
+                            Some("baseview"),
+                            "Expected the user data to still be set"
+                        );
+                    }
+                    _ => (),
+                }
+            }
+            Event::Dialog(result) => println!("Dialog closed: {}", result),
+            #[cfg(feature = "accesskit")]
+            Event::AccessKit(_) => return EventStatus::Ignored,
         }
 
         EventStatus::Captured
     }
 }
 
+fn ring_cursor_icon() -> CursorIcon {
+    const SIZE: u32 = 24;
+
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = (x as f32 - center).hypot(y as f32 - center);
+            let alpha = if (7.0..=10.0).contains(&distance) { 255 } else { 0 };
+            rgba.extend_from_slice(&[255, 0, 0, alpha]);
+        }
+    }
+
+    CursorIcon::from_rgba(rgba, SIZE, SIZE, SIZE / 2, SIZE / 2)
+        .unwrap()
+        .with_fallback(MouseCursor::Crosshair)
+}
+
+/// A slightly transparent page with a few lines of text on it, centered on the cursor.
+fn file_thumbnail() -> DragImage {
+    const WIDTH: u32 = 40;
+    const HEIGHT: u32 = 48;
+
+    let mut rgba = Vec::with_capacity((WIDTH * HEIGHT * 4) as usize);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let border = x == 0 || y == 0 || x == WIDTH - 1 || y == HEIGHT - 1;
+            let text = (6..WIDTH - 6).contains(&x) && (8..HEIGHT - 8).contains(&y) && y % 6 < 2;
+            let pixel = if border || text { [64, 64, 64, 220] } else { [255, 255, 255, 200] };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+
+    DragImage::from_rgba(rgba, WIDTH, HEIGHT, WIDTH / 2, HEIGHT / 2).unwrap()
+}
+
 fn main() {
-    let window_open_options = baseview::WindowOpenOptions {
-        title: "baseview".into(),
-        size: baseview::Size::new(512.0, 512.0),
-        scale: WindowScalePolicy::SystemScaleFactor,
-        drop_target_valid: None,
-        resizable: true,
-
-        // TODO: Add an example that uses the OpenGL context
-        #[cfg(feature = "opengl")]
-        gl_config: None,
-    };
+    let window_open_options =
+        baseview::WindowOpenOptions::new("baseview", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true)
+            // Only used on Linux. Shows up in `xprop` as `_BASEVIEW_EXAMPLE(UTF8_STRING)`.
+            .x11_properties(vec![(
+                "_BASEVIEW_EXAMPLE".to_string(),
+                X11PropertyValue::Utf8String("open_window".to_string()),
+            )]);
 
     let (mut tx, rx) = RingBuffer::new(128);
 
     ::std::thread::spawn(move || loop {
         ::std::thread::sleep(Duration::from_secs(5));
 
-        if let Err(_) = tx.push(Message::Hello) {
+        if tx.push(Message::Hello).is_err() {
             println!("Failed sending message");
         }
     });
 
-    Window::open_blocking(window_open_options, |_| OpenWindowExample { rx });
+    EventContext::open_blocking(window_open_options, |window| {
+        window.set_user_data(WindowTitle("baseview"));
+        println!("Drag and drop available: {}", window.drag_and_drop_available());
+
+        OpenWindowExample {
+            rx,
+            cursor_index: 0,
+            ring_cursor: window.register_cursor(ring_cursor_icon()),
+            initial_resized_events: 0,
+            first_frame_drawn: false,
+            frames_paused: false,
+            slow_frames: false,
+            cursor_position: Point::new(0.0, 0.0),
+            drag_start_position: None,
+        }
+    });
 }
@@ -0,0 +1,121 @@
+//! Records strokes like a paint program would, with a stroke width that follows the pen's pressure.
+//! The examples don't have a renderer, so instead of drawing the strokes this prints a summary of
+//! every stroke once the pen is lifted, along with the pressure and tilt ranges seen so far. Press
+//! the pen down hard and tilt it in every direction to check that the values cover the documented
+//! ranges. Mice and touchpads should only produce `CursorMoved` events.
+
+use baseview::{
+    Event, EventContext, EventStatus, MouseButton, MouseEvent, Point, WindowHandler,
+    WindowScalePolicy,
+};
+
+/// The stroke width at full pressure, in logical pixels.
+const MAX_STROKE_WIDTH: f32 = 24.0;
+
+#[derive(Default)]
+struct Stroke {
+    /// The points of the stroke and the stroke's width at those points.
+    points: Vec<(Point, f32)>,
+}
+
+struct Range {
+    min: f32,
+    max: f32,
+}
+
+impl Range {
+    fn new() -> Self {
+        Self { min: f32::INFINITY, max: f32::NEG_INFINITY }
+    }
+
+    fn add(&mut self, value: f32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+}
+
+struct PenPressureExample {
+    /// The stroke that's being drawn while the left mouse button or the pen is held down.
+    current_stroke: Option<Stroke>,
+    pressure: Range,
+    tilt_x: Range,
+    tilt_y: Range,
+}
+
+impl PenPressureExample {
+    fn finish_stroke(&mut self) {
+        let Some(stroke) = self.current_stroke.take() else { return };
+        if stroke.points.is_empty() {
+            println!("Finished a stroke without pen input");
+            return;
+        }
+
+        let mut width = Range::new();
+        for &(_, point_width) in &stroke.points {
+            width.add(point_width);
+        }
+        println!(
+            "Finished a stroke with {} points and a width from {:.1} to {:.1}",
+            stroke.points.len(),
+            width.min,
+            width.max
+        );
+        println!(
+            "Ranges so far: pressure {:.3} to {:.3}, tilt {:.3} to {:.3} horizontally and {:.3} to \
+             {:.3} vertically",
+            self.pressure.min,
+            self.pressure.max,
+            self.tilt_x.min,
+            self.tilt_x.max,
+            self.tilt_y.min,
+            self.tilt_y.max
+        );
+    }
+}
+
+impl WindowHandler for PenPressureExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, .. }) => {
+                self.current_stroke = Some(Stroke::default());
+            }
+            Event::Mouse(MouseEvent::ButtonReleased { button: MouseButton::Left, .. }) => {
+                self.finish_stroke();
+            }
+            Event::Mouse(MouseEvent::PenMoved { position, pressure, tilt, .. }) => {
+                if !(0.0..=1.0).contains(&pressure)
+                    || !(-1.0..=1.0).contains(&tilt.0)
+                    || !(-1.0..=1.0).contains(&tilt.1)
+                {
+                    println!("Out of range: pressure {}, tilt {:?}", pressure, tilt);
+                }
+
+                self.pressure.add(pressure);
+                self.tilt_x.add(tilt.0);
+                self.tilt_y.add(tilt.1);
+                if let Some(stroke) = &mut self.current_stroke {
+                    stroke.points.push((position, pressure * MAX_STROKE_WIDTH));
+                }
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Draw with a pen", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true);
+
+    EventContext::open_blocking(window_open_options, |_| PenPressureExample {
+        current_stroke: None,
+        pressure: Range::new(),
+        tilt_x: Range::new(),
+        tilt_y: Range::new(),
+    });
+}
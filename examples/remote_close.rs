@@ -0,0 +1,114 @@
+//! Closes an embedded window from another thread through a `RemoteWindowHandle`. The host window
+//! opens an editor inside of itself, and a thread of its own closes the editor after a second and
+//! waits until the editor is closed. The host closes itself once the editor is gone, and prints
+//! whether the editor received `WillClose` before that. The process exits with an error if the
+//! editor didn't close within a few seconds.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use baseview::{
+    Event, EventContext, EventStatus, Size, WindowEvent, WindowHandle, WindowHandler,
+    WindowHandlerWithResult, WindowOpenOptions, WindowScalePolicy,
+};
+
+const CLOSE_AFTER: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Editor {
+    received_will_close: Arc<AtomicBool>,
+}
+
+impl WindowHandler for Editor {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        if let Event::Window(WindowEvent::WillClose) = event {
+            self.received_will_close.store(true, Ordering::Relaxed);
+        }
+
+        EventStatus::Ignored
+    }
+}
+
+struct Host {
+    editor: WindowHandle,
+    editor_received_will_close: Arc<AtomicBool>,
+    opened_at: Instant,
+    passed: bool,
+}
+
+impl WindowHandler for Host {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        if !self.editor.is_open() {
+            self.passed = self.editor_received_will_close.load(Ordering::Relaxed);
+            if self.passed {
+                println!("PASS: the editor received WillClose and closed");
+            } else {
+                println!("FAIL: the editor closed without receiving WillClose");
+            }
+            window.close();
+        } else if self.opened_at.elapsed() > TIMEOUT {
+            println!("FAIL: the editor was still open after {:?}", TIMEOUT);
+            window.close();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+impl WindowHandlerWithResult for Host {
+    type Output = bool;
+
+    fn on_close(self: Box<Self>) -> bool {
+        self.passed
+    }
+}
+
+fn main() {
+    let host_options = WindowOpenOptions::new("Host", Size::new(512.0, 512.0))
+        .scale(WindowScalePolicy::SystemScaleFactor);
+
+    let passed = EventContext::open_blocking_with_result(host_options, |window| {
+        window.set_animating(true);
+
+        let received_will_close = Arc::new(AtomicBool::new(false));
+        let editor_options = WindowOpenOptions::new("Editor", Size::new(256.0, 256.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+        let editor = EventContext::open_parented(window, editor_options, {
+            let received_will_close = received_will_close.clone();
+            move |_| Editor { received_will_close }
+        });
+
+        // Unlike the `WindowHandle`, this can be moved to another thread
+        let remote = editor.remote();
+        thread::spawn(move || {
+            thread::sleep(CLOSE_AFTER);
+
+            let closed_at = Instant::now();
+            remote.close();
+            while remote.is_open() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            println!(
+                "The editor closed {:?} after it was closed from another thread",
+                closed_at.elapsed()
+            );
+        });
+
+        Host {
+            editor,
+            editor_received_will_close: received_will_close,
+            opened_at: Instant::now(),
+            passed: false,
+        }
+    });
+
+    if passed != Some(true) {
+        std::process::exit(1);
+    }
+}
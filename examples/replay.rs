@@ -0,0 +1,149 @@
+//! Records the window's events to a file, or plays a recording back:
+//!
+//! ```text
+//! cargo run --example replay --features serde -- record events.jsonl
+//! cargo run --example replay --features serde -- replay events.jsonl
+//! ```
+//!
+//! Every line of the file contains a single event and the time it arrived at as JSON.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use baseview::{
+    Event, EventContext, EventStatus, MouseButton, MouseCursor, MouseEvent, WindowEvent,
+    WindowHandler, WindowScalePolicy,
+};
+
+const MOUSE_CURSORS: &[MouseCursor] = &[
+    MouseCursor::Default,
+    MouseCursor::Hand,
+    MouseCursor::Text,
+    MouseCursor::Crosshair,
+    MouseCursor::NotAllowed,
+];
+
+/// A single line in the recording.
+#[derive(Serialize, Deserialize)]
+struct RecordedEvent {
+    /// The time since the window was opened.
+    time: Duration,
+    event: Event,
+}
+
+/// The handler that receives either the live or the recorded events. Left clicking cycles through
+/// some of the mouse cursors, so replaying a recording visibly does the same thing.
+struct App {
+    cursor_index: usize,
+}
+
+impl App {
+    fn on_event(&mut self, window: &mut EventContext, event: &Event) {
+        println!("{}", event);
+
+        if let Event::Mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, .. }) = event {
+            self.cursor_index = (self.cursor_index + 1) % MOUSE_CURSORS.len();
+            window.set_mouse_cursor(MOUSE_CURSORS[self.cursor_index]);
+        }
+    }
+}
+
+enum Mode {
+    Record(BufWriter<File>),
+    /// The remaining events, in the order they were recorded.
+    Replay(std::vec::IntoIter<RecordedEvent>),
+}
+
+struct ReplayExample {
+    app: App,
+    mode: Mode,
+    opened_at: Instant,
+    /// The next event to replay, once its time has come.
+    next_event: Option<RecordedEvent>,
+}
+
+impl WindowHandler for ReplayExample {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let Mode::Replay(events) = &mut self.mode else {
+            return;
+        };
+
+        let elapsed = self.opened_at.elapsed();
+        loop {
+            match self.next_event.take().or_else(|| events.next()) {
+                Some(recorded) if recorded.time <= elapsed => {
+                    self.app.on_event(window, &recorded.event)
+                }
+                Some(recorded) => {
+                    self.next_event = Some(recorded);
+                    break;
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        match &mut self.mode {
+            Mode::Record(file) => {
+                let recorded = RecordedEvent { time: self.opened_at.elapsed(), event };
+                serde_json::to_writer(&mut *file, &recorded).expect("Could not write the event");
+                writeln!(file).expect("Could not write the event");
+                if let Event::Window(WindowEvent::WillClose) = recorded.event {
+                    file.flush().expect("Could not write the recording");
+                }
+
+                self.app.on_event(window, &recorded.event);
+            }
+            // Only the window's own events are passed through while replaying, the input comes
+            // from the recording
+            Mode::Replay(_) => {
+                if let Event::Window(_) = &event {
+                    self.app.on_event(window, &event);
+                }
+            }
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mode = match (args.get(1).map(String::as_str), args.get(2)) {
+        (Some("record"), Some(path)) => {
+            Mode::Record(BufWriter::new(File::create(path).expect("Could not create the file")))
+        }
+        (Some("replay"), Some(path)) => {
+            let file = BufReader::new(File::open(path).expect("Could not open the file"));
+            let events: Vec<RecordedEvent> = file
+                .lines()
+                .map(|line| {
+                    serde_json::from_str(&line.expect("Could not read the file"))
+                        .expect("Could not parse the event")
+                })
+                .collect();
+
+            Mode::Replay(events.into_iter())
+        }
+        _ => {
+            eprintln!("Usage: replay (record|replay) <file>");
+            std::process::exit(1);
+        }
+    };
+
+    let window_open_options =
+        baseview::WindowOpenOptions::new("baseview", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true);
+
+    EventContext::open_blocking(window_open_options, move |_| ReplayExample {
+        app: App { cursor_index: 0 },
+        mode,
+        opened_at: Instant::now(),
+        next_event: None,
+    });
+}
@@ -0,0 +1,58 @@
+//! Prints every scroll event along with the direction it scrolls in, so the signs can be checked
+//! against the conventions documented on `ScrollDelta`. Scroll with a mouse wheel, tilt the wheel
+//! or hold Shift for horizontal scrolling, and try a touchpad with and without natural scrolling.
+
+use baseview::{
+    Event, EventContext, EventStatus, MouseEvent, ScrollDelta, WindowHandler, WindowScalePolicy,
+};
+
+struct ScrollDeltasExample;
+
+/// Describe a delta's direction in terms of scrolling, not in terms of the content's movement.
+fn direction(x: f32, y: f32) -> String {
+    let vertical = match y {
+        y if y > 0.0 => Some("up"),
+        y if y < 0.0 => Some("down"),
+        _ => None,
+    };
+    let horizontal = match x {
+        x if x > 0.0 => Some("right"),
+        x if x < 0.0 => Some("left"),
+        _ => None,
+    };
+
+    match (vertical, horizontal) {
+        (Some(vertical), Some(horizontal)) => format!("{} and {}", vertical, horizontal),
+        (Some(direction), None) | (None, Some(direction)) => direction.to_string(),
+        (None, None) => "nowhere".to_string(),
+    }
+}
+
+impl WindowHandler for ScrollDeltasExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Mouse(MouseEvent::WheelScrolled { delta, .. }) = event else {
+            return EventStatus::Ignored;
+        };
+
+        match delta {
+            ScrollDelta::Lines { x, y } => {
+                println!("Scrolled {} by lines: x {:+.2}, y {:+.2}", direction(x, y), x, y)
+            }
+            ScrollDelta::Pixels { x, y } => {
+                println!("Scrolled {} by pixels: x {:+.1}, y {:+.1}", direction(x, y), x, y)
+            }
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Scroll deltas", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| ScrollDeltasExample);
+}
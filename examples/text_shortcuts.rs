@@ -0,0 +1,137 @@
+//! A text field that implements its own clipboard shortcuts, like a plugin's text fields would.
+//! Type to edit the text, and use Cmd+A, Cmd+C, and Cmd+X on macOS or Ctrl+A, Ctrl+C, and Ctrl+X
+//! elsewhere to select, copy, and cut all of it. Other shortcuts are ignored, so on macOS Cmd+Q
+//! still reaches the application's menu. The examples don't have a renderer, so instead of an
+//! on-screen log this prints the last key events along with what the field did with them. Every
+//! key press should show up exactly once, including the shortcuts the field ignored.
+
+use std::collections::VecDeque;
+
+use baseview::keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers};
+use baseview::{
+    copy_to_clipboard, Event, EventContext, EventStatus, WindowHandler, WindowScalePolicy,
+};
+
+/// How many key events the log shows.
+const LOG_LENGTH: usize = 8;
+
+#[cfg(target_os = "macos")]
+const SHORTCUT_MODIFIER: Modifiers = Modifiers::META;
+#[cfg(not(target_os = "macos"))]
+const SHORTCUT_MODIFIER: Modifiers = Modifiers::CONTROL;
+
+struct TextShortcutsExample {
+    text: String,
+    all_selected: bool,
+    log: VecDeque<String>,
+}
+
+impl TextShortcutsExample {
+    /// Apply a key press to the text field, and describe what it did. Returns `None` if the field
+    /// ignored the key.
+    fn key_pressed(&mut self, event: &KeyboardEvent) -> Option<String> {
+        if event.modifiers.contains(SHORTCUT_MODIFIER) {
+            return match &event.key {
+                Key::Character(c) if c.eq_ignore_ascii_case("a") => {
+                    self.all_selected = true;
+                    Some("selected all".to_string())
+                }
+                Key::Character(c) if c.eq_ignore_ascii_case("c") => {
+                    copy_to_clipboard(&self.selection());
+                    Some(format!("copied {:?}", self.selection()))
+                }
+                Key::Character(c) if c.eq_ignore_ascii_case("x") => {
+                    copy_to_clipboard(&self.selection());
+                    let cut = format!("cut {:?}", self.selection());
+                    if self.all_selected {
+                        self.replace_selection("");
+                    }
+                    Some(cut)
+                }
+                _ => None,
+            };
+        }
+
+        match &event.key {
+            Key::Character(text)
+                if !event.modifiers.intersects(Modifiers::ALT | Modifiers::CONTROL) =>
+            {
+                self.replace_selection(text);
+            }
+            Key::Backspace => {
+                if self.all_selected {
+                    self.replace_selection("");
+                } else {
+                    self.text.pop();
+                }
+            }
+            _ => return None,
+        }
+
+        Some(format!("text is now {:?}", self.text))
+    }
+
+    fn selection(&self) -> String {
+        if self.all_selected {
+            self.text.clone()
+        } else {
+            String::new()
+        }
+    }
+
+    fn replace_selection(&mut self, text: &str) {
+        if std::mem::take(&mut self.all_selected) {
+            self.text.clear();
+        }
+        self.text.push_str(text);
+    }
+
+    fn log(&mut self, entry: String) {
+        if self.log.len() == LOG_LENGTH {
+            self.log.pop_front();
+        }
+        self.log.push_back(entry);
+
+        println!("--- last {} key events ---", self.log.len());
+        for entry in &self.log {
+            println!("{}", entry);
+        }
+    }
+}
+
+impl WindowHandler for TextShortcutsExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Keyboard(event) = event else {
+            return EventStatus::Ignored;
+        };
+        if event.state == KeyState::Up {
+            return EventStatus::Ignored;
+        }
+
+        let description = format!("{:?} {:?}", event.modifiers, event.key);
+        match self.key_pressed(&event) {
+            Some(action) => {
+                self.log(format!("{}: {}", description, action));
+                EventStatus::Captured
+            }
+            None => {
+                self.log(format!("{}: ignored", description));
+                EventStatus::Ignored
+            }
+        }
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Text shortcuts", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| TextShortcutsExample {
+        text: String::new(),
+        all_selected: false,
+        log: VecDeque::new(),
+    });
+}
@@ -0,0 +1,31 @@
+use baseview::{Event, EventContext, EventStatus, WindowHandler, WindowScalePolicy};
+use tracing_subscriber::EnvFilter;
+
+/// Opens an empty window while printing baseview's instrumentation. The output can be filtered
+/// through the `RUST_LOG` environment variable, e.g. `RUST_LOG=baseview::dnd=trace`. Run this
+/// with `cargo run --example tracing --features tracing`.
+struct TracingExample;
+
+impl WindowHandler for TracingExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, _window: &mut EventContext, _event: Event) -> EventStatus {
+        EventStatus::Ignored
+    }
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("baseview=trace,baseview::handler=debug")),
+        )
+        .init();
+
+    let window_open_options =
+        baseview::WindowOpenOptions::new("baseview tracing", baseview::Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor)
+            .resizable(true);
+
+    EventContext::open_blocking(window_open_options, |_| TracingExample);
+}
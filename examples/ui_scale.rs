@@ -0,0 +1,85 @@
+//! A UI scale setting on top of the system's dpi scaling, like the zoom setting in a plugin's
+//! editor. Scroll with Control held down to make everything bigger or smaller, and click to reset
+//! the zoom. The window keeps its size on screen while its logical size changes, and the cursor
+//! positions printed while moving the mouse stay consistent with the new logical size.
+
+use baseview::keyboard_types::Modifiers;
+use baseview::{
+    Event, EventContext, EventStatus, MouseButton, MouseEvent, ScrollDelta, Size, WindowEvent,
+    WindowHandler, WindowScalePolicy,
+};
+
+const ZOOM_STEP: f64 = 0.25;
+const ZOOM_RANGE: (f64, f64) = (0.5, 3.0);
+
+struct UiScaleExample {
+    /// The system's scale factor, which the zoom is applied on top of.
+    system_scale: f64,
+    zoom: f64,
+}
+
+impl UiScaleExample {
+    fn set_zoom(&mut self, window: &mut EventContext, zoom: f64) {
+        self.zoom = zoom.clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+        if self.zoom == 1.0 {
+            window.set_scale_override(None);
+        } else {
+            window.set_scale_override(Some(self.system_scale * self.zoom));
+        }
+    }
+}
+
+impl WindowHandler for UiScaleExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Window(WindowEvent::Resized { new, .. }) => {
+                // The override replaces the system's scale factor, so it's only known without one
+                if self.zoom == 1.0 {
+                    self.system_scale = new.scale();
+                }
+
+                let size = new.logical_size();
+                println!(
+                    "Zoom {:.0}%: {:.1}x{:.1} logical pixels at a scale factor of {:.2}",
+                    self.zoom * 100.0,
+                    size.width,
+                    size.height,
+                    new.scale()
+                );
+            }
+            Event::Mouse(MouseEvent::WheelScrolled { delta, modifiers })
+                if modifiers.contains(Modifiers::CONTROL) =>
+            {
+                let y = match delta {
+                    ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. } => y,
+                };
+                if y > 0.0 {
+                    self.set_zoom(window, self.zoom + ZOOM_STEP);
+                } else if y < 0.0 {
+                    self.set_zoom(window, self.zoom - ZOOM_STEP);
+                }
+            }
+            Event::Mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, .. }) => {
+                self.set_zoom(window, 1.0);
+            }
+            Event::Mouse(MouseEvent::CursorMoved { position, .. }) => {
+                println!("Cursor at {:.1}, {:.1}", position.x, position.y);
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options = baseview::WindowOpenOptions::new("UI scale", Size::new(400.0, 300.0))
+        .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| UiScaleExample {
+        system_scale: 1.0,
+        zoom: 1.0,
+    });
+}
@@ -0,0 +1,74 @@
+//! Prints the window's state whenever it changes. Minimize, hide and restore the window, or click
+//! on another window, and compare the printed state with what the window manager shows. The
+//! queries are checked against the events that describe the same state, so this panics if a
+//! backend's queries disagree with its own events.
+
+use baseview::{Event, EventContext, EventStatus, Size, WindowEvent, WindowHandler};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WindowState {
+    visible: bool,
+    minimized: bool,
+    focused: bool,
+    resizable: bool,
+}
+
+impl WindowState {
+    fn query(window: &EventContext) -> Self {
+        WindowState {
+            visible: window.is_visible(),
+            minimized: window.is_minimized(),
+            focused: window.has_focus(),
+            resizable: window.is_resizable(),
+        }
+    }
+}
+
+struct WindowStateExample {
+    last_state: Option<WindowState>,
+}
+
+impl WindowStateExample {
+    fn print_state(&mut self, window: &EventContext) {
+        let state = WindowState::query(window);
+        if self.last_state != Some(state) {
+            println!("{:?}", state);
+            self.last_state = Some(state);
+        }
+    }
+}
+
+impl WindowHandler for WindowStateExample {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        // Minimizing doesn't always come with an event, so the state is also polled while frames
+        // are being drawn
+        self.print_state(window);
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let Event::Window(event) = event else {
+            return EventStatus::Ignored;
+        };
+
+        println!("{}", event);
+        match event {
+            WindowEvent::Shown => assert!(window.is_visible()),
+            WindowEvent::Hidden => assert!(!window.is_visible()),
+            WindowEvent::Focused => assert!(window.has_focus()),
+            _ => {}
+        }
+        self.print_state(window);
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("Window state", Size::new(400.0, 300.0)).resizable(true);
+
+    EventContext::open_blocking(window_open_options, |window| {
+        assert!(window.is_resizable());
+        WindowStateExample { last_state: None }
+    });
+}
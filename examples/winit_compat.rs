@@ -0,0 +1,232 @@
+//! Drives a small immediate mode GUI integration through the `winit_compat` module. The `State`
+//! below follows the shape of `egui-winit`'s `State`: it collects the `winit` events into the
+//! input for the next frame, tells the caller whether the GUI consumed them and needs to be
+//! repainted, and applies the GUI's output, like its cursor icon, to the window. Porting it from
+//! `winit` only meant changing the imports, which is the point of the compatibility layer.
+//!
+//! The GUI consists of a single button in the top left corner, which changes the cursor when it's
+//! hovered and counts its clicks. Dropped files are listed, and the GUI only asks for a redraw
+//! when something changed.
+
+use std::path::PathBuf;
+
+use baseview::keyboard_types::{Key, Modifiers};
+use baseview::winit_compat::{
+    CompatWindow, CursorIcon, ElementState, MouseButton, MouseScrollDelta, PhysicalPosition,
+    WindowEvent, WinitCompat,
+};
+use baseview::{Event, EventContext, EventStatus, Size, WindowHandler, WindowScalePolicy};
+
+/// The input for a single frame, like `egui::RawInput`.
+#[derive(Debug, Default)]
+struct RawInput {
+    /// The screen size in points.
+    screen_size: (f32, f32),
+    pixels_per_point: f32,
+    modifiers: Modifiers,
+    events: Vec<InputEvent>,
+    dropped_files: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+enum InputEvent {
+    PointerMoved(f32, f32),
+    PointerButton { pressed: bool },
+    PointerGone,
+    Scroll(f32, f32),
+    Text(String),
+    Key(Key),
+}
+
+/// Whether the GUI used an event and needs to be repainted, like `egui_winit::EventResponse`.
+struct EventResponse {
+    consumed: bool,
+    repaint: bool,
+}
+
+/// The `winit` glue. Only the imports differ from a version written for `winit` itself.
+#[derive(Default)]
+struct State {
+    input: RawInput,
+    current_cursor_icon: Option<CursorIcon>,
+}
+
+impl State {
+    fn on_window_event(&mut self, window: &CompatWindow, event: &WindowEvent) -> EventResponse {
+        let pixels_per_point = window.scale_factor() as f32;
+        self.input.pixels_per_point = pixels_per_point;
+        let size = window.inner_size();
+        self.input.screen_size =
+            (size.width as f32 / pixels_per_point, size.height as f32 / pixels_per_point);
+
+        match event {
+            WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                EventResponse { consumed: false, repaint: true }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.input.modifiers = *modifiers;
+                EventResponse { consumed: false, repaint: false }
+            }
+            WindowEvent::CursorMoved { position } => {
+                self.on_cursor_moved(pixels_per_point, *position);
+                EventResponse { consumed: true, repaint: true }
+            }
+            WindowEvent::CursorLeft => {
+                self.input.events.push(InputEvent::PointerGone);
+                EventResponse { consumed: false, repaint: true }
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left } => {
+                let pressed = *state == ElementState::Pressed;
+                self.input.events.push(InputEvent::PointerButton { pressed });
+                EventResponse { consumed: true, repaint: true }
+            }
+            WindowEvent::MouseWheel { delta } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x * 50.0, y * 50.0),
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        (delta.x as f32 / pixels_per_point, delta.y as f32 / pixels_per_point)
+                    }
+                };
+                self.input.events.push(InputEvent::Scroll(x, y));
+                EventResponse { consumed: true, repaint: true }
+            }
+            WindowEvent::KeyboardInput { event } => {
+                if event.state.is_pressed() {
+                    self.input.events.push(InputEvent::Key(event.logical_key.clone()));
+                    if let Some(text) = &event.text {
+                        self.input.events.push(InputEvent::Text(text.clone()));
+                    }
+                }
+                EventResponse { consumed: true, repaint: true }
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.input.dropped_files.push(path.clone());
+                EventResponse { consumed: true, repaint: true }
+            }
+            _ => EventResponse { consumed: false, repaint: false },
+        }
+    }
+
+    fn on_cursor_moved(&mut self, pixels_per_point: f32, position: PhysicalPosition<f64>) {
+        let pos = (position.x as f32 / pixels_per_point, position.y as f32 / pixels_per_point);
+        self.input.events.push(InputEvent::PointerMoved(pos.0, pos.1));
+    }
+
+    fn take_input(&mut self) -> RawInput {
+        RawInput {
+            screen_size: self.input.screen_size,
+            pixels_per_point: self.input.pixels_per_point,
+            modifiers: self.input.modifiers,
+            events: std::mem::take(&mut self.input.events),
+            dropped_files: std::mem::take(&mut self.input.dropped_files),
+        }
+    }
+
+    /// Apply the GUI's output to the window, like `egui_winit::State::handle_platform_output()`.
+    fn handle_platform_output(&mut self, window: &mut CompatWindow, cursor_icon: CursorIcon) {
+        if self.current_cursor_icon != Some(cursor_icon) {
+            self.current_cursor_icon = Some(cursor_icon);
+            window.set_cursor_icon(cursor_icon);
+        }
+    }
+}
+
+/// The GUI itself, which would be an `egui::Context` in a real integration.
+#[derive(Default)]
+struct Gui {
+    screen_size: (f32, f32),
+    pointer: Option<(f32, f32)>,
+    clicks: u32,
+}
+
+impl Gui {
+    const BUTTON: (f32, f32, f32, f32) = (20.0, 20.0, 120.0, 40.0);
+
+    /// Run one frame and return the cursor icon the GUI wants.
+    fn run(&mut self, input: RawInput) -> CursorIcon {
+        if input.screen_size != self.screen_size {
+            self.screen_size = input.screen_size;
+            let (width, height) = input.screen_size;
+            let scale = input.pixels_per_point;
+            println!("Screen size: {}x{} points at {} pixels per point", width, height, scale);
+        }
+        for event in &input.events {
+            match event {
+                InputEvent::PointerMoved(x, y) => self.pointer = Some((*x, *y)),
+                InputEvent::PointerGone => self.pointer = None,
+                InputEvent::PointerButton { pressed: true } if self.button_hovered() => {
+                    self.clicks += 1;
+                    println!("Button clicked {} times", self.clicks);
+                }
+                InputEvent::Key(key) => println!("Key pressed: {:?} {:?}", input.modifiers, key),
+                InputEvent::Text(text) => println!("Text entered: {:?}", text),
+                InputEvent::Scroll(x, y) => println!("Scrolled by {}, {}", x, y),
+                _ => (),
+            }
+        }
+        for path in &input.dropped_files {
+            println!("Dropped file: {}", path.display());
+        }
+
+        if self.button_hovered() {
+            CursorIcon::Pointer
+        } else {
+            CursorIcon::Default
+        }
+    }
+
+    fn button_hovered(&self) -> bool {
+        let (left, top, width, height) = Self::BUTTON;
+        self.pointer
+            .is_some_and(|(x, y)| x >= left && y >= top && x < left + width && y < top + height)
+    }
+}
+
+struct WinitCompatExample {
+    compat: WinitCompat,
+    state: State,
+    gui: Gui,
+}
+
+impl WindowHandler for WinitCompatExample {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        // This is where `winit` would have sent `RedrawRequested`
+        let input = self.state.take_input();
+        let cursor_icon = self.gui.run(input);
+        let mut window = self.compat.window(window);
+        self.state.handle_platform_output(&mut window, cursor_icon);
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let mut status = EventStatus::Ignored;
+        for event in self.compat.translate(event) {
+            let mut window = self.compat.window(window);
+            let response = self.state.on_window_event(&window, &event);
+            if response.repaint {
+                window.request_redraw();
+            }
+            if response.consumed {
+                status = EventStatus::Captured;
+            }
+        }
+
+        status
+    }
+}
+
+fn main() {
+    let window_open_options =
+        baseview::WindowOpenOptions::new("winit compat", Size::new(512.0, 512.0))
+            .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |window| {
+        // Only redraw when the GUI asks for it, like a `winit` application would
+        window.set_animating(false);
+
+        WinitCompatExample {
+            compat: WinitCompat::new(),
+            state: State::default(),
+            gui: Gui::default(),
+        }
+    });
+}
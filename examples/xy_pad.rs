@@ -0,0 +1,65 @@
+//! An XY pad that covers the whole window, like the filter cutoff and resonance pad in a plugin's
+//! editor. While the left mouse button is held down the cursor is confined to the window, so the
+//! pad's values can be dragged all the way to their limits without the cursor wandering off. The
+//! examples don't have a renderer, so the pad's values are printed instead.
+
+use baseview::{
+    Event, EventContext, EventStatus, MouseButton, MouseEvent, Point, Size, WindowEvent,
+    WindowHandler, WindowScalePolicy,
+};
+
+struct XyPadExample {
+    /// The window's logical size, which the pad covers.
+    size: Size,
+    dragging: bool,
+}
+
+impl XyPadExample {
+    /// Print the pad's values for a cursor position. Both values are between 0 and 1, and the
+    /// vertical value increases towards the top.
+    fn print_values(&self, position: Point) {
+        let x = (position.x / self.size.width).clamp(0.0, 1.0);
+        let y = 1.0 - (position.y / self.size.height).clamp(0.0, 1.0);
+        println!("X: {:.3}, Y: {:.3}", x, y);
+    }
+}
+
+impl WindowHandler for XyPadExample {
+    fn on_frame(&mut self, _window: &mut EventContext) {}
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        match event {
+            Event::Window(WindowEvent::Resized { new, .. }) => {
+                self.size = new.logical_size();
+            }
+            Event::Window(WindowEvent::Unfocused) => {
+                // The window releases the cursor by itself here
+                self.dragging = false;
+            }
+            Event::Mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, .. }) => {
+                self.dragging = true;
+                window.set_cursor_confined(true);
+            }
+            Event::Mouse(MouseEvent::ButtonReleased { button: MouseButton::Left, .. }) => {
+                self.dragging = false;
+                window.set_cursor_confined(false);
+            }
+            Event::Mouse(MouseEvent::CursorMoved { position, .. }) if self.dragging => {
+                self.print_values(position);
+            }
+            _ => return EventStatus::Ignored,
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options = baseview::WindowOpenOptions::new("XY pad", Size::new(256.0, 256.0))
+        .scale(WindowScalePolicy::SystemScaleFactor);
+
+    EventContext::open_blocking(window_open_options, |_| XyPadExample {
+        size: Size::new(256.0, 256.0),
+        dragging: false,
+    });
+}
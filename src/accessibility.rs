@@ -0,0 +1,62 @@
+use std::fmt;
+
+use accesskit::{ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, TreeUpdate};
+
+/// Requests from assistive technology like screen readers, sent as [`Event::AccessKit`] to windows
+/// that were opened with [`WindowOpenOptions::accesskit()`].
+///
+/// [`Event::AccessKit`]: crate::Event::AccessKit
+/// [`WindowOpenOptions::accesskit()`]: crate::WindowOpenOptions::accesskit
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessKitEvent {
+    /// Assistive technology started inspecting the window. It's shown an empty placeholder until
+    /// the handler responds with the full tree through
+    /// [`EventContext::update_accesskit_tree()`][crate::EventContext::update_accesskit_tree].
+    InitialTreeRequested,
+    /// Assistive technology asked for an action, like clicking a button or moving the focus to
+    /// another node.
+    ActionRequested(ActionRequest),
+    /// Assistive technology stopped inspecting the window. Tree updates are ignored until the next
+    /// [`AccessKitEvent::InitialTreeRequested`] event. This is only sent on Linux.
+    AccessibilityDeactivated,
+}
+
+impl fmt::Display for AccessKitEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessKitEvent::InitialTreeRequested => write!(f, "InitialTreeRequested"),
+            AccessKitEvent::ActionRequested(request) => {
+                write!(f, "ActionRequested {:?} {:?}", request.action, request.target.0)
+            }
+            AccessKitEvent::AccessibilityDeactivated => write!(f, "AccessibilityDeactivated"),
+        }
+    }
+}
+
+/// Implements all of AccessKit's handler traits by passing the requests on to a function. The
+/// adapters may call the handlers from other threads or while the window's handler is running, so
+/// the backends use this to queue the requests as events for the window's handler.
+///
+/// The tree is never built by the activation handler itself. The adapters show a placeholder
+/// instead, which is replaced by the first tree update the window's handler makes.
+pub(crate) struct AccessKitHandler<F>(pub F);
+
+impl<F: FnMut(AccessKitEvent)> ActivationHandler for AccessKitHandler<F> {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        (self.0)(AccessKitEvent::InitialTreeRequested);
+        None
+    }
+}
+
+impl<F: FnMut(AccessKitEvent)> ActionHandler for AccessKitHandler<F> {
+    fn do_action(&mut self, request: ActionRequest) {
+        (self.0)(AccessKitEvent::ActionRequested(request));
+    }
+}
+
+impl<F: FnMut(AccessKitEvent)> DeactivationHandler for AccessKitHandler<F> {
+    fn deactivate_accessibility(&mut self) {
+        (self.0)(AccessKitEvent::AccessibilityDeactivated);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::{
+    Event, EventContext, EventStatus, MouseCursor, Rect, Size, WindowEvent, WindowHandler,
+};
+
+/// A [`WindowHandler`] that hands the window's events to async code instead of handling them
+/// itself. Events are read from a [`WindowEventStream`] and frame ticks from a [`FrameStream`],
+/// and the window is controlled through a [`WindowCommander`]. The streams and the commander can
+/// be moved to another thread, for instance one that runs an async executor, while the adapter is
+/// passed to [`EventContext::open_blocking()`] or one of the other ways to open a window. See the
+/// `async_counter` example.
+///
+/// The event queue holds at most `capacity` events. The window's thread never waits for the async
+/// side, so once the queue is full the oldest event is dropped to make room for the new one. The
+/// number of dropped events is available through [`WindowEventStream::dropped_events()`].
+///
+/// Both streams end after [`WindowEvent::WillClose`] has been received, or when the adapter is
+/// dropped. Since events are only handled later, every event is reported as
+/// [`EventStatus::Captured`].
+pub struct AsyncWindowAdapter {
+    events: Arc<Mutex<EventQueue>>,
+    frames: Arc<Mutex<FrameTicks>>,
+    commands: Receiver<WindowCommand>,
+}
+
+struct EventQueue {
+    events: VecDeque<Event>,
+    capacity: usize,
+    dropped: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+struct FrameTicks {
+    /// The number of frames drawn so far.
+    frame: u64,
+    /// The last frame the [`FrameStream`] returned.
+    seen: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+enum WindowCommand {
+    Resize(Size),
+    SetMouseCursor(MouseCursor),
+    SetCursorRegions(Vec<(Rect, MouseCursor)>),
+    SetAnimating(bool),
+    Close,
+}
+
+impl AsyncWindowAdapter {
+    /// Create the adapter along with the streams and the commander that belong to it. `capacity`
+    /// is the maximum number of events that are queued before the oldest ones are dropped, and it's
+    /// at least one.
+    pub fn new(capacity: usize) -> (Self, WindowEventStream, FrameStream, WindowCommander) {
+        let events = Arc::new(Mutex::new(EventQueue {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+            closed: false,
+            waker: None,
+        }));
+        let frames =
+            Arc::new(Mutex::new(FrameTicks { frame: 0, seen: 0, closed: false, waker: None }));
+        let (sender, commands) = mpsc::channel();
+
+        let adapter = Self { events: events.clone(), frames: frames.clone(), commands };
+        let commander = WindowCommander { sender, events: events.clone() };
+
+        (adapter, WindowEventStream { events }, FrameStream { frames }, commander)
+    }
+
+    /// End both streams once they've returned everything that's still queued.
+    fn close_streams(&self) {
+        let mut events = self.events.lock().unwrap();
+        events.closed = true;
+        if let Some(waker) = events.waker.take() {
+            waker.wake();
+        }
+
+        let mut frames = self.frames.lock().unwrap();
+        frames.closed = true;
+        if let Some(waker) = frames.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl WindowHandler for AsyncWindowAdapter {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        for command in self.commands.try_iter() {
+            match command {
+                WindowCommand::Resize(size) => window.resize(size),
+                WindowCommand::SetMouseCursor(cursor) => window.set_mouse_cursor(cursor),
+                WindowCommand::SetCursorRegions(regions) => window.set_cursor_regions(&regions),
+                WindowCommand::SetAnimating(animating) => window.set_animating(animating),
+                WindowCommand::Close => window.close(),
+            }
+        }
+
+        let mut frames = self.frames.lock().unwrap();
+        frames.frame += 1;
+        if let Some(waker) = frames.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut EventContext, event: Event) -> EventStatus {
+        let is_will_close = matches!(event, Event::Window(WindowEvent::WillClose));
+
+        {
+            let mut events = self.events.lock().unwrap();
+            if events.events.len() >= events.capacity {
+                events.events.pop_front();
+                events.dropped += 1;
+            }
+            events.events.push_back(event);
+            if let Some(waker) = events.waker.take() {
+                waker.wake();
+            }
+        }
+
+        if is_will_close {
+            self.close_streams();
+        }
+
+        EventStatus::Captured
+    }
+}
+
+impl Drop for AsyncWindowAdapter {
+    fn drop(&mut self) {
+        self.close_streams();
+    }
+}
+
+/// The events received by an [`AsyncWindowAdapter`], in the order they arrived.
+pub struct WindowEventStream {
+    events: Arc<Mutex<EventQueue>>,
+}
+
+impl WindowEventStream {
+    /// The number of events that were dropped so far because the queue was full.
+    pub fn dropped_events(&self) -> u64 {
+        self.events.lock().unwrap().dropped
+    }
+}
+
+impl Stream for WindowEventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let mut events = self.events.lock().unwrap();
+        if let Some(event) = events.events.pop_front() {
+            Poll::Ready(Some(event))
+        } else if events.closed {
+            Poll::Ready(None)
+        } else {
+            events.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// A tick for every frame of the window that belongs to an [`AsyncWindowAdapter`], which is the
+/// point where async code should draw. Each item is the number of frames drawn so far. Ticks that
+/// arrive while the previous one is still being handled are merged, so a slow consumer skips
+/// frames instead of falling behind.
+///
+/// Frames are only drawn while the window is animating or has received events, see
+/// [`WindowCommander::set_animating()`].
+pub struct FrameStream {
+    frames: Arc<Mutex<FrameTicks>>,
+}
+
+impl Stream for FrameStream {
+    type Item = u64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<u64>> {
+        let mut frames = self.frames.lock().unwrap();
+        if frames.frame != frames.seen {
+            frames.seen = frames.frame;
+            Poll::Ready(Some(frames.frame))
+        } else if frames.closed {
+            Poll::Ready(None)
+        } else {
+            frames.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Controls the window that belongs to an [`AsyncWindowAdapter`] from any thread. The commands are
+/// carried out at the start of the window's next frame, in the order they were sent. Sending a
+/// command does not wake up the window, so a window that isn't animating only carries them out
+/// after the next event. Commands sent after the window has closed are ignored.
+#[derive(Clone)]
+pub struct WindowCommander {
+    sender: Sender<WindowCommand>,
+    events: Arc<Mutex<EventQueue>>,
+}
+
+impl WindowCommander {
+    /// See [`EventContext::resize()`].
+    pub fn resize(&self, size: Size) {
+        let _ = self.sender.send(WindowCommand::Resize(size));
+    }
+
+    /// See [`EventContext::set_mouse_cursor()`].
+    pub fn set_mouse_cursor(&self, cursor: MouseCursor) {
+        let _ = self.sender.send(WindowCommand::SetMouseCursor(cursor));
+    }
+
+    /// See [`EventContext::set_cursor_regions()`].
+    pub fn set_cursor_regions(&self, regions: &[(Rect, MouseCursor)]) {
+        let _ = self.sender.send(WindowCommand::SetCursorRegions(regions.to_vec()));
+    }
+
+    /// See [`EventContext::set_animating()`].
+    pub fn set_animating(&self, animating: bool) {
+        let _ = self.sender.send(WindowCommand::SetAnimating(animating));
+    }
+
+    /// See [`EventContext::close()`].
+    pub fn close(&self) {
+        let _ = self.sender.send(WindowCommand::Close);
+    }
+
+    /// Whether the window is still open. This returns `false` once the window has sent
+    /// [`WindowEvent::WillClose`].
+    pub fn is_open(&self) -> bool {
+        !self.events.lock().unwrap().closed
+    }
+}
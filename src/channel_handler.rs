@@ -0,0 +1,73 @@
+use std::collections::vec_deque::{Drain, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{Event, EventContext, EventStatus, WindowEvent, WindowHandler};
+
+/// The part of a [`ChannelHandler`] that contains the application's own logic.
+pub trait MessageHandler<M> {
+    /// Handle every message that was queued since the last frame, and draw the next frame. This is
+    /// called once per [`WindowHandler::on_frame`], even if there are no new messages.
+    fn update(&mut self, window: &mut EventContext, messages: Drain<'_, M>);
+}
+
+/// A [`WindowHandler`] that doesn't act on events right away. Every event is converted into a
+/// message of type `M` and added to a queue, and the wrapped [`MessageHandler`] receives all of the
+/// queued messages at the start of the next frame. That way all of the application's code runs at a
+/// single point in the frame, with full access to the [`EventContext`], and never from within a
+/// callback that's nested inside of another one.
+///
+/// [`WindowEvent::WillClose`] is the only exception. There won't be any frames after that event,
+/// so the queue is handed to the `MessageHandler` right away.
+///
+/// Messages can also be sent from other threads through [`ChannelHandler::sender()`]. These are
+/// added to the queue after the events that arrived during the same frame. Sending a message does
+/// not wake up the window, so a window that isn't animating only receives these messages after the
+/// next event.
+///
+/// Since events are only handled later, every event is reported as [`EventStatus::Captured`].
+pub struct ChannelHandler<M, H> {
+    handler: H,
+    messages: VecDeque<M>,
+    sender: Sender<M>,
+    receiver: Receiver<M>,
+}
+
+impl<M, H: MessageHandler<M>> ChannelHandler<M, H> {
+    pub fn new(handler: H) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self { handler, messages: VecDeque::new(), sender, receiver }
+    }
+
+    /// A handle for adding messages to the queue from any thread.
+    pub fn sender(&self) -> Sender<M> {
+        self.sender.clone()
+    }
+
+    /// The wrapped [`MessageHandler`].
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// The wrapped [`MessageHandler`].
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+}
+
+impl<M: From<Event>, H: MessageHandler<M>> WindowHandler for ChannelHandler<M, H> {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        self.messages.extend(self.receiver.try_iter());
+        self.handler.update(window, self.messages.drain(..));
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let is_will_close = matches!(event, Event::Window(WindowEvent::WillClose));
+        self.messages.push_back(M::from(event));
+        if is_will_close {
+            self.on_frame(window);
+        }
+
+        EventStatus::Captured
+    }
+}
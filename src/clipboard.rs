@@ -1,8 +1,10 @@
-#[cfg(target_os = "macos")]
+#[cfg(not(feature = "windowing"))]
+use crate::headless as platform;
+#[cfg(all(feature = "windowing", target_os = "macos"))]
 use crate::macos as platform;
-#[cfg(target_os = "windows")]
+#[cfg(all(feature = "windowing", target_os = "windows"))]
 use crate::win as platform;
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "windowing", target_os = "linux"))]
 use crate::x11 as platform;
 
 pub fn copy_to_clipboard(data: &str) {
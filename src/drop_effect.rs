@@ -0,0 +1,181 @@
+use crate::event::WindowEvent;
+use crate::Error;
+
+/// What happens to dragged data once it's dropped. Drags started with
+/// [`EventContext::start_drag()`][crate::EventContext::start_drag] request one of these, and the
+/// window the data is dropped on picks the effect that's actually performed. See
+/// [`EventContext::set_drop_effect()`][crate::EventContext::set_drop_effect] for the receiving
+/// side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DropEffect {
+    /// The target copies the data, and the source keeps it.
+    #[default]
+    Copy,
+    /// The target takes over the data. For files this means the source should delete its own copy
+    /// once the drag has ended, unless the target already moved the file itself.
+    Move,
+    /// Let the target decide between copying and moving. On Linux this is `XdndActionAsk`, for
+    /// which file managers show a menu when the data is dropped. Windows and macOS don't have an
+    /// equivalent, so there the source allows both and the target picks one, usually based on the
+    /// modifier keys held during the drop.
+    ///
+    /// This is only meaningful for drags started by the window. When it's passed to
+    /// [`EventContext::set_drop_effect()`][crate::EventContext::set_drop_effect] it's treated as
+    /// [`DropEffect::Copy`].
+    Ask,
+}
+
+impl DropEffect {
+    /// Whether a drag that requested `self` allows the target to perform `effect`.
+    pub(crate) fn allows(self, effect: DropEffect) -> bool {
+        match self {
+            DropEffect::Ask => true,
+            _ => self == effect,
+        }
+    }
+
+    /// The effect that's performed when the target chose this one. `Ask` is a request, not
+    /// something a target can perform.
+    pub(crate) fn resolved(self) -> DropEffect {
+        match self {
+            DropEffect::Ask => DropEffect::Copy,
+            effect => effect,
+        }
+    }
+}
+
+/// The image shown under the cursor while data dragged out of the window with
+/// [`EventContext::start_drag()`][crate::EventContext::start_drag] is moved around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DragImage {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    hotspot_x: u32,
+    hotspot_y: u32,
+}
+
+impl DragImage {
+    /// Create a drag image from non-premultiplied RGBA pixels, stored row by row starting at the
+    /// top left. The image is shown at its actual size in physical pixels. The hotspot is the pixel
+    /// that stays under the cursor, so a hotspot in the middle centers the image on the cursor.
+    ///
+    /// Returns [`Error::InvalidDragImage`] if the image is empty, if the buffer doesn't contain
+    /// exactly `width * height` pixels, or if the hotspot lies outside of the image.
+    pub fn from_rgba(
+        rgba: Vec<u8>, width: u32, height: u32, hotspot_x: u32, hotspot_y: u32,
+    ) -> Result<Self, Error> {
+        let expected_len =
+            (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4));
+        if width == 0
+            || height == 0
+            || expected_len != Some(rgba.len())
+            || hotspot_x >= width
+            || hotspot_y >= height
+        {
+            return Err(Error::InvalidDragImage);
+        }
+
+        Ok(Self { rgba, width, height, hotspot_x, hotspot_y })
+    }
+
+    /// The image's pixels, in the format described in [`Self::from_rgba()`].
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The hotspot's `(x, y)` position within the image.
+    pub fn hotspot(&self) -> (u32, u32) {
+        (self.hotspot_x, self.hotspot_y)
+    }
+}
+
+/// The drop effect negotiation for the drag that's currently over the window, shared by the
+/// backends. The backends update the allowed and proposed effects whenever the platform reports
+/// them, and the handler can override the proposal.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct DropTargetEffect {
+    /// The effects the drag source allows, with `Ask` meaning both copying and moving.
+    pub allowed: Option<DropEffect>,
+    /// The effect the platform suggests based on the source's request and the held modifier keys.
+    pub proposed: Option<DropEffect>,
+    /// The effect set through [`crate::EventContext::set_drop_effect()`] for this drag, if any.
+    pub chosen: Option<Option<DropEffect>>,
+}
+
+impl DropTargetEffect {
+    /// Forget everything about the previous drag.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The effect the drop will have, or `None` if it will be rejected. Effects the source
+    /// doesn't allow are rejected too.
+    pub fn effect(&self) -> Option<DropEffect> {
+        let effect = self.chosen.unwrap_or(self.proposed)?.resolved();
+
+        self.allowed.filter(|allowed| allowed.allows(effect)).map(|_| effect)
+    }
+}
+
+/// Where the drag that's over the window is in its sequence of events. Every incoming drag is sent
+/// to the handler as a `DragEnter` event for each dragged item, any number of `Dragging` events,
+/// and then either a single `Drop` event or a single `DragLeave` event. The backends track
+/// the events they send with this so debug builds catch events that are out of order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DragEventOrder {
+    /// There's no drag over the window.
+    #[default]
+    Idle,
+    /// The handler received the drag's `DragEnter` events.
+    Entered,
+    /// The handler received at least one `Dragging` event.
+    Dragging,
+    /// The handler received the drag's `Drop` event.
+    Dropped,
+}
+
+impl DragEventOrder {
+    /// Whether the handler has seen the drag enter the window, and the drag hasn't ended yet.
+    pub fn in_drag(self) -> bool {
+        matches!(self, DragEventOrder::Entered | DragEventOrder::Dragging)
+    }
+
+    /// Record that `event` is about to be sent to the handler. Events that aren't part of an
+    /// incoming drag are ignored.
+    pub fn track(&mut self, event: &WindowEvent) {
+        use DragEventOrder::*;
+
+        let next = match (*self, event) {
+            (Idle | Entered | Dropped, WindowEvent::DragEnter { .. }) => Entered,
+            (Entered | Dragging, WindowEvent::Dragging) => Dragging,
+            (Entered | Dragging, WindowEvent::Drop(_)) => Dropped,
+            (Entered | Dragging, WindowEvent::DragLeave) => Idle,
+            // A drag whose data couldn't be read never enters the window
+            (Idle | Dropped, WindowEvent::DropFailed(_)) => return,
+            (
+                _,
+                WindowEvent::DragEnter { .. }
+                | WindowEvent::Dragging
+                | WindowEvent::Drop(_)
+                | WindowEvent::DropFailed(_)
+                | WindowEvent::DragLeave,
+            ) => {
+                debug_assert!(false, "{} was sent out of order, the drag was {:?}", event, self);
+                return;
+            }
+            _ => return,
+        };
+
+        *self = next;
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+/// The errors returned by baseview, for instance when a window could not be opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The parent window's handle belongs to a platform or windowing system this build of
+    /// baseview doesn't support. Contains the handle's debug representation.
+    UnsupportedParentHandle(String),
+    /// The parent window is a Wayland surface. Baseview's Linux backend uses X11, so it can only
+    /// be embedded in X11 windows, for instance a host running under XWayland.
+    WaylandParentUnsupported,
+    /// The parent window's handle does not point to a window, for instance a `Win32` handle with a
+    /// null `HWND`.
+    NullParentHandle,
+    /// The parent could not provide a window handle.
+    ParentHandleUnavailable,
+    /// The operation is not supported for this window on the current platform, for instance
+    /// because the window is embedded in a parent window.
+    Unsupported,
+    /// The platform could not create the window, for instance because the parent window handle
+    /// doesn't refer to an existing window. Contains the platform's description of the error.
+    WindowCreationFailed(String),
+    /// The image passed to [`CursorIcon::from_rgba()`](crate::CursorIcon::from_rgba) is empty, its
+    /// size doesn't match the number of pixels, or its hotspot lies outside of the image.
+    InvalidCursorIcon,
+    /// The image passed to [`DragImage::from_rgba()`](crate::DragImage::from_rgba) is empty, its
+    /// size doesn't match the number of pixels, or its hotspot lies outside of the image.
+    InvalidDragImage,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedParentHandle(handle) => {
+                write!(f, "unsupported parent handle type {}", handle)
+            }
+            Error::WaylandParentUnsupported => {
+                write!(f, "Wayland parent windows are not supported, only X11 parent windows are")
+            }
+            Error::NullParentHandle => write!(f, "the parent window handle is null"),
+            Error::ParentHandleUnavailable => write!(f, "parent window handle unavailable"),
+            Error::Unsupported => write!(f, "not supported for this window on this platform"),
+            Error::WindowCreationFailed(reason) => {
+                write!(f, "could not create the window: {}", reason)
+            }
+            Error::InvalidCursorIcon => write!(f, "invalid cursor image"),
+            Error::InvalidDragImage => write!(f, "invalid drag image"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Why the data dragged over the window couldn't be read, see
+/// [`WindowEvent::DropFailed`](crate::WindowEvent::DropFailed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DropError {
+    /// The drag doesn't contain any items.
+    Empty,
+    /// One of the items is a file on another computer. Contains the item's URI. This only happens
+    /// on Linux.
+    RemoteFile(String),
+    /// One of the items isn't a file, for instance a link dragged out of a web browser. On Linux
+    /// this contains the item's URI, if the drag contained a URI list at all. The other platforms
+    /// don't say what the items are.
+    NotAFile(Option<String>),
+    /// The platform couldn't provide the dragged data.
+    Unavailable,
+}
+
+impl fmt::Display for DropError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DropError::Empty => write!(f, "the drag doesn't contain any items"),
+            DropError::RemoteFile(uri) => write!(f, "{} is a file on another computer", uri),
+            DropError::NotAFile(Some(uri)) => write!(f, "{} is not a file", uri),
+            DropError::NotAFile(None) => write!(f, "the dragged items are not files"),
+            DropError::Unavailable => write!(f, "the dragged data is not available"),
+        }
+    }
+}
+
+impl std::error::Error for DropError {}
+
+/// An error the X server reported for one of the requests made for a window. Most requests don't
+/// wait for the server's response, so these errors arrive asynchronously through the event loop.
+/// See [`WindowOpenOptions::x11_error_handler`](crate::WindowOpenOptions::x11_error_handler).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct X11Error {
+    /// The X11 error code, like `3` for `BadWindow`.
+    pub error_code: u8,
+    /// The major opcode of the request that failed.
+    pub major_opcode: u8,
+    /// The minor opcode of the request that failed. This is only used by extensions.
+    pub minor_opcode: u16,
+    /// The resource the failed request referred to, for instance the window's ID for a `BadWindow`
+    /// error.
+    pub resource_id: u32,
+    /// The low 16 bits of the failed request's sequence number.
+    pub sequence: u16,
+}
+
+impl X11Error {
+    /// The name of the core protocol error, like `BadWindow`. Returns `None` for errors defined by
+    /// extensions.
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self.error_code {
+            1 => "BadRequest",
+            2 => "BadValue",
+            3 => "BadWindow",
+            4 => "BadPixmap",
+            5 => "BadAtom",
+            6 => "BadCursor",
+            7 => "BadFont",
+            8 => "BadMatch",
+            9 => "BadDrawable",
+            10 => "BadAccess",
+            11 => "BadAlloc",
+            12 => "BadColor",
+            13 => "BadGC",
+            14 => "BadIDChoice",
+            15 => "BadName",
+            16 => "BadLength",
+            17 => "BadImplementation",
+            _ => return None,
+        };
+
+        Some(name)
+    }
+}
+
+impl fmt::Display for X11Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "error {}", self.error_code)?,
+        }
+
+        write!(
+            f,
+            " for request {}.{} on resource {:#x} (sequence {})",
+            self.major_opcode, self.minor_opcode, self.resource_id, self.sequence
+        )
+    }
+}
+
+impl std::error::Error for X11Error {}
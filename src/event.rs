@@ -1,47 +1,161 @@
+use std::fmt;
+use std::io;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use keyboard_types::{KeyboardEvent, Modifiers};
+use keyboard_types::{KeyboardEvent, Location, Modifiers};
 
-use crate::{Point, WindowInfo};
+use crate::{DropEffect, DropError, PhyPoint, Point, WindowInfo};
 
+/// A mouse button. The buttons are numbered the same way on every platform, so bindings that are
+/// stored on one platform keep working on the others.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
+    /// The first side button, which usually navigates back.
     Back,
+    /// The second side button, which usually navigates forward.
     Forward,
+    /// Any button after the first five. `Other(0)` is the sixth button, `Other(1)` the seventh,
+    /// and so on.
+    ///
+    /// - On Linux these are X11 buttons 10 and up. X11 buttons 4 to 7 belong to the scroll wheel
+    ///   and are sent as [`MouseEvent::WheelScrolled`] events instead.
+    /// - On macOS these are the buttons with a `buttonNumber` of 5 and up.
+    /// - Windows only reports the first five buttons, both through its mouse messages and
+    ///   through Raw Input, so this is never sent there.
     Other(u8),
 }
 
+bitflags::bitflags! {
+    /// A set of mouse buttons, like the buttons that are held down during a [`MouseEvent`]. Handlers
+    /// can use this to tell chords apart, like a middle button drag that pans while the left button
+    /// is also held down.
+    ///
+    /// The bits for the first five buttons are named. The bit for
+    /// [`MouseButton::Other(n)`](MouseButton::Other) is `1 << (5 + n)`, so the buttons after
+    /// `Other(26)` aren't included.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct MouseButtonSet: u32 {
+        const LEFT = 1 << 0;
+        const MIDDLE = 1 << 1;
+        const RIGHT = 1 << 2;
+        const BACK = 1 << 3;
+        const FORWARD = 1 << 4;
+
+        // The remaining bits belong to the `Other` buttons
+        const _ = !0;
+    }
+}
+
+impl MouseButtonSet {
+    /// The set containing only `button`. This is empty for the `Other` buttons that can't be
+    /// included.
+    pub const fn from_button(button: MouseButton) -> Self {
+        match button {
+            MouseButton::Left => Self::LEFT,
+            MouseButton::Middle => Self::MIDDLE,
+            MouseButton::Right => Self::RIGHT,
+            MouseButton::Back => Self::BACK,
+            MouseButton::Forward => Self::FORWARD,
+            MouseButton::Other(n) if n < 27 => Self::from_bits_retain(1 << (5 + n)),
+            MouseButton::Other(_) => Self::empty(),
+        }
+    }
+
+    pub const fn contains_button(&self, button: MouseButton) -> bool {
+        let bits = Self::from_button(button).bits();
+        bits != 0 && self.bits() & bits == bits
+    }
+
+    /// The number of buttons in the set.
+    pub const fn len(&self) -> u32 {
+        self.bits().count_ones()
+    }
+
+    /// The buttons in the set, in the order of their bits.
+    pub fn buttons(self) -> impl Iterator<Item = MouseButton> {
+        (0..32).filter(move |bit| self.bits() & (1 << bit) != 0).map(|bit| match bit {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            3 => MouseButton::Back,
+            4 => MouseButton::Forward,
+            bit => MouseButton::Other(bit - 5),
+        })
+    }
+}
+
+impl From<MouseButton> for MouseButtonSet {
+    fn from(button: MouseButton) -> Self {
+        Self::from_button(button)
+    }
+}
+
 /// A scroll movement.
+///
+/// The signs are the same on every platform. A positive `y` scrolls up, towards the start of the
+/// document, so the content moves down. A positive `x` scrolls right, so the content moves left.
+///
+/// | Platform | Scrolling up | Scrolling right | Source of the deltas |
+/// |----------|--------------|-----------------|----------------------|
+/// | Linux    | `y: 1.0`     | `x: 1.0`        | Scroll wheel buttons 4 (up), 5 (down), 6 (left), and 7 (right) |
+/// | Windows  | `y > 0`      | `x > 0`         | `WM_MOUSEWHEEL` and `WM_MOUSEHWHEEL`, as is |
+/// | macOS    | `y > 0`      | `x > 0`         | `scrollingDeltaY`, and `scrollingDeltaX` negated |
+///
+/// Scrolling up on a touchpad means moving the fingers down, unless the system uses natural
+/// scrolling. None of the backends apply the natural scrolling setting themselves. On macOS the
+/// setting is always included in the deltas. On Windows and Linux that's up to the touchpad's
+/// driver, which usually applies it before the deltas reach the application.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ScrollDelta {
     /// A line-based scroll movement
     Lines {
-        /// The number of horizontal lines scrolled
+        /// The number of horizontal lines scrolled, positive when scrolling right
         x: f32,
 
-        /// The number of vertical lines scrolled
+        /// The number of vertical lines scrolled, positive when scrolling up
         y: f32,
     },
     /// A pixel-based scroll movement
     Pixels {
-        /// The number of horizontal pixels scrolled
+        /// The number of horizontal pixels scrolled, positive when scrolling right
         x: f32,
-        /// The number of vertical pixels scrolled
+        /// The number of vertical pixels scrolled, positive when scrolling up
         y: f32,
     },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseEvent {
     /// The mouse cursor was moved
     CursorMoved {
         /// The logical coordinates of the mouse position
         position: Point,
+        /// How far the cursor moved since the previous `CursorMoved` event, in physical pixels.
+        /// This is taken from the platform's own coordinates before they're converted to logical
+        /// ones, so it can be non-zero even when `position` didn't change. That makes it useful for
+        /// fine adjustments with slow mouse movements.
+        ///
+        /// On Linux and Windows this is the difference between the raw positions of two
+        /// consecutive events, so it also covers movement outside of the window if the cursor left
+        /// and came back in the meantime. On macOS this is the event's `deltaX` and `deltaY`, which
+        /// keep changing when the cursor is pushed against the edge of the screen. The delta is
+        /// zero for the first position the window receives and for positions sent during drags.
+        #[cfg_attr(feature = "serde", serde(default))]
+        delta: PhyPoint,
         /// The modifiers that were held down just before the event.
         modifiers: Modifiers,
+        /// The mouse buttons that are held down. This is empty while something is being dragged
+        /// into the window.
+        buttons: MouseButtonSet,
     },
 
     /// A mouse button was pressed.
@@ -50,6 +164,8 @@ pub enum MouseEvent {
         button: MouseButton,
         /// The modifiers that were held down just before the event.
         modifiers: Modifiers,
+        /// The mouse buttons that are held down now, including `button`.
+        buttons: MouseButtonSet,
     },
 
     /// A mouse button was released.
@@ -58,6 +174,8 @@ pub enum MouseEvent {
         button: MouseButton,
         /// The modifiers that were held down just before the event.
         modifiers: Modifiers,
+        /// The mouse buttons that are still held down, so without `button`.
+        buttons: MouseButtonSet,
     },
 
     /// The mouse wheel was scrolled.
@@ -68,6 +186,31 @@ pub enum MouseEvent {
         modifiers: Modifiers,
     },
 
+    /// A pen on a graphics tablet moved, or its pressure or tilt changed. Pens also move the mouse
+    /// cursor, so this is sent in addition to the regular [`MouseEvent::CursorMoved`] events and
+    /// handlers that don't care about pens can simply ignore it. Mice, touchpads, and pens that
+    /// don't report their pressure only produce `CursorMoved` events.
+    ///
+    /// On Windows this uses the pointer messages Windows 8 and up send for pens. baseview doesn't
+    /// call `EnableMouseInPointer()` since that would change how the host's own windows receive
+    /// mouse input, so mice keep using the classic mouse messages. On macOS this uses the tablet
+    /// data attached to mouse events as well as separate tablet point events. On Linux this uses
+    /// the XInput 2 pressure and tilt axes of the tablet's devices. Tablets that are plugged in
+    /// while a window is open are only picked up by windows that are opened afterwards.
+    PenMoved {
+        /// The logical coordinates of the pen's position
+        position: Point,
+        /// How hard the pen is pressed down, from 0.0 when it's not touching the tablet to 1.0 at
+        /// the tablet's maximum pressure.
+        pressure: f32,
+        /// How far the pen is tilted to the right and towards the user, from -1.0 to 1.0 for the
+        /// tablet's maximum tilt in the opposite and in the same direction. This is `(0.0, 0.0)`
+        /// for pens that don't report their tilt.
+        tilt: (f32, f32),
+        /// The modifiers that were held down just before the event.
+        modifiers: Modifiers,
+    },
+
     /// The mouse cursor entered the window.
     ///
     /// May not be available on all platforms.
@@ -79,29 +222,510 @@ pub enum MouseEvent {
     CursorLeft,
 }
 
-#[derive(Debug, Clone)]
+/// With the `serde` feature, file paths are serialized as strings if they're valid UTF-8. Other
+/// paths use serde's representation for `OsString`, which can only be deserialized on the same
+/// kind of platform. Virtual files are serialized as their name and their contents, so their
+/// contents are generated while serializing them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Data {
     String(String),
-    Filepath(PathBuf),
+    /// A path to a file on disk. Paths don't need to be valid UTF-8 when they're dragged or
+    /// dropped, and they're passed on without any lossy conversions. On Linux the path's bytes are
+    /// percent-encoded in the `file://` URI sent to the drop target, on Windows the path's UTF-16
+    /// is used unchanged, and on macOS AppKit receives the path's bytes. Whether the other
+    /// application can open such a path is up to that application.
+    Filepath(#[cfg_attr(feature = "serde", serde(with = "serialized_path"))] PathBuf),
+    /// A file that doesn't exist on disk yet. This can only be used with
+    /// [`EventContext::start_drag()`][crate::EventContext::start_drag]. See [`VirtualFile`] for how
+    /// each platform handles these.
+    VirtualFile(VirtualFile),
+}
+
+impl Data {
+    /// Replace a [`Data::VirtualFile`] with a [`Data::Filepath`] pointing to a temporary file with
+    /// the same contents, for platforms that don't support virtual files yet.
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    pub(crate) fn with_virtual_file_on_disk(self) -> io::Result<Data> {
+        match self {
+            Data::VirtualFile(file) => file.write_to_temp_file().map(Data::Filepath),
+            data => Ok(data),
+        }
+    }
+}
+
+/// The data that was dropped onto the window, see [`WindowEvent::Drop`]. The payload owns all of
+/// the drop's items, so they can be moved out of it without copying them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DropPayload {
+    /// The dropped items, in the order the platform listed them in.
+    pub items: Vec<Data>,
+    /// Where the data was dropped, in logical pixels relative to the window's top left corner.
+    /// This is always the position reported by the platform for the drop itself, so it's also set
+    /// correctly when the data is dropped without being dragged around first. Just like with
+    /// [`WindowEvent::Dragging`], this can lie outside of the window's content area.
+    pub position: Point,
+    /// Where the drag came from, if the platform could tell. This is the same as the source sent
+    /// with the drag's [`WindowEvent::DragEnter`] events.
+    pub source: Option<DragSourceInfo>,
+}
+
+/// The application a drag came from, see [`WindowEvent::DragEnter`] and [`DropPayload`]. This is
+/// only a best-effort hint for treating drops from some applications differently, like drops from
+/// a plugin's host. None of the platforms guarantee that the source can be identified, so this
+/// should never be relied on for anything security related.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DragSourceInfo {
+    /// A platform-specific identifier for the drag's source:
+    ///
+    /// - On Linux this is the X11 window ID of the source window from the XDND messages.
+    /// - On Windows this is the process ID of the foreground window's process while the drag
+    ///   entered, since OLE drag and drop doesn't tell the drop target where a drag came from.
+    ///   This is usually, but not always, the application that started the drag.
+    /// - On macOS this is the drag's `draggingSequenceNumber`, which is only unique among drags.
+    pub platform_id: u64,
+    /// The name of the source application's executable without its extension, like `explorer` or
+    /// `nautilus`. This is `None` if it couldn't be determined. On Linux it's read from the source
+    /// window's `_NET_WM_PID` or `WM_CLASS` properties, which not every application sets. On macOS
+    /// it's only known for drags that were started by the application that owns the window.
+    pub application: Option<String>,
+}
+
+/// A file that is dragged out of the window without having been written to disk. The contents are
+/// only requested once a drop target asks for them.
+///
+/// On Windows this uses the `CFSTR_FILEDESCRIPTORW` and `CFSTR_FILECONTENTS` clipboard formats,
+/// and the contents are only generated when the drop target reads the file. On macOS and Linux the
+/// contents are written to a temporary file as soon as the drag starts, and that file is dragged
+/// instead. Using `NSFilePromiseProvider` and the XDS protocol for those platforms is still left to
+/// do.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerializedVirtualFile", from = "SerializedVirtualFile")
+)]
+pub struct VirtualFile {
+    name: String,
+    contents: Arc<dyn Fn() -> Vec<u8> + Send + Sync>,
+}
+
+impl VirtualFile {
+    /// Create a virtual file. `name` is the file name the drop target will use, without any
+    /// directories. The `contents` function is called whenever the file's contents are needed,
+    /// which may happen more than once for a single drag.
+    pub fn new(
+        name: impl Into<String>, contents: impl Fn() -> Vec<u8> + Send + Sync + 'static,
+    ) -> Self {
+        Self { name: name.into(), contents: Arc::new(contents) }
+    }
+
+    /// The file's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Generate the file's contents.
+    pub fn contents(&self) -> Vec<u8> {
+        (self.contents)()
+    }
+
+    /// Write the contents to `<temp dir>/baseview-<pid>-<n>/<name>`. Every file gets its own
+    /// directory so the file name can be used as is.
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    pub(crate) fn write_to_temp_file(&self) -> io::Result<PathBuf> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        // Path separators would let the name escape the directory
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| if std::path::is_separator(c) || c == '\0' { '_' } else { c })
+            .collect();
+
+        let dir = std::env::temp_dir().join(format!(
+            "baseview-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join(name);
+        std::fs::write(&path, self.contents())?;
+
+        Ok(path)
+    }
+}
+
+impl fmt::Debug for VirtualFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualFile")
+            .field("name", &self.name)
+            .field("contents", &format_args!("<fn>"))
+            .finish()
+    }
+}
+
+/// Two virtual files are only equal if they share the same contents function, since the contents
+/// themselves are not generated just to compare them.
+impl PartialEq for VirtualFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Arc::ptr_eq(&self.contents, &other.contents)
+    }
+}
+
+impl Eq for VirtualFile {}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedVirtualFile {
+    name: String,
+    contents: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<VirtualFile> for SerializedVirtualFile {
+    fn from(file: VirtualFile) -> Self {
+        Self { contents: file.contents(), name: file.name }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SerializedVirtualFile> for VirtualFile {
+    fn from(file: SerializedVirtualFile) -> Self {
+        let contents = file.contents;
+        VirtualFile::new(file.name, move || contents.clone())
+    }
+}
+
+/// See [`Data`] for how paths are serialized.
+#[cfg(feature = "serde")]
+mod serialized_path {
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SerializedPath {
+        Utf8(String),
+        Native(OsString),
+    }
+
+    pub(super) fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        match path.to_str() {
+            Some(path) => serializer.serialize_str(path),
+            None => path.as_os_str().serialize(serializer),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PathBuf, D::Error> {
+        Ok(match SerializedPath::deserialize(deserializer)? {
+            SerializedPath::Utf8(path) => path.into(),
+            SerializedPath::Native(path) => path.into(),
+        })
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowEvent {
-    Resized(WindowInfo),
+    /// The window's physical size, its scale factor, or both have changed. Compare `new` and `old`
+    /// to find out what has changed. When both change at the same time, for instance when the
+    /// window is moved to a monitor with a different DPI, only a single event is sent.
+    ///
+    /// Every window receives exactly one of these events right after it has been opened, before the
+    /// first call to [`WindowHandler::on_frame()`][crate::WindowHandler::on_frame]. In that event
+    /// `new` and `old` are identical. Any later `Resized` event describes an actual change.
+    Resized {
+        /// The window's current size and scale factor.
+        new: WindowInfo,
+        /// The window's size and scale factor before this event.
+        old: WindowInfo,
+    },
+    /// The user started resizing the window by dragging its frame. Until the matching
+    /// [`WindowEvent::ResizeEnded`] event, [`WindowEvent::Resized`] events are sent at most once
+    /// per frame, right before [`WindowHandler::on_frame()`][crate::WindowHandler::on_frame] is
+    /// called. Handlers can use this to switch to a cheaper way of drawing while the size keeps
+    /// changing.
+    ///
+    /// This is currently only sent for standalone windows on Windows.
+    ResizeStarted,
+    /// The user stopped resizing the window. The window's final size has already been sent in a
+    /// [`WindowEvent::Resized`] event when this event arrives.
+    ResizeEnded,
+    /// The window has gained the keyboard focus.
+    ///
+    /// On Linux, embedded windows take the keyboard focus when they're clicked or when the host
+    /// asks them to through `WM_TAKE_FOCUS`. Keyboard shortcuts handled by the host stop working
+    /// until the host takes the focus back.
     Focused,
+    /// The window has lost the keyboard focus. Mouse buttons and keys that were still held down at
+    /// that point won't be released in this window, so the matching `ButtonReleased` and key up
+    /// events are sent right before this event. These synthesized events don't have any modifiers
+    /// set.
     Unfocused,
+    /// The window has become visible. Windows that are visible when they're opened receive this
+    /// event after the initial [`WindowEvent::Resized`] event. After that it's only sent when the
+    /// window becomes visible again after a [`WindowEvent::Hidden`] event.
+    ///
+    /// What counts as visible depends on the platform. On Windows this follows the window's own
+    /// `WS_VISIBLE` style, as changed by `ShowWindow()`. On Linux the window is visible while it's
+    /// mapped. On macOS the view is visible while it's in a window, it isn't hidden, and the window
+    /// isn't fully covered by other windows or ordered out.
+    Shown,
+    /// The window is no longer visible. See [`WindowEvent::Shown`].
+    Hidden,
+    /// The window is about to close, and this is the last event the handler receives. This is sent
+    /// however the window is closed, including when the window's parent is destroyed.
+    ///
+    /// This is the last opportunity to free GPU resources. If the window has an OpenGL context,
+    /// that context is made current on the thread that delivers this event, and it's only
+    /// destroyed after the handler has returned. The exception is a Linux window whose parent
+    /// already destroyed it, since its context can no longer be made current.
     WillClose,
-    DragEnter(Data),
+    /// Data was dragged into the window. There is one of these events for every dragged item, and
+    /// the drag's position is sent as a [`MouseEvent::CursorMoved`] event right before the first
+    /// one.
+    ///
+    /// Every drag is sent as these events, followed by any number of [`WindowEvent::Dragging`]
+    /// events, and it ends with either a single [`WindowEvent::Drop`] event with all of the items
+    /// or a single [`WindowEvent::DragLeave`] event. This order holds even when the handler was
+    /// busy while the platform reported the drag. Drags whose data can't be read are sent as a
+    /// single [`WindowEvent::DropFailed`] event instead.
+    DragEnter {
+        /// One of the dragged items.
+        data: Data,
+        /// Where the drag came from, if the platform could tell. This is the same for every
+        /// `DragEnter` event of a drag.
+        source: Option<DragSourceInfo>,
+    },
+    /// The drag left the window without its data being dropped.
     DragLeave,
+    /// Data is being dragged over the window. The drag's position is sent as a
+    /// [`MouseEvent::CursorMoved`] event right before this event.
+    ///
+    /// Drag positions are always relative to the top left corner of the window's content area.
+    /// Standalone windows on Windows also accept drags over their title bar and borders, so
+    /// positions outside of the window's size are possible there. A drag over the title bar has a
+    /// negative `y` coordinate.
     Dragging,
-    Drop(Data),
+    /// The data was dropped onto the window. This single event contains every dropped item.
+    Drop(DropPayload),
+    /// Data was dragged over the window, but it can't be dropped because it couldn't be read, for
+    /// instance because it's a link instead of a file. The drag is rejected, so the handler won't
+    /// receive any other events for it. This is sent at most once per drag, at the point where the
+    /// drag would otherwise have entered the window, and is meant for showing the user why the drop
+    /// isn't possible.
+    DropFailed(DropError),
+    /// A drag started with [`EventContext::start_drag()`][crate::EventContext::start_drag] has
+    /// moved. This is sent while the drag is in progress, since the window doesn't receive any
+    /// mouse events until the drag has ended. It's followed by a single
+    /// [`WindowEvent::DragSourceEnded`] event.
+    DragSourceMoved {
+        /// The cursor's position in logical pixels relative to the window's top left corner. The
+        /// cursor is usually outside of the window, so this can lie outside of its content area.
+        position: Point,
+        /// Whether the target under the cursor will accept the data if it's dropped right now. On
+        /// macOS the target's answer is only known when the drag ends, so this is always `true`
+        /// there.
+        will_accept: bool,
+    },
+    /// A drag started with [`EventContext::start_drag()`][crate::EventContext::start_drag] has
+    /// ended. This is sent once for every drag, whether it ended in a drop, was cancelled with the
+    /// Escape key, or was interrupted by the system.
+    DragSourceEnded {
+        /// What the target did with the data, or `None` if the drag was cancelled or the target
+        /// rejected the data. This can differ from the effect passed to `start_drag()`, and
+        /// drags started with [`DropEffect::Ask`] end with the effect the target picked. After a
+        /// [`DropEffect::Move`], the source should remove its own copy of the data.
+        effect: Option<DropEffect>,
+    },
 }
 
-#[derive(Debug, Clone)]
+/// Events implement `Display` as a compact single line like `Mouse(CursorMoved 123.0,45.0 SHIFT)`,
+/// which is useful for logging and for comparing event traces. Modifiers are only printed when any
+/// are held down. Paths are quoted, and bytes that aren't valid UTF-8 are escaped like `\xFF`.
+///
+/// With the `serde` feature, events can be serialized and deserialized using serde's default
+/// representation for enums. Keyboard events and modifiers use the representation from
+/// `keyboard-types`, which stores modifiers as their bit flags, like `{"bits": 1}` for `SHIFT`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
     Mouse(MouseEvent),
     Keyboard(KeyboardEvent),
     Window(WindowEvent),
+    /// A dialog opened with
+    /// [`EventContext::open_file_dialog()`](crate::EventContext::open_file_dialog) was closed.
+    Dialog(crate::DialogResult),
+    /// Only sent to windows opened with
+    /// [`WindowOpenOptions::accesskit()`](crate::WindowOpenOptions::accesskit). This variant only
+    /// exists when the `accesskit` feature is enabled.
+    #[cfg(feature = "accesskit")]
+    AccessKit(crate::AccessKitEvent),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Mouse(event) => write!(f, "Mouse({})", event),
+            Event::Keyboard(event) => {
+                write!(f, "Keyboard({} {} {:?}", event.state, event.code, event.key)?;
+                if event.location != Location::Standard {
+                    write!(f, " {:?}", event.location)?;
+                }
+                write_modifiers(f, event.modifiers)?;
+                if event.repeat {
+                    write!(f, " repeat")?;
+                }
+                if event.is_composing {
+                    write!(f, " composing")?;
+                }
+                write!(f, ")")
+            }
+            Event::Window(event) => write!(f, "Window({})", event),
+            Event::Dialog(result) => write!(f, "Dialog({})", result),
+            #[cfg(feature = "accesskit")]
+            Event::AccessKit(event) => write!(f, "AccessKit({})", event),
+        }
+    }
+}
+
+impl fmt::Display for MouseEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MouseEvent::CursorMoved { position, delta, modifiers, buttons } => {
+                write!(f, "CursorMoved {}", position)?;
+                if *delta != PhyPoint::default() {
+                    write!(f, " delta {},{}", delta.x, delta.y)?;
+                }
+                write_modifiers(f, *modifiers)?;
+                write_buttons(f, *buttons)
+            }
+            MouseEvent::ButtonPressed { button, modifiers, buttons } => {
+                write!(f, "ButtonPressed {:?}", button)?;
+                write_modifiers(f, *modifiers)?;
+                write_buttons(f, *buttons)
+            }
+            MouseEvent::ButtonReleased { button, modifiers, buttons } => {
+                write!(f, "ButtonReleased {:?}", button)?;
+                write_modifiers(f, *modifiers)?;
+                write_buttons(f, *buttons)
+            }
+            MouseEvent::WheelScrolled { delta, modifiers } => {
+                match delta {
+                    ScrollDelta::Lines { x, y } => {
+                        write!(f, "WheelScrolled {:?},{:?} lines", x, y)?
+                    }
+                    ScrollDelta::Pixels { x, y } => {
+                        write!(f, "WheelScrolled {:?},{:?} pixels", x, y)?
+                    }
+                }
+                write_modifiers(f, *modifiers)
+            }
+            MouseEvent::PenMoved { position, pressure, tilt, modifiers } => {
+                write!(
+                    f,
+                    "PenMoved {} pressure {:?} tilt {:?},{:?}",
+                    position, pressure, tilt.0, tilt.1
+                )?;
+                write_modifiers(f, *modifiers)
+            }
+            MouseEvent::CursorEntered => write!(f, "CursorEntered"),
+            MouseEvent::CursorLeft => write!(f, "CursorLeft"),
+        }
+    }
+}
+
+impl fmt::Display for WindowEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowEvent::Resized { new, old } => write!(f, "Resized {} from {}", new, old),
+            WindowEvent::ResizeStarted => write!(f, "ResizeStarted"),
+            WindowEvent::ResizeEnded => write!(f, "ResizeEnded"),
+            WindowEvent::Focused => write!(f, "Focused"),
+            WindowEvent::Unfocused => write!(f, "Unfocused"),
+            WindowEvent::Shown => write!(f, "Shown"),
+            WindowEvent::Hidden => write!(f, "Hidden"),
+            WindowEvent::WillClose => write!(f, "WillClose"),
+            WindowEvent::DragEnter { data, .. } => write!(f, "DragEnter {}", data),
+            WindowEvent::DragLeave => write!(f, "DragLeave"),
+            WindowEvent::Dragging => write!(f, "Dragging"),
+            WindowEvent::Drop(payload) => {
+                write!(f, "Drop {} items at {}", payload.items.len(), payload.position)
+            }
+            WindowEvent::DropFailed(error) => write!(f, "DropFailed: {}", error),
+            WindowEvent::DragSourceMoved { position, will_accept: true } => {
+                write!(f, "DragSourceMoved {}", position)
+            }
+            WindowEvent::DragSourceMoved { position, will_accept: false } => {
+                write!(f, "DragSourceMoved {} rejected", position)
+            }
+            WindowEvent::DragSourceEnded { effect: Some(effect) } => {
+                write!(f, "DragSourceEnded {:?}", effect)
+            }
+            WindowEvent::DragSourceEnded { effect: None } => {
+                write!(f, "DragSourceEnded rejected")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Data::String(string) => write!(f, "{:?}", string),
+            Data::Filepath(path) => write!(f, "{:?}", path),
+            Data::VirtualFile(file) => write!(f, "virtual file {:?}", file.name()),
+        }
+    }
+}
+
+/// Write the modifiers as ` SHIFT+CONTROL`, or nothing at all if no modifiers are held down.
+fn write_modifiers(f: &mut fmt::Formatter<'_>, modifiers: Modifiers) -> fmt::Result {
+    const NAMES: [(Modifiers, &str); 14] = [
+        (Modifiers::SHIFT, "SHIFT"),
+        (Modifiers::CONTROL, "CONTROL"),
+        (Modifiers::ALT, "ALT"),
+        (Modifiers::ALT_GRAPH, "ALT_GRAPH"),
+        (Modifiers::META, "META"),
+        (Modifiers::SUPER, "SUPER"),
+        (Modifiers::HYPER, "HYPER"),
+        (Modifiers::FN, "FN"),
+        (Modifiers::FN_LOCK, "FN_LOCK"),
+        (Modifiers::CAPS_LOCK, "CAPS_LOCK"),
+        (Modifiers::NUM_LOCK, "NUM_LOCK"),
+        (Modifiers::SCROLL_LOCK, "SCROLL_LOCK"),
+        (Modifiers::SYMBOL, "SYMBOL"),
+        (Modifiers::SYMBOL_LOCK, "SYMBOL_LOCK"),
+    ];
+
+    let mut separator = " ";
+    for (modifier, name) in NAMES {
+        if modifiers.contains(modifier) {
+            write!(f, "{}{}", separator, name)?;
+            separator = "+";
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the mouse buttons as ` with Left+Middle`, or nothing at all if no buttons are held down.
+fn write_buttons(f: &mut fmt::Formatter<'_>, buttons: MouseButtonSet) -> fmt::Result {
+    let mut separator = " with ";
+    for button in buttons.buttons() {
+        write!(f, "{}{:?}", separator, button)?;
+        separator = "+";
+    }
+
+    Ok(())
 }
 
 /// Return value for [WindowHandler::on_event](`crate::WindowHandler::on_event()`),
@@ -124,3 +748,219 @@ pub enum EventStatus {
     /// plugin window is in focus.
     Ignored,
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    use keyboard_types::{Code, Key, KeyState};
+
+    use super::*;
+    #[cfg(feature = "serde")]
+    use crate::{DialogId, Size};
+
+    #[cfg(feature = "serde")]
+    fn round_trip(event: Event) {
+        let json = serde_json::to_string(&event).unwrap();
+        let deserialized: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, event, "{}", json);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn mouse_events() {
+        let modifiers = Modifiers::SHIFT | Modifiers::CONTROL;
+        let buttons = MouseButtonSet::LEFT | MouseButtonSet::from_button(MouseButton::Other(3));
+
+        for event in [
+            MouseEvent::CursorMoved {
+                position: Point::new(12.5, -3.0),
+                delta: PhyPoint::new(-2, 1),
+                modifiers,
+                buttons,
+            },
+            MouseEvent::ButtonPressed { button: MouseButton::Other(3), modifiers, buttons },
+            MouseEvent::ButtonReleased {
+                button: MouseButton::Back,
+                modifiers: Modifiers::empty(),
+                buttons: MouseButtonSet::empty(),
+            },
+            MouseEvent::WheelScrolled { delta: ScrollDelta::Lines { x: 0.0, y: -1.5 }, modifiers },
+            MouseEvent::WheelScrolled { delta: ScrollDelta::Pixels { x: 4.0, y: 0.25 }, modifiers },
+            MouseEvent::PenMoved {
+                position: Point::new(1.0, 2.0),
+                pressure: 0.75,
+                tilt: (-0.5, 0.125),
+                modifiers,
+            },
+            MouseEvent::CursorEntered,
+            MouseEvent::CursorLeft,
+        ] {
+            round_trip(Event::Mouse(event));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn cursor_moved_without_delta() {
+        // Recordings made before the delta was added are still readable
+        let json = r#"{"Mouse":{"CursorMoved":{"position":{"x":1.0,"y":2.0},"modifiers":{"bits":0},"buttons":""}}}"#;
+        let event: Event = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            event,
+            Event::Mouse(MouseEvent::CursorMoved {
+                position: Point::new(1.0, 2.0),
+                delta: PhyPoint::default(),
+                modifiers: Modifiers::empty(),
+                buttons: MouseButtonSet::empty(),
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn keyboard_events() {
+        round_trip(Event::Keyboard(KeyboardEvent {
+            state: KeyState::Down,
+            key: Key::Character("Ä".into()),
+            code: Code::Quote,
+            location: Location::Standard,
+            modifiers: Modifiers::SHIFT | Modifiers::ALT_GRAPH,
+            repeat: true,
+            is_composing: false,
+        }));
+        round_trip(Event::Keyboard(KeyboardEvent {
+            state: KeyState::Up,
+            key: Key::Enter,
+            code: Code::NumpadEnter,
+            location: Location::Numpad,
+            modifiers: Modifiers::empty(),
+            repeat: false,
+            is_composing: true,
+        }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn window_events() {
+        let new = WindowInfo::from_logical_size_non_uniform(Size::new(640.0, 480.0), 1.5, 1.25);
+        let old = WindowInfo::from_logical_size(Size::new(320.0, 240.0), 1.0);
+        let source = Some(DragSourceInfo { platform_id: 42, application: Some("nautilus".into()) });
+
+        for event in [
+            WindowEvent::Resized { new, old },
+            WindowEvent::ResizeStarted,
+            WindowEvent::ResizeEnded,
+            WindowEvent::Focused,
+            WindowEvent::Unfocused,
+            WindowEvent::Shown,
+            WindowEvent::Hidden,
+            WindowEvent::WillClose,
+            WindowEvent::DragEnter {
+                data: Data::Filepath("/tmp/some file.wav".into()),
+                source: source.clone(),
+            },
+            WindowEvent::DragEnter { data: Data::String("text".into()), source: None },
+            WindowEvent::DragLeave,
+            WindowEvent::Dragging,
+            WindowEvent::Drop(DropPayload {
+                items: vec![Data::Filepath("a.wav".into()), Data::Filepath("b.wav".into())],
+                position: Point::new(10.0, -4.0),
+                source,
+            }),
+            WindowEvent::DropFailed(DropError::NotAFile(Some("https://example.com".into()))),
+            WindowEvent::DropFailed(DropError::Empty),
+            WindowEvent::DragSourceMoved { position: Point::new(-100.0, 20.0), will_accept: false },
+            WindowEvent::DragSourceEnded { effect: Some(DropEffect::Move) },
+            WindowEvent::DragSourceEnded { effect: None },
+        ] {
+            round_trip(Event::Window(event));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn dialog_events() {
+        round_trip(Event::Dialog(crate::DialogResult {
+            id: DialogId::next(),
+            paths: vec!["/home/user/preset.fxp".into()],
+        }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn utf8_paths_are_strings() {
+        let data = Data::Filepath("/tmp/sample.wav".into());
+        assert_eq!(serde_json::to_string(&data).unwrap(), r#"{"Filepath":"/tmp/sample.wav"}"#);
+    }
+
+    #[cfg(all(unix, feature = "serde"))]
+    #[test]
+    fn non_utf8_paths() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = PathBuf::from(OsString::from_vec(b"/tmp/\xff\xfe.wav".to_vec()));
+        round_trip(Event::Window(WindowEvent::DragEnter {
+            data: Data::Filepath(path),
+            source: None,
+        }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn virtual_files_keep_their_contents() {
+        let data = Data::VirtualFile(VirtualFile::new("notes.txt", || b"contents".to_vec()));
+        let json = serde_json::to_string(&data).unwrap();
+
+        // Deserializing creates a new contents function, so the files aren't equal
+        let Data::VirtualFile(file) = serde_json::from_str(&json).unwrap() else {
+            panic!("{} wasn't deserialized as a virtual file", json);
+        };
+        assert_eq!(file.name(), "notes.txt");
+        assert_eq!(file.contents(), b"contents");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_are_escaped_when_printed() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = PathBuf::from(OsString::from_vec(b"/tmp/\xff.wav".to_vec()));
+        let event =
+            Event::Window(WindowEvent::DragEnter { data: Data::Filepath(path), source: None });
+        assert_eq!(event.to_string(), r#"Window(DragEnter "/tmp/\xFF.wav")"#);
+    }
+
+    #[test]
+    fn mouse_button_set_bits() {
+        assert_eq!(MouseButtonSet::from_button(MouseButton::Left).bits(), 1);
+        assert_eq!(MouseButtonSet::from_button(MouseButton::Forward).bits(), 1 << 4);
+        assert_eq!(MouseButtonSet::from_button(MouseButton::Other(0)).bits(), 1 << 5);
+        assert_eq!(MouseButtonSet::from_button(MouseButton::Other(26)).bits(), 1 << 31);
+        // These don't fit
+        assert_eq!(MouseButtonSet::from_button(MouseButton::Other(27)), MouseButtonSet::empty());
+        assert!(!MouseButtonSet::all().contains_button(MouseButton::Other(27)));
+    }
+
+    #[test]
+    fn mouse_button_set_contents() {
+        let buttons = MouseButtonSet::RIGHT
+            | MouseButtonSet::LEFT
+            | MouseButtonSet::from_button(MouseButton::Other(2));
+
+        assert_eq!(buttons.len(), 3);
+        assert!(buttons.contains_button(MouseButton::Left));
+        assert!(buttons.contains_button(MouseButton::Other(2)));
+        assert!(!buttons.contains_button(MouseButton::Middle));
+        assert_eq!(
+            buttons.buttons().collect::<Vec<_>>(),
+            [MouseButton::Left, MouseButton::Right, MouseButton::Other(2)]
+        );
+
+        let all: Vec<_> = MouseButtonSet::all().buttons().collect();
+        assert_eq!(all.len(), 32);
+        assert_eq!(all[31], MouseButton::Other(26));
+    }
+}
@@ -0,0 +1,243 @@
+//! Timestamps for the events sent to the handler. Every platform timestamps its native events with
+//! its own clock, and some events don't have a native timestamp at all. This maps all of them onto
+//! a single clock that starts when the window is opened, and that never goes backwards.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use crate::{Event, MouseEvent};
+
+#[derive(Debug)]
+pub(crate) struct EventClock {
+    /// Every timestamp is measured from here.
+    epoch: Instant,
+    /// The smallest difference seen so far between when a native event was handled and its native
+    /// timestamp, in nanoseconds. Events are always handled some time after they happened, so
+    /// this is the best estimate of the offset between the native clock and this one.
+    native_offset: Cell<Option<i128>>,
+    /// The last native millisecond timestamp along with its value extended to 64 bits, used to
+    /// undo the wraparound of 32-bit millisecond timestamps. See [`Self::extend_millis()`].
+    last_native_millis: Cell<Option<(u32, i64)>>,
+    /// The timestamp of the event that's currently being handled, if it has one.
+    current: Cell<Option<Duration>>,
+    /// The last timestamp that was handed out. Timestamps are never earlier than this.
+    last: Cell<Duration>,
+    /// The timestamp of the last `ButtonPressed` event.
+    last_mouse_down: Cell<Option<Duration>>,
+}
+
+impl EventClock {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            native_offset: Cell::new(None),
+            last_native_millis: Cell::new(None),
+            current: Cell::new(None),
+            last: Cell::new(Duration::ZERO),
+            last_mouse_down: Cell::new(None),
+        }
+    }
+
+    /// The current time on this clock. This may be earlier than the last timestamp, see
+    /// [`Self::event_time()`].
+    pub fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    /// Convert a native 32-bit millisecond timestamp, like X11's server time or Windows'
+    /// `GetMessageTime()`, to a duration. These wrap around after about 49.7 days, which is undone
+    /// by comparing the timestamp to the previous one.
+    pub fn extend_millis(&self, millis: u32) -> Duration {
+        let extended = match self.last_native_millis.get() {
+            // Events don't always arrive in order, so this also allows small steps backwards
+            Some((last_millis, last_extended)) => {
+                last_extended + millis.wrapping_sub(last_millis) as i32 as i64
+            }
+            None => millis as i64,
+        };
+        self.last_native_millis.set(Some((millis, extended)));
+
+        Duration::from_millis(extended.max(0) as u64)
+    }
+
+    /// Start handling a native event. Events sent to the handler until [`Self::end_event()`] is
+    /// called get the native event's timestamp, or the current time if it doesn't have one.
+    /// Returns the timestamp of the outer event when native events are handled recursively, which
+    /// should be passed to `end_event()`.
+    pub fn begin_event(&self, native_time: Option<Duration>) -> Option<Duration> {
+        let now = self.now();
+        let timestamp = match native_time {
+            Some(native_time) => {
+                let offset = now.as_nanos() as i128 - native_time.as_nanos() as i128;
+                let offset = self.native_offset.get().map_or(offset, |min| min.min(offset));
+                self.native_offset.set(Some(offset));
+
+                // With the smallest offset, native events can't end up later than the current time
+                let nanos = (native_time.as_nanos() as i128 + offset).max(0);
+                Duration::from_nanos(nanos as u64)
+            }
+            None => now,
+        };
+
+        self.current.replace(Some(self.monotonic(timestamp)))
+    }
+
+    /// Stop handling the native event started with [`Self::begin_event()`].
+    pub fn end_event(&self, outer_event_time: Option<Duration>) {
+        self.current.set(outer_event_time);
+    }
+
+    /// The timestamp of the native event that's being handled, or the current time if there is
+    /// none.
+    pub fn event_time(&self) -> Duration {
+        match self.current.get() {
+            Some(timestamp) => timestamp,
+            None => self.monotonic(self.now()),
+        }
+    }
+
+    /// Remember when mouse buttons are pressed. Called with every event that's about to be sent to
+    /// the handler.
+    pub fn track(&self, event: &Event) {
+        if let Event::Mouse(MouseEvent::ButtonPressed { .. }) = event {
+            self.last_mouse_down.set(Some(self.event_time()));
+        }
+    }
+
+    /// The timestamp of the last `ButtonPressed` event.
+    pub fn last_mouse_down(&self) -> Option<Duration> {
+        self.last_mouse_down.get()
+    }
+
+    fn monotonic(&self, timestamp: Duration) -> Duration {
+        let timestamp = timestamp.max(self.last.get());
+        self.last.set(timestamp);
+
+        timestamp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use keyboard_types::Modifiers;
+
+    use super::*;
+    use crate::{MouseButton, MouseButtonSet};
+
+    fn button_pressed() -> Event {
+        Event::Mouse(MouseEvent::ButtonPressed {
+            button: MouseButton::Left,
+            modifiers: Modifiers::empty(),
+            buttons: MouseButtonSet::LEFT,
+        })
+    }
+
+    /// Handle a native event with the given timestamp, and return the timestamp the handler sees.
+    fn handle(clock: &EventClock, native_time: Option<Duration>) -> Duration {
+        let outer = clock.begin_event(native_time);
+        let timestamp = clock.event_time();
+        clock.end_event(outer);
+
+        timestamp
+    }
+
+    #[test]
+    fn monotonic_burst() {
+        let clock = EventClock::new();
+        let base = 5_000_000u32;
+
+        // Out of order native events, events without a native timestamp, and synthesized events in
+        // between. The native clock is far ahead of this one.
+        let burst = [
+            Some(base),
+            Some(base + 16),
+            None,
+            Some(base + 8),
+            Some(base + 40),
+            Some(base + 39),
+            None,
+            Some(base + 200),
+            Some(base + 100),
+        ];
+
+        let mut last = Duration::ZERO;
+        for native_millis in burst {
+            let native_time = native_millis.map(|millis| clock.extend_millis(millis));
+            let timestamp = handle(&clock, native_time);
+            assert!(timestamp >= last, "{:?} came before {:?}", timestamp, last);
+            last = timestamp;
+
+            let synthesized = clock.event_time();
+            assert!(synthesized >= last);
+            last = synthesized;
+        }
+    }
+
+    #[test]
+    fn native_events_are_never_in_the_future() {
+        let clock = EventClock::new();
+
+        // The first event sets the offset, so it's at the current time
+        let first = handle(&clock, Some(Duration::from_secs(1000)));
+        assert!(first <= clock.now());
+
+        // A native timestamp that's further ahead than the one before can't be later than now
+        let second = handle(&clock, Some(Duration::from_secs(2000)));
+        assert!(second <= clock.now());
+        assert!(second >= first);
+    }
+
+    #[test]
+    fn millisecond_wraparound() {
+        let clock = EventClock::new();
+
+        assert_eq!(clock.extend_millis(u32::MAX - 10), Duration::from_millis(u32::MAX as u64 - 10));
+        assert_eq!(clock.extend_millis(5), Duration::from_millis(u32::MAX as u64 + 6));
+        // Small steps backwards across the wraparound
+        assert_eq!(clock.extend_millis(u32::MAX), Duration::from_millis(u32::MAX as u64));
+        assert_eq!(clock.extend_millis(20), Duration::from_millis(u32::MAX as u64 + 21));
+    }
+
+    #[test]
+    fn millisecond_steps_backwards_at_the_start() {
+        let clock = EventClock::new();
+
+        assert_eq!(clock.extend_millis(10), Duration::from_millis(10));
+        // This can't go below zero
+        assert_eq!(clock.extend_millis(u32::MAX - 100), Duration::ZERO);
+    }
+
+    #[test]
+    fn nested_events() {
+        let clock = EventClock::new();
+        clock.extend_millis(0);
+
+        let outer = clock.begin_event(Some(clock.extend_millis(1000)));
+        let outer_time = clock.event_time();
+
+        // A native event handled from within the handler, like a modal loop
+        let inner_time = handle(&clock, Some(clock.extend_millis(1500)));
+        assert!(inner_time >= outer_time);
+        assert_eq!(clock.event_time(), outer_time);
+
+        clock.end_event(outer);
+        assert!(clock.event_time() >= inner_time);
+    }
+
+    #[test]
+    fn last_mouse_down() {
+        let clock = EventClock::new();
+        assert_eq!(clock.last_mouse_down(), None);
+
+        let outer = clock.begin_event(None);
+        let pressed_at = clock.event_time();
+        clock.track(&button_pressed());
+        clock.track(&Event::Window(crate::WindowEvent::Focused));
+        clock.end_event(outer);
+        assert_eq!(clock.last_mouse_down(), Some(pressed_at));
+
+        let later = handle(&clock, None);
+        assert_eq!(clock.last_mouse_down(), Some(pressed_at));
+        assert!(later >= pressed_at);
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// What a file dialog lets the user pick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileDialogKind {
+    /// One or more existing files.
+    #[default]
+    Open,
+    /// One or more existing directories.
+    OpenDirectory,
+    /// The location of a new or existing file to save to. The platform asks for confirmation
+    /// before an existing file is picked.
+    Save,
+}
+
+/// A named group of file extensions, like `Audio files` for `wav` and `flac`. Extensions don't
+/// include the leading dot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFilter {
+    pub name: String,
+    pub extensions: Vec<String>,
+}
+
+/// Options for [`EventContext::open_file_dialog()`](crate::EventContext::open_file_dialog).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileDialogOptions {
+    pub kind: FileDialogKind,
+    /// Shown in the dialog's title bar. On macOS the dialog is usually shown as a sheet without a
+    /// title bar, so it's shown as a message in the dialog instead.
+    pub title: Option<String>,
+    /// Only files matching one of these filters can be picked. On Windows the user chooses which
+    /// filter is active, and the first filter is active initially. On macOS the extensions of all
+    /// filters are allowed at the same time. Directory dialogs ignore this.
+    pub filters: Vec<FileFilter>,
+    /// Whether more than one file or directory can be picked. Save dialogs ignore this.
+    pub multiple: bool,
+    /// The directory the dialog starts in. The platform picks one if this is not set or if the
+    /// directory doesn't exist, usually the one that was used last.
+    pub directory: Option<PathBuf>,
+    /// The file name that's filled in initially. Only used for save dialogs.
+    pub file_name: Option<String>,
+}
+
+impl FileDialogOptions {
+    /// Options for a dialog of the given kind. All other options are empty or disabled.
+    pub fn new(kind: FileDialogKind) -> Self {
+        Self { kind, ..Default::default() }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Add a filter. See [`Self::filters`].
+    pub fn filter(mut self, name: impl Into<String>, extensions: &[&str]) -> Self {
+        self.filters.push(FileFilter {
+            name: name.into(),
+            extensions: extensions.iter().map(|extension| extension.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn multiple(mut self, multiple: bool) -> Self {
+        self.multiple = multiple;
+        self
+    }
+
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+}
+
+/// Refers to a dialog opened with
+/// [`EventContext::open_file_dialog()`](crate::EventContext::open_file_dialog). IDs are unique for
+/// the entire process and they're never reused.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DialogId(u64);
+
+impl DialogId {
+    // The X11 backend has no file dialogs
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub(crate) fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        DialogId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Sent as [`Event::Dialog`](crate::Event::Dialog) once a dialog opened with
+/// [`EventContext::open_file_dialog()`](crate::EventContext::open_file_dialog) has been closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DialogResult {
+    /// The ID `open_file_dialog()` returned for the dialog.
+    pub id: DialogId,
+    /// The paths the user picked. This is empty if the dialog was cancelled, or if it couldn't be
+    /// shown at all.
+    pub paths: Vec<PathBuf>,
+}
+
+impl fmt::Display for DialogResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id.0)?;
+        if self.paths.is_empty() {
+            return write!(f, " cancelled");
+        }
+        for path in &self.paths {
+            write!(f, " {}", path.display())?;
+        }
+
+        Ok(())
+    }
+}
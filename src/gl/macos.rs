@@ -1,7 +1,7 @@
 use std::ffi::c_void;
 use std::str::FromStr;
 
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use crate::platform_handle::PlatformWindowHandle;
 
 use cocoa::appkit::{
     NSOpenGLContext, NSOpenGLContextParameter, NSOpenGLPFAAccelerated, NSOpenGLPFAAlphaSize,
@@ -19,76 +19,93 @@ use core_foundation::string::CFString;
 
 use objc::{msg_send, sel, sel_impl};
 
-use super::{GlConfig, GlError, Profile};
+use super::{GlConfig, GlError, GlPixelFormat, Profile};
+
+#[derive(Debug)]
+pub enum CreationFailedError {
+    /// The `NSOpenGLView` couldn't be created.
+    ViewCreationFailed,
+    /// The context for [`GlContext::create_shared()`] couldn't be created.
+    SharedContextFailed,
+}
 
-pub type CreationFailedError = ();
 pub struct GlContext {
     view: id,
     context: id,
+    config: GlConfig,
+    pixel_format: GlPixelFormat,
+}
+
+/// A context that was current on a thread, see [`GlContext::current_context()`].
+pub struct CurrentContext {
+    context: id,
+}
+
+impl CurrentContext {
+    /// Make the context current again, or release the current context if there was none.
+    pub unsafe fn restore(&self) {
+        if self.context == nil {
+            NSOpenGLContext::clearCurrentContext(nil);
+        } else {
+            self.context.makeCurrentContext();
+        }
+    }
 }
 
 impl GlContext {
     pub unsafe fn create(
-        parent: &impl HasRawWindowHandle, config: GlConfig,
+        parent: PlatformWindowHandle, config: GlConfig,
     ) -> Result<GlContext, GlError> {
-        let handle = if let RawWindowHandle::AppKit(handle) = parent.raw_window_handle() {
-            handle
-        } else {
-            return Err(GlError::InvalidWindowHandle);
-        };
+        let PlatformWindowHandle::AppKit { ns_view, .. } = parent;
 
-        if handle.ns_view.is_null() {
+        if ns_view.is_null() {
             return Err(GlError::InvalidWindowHandle);
         }
 
-        let parent_view = handle.ns_view as id;
-
-        let version = if config.version < (3, 2) && config.profile == Profile::Compatibility {
-            NSOpenGLProfileVersionLegacy
-        } else if config.version == (3, 2) && config.profile == Profile::Core {
-            NSOpenGLProfileVersion3_2Core
-        } else if config.version > (3, 2) && config.profile == Profile::Core {
-            NSOpenGLProfileVersion4_1Core
-        } else {
-            return Err(GlError::VersionNotSupported);
-        };
+        let parent_view = ns_view as id;
 
-        #[rustfmt::skip]
-        let mut attrs = vec![
-            NSOpenGLPFAOpenGLProfile as u32, version as u32,
-            NSOpenGLPFAColorSize as u32, (config.red_bits + config.blue_bits + config.green_bits) as u32,
-            NSOpenGLPFAAlphaSize as u32, config.alpha_bits as u32,
-            NSOpenGLPFADepthSize as u32, config.depth_bits as u32,
-            NSOpenGLPFAStencilSize as u32, config.stencil_bits as u32,
-            NSOpenGLPFAAccelerated as u32,
-        ];
-
-        if config.samples.is_some() {
-            #[rustfmt::skip]
-            attrs.extend_from_slice(&[
-                NSOpenGLPFAMultisample as u32,
-                NSOpenGLPFASampleBuffers as u32, 1,
-                NSOpenGLPFASamples as u32, config.samples.unwrap() as u32,
-            ]);
-        }
+        // AppKit picks the context's version through the pixel format's profile, so every
+        // combination of version, profile, and number of samples is a pixel format of its own
+        let mut any_version_supported = false;
+        let mut chosen = None;
+        'versions: for (version, profile) in config.version_candidates(false) {
+            let Some(ns_profile) = ns_profile(version, profile) else { continue };
+            any_version_supported = true;
 
-        if config.double_buffer {
-            attrs.push(NSOpenGLPFADoubleBuffer as u32);
+            for samples in config.sample_candidates() {
+                let pixel_format = choose_pixel_format(&config, ns_profile, samples);
+                if pixel_format != nil {
+                    chosen = Some((pixel_format, version, profile, samples));
+                    break 'versions;
+                }
+            }
         }
 
-        attrs.push(0);
+        let Some((pixel_format, version, profile, samples)) = chosen else {
+            if !any_version_supported {
+                return Err(GlError::VersionNotSupported {
+                    version: config.version,
+                    profile: config.profile,
+                });
+            }
 
-        let pixel_format = NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attrs);
+            // Without a profile AppKit picks a legacy pixel format, which every Mac supports
+            let attrs = [NSOpenGLPFAAccelerated as u32, 0];
+            let closest = NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attrs);
+            let closest_available = (closest != nil).then(|| describe_pixel_format(closest));
+            if closest != nil {
+                let () = msg_send![closest, release];
+            }
 
-        if pixel_format == nil {
-            return Err(GlError::CreationFailed(()));
-        }
+            return Err(GlError::NoMatchingConfig { requested: config, closest_available });
+        };
 
         let view =
             NSOpenGLView::alloc(nil).initWithFrame_pixelFormat_(parent_view.frame(), pixel_format);
 
         if view == nil {
-            return Err(GlError::CreationFailed(()));
+            let () = msg_send![pixel_format, release];
+            return Err(GlError::CreationFailed(CreationFailedError::ViewCreationFailed));
         }
 
         view.setWantsBestResolutionOpenGLSurface_(YES);
@@ -105,9 +122,16 @@ impl GlContext {
             NSOpenGLContextParameter::NSOpenGLCPSwapInterval,
         );
 
+        let pixel_format_info = describe_pixel_format(pixel_format);
+
         let () = msg_send![pixel_format, release];
 
-        Ok(GlContext { view, context })
+        Ok(GlContext {
+            view,
+            context,
+            config: GlConfig { samples, version, profile, ..config },
+            pixel_format: pixel_format_info,
+        })
     }
 
     pub unsafe fn make_current(&self) {
@@ -119,14 +143,7 @@ impl GlContext {
     }
 
     pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
-        let symbol_name = CFString::from_str(symbol).unwrap();
-        let framework_name = CFString::from_str("com.apple.opengl").unwrap();
-        let framework =
-            unsafe { CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef()) };
-        let addr = unsafe {
-            CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef())
-        };
-        addr as *const c_void
+        get_proc_address(symbol)
     }
 
     pub fn swap_buffers(&self) {
@@ -136,6 +153,55 @@ impl GlContext {
         }
     }
 
+    pub fn pixel_format(&self) -> GlPixelFormat {
+        self.pixel_format
+    }
+
+    pub fn config(&self) -> &GlConfig {
+        &self.config
+    }
+
+    pub fn is_current(&self) -> bool {
+        unsafe { NSOpenGLContext::currentContext(nil) == self.context }
+    }
+
+    /// The context that's current on the calling thread, so it can be restored afterwards.
+    pub fn current_context(&self) -> CurrentContext {
+        unsafe { CurrentContext { context: NSOpenGLContext::currentContext(nil) } }
+    }
+
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), GlError> {
+        // AppKit has no adaptive vsync
+        if interval < 0 {
+            return Err(GlError::SwapIntervalNotSupported);
+        }
+
+        unsafe {
+            self.context.setValues_forParameter_(
+                &interval,
+                NSOpenGLContextParameter::NSOpenGLCPSwapInterval,
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn swap_interval(&self) -> i32 {
+        let mut interval = 0;
+        unsafe {
+            self.context.getValues_forParameter_(
+                &mut interval,
+                NSOpenGLContextParameter::NSOpenGLCPSwapInterval,
+            );
+        }
+
+        interval
+    }
+
+    pub fn supports_adaptive_swap_interval(&self) -> bool {
+        false
+    }
+
     /// On macOS the `NSOpenGLView` needs to be resized separtely from our main view.
     pub(crate) fn resize(&self, size: NSSize) {
         unsafe { NSView::setFrameSize(self.view, size) };
@@ -143,6 +209,123 @@ impl GlContext {
             let _: () = msg_send![self.view, setNeedsDisplay: YES];
         }
     }
+
+    /// Create a context with the same pixel format that shares its objects with this one. It's
+    /// never attached to a view, so it has no drawable.
+    pub fn create_shared(&self) -> Result<OffscreenGlContext, GlError> {
+        unsafe {
+            let pixel_format: id = msg_send![self.context, pixelFormat];
+            let context = NSOpenGLContext::alloc(nil)
+                .initWithFormat_shareContext_(pixel_format, self.context);
+            if context == nil {
+                return Err(GlError::CreationFailed(CreationFailedError::SharedContextFailed));
+            }
+
+            Ok(OffscreenGlContext { context })
+        }
+    }
+}
+
+/// See [`crate::gl::OffscreenGlContext`].
+pub struct OffscreenGlContext {
+    context: id,
+}
+
+impl OffscreenGlContext {
+    pub unsafe fn make_current(&self) {
+        self.context.makeCurrentContext();
+    }
+
+    pub unsafe fn make_not_current(&self) {
+        NSOpenGLContext::clearCurrentContext(self.context);
+    }
+
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        get_proc_address(symbol)
+    }
+}
+
+impl Drop for OffscreenGlContext {
+    fn drop(&mut self) {
+        unsafe {
+            if NSOpenGLContext::currentContext(nil) == self.context {
+                NSOpenGLContext::clearCurrentContext(nil);
+            }
+            let () = msg_send![self.context, release];
+        }
+    }
+}
+
+/// Look up an OpenGL function in the OpenGL framework.
+fn get_proc_address(symbol: &str) -> *const c_void {
+    let symbol_name = CFString::from_str(symbol).unwrap();
+    let framework_name = CFString::from_str("com.apple.opengl").unwrap();
+    let framework =
+        unsafe { CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef()) };
+    let addr =
+        unsafe { CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef()) };
+    addr as *const c_void
+}
+
+/// The `NSOpenGLPFAOpenGLProfile` that provides a version and profile, if AppKit has one.
+fn ns_profile(version: (u8, u8), profile: Profile) -> Option<u32> {
+    let ns_profile = match profile {
+        Profile::Compatibility if version < (3, 2) => NSOpenGLProfileVersionLegacy,
+        Profile::Core if version == (3, 2) => NSOpenGLProfileVersion3_2Core,
+        Profile::Core if version > (3, 2) => NSOpenGLProfileVersion4_1Core,
+        _ => return None,
+    };
+
+    Some(ns_profile as u32)
+}
+
+/// Create a pixel format for the configuration, with the given profile and number of samples
+/// instead of the configured ones. Returns `nil` if the configuration isn't supported.
+unsafe fn choose_pixel_format(config: &GlConfig, ns_profile: u32, samples: Option<u8>) -> id {
+    #[rustfmt::skip]
+    let mut attrs = vec![
+        NSOpenGLPFAOpenGLProfile as u32, ns_profile,
+        NSOpenGLPFAColorSize as u32, (config.red_bits + config.blue_bits + config.green_bits) as u32,
+        NSOpenGLPFAAlphaSize as u32, config.alpha_bits as u32,
+        NSOpenGLPFADepthSize as u32, config.depth_bits as u32,
+        NSOpenGLPFAStencilSize as u32, config.stencil_bits as u32,
+        NSOpenGLPFAAccelerated as u32,
+    ];
+
+    if let Some(samples) = samples {
+        #[rustfmt::skip]
+        attrs.extend_from_slice(&[
+            NSOpenGLPFAMultisample as u32,
+            NSOpenGLPFASampleBuffers as u32, 1,
+            NSOpenGLPFASamples as u32, samples as u32,
+        ]);
+    }
+
+    if config.double_buffer {
+        attrs.push(NSOpenGLPFADoubleBuffer as u32);
+    }
+
+    attrs.push(0);
+
+    NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attrs)
+}
+
+/// The pixel format object describes the closest match to the attributes it was created with.
+unsafe fn describe_pixel_format(pixel_format: id) -> GlPixelFormat {
+    let attrib = |attribute| {
+        let mut value = 0;
+        pixel_format.getValues_forAttribute_forVirtualScreen_(&mut value, attribute, 0);
+        value.clamp(0, u8::MAX as i32) as u8
+    };
+
+    GlPixelFormat {
+        color_bits: attrib(NSOpenGLPFAColorSize),
+        alpha_bits: attrib(NSOpenGLPFAAlphaSize),
+        depth_bits: attrib(NSOpenGLPFADepthSize),
+        stencil_bits: attrib(NSOpenGLPFAStencilSize),
+        samples: (attrib(NSOpenGLPFASampleBuffers) > 0).then(|| attrib(NSOpenGLPFASamples)),
+        double_buffer: attrib(NSOpenGLPFADoubleBuffer) != 0,
+    }
 }
 
 impl Drop for GlContext {
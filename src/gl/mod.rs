@@ -1,9 +1,11 @@
 use std::ffi::c_void;
+use std::fmt;
 use std::marker::PhantomData;
 
 // On X11 creating the context is a two step process
 #[cfg(not(target_os = "linux"))]
-use raw_window_handle::HasRawWindowHandle;
+use crate::platform_handle::PlatformWindowHandle;
+use crate::PhySize;
 
 #[cfg(target_os = "windows")]
 mod win;
@@ -35,6 +37,16 @@ pub struct GlConfig {
     pub srgb: bool,
     pub double_buffer: bool,
     pub vsync: bool,
+    /// Whether to settle for less than the requested configuration when the platform can't
+    /// provide it. The number of samples is halved until a matching pixel format is found, ending
+    /// with no multisampling at all. Lower versions of the requested profile are tried next, down
+    /// to 3.2 for desktop OpenGL, and core profile requests finally try OpenGL ES 3.0 where the
+    /// platform supports it. [`GlContext::config()`] returns the configuration that was used.
+    pub fallback: bool,
+    /// Whether the window should be opened without an OpenGL context if no context can be created
+    /// for this configuration, instead of panicking. The reason is then available through
+    /// [`EventContext::gl_context_error()`](crate::EventContext::gl_context_error).
+    pub fallback_to_no_gl: bool,
 }
 
 impl Default for GlConfig {
@@ -52,23 +64,144 @@ impl Default for GlConfig {
             srgb: true,
             double_buffer: true,
             vsync: false,
+            fallback: false,
+            fallback_to_no_gl: false,
         }
     }
 }
 
+/// The desktop OpenGL versions [`GlConfig::fallback`] steps down through.
+const DESKTOP_VERSIONS: [(u8, u8); 9] =
+    [(4, 6), (4, 5), (4, 4), (4, 3), (4, 2), (4, 1), (4, 0), (3, 3), (3, 2)];
+
+/// The OpenGL ES versions [`GlConfig::fallback`] steps down through.
+const ES_VERSIONS: [(u8, u8); 4] = [(3, 2), (3, 1), (3, 0), (2, 0)];
+
+impl GlConfig {
+    /// The numbers of samples to choose a pixel format with, in the order they should be tried.
+    pub(crate) fn sample_candidates(&self) -> Vec<Option<u8>> {
+        let mut candidates = vec![self.samples];
+        if self.fallback {
+            let mut samples = self.samples;
+            while let Some(count) = samples {
+                samples = Some(count / 2).filter(|&count| count >= 2);
+                candidates.push(samples);
+            }
+        }
+
+        candidates
+    }
+
+    /// The versions and profiles to create the context with, in the order they should be tried.
+    /// `es_available` tells whether the platform can create OpenGL ES contexts at all.
+    pub(crate) fn version_candidates(&self, es_available: bool) -> Vec<((u8, u8), Profile)> {
+        let mut candidates = vec![(self.version, self.profile)];
+        if !self.fallback {
+            return candidates;
+        }
+
+        let lower_versions = |versions: &'static [(u8, u8)], profile| {
+            let requested = self.version;
+            versions
+                .iter()
+                .filter(move |&&version| version < requested)
+                .map(move |&version| (version, profile))
+        };
+        match self.profile {
+            Profile::Core => {
+                candidates.extend(lower_versions(&DESKTOP_VERSIONS, Profile::Core));
+                if es_available {
+                    candidates.push(((3, 0), Profile::Es));
+                }
+            }
+            Profile::Compatibility => {
+                candidates.extend(lower_versions(&DESKTOP_VERSIONS, Profile::Compatibility))
+            }
+            Profile::Es if es_available => {
+                candidates.extend(lower_versions(&ES_VERSIONS, Profile::Es))
+            }
+            Profile::Es => (),
+        }
+
+        candidates
+    }
+}
+
+/// The pixel format the platform actually picked for an OpenGL context. This can differ from what was
+/// asked for in the [`GlConfig`], since the platform picks the closest match it supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GlPixelFormat {
+    /// The combined size of the red, green, and blue channels.
+    pub color_bits: u8,
+    pub alpha_bits: u8,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    /// The number of samples per pixel, or `None` if the framebuffer isn't multisampled.
+    pub samples: Option<u8>,
+    pub double_buffer: bool,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Profile {
     Compatibility,
     Core,
+    /// OpenGL ES. This isn't available on macOS, and on Linux and Windows the driver needs to
+    /// support the `EXT_create_context_es2_profile` extension.
+    Es,
 }
 
 #[derive(Debug)]
 pub enum GlError {
     InvalidWindowHandle,
-    VersionNotSupported,
+    /// None of the platform's pixel formats match the requested configuration. When
+    /// [`GlConfig::fallback`] is enabled, none of the fallbacks matched either.
+    NoMatchingConfig {
+        requested: GlConfig,
+        /// The pixel format the platform picks when it's only asked for a window that can be drawn
+        /// to with OpenGL, if there is one. This shows which of the requested attributes are out
+        /// of reach.
+        closest_available: Option<GlPixelFormat>,
+    },
+    /// The platform can't create a context with the requested version and profile. When
+    /// [`GlConfig::fallback`] is enabled, it can't create one for any of the fallbacks either.
+    VersionNotSupported {
+        version: (u8, u8),
+        profile: Profile,
+    },
     CreationFailed(platform::CreationFailedError),
+    /// The platform can't use the requested swap interval. Negative intervals need adaptive vsync,
+    /// see [`GlContext::supports_adaptive_swap_interval()`].
+    SwapIntervalNotSupported,
+}
+
+impl fmt::Display for GlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlError::InvalidWindowHandle => write!(f, "invalid window handle"),
+            GlError::NoMatchingConfig { requested, closest_available } => {
+                write!(f, "no pixel format matches {:?}", requested)?;
+                match closest_available {
+                    Some(format) => write!(f, ", the closest available one is {:?}", format),
+                    None => write!(f, ", and no OpenGL pixel formats are available at all"),
+                }
+            }
+            GlError::VersionNotSupported { version: (major, minor), profile } => {
+                write!(
+                    f,
+                    "OpenGL {}.{} with the {:?} profile is not supported",
+                    major, minor, profile
+                )
+            }
+            GlError::CreationFailed(error) => {
+                write!(f, "could not create the OpenGL context: {:?}", error)
+            }
+            GlError::SwapIntervalNotSupported => write!(f, "the swap interval is not supported"),
+        }
+    }
 }
 
+impl std::error::Error for GlError {}
+
 pub struct GlContext {
     context: platform::GlContext,
     phantom: PhantomData<*mut ()>,
@@ -77,7 +210,7 @@ pub struct GlContext {
 impl GlContext {
     #[cfg(not(target_os = "linux"))]
     pub(crate) unsafe fn create(
-        parent: &impl HasRawWindowHandle, config: GlConfig,
+        parent: PlatformWindowHandle, config: GlConfig,
     ) -> Result<GlContext, GlError> {
         platform::GlContext::create(parent, config)
             .map(|context| GlContext { context, phantom: PhantomData })
@@ -107,9 +240,137 @@ impl GlContext {
         self.context.swap_buffers();
     }
 
+    /// Set the number of vertical blanks [`swap_buffers()`][Self::swap_buffers] waits for before
+    /// swapping the buffers. `0` disables vsync and `1` syncs every swap to the display's refresh
+    /// rate. `-1` enables adaptive vsync, which syncs swaps that are on time but swaps late frames
+    /// immediately instead of waiting for the next vertical blank. This only works if
+    /// [`supports_adaptive_swap_interval()`][Self::supports_adaptive_swap_interval] returns `true`.
+    ///
+    /// The context needs to be current on the calling thread. This is checked in debug builds.
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), GlError> {
+        debug_assert!(self.context.is_current(), "The OpenGL context is not current");
+        self.context.set_swap_interval(interval)
+    }
+
+    /// The swap interval [`swap_buffers()`][Self::swap_buffers] currently uses, see
+    /// [`set_swap_interval()`][Self::set_swap_interval].
+    ///
+    /// The context needs to be current on the calling thread. This is checked in debug builds.
+    pub fn swap_interval(&self) -> i32 {
+        debug_assert!(self.context.is_current(), "The OpenGL context is not current");
+        self.context.swap_interval()
+    }
+
+    /// Whether the platform supports adaptive vsync through negative swap intervals. This is
+    /// never the case on macOS.
+    pub fn supports_adaptive_swap_interval(&self) -> bool {
+        self.context.supports_adaptive_swap_interval()
+    }
+
+    /// The pixel format of the context's framebuffer, as reported by the platform after creating
+    /// the context.
+    pub fn pixel_format(&self) -> GlPixelFormat {
+        self.context.pixel_format()
+    }
+
+    /// The configuration the context was created with. When [`GlConfig::fallback`] is enabled,
+    /// its number of samples, version, and profile can differ from the requested configuration.
+    pub fn config(&self) -> &GlConfig {
+        self.context.config()
+    }
+
+    /// Make the context current for the `build` closure passed to the `open_*()` functions, with
+    /// a viewport that covers the window's physical size. The context that was current before is
+    /// restored when the returned guard is dropped.
+    pub(crate) unsafe fn make_current_for_build(&self, size: PhySize) -> BuildContextGuard {
+        let previous = self.context.current_context();
+        self.make_current();
+
+        type GlViewport = extern "system" fn(i32, i32, i32, i32);
+        let viewport = self.get_proc_address("glViewport");
+        if !viewport.is_null() {
+            let viewport: GlViewport = std::mem::transmute(viewport);
+            let size = size.to_point();
+            viewport(0, 0, size.x, size.y);
+        }
+
+        BuildContextGuard { previous }
+    }
+
+    /// Create a context without a framebuffer of its own that shares objects like textures and
+    /// buffers with this one, for loading resources on another thread. It uses the same version,
+    /// profile, and pixel format as this context. See [`OffscreenGlContext`] for how the two
+    /// contexts need to be synchronized.
+    pub fn create_shared(&self) -> Result<OffscreenGlContext, GlError> {
+        self.context.create_shared().map(|context| OffscreenGlContext { context })
+    }
+
     /// On macOS the `NSOpenGLView` needs to be resized separtely from our main view.
     #[cfg(target_os = "macos")]
     pub(crate) fn resize(&self, size: cocoa::foundation::NSSize) {
         self.context.resize(size);
     }
 }
+
+/// An OpenGL context that shares its objects with a window's [`GlContext`], created with
+/// [`GlContext::create_shared()`]. It has no framebuffer of its own, so it's meant for creating
+/// textures, buffers, and other resources on a background thread. Anything drawn with it needs to
+/// go to a framebuffer object.
+///
+/// The context can be moved to another thread, and like any OpenGL context it can only be current
+/// on one thread at a time. Make it not current before moving it to a different thread, and drop
+/// it either on the thread it was last current on or after making it not current.
+///
+/// OpenGL doesn't synchronize contexts that share objects. Before the window's context uses an
+/// object, the commands that created or changed it need to have completed. Either call
+/// `glFinish()` on this context before handing the object over, or create a fence with
+/// `glFenceSync()`, call `glFlush()`, and have the window's context wait for the fence with
+/// `glWaitSync()`. Calling `glFlush()` on its own doesn't guarantee that the commands have
+/// completed. An object also shouldn't be changed on one context while the other one uses it.
+/// Container objects like vertex array objects and framebuffer objects aren't shared at all.
+pub struct OffscreenGlContext {
+    context: platform::OffscreenGlContext,
+}
+
+// The context isn't bound to the thread that created it, OpenGL only requires it to be current on
+// a single thread at a time. Since all methods that make it current take `&self`, it's not `Sync`.
+unsafe impl Send for OffscreenGlContext {}
+
+impl OffscreenGlContext {
+    /// Make the context current on the calling thread.
+    ///
+    /// # Safety
+    ///
+    /// The context must not be current on any other thread. It also needs to outlive the time it
+    /// stays current, so call [`Self::make_not_current()`] before dropping it on any thread other
+    /// than the one it's current on.
+    pub unsafe fn make_current(&self) {
+        self.context.make_current();
+    }
+
+    /// Make the context not current on the calling thread.
+    ///
+    /// # Safety
+    ///
+    /// The context must be current on the calling thread. Otherwise this releases whichever
+    /// context is current on it instead.
+    pub unsafe fn make_not_current(&self) {
+        self.context.make_not_current();
+    }
+
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        self.context.get_proc_address(symbol)
+    }
+}
+
+/// Restores the context that was current before [`GlContext::make_current_for_build()`] when it's
+/// dropped, including when `build` panics.
+pub(crate) struct BuildContextGuard {
+    previous: platform::CurrentContext,
+}
+
+impl Drop for BuildContextGuard {
+    fn drop(&mut self) {
+        unsafe { self.previous.restore() };
+    }
+}
@@ -1,24 +1,27 @@
-use std::ffi::{c_void, CString, OsStr};
+use std::ffi::{c_char, c_void, CStr, CString, OsStr};
 use std::os::windows::ffi::OsStrExt;
+use std::thread::{self, ThreadId};
 
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use crate::platform_handle::PlatformWindowHandle;
 
-use winapi::shared::minwindef::{HINSTANCE, HMODULE};
+use winapi::shared::minwindef::{ATOM, HINSTANCE, HMODULE};
 use winapi::shared::ntdef::WCHAR;
 use winapi::shared::windef::{HDC, HGLRC, HWND};
+use winapi::um::errhandlingapi::GetLastError;
 use winapi::um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA};
 use winapi::um::wingdi::{
-    wglCreateContext, wglDeleteContext, wglGetProcAddress, wglMakeCurrent, ChoosePixelFormat,
-    DescribePixelFormat, SetPixelFormat, SwapBuffers, PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW,
-    PFD_MAIN_PLANE, PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA, PIXELFORMATDESCRIPTOR,
+    wglCreateContext, wglDeleteContext, wglGetCurrentContext, wglGetCurrentDC, wglGetProcAddress,
+    wglMakeCurrent, ChoosePixelFormat, DescribePixelFormat, SetPixelFormat, SwapBuffers,
+    PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW, PFD_MAIN_PLANE, PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA,
+    PIXELFORMATDESCRIPTOR,
 };
 use winapi::um::winnt::IMAGE_DOS_HEADER;
 use winapi::um::winuser::{
-    CreateWindowExW, DefWindowProcW, DestroyWindow, GetDC, RegisterClassW, ReleaseDC,
-    UnregisterClassW, CS_OWNDC, CW_USEDEFAULT, WNDCLASSW,
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetDC, PostMessageW, RegisterClassW, ReleaseDC,
+    UnregisterClassW, CS_OWNDC, CW_USEDEFAULT, WM_CLOSE, WNDCLASSW,
 };
 
-use super::{GlConfig, GlError, Profile};
+use super::{GlConfig, GlError, GlPixelFormat, Profile};
 
 // See https://www.khronos.org/registry/OpenGL/extensions/ARB/WGL_ARB_create_context.txt
 
@@ -31,11 +34,18 @@ const WGL_CONTEXT_PROFILE_MASK_ARB: i32 = 0x9126;
 const WGL_CONTEXT_CORE_PROFILE_BIT_ARB: i32 = 0x00000001;
 const WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB: i32 = 0x00000002;
 
+// See https://registry.khronos.org/OpenGL/extensions/EXT/WGL_EXT_create_context_es2_profile.txt
+
+const WGL_CONTEXT_ES2_PROFILE_BIT_EXT: i32 = 0x00000004;
+
 // See https://www.khronos.org/registry/OpenGL/extensions/ARB/WGL_ARB_pixel_format.txt
 
 type WglChoosePixelFormatARB =
     extern "system" fn(HDC, *const i32, *const f32, u32, *mut i32, *mut u32) -> i32;
 
+type WglGetPixelFormatAttribivARB =
+    extern "system" fn(HDC, i32, i32, u32, *const i32, *mut i32) -> i32;
+
 const WGL_DRAW_TO_WINDOW_ARB: i32 = 0x2001;
 const WGL_ACCELERATION_ARB: i32 = 0x2003;
 const WGL_SUPPORT_OPENGL_ARB: i32 = 0x2010;
@@ -64,34 +74,71 @@ const WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB: i32 = 0x20A9;
 
 type WglSwapIntervalEXT = extern "system" fn(i32) -> i32;
 
-pub type CreationFailedError = ();
+type WglGetSwapIntervalEXT = extern "system" fn() -> i32;
+
+// See https://www.khronos.org/registry/OpenGL/extensions/EXT/WGL_EXT_extensions_string.txt
+
+type WglGetExtensionsStringEXT = extern "system" fn() -> *const c_char;
+
+/// The step of creating the context that failed. Where Windows reports why, this contains the
+/// error code `GetLastError()` returned.
+#[derive(Debug)]
+pub enum CreationFailedError {
+    /// The hidden window and context used to load the WGL extensions couldn't be created.
+    TemporaryContext(u32),
+    /// The driver doesn't support a WGL extension that's needed to create the context.
+    MissingExtension(&'static str),
+    /// The pixel format couldn't be set on the window's device context.
+    SetPixelFormat(u32),
+    /// The hidden window or the context for [`GlContext::create_shared()`] couldn't be created.
+    SharedContext(u32),
+}
+
 pub struct GlContext {
     hwnd: HWND,
     hdc: HDC,
     hglrc: HGLRC,
     gl_library: HMODULE,
+    config: GlConfig,
+    pixel_format: GlPixelFormat,
+    /// The index of the pixel format, which contexts that share objects with this one need too.
+    pixel_format_index: i32,
+    create_context_attribs: WglCreateContextAttribsARB,
+    swap_interval_ext: WglSwapIntervalEXT,
+    get_swap_interval_ext: WglGetSwapIntervalEXT,
+    adaptive_swap_interval: bool,
 }
 
-extern "C" {
-    static __ImageBase: IMAGE_DOS_HEADER;
+/// A context that was current on a thread, see [`GlContext::current_context()`].
+pub struct CurrentContext {
+    hdc: HDC,
+    hglrc: HGLRC,
 }
 
-impl GlContext {
-    pub unsafe fn create(
-        parent: &impl HasRawWindowHandle, config: GlConfig,
-    ) -> Result<GlContext, GlError> {
-        let handle = if let RawWindowHandle::Win32(handle) = parent.raw_window_handle() {
-            handle
-        } else {
-            return Err(GlError::InvalidWindowHandle);
-        };
+impl CurrentContext {
+    /// Make the context current again, or release the current context if there was none.
+    pub unsafe fn restore(&self) {
+        wglMakeCurrent(self.hdc, self.hglrc);
+    }
+}
 
-        if handle.hwnd.is_null() {
-            return Err(GlError::InvalidWindowHandle);
-        }
+extern "C" {
+    static __ImageBase: IMAGE_DOS_HEADER;
+}
 
-        // Create temporary window and context to load function pointers
+/// A hidden window with a device context of its own. Creating a context needs one to load the WGL
+/// extensions, and offscreen contexts are made current with one.
+struct HiddenWindow {
+    hwnd: HWND,
+    hdc: HDC,
+    class: ATOM,
+    hinstance: HINSTANCE,
+    thread: ThreadId,
+}
 
+impl HiddenWindow {
+    /// Returns the error code `GetLastError()` returned if the window couldn't be created.
+    unsafe fn create() -> Result<HiddenWindow, u32> {
         let class_name_str = format!("raw-gl-context-window-{}", uuid::Uuid::new_v4().to_simple());
         let mut class_name: Vec<WCHAR> = OsStr::new(&class_name_str).encode_wide().collect();
         class_name.push(0);
@@ -108,10 +155,10 @@ impl GlContext {
 
         let class = RegisterClassW(&wnd_class);
         if class == 0 {
-            return Err(GlError::CreationFailed(()));
+            return Err(GetLastError());
         }
 
-        let hwnd_tmp = CreateWindowExW(
+        let hwnd = CreateWindowExW(
             0,
             class as *const WCHAR,
             [0].as_ptr(),
@@ -126,11 +173,95 @@ impl GlContext {
             std::ptr::null_mut(),
         );
 
-        if hwnd_tmp.is_null() {
-            return Err(GlError::CreationFailed(()));
+        if hwnd.is_null() {
+            let error = GetLastError();
+            UnregisterClassW(class as *const WCHAR, hinstance);
+            return Err(error);
         }
 
-        let hdc_tmp = GetDC(hwnd_tmp);
+        Ok(HiddenWindow {
+            hwnd,
+            hdc: GetDC(hwnd),
+            class,
+            hinstance,
+            thread: thread::current().id(),
+        })
+    }
+}
+
+impl Drop for HiddenWindow {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseDC(self.hwnd, self.hdc);
+
+            // Windows can only be destroyed by the thread that created them. An offscreen context
+            // may be dropped on another thread, and then the window's thread destroys the window
+            // once it handles the message. The class stays registered in that case, since it can
+            // only be unregistered after the window is gone.
+            if thread::current().id() == self.thread {
+                DestroyWindow(self.hwnd);
+                UnregisterClassW(self.class as *const WCHAR, self.hinstance);
+            } else {
+                PostMessageW(self.hwnd, WM_CLOSE, 0, 0);
+            }
+        }
+    }
+}
+
+/// The attributes for `wglCreateContextAttribsARB()` that request a version and profile.
+fn context_attribs(version: (u8, u8), profile: Profile) -> [i32; 7] {
+    let profile_mask = match profile {
+        Profile::Core => WGL_CONTEXT_CORE_PROFILE_BIT_ARB,
+        Profile::Compatibility => WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+        Profile::Es => WGL_CONTEXT_ES2_PROFILE_BIT_EXT,
+    };
+
+    #[rustfmt::skip]
+    let attribs = [
+        WGL_CONTEXT_MAJOR_VERSION_ARB, version.0 as i32,
+        WGL_CONTEXT_MINOR_VERSION_ARB, version.1 as i32,
+        WGL_CONTEXT_PROFILE_MASK_ARB, profile_mask,
+        0
+    ];
+
+    attribs
+}
+
+/// Look up an OpenGL function. `wglGetProcAddress()` only returns the functions that aren't part
+/// of OpenGL 1.1, and those are exported by `opengl32.dll` instead.
+fn get_proc_address(gl_library: HMODULE, symbol: &str) -> *const c_void {
+    let symbol = CString::new(symbol).unwrap();
+    let addr = unsafe { wglGetProcAddress(symbol.as_ptr()) as *const c_void };
+    if !addr.is_null() {
+        addr
+    } else {
+        unsafe { GetProcAddress(gl_library, symbol.as_ptr()) as *const c_void }
+    }
+}
+
+/// Load `opengl32.dll` for [`get_proc_address()`]. This only adds a reference, since the library is
+/// already loaded for WGL.
+unsafe fn load_gl_library() -> HMODULE {
+    let gl_library_name = CString::new("opengl32.dll").unwrap();
+    LoadLibraryA(gl_library_name.as_ptr())
+}
+
+impl GlContext {
+    pub unsafe fn create(
+        parent: PlatformWindowHandle, config: GlConfig,
+    ) -> Result<GlContext, GlError> {
+        let PlatformWindowHandle::Win32 { hwnd } = parent;
+
+        if hwnd.is_null() {
+            return Err(GlError::InvalidWindowHandle);
+        }
+
+        // Create temporary window and context to load function pointers
+
+        let window_tmp = HiddenWindow::create().map_err(|error| {
+            GlError::CreationFailed(CreationFailedError::TemporaryContext(error))
+        })?;
+        let hdc_tmp = window_tmp.hdc;
 
         let pfd_tmp = PIXELFORMATDESCRIPTOR {
             nSize: std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16,
@@ -149,10 +280,8 @@ impl GlContext {
 
         let hglrc_tmp = wglCreateContext(hdc_tmp);
         if hglrc_tmp.is_null() {
-            ReleaseDC(hwnd_tmp, hdc_tmp);
-            UnregisterClassW(class as *const WCHAR, hinstance);
-            DestroyWindow(hwnd_tmp);
-            return Err(GlError::CreationFailed(()));
+            let error = GetLastError();
+            return Err(GlError::CreationFailed(CreationFailedError::TemporaryContext(error)));
         }
 
         wglMakeCurrent(hdc_tmp, hglrc_tmp);
@@ -179,6 +308,17 @@ impl GlContext {
             }
         };
 
+        #[allow(non_snake_case)]
+        let wglGetPixelFormatAttribivARB: Option<WglGetPixelFormatAttribivARB> = {
+            let symbol = CString::new("wglGetPixelFormatAttribivARB").unwrap();
+            let addr = wglGetProcAddress(symbol.as_ptr());
+            if !addr.is_null() {
+                Some(std::mem::transmute(addr))
+            } else {
+                None
+            }
+        };
+
         #[allow(non_snake_case)]
         let wglSwapIntervalEXT: Option<WglSwapIntervalEXT> = {
             let symbol = CString::new("wglSwapIntervalEXT").unwrap();
@@ -190,83 +330,149 @@ impl GlContext {
             }
         };
 
+        #[allow(non_snake_case)]
+        let wglGetSwapIntervalEXT: Option<WglGetSwapIntervalEXT> = {
+            let symbol = CString::new("wglGetSwapIntervalEXT").unwrap();
+            let addr = wglGetProcAddress(symbol.as_ptr());
+            if !addr.is_null() {
+                Some(std::mem::transmute(addr))
+            } else {
+                None
+            }
+        };
+
+        #[allow(non_snake_case)]
+        let wglGetExtensionsStringEXT: Option<WglGetExtensionsStringEXT> = {
+            let symbol = CString::new("wglGetExtensionsStringEXT").unwrap();
+            let addr = wglGetProcAddress(symbol.as_ptr());
+            if !addr.is_null() {
+                Some(std::mem::transmute(addr))
+            } else {
+                None
+            }
+        };
+
+        // Adaptive vsync and OpenGL ES are extensions of their own, without any functions to load
+        let extensions = wglGetExtensionsStringEXT
+            .map(|get_extensions| get_extensions())
+            .filter(|extensions| !extensions.is_null())
+            .map(|extensions| CStr::from_ptr(extensions).to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let has_extension = |name| extensions.split_whitespace().any(|ext| ext == name);
+        let adaptive_swap_interval = has_extension("WGL_EXT_swap_control_tear");
+        let es_available = has_extension("WGL_EXT_create_context_es2_profile");
+
         wglMakeCurrent(hdc_tmp, std::ptr::null_mut());
-        ReleaseDC(hwnd_tmp, hdc_tmp);
-        UnregisterClassW(class as *const WCHAR, hinstance);
-        DestroyWindow(hwnd_tmp);
+        wglDeleteContext(hglrc_tmp);
+        drop(window_tmp);
+
+        let missing_extension =
+            |name| GlError::CreationFailed(CreationFailedError::MissingExtension(name));
+        let create_context_attribs = wglCreateContextAttribsARB
+            .ok_or_else(|| missing_extension("WGL_ARB_create_context"))?;
+        let choose_pixel_format =
+            wglChoosePixelFormatARB.ok_or_else(|| missing_extension("WGL_ARB_pixel_format"))?;
+        let (Some(swap_interval_ext), Some(get_swap_interval_ext)) =
+            (wglSwapIntervalEXT, wglGetSwapIntervalEXT)
+        else {
+            return Err(missing_extension("WGL_EXT_swap_control"));
+        };
 
         // Create actual context
 
-        let hwnd = handle.hwnd as HWND;
+        let hwnd = hwnd as HWND;
 
         let hdc = GetDC(hwnd);
 
-        #[rustfmt::skip]
-        let pixel_format_attribs = [
-            WGL_DRAW_TO_WINDOW_ARB, 1,
-            WGL_ACCELERATION_ARB, WGL_FULL_ACCELERATION_ARB,
-            WGL_SUPPORT_OPENGL_ARB, 1,
-            WGL_DOUBLE_BUFFER_ARB, config.double_buffer as i32,
-            WGL_PIXEL_TYPE_ARB, WGL_TYPE_RGBA_ARB,
-            WGL_RED_BITS_ARB, config.red_bits as i32,
-            WGL_GREEN_BITS_ARB, config.green_bits as i32,
-            WGL_BLUE_BITS_ARB, config.blue_bits as i32,
-            WGL_ALPHA_BITS_ARB, config.alpha_bits as i32,
-            WGL_DEPTH_BITS_ARB, config.depth_bits as i32,
-            WGL_STENCIL_BITS_ARB, config.stencil_bits as i32,
-            WGL_SAMPLE_BUFFERS_ARB, config.samples.is_some() as i32,
-            WGL_SAMPLES_ARB, config.samples.unwrap_or(0) as i32,
-            WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB, config.srgb as i32,
-            0,
-        ];
-
-        let mut pixel_format = 0;
-        let mut num_formats = 0;
-        wglChoosePixelFormatARB.unwrap()(
-            hdc,
-            pixel_format_attribs.as_ptr(),
-            std::ptr::null(),
-            1,
-            &mut pixel_format,
-            &mut num_formats,
-        );
-
-        let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
-        DescribePixelFormat(
-            hdc,
-            pixel_format,
-            std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u32,
-            &mut pfd,
-        );
-        SetPixelFormat(hdc, pixel_format, &pfd);
+        let mut chosen = None;
+        for samples in config.sample_candidates() {
+            #[rustfmt::skip]
+            let pixel_format_attribs = [
+                WGL_DRAW_TO_WINDOW_ARB, 1,
+                WGL_ACCELERATION_ARB, WGL_FULL_ACCELERATION_ARB,
+                WGL_SUPPORT_OPENGL_ARB, 1,
+                WGL_DOUBLE_BUFFER_ARB, config.double_buffer as i32,
+                WGL_PIXEL_TYPE_ARB, WGL_TYPE_RGBA_ARB,
+                WGL_RED_BITS_ARB, config.red_bits as i32,
+                WGL_GREEN_BITS_ARB, config.green_bits as i32,
+                WGL_BLUE_BITS_ARB, config.blue_bits as i32,
+                WGL_ALPHA_BITS_ARB, config.alpha_bits as i32,
+                WGL_DEPTH_BITS_ARB, config.depth_bits as i32,
+                WGL_STENCIL_BITS_ARB, config.stencil_bits as i32,
+                WGL_SAMPLE_BUFFERS_ARB, samples.is_some() as i32,
+                WGL_SAMPLES_ARB, samples.unwrap_or(0) as i32,
+                WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB, config.srgb as i32,
+                0,
+            ];
+
+            let mut pixel_format = 0;
+            let mut num_formats = 0;
+            let res = choose_pixel_format(
+                hdc,
+                pixel_format_attribs.as_ptr(),
+                std::ptr::null(),
+                1,
+                &mut pixel_format,
+                &mut num_formats,
+            );
+            if res != 0 && num_formats > 0 {
+                chosen = Some((pixel_format, samples));
+                break;
+            }
+        }
 
-        let profile_mask = match config.profile {
-            Profile::Core => WGL_CONTEXT_CORE_PROFILE_BIT_ARB,
-            Profile::Compatibility => WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+        let Some((pixel_format, samples)) = chosen else {
+            let closest_available =
+                closest_pixel_format(hdc, choose_pixel_format, wglGetPixelFormatAttribivARB);
+            ReleaseDC(hwnd, hdc);
+            return Err(GlError::NoMatchingConfig { requested: config, closest_available });
         };
 
-        #[rustfmt::skip]
-        let ctx_attribs = [
-            WGL_CONTEXT_MAJOR_VERSION_ARB, config.version.0 as i32,
-            WGL_CONTEXT_MINOR_VERSION_ARB, config.version.1 as i32,
-            WGL_CONTEXT_PROFILE_MASK_ARB, profile_mask,
-            0
-        ];
-
-        let hglrc =
-            wglCreateContextAttribsARB.unwrap()(hdc, std::ptr::null_mut(), ctx_attribs.as_ptr());
-        if hglrc.is_null() {
-            return Err(GlError::CreationFailed(()));
+        let (pfd, pixel_format_info) =
+            describe_pixel_format(hdc, pixel_format, wglGetPixelFormatAttribivARB);
+        if SetPixelFormat(hdc, pixel_format, &pfd) == 0 {
+            let error = GetLastError();
+            ReleaseDC(hwnd, hdc);
+            return Err(GlError::CreationFailed(CreationFailedError::SetPixelFormat(error)));
         }
 
-        let gl_library_name = CString::new("opengl32.dll").unwrap();
-        let gl_library = LoadLibraryA(gl_library_name.as_ptr());
+        let mut created = None;
+        for (version, profile) in config.version_candidates(es_available) {
+            let ctx_attribs = context_attribs(version, profile);
+            let hglrc = create_context_attribs(hdc, std::ptr::null_mut(), ctx_attribs.as_ptr());
+            if !hglrc.is_null() {
+                created = Some((hglrc, version, profile));
+                break;
+            }
+        }
+
+        let Some((hglrc, version, profile)) = created else {
+            ReleaseDC(hwnd, hdc);
+            return Err(GlError::VersionNotSupported {
+                version: config.version,
+                profile: config.profile,
+            });
+        };
+
+        let gl_library = load_gl_library();
 
         wglMakeCurrent(hdc, hglrc);
-        wglSwapIntervalEXT.unwrap()(config.vsync as i32);
+        swap_interval_ext(config.vsync as i32);
         wglMakeCurrent(hdc, std::ptr::null_mut());
 
-        Ok(GlContext { hwnd, hdc, hglrc, gl_library })
+        Ok(GlContext {
+            hwnd,
+            hdc,
+            hglrc,
+            gl_library,
+            config: GlConfig { samples, version, profile, ..config },
+            pixel_format: pixel_format_info,
+            pixel_format_index: pixel_format,
+            create_context_attribs,
+            swap_interval_ext,
+            get_swap_interval_ext,
+            adaptive_swap_interval,
+        })
     }
 
     pub unsafe fn make_current(&self) {
@@ -278,13 +484,7 @@ impl GlContext {
     }
 
     pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
-        let symbol = CString::new(symbol).unwrap();
-        let addr = unsafe { wglGetProcAddress(symbol.as_ptr()) as *const c_void };
-        if !addr.is_null() {
-            addr
-        } else {
-            unsafe { GetProcAddress(self.gl_library, symbol.as_ptr()) as *const c_void }
-        }
+        get_proc_address(self.gl_library, symbol)
     }
 
     pub fn swap_buffers(&self) {
@@ -292,6 +492,169 @@ impl GlContext {
             SwapBuffers(self.hdc);
         }
     }
+
+    pub fn pixel_format(&self) -> GlPixelFormat {
+        self.pixel_format
+    }
+
+    pub fn config(&self) -> &GlConfig {
+        &self.config
+    }
+
+    pub fn is_current(&self) -> bool {
+        unsafe { wglGetCurrentContext() == self.hglrc }
+    }
+
+    /// The context that's current on the calling thread, so it can be restored afterwards.
+    pub fn current_context(&self) -> CurrentContext {
+        unsafe { CurrentContext { hdc: wglGetCurrentDC(), hglrc: wglGetCurrentContext() } }
+    }
+
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), GlError> {
+        if interval < 0 && !self.adaptive_swap_interval {
+            return Err(GlError::SwapIntervalNotSupported);
+        }
+
+        if (self.swap_interval_ext)(interval) != 0 {
+            Ok(())
+        } else {
+            Err(GlError::SwapIntervalNotSupported)
+        }
+    }
+
+    pub fn swap_interval(&self) -> i32 {
+        (self.get_swap_interval_ext)()
+    }
+
+    pub fn supports_adaptive_swap_interval(&self) -> bool {
+        self.adaptive_swap_interval
+    }
+
+    /// Create a context that shares its objects with this one. Passing this context to
+    /// `wglCreateContextAttribsARB()` does the same as `wglShareLists()`. A context can only be
+    /// made current with a device context that has the same pixel format, so the new context gets
+    /// a hidden window of its own.
+    pub fn create_shared(&self) -> Result<OffscreenGlContext, GlError> {
+        let shared_context_failed =
+            |error| GlError::CreationFailed(CreationFailedError::SharedContext(error));
+
+        unsafe {
+            let window = HiddenWindow::create().map_err(shared_context_failed)?;
+
+            let (pfd, _) = describe_pixel_format(window.hdc, self.pixel_format_index, None);
+            if SetPixelFormat(window.hdc, self.pixel_format_index, &pfd) == 0 {
+                return Err(GlError::CreationFailed(CreationFailedError::SetPixelFormat(
+                    GetLastError(),
+                )));
+            }
+
+            let ctx_attribs = context_attribs(self.config.version, self.config.profile);
+            let hglrc = (self.create_context_attribs)(window.hdc, self.hglrc, ctx_attribs.as_ptr());
+            if hglrc.is_null() {
+                return Err(shared_context_failed(GetLastError()));
+            }
+
+            Ok(OffscreenGlContext { window, hglrc, gl_library: load_gl_library() })
+        }
+    }
+}
+
+/// See [`crate::gl::OffscreenGlContext`].
+pub struct OffscreenGlContext {
+    window: HiddenWindow,
+    hglrc: HGLRC,
+    gl_library: HMODULE,
+}
+
+impl OffscreenGlContext {
+    pub unsafe fn make_current(&self) {
+        wglMakeCurrent(self.window.hdc, self.hglrc);
+    }
+
+    pub unsafe fn make_not_current(&self) {
+        wglMakeCurrent(self.window.hdc, std::ptr::null_mut());
+    }
+
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        get_proc_address(self.gl_library, symbol)
+    }
+}
+
+impl Drop for OffscreenGlContext {
+    fn drop(&mut self) {
+        unsafe {
+            if wglGetCurrentContext() == self.hglrc {
+                wglMakeCurrent(std::ptr::null_mut(), std::ptr::null_mut());
+            }
+            wglDeleteContext(self.hglrc);
+            FreeLibrary(self.gl_library);
+        }
+    }
+}
+
+/// Describe one of the device context's pixel formats.
+unsafe fn describe_pixel_format(
+    hdc: HDC, pixel_format: i32, get_attribs: Option<WglGetPixelFormatAttribivARB>,
+) -> (PIXELFORMATDESCRIPTOR, GlPixelFormat) {
+    let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
+    DescribePixelFormat(
+        hdc,
+        pixel_format,
+        std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u32,
+        &mut pfd,
+    );
+
+    // The descriptor doesn't include the multisampling attributes
+    let mut sample_attribs = [0; 2];
+    if let Some(get_attribs) = get_attribs {
+        get_attribs(
+            hdc,
+            pixel_format,
+            0,
+            2,
+            [WGL_SAMPLE_BUFFERS_ARB, WGL_SAMPLES_ARB].as_ptr(),
+            sample_attribs.as_mut_ptr(),
+        );
+    }
+    let [sample_buffers, samples] = sample_attribs;
+
+    let format = GlPixelFormat {
+        color_bits: pfd.cRedBits + pfd.cGreenBits + pfd.cBlueBits,
+        alpha_bits: pfd.cAlphaBits,
+        depth_bits: pfd.cDepthBits,
+        stencil_bits: pfd.cStencilBits,
+        samples: (sample_buffers > 0).then_some(samples as u8),
+        double_buffer: pfd.dwFlags & PFD_DOUBLEBUFFER != 0,
+    };
+
+    (pfd, format)
+}
+
+/// The pixel format `wglChoosePixelFormatARB()` picks when it's asked for nothing more than a
+/// window that can be drawn to with OpenGL.
+unsafe fn closest_pixel_format(
+    hdc: HDC, choose_pixel_format: WglChoosePixelFormatARB,
+    get_attribs: Option<WglGetPixelFormatAttribivARB>,
+) -> Option<GlPixelFormat> {
+    #[rustfmt::skip]
+    let pixel_format_attribs = [
+        WGL_DRAW_TO_WINDOW_ARB, 1,
+        WGL_SUPPORT_OPENGL_ARB, 1,
+        WGL_PIXEL_TYPE_ARB, WGL_TYPE_RGBA_ARB,
+        0,
+    ];
+
+    let mut pixel_format = 0;
+    let mut num_formats = 0;
+    let res = choose_pixel_format(
+        hdc,
+        pixel_format_attribs.as_ptr(),
+        std::ptr::null(),
+        1,
+        &mut pixel_format,
+        &mut num_formats,
+    );
+    (res != 0 && num_formats > 0).then(|| describe_pixel_format(hdc, pixel_format, get_attribs).1)
 }
 
 impl Drop for GlContext {
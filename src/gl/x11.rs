@@ -1,22 +1,23 @@
-use std::ffi::{c_void, CString};
+use std::ffi::{c_void, CStr, CString};
 use std::os::raw::{c_int, c_ulong};
 
-use raw_window_handle::{HasRawWindowHandle, RawDisplayHandle, RawWindowHandle};
+use crate::platform_handle::{PlatformDisplayHandle, PlatformWindowHandle};
 
 use x11::glx;
 use x11::xlib;
 
-use super::{GlConfig, GlError, Profile};
+use super::{GlConfig, GlError, GlPixelFormat, Profile};
 
 mod errors;
 
 #[derive(Debug)]
 pub enum CreationFailedError {
-    InvalidFBConfig,
     NoVisual,
     GetProcAddressFailed,
     MakeCurrentFailed,
-    ContextCreationFailed,
+    /// The driver returned no context for [`GlContext::create_shared()`] without reporting an
+    /// error.
+    SharedContextFailed,
     X11Error(errors::XLibError),
 }
 
@@ -41,19 +42,77 @@ type GlXCreateContextAttribsARB = unsafe extern "C" fn(
 type GlXSwapIntervalEXT =
     unsafe extern "C" fn(dpy: *mut xlib::Display, drawable: glx::GLXDrawable, interval: i32);
 
+// See https://www.khronos.org/registry/OpenGL/extensions/EXT/GLX_EXT_swap_control_tear.txt
+
+const GLX_LATE_SWAPS_TEAR_EXT: i32 = 0x20F3;
+
 // See https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_framebuffer_sRGB.txt
 
 const GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB: i32 = 0x20B2;
 
+// See https://registry.khronos.org/OpenGL/extensions/EXT/GLX_EXT_create_context_es2_profile.txt
+
+const GLX_CONTEXT_ES2_PROFILE_BIT_EXT: i32 = 0x0004;
+
 fn get_proc_address(symbol: &str) -> *const c_void {
     let symbol = CString::new(symbol).unwrap();
     unsafe { glx::glXGetProcAddress(symbol.as_ptr() as *const u8).unwrap() as *const c_void }
 }
 
+/// The attributes for `glXCreateContextAttribsARB()` that request a version and profile.
+fn context_attribs(version: (u8, u8), profile: Profile) -> [c_int; 7] {
+    let profile_mask = match profile {
+        Profile::Core => glx::arb::GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
+        Profile::Compatibility => glx::arb::GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+        Profile::Es => GLX_CONTEXT_ES2_PROFILE_BIT_EXT,
+    };
+
+    #[rustfmt::skip]
+    let attribs = [
+        glx::arb::GLX_CONTEXT_MAJOR_VERSION_ARB, version.0 as i32,
+        glx::arb::GLX_CONTEXT_MINOR_VERSION_ARB, version.1 as i32,
+        glx::arb::GLX_CONTEXT_PROFILE_MASK_ARB, profile_mask,
+        0,
+    ];
+
+    attribs
+}
+
+/// Whether the display's default screen supports a GLX extension. `glXGetProcAddress()` can't be
+/// used for this since Mesa returns a function pointer for any name.
+unsafe fn has_extension(display: *mut xlib::_XDisplay, name: &str) -> bool {
+    let extensions = glx::glXQueryExtensionsString(display, xlib::XDefaultScreen(display));
+    if extensions.is_null() {
+        return false;
+    }
+
+    CStr::from_ptr(extensions).to_string_lossy().split_whitespace().any(|ext| ext == name)
+}
+
 pub struct GlContext {
     window: c_ulong,
     display: *mut xlib::_XDisplay,
     context: glx::GLXContext,
+    /// Needed to create contexts that share objects with this one.
+    fb_config: glx::GLXFBConfig,
+    config: GlConfig,
+    pixel_format: GlPixelFormat,
+    swap_interval_ext: GlXSwapIntervalEXT,
+    adaptive_swap_interval: bool,
+}
+
+/// A context that was current on a thread, see [`GlContext::current_context()`].
+pub struct CurrentContext {
+    display: *mut xlib::_XDisplay,
+    drawable: glx::GLXDrawable,
+    context: glx::GLXContext,
+}
+
+impl CurrentContext {
+    /// Make the context current again, or release the current context if there was none.
+    pub unsafe fn restore(&self) {
+        glx::glXMakeCurrent(self.display, self.drawable, self.context);
+    }
 }
 
 /// The frame buffer configuration along with the general OpenGL configuration to somewhat minimize
@@ -80,24 +139,16 @@ impl GlContext {
     ///
     /// Use [Self::get_fb_config_and_visual] to create both of these things.
     pub unsafe fn create(
-        parent: &impl HasRawWindowHandle, config: FbConfig, display: &RawDisplayHandle,
+        parent: PlatformWindowHandle, config: FbConfig, display: PlatformDisplayHandle,
     ) -> Result<GlContext, GlError> {
-        let handle = if let RawWindowHandle::Xlib(handle) = parent.raw_window_handle() {
-            handle
-        } else {
-            return Err(GlError::InvalidWindowHandle);
-        };
+        let PlatformWindowHandle::Xlib { window, .. } = parent;
+        let PlatformDisplayHandle::Xlib { display, .. } = display;
 
-        // if handle.display.is_null() {
-        //     return Err(GlError::InvalidWindowHandle);
-        // }
-        let display = if let RawDisplayHandle::Xlib(handle) = display {
-            handle
-        } else {
+        if display.is_null() {
             return Err(GlError::InvalidWindowHandle);
-        };
+        }
 
-        let display = display.display as *mut xlib::_XDisplay;
+        let display = display as *mut xlib::_XDisplay;
 
         errors::XErrorHandler::handle(display, |error_handler| {
             #[allow(non_snake_case)]
@@ -122,40 +173,40 @@ impl GlContext {
 
             error_handler.check()?;
 
-            let profile_mask = match config.gl_config.profile {
-                Profile::Core => glx::arb::GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
-                Profile::Compatibility => glx::arb::GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
-            };
-
-            #[rustfmt::skip]
-                let ctx_attribs = [
-                glx::arb::GLX_CONTEXT_MAJOR_VERSION_ARB, config.gl_config.version.0 as i32,
-                glx::arb::GLX_CONTEXT_MINOR_VERSION_ARB, config.gl_config.version.1 as i32,
-                glx::arb::GLX_CONTEXT_PROFILE_MASK_ARB, profile_mask,
-                0,
-            ];
-
-            let context = glXCreateContextAttribsARB(
-                display,
-                config.fb_config,
-                std::ptr::null_mut(),
-                1,
-                ctx_attribs.as_ptr(),
-            );
-
-            error_handler.check()?;
-
-            if context.is_null() {
-                return Err(GlError::CreationFailed(CreationFailedError::ContextCreationFailed));
+            let es_available = has_extension(display, "GLX_EXT_create_context_es2_profile");
+            let mut created = None;
+            for (version, profile) in config.gl_config.version_candidates(es_available) {
+                let ctx_attribs = context_attribs(version, profile);
+                let context = glXCreateContextAttribsARB(
+                    display,
+                    config.fb_config,
+                    std::ptr::null_mut(),
+                    1,
+                    ctx_attribs.as_ptr(),
+                );
+
+                // Depending on the driver, unsupported versions either return a null context or
+                // raise a `GLXBadFBConfig` or `BadMatch` error
+                if error_handler.check().is_ok() && !context.is_null() {
+                    created = Some((context, version, profile));
+                    break;
+                }
             }
 
-            let res = glx::glXMakeCurrent(display, handle.window, context);
+            let Some((context, version, profile)) = created else {
+                return Err(GlError::VersionNotSupported {
+                    version: config.gl_config.version,
+                    profile: config.gl_config.profile,
+                });
+            };
+
+            let res = glx::glXMakeCurrent(display, window, context);
             error_handler.check()?;
             if res == 0 {
                 return Err(GlError::CreationFailed(CreationFailedError::MakeCurrentFailed));
             }
 
-            glXSwapIntervalEXT(display, handle.window, config.gl_config.vsync as i32);
+            glXSwapIntervalEXT(display, window, config.gl_config.vsync as i32);
             error_handler.check()?;
 
             if glx::glXMakeCurrent(display, 0, std::ptr::null_mut()) == 0 {
@@ -163,7 +214,21 @@ impl GlContext {
                 return Err(GlError::CreationFailed(CreationFailedError::MakeCurrentFailed));
             }
 
-            Ok(GlContext { window: handle.window, display, context })
+            let pixel_format = config.pixel_format(display);
+            error_handler.check()?;
+
+            let adaptive_swap_interval = has_extension(display, "GLX_EXT_swap_control_tear");
+
+            Ok(GlContext {
+                window,
+                display,
+                context,
+                fb_config: config.fb_config,
+                config: GlConfig { version, profile, ..config.gl_config },
+                pixel_format,
+                swap_interval_ext: glXSwapIntervalEXT,
+                adaptive_swap_interval,
+            })
         })
     }
 
@@ -176,46 +241,61 @@ impl GlContext {
         errors::XErrorHandler::handle(display, |error_handler| {
             let screen = xlib::XDefaultScreen(display);
 
-            #[rustfmt::skip]
+            for samples in config.sample_candidates() {
+                #[rustfmt::skip]
                 let fb_attribs = [
-                glx::GLX_X_RENDERABLE, 1,
-                glx::GLX_X_VISUAL_TYPE, glx::GLX_TRUE_COLOR,
-                glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
-                glx::GLX_RENDER_TYPE, glx::GLX_RGBA_BIT,
-                glx::GLX_RED_SIZE, config.red_bits as i32,
-                glx::GLX_GREEN_SIZE, config.green_bits as i32,
-                glx::GLX_BLUE_SIZE, config.blue_bits as i32,
-                glx::GLX_ALPHA_SIZE, config.alpha_bits as i32,
-                glx::GLX_DEPTH_SIZE, config.depth_bits as i32,
-                glx::GLX_STENCIL_SIZE, config.stencil_bits as i32,
-                glx::GLX_DOUBLEBUFFER, config.double_buffer as i32,
-                glx::GLX_SAMPLE_BUFFERS, config.samples.is_some() as i32,
-                glx::GLX_SAMPLES, config.samples.unwrap_or(0) as i32,
-                GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB, config.srgb as i32,
-                0,
-            ];
-
-            let mut n_configs = 0;
-            let fb_config =
-                glx::glXChooseFBConfig(display, screen, fb_attribs.as_ptr(), &mut n_configs);
+                    glx::GLX_X_RENDERABLE, 1,
+                    glx::GLX_X_VISUAL_TYPE, glx::GLX_TRUE_COLOR,
+                    glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
+                    glx::GLX_RENDER_TYPE, glx::GLX_RGBA_BIT,
+                    glx::GLX_RED_SIZE, config.red_bits as i32,
+                    glx::GLX_GREEN_SIZE, config.green_bits as i32,
+                    glx::GLX_BLUE_SIZE, config.blue_bits as i32,
+                    glx::GLX_ALPHA_SIZE, config.alpha_bits as i32,
+                    glx::GLX_DEPTH_SIZE, config.depth_bits as i32,
+                    glx::GLX_STENCIL_SIZE, config.stencil_bits as i32,
+                    glx::GLX_DOUBLEBUFFER, config.double_buffer as i32,
+                    glx::GLX_SAMPLE_BUFFERS, samples.is_some() as i32,
+                    glx::GLX_SAMPLES, samples.unwrap_or(0) as i32,
+                    GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB, config.srgb as i32,
+                    0,
+                ];
+
+                let mut n_configs = 0;
+                let fb_configs =
+                    glx::glXChooseFBConfig(display, screen, fb_attribs.as_ptr(), &mut n_configs);
 
-            error_handler.check()?;
-            if n_configs <= 0 || fb_config.is_null() {
-                return Err(GlError::CreationFailed(CreationFailedError::InvalidFBConfig));
-            }
+                error_handler.check()?;
+                if n_configs <= 0 || fb_configs.is_null() {
+                    continue;
+                }
+
+                // Now that we have a matching framebuffer config, we need to know which visual
+                // matches this config so the window is compatible with the OpenGL context we're
+                // about to create
+                let fb_config = *fb_configs;
+                xlib::XFree(fb_configs.cast());
+                let visual = glx::glXGetVisualFromFBConfig(display, fb_config);
+                if visual.is_null() {
+                    return Err(GlError::CreationFailed(CreationFailedError::NoVisual));
+                }
 
-            // Now that we have a matching framebuffer config, we need to know which visual matches
-            // thsi config so the window is compatible with the OpenGL context we're about to create
-            let fb_config = *fb_config;
-            let visual = glx::glXGetVisualFromFBConfig(display, fb_config);
-            if visual.is_null() {
-                return Err(GlError::CreationFailed(CreationFailedError::NoVisual));
+                let window_config = WindowConfig {
+                    depth: (*visual).depth as u8,
+                    visual: (*visual).visualid as u32,
+                };
+                xlib::XFree(visual.cast());
+
+                return Ok((
+                    FbConfig { fb_config, gl_config: GlConfig { samples, ..config } },
+                    window_config,
+                ));
             }
 
-            Ok((
-                FbConfig { fb_config, gl_config: config },
-                WindowConfig { depth: (*visual).depth as u8, visual: (*visual).visualid as u32 },
-            ))
+            let closest_available = closest_pixel_format(display, screen);
+            error_handler.check()?;
+
+            Err(GlError::NoMatchingConfig { requested: config, closest_available })
         })
     }
 
@@ -251,8 +331,236 @@ impl GlContext {
             error_handler.check().unwrap();
         })
     }
+
+    pub fn pixel_format(&self) -> GlPixelFormat {
+        self.pixel_format
+    }
+
+    pub fn config(&self) -> &GlConfig {
+        &self.config
+    }
+
+    pub fn is_current(&self) -> bool {
+        unsafe { glx::glXGetCurrentContext() == self.context }
+    }
+
+    /// The context that's current on the calling thread, so it can be restored afterwards.
+    pub fn current_context(&self) -> CurrentContext {
+        unsafe {
+            let display = glx::glXGetCurrentDisplay();
+            CurrentContext {
+                // Releasing the context needs a display, even if no context was current
+                display: if display.is_null() { self.display } else { display },
+                drawable: glx::glXGetCurrentDrawable(),
+                context: glx::glXGetCurrentContext(),
+            }
+        }
+    }
+
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), GlError> {
+        if interval < 0 && !self.adaptive_swap_interval {
+            return Err(GlError::SwapIntervalNotSupported);
+        }
+
+        errors::XErrorHandler::handle(self.display, |error_handler| {
+            unsafe { (self.swap_interval_ext)(self.display, self.window, interval) };
+            error_handler.check().map_err(|_| GlError::SwapIntervalNotSupported)
+        })
+    }
+
+    pub fn swap_interval(&self) -> i32 {
+        let query = |attribute| {
+            let mut value = 0;
+            unsafe { glx::glXQueryDrawable(self.display, self.window, attribute, &mut value) };
+            value as i32
+        };
+
+        // The drawable stores the interval's absolute value, and adaptive vsync separately
+        let interval = query(glx::ext::GLX_SWAP_INTERVAL_EXT);
+        if self.adaptive_swap_interval && query(GLX_LATE_SWAPS_TEAR_EXT) != 0 {
+            -interval
+        } else {
+            interval
+        }
+    }
+
+    pub fn supports_adaptive_swap_interval(&self) -> bool {
+        self.adaptive_swap_interval
+    }
+
+    /// Create a context with the same framebuffer config, version, and profile that shares its
+    /// objects with this one. It's made current with a 1x1 pbuffer if the framebuffer config
+    /// supports them, and without a drawable otherwise, which `GLX_ARB_create_context` allows for
+    /// OpenGL 3.0 and later.
+    pub fn create_shared(&self) -> Result<OffscreenGlContext, GlError> {
+        unsafe {
+            errors::XErrorHandler::handle(self.display, |error_handler| {
+                #[allow(non_snake_case)]
+                let glXCreateContextAttribsARB: GlXCreateContextAttribsARB = {
+                    let addr = get_proc_address("glXCreateContextAttribsARB");
+                    if addr.is_null() {
+                        return Err(GlError::CreationFailed(
+                            CreationFailedError::GetProcAddressFailed,
+                        ));
+                    } else {
+                        std::mem::transmute::<*const c_void, GlXCreateContextAttribsARB>(addr)
+                    }
+                };
+
+                let ctx_attribs = context_attribs(self.config.version, self.config.profile);
+                let context = glXCreateContextAttribsARB(
+                    self.display,
+                    self.fb_config,
+                    self.context,
+                    1,
+                    ctx_attribs.as_ptr(),
+                );
+                error_handler.check()?;
+                if context.is_null() {
+                    return Err(GlError::CreationFailed(CreationFailedError::SharedContextFailed));
+                }
+
+                let mut drawable_type = 0;
+                glx::glXGetFBConfigAttrib(
+                    self.display,
+                    self.fb_config,
+                    glx::GLX_DRAWABLE_TYPE,
+                    &mut drawable_type,
+                );
+                let pbuffer = if drawable_type & glx::GLX_PBUFFER_BIT != 0 {
+                    #[rustfmt::skip]
+                    let pbuffer_attribs = [
+                        glx::GLX_PBUFFER_WIDTH, 1,
+                        glx::GLX_PBUFFER_HEIGHT, 1,
+                        0,
+                    ];
+
+                    glx::glXCreatePbuffer(self.display, self.fb_config, pbuffer_attribs.as_ptr())
+                } else {
+                    0
+                };
+                if let Err(error) = error_handler.check() {
+                    glx::glXDestroyContext(self.display, context);
+                    return Err(error.into());
+                }
+
+                Ok(OffscreenGlContext { display: self.display, pbuffer, context })
+            })
+        }
+    }
+}
+
+/// See [`crate::gl::OffscreenGlContext`].
+pub struct OffscreenGlContext {
+    display: *mut xlib::_XDisplay,
+    /// A 1x1 pbuffer, or 0 if the context is made current without a drawable.
+    pbuffer: glx::GLXPbuffer,
+    context: glx::GLXContext,
+}
+
+impl OffscreenGlContext {
+    pub unsafe fn make_current(&self) {
+        errors::XErrorHandler::handle(self.display, |error_handler| {
+            let res =
+                glx::glXMakeContextCurrent(self.display, self.pbuffer, self.pbuffer, self.context);
+            error_handler.check().unwrap();
+            if res == 0 {
+                panic!("make_current failed")
+            }
+        })
+    }
+
+    pub unsafe fn make_not_current(&self) {
+        errors::XErrorHandler::handle(self.display, |error_handler| {
+            let res = glx::glXMakeContextCurrent(self.display, 0, 0, std::ptr::null_mut());
+            error_handler.check().unwrap();
+            if res == 0 {
+                panic!("make_not_current failed")
+            }
+        })
+    }
+
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        get_proc_address(symbol)
+    }
+}
+
+impl Drop for OffscreenGlContext {
+    fn drop(&mut self) {
+        unsafe {
+            if glx::glXGetCurrentContext() == self.context {
+                glx::glXMakeContextCurrent(self.display, 0, 0, std::ptr::null_mut());
+            }
+            glx::glXDestroyContext(self.display, self.context);
+            if self.pbuffer != 0 {
+                glx::glXDestroyPbuffer(self.display, self.pbuffer);
+            }
+        }
+    }
+}
+
+impl FbConfig {
+    /// Read the attributes of the framebuffer config `glXChooseFBConfig()` picked.
+    unsafe fn pixel_format(&self, display: *mut xlib::_XDisplay) -> GlPixelFormat {
+        fb_config_pixel_format(display, self.fb_config)
+    }
+}
+
+/// The pixel format of the framebuffer config `glXChooseFBConfig()` picks when it's asked for
+/// nothing more than a window that can be drawn to with OpenGL.
+unsafe fn closest_pixel_format(
+    display: *mut xlib::_XDisplay, screen: c_int,
+) -> Option<GlPixelFormat> {
+    #[rustfmt::skip]
+    let fb_attribs = [
+        glx::GLX_X_RENDERABLE, 1,
+        glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
+        glx::GLX_RENDER_TYPE, glx::GLX_RGBA_BIT,
+        0,
+    ];
+
+    let mut n_configs = 0;
+    let fb_configs = glx::glXChooseFBConfig(display, screen, fb_attribs.as_ptr(), &mut n_configs);
+    if n_configs <= 0 || fb_configs.is_null() {
+        return None;
+    }
+
+    let pixel_format = fb_config_pixel_format(display, *fb_configs);
+    xlib::XFree(fb_configs.cast());
+
+    Some(pixel_format)
+}
+
+unsafe fn fb_config_pixel_format(
+    display: *mut xlib::_XDisplay, fb_config: glx::GLXFBConfig,
+) -> GlPixelFormat {
+    let attrib = |attribute: c_int| {
+        let mut value = 0;
+        glx::glXGetFBConfigAttrib(display, fb_config, attribute, &mut value);
+        value.clamp(0, u8::MAX as c_int) as u8
+    };
+
+    GlPixelFormat {
+        color_bits: attrib(glx::GLX_RED_SIZE)
+            + attrib(glx::GLX_GREEN_SIZE)
+            + attrib(glx::GLX_BLUE_SIZE),
+        alpha_bits: attrib(glx::GLX_ALPHA_SIZE),
+        depth_bits: attrib(glx::GLX_DEPTH_SIZE),
+        stencil_bits: attrib(glx::GLX_STENCIL_SIZE),
+        samples: (attrib(glx::GLX_SAMPLE_BUFFERS) > 0).then(|| attrib(glx::GLX_SAMPLES)),
+        double_buffer: attrib(glx::GLX_DOUBLEBUFFER) != 0,
+    }
 }
 
 impl Drop for GlContext {
-    fn drop(&mut self) {}
+    fn drop(&mut self) {
+        unsafe {
+            // Otherwise the context would only be destroyed once it's no longer current, which
+            // won't happen on the window's thread
+            if glx::glXGetCurrentContext() == self.context {
+                glx::glXMakeCurrent(self.display, 0, std::ptr::null_mut());
+            }
+            glx::glXDestroyContext(self.display, self.context);
+        }
+    }
 }
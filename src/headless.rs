@@ -0,0 +1,252 @@
+//! The backend used when the `windowing` feature is disabled. None of these types can be
+//! constructed, so they only exist to give [`crate::EventContext`] and [`crate::WindowHandle`] the
+//! same API as on the real platforms. Opening a window panics.
+
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use keyboard_types::Code;
+
+use crate::event_clock::EventClock;
+use crate::platform_handle::{PlatformDisplayHandle, PlatformWindowHandle};
+use crate::user_data::UserData;
+use crate::{
+    AttentionType, CursorIcon, CursorId, Data, DialogId, DragImage, DropEffect, Error,
+    FileDialogOptions, MouseCursor, Point, Rect, Region, Size, SurfaceInfo, WindowHandler,
+    WindowId, WindowOpenOptions, WindowScalePolicy,
+};
+
+const NO_WINDOWING: &str = "baseview was compiled without the `windowing` feature";
+
+/// Uninhabited, so every method below is unreachable.
+#[derive(Clone, Copy)]
+enum Never {}
+
+pub struct WindowHandle {
+    never: Never,
+    // Ensure handle is !Send
+    _phantom: PhantomData<*mut ()>,
+}
+
+impl WindowHandle {
+    pub fn close(&mut self) {
+        match self.never {}
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn id(&self) -> WindowId {
+        match self.never {}
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> Option<PlatformWindowHandle> {
+        match self.never {}
+    }
+
+    pub fn remote(&self) -> RemoteWindowHandle {
+        match self.never {}
+    }
+
+    pub fn trigger_frame(&self) {
+        match self.never {}
+    }
+}
+
+#[derive(Clone)]
+pub struct RemoteWindowHandle {
+    never: Never,
+}
+
+impl RemoteWindowHandle {
+    pub fn close(&self) {
+        match self.never {}
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn id(&self) -> WindowId {
+        match self.never {}
+    }
+}
+
+pub struct Window {
+    never: Never,
+}
+
+impl Window {
+    pub fn open_parented<H, B>(
+        _parent: PlatformWindowHandle, _options: WindowOpenOptions, _build: B,
+    ) -> Result<WindowHandle, Error>
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: Send + 'static,
+    {
+        panic!("{}", NO_WINDOWING)
+    }
+
+    pub fn open_as_if_parented<H, B>(_options: WindowOpenOptions, _build: B) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: Send + 'static,
+    {
+        panic!("{}", NO_WINDOWING)
+    }
+
+    pub fn open_blocking<H, B>(_options: WindowOpenOptions, _build: B)
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: 'static,
+    {
+        panic!("{}", NO_WINDOWING)
+    }
+
+    pub fn set_mouse_cursor(&mut self, _mouse_cursor: MouseCursor) {
+        match self.never {}
+    }
+
+    pub fn set_cursor_regions(&mut self, _regions: Vec<(Rect, MouseCursor)>) {
+        match self.never {}
+    }
+
+    pub fn set_cursor_confined(&mut self, _confined: bool) {
+        match self.never {}
+    }
+
+    pub fn register_cursor(&mut self, _icon: CursorIcon) -> CursorId {
+        match self.never {}
+    }
+
+    pub fn close(&mut self) {
+        match self.never {}
+    }
+
+    pub fn cancel_close(&mut self) {
+        match self.never {}
+    }
+
+    pub fn set_opacity(&mut self, _opacity: f64) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    pub fn open_file_dialog(&mut self, _options: FileDialogOptions) -> Result<DialogId, Error> {
+        match self.never {}
+    }
+
+    pub fn request_attention(&mut self, _attention: AttentionType) {
+        match self.never {}
+    }
+
+    pub fn stop_attention(&mut self) {
+        match self.never {}
+    }
+
+    pub fn set_animating(&mut self, _animating: bool) {
+        match self.never {}
+    }
+
+    #[cfg(feature = "accesskit")]
+    pub fn update_accesskit_tree(&mut self, _update: impl FnOnce() -> accesskit::TreeUpdate) {
+        match self.never {}
+    }
+
+    pub fn invalidate_rect(&mut self, _rect: Rect) {
+        match self.never {}
+    }
+
+    pub fn damage(&self) -> Region {
+        match self.never {}
+    }
+
+    pub fn set_frame_interval(&mut self, _interval: Duration) {
+        match self.never {}
+    }
+
+    pub fn pause_frames(&mut self, _paused: bool) {
+        match self.never {}
+    }
+
+    pub fn resize(&mut self, _size: Size) {
+        match self.never {}
+    }
+
+    pub fn set_scale_policy(&mut self, _policy: WindowScalePolicy) {
+        match self.never {}
+    }
+
+    pub fn set_scale_override(&mut self, _scale: Option<f64>) {
+        match self.never {}
+    }
+
+    pub fn set_position(&mut self, _position: Point) {
+        match self.never {}
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> PlatformWindowHandle {
+        match self.never {}
+    }
+
+    pub(crate) fn platform_display_handle(&self) -> PlatformDisplayHandle {
+        match self.never {}
+    }
+
+    pub fn id(&self) -> WindowId {
+        match self.never {}
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        match self.never {}
+    }
+
+    pub(crate) fn event_clock(&self) -> &EventClock {
+        match self.never {}
+    }
+
+    pub(crate) fn user_data(&self) -> &UserData {
+        match self.never {}
+    }
+
+    pub fn surface_info(&self) -> SurfaceInfo {
+        match self.never {}
+    }
+
+    pub fn start_drag(&self, _data: Data, _effect: DropEffect, _image: Option<DragImage>) {
+        match self.never {}
+    }
+
+    pub fn drag_and_drop_available(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn is_visible(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn has_focus(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        match self.never {}
+    }
+
+    pub fn set_drop_effect(&mut self, _effect: Option<DropEffect>) {
+        match self.never {}
+    }
+
+    pub fn drop_effect(&self) -> Option<DropEffect> {
+        match self.never {}
+    }
+}
+
+pub fn copy_to_clipboard(_data: &str) {}
@@ -0,0 +1,98 @@
+//! Bookkeeping for the mouse buttons and keys the handler currently believes to be held down. When
+//! the window loses focus or its mouse capture in the middle of a gesture, the platform will never
+//! send the matching release events, so they are synthesized from this instead.
+
+use keyboard_types::{Code, KeyState, KeyboardEvent, Modifiers};
+
+use crate::{Event, MouseButton, MouseButtonSet, MouseEvent};
+
+#[derive(Debug, Default)]
+pub(crate) struct HeldInputs {
+    mouse_buttons: Vec<MouseButton>,
+    /// The key down events for all keys that haven't been released yet.
+    keys: Vec<KeyboardEvent>,
+}
+
+impl HeldInputs {
+    /// Update the held buttons and keys with an event that's about to be sent to the handler.
+    pub(crate) fn track(&mut self, event: &Event) {
+        match event {
+            Event::Mouse(MouseEvent::ButtonPressed { button, .. })
+                if !self.mouse_buttons.contains(button) =>
+            {
+                self.mouse_buttons.push(*button);
+            }
+            Event::Mouse(MouseEvent::ButtonReleased { button, .. }) => {
+                self.mouse_buttons.retain(|held| held != button);
+            }
+            Event::Keyboard(event) if event.state == KeyState::Up => {
+                self.keys.retain(|held| held.code != event.code);
+            }
+            Event::Keyboard(event) if !self.keys.iter().any(|held| held.code == event.code) => {
+                self.keys.push(event.clone());
+            }
+            _ => (),
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    pub(crate) fn any_mouse_button_held(&self) -> bool {
+        !self.mouse_buttons.is_empty()
+    }
+
+    /// The held mouse buttons. Backends should prefer the platform's own button state where it
+    /// has one, since that also includes presses that happened outside of the window.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn mouse_buttons(&self) -> MouseButtonSet {
+        self.mouse_buttons.iter().copied().map(MouseButtonSet::from_button).collect()
+    }
+
+    /// The held keys, in the order they were pressed in. See
+    /// [`crate::EventContext::pressed_keys()`].
+    pub(crate) fn pressed_keys(&self) -> Vec<Code> {
+        self.keys.iter().map(|event| event.code).collect()
+    }
+
+    /// Forget about all held mouse buttons, returning the `ButtonReleased` events the handler
+    /// still needs to receive.
+    pub(crate) fn release_mouse_buttons(&mut self) -> Vec<Event> {
+        let mut buttons: MouseButtonSet =
+            self.mouse_buttons.iter().copied().map(MouseButtonSet::from_button).collect();
+
+        self.mouse_buttons
+            .drain(..)
+            .map(|button| {
+                buttons.remove(MouseButtonSet::from_button(button));
+                Event::Mouse(MouseEvent::ButtonReleased {
+                    button,
+                    modifiers: Modifiers::empty(),
+                    buttons,
+                })
+            })
+            .collect()
+    }
+
+    /// Forget about all held keys, returning the key up events the handler still needs to receive.
+    pub(crate) fn release_keys(&mut self) -> Vec<Event> {
+        self.keys
+            .drain(..)
+            .map(|event| {
+                Event::Keyboard(KeyboardEvent {
+                    state: KeyState::Up,
+                    modifiers: Modifiers::empty(),
+                    repeat: false,
+                    is_composing: false,
+                    ..event
+                })
+            })
+            .collect()
+    }
+
+    /// The combination of [`Self::release_mouse_buttons()`] and [`Self::release_keys()`].
+    pub(crate) fn release_all(&mut self) -> Vec<Event> {
+        let mut events = self.release_mouse_buttons();
+        events.extend(self.release_keys());
+
+        events
+    }
+}
@@ -1,24 +1,83 @@
-#[cfg(target_os = "macos")]
+// Most of the crate-private helpers are only used by the platform backends
+#![cfg_attr(not(feature = "windowing"), allow(dead_code))]
+
+// This needs to come first so the macros can be used in the other modules
+#[macro_use]
+mod trace;
+
+#[cfg(not(feature = "windowing"))]
+mod headless;
+#[cfg(all(feature = "windowing", target_os = "macos"))]
 mod macos;
-#[cfg(target_os = "windows")]
+#[cfg(all(feature = "windowing", target_os = "windows"))]
 mod win;
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "windowing", target_os = "linux"))]
 mod x11;
 
+#[cfg(feature = "accesskit")]
+mod accessibility;
+#[cfg(feature = "async")]
+mod async_adapter;
+mod channel_handler;
 mod clipboard;
+mod drop_effect;
+mod error;
 mod event;
+mod event_clock;
+mod file_dialog;
+mod held_inputs;
 mod keyboard;
+mod lifecycle;
+mod metrics;
 mod mouse_cursor;
+pub mod platform;
+mod platform_handle;
+mod region;
+mod shortcut;
+mod surface_info;
+mod user_data;
 mod window;
 mod window_info;
 mod window_open_options;
+mod x11_property;
 
 #[cfg(feature = "opengl")]
 pub mod gl;
+#[cfg(feature = "winit-compat")]
+pub mod winit_compat;
+
+#[cfg(not(any(feature = "rwh_05", feature = "rwh_06")))]
+compile_error!("At least one of the `rwh_05` or `rwh_06` features needs to be enabled");
+
+#[cfg(feature = "rwh_05")]
+pub use rwh_05;
+#[cfg(feature = "rwh_06")]
+pub use rwh_06;
+
+// The accessibility events and tree updates use these types
+#[cfg(feature = "accesskit")]
+pub use accesskit;
+
+// The keyboard events use these types, so this makes sure the same version is used
+pub use keyboard_types;
 
+#[cfg(feature = "accesskit")]
+pub use accessibility::AccessKitEvent;
+#[cfg(feature = "async")]
+pub use async_adapter::{AsyncWindowAdapter, FrameStream, WindowCommander, WindowEventStream};
+pub use channel_handler::{ChannelHandler, MessageHandler};
 pub use clipboard::*;
+pub use drop_effect::{DragImage, DropEffect};
+pub use error::{DropError, Error, X11Error};
 pub use event::*;
-pub use mouse_cursor::MouseCursor;
+pub use file_dialog::{DialogId, DialogResult, FileDialogKind, FileDialogOptions, FileFilter};
+#[cfg(feature = "metrics")]
+pub use metrics::{EventCounts, WindowMetrics};
+pub use mouse_cursor::{CursorIcon, CursorId, MouseCursor};
+pub use region::Region;
+pub use shortcut::Shortcut;
+pub use surface_info::SurfaceInfo;
 pub use window::*;
 pub use window_info::*;
 pub use window_open_options::*;
+pub use x11_property::X11PropertyValue;
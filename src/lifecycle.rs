@@ -0,0 +1,174 @@
+#[cfg(debug_assertions)]
+use crate::{Event, EventContext, EventStatus, WindowEvent, WindowHandler};
+
+/// Where a window is in its lifecycle. Every backend keeps the same contract with the handler: it
+/// receives at least one `Resized` event before its first frame, and nothing at all after
+/// `WillClose`. Other events, like the initial cursor position, may arrive before the first
+/// `Resized` event.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lifecycle {
+    /// The handler was built, but it hasn't been sent its initial `Resized` event yet. Events can
+    /// be sent, but no frames can be drawn.
+    #[default]
+    Created,
+    /// The handler was sent a `Resized` event, so frames can be drawn.
+    Ready,
+    /// The window's handle was closed from outside of the handler, and the host may already have
+    /// freed resources the handler uses. Only `WillClose` can still be sent. Only the X11 backend
+    /// notices closed handles asynchronously, so the others never get here.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    ClosePending,
+    /// The handler is handling `WillClose`.
+    Closing,
+    /// The handler has received `WillClose`, and it won't be called again.
+    Closed,
+}
+
+impl Lifecycle {
+    pub fn can_draw(self) -> bool {
+        self == Lifecycle::Ready
+    }
+
+    pub fn can_send_events(self) -> bool {
+        matches!(self, Lifecycle::Created | Lifecycle::Ready)
+    }
+
+    pub fn can_send_will_close(self) -> bool {
+        matches!(self, Lifecycle::Created | Lifecycle::Ready | Lifecycle::ClosePending)
+    }
+
+    /// Record that the window's handle was closed from outside of the handler. Returns `false` if
+    /// the handler was already told that the window is closing.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn handle_closed(&mut self) -> bool {
+        if !self.can_send_events() {
+            return false;
+        }
+
+        *self = Lifecycle::ClosePending;
+        true
+    }
+
+    /// Record that the handler is about to receive `event`.
+    #[cfg(debug_assertions)]
+    pub fn track(&mut self, event: &Event) {
+        match (*self, event) {
+            (Lifecycle::Created, Event::Window(WindowEvent::Resized { .. })) => {
+                *self = Lifecycle::Ready
+            }
+            (
+                Lifecycle::Created | Lifecycle::Ready | Lifecycle::ClosePending,
+                Event::Window(WindowEvent::WillClose),
+            ) => *self = Lifecycle::Closing,
+            _ => (),
+        }
+    }
+}
+
+/// Wrap the window handler so debug builds catch backends that break the lifecycle contract. Like
+/// [`crate::trace::instrument_handler()`], this sees every call to the handler.
+#[cfg(debug_assertions)]
+pub(crate) fn check_lifecycle<H: WindowHandler>(handler: H) -> LifecycleChecker<H> {
+    LifecycleChecker { handler, lifecycle: Lifecycle::Created }
+}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn check_lifecycle<H>(handler: H) -> H {
+    handler
+}
+
+#[cfg(debug_assertions)]
+pub(crate) struct LifecycleChecker<H> {
+    handler: H,
+    lifecycle: Lifecycle,
+}
+
+#[cfg(debug_assertions)]
+impl<H: WindowHandler> WindowHandler for LifecycleChecker<H> {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        debug_assert!(
+            self.lifecycle.can_draw(),
+            "a frame was drawn while the window was {:?}",
+            self.lifecycle
+        );
+
+        self.handler.on_frame(window);
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        debug_assert!(
+            self.lifecycle.can_send_events(),
+            "{} was sent while the window was {:?}",
+            event,
+            self.lifecycle
+        );
+
+        self.lifecycle.track(&event);
+        let status = self.handler.on_event(window, event);
+        if self.lifecycle == Lifecycle::Closing {
+            self.lifecycle = Lifecycle::Closed;
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_pending_only_accepts_will_close() {
+        for mut lifecycle in [Lifecycle::Created, Lifecycle::Ready] {
+            assert!(lifecycle.handle_closed());
+            assert_eq!(lifecycle, Lifecycle::ClosePending);
+        }
+
+        let mut lifecycle = Lifecycle::ClosePending;
+        assert!(!lifecycle.can_draw());
+        assert!(!lifecycle.can_send_events());
+        assert!(lifecycle.can_send_will_close());
+
+        // Closing the handle again doesn't change anything
+        assert!(!lifecycle.handle_closed());
+        assert_eq!(lifecycle, Lifecycle::ClosePending);
+    }
+
+    #[test]
+    fn will_close_is_only_sent_once() {
+        for lifecycle in [Lifecycle::Created, Lifecycle::Ready, Lifecycle::ClosePending] {
+            assert!(lifecycle.can_send_will_close(), "{:?}", lifecycle);
+        }
+
+        for mut lifecycle in [Lifecycle::Closing, Lifecycle::Closed] {
+            assert!(!lifecycle.can_send_events(), "{:?}", lifecycle);
+            assert!(!lifecycle.can_send_will_close(), "{:?}", lifecycle);
+
+            // The handle can still be closed while the handler is handling `WillClose`
+            assert!(!lifecycle.handle_closed());
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn tracked_transitions() {
+        let resized = Event::Window(WindowEvent::Resized {
+            new: crate::WindowInfo::from_logical_size(crate::Size::new(100.0, 100.0), 1.0),
+            old: crate::WindowInfo::from_logical_size(crate::Size::new(100.0, 100.0), 1.0),
+        });
+        let will_close = Event::Window(WindowEvent::WillClose);
+
+        let mut lifecycle = Lifecycle::Created;
+        lifecycle.track(&resized);
+        assert_eq!(lifecycle, Lifecycle::Ready);
+        lifecycle.track(&will_close);
+        assert_eq!(lifecycle, Lifecycle::Closing);
+
+        let mut lifecycle = Lifecycle::Ready;
+        lifecycle.handle_closed();
+        lifecycle.track(&resized);
+        assert_eq!(lifecycle, Lifecycle::ClosePending);
+        lifecycle.track(&will_close);
+        assert_eq!(lifecycle, Lifecycle::Closing);
+    }
+}
@@ -0,0 +1,140 @@
+//! Vsync-aligned frames using `CVDisplayLink`. The display link calls its callback on a separate
+//! high priority thread, so the frame itself is dispatched to the main thread from there.
+
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::Arc;
+
+use super::window::WindowState;
+
+type CVDisplayLinkRef = *mut c_void;
+type CVReturn = i32;
+type CVOptionFlags = u64;
+type CVDisplayLinkOutputCallback = extern "C" fn(
+    display_link: CVDisplayLinkRef,
+    in_now: *const c_void,
+    in_output_time: *const c_void,
+    flags_in: CVOptionFlags,
+    flags_out: *mut CVOptionFlags,
+    display_link_context: *mut c_void,
+) -> CVReturn;
+
+const K_CV_RETURN_SUCCESS: CVReturn = 0;
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern "C" {
+    fn CVDisplayLinkCreateWithActiveCGDisplays(display_link_out: *mut CVDisplayLinkRef)
+        -> CVReturn;
+    fn CVDisplayLinkSetOutputCallback(
+        display_link: CVDisplayLinkRef, callback: CVDisplayLinkOutputCallback,
+        user_info: *mut c_void,
+    ) -> CVReturn;
+    fn CVDisplayLinkStart(display_link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkStop(display_link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkRelease(display_link: CVDisplayLinkRef);
+}
+
+// These are part of libdispatch, which is always linked on macOS
+extern "C" {
+    static _dispatch_main_q: c_void;
+    fn dispatch_async_f(
+        queue: *const c_void, context: *mut c_void, work: extern "C" fn(*mut c_void),
+    );
+}
+
+/// The state shared between the display link's thread and the main thread.
+struct Shared {
+    /// Cleared when the display link is dropped, since frames may still be queued on the main
+    /// thread at that point.
+    window_state: AtomicPtr<WindowState>,
+    /// Set while a frame is queued on the main thread. Vsyncs are skipped until that frame has
+    /// been drawn so frames don't pile up when the handler can't keep up.
+    frame_queued: AtomicBool,
+}
+
+pub(super) struct DisplayLink {
+    display_link: CVDisplayLinkRef,
+    /// The display link's callback holds a pointer to this.
+    shared: Arc<Shared>,
+    running: bool,
+}
+
+impl DisplayLink {
+    /// Create a stopped display link that calls [`WindowState::trigger_frame()`] on the main thread
+    /// on every vsync. Returns `None` if the display link could not be created, in which case the
+    /// frame timer should be used instead.
+    pub(super) unsafe fn new(window_state: *mut WindowState) -> Option<Self> {
+        let mut display_link: CVDisplayLinkRef = ptr::null_mut();
+        if CVDisplayLinkCreateWithActiveCGDisplays(&mut display_link) != K_CV_RETURN_SUCCESS
+            || display_link.is_null()
+        {
+            return None;
+        }
+
+        let shared = Arc::new(Shared {
+            window_state: AtomicPtr::new(window_state),
+            frame_queued: AtomicBool::new(false),
+        });
+        let context = Arc::as_ptr(&shared) as *mut c_void;
+        if CVDisplayLinkSetOutputCallback(display_link, display_link_callback, context)
+            != K_CV_RETURN_SUCCESS
+        {
+            CVDisplayLinkRelease(display_link);
+            return None;
+        }
+
+        Some(DisplayLink { display_link, shared, running: false })
+    }
+
+    pub(super) fn start(&mut self) {
+        if !self.running {
+            self.running = unsafe { CVDisplayLinkStart(self.display_link) } == K_CV_RETURN_SUCCESS;
+        }
+    }
+
+    pub(super) fn stop(&mut self) {
+        if self.running {
+            unsafe { CVDisplayLinkStop(self.display_link) };
+            self.running = false;
+        }
+    }
+}
+
+impl Drop for DisplayLink {
+    fn drop(&mut self) {
+        // `CVDisplayLinkStop()` waits for a callback that's currently running to finish, so the
+        // shared state is no longer used by the display link's thread after this
+        unsafe { CVDisplayLinkStop(self.display_link) };
+        self.shared.window_state.store(ptr::null_mut(), Ordering::Release);
+        unsafe { CVDisplayLinkRelease(self.display_link) };
+    }
+}
+
+extern "C" fn display_link_callback(
+    _display_link: CVDisplayLinkRef, _in_now: *const c_void, _in_output_time: *const c_void,
+    _flags_in: CVOptionFlags, _flags_out: *mut CVOptionFlags, display_link_context: *mut c_void,
+) -> CVReturn {
+    let shared_ptr = display_link_context as *const Shared;
+    let shared = unsafe { &*shared_ptr };
+    if !shared.frame_queued.swap(true, Ordering::AcqRel) {
+        unsafe {
+            // The queued frame keeps the shared state alive, since the display link may already
+            // have been dropped by the time it runs
+            Arc::increment_strong_count(shared_ptr);
+            dispatch_async_f(&_dispatch_main_q, shared_ptr as *mut c_void, frame_on_main_thread);
+        }
+    }
+
+    K_CV_RETURN_SUCCESS
+}
+
+extern "C" fn frame_on_main_thread(context: *mut c_void) {
+    let shared = unsafe { Arc::from_raw(context as *const Shared) };
+    shared.frame_queued.store(false, Ordering::Release);
+
+    let window_state = shared.window_state.load(Ordering::Acquire);
+    if !window_state.is_null() {
+        unsafe { (*window_state).trigger_frame() };
+    }
+}
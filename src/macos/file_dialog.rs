@@ -0,0 +1,139 @@
+use std::ffi::{c_char, c_void, CStr, CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use block::ConcreteBlock;
+use cocoa::base::{id, nil, NO, YES};
+use cocoa::foundation::{NSArray, NSInteger, NSString, NSUInteger};
+use objc::{class, msg_send, rc::StrongPtr, sel, sel_impl};
+
+use super::view::BASEVIEW_STATE_IVAR;
+use super::window::WindowState;
+use crate::{DialogId, DialogResult, Event, FileDialogKind, FileDialogOptions};
+
+/// `NSModalResponseOK`, which the cocoa crate doesn't define.
+const NS_MODAL_RESPONSE_OK: NSInteger = 1;
+
+/// Show an `NSOpenPanel` or `NSSavePanel` for the view. The panel is attached to `ns_window` as a
+/// sheet if that's set, and it's shown as a separate window otherwise. Either way this returns
+/// right away, and the view's handler receives the result once the panel has been closed.
+pub(super) unsafe fn begin(
+    ns_view: id, ns_window: Option<id>, dialog_id: DialogId, options: &FileDialogOptions,
+) {
+    let panel: id = match options.kind {
+        FileDialogKind::Save => msg_send![class!(NSSavePanel), savePanel],
+        FileDialogKind::Open | FileDialogKind::OpenDirectory => {
+            msg_send![class!(NSOpenPanel), openPanel]
+        }
+    };
+    configure(panel, options);
+
+    // The view may be closed while the panel is open, so the completion handler checks whether it
+    // still has a window state before sending the result
+    let view = StrongPtr::retain(ns_view);
+    let panel = StrongPtr::retain(panel);
+    let is_save_panel = options.kind == FileDialogKind::Save;
+    let completion_panel = panel.clone();
+    let completion_handler = ConcreteBlock::new(move |response: NSInteger| {
+        let paths = if response == NS_MODAL_RESPONSE_OK {
+            selected_paths(*completion_panel, is_save_panel)
+        } else {
+            Vec::new()
+        };
+
+        let state_ptr: *mut c_void = *(**view).get_ivar(BASEVIEW_STATE_IVAR);
+        if !state_ptr.is_null() {
+            let result = DialogResult { id: dialog_id, paths };
+            WindowState::from_field(&**view).trigger_event(Event::Dialog(result));
+        }
+    })
+    .copy();
+
+    match ns_window {
+        Some(ns_window) => {
+            let () = msg_send![
+                *panel,
+                beginSheetModalForWindow: ns_window
+                completionHandler: &*completion_handler
+            ];
+        }
+        None => {
+            let () = msg_send![*panel, beginWithCompletionHandler: &*completion_handler];
+        }
+    }
+}
+
+unsafe fn configure(panel: id, options: &FileDialogOptions) {
+    // Sheets don't have a title bar, so the title is also shown as the panel's message
+    if let Some(title) = &options.title {
+        let title = NSString::alloc(nil).init_str(title).autorelease();
+        let () = msg_send![panel, setTitle: title];
+        let () = msg_send![panel, setMessage: title];
+    }
+
+    match options.kind {
+        FileDialogKind::Open | FileDialogKind::OpenDirectory => {
+            let (files, directories) = match options.kind {
+                FileDialogKind::OpenDirectory => (NO, YES),
+                _ => (YES, NO),
+            };
+            let multiple = if options.multiple { YES } else { NO };
+            let () = msg_send![panel, setCanChooseFiles: files];
+            let () = msg_send![panel, setCanChooseDirectories: directories];
+            let () = msg_send![panel, setAllowsMultipleSelection: multiple];
+        }
+        FileDialogKind::Save => {
+            if let Some(file_name) = &options.file_name {
+                let file_name = NSString::alloc(nil).init_str(file_name).autorelease();
+                let () = msg_send![panel, setNameFieldStringValue: file_name];
+            }
+        }
+    }
+
+    // Panels don't have separate filters, so all of their extensions are allowed at once
+    if options.kind != FileDialogKind::OpenDirectory && !options.filters.is_empty() {
+        let extensions: Vec<id> = options
+            .filters
+            .iter()
+            .flat_map(|filter| &filter.extensions)
+            .map(|extension| NSString::alloc(nil).init_str(extension).autorelease())
+            .collect();
+        let extensions = NSArray::arrayWithObjects(nil, &extensions);
+        let () = msg_send![panel, setAllowedFileTypes: extensions];
+    }
+
+    if let Some(directory) = &options.directory {
+        if let Ok(path) = CString::new(directory.as_os_str().as_bytes()) {
+            let url: id = msg_send![
+                class!(NSURL),
+                fileURLWithFileSystemRepresentation: path.as_ptr()
+                isDirectory: YES
+                relativeToURL: nil
+            ];
+            let () = msg_send![panel, setDirectoryURL: url];
+        }
+    }
+}
+
+/// The paths of the URLs the user picked in the panel.
+unsafe fn selected_paths(panel: id, is_save_panel: bool) -> Vec<PathBuf> {
+    let urls: Vec<id> = if is_save_panel {
+        vec![msg_send![panel, URL]]
+    } else {
+        let urls: id = msg_send![panel, URLs];
+        let count: NSUInteger = msg_send![urls, count];
+        (0..count).map(|index| -> id { msg_send![urls, objectAtIndex: index] }).collect()
+    };
+
+    urls.into_iter()
+        .filter(|&url| url != nil)
+        .filter_map(|url| {
+            let path: *const c_char = msg_send![url, fileSystemRepresentation];
+            if path.is_null() {
+                return None;
+            }
+
+            Some(PathBuf::from(OsStr::from_bytes(CStr::from_ptr(path).to_bytes())))
+        })
+        .collect()
+}
@@ -28,7 +28,14 @@ use crate::keyboard::code_to_location;
 
 pub(crate) fn from_nsstring(s: id) -> String {
     unsafe {
-        let slice = std::slice::from_raw_parts(s.UTF8String() as *const _, s.len());
+        // `UTF8String` returns null for strings that can't be converted, like a lone half of a
+        // surrogate pair
+        let utf8 = s.UTF8String();
+        if utf8.is_null() {
+            return String::new();
+        }
+
+        let slice = std::slice::from_raw_parts(utf8 as *const _, s.len());
         let result = std::str::from_utf8_unchecked(slice);
         result.into()
     }
@@ -273,6 +280,12 @@ impl KeyboardState {
         KeyboardState { last_mods }
     }
 
+    /// Forget about the modifiers that were held down, for instance because the window lost focus
+    /// and won't see them being released.
+    pub(crate) fn reset(&mut self) {
+        self.last_mods = NSEventModifierFlags::empty();
+    }
+
     pub(crate) fn process_native_event(&mut self, event: id) -> Option<KeyboardEvent> {
         unsafe {
             let event_type = event.eventType();
@@ -1,3 +1,5 @@
+mod display_link;
+mod file_dialog;
 mod keyboard;
 mod menu;
 mod view;
@@ -1,8 +1,10 @@
 use std::ffi::c_void;
 
-use cocoa::appkit::{NSEvent, NSFilenamesPboardType, NSView, NSWindow};
+use cocoa::appkit::{NSEvent, NSEventType, NSFilenamesPboardType, NSView};
 use cocoa::base::{id, nil, BOOL, NO, YES};
-use cocoa::foundation::{NSArray, NSInteger, NSPoint, NSRect, NSSize, NSUInteger};
+use cocoa::foundation::{
+    NSArray, NSAutoreleasePool, NSInteger, NSPoint, NSRect, NSSize, NSString, NSUInteger,
+};
 
 use objc::{
     class,
@@ -17,13 +19,16 @@ use uuid::Uuid;
 use super::keyboard::from_nsstring;
 use crate::MouseEvent::{ButtonPressed, ButtonReleased};
 use crate::{
-    Data, Event, EventStatus, MouseButton, MouseEvent, Point, ScrollDelta, Size, WindowEvent,
-    WindowInfo, WindowOpenOptions,
+    Data, DragSourceInfo, DropEffect, DropError, DropPayload, Event, EventStatus, MouseButton,
+    MouseButtonSet, MouseEvent, PhyPoint, Point, ScrollDelta, WindowEvent, WindowOpenOptions,
 };
 
 use super::keyboard::make_modifiers;
 use super::window::WindowState;
 
+#[cfg(feature = "accesskit")]
+use super::window::AccessKit;
+
 pub type NSDragOperation = NSUInteger;
 #[allow(non_upper_case_globals)]
 pub const NSDragOperationNone: NSDragOperation = 0;
@@ -33,14 +38,25 @@ pub const NSDragOperationCopy: NSDragOperation = 1;
 pub const NSDragOperationGeneric: NSDragOperation = 4;
 // #[allow(non_upper_case_globals)]
 // pub const NSDragOperationLink: NSDragOperation = 2;
-// #[allow(non_upper_case_globals)]
-// pub const NSDragOperationMove: NSDragOperation = 16;
+#[allow(non_upper_case_globals)]
+pub const NSDragOperationMove: NSDragOperation = 16;
+
+/// `NSEventSubtypeTabletPoint`, for mouse events that came from a graphics tablet. The cocoa crate's
+/// `NSEventSubtype` enum doesn't contain the mouse event subtypes.
+const NS_EVENT_SUBTYPE_TABLET_POINT: i16 = 1;
 
 #[allow(non_upper_case_globals)]
 pub const NSDraggingContextOutsideApplication: NSInteger = 0;
 #[allow(non_upper_case_globals)]
 pub const NSDraggingContextWithinApplication: NSInteger = 1;
 
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    // Takes a `CGPoint`, which has the same layout as `NSPoint`
+    fn CGWarpMouseCursorPosition(new_cursor_position: NSPoint) -> i32;
+    fn CGAssociateMouseAndMouseCursorPosition(connected: u32) -> i32;
+}
+
 /// Name of the field used to store the `WindowState` pointer.
 pub(super) const BASEVIEW_STATE_IVAR: &str = "baseview_state";
 
@@ -63,9 +79,9 @@ macro_rules! add_simple_mouse_class_method {
 }
 
 /// Similar to [add_simple_mouse_class_method!], but this creates its own event object for the
-/// press/release event and adds the active modifier keys to that event.
+/// press/release event and adds the active modifier keys and mouse buttons to that event.
 macro_rules! add_mouse_button_class_method {
-    ($class:ident, $sel:ident, $event_ty:ident, $button:expr) => {
+    ($class:ident, $sel:ident, $event_ty:ident, $button:expr, $pressed:expr) => {
         #[allow(non_snake_case)]
         extern "C" fn $sel(this: &Object, _: Sel, event: id){
             let state: &mut WindowState = unsafe {
@@ -77,6 +93,7 @@ macro_rules! add_mouse_button_class_method {
             state.trigger_event(Event::Mouse($event_ty {
                 button: $button,
                 modifiers: make_modifiers(modifiers),
+                buttons: unsafe { mouse_buttons_after($button, $pressed) },
             }));
         }
 
@@ -95,6 +112,16 @@ macro_rules! add_simple_keyboard_class_method {
                 WindowState::from_field(this)
             };
 
+            // The handler already ignored this event in `performKeyEquivalent:`
+            if state.take_ignored_key_equivalent(event) {
+                unsafe {
+                    let superclass = msg_send![this, superclass];
+
+                    let () = msg_send![super(this, superclass), $sel:event];
+                }
+                return;
+            }
+
             if let Some(key_event) = state.process_native_key_event(event){
                 let status = state.trigger_event(Event::Keyboard(key_event));
 
@@ -124,10 +151,12 @@ pub(super) unsafe fn create_view(window_options: &WindowOpenOptions) -> id {
 
     view.initWithFrame_(NSRect::new(NSPoint::new(0., 0.), NSSize::new(size.width, size.height)));
 
-    let _: id = msg_send![
-        view,
-        registerForDraggedTypes: NSArray::arrayWithObjects(nil, &[NSFilenamesPboardType])
-    ];
+    if window_options.enable_drag_and_drop {
+        let _: id = msg_send![
+            view,
+            registerForDraggedTypes: NSArray::arrayWithObjects(nil, &[NSFilenamesPboardType])
+        ];
+    }
 
     view
 }
@@ -161,15 +190,34 @@ unsafe fn create_view_class() -> &'static Class {
         sel!(viewWillMoveToWindow:),
         view_will_move_to_window as extern "C" fn(&Object, Sel, id),
     );
+    class.add_method(
+        sel!(viewDidMoveToWindow),
+        view_did_move_to_window as extern "C" fn(&Object, Sel),
+    );
+    class.add_method(sel!(viewDidHide), view_did_hide as extern "C" fn(&Object, Sel));
+    class.add_method(sel!(viewDidUnhide), view_did_unhide as extern "C" fn(&Object, Sel));
+    class.add_method(
+        sel!(windowDidChangeOcclusionState:),
+        window_did_change_occlusion_state as extern "C" fn(&Object, Sel, id),
+    );
     class.add_method(
         sel!(updateTrackingAreas:),
         update_tracking_areas as extern "C" fn(&Object, Sel, id),
     );
+    class.add_method(
+        sel!(windowDidBecomeKey:),
+        window_did_become_key as extern "C" fn(&Object, Sel, id),
+    );
+    class.add_method(
+        sel!(windowDidResignKey:),
+        window_did_resign_key as extern "C" fn(&Object, Sel, id),
+    );
 
     class.add_method(sel!(mouseMoved:), mouse_moved as extern "C" fn(&Object, Sel, id));
     class.add_method(sel!(mouseDragged:), mouse_moved as extern "C" fn(&Object, Sel, id));
     class.add_method(sel!(rightMouseDragged:), mouse_moved as extern "C" fn(&Object, Sel, id));
     class.add_method(sel!(otherMouseDragged:), mouse_moved as extern "C" fn(&Object, Sel, id));
+    class.add_method(sel!(tabletPoint:), tablet_point as extern "C" fn(&Object, Sel, id));
 
     class.add_method(sel!(scrollWheel:), scroll_wheel as extern "C" fn(&Object, Sel, id));
 
@@ -207,27 +255,59 @@ unsafe fn create_view_class() -> &'static Class {
         source_operation_mask_for_dragging_context
             as extern "C" fn(&mut Object, Sel, id, NSInteger) -> NSDragOperation,
     );
+    class.add_method(
+        sel!(draggingSession:movedToPoint:),
+        dragging_session_moved as extern "C" fn(&Object, Sel, id, NSPoint),
+    );
+    class.add_method(
+        sel!(draggingSession:endedAtPoint:operation:),
+        dragging_session_ended as extern "C" fn(&Object, Sel, id, NSPoint, NSDragOperation),
+    );
 
     class.add_method(sel!(mouseDown:), mouse_down as extern "C" fn(&Object, Sel, id));
 
-    add_mouse_button_class_method!(class, mouseUp, ButtonReleased, MouseButton::Left);
-    add_mouse_button_class_method!(class, rightMouseDown, ButtonPressed, MouseButton::Right);
-    add_mouse_button_class_method!(class, rightMouseUp, ButtonReleased, MouseButton::Right);
-    add_mouse_button_class_method!(class, otherMouseDown, ButtonPressed, MouseButton::Middle);
-    add_mouse_button_class_method!(class, otherMouseUp, ButtonReleased, MouseButton::Middle);
+    add_mouse_button_class_method!(class, mouseUp, ButtonReleased, MouseButton::Left, false);
+    add_mouse_button_class_method!(class, rightMouseDown, ButtonPressed, MouseButton::Right, true);
+    add_mouse_button_class_method!(class, rightMouseUp, ButtonReleased, MouseButton::Right, false);
+    class.add_method(sel!(otherMouseDown:), other_mouse_down as extern "C" fn(&Object, Sel, id));
+    class.add_method(sel!(otherMouseUp:), other_mouse_up as extern "C" fn(&Object, Sel, id));
     add_simple_mouse_class_method!(class, mouseEntered, MouseEvent::CursorEntered);
-    add_simple_mouse_class_method!(class, mouseExited, MouseEvent::CursorLeft);
+    class.add_method(sel!(mouseExited:), mouse_exited as extern "C" fn(&Object, Sel, id));
 
     add_simple_keyboard_class_method!(class, keyDown);
     add_simple_keyboard_class_method!(class, keyUp);
     add_simple_keyboard_class_method!(class, flagsChanged);
+    class.add_method(
+        sel!(performKeyEquivalent:),
+        perform_key_equivalent as extern "C" fn(&Object, Sel, id) -> BOOL,
+    );
+
+    #[cfg(feature = "accesskit")]
+    {
+        class.add_method(
+            sel!(accessibilityChildren),
+            accessibility_children as extern "C" fn(&Object, Sel) -> id,
+        );
+        class.add_method(
+            sel!(accessibilityFocusedUIElement),
+            accessibility_focused_ui_element as extern "C" fn(&Object, Sel) -> id,
+        );
+        class.add_method(
+            sel!(accessibilityHitTest:),
+            accessibility_hit_test as extern "C" fn(&Object, Sel, NSPoint) -> id,
+        );
+        class.add_method(
+            sel!(handleAccessKitEvents),
+            handle_accesskit_events as extern "C" fn(&Object, Sel),
+        );
+    }
 
     class.add_ivar::<*mut c_void>(BASEVIEW_STATE_IVAR);
 
     class.register()
 }
 
-extern "C" fn property_yes(_this: &Object, _sel: Sel) -> BOOL {
+pub(super) extern "C" fn property_yes(_this: &Object, _sel: Sel) -> BOOL {
     YES
 }
 
@@ -239,6 +319,33 @@ extern "C" fn accepts_first_mouse(_this: &Object, _sel: Sel, _event: id) -> BOOL
     YES
 }
 
+/// Key presses with the command key go to the main menu before they reach `keyDown:`, so the
+/// handler gets them here first. Returning `NO` lets the menu and the rest of the responder chain
+/// handle the ones the handler ignored.
+extern "C" fn perform_key_equivalent(this: &Object, _sel: Sel, event: id) -> BOOL {
+    // AppKit asks every view in the key window, but only the focused view should receive the keys
+    let focused = unsafe {
+        let window: id = msg_send![this, window];
+        let first_responder: id = msg_send![window, firstResponder];
+        first_responder == this as *const Object as id
+    };
+    if !focused || unsafe { event.eventType() } != NSEventType::NSKeyDown {
+        return NO;
+    }
+
+    let state: &mut WindowState = unsafe { WindowState::from_field(this) };
+    let Some(key_event) = state.process_native_key_event(event) else {
+        return NO;
+    };
+    match state.trigger_event(Event::Keyboard(key_event)) {
+        EventStatus::Captured => YES,
+        EventStatus::Ignored => {
+            state.ignore_key_equivalent(event);
+            NO
+        }
+    }
+}
+
 extern "C" fn release(this: &mut Object, _sel: Sel) {
     // Hack for breaking circular references. We store the value of retainCount
     // after build(), and then when retainCount drops back to that value, we
@@ -273,6 +380,15 @@ extern "C" fn release(this: &mut Object, _sel: Sel) {
 }
 
 extern "C" fn dealloc(this: &mut Object, _sel: Sel) {
+    // Normally the state is freed by `release` before this point. If it's still here the view was
+    // torn down without that hack noticing, and the window still needs to be closed.
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(BASEVIEW_STATE_IVAR);
+        if !state_ptr.is_null() {
+            WindowState::stop_and_free(this);
+        }
+    }
+
     unsafe {
         let class = msg_send![this, class];
 
@@ -286,40 +402,30 @@ extern "C" fn dealloc(this: &mut Object, _sel: Sel) {
 
 extern "C" fn view_did_change_backing_properties(this: &Object, _: Sel, _: id) {
     unsafe {
-        let ns_window: *mut Object = msg_send![this, window];
-
-        let scale_factor: f64 =
-            if ns_window.is_null() { 1.0 } else { NSWindow::backingScaleFactor(ns_window) };
-
         let state: &mut WindowState = WindowState::from_field(this);
 
         let bounds: NSRect = msg_send![this, bounds];
 
-        let new_window_info = WindowInfo::from_logical_size(
-            Size::new(bounds.size.width, bounds.size.height),
-            scale_factor,
-        );
+        let new_window_info = state.view_window_info(bounds.size);
 
         // Only send the event when the window's size has actually changed to be in line with the
         // other platform implementations
         if new_window_info.physical_size() != state.window_info.physical_size() {
-            state.window_info = new_window_info;
-            state.trigger_event(Event::Window(WindowEvent::Resized(new_window_info)));
+            state.update_window_info(new_window_info);
         }
     }
 }
 
 extern "C" fn set_frame_size(this: &Object, _: Sel, size: NSSize) {
     unsafe {
-        let ns_window: *mut Object = msg_send![this, window];
-        let scale_factor: f64 =
-            if ns_window.is_null() { 1.0 } else { NSWindow::backingScaleFactor(ns_window) };
         let state: &mut WindowState = WindowState::from_field(this);
-        let new_window_info =
-            WindowInfo::from_logical_size(Size::new(size.width, size.height), scale_factor);
+        let new_window_info = state.view_window_info(size);
 
-        state.window_info = new_window_info;
-        state.trigger_event(Event::Window(WindowEvent::Resized(new_window_info)));
+        // `Window::resize()` resizes both the view and a standalone window, and resizing the
+        // window sets the view's frame size again
+        if new_window_info.physical_size() != state.window_info.physical_size() {
+            state.update_window_info(new_window_info);
+        }
 
         let superclass = msg_send![this, superclass];
         let () = msg_send![super(this, superclass), setFrameSize: size];
@@ -387,6 +493,42 @@ extern "C" fn view_will_move_to_window(this: &Object, _self: Sel, new_window: id
             let _: () = msg_send![new_window, setAcceptsMouseMovedEvents: YES];
             let _: () = msg_send![new_window, makeFirstResponder: this];
         }
+
+        // The view isn't the window's delegate, so it needs to observe the key window changes
+        // through notifications
+        let notification_center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+        let old_window: id = msg_send![this, window];
+        if old_window != nil {
+            let () = msg_send![notification_center, removeObserver:this name:nil object:old_window];
+        }
+        if new_window != nil {
+            for (selector, name) in [
+                (sel!(windowDidBecomeKey:), "NSWindowDidBecomeKeyNotification"),
+                (sel!(windowDidResignKey:), "NSWindowDidResignKeyNotification"),
+                (
+                    sel!(windowDidChangeOcclusionState:),
+                    "NSWindowDidChangeOcclusionStateNotification",
+                ),
+            ] {
+                let name = NSString::alloc(nil).init_str(name).autorelease();
+                let () = msg_send![
+                    notification_center,
+                    addObserver:this
+                    selector:selector
+                    name:name
+                    object:new_window
+                ];
+            }
+        }
+    }
+
+    // The view is added to its parent before the state exists, and it's removed after the state
+    // has been freed
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(BASEVIEW_STATE_IVAR);
+        if !state_ptr.is_null() {
+            WindowState::from_field(this).set_detached(new_window == nil);
+        }
     }
 
     unsafe {
@@ -396,6 +538,133 @@ extern "C" fn view_will_move_to_window(this: &Object, _self: Sel, new_window: id
     }
 }
 
+/// Let the handler know if adding the view to a window, removing it from its window, hiding it, or
+/// the window being covered up changed the view's visibility.
+fn update_visibility(this: &Object) {
+    // The view is added to its parent before the state exists, and it's removed after the state
+    // has been freed
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(BASEVIEW_STATE_IVAR);
+        if !state_ptr.is_null() {
+            WindowState::from_field(this).update_visibility();
+        }
+    }
+}
+
+extern "C" fn view_did_move_to_window(this: &Object, _: Sel) {
+    update_visibility(this);
+
+    unsafe {
+        let superclass = msg_send![this, superclass];
+
+        let () = msg_send![super(this, superclass), viewDidMoveToWindow];
+    }
+}
+
+extern "C" fn view_did_hide(this: &Object, _: Sel) {
+    update_visibility(this);
+
+    unsafe {
+        let superclass = msg_send![this, superclass];
+
+        let () = msg_send![super(this, superclass), viewDidHide];
+    }
+}
+
+extern "C" fn view_did_unhide(this: &Object, _: Sel) {
+    update_visibility(this);
+
+    unsafe {
+        let superclass = msg_send![this, superclass];
+
+        let () = msg_send![super(this, superclass), viewDidUnhide];
+    }
+}
+
+extern "C" fn window_did_change_occlusion_state(this: &Object, _: Sel, _: id) {
+    update_visibility(this);
+}
+
+extern "C" fn window_did_become_key(this: &Object, _: Sel, _: id) {
+    let state: &mut WindowState = unsafe { WindowState::from_field(this) };
+
+    #[cfg(feature = "accesskit")]
+    state.update_accesskit_focus(true);
+
+    state.trigger_event(Event::Window(WindowEvent::Focused));
+}
+
+/// Call `f` with the view's AccessKit adapter, if the window was opened with AccessKit enabled and
+/// the window state still exists.
+#[cfg(feature = "accesskit")]
+fn with_accesskit<R>(this: &Object, f: impl FnOnce(&mut AccessKit) -> R) -> Option<R> {
+    let state_ptr: *mut c_void = unsafe { *this.get_ivar(BASEVIEW_STATE_IVAR) };
+    if state_ptr.is_null() {
+        return None;
+    }
+
+    let state: &mut WindowState = unsafe { WindowState::from_field(this) };
+    state.window.accesskit.as_mut().map(f)
+}
+
+#[cfg(feature = "accesskit")]
+extern "C" fn accessibility_children(this: &Object, _: Sel) -> id {
+    let children = with_accesskit(this, |accesskit| {
+        let mut activation_handler = accesskit.activation_handler();
+        accesskit.adapter.view_children(&mut activation_handler) as id
+    });
+
+    children.unwrap_or_else(|| unsafe {
+        let superclass = msg_send![this, superclass];
+        msg_send![super(this, superclass), accessibilityChildren]
+    })
+}
+
+#[cfg(feature = "accesskit")]
+extern "C" fn accessibility_focused_ui_element(this: &Object, _: Sel) -> id {
+    let focus = with_accesskit(this, |accesskit| {
+        let mut activation_handler = accesskit.activation_handler();
+        accesskit.adapter.focus(&mut activation_handler) as id
+    });
+
+    focus.unwrap_or_else(|| unsafe {
+        let superclass = msg_send![this, superclass];
+        msg_send![super(this, superclass), accessibilityFocusedUIElement]
+    })
+}
+
+#[cfg(feature = "accesskit")]
+extern "C" fn accessibility_hit_test(this: &Object, _: Sel, point: NSPoint) -> id {
+    let hit = with_accesskit(this, |accesskit| {
+        let mut activation_handler = accesskit.activation_handler();
+        let point = accesskit_macos::NSPoint::new(point.x, point.y);
+        accesskit.adapter.hit_test(point, &mut activation_handler) as id
+    });
+
+    hit.unwrap_or_else(|| unsafe {
+        let superclass = msg_send![this, superclass];
+        msg_send![super(this, superclass), accessibilityHitTest: point]
+    })
+}
+
+/// Scheduled by the adapter's handlers to pass the requests they queued on to the window's
+/// handler.
+#[cfg(feature = "accesskit")]
+extern "C" fn handle_accesskit_events(this: &Object, _: Sel) {
+    let Some(events) = with_accesskit(this, |accesskit| accesskit.take_events()) else { return };
+
+    let state: &mut WindowState = unsafe { WindowState::from_field(this) };
+    for event in events {
+        state.trigger_event(Event::AccessKit(event));
+    }
+}
+
+extern "C" fn window_did_resign_key(this: &Object, _: Sel, _: id) {
+    let state: &mut WindowState = unsafe { WindowState::from_field(this) };
+
+    state.handle_focus_lost();
+}
+
 extern "C" fn update_tracking_areas(this: &Object, _self: Sel, _: id) {
     unsafe {
         let tracking_areas: *mut Object = msg_send![this, trackingAreas];
@@ -414,6 +683,100 @@ extern "C" fn mouse_down(this: &Object, _: Sel, event: id) {
         state.trigger_event(Event::Mouse(ButtonPressed {
             button: MouseButton::Left,
             modifiers: make_modifiers(modifiers),
+            buttons: mouse_buttons_after(MouseButton::Left, true),
+        }));
+        send_pen_moved(state, event);
+    }
+}
+
+/// `otherMouseDown:` and `otherMouseUp:` are sent for every button except for the left and right
+/// buttons. See [`MouseButton::Other`] for how the buttons after the fifth are numbered.
+unsafe fn other_mouse_button(event: id) -> MouseButton {
+    let button_number: NSInteger = msg_send![event, buttonNumber];
+
+    mouse_button(button_number)
+}
+
+/// The mouse button for an `NSEvent`'s `buttonNumber`.
+fn mouse_button(button_number: NSInteger) -> MouseButton {
+    match button_number {
+        0 => MouseButton::Left,
+        1 => MouseButton::Right,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Back,
+        4 => MouseButton::Forward,
+        n => MouseButton::Other((n - 5).min(u8::MAX as NSInteger) as u8),
+    }
+}
+
+/// The mouse buttons in a `pressedMouseButtons` mask. Bit `n` is set while the button with
+/// `buttonNumber` `n` is held down, so the right and middle buttons are swapped compared to
+/// [`MouseButtonSet`].
+const fn mouse_buttons(mask: NSUInteger) -> MouseButtonSet {
+    let first_buttons = [
+        MouseButtonSet::LEFT,
+        MouseButtonSet::RIGHT,
+        MouseButtonSet::MIDDLE,
+        MouseButtonSet::BACK,
+        MouseButtonSet::FORWARD,
+    ];
+
+    let mut buttons = MouseButtonSet::empty();
+    let mut i = 0;
+    while i < first_buttons.len() {
+        if mask & (1 << i) != 0 {
+            buttons = buttons.union(first_buttons[i]);
+        }
+        i += 1;
+    }
+
+    // The other buttons already use the same bits
+    let other_buttons = MouseButtonSet::from_bits_retain(mask as u32 & !0b11111);
+    buttons.union(other_buttons)
+}
+
+/// The mouse buttons that are held down right now. `pressedMouseButtons` is the current state
+/// rather than the state at the time of the event that's being handled.
+pub(super) unsafe fn held_mouse_buttons() -> MouseButtonSet {
+    let mask: NSUInteger = msg_send![class!(NSEvent), pressedMouseButtons];
+    mouse_buttons(mask)
+}
+
+/// The mouse buttons that are held down after `button` was pressed or released. The event's own
+/// button is set or cleared explicitly, since the current state may not match the event anymore.
+unsafe fn mouse_buttons_after(button: MouseButton, pressed: bool) -> MouseButtonSet {
+    let buttons = held_mouse_buttons();
+    if pressed {
+        buttons | MouseButtonSet::from_button(button)
+    } else {
+        buttons - MouseButtonSet::from_button(button)
+    }
+}
+
+extern "C" fn other_mouse_down(this: &Object, _: Sel, event: id) {
+    unsafe {
+        let state: &mut WindowState = WindowState::from_field(this);
+        let modifiers = NSEvent::modifierFlags(event);
+
+        let button = other_mouse_button(event);
+        state.trigger_event(Event::Mouse(ButtonPressed {
+            button,
+            modifiers: make_modifiers(modifiers),
+            buttons: mouse_buttons_after(button, true),
+        }));
+    }
+}
+
+extern "C" fn other_mouse_up(this: &Object, _: Sel, event: id) {
+    unsafe {
+        let state: &mut WindowState = WindowState::from_field(this);
+        let modifiers = NSEvent::modifierFlags(event);
+
+        let button = other_mouse_button(event);
+        state.trigger_event(Event::Mouse(ButtonReleased {
+            button,
+            modifiers: make_modifiers(modifiers),
+            buttons: mouse_buttons_after(button, false),
         }));
     }
 }
@@ -421,18 +784,100 @@ extern "C" fn mouse_down(this: &Object, _: Sel, event: id) {
 extern "C" fn mouse_moved(this: &Object, _sel: Sel, event: id) {
     let state: &mut WindowState = unsafe { WindowState::from_field(this) };
 
-    let point: NSPoint = unsafe {
-        let point = NSEvent::locationInWindow(event);
+    let mut position = unsafe { state.view_position(NSEvent::locationInWindow(event)) };
+    if state.cursor_state.confined {
+        position = unsafe { confine_cursor(this, state, position) };
+    }
+    // Dragging reports positions outside of the view as well, where the cursor regions don't apply
+    let size = state.window_info.logical_size();
+    let inside = position.x >= 0.0
+        && position.y >= 0.0
+        && position.x < size.width
+        && position.y < size.height;
+    unsafe { state.pointer_moved(inside.then_some(position)) };
 
-        msg_send![this, convertPoint:point fromView:nil]
-    };
     let modifiers = unsafe { NSEvent::modifierFlags(event) };
-
-    let position = Point { x: point.x, y: point.y };
+    // The deltas are in points, and `deltaY` is already positive when moving down
+    let scale = state.window.backing_scale;
+    let delta = unsafe {
+        PhyPoint::new(
+            (NSEvent::deltaX(event) * scale).round() as i32,
+            (NSEvent::deltaY(event) * scale).round() as i32,
+        )
+    };
 
     state.trigger_event(Event::Mouse(MouseEvent::CursorMoved {
         position,
+        delta,
         modifiers: make_modifiers(modifiers),
+        buttons: unsafe { held_mouse_buttons() },
+    }));
+    unsafe { send_pen_moved(state, event) };
+}
+
+extern "C" fn mouse_exited(this: &Object, _sel: Sel, _event: id) {
+    let state: &mut WindowState = unsafe { WindowState::from_field(this) };
+
+    // The cursor regions only apply inside of the view
+    unsafe { state.pointer_moved(None) };
+    state.trigger_event(Event::Mouse(MouseEvent::CursorLeft));
+}
+
+/// Move the cursor back into the view if it left while it's confined, and return the position it
+/// ends up at. This only works while the view receives mouse moved events, which AppKit only sends
+/// while a button is held down or while the cursor is over the view.
+unsafe fn confine_cursor(view: &Object, state: &WindowState, position: Point) -> Point {
+    let size = state.window_info.logical_size();
+    let confined =
+        Point::new(position.x.clamp(0.0, size.width), position.y.clamp(0.0, size.height));
+    let ns_window: id = msg_send![view, window];
+    if confined == position || ns_window == nil {
+        return position;
+    }
+
+    let points = state.window.logical_per_point();
+    let view_point = NSPoint::new(confined.x / points, confined.y / points);
+    let window_point: NSPoint = msg_send![view, convertPoint: view_point toView: nil];
+    let screen_rect: NSRect = msg_send![
+        ns_window,
+        convertRectToScreen: NSRect::new(window_point, NSSize::new(0.0, 0.0))
+    ];
+
+    // Quartz's global coordinates start at the top left corner of the primary display, and AppKit's
+    // start at its bottom left corner
+    let screens: id = msg_send![class!(NSScreen), screens];
+    let primary_frame: NSRect = msg_send![NSArray::objectAtIndex(screens, 0), frame];
+    CGWarpMouseCursorPosition(NSPoint::new(
+        screen_rect.origin.x,
+        primary_frame.size.height - screen_rect.origin.y,
+    ));
+    // Warping suppresses mouse movement for a short while, which makes the cursor feel stuck
+    CGAssociateMouseAndMouseCursorPosition(1);
+
+    confined
+}
+
+/// Tablets send these when the pen's pressure or tilt changes without a mouse event.
+extern "C" fn tablet_point(this: &Object, _sel: Sel, event: id) {
+    unsafe { send_pen_moved(WindowState::from_field(this), event) };
+}
+
+/// Send a [`MouseEvent::PenMoved`] event if the event came from a graphics tablet.
+unsafe fn send_pen_moved(state: &mut WindowState, event: id) {
+    if NSEvent::eventType(event) != NSEventType::NSTabletPoint {
+        let subtype: i16 = msg_send![event, subtype];
+        if subtype != NS_EVENT_SUBTYPE_TABLET_POINT {
+            return;
+        }
+    }
+
+    // macOS reports positive vertical tilt when the pen is tilted away from the user
+    let tilt = NSEvent::tilt(event);
+    state.trigger_event(Event::Mouse(MouseEvent::PenMoved {
+        position: state.view_position(NSEvent::locationInWindow(event)),
+        pressure: NSEvent::pressure(event),
+        tilt: (tilt.x as f32, -tilt.y as f32),
+        modifiers: make_modifiers(NSEvent::modifierFlags(event)),
     }));
 }
 
@@ -440,7 +885,9 @@ extern "C" fn scroll_wheel(this: &Object, _: Sel, event: id) {
     let state: &mut WindowState = unsafe { WindowState::from_field(this) };
 
     let delta = unsafe {
-        let x = NSEvent::scrollingDeltaX(event) as f32;
+        // AppKit's horizontal deltas are positive when the content should move right, which is
+        // scrolling left. The natural scrolling setting is already applied to both deltas.
+        let x = -NSEvent::scrollingDeltaX(event) as f32;
         let y = NSEvent::scrollingDeltaY(event) as f32;
 
         if NSEvent::hasPreciseScrollingDeltas(event) != NO {
@@ -469,55 +916,140 @@ extern "C" fn reset_cursor_rects(this: &Object, _self: Sel) {
     }
 }
 
-unsafe fn get_drag_data(_this: &Object, dragging_info: id) -> (Vec<Data>, Point) {
-    let mut ret: Vec<Data> = vec![];
+/// Returns the dragged files along with the drag's current position within the view.
+/// The paths of the dragged files as an `NSArray` of `NSString`s, or `nil` if the drag doesn't
+/// contain any files. The items are converted one at a time by [`dragged_file()`], so the paths are
+/// only copied once.
+unsafe fn dragged_files(dragging_info: id) -> id {
     if dragging_info == nil {
-        return (ret, Point::new(0.0, 0.0));
+        return nil;
     }
 
     let pasteboard: id = msg_send![dragging_info, draggingPasteboard];
-    let file_list: id = msg_send![pasteboard, propertyListForType: NSFilenamesPboardType];
+    msg_send![pasteboard, propertyListForType: NSFilenamesPboardType]
+}
 
+/// The number of files in an array returned by [`dragged_files()`].
+unsafe fn dragged_file_count(file_list: id) -> NSUInteger {
     if file_list == nil {
-        return (ret, Point::new(0.0, 0.0));
+        0
+    } else {
+        NSArray::count(file_list)
     }
+}
+
+unsafe fn dragged_file(file_list: id, index: NSUInteger) -> Data {
+    Data::Filepath(from_nsstring(NSArray::objectAtIndex(file_list, index)).into())
+}
 
-    let count = NSArray::count(file_list);
-    for i in 0..count {
-        let data = NSArray::objectAtIndex(file_list, i);
-        ret.push(Data::Filepath(from_nsstring(data).into()));
+/// The drag's position in the view.
+unsafe fn drag_position(this: &Object, dragging_info: id) -> Point {
+    if dragging_info == nil {
+        return Point::new(0.0, 0.0);
     }
 
+    // The dragging location is in window coordinates, just like the locations of mouse events
     let point: NSPoint = msg_send![dragging_info, draggingLocation];
+    WindowState::from_field(this).view_position(point)
+}
+
+/// Update the effects the drag source allows. AppKit already narrows the source's operation mask
+/// down to copying when the Option key is held, so this is also the proposed effect.
+unsafe fn update_drop_effect(state: &mut WindowState, dragging_info: id) {
+    let mask: NSDragOperation = msg_send![dragging_info, draggingSourceOperationMask];
+    let allowed = match (
+        mask & (NSDragOperationCopy | NSDragOperationGeneric) != 0,
+        mask & NSDragOperationMove != 0,
+    ) {
+        (true, true) => Some(DropEffect::Ask),
+        (true, false) => Some(DropEffect::Copy),
+        (false, true) => Some(DropEffect::Move),
+        (false, false) => None,
+    };
+
+    let effect = &mut state.window.drop_target_effect;
+    effect.allowed = allowed;
+    effect.proposed = allowed.map(DropEffect::resolved);
+}
+
+/// Where a drag came from. `draggingSource` is only set for drags that were started by this
+/// application, so the source application is unknown for all other drags.
+unsafe fn drag_source(dragging_info: id) -> Option<DragSourceInfo> {
+    let sequence_number: NSInteger = msg_send![dragging_info, draggingSequenceNumber];
+    let source: id = msg_send![dragging_info, draggingSource];
+    let application = if source != nil {
+        let app: id = msg_send![class!(NSRunningApplication), currentApplication];
+        let url: id = msg_send![app, executableURL];
+        let name: id = if url != nil { msg_send![url, lastPathComponent] } else { nil };
+        (name != nil).then(|| from_nsstring(name))
+    } else {
+        None
+    };
+
+    Some(DragSourceInfo { platform_id: sequence_number as u64, application })
+}
 
-    (ret, Point::new(point.x, point.y))
+/// The operation for the drop effect the window will perform.
+fn drop_operation(state: &WindowState) -> NSDragOperation {
+    match state.window.drop_effect() {
+        Some(DropEffect::Move) => NSDragOperationMove,
+        Some(_) => NSDragOperationCopy,
+        None => NSDragOperationNone,
+    }
 }
 
 extern "C" fn dragging_entered(this: &Object, _sel: Sel, dragging_info: id) -> NSUInteger {
     unsafe {
-        let (mut data, _) = get_drag_data(this, dragging_info);
+        let file_list = dragged_files(dragging_info);
+        let count = dragged_file_count(file_list);
+        debug!(target: "baseview::dnd", files = count, "drag entered");
+        let position = drag_position(this, dragging_info);
         let state: &mut WindowState = WindowState::from_field(this);
-        for d in data.drain(..) {
-            state.trigger_event(Event::Window(WindowEvent::DragEnter(d)));
+        state.window.drop_target_effect.reset();
+        update_drop_effect(state, dragging_info);
+        // The handler should know where the drag entered before the first `Dragging` event
+        if count > 0 {
+            state.trigger_event(Event::Mouse(MouseEvent::CursorMoved {
+                position,
+                delta: PhyPoint::default(),
+                modifiers: keyboard_types::Modifiers::empty(),
+                buttons: MouseButtonSet::empty(),
+            }));
         }
+        let source = drag_source(dragging_info);
+        for i in 0..count {
+            let data = dragged_file(file_list, i);
+            state.trigger_drag_event(WindowEvent::DragEnter { data, source: source.clone() });
+        }
+        // The pasteboard doesn't have a file list for drags that don't contain any files
+        if count == 0 {
+            let error = if file_list == nil { DropError::NotAFile(None) } else { DropError::Empty };
+            state.trigger_drag_event(WindowEvent::DropFailed(error));
+        }
+
+        drop_operation(state)
     }
-    4 // NSDragOperationGeneric
 }
 
 extern "C" fn dragging_updated(this: &Object, _sel: Sel, dragging_info: id) -> NSUInteger {
     unsafe {
-        let (data, p) = get_drag_data(this, dragging_info);
-        if !data.is_empty() {
-            let state = WindowState::from_field(this);
+        // Only the position is needed here, the files were already sent when the drag entered
+        let p = drag_position(this, dragging_info);
+        let state = WindowState::from_field(this);
+        update_drop_effect(state, dragging_info);
+        if state.in_drag() {
             state.trigger_event(Event::Mouse(MouseEvent::CursorMoved {
                 position: p,
+                delta: PhyPoint::default(),
                 modifiers: keyboard_types::Modifiers::empty(),
+                buttons: MouseButtonSet::empty(),
             }));
 
-            state.trigger_event(Event::Window(WindowEvent::Dragging));
+            state.trigger_drag_event(WindowEvent::Dragging);
         }
+
+        drop_operation(state)
     }
-    4 // NSDragOperationGeneric
 }
 
 extern "C" fn prepare_for_drag_operation(_this: &Object, _sel: Sel, _dragging_info: id) -> BOOL {
@@ -526,32 +1058,142 @@ extern "C" fn prepare_for_drag_operation(_this: &Object, _sel: Sel, _dragging_in
 
 extern "C" fn perform_drag_operation(this: &Object, _sel: Sel, dragging_info: id) -> BOOL {
     unsafe {
-        let (mut data, _) = get_drag_data(this, dragging_info);
+        let file_list = dragged_files(dragging_info);
+        let count = dragged_file_count(file_list);
+        let position = drag_position(this, dragging_info);
         let state: &mut WindowState = WindowState::from_field(this);
-        for d in data.drain(..) {
-            state.trigger_event(Event::Window(WindowEvent::Drop(d)));
+        update_drop_effect(state, dragging_info);
+        let effect = state.window.drop_effect();
+        debug!(target: "baseview::dnd", files = count, ?effect, "dropped");
+        // Drags without any files never entered as far as the handler is concerned
+        if !state.in_drag() {
+            state.window.drop_target_effect.reset();
+            return NO;
         }
+        if effect.is_none() || count == 0 {
+            state.trigger_drag_event(WindowEvent::DragLeave);
+            state.window.drop_target_effect.reset();
+            return NO;
+        }
+
+        let items = (0..count).map(|i| dragged_file(file_list, i)).collect();
+        let source = drag_source(dragging_info);
+        state.trigger_drag_event(WindowEvent::Drop(DropPayload { items, position, source }));
+        state.window.drop_target_effect.reset();
     }
     YES
 }
 
-extern "C" fn dragging_exited(this: &Object, _sel: Sel, dragging_info: id) {
+extern "C" fn dragging_exited(this: &Object, _sel: Sel, _dragging_info: id) {
     unsafe {
-        let (data, _) = get_drag_data(this, dragging_info);
-        if !data.is_empty() {
-            let state = WindowState::from_field(this);
-            state.trigger_event(Event::Window(WindowEvent::DragLeave));
+        debug!(target: "baseview::dnd", "drag exited");
+        let state = WindowState::from_field(this);
+        if state.in_drag() {
+            state.trigger_drag_event(WindowEvent::DragLeave);
         }
+        state.window.drop_target_effect.reset();
     }
 }
 
 extern "C" fn source_operation_mask_for_dragging_context(
-    _this: &mut Object, _: Sel, _session: id, context: NSInteger,
+    this: &mut Object, _: Sel, _session: id, context: NSInteger,
 ) -> NSDragOperation {
+    let state = unsafe { WindowState::from_field(this) };
+    // There's no equivalent to asking, so the destination picks one of the operations
+    let mask = match state.window.drag_effect.get() {
+        DropEffect::Copy => NSDragOperationCopy,
+        DropEffect::Move => NSDragOperationMove,
+        DropEffect::Ask => NSDragOperationCopy | NSDragOperationMove,
+    };
+
     #[allow(non_upper_case_globals)]
     match context {
-        NSDraggingContextWithinApplication => NSDragOperationGeneric,
-        NSDraggingContextOutsideApplication => NSDragOperationCopy,
+        NSDraggingContextWithinApplication => mask | NSDragOperationGeneric,
+        NSDraggingContextOutsideApplication => mask,
         _ => NSDragOperationNone,
     }
 }
+
+extern "C" fn dragging_session_moved(this: &Object, _: Sel, _session: id, screen_point: NSPoint) {
+    unsafe {
+        let ns_window: id = msg_send![this, window];
+        if ns_window == nil {
+            return;
+        }
+
+        let state = WindowState::from_field(this);
+        let screen_rect = NSRect::new(screen_point, NSSize::new(0.0, 0.0));
+        let window_rect: NSRect = msg_send![ns_window, convertRectFromScreen:screen_rect];
+        let position = state.view_position(window_rect.origin);
+        // The system doesn't tell the source what the destination will do until the drag ends
+        state.trigger_event(Event::Window(WindowEvent::DragSourceMoved {
+            position,
+            will_accept: true,
+        }));
+    }
+}
+
+extern "C" fn dragging_session_ended(
+    this: &Object, _: Sel, _session: id, _point: NSPoint, operation: NSDragOperation,
+) {
+    debug!(target: "baseview::dnd", operation, "outgoing drag ended");
+    let state = unsafe { WindowState::from_field(this) };
+    let effect = if operation == NSDragOperationNone {
+        None
+    } else if operation & NSDragOperationMove != 0 {
+        Some(DropEffect::Move)
+    } else {
+        Some(DropEffect::Copy)
+    };
+    state.trigger_event(Event::Window(WindowEvent::DragSourceEnded { effect }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_button_numbers() {
+        let table = [
+            (0, MouseButton::Left),
+            (1, MouseButton::Right),
+            (2, MouseButton::Middle),
+            (3, MouseButton::Back),
+            (4, MouseButton::Forward),
+            (5, MouseButton::Other(0)),
+            (6, MouseButton::Other(1)),
+            (31, MouseButton::Other(26)),
+            (260, MouseButton::Other(255)),
+            (NSInteger::MAX, MouseButton::Other(255)),
+        ];
+
+        for (button_number, button) in table {
+            assert_eq!(mouse_button(button_number), button, "button number {}", button_number);
+        }
+    }
+
+    #[test]
+    fn pressed_mouse_buttons_masks() {
+        assert_eq!(mouse_buttons(0), MouseButtonSet::empty());
+        assert_eq!(mouse_buttons(0b101), MouseButtonSet::LEFT | MouseButtonSet::MIDDLE);
+        assert_eq!(
+            mouse_buttons(0b1_0001_1010),
+            MouseButtonSet::RIGHT
+                | MouseButtonSet::BACK
+                | MouseButtonSet::FORWARD
+                | MouseButtonSet::from_button(MouseButton::Other(3))
+        );
+    }
+
+    #[test]
+    fn pressed_mouse_buttons_match_button_numbers() {
+        for button_number in 0..32 {
+            assert_eq!(
+                mouse_buttons(1 << button_number),
+                MouseButtonSet::from_button(mouse_button(button_number as NSInteger)),
+                "button number {}",
+                button_number
+            );
+        }
+    }
+}
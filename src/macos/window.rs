@@ -1,46 +1,73 @@
-use std::cell::RefCell;
-use std::ffi::c_void;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{c_void, CString};
 use std::marker::PhantomData;
+use std::os::unix::ffi::OsStrExt;
 use std::ptr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use cocoa::appkit::{
-    NSApp, NSApplication, NSApplicationActivationPolicyRegular, NSBackingStoreBuffered, NSCursor,
-    NSEvent, NSImage, NSPasteboard, NSView, NSWindow, NSWindowStyleMask,
+    NSApp, NSApplication, NSApplicationActivationPolicyAccessory,
+    NSApplicationActivationPolicyProhibited, NSApplicationActivationPolicyRegular,
+    NSBackingStoreBuffered, NSCursor, NSEvent, NSEventModifierFlags, NSImage, NSPasteboard, NSView,
+    NSWindow, NSWindowStyleMask,
+};
+use cocoa::base::{id, nil, BOOL, NO, YES};
+use cocoa::foundation::{
+    NSArray, NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString, NSUInteger,
 };
-use cocoa::base::{id, nil, NO, YES};
-use cocoa::foundation::{NSArray, NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString, NSURL};
 use core_foundation::runloop::{
     CFRunLoop, CFRunLoopTimer, CFRunLoopTimerContext, __CFRunLoopTimer, kCFRunLoopDefaultMode,
 };
-use keyboard_types::KeyboardEvent;
-
-use objc::{class, msg_send, rc::StrongPtr, runtime::Object, sel, sel_impl};
-
-use raw_window_handle::{
-    AppKitDisplayHandle, AppKitWindowHandle, HasRawDisplayHandle, HasRawWindowHandle,
-    RawDisplayHandle, RawWindowHandle,
+use keyboard_types::{Code, KeyboardEvent};
+
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    rc::StrongPtr,
+    runtime::{Object, Sel},
+    sel, sel_impl,
 };
-
+use uuid::Uuid;
+
+use crate::drop_effect::{DragEventOrder, DropTargetEffect};
+use crate::event_clock::EventClock;
+use crate::held_inputs::HeldInputs;
+use crate::lifecycle::Lifecycle;
+use crate::mouse_cursor::CursorRegions;
+use crate::platform_handle::{PlatformDisplayHandle, PlatformWindowHandle};
+use crate::region::Damage;
+use crate::user_data::UserData;
 use crate::{
-    Data, Event, EventStatus, Point, Size, WindowEvent, WindowHandler, WindowInfo,
-    WindowOpenOptions, WindowScalePolicy,
+    ActivationPolicy, Data, DialogId, DragImage, DropEffect, Error, Event, EventStatus,
+    FileDialogOptions, FrameSource, MouseEvent, PanicPolicy, PhyPoint, Point, Rect, Region, Size,
+    SurfaceInfo, WindowEvent, WindowHandler, WindowId, WindowInfo, WindowKind, WindowOpenOptions,
+    WindowScalePolicy,
 };
 
-use super::keyboard::KeyboardState;
+use super::display_link::DisplayLink;
+use super::keyboard::{make_modifiers, KeyboardState};
 use super::menu;
-use super::view::{create_view, BASEVIEW_STATE_IVAR};
-use crate::MouseCursor;
+use super::view::{create_view, held_mouse_buttons, property_yes, BASEVIEW_STATE_IVAR};
+use crate::{AttentionType, CursorIcon, CursorId, MouseCursor};
 
 #[cfg(feature = "opengl")]
-use crate::{
-    gl::{GlConfig, GlContext},
-    window::RawWindowHandleWrapper,
-};
+use crate::gl::{GlConfig, GlContext, GlError};
+
+#[cfg(feature = "accesskit")]
+use std::rc::Rc;
+
+#[cfg(feature = "accesskit")]
+use crate::accessibility::AccessKitHandler;
+#[cfg(feature = "accesskit")]
+use crate::AccessKitEvent;
 
 pub struct WindowHandle {
-    raw_window_handle: Option<RawWindowHandle>,
+    id: WindowId,
+    raw_window_handle: Option<PlatformWindowHandle>,
     close_requested: Arc<AtomicBool>,
     is_open: Arc<AtomicBool>,
 
@@ -58,45 +85,138 @@ impl WindowHandle {
     pub fn is_open(&self) -> bool {
         self.is_open.load(Ordering::Relaxed)
     }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> Option<PlatformWindowHandle> {
+        self.raw_window_handle.filter(|_| self.is_open.load(Ordering::Relaxed))
+    }
+
+    pub fn remote(&self) -> RemoteWindowHandle {
+        let ns_view = match self.raw_window_handle {
+            Some(PlatformWindowHandle::AppKit { ns_view, .. }) => ns_view as usize,
+            None => 0,
+        };
+
+        RemoteWindowHandle {
+            id: self.id,
+            ns_view,
+            close_requested: Arc::clone(&self.close_requested),
+            is_open: Arc::clone(&self.is_open),
+        }
+    }
+
+    pub fn trigger_frame(&self) {
+        let Some(PlatformWindowHandle::AppKit { ns_view, .. }) = self.platform_window_handle()
+        else {
+            return;
+        };
+
+        unsafe {
+            let is_main_thread: BOOL = msg_send![class!(NSThread), isMainThread];
+            assert!(
+                is_main_thread == YES,
+                "WindowHandle::trigger_frame() must be called on the main thread"
+            );
+
+            let ns_view = &*(ns_view as id);
+            let state_ptr: *mut c_void = *ns_view.get_ivar(BASEVIEW_STATE_IVAR);
+            if !state_ptr.is_null() {
+                WindowState::from_field(ns_view).trigger_external_frame();
+            }
+        }
+    }
 }
 
-unsafe impl HasRawWindowHandle for WindowHandle {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        if let Some(raw_window_handle) = self.raw_window_handle {
-            if self.is_open.load(Ordering::Relaxed) {
-                return raw_window_handle;
+/// Shares the atomics of a [`WindowHandle`]. Windows only check for close requests after drawing
+/// a frame, so closing the window also schedules a check on the main thread's run loop.
+#[derive(Clone)]
+pub struct RemoteWindowHandle {
+    id: WindowId,
+    /// The view's address, since pointers aren't `Send`. This is `0` if the [`WindowHandle`] was
+    /// already closed when this handle was created.
+    ns_view: usize,
+    close_requested: Arc<AtomicBool>,
+    is_open: Arc<AtomicBool>,
+}
+
+impl RemoteWindowHandle {
+    pub fn close(&self) {
+        self.close_requested.store(true, Ordering::Relaxed);
+        if self.ns_view == 0 || !self.is_open() {
+            return;
+        }
+
+        extern "C" fn close_callback(_: *mut __CFRunLoopTimer, info: *mut c_void) {
+            let remote = unsafe { &*(info as *const RemoteWindowHandle) };
+
+            // The window state is freed on the main thread, and it marks the window as closed when
+            // it is, so the view is still alive here if the window is open
+            if !remote.is_open() {
+                return;
             }
+
+            unsafe {
+                let ns_view = &mut *(remote.ns_view as id);
+                let state_ptr: *mut c_void = *ns_view.get_ivar(BASEVIEW_STATE_IVAR);
+                if !state_ptr.is_null() {
+                    WindowState::from_field(ns_view).handle_close_requests();
+                }
+            }
+        }
+
+        extern "C" fn release(info: *const c_void) {
+            drop(unsafe { Box::from_raw(info as *mut RemoteWindowHandle) });
         }
 
-        RawWindowHandle::AppKit(AppKitWindowHandle::empty())
+        let mut timer_context = CFRunLoopTimerContext {
+            version: 0,
+            info: Box::into_raw(Box::new(self.clone())) as *mut c_void,
+            retain: None,
+            release: Some(release),
+            copyDescription: None,
+        };
+
+        // A timer with a zero interval fires once, and the run loop releases it afterwards
+        let timer = CFRunLoopTimer::new(0.0, 0.0, 0, 0, close_callback, &mut timer_context);
+        unsafe { CFRunLoop::get_main().add_timer(&timer, kCFRunLoopDefaultMode) };
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open.load(Ordering::Relaxed)
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
     }
 }
 
 struct ParentHandle {
-    _close_requested: Arc<AtomicBool>,
+    close_requested: Arc<AtomicBool>,
     is_open: Arc<AtomicBool>,
 }
 
 impl ParentHandle {
-    pub fn new(raw_window_handle: RawWindowHandle) -> (Self, WindowHandle) {
+    pub fn new(raw_window_handle: PlatformWindowHandle, id: WindowId) -> (Self, WindowHandle) {
         let close_requested = Arc::new(AtomicBool::new(false));
         let is_open = Arc::new(AtomicBool::new(true));
 
         let handle = WindowHandle {
+            id,
             raw_window_handle: Some(raw_window_handle),
             close_requested: Arc::clone(&close_requested),
             is_open: Arc::clone(&is_open),
             _phantom: PhantomData::default(),
         };
 
-        (Self { _close_requested: close_requested, is_open }, handle)
+        (Self { close_requested, is_open }, handle)
     }
 
-    /*
     pub fn parent_did_drop(&self) -> bool {
         self.close_requested.load(Ordering::Relaxed)
     }
-    */
 }
 
 impl Drop for ParentHandle {
@@ -105,7 +225,31 @@ impl Drop for ParentHandle {
     }
 }
 
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(15);
+
+/// `NSWindowOcclusionStateVisible`, which the cocoa crate doesn't define. This bit is set while at
+/// least part of the window is visible on screen.
+const NS_WINDOW_OCCLUSION_STATE_VISIBLE: NSUInteger = 1 << 1;
+
+/// `NSWindowStyleMaskNonactivatingPanel`, which the cocoa crate doesn't define.
+const NS_NONACTIVATING_PANEL_MASK: NSUInteger = 1 << 7;
+
+/// The corner radius in points for popups with rounded corners, which matches macOS's own menus.
+const POPUP_CORNER_RADIUS: f64 = 8.0;
+
+/// Whether the view is in a window, neither it nor its superviews are hidden, and at least part of
+/// the window is visible on screen.
+unsafe fn view_is_visible(ns_view: id) -> bool {
+    let ns_window: id = msg_send![ns_view, window];
+    ns_window != nil && {
+        let hidden: BOOL = msg_send![ns_view, isHiddenOrHasHiddenAncestor];
+        let occlusion_state: NSUInteger = msg_send![ns_window, occlusionState];
+        hidden == NO && occlusion_state & NS_WINDOW_OCCLUSION_STATE_VISIBLE != 0
+    }
+}
+
 pub struct Window {
+    id: WindowId,
     /// Only set if we created the parent window, i.e. we are running in
     /// parentless mode
     ns_app: Option<id>,
@@ -117,98 +261,221 @@ pub struct Window {
     /// Our subclassed NSView
     ns_view: id,
     close_requested: bool,
+    /// The request returned by `requestUserAttention:`, so it can be canceled again.
+    attention_request: Option<isize>,
+    /// Whether `on_frame` should be called continuously. See
+    /// [`crate::EventContext::set_animating`].
+    animating: bool,
+    /// See [`crate::EventContext::set_frame_interval`]. The `WindowState` recreates the frame timer
+    /// when this no longer matches the running timer's interval.
+    frame_interval: Duration,
+    /// See [`crate::EventContext::pause_frames`].
+    frames_paused: bool,
+    /// See [`crate::EventContext::set_scale_policy`]. The view's size is in points, so every window
+    /// starts out following the backing scale factor regardless of the policy it was opened with.
+    scale_policy: WindowScalePolicy,
+    /// See [`crate::EventContext::set_scale_override`].
+    scale_override: Option<f64>,
+    /// Set when the scale policy or the override changed. The `WindowState` updates its window info
+    /// after the handler returns.
+    scale_changed: bool,
+    /// The window's backing scale factor, or 1 while the view isn't in a window. The view's points
+    /// are converted to logical pixels with this and the window's own scale factor.
+    pub(super) backing_scale: f64,
+    /// See [`crate::EventContext::drag_and_drop_available`].
+    drag_and_drop_available: bool,
+    /// The effect requested by the last call to [`crate::EventContext::start_drag`]. The view's
+    /// operation mask for outgoing drags is based on this.
+    pub(super) drag_effect: Cell<DropEffect>,
+    /// See [`crate::EventContext::set_drop_effect`]. This is updated by the view's
+    /// `NSDraggingDestination` methods.
+    pub(super) drop_target_effect: DropTargetEffect,
+    drop_target_valid: Option<Box<dyn Fn() -> bool + Send + Sync>>,
     /// Required for Drag support
     pub(crate) last_mouse_down: RefCell<Option<StrongPtr>>,
+    user_data: UserData,
+    /// Used to synthesize release events when the window loses focus, and for
+    /// [`crate::EventContext::pressed_keys`].
+    held_inputs: HeldInputs,
+    /// Timestamps for [`crate::EventContext::event_time()`] and related methods.
+    event_clock: EventClock,
+    /// See [`crate::EventContext::damage`]. Rectangles invalidated by the handler make the
+    /// `WindowState` draw another frame.
+    damage: Damage,
 
     #[cfg(feature = "opengl")]
     gl_context: Option<GlContext>,
+    /// See [`crate::EventContext::gl_context_error()`].
+    #[cfg(feature = "opengl")]
+    gl_context_error: Option<GlError>,
+    /// See [`WindowOpenOptions::accesskit`]. The view forwards AppKit's accessibility queries to
+    /// this.
+    #[cfg(feature = "accesskit")]
+    pub(super) accesskit: Option<AccessKit>,
 }
 
 impl Window {
-    pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
+    pub fn open_parented<H, B>(
+        parent: PlatformWindowHandle, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, Error>
     where
-        P: HasRawWindowHandle,
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
+        B: FnOnce(&mut crate::EventContext) -> H,
         B: Send + 'static,
     {
         let pool = unsafe { NSAutoreleasePool::new(nil) };
 
         let scaling = match options.scale {
             WindowScalePolicy::ScaleFactor(scale) => scale,
-            WindowScalePolicy::SystemScaleFactor => 1.0,
+            _ => 1.0,
         };
 
         let window_info = WindowInfo::from_logical_size(options.size, scaling);
 
-        let handle = if let RawWindowHandle::AppKit(handle) = parent.raw_window_handle() {
-            handle
+        let PlatformWindowHandle::AppKit { ns_window: parent_ns_window, ns_view: parent_ns_view } =
+            parent;
+        // Handles from raw-window-handle 0.6 only contain the view
+        let parent_ns_window: id = if parent_ns_window.is_null() {
+            unsafe { msg_send![parent_ns_view as id, window] }
         } else {
-            panic!("Not a macOS window");
+            parent_ns_window as id
         };
 
         let ns_view = unsafe { create_view(&options) };
 
+        #[cfg(feature = "opengl")]
+        let (gl_context, gl_context_error) =
+            Self::create_gl_context(None, ns_view, options.gl_config, options.vsync_frames);
+
         let window = Window {
+            id: WindowId::next(),
             ns_app: None,
             ns_window: None,
-            parent_ns_window: Some(handle.ns_window as *mut Object),
+            parent_ns_window: Some(parent_ns_window),
             ns_view,
             close_requested: false,
+            attention_request: None,
+            animating: true,
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+            frames_paused: false,
+            scale_policy: WindowScalePolicy::SystemScaleFactor,
+            scale_override: None,
+            scale_changed: false,
+            backing_scale: 1.0,
+            drag_and_drop_available: options.enable_drag_and_drop,
+            drag_effect: Cell::new(DropEffect::default()),
+            drop_target_effect: DropTargetEffect::default(),
+            drop_target_valid: options.drop_target_valid,
             last_mouse_down: RefCell::new(None),
+            user_data: UserData::default(),
+            held_inputs: HeldInputs::default(),
+            event_clock: EventClock::new(),
+            damage: Damage::default(),
 
             #[cfg(feature = "opengl")]
-            gl_context: options
-                .gl_config
-                .map(|gl_config| Self::create_gl_context(None, ns_view, gl_config)),
+            gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+
+            #[cfg(feature = "accesskit")]
+            accesskit: options.accesskit.then(|| unsafe { AccessKit::new(ns_view) }),
         };
 
-        let window_handle = Self::init(true, window, window_info, build);
+        let window_handle = Self::init(
+            true,
+            window,
+            window_info,
+            options.on_handler_panic,
+            options.vsync_frames,
+            options.frame_source,
+            build,
+        );
 
         unsafe {
-            let _: id = msg_send![handle.ns_view as *mut Object, addSubview: ns_view];
+            let _: id = msg_send![parent_ns_view as *mut Object, addSubview: ns_view];
+            WindowState::from_field(&*ns_view).send_initial_resized();
+            WindowState::from_field(&*ns_view).update_visibility();
+            WindowState::from_field(&*ns_view).send_initial_cursor_position();
             let () = msg_send![ns_view as id, release];
 
             let () = msg_send![pool, drain];
         }
 
-        window_handle
+        Ok(window_handle)
     }
 
     pub fn open_as_if_parented<H, B>(options: WindowOpenOptions, build: B) -> WindowHandle
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
+        B: FnOnce(&mut crate::EventContext) -> H,
         B: Send + 'static,
     {
         let pool = unsafe { NSAutoreleasePool::new(nil) };
 
         let scaling = match options.scale {
             WindowScalePolicy::ScaleFactor(scale) => scale,
-            WindowScalePolicy::SystemScaleFactor => 1.0,
+            _ => 1.0,
         };
 
         let window_info = WindowInfo::from_logical_size(options.size, scaling);
 
         let ns_view = unsafe { create_view(&options) };
 
+        #[cfg(feature = "opengl")]
+        let (gl_context, gl_context_error) =
+            Self::create_gl_context(None, ns_view, options.gl_config, options.vsync_frames);
+
         let window = Window {
+            id: WindowId::next(),
             ns_app: None,
             ns_window: None,
             parent_ns_window: None,
             ns_view,
             close_requested: false,
+            attention_request: None,
+            animating: true,
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+            frames_paused: false,
+            scale_policy: WindowScalePolicy::SystemScaleFactor,
+            scale_override: None,
+            scale_changed: false,
+            backing_scale: 1.0,
+            drag_and_drop_available: options.enable_drag_and_drop,
+            drag_effect: Cell::new(DropEffect::default()),
+            drop_target_effect: DropTargetEffect::default(),
+            drop_target_valid: options.drop_target_valid,
             last_mouse_down: RefCell::new(None),
+            user_data: UserData::default(),
+            held_inputs: HeldInputs::default(),
+            event_clock: EventClock::new(),
+            damage: Damage::default(),
 
             #[cfg(feature = "opengl")]
-            gl_context: options
-                .gl_config
-                .map(|gl_config| Self::create_gl_context(None, ns_view, gl_config)),
+            gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+
+            #[cfg(feature = "accesskit")]
+            accesskit: options.accesskit.then(|| unsafe { AccessKit::new(ns_view) }),
         };
 
-        let window_handle = Self::init(true, window, window_info, build);
+        let window_handle = Self::init(
+            true,
+            window,
+            window_info,
+            options.on_handler_panic,
+            options.vsync_frames,
+            options.frame_source,
+            build,
+        );
 
+        // The view only gets added to a window by the host later on, so this uses a scale factor
+        // of 1 for now. The view sends another `Resized` event once its backing scale is known.
         unsafe {
+            WindowState::from_field(&*ns_view).send_initial_resized();
+            WindowState::from_field(&*ns_view).update_visibility();
+            WindowState::from_field(&*ns_view).send_initial_cursor_position();
+
             let () = msg_send![pool, drain];
         }
 
@@ -218,8 +485,8 @@ impl Window {
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: 'static,
     {
         let pool = unsafe { NSAutoreleasePool::new(nil) };
 
@@ -230,13 +497,28 @@ impl Window {
         // https://developer.apple.com/documentation/appkit/nsapplication
         let app = unsafe { NSApp() };
 
-        unsafe {
-            app.setActivationPolicy_(NSApplicationActivationPolicyRegular);
+        // A larger application that opens the window may have set up the app already, and its
+        // activation policy and main menu shouldn't be clobbered
+        let configure_app = unsafe { !app_is_configured(app) };
+
+        if let Some(policy) = options.macos_activation_policy.filter(|_| configure_app) {
+            // Popups don't get a Dock icon
+            let activation_policy = match (policy, options.window_kind) {
+                (ActivationPolicy::Regular, WindowKind::Normal) => {
+                    NSApplicationActivationPolicyRegular
+                }
+                (ActivationPolicy::Regular, WindowKind::Popup { .. })
+                | (ActivationPolicy::Accessory, _) => NSApplicationActivationPolicyAccessory,
+                (ActivationPolicy::Prohibited, _) => NSApplicationActivationPolicyProhibited,
+            };
+            unsafe {
+                app.setActivationPolicy_(activation_policy);
+            }
         }
 
         let scaling = match options.scale {
             WindowScalePolicy::ScaleFactor(scale) => scale,
-            WindowScalePolicy::SystemScaleFactor => 1.0,
+            _ => 1.0,
         };
 
         let window_info = WindowInfo::from_logical_size(options.size, scaling);
@@ -246,52 +528,104 @@ impl Window {
             NSSize::new(window_info.logical_size().width, window_info.logical_size().height),
         );
 
-        let mut style_mask = NSWindowStyleMask::NSTitledWindowMask
-            | NSWindowStyleMask::NSClosableWindowMask
-            | NSWindowStyleMask::NSMiniaturizableWindowMask;
-
-        if options.resizable {
-            style_mask |= NSWindowStyleMask::NSResizableWindowMask;
-        }
-
         let ns_window = unsafe {
-            let ns_window = NSWindow::alloc(nil).initWithContentRect_styleMask_backing_defer_(
-                rect,
-                style_mask,
-                NSBackingStoreBuffered,
-                NO,
-            );
+            let ns_window = match options.window_kind {
+                WindowKind::Normal => {
+                    let mut style_mask = NSWindowStyleMask::NSTitledWindowMask
+                        | NSWindowStyleMask::NSClosableWindowMask
+                        | NSWindowStyleMask::NSMiniaturizableWindowMask;
+
+                    if options.resizable {
+                        style_mask |= NSWindowStyleMask::NSResizableWindowMask;
+                    }
+
+                    NSWindow::alloc(nil).initWithContentRect_styleMask_backing_defer_(
+                        rect,
+                        style_mask,
+                        NSBackingStoreBuffered,
+                        NO,
+                    )
+                }
+                WindowKind::Popup { shadow, rounded_corners, focusable } => {
+                    create_popup_panel(rect, shadow, rounded_corners, focusable)
+                }
+            };
             ns_window.center();
 
             let title = NSString::alloc(nil).init_str(&options.title).autorelease();
             ns_window.setTitle_(title);
 
+            // Popups that can't become the key window are only ordered to the front
             ns_window.makeKeyAndOrderFront_(nil);
 
             ns_window
         };
 
         let ns_view = unsafe { create_view(&options) };
+        if let WindowKind::Popup { rounded_corners: true, .. } = options.window_kind {
+            unsafe { round_view_corners(ns_view) };
+        }
+
+        #[cfg(feature = "opengl")]
+        let (gl_context, gl_context_error) = Self::create_gl_context(
+            Some(ns_window),
+            ns_view,
+            options.gl_config,
+            options.vsync_frames,
+        );
 
         let window = Window {
+            id: WindowId::next(),
             ns_app: Some(app),
             ns_window: Some(ns_window),
             parent_ns_window: None,
             ns_view,
             close_requested: false,
+            attention_request: None,
+            animating: true,
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+            frames_paused: false,
+            scale_policy: WindowScalePolicy::SystemScaleFactor,
+            scale_override: None,
+            scale_changed: false,
+            backing_scale: 1.0,
+            drag_and_drop_available: options.enable_drag_and_drop,
+            drag_effect: Cell::new(DropEffect::default()),
+            drop_target_effect: DropTargetEffect::default(),
+            drop_target_valid: options.drop_target_valid,
             last_mouse_down: RefCell::new(None),
+            user_data: UserData::default(),
+            held_inputs: HeldInputs::default(),
+            event_clock: EventClock::new(),
+            damage: Damage::default(),
 
             #[cfg(feature = "opengl")]
-            gl_context: options
-                .gl_config
-                .map(|gl_config| Self::create_gl_context(Some(ns_window), ns_view, gl_config)),
+            gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+
+            #[cfg(feature = "accesskit")]
+            accesskit: options.accesskit.then(|| unsafe { AccessKit::new(ns_view) }),
         };
 
-        let _ = Self::init(false, window, window_info, build);
-        menu::initialize();
+        let _ = Self::init(
+            false,
+            window,
+            window_info,
+            options.on_handler_panic,
+            options.vsync_frames,
+            options.frame_source,
+            build,
+        );
+        if configure_app && options.macos_install_menu {
+            menu::initialize();
+        }
 
         unsafe {
             ns_window.setContentView_(ns_view);
+            WindowState::from_field(&*ns_view).send_initial_resized();
+            WindowState::from_field(&*ns_view).update_visibility();
+            WindowState::from_field(&*ns_view).send_initial_cursor_position();
 
             let () = msg_send![ns_view as id, release];
             let () = msg_send![pool, drain];
@@ -301,16 +635,32 @@ impl Window {
     }
 
     fn init<H, B>(
-        parented: bool, mut window: Window, window_info: WindowInfo, build: B,
+        parented: bool, mut window: Window, window_info: WindowInfo, panic_policy: PanicPolicy,
+        vsync_frames: bool, frame_source: FrameSource, build: B,
     ) -> WindowHandle
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: 'static,
     {
-        let window_handler = Box::new(build(&mut crate::Window::new(&mut window)));
+        let _span = window_span!(ns_view = ?window.ns_view);
+        debug!(target: "baseview::lifecycle", parented, "created window");
+
+        // The view isn't in a window yet, so the context doesn't have a drawable during `build`.
+        // Shaders and other resources can be created, but the default framebuffer can only be
+        // drawn to once the view has been attached.
+        #[cfg(feature = "opengl")]
+        let gl_guard = window.gl_context.as_ref().map(|gl_context| unsafe {
+            gl_context.make_current_for_build(window_info.physical_size())
+        });
+        let window_handler = Box::new(crate::lifecycle::check_lifecycle(
+            crate::trace::instrument_handler(build(&mut crate::EventContext::new(&mut window))),
+        ));
+        #[cfg(feature = "opengl")]
+        drop(gl_guard);
 
-        let (parent_handle, window_handle) = ParentHandle::new(window.raw_window_handle());
+        let (parent_handle, window_handle) =
+            ParentHandle::new(window.platform_window_handle(), window.id);
         let parent_handle = if parented { Some(parent_handle) } else { None };
 
         let retain_count_after_build: usize = unsafe { msg_send![window.ns_view, retainCount] };
@@ -321,16 +671,39 @@ impl Window {
             keyboard_state: KeyboardState::new(),
             cursor_state: Default::default(),
             frame_timer: None,
+            display_link: None,
+            frame_source,
+            frame_pending: true,
             retain_count_after_build,
             window_info,
-            _parent_handle: parent_handle,
+            parent_handle,
+            panic_policy,
+            handler_panicked: false,
+            handler_active: false,
+            deferred_events: VecDeque::new(),
+            lifecycle: Lifecycle::Created,
+            drag_event_order: DragEventOrder::Idle,
+            detached: false,
+            visible: false,
+            ignored_key_equivalent: None,
         }));
 
         unsafe {
             (*(*window_state_ptr).window.ns_view)
                 .set_ivar(BASEVIEW_STATE_IVAR, window_state_ptr as *mut c_void);
 
-            WindowState::setup_timer(window_state_ptr);
+            if vsync_frames && frame_source == FrameSource::Internal {
+                (*window_state_ptr).display_link = DisplayLink::new(window_state_ptr);
+                if (*window_state_ptr).display_link.is_none() {
+                    debug!(
+                        target: "baseview::frame",
+                        "could not create a display link, falling back to the frame timer"
+                    );
+                }
+            }
+
+            // The frame timer is only started once the handler has received its initial `Resized`
+            // event
         }
 
         window_handle
@@ -340,10 +713,60 @@ impl Window {
         self.close_requested = true;
     }
 
+    pub fn cancel_close(&mut self) {
+        self.close_requested = false;
+    }
+
+    pub fn set_animating(&mut self, animating: bool) {
+        // The frame timer is started or stopped by the `WindowState` after the handler returns
+        self.animating = animating;
+    }
+
+    pub fn invalidate_rect(&mut self, rect: Rect) {
+        self.damage.add(rect);
+    }
+
+    pub fn damage(&self) -> Region {
+        self.damage.current().clone()
+    }
+
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.frame_interval = interval;
+    }
+
+    pub fn pause_frames(&mut self, paused: bool) {
+        self.frames_paused = paused;
+    }
+
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.scale_policy = policy;
+        self.scale_changed = true;
+    }
+
+    pub fn set_scale_override(&mut self, scale: Option<f64>) {
+        self.scale_override = scale;
+        self.scale_changed = true;
+    }
+
+    /// The window's scale factor according to its scale policy and override.
+    fn scale_factor(&self) -> f64 {
+        match self.scale_policy.with_override(self.scale_override) {
+            WindowScalePolicy::ScaleFactor(scale) => scale,
+            _ => self.backing_scale,
+        }
+    }
+
+    /// How many logical pixels a point covers. This is 1 unless the scale policy or an override
+    /// made the window's scale factor differ from the backing scale factor.
+    pub(super) fn logical_per_point(&self) -> f64 {
+        self.backing_scale / self.scale_factor()
+    }
+
     pub fn resize(&mut self, size: Size) {
         // NOTE: macOS gives you a personal rave if you pass in fractional pixels here. Even though
         //       the size is in fractional pixels.
-        let size = NSSize::new(size.width.round(), size.height.round());
+        let points = self.logical_per_point();
+        let size = NSSize::new((size.width / points).round(), (size.height / points).round());
 
         unsafe { NSView::setFrameSize(self.ns_view, size) };
         unsafe {
@@ -372,17 +795,50 @@ impl Window {
                 let window_frame: NSRect = msg_send![ns_window, frame];
 
                 // Convert from top-left to bottom-left coordinates
-                let y_bottom_left =
-                    screen_frame.size.height - position.y.round() - window_frame.size.height;
-                let origin = NSPoint::new(position.x.round(), y_bottom_left);
+                let points = self.logical_per_point();
+                let (x, y) = ((position.x / points).round(), (position.y / points).round());
+                let y_bottom_left = screen_frame.size.height - y - window_frame.size.height;
+                let origin = NSPoint::new(x, y_bottom_left);
 
                 let _: () = msg_send![ns_window, setFrameOrigin: origin];
             }
         }
     }
 
-    // TODO Improve me
-    fn drag_image(size: NSSize) -> StrongPtr {
+    pub fn drag_and_drop_available(&self) -> bool {
+        self.drag_and_drop_available
+    }
+
+    pub fn is_visible(&self) -> bool {
+        unsafe { view_is_visible(self.ns_view) }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        unsafe {
+            let ns_window: id = msg_send![self.ns_view, window];
+            ns_window != nil && ns_window.isMiniaturized() == YES
+        }
+    }
+
+    pub fn has_focus(&self) -> bool {
+        unsafe {
+            let ns_window: id = msg_send![self.ns_view, window];
+            ns_window != nil && ns_window.isKeyWindow() == YES
+        }
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        // Parented views are sized by the host
+        match self.ns_window {
+            Some(ns_window) => unsafe {
+                ns_window.styleMask().contains(NSWindowStyleMask::NSResizableWindowMask)
+            },
+            None => false,
+        }
+    }
+
+    /// The image shown for drags that were started without a [`DragImage`].
+    fn default_drag_image(size: NSSize) -> StrongPtr {
         unsafe {
             let image = NSImage::alloc(nil).initWithSize_(size);
             let color: id =
@@ -395,18 +851,63 @@ impl Window {
         }
     }
 
-    pub fn start_drag(&self, data: Data) {
+    pub fn start_drag(&self, data: Data, effect: DropEffect, image: Option<DragImage>) {
+        if !self.drag_and_drop_available {
+            return;
+        }
+        self.drag_effect.set(effect);
+
+        // TODO: Use `NSFilePromiseProvider` for virtual files instead of writing them to disk
+        let data = match data.with_virtual_file_on_disk() {
+            Ok(data) => data,
+            Err(_err) => {
+                debug!(target: "baseview::dnd", error = %_err, "could not write virtual file");
+                return;
+            }
+        };
+
         match data {
             Data::Filepath(p) => unsafe {
-                let size = NSSize::new(20.0, 20.0);
-                let image = Self::drag_image(size);
-
-                let file_url = NSURL::fileURLWithPath_(
-                    nil,
-                    NSString::alloc(nil)
-                        .init_str(&p.into_os_string().into_string().unwrap())
-                        .autorelease(),
-                );
+                // The view is flipped, so the hotspot is measured from the image's top left like
+                // the view's own coordinates are
+                let (image, size, hotspot) = match &image {
+                    Some(image) => {
+                        let scale = self.backing_scale;
+                        let (hotspot_x, hotspot_y) = image.hotspot();
+                        let ns_image =
+                            create_image(image.rgba(), image.width(), image.height(), scale);
+                        (
+                            StrongPtr::retain(ns_image),
+                            NSSize::new(
+                                image.width() as f64 / scale,
+                                image.height() as f64 / scale,
+                            ),
+                            NSPoint::new(hotspot_x as f64 / scale, hotspot_y as f64 / scale),
+                        )
+                    }
+                    None => {
+                        let size = NSSize::new(20.0, 20.0);
+                        let hotspot = NSPoint::new(size.width / 2.0, size.height / 2.0);
+                        (Self::default_drag_image(size), size, hotspot)
+                    }
+                };
+
+                // This takes the path's bytes as is, so it doesn't need to be valid UTF-8
+                let Ok(path) = CString::new(p.as_os_str().as_bytes()) else {
+                    debug!(
+                        target: "baseview::dnd",
+                        path = ?p,
+                        "can't drag a path containing null bytes"
+                    );
+                    return;
+                };
+                let is_directory = if p.is_dir() { YES } else { NO };
+                let file_url: id = msg_send![
+                    class!(NSURL),
+                    fileURLWithFileSystemRepresentation: path.as_ptr()
+                    isDirectory: is_directory
+                    relativeToURL: nil
+                ];
                 let event = self
                     .last_mouse_down
                     .borrow()
@@ -419,16 +920,14 @@ impl Window {
                     msg_send![self.ns_view, convertPoint:point fromView:nil]
                 };
 
-                let frame = NSRect::new(
-                    NSPoint::new(point.x - (size.width / 2.0), point.y - (size.height / 2.0)),
-                    size,
-                );
+                let frame =
+                    NSRect::new(NSPoint::new(point.x - hotspot.x, point.y - hotspot.y), size);
 
                 let dragging_item: id = msg_send![class!(NSDraggingItem), alloc];
                 let dragging_item: id =
                     msg_send![dragging_item, initWithPasteboardWriter: file_url];
                 let dragging_item: id = msg_send![dragging_item, autorelease];
-                let _: id = msg_send![dragging_item, setDraggingFrame: frame contents: image];
+                let _: id = msg_send![dragging_item, setDraggingFrame: frame contents: *image];
 
                 let items = NSArray::arrayWithObject(nil, dragging_item);
                 let _dragging_session: id = msg_send![
@@ -442,58 +941,136 @@ impl Window {
         }
     }
 
+    pub fn set_drop_effect(&mut self, effect: Option<DropEffect>) {
+        self.drop_target_effect.chosen = Some(effect);
+    }
+
+    pub fn drop_effect(&self) -> Option<DropEffect> {
+        let drop_target_valid = self.drop_target_valid.as_ref().is_none_or(|f| f());
+
+        if drop_target_valid {
+            self.drop_target_effect.effect()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        let state: &mut WindowState = unsafe { WindowState::from_field(&*self.ns_view) };
+        state.cursor_state.confined = confined;
+    }
+
     pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
         unsafe {
-            let ns_window = self.ns_window.or(self.parent_ns_window).unwrap_or(ptr::null_mut());
             let state: &mut WindowState = WindowState::from_field(&*self.ns_view);
-            if mouse_cursor == MouseCursor::Hidden {
-                if state.cursor_state.visible {
-                    state.cursor_state.visible = false;
-                    let _: id = msg_send![class!(NSCursor), hide];
-                }
-                return;
-            } else if mouse_cursor != MouseCursor::Hidden && !state.cursor_state.visible {
-                state.cursor_state.visible = true;
-                let _: id = msg_send![class!(NSCursor), unhide];
+            state.cursor_state.mouse_cursor = mouse_cursor;
+            state.update_cursor(state.pointer_position_in_view());
+        }
+    }
+
+    pub fn set_cursor_regions(&mut self, regions: Vec<(Rect, MouseCursor)>) {
+        unsafe {
+            let state: &mut WindowState = WindowState::from_field(&*self.ns_view);
+            state.cursor_state.regions.set(regions);
+            state.update_cursor(state.pointer_position_in_view());
+        }
+    }
+
+    pub fn open_file_dialog(&mut self, options: FileDialogOptions) -> Result<DialogId, Error> {
+        // Parented views live in the host's window, which shouldn't get a sheet it doesn't know
+        // about
+        let id = DialogId::next();
+        unsafe { super::file_dialog::begin(self.ns_view, self.ns_window, id, &options) };
+
+        Ok(id)
+    }
+
+    pub fn request_attention(&mut self, attention: AttentionType) {
+        // `NSCriticalRequest` and `NSInformationalRequest`
+        let request_type: isize = match attention {
+            AttentionType::Informational => 10,
+            AttentionType::Critical => 0,
+        };
+
+        self.stop_attention();
+        unsafe {
+            // This returns 0 if the application is already active, in which case there's nothing
+            // to cancel
+            let request: isize = msg_send![NSApp(), requestUserAttention: request_type];
+            if request != 0 {
+                self.attention_request = Some(request);
             }
-            let cursor: id = match mouse_cursor {
-                MouseCursor::Default => NSCursor::arrow_cursor(nil),
-                MouseCursor::Hand => NSCursor::open_hand_cursor(nil),
-                MouseCursor::PointingHand => NSCursor::pointing_hand_cursor(nil),
-                MouseCursor::HandGrabbing => NSCursor::closed_hand_cursor(nil),
-                MouseCursor::Text => NSCursor::i_beam_cursor(nil),
-                MouseCursor::VerticalText => NSCursor::i_beam_cursor_for_vertical_layout(nil),
-                MouseCursor::Crosshair => NSCursor::crosshair_cursor(nil),
-                MouseCursor::EResize => NSCursor::resize_right_cursor(nil),
-                MouseCursor::WResize => NSCursor::resize_left_cursor(nil),
-                MouseCursor::NResize => NSCursor::resize_up_cursor(nil),
-                MouseCursor::SResize => NSCursor::resize_down_cursor(nil),
-                MouseCursor::NsResize | MouseCursor::RowResize => {
-                    NSCursor::resize_up_down_cursor(nil)
-                }
-                MouseCursor::EwResize | MouseCursor::ColResize => {
-                    NSCursor::resize_left_right_cursor(nil)
-                }
-                MouseCursor::NotAllowed => NSCursor::operation_not_allowed_cursor(nil),
-                MouseCursor::Copy => NSCursor::drag_copy_cursor(nil),
-                MouseCursor::NeResize => msg_send![class!(NSCursor), _windowResizeNorthEastCursor],
-                MouseCursor::NwResize => msg_send![class!(NSCursor), _windowResizeNorthWestCursor],
-                MouseCursor::SeResize => msg_send![class!(NSCursor), _windowResizeSouthEastCursor],
-                MouseCursor::SwResize => msg_send![class!(NSCursor), _windowResizeSouthWestCursor],
-                MouseCursor::NeswResize => {
-                    msg_send![class!(NSCursor), _windowResizeNorthEastSouthWestCursor]
+        }
+    }
+
+    pub fn stop_attention(&mut self) {
+        if let Some(request) = self.attention_request.take() {
+            unsafe {
+                let () = msg_send![NSApp(), cancelUserAttentionRequest: request];
+            }
+        }
+    }
+
+    pub fn register_cursor(&mut self, icon: CursorIcon) -> CursorId {
+        let id = CursorId::next();
+        unsafe {
+            let state: &mut WindowState = WindowState::from_field(&*self.ns_view);
+            let cursor = create_cursor(&icon, self.backing_scale);
+            state.cursor_state.custom_cursors.insert(id, cursor);
+        }
+
+        id
+    }
+
+    pub fn set_opacity(&mut self, opacity: f64) -> Result<(), Error> {
+        unsafe {
+            match self.ns_window {
+                Some(ns_window) => {
+                    let () = msg_send![ns_window, setAlphaValue: opacity];
                 }
-                MouseCursor::NwseResize => {
-                    msg_send![class!(NSCursor), _windowResizeNorthWestSouthEastCursor]
+                // The host's window isn't ours to change, so only the view becomes transparent
+                None => {
+                    let () = msg_send![self.ns_view, setAlphaValue: opacity];
                 }
-                MouseCursor::Help => msg_send![class!(NSCursor), _helpCursor],
-                MouseCursor::ZoomIn => msg_send![class!(NSCursor), _zoomInCursor],
-                MouseCursor::ZoomOut => msg_send![class!(NSCursor), _zoomOutCursor],
-                MouseCursor::Working => msg_send![class!(NSCursor), busyButClickableCursor],
-                _ => msg_send![class!(NSCursor), arrowCursor],
-            };
-            state.cursor_state.cursor = cursor;
-            let _: id = msg_send![ns_window, invalidateCursorRectsForView: self.ns_view];
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        self.held_inputs.pressed_keys()
+    }
+
+    pub(crate) fn event_clock(&self) -> &EventClock {
+        &self.event_clock
+    }
+
+    /// See [`crate::EventContext::set_user_data()`].
+    pub(crate) fn user_data(&self) -> &UserData {
+        &self.user_data
+    }
+
+    pub fn with_ns_view<R>(&mut self, f: impl FnOnce(*mut c_void) -> R) -> R {
+        f(self.ns_view as *mut c_void)
+    }
+
+    pub fn surface_info(&self) -> SurfaceInfo {
+        #[cfg(feature = "opengl")]
+        if let Some(gl_context) = &self.gl_context {
+            return SurfaceInfo::from_gl_pixel_format(gl_context.pixel_format());
+        }
+
+        // The view draws into its window's backing store, which uses 8 bits per channel
+        SurfaceInfo {
+            color_depth: 32,
+            has_alpha: true,
+            #[cfg(feature = "opengl")]
+            gl: None,
         }
     }
 
@@ -503,38 +1080,350 @@ impl Window {
     }
 
     #[cfg(feature = "opengl")]
-    fn create_gl_context(ns_window: Option<id>, ns_view: id, config: GlConfig) -> GlContext {
-        let mut handle = AppKitWindowHandle::empty();
-        handle.ns_window = ns_window.unwrap_or(ptr::null_mut()) as *mut c_void;
-        handle.ns_view = ns_view as *mut c_void;
-        let handle = RawWindowHandleWrapper { handle: RawWindowHandle::AppKit(handle) };
+    pub fn gl_context_error(&self) -> Option<&GlError> {
+        self.gl_context_error.as_ref()
+    }
+
+    /// Called right before the handler receives its final `WillClose` event, so the handler can
+    /// still free its GPU resources. The context is released together with the window state
+    /// afterwards.
+    #[cfg(feature = "opengl")]
+    fn make_gl_context_current(&self) {
+        if let Some(gl_context) = &self.gl_context {
+            unsafe { gl_context.make_current() };
+        }
+    }
+
+    /// Create the view's OpenGL context, if the options ask for one. When creating the context
+    /// fails and the config allows falling back to a window without OpenGL, the error is returned
+    /// instead of panicking.
+    #[cfg(feature = "opengl")]
+    fn create_gl_context(
+        ns_window: Option<id>, ns_view: id, config: Option<GlConfig>, vsync_frames: bool,
+    ) -> (Option<GlContext>, Option<GlError>) {
+        let Some(mut config) = config else { return (None, None) };
+        // Frames driven by the display link should also be presented in sync with the display
+        config.vsync |= vsync_frames;
+        let fallback_to_no_gl = config.fallback_to_no_gl;
+
+        let handle = PlatformWindowHandle::AppKit {
+            ns_window: ns_window.unwrap_or(ptr::null_mut()) as *mut c_void,
+            ns_view: ns_view as *mut c_void,
+        };
+
+        match unsafe { GlContext::create(handle, config) } {
+            Ok(context) => (Some(context), None),
+            Err(err) if fallback_to_no_gl => (None, Some(err)),
+            Err(err) => panic!("Could not create OpenGL context: {}", err),
+        }
+    }
+
+    #[cfg(feature = "accesskit")]
+    pub fn update_accesskit_tree(&mut self, update: impl FnOnce() -> accesskit::TreeUpdate) {
+        let events = self
+            .accesskit
+            .as_mut()
+            .and_then(|accesskit| accesskit.adapter.update_if_active(update));
+        if let Some(events) = events {
+            events.raise();
+        }
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> PlatformWindowHandle {
+        PlatformWindowHandle::AppKit {
+            ns_window: self.ns_window.unwrap_or(ptr::null_mut()) as *mut c_void,
+            ns_view: self.ns_view as *mut c_void,
+        }
+    }
+
+    pub(crate) fn platform_display_handle(&self) -> PlatformDisplayHandle {
+        PlatformDisplayHandle::AppKit
+    }
+}
+
+/// The view's AccessKit adapter, along with the requests it made that haven't reached the handler
+/// yet.
+#[cfg(feature = "accesskit")]
+pub(super) struct AccessKit {
+    pub adapter: accesskit_macos::Adapter,
+    events: Rc<RefCell<VecDeque<AccessKitEvent>>>,
+    ns_view: id,
+}
+
+#[cfg(feature = "accesskit")]
+impl AccessKit {
+    /// # Safety
+    ///
+    /// `ns_view` must be a valid, unreleased `NSView`.
+    unsafe fn new(ns_view: id) -> Self {
+        let events = Rc::new(RefCell::new(VecDeque::new()));
+        let action_handler = AccessKitHandler(Self::sender(ns_view, &events));
+        let adapter = accesskit_macos::Adapter::new(ns_view as *mut c_void, false, action_handler);
+
+        Self { adapter, events, ns_view }
+    }
+
+    /// The handler for the adapter's methods that may need to activate the tree. The view passes
+    /// this along with AppKit's accessibility queries.
+    pub fn activation_handler(&self) -> AccessKitHandler<impl FnMut(AccessKitEvent)> {
+        AccessKitHandler(Self::sender(self.ns_view, &self.events))
+    }
+
+    /// Take the requests that were queued by the handlers returned from [`Self::sender()`].
+    pub fn take_events(&self) -> VecDeque<AccessKitEvent> {
+        self.events.take()
+    }
 
-        unsafe { GlContext::create(&handle, config).expect("Could not create OpenGL context") }
+    /// AccessKit calls its handlers while the adapter and its tree are still borrowed, and usually
+    /// while the window state is borrowed for an accessibility query. The requests are queued
+    /// instead, and the view passes them on to the handler from the next run loop iteration.
+    fn sender(
+        ns_view: id, events: &Rc<RefCell<VecDeque<AccessKitEvent>>>,
+    ) -> impl FnMut(AccessKitEvent) + 'static {
+        let events = Rc::clone(events);
+        move |event| {
+            let mut events = events.borrow_mut();
+            if events.is_empty() {
+                unsafe {
+                    let () = msg_send![ns_view, performSelector: sel!(handleAccessKitEvents)
+                                                     withObject: nil
+                                                     afterDelay: 0.0f64];
+                }
+            }
+
+            events.push_back(event);
+        }
     }
 }
 
 pub struct CursorState {
+    /// The cursor set through [`crate::EventContext::set_mouse_cursor()`].
+    pub mouse_cursor: MouseCursor,
+    pub regions: CursorRegions,
+    /// The cursor that's currently shown, resolved from the two fields above.
+    pub shown: MouseCursor,
+    /// The `NSCursor` for `shown`, which is used for the view's cursor rect.
     pub cursor: id,
     pub visible: bool,
+    /// The cursors created through [`crate::EventContext::register_cursor()`]. These are released
+    /// together with the window state.
+    pub custom_cursors: HashMap<CursorId, StrongPtr>,
+    /// See [`crate::EventContext::set_cursor_confined()`]. AppKit can't confine the cursor, so the
+    /// view moves it back inside when it leaves.
+    pub confined: bool,
 }
 
 impl Default for CursorState {
     fn default() -> Self {
         let cursor = unsafe { NSCursor::arrow_cursor(nil) };
-        Self { visible: true, cursor }
+        Self {
+            mouse_cursor: MouseCursor::Default,
+            regions: CursorRegions::default(),
+            shown: MouseCursor::Default,
+            cursor,
+            visible: true,
+            custom_cursors: HashMap::new(),
+            confined: false,
+        }
+    }
+}
+
+/// The `NSCursor` for a cursor other than [`MouseCursor::Hidden`].
+unsafe fn ns_cursor(
+    mouse_cursor: MouseCursor, custom_cursors: &HashMap<CursorId, StrongPtr>,
+) -> id {
+    match mouse_cursor {
+        MouseCursor::Default => NSCursor::arrow_cursor(nil),
+        MouseCursor::Hand => NSCursor::open_hand_cursor(nil),
+        MouseCursor::PointingHand => NSCursor::pointing_hand_cursor(nil),
+        MouseCursor::HandGrabbing => NSCursor::closed_hand_cursor(nil),
+        MouseCursor::Text => NSCursor::i_beam_cursor(nil),
+        MouseCursor::VerticalText => NSCursor::i_beam_cursor_for_vertical_layout(nil),
+        MouseCursor::Crosshair => NSCursor::crosshair_cursor(nil),
+        MouseCursor::EResize => NSCursor::resize_right_cursor(nil),
+        MouseCursor::WResize => NSCursor::resize_left_cursor(nil),
+        MouseCursor::NResize => NSCursor::resize_up_cursor(nil),
+        MouseCursor::SResize => NSCursor::resize_down_cursor(nil),
+        MouseCursor::NsResize | MouseCursor::RowResize => NSCursor::resize_up_down_cursor(nil),
+        MouseCursor::EwResize | MouseCursor::ColResize => NSCursor::resize_left_right_cursor(nil),
+        MouseCursor::NotAllowed => NSCursor::operation_not_allowed_cursor(nil),
+        MouseCursor::Copy => NSCursor::drag_copy_cursor(nil),
+        MouseCursor::NeResize => msg_send![class!(NSCursor), _windowResizeNorthEastCursor],
+        MouseCursor::NwResize => msg_send![class!(NSCursor), _windowResizeNorthWestCursor],
+        MouseCursor::SeResize => msg_send![class!(NSCursor), _windowResizeSouthEastCursor],
+        MouseCursor::SwResize => msg_send![class!(NSCursor), _windowResizeSouthWestCursor],
+        MouseCursor::NeswResize => {
+            msg_send![class!(NSCursor), _windowResizeNorthEastSouthWestCursor]
+        }
+        MouseCursor::NwseResize => {
+            msg_send![class!(NSCursor), _windowResizeNorthWestSouthEastCursor]
+        }
+        MouseCursor::Help => msg_send![class!(NSCursor), _helpCursor],
+        MouseCursor::ZoomIn => msg_send![class!(NSCursor), _zoomInCursor],
+        MouseCursor::ZoomOut => msg_send![class!(NSCursor), _zoomOutCursor],
+        MouseCursor::Working => msg_send![class!(NSCursor), busyButClickableCursor],
+        MouseCursor::Custom(id) => match custom_cursors.get(&id) {
+            Some(cursor) => **cursor,
+            None => NSCursor::arrow_cursor(nil),
+        },
+        _ => msg_send![class!(NSCursor), arrowCursor],
+    }
+}
+
+/// Whether the app has finished launching or has a delegate, which means that it was set up by
+/// something other than baseview.
+unsafe fn app_is_configured(app: id) -> bool {
+    let running: BOOL = msg_send![app, isRunning];
+    let delegate: id = msg_send![app, delegate];
+    running == YES || delegate != nil
+}
+
+/// Create a borderless `NSPanel` for [`WindowKind::Popup`]. Panels with
+/// `NSWindowStyleMaskNonactivatingPanel` can become the key window without activating the
+/// application, but borderless windows refuse to become the key window unless `canBecomeKeyWindow`
+/// is overridden, which is only done for focusable popups.
+unsafe fn create_popup_panel(
+    rect: NSRect, shadow: bool, rounded_corners: bool, focusable: bool,
+) -> id {
+    let class = if focusable {
+        let class_name = format!("BaseviewPopupPanel_{}", Uuid::new_v4().to_simple());
+        let mut class = ClassDecl::new(&class_name, class!(NSPanel)).unwrap();
+        class.add_method(
+            sel!(canBecomeKeyWindow),
+            property_yes as extern "C" fn(&Object, Sel) -> BOOL,
+        );
+
+        class.register()
+    } else {
+        class!(NSPanel)
+    };
+
+    let style_mask = NSWindowStyleMask::NSBorderlessWindowMask.bits() | NS_NONACTIVATING_PANEL_MASK;
+    let panel: id = msg_send![class, alloc];
+    let panel: id = msg_send![
+        panel,
+        initWithContentRect: rect
+        styleMask: style_mask
+        backing: NSBackingStoreBuffered
+        defer: NO
+    ];
+
+    // The shadow follows the rounded corners because the rest of the window is transparent
+    let () = msg_send![panel, setHasShadow: if shadow { YES } else { NO }];
+    if rounded_corners {
+        let () = msg_send![panel, setOpaque: NO];
+        let clear_color: id = msg_send![class!(NSColor), clearColor];
+        let () = msg_send![panel, setBackgroundColor: clear_color];
     }
+    // Panels hide themselves when the application isn't active, which a popup never makes it
+    let () = msg_send![panel, setHidesOnDeactivate: NO];
+
+    panel
+}
+
+/// Clip the view's contents to rounded corners. The popup's panel is transparent, so this also
+/// rounds the window.
+unsafe fn round_view_corners(ns_view: id) {
+    let () = msg_send![ns_view, setWantsLayer: YES];
+    let layer: id = msg_send![ns_view, layer];
+    let () = msg_send![layer, setCornerRadius: POPUP_CORNER_RADIUS];
+    let () = msg_send![layer, setMasksToBounds: YES];
+}
+
+/// Create an autoreleased `NSImage` from non-premultiplied RGBA pixels. The image's size in points
+/// is its size in pixels divided by `scale`, so it covers the same number of physical pixels as on
+/// the other platforms.
+unsafe fn create_image(rgba: &[u8], width: u32, height: u32, scale: f64) -> id {
+    let pixels_wide = width as isize;
+    let pixels_high = height as isize;
+
+    // `NSBitmapFormatAlphaNonpremultiplied`
+    let bitmap_format: usize = 1 << 1;
+    let color_space = NSString::alloc(nil).init_str("NSDeviceRGBColorSpace").autorelease();
+    let bitmap: id = msg_send![class!(NSBitmapImageRep), alloc];
+    let bitmap: id = msg_send![
+        bitmap,
+        initWithBitmapDataPlanes: ptr::null_mut::<*mut u8>()
+        pixelsWide: pixels_wide
+        pixelsHigh: pixels_high
+        bitsPerSample: 8isize
+        samplesPerPixel: 4isize
+        hasAlpha: YES
+        isPlanar: NO
+        colorSpaceName: color_space
+        bitmapFormat: bitmap_format
+        bytesPerRow: pixels_wide * 4
+        bitsPerPixel: 32isize
+    ];
+    let bitmap: id = msg_send![bitmap, autorelease];
+    let bitmap_data: *mut u8 = msg_send![bitmap, bitmapData];
+    ptr::copy_nonoverlapping(rgba.as_ptr(), bitmap_data, rgba.len());
+
+    let size = NSSize::new(width as f64 / scale, height as f64 / scale);
+    let image: id = msg_send![class!(NSImage), alloc];
+    let image: id = msg_send![image, initWithSize: size];
+    let image: id = msg_send![image, autorelease];
+    let () = msg_send![image, addRepresentation: bitmap];
+
+    image
+}
+
+/// Create an `NSCursor` from the icon's image, scaled like [`create_image()`] does.
+unsafe fn create_cursor(icon: &CursorIcon, scale: f64) -> StrongPtr {
+    let image = create_image(icon.rgba(), icon.width(), icon.height(), scale);
+
+    // The hotspot is in the image's coordinate space, with the origin at the top left
+    let (hotspot_x, hotspot_y) = icon.hotspot();
+    let hotspot = NSPoint::new(hotspot_x as f64 / scale, hotspot_y as f64 / scale);
+    let cursor: id = msg_send![class!(NSCursor), alloc];
+    let cursor: id = msg_send![cursor, initWithImage: image hotSpot: hotspot];
+
+    StrongPtr::new(cursor)
 }
 
 pub(super) struct WindowState {
     pub(crate) window: Window,
     window_handler: Box<dyn WindowHandler>,
     keyboard_state: KeyboardState,
-    frame_timer: Option<CFRunLoopTimer>,
-    _parent_handle: Option<ParentHandle>,
+    /// The frame timer along with the interval it was created with.
+    frame_timer: Option<(CFRunLoopTimer, Duration)>,
+    /// Replaces the frame timer when [`WindowOpenOptions::vsync_frames`] is set.
+    display_link: Option<DisplayLink>,
+    /// With [`FrameSource::External`] there's neither a frame timer nor a display link, and frames
+    /// are drawn by [`Self::trigger_external_frame()`] instead.
+    frame_source: FrameSource,
+    /// Set when events were delivered to the handler so a single frame gets drawn even when the
+    /// window isn't animating. This survives pausing the frames.
+    frame_pending: bool,
+    parent_handle: Option<ParentHandle>,
     pub retain_count_after_build: usize,
     pub(crate) cursor_state: CursorState,
     /// The last known window info for this window.
     pub window_info: WindowInfo,
+    panic_policy: PanicPolicy,
+    /// Set once the handler has panicked. The handler won't be called again after that.
+    handler_panicked: bool,
+    /// Set while the handler is being called. The handler may run a native modal loop, like an
+    /// `NSSavePanel`, which keeps dispatching events and timers to this view while the handler is
+    /// still on the stack. Those calls must not re-enter the handler.
+    handler_active: bool,
+    /// Events that arrived while `handler_active` was set. These are sent once the handler returns.
+    deferred_events: VecDeque<Event>,
+    /// Size changes before the initial `Resized` event has been sent only update `window_info`,
+    /// and no frames are drawn until then. The handler isn't called at all anymore after
+    /// `WillClose`.
+    lifecycle: Lifecycle,
+    /// The incoming drag events sent for the drag that's over the view.
+    drag_event_order: DragEventOrder,
+    /// Set while the view isn't in a window, for instance after the host removed it from its
+    /// superview. No frames are drawn while the view is detached. If the view gets deallocated in
+    /// this state without the retain count hack in `release` noticing, `dealloc` closes the window.
+    detached: bool,
+    /// Whether the handler was last told that the view is visible. See [`WindowEvent::Shown`].
+    visible: bool,
+    /// The timestamp and key code of the last key equivalent the handler ignored. AppKit sends
+    /// the same event to `keyDown:` when the menu doesn't handle it either.
+    ignored_key_equivalent: Option<(f64, u16)>,
 }
 
 impl WindowState {
@@ -550,24 +1439,158 @@ impl WindowState {
     }
 
     pub(super) fn trigger_event(&mut self, event: Event) -> EventStatus {
-        self.window_handler.on_event(&mut crate::Window::new(&mut self.window), event)
+        let _span = window_span!(ns_view = ?self.window.ns_view);
+        if self.handler_panicked {
+            return EventStatus::Ignored;
+        }
+
+        if self.handler_active {
+            trace!(target: "baseview::handler", "deferring event sent while the handler is running");
+            self.deferred_events.push_back(event);
+            return EventStatus::Ignored;
+        }
+
+        match self.dispatch_event(event) {
+            Some(status) => {
+                self.send_deferred_events();
+                status
+            }
+            None => EventStatus::Ignored,
+        }
+    }
+
+    /// Send an event for the drag that's over the view. These are deferred like any other event,
+    /// so checking their order when they're triggered is enough.
+    pub(super) fn trigger_drag_event(&mut self, event: WindowEvent) {
+        self.drag_event_order.track(&event);
+        self.trigger_event(Event::Window(event));
+    }
+
+    /// Whether the handler was sent `DragEnter` events for the drag that's over the view, and the
+    /// drag hasn't ended yet.
+    pub(super) fn in_drag(&self) -> bool {
+        self.drag_event_order.in_drag()
+    }
+
+    /// Send an event to the handler. Returns `None` if the handler panicked, in which case the
+    /// handler must not be called anymore. See [`Self::handle_handler_panic()`].
+    fn dispatch_event(&mut self, event: Event) -> Option<EventStatus> {
+        // Events that were deferred while the handler was handling `WillClose` are dropped here
+        if !self.lifecycle.can_send_events() {
+            return Some(EventStatus::Ignored);
+        }
+
+        // Input events are sent while AppKit handles the `NSEvent` they came from
+        let native_time = match event {
+            Event::Mouse(_) | Event::Keyboard(_) => unsafe { current_event_time() },
+            _ => None,
+        };
+        let outer_event_time = self.window.event_clock.begin_event(native_time);
+        self.window.held_inputs.track(&event);
+        self.window.event_clock.track(&event);
+
+        let is_will_close = matches!(event, Event::Window(WindowEvent::WillClose));
+        if is_will_close {
+            self.lifecycle = Lifecycle::Closing;
+        }
+        let (window, window_handler) = (&mut self.window, &mut self.window_handler);
+        self.handler_active = true;
+        let status = self
+            .panic_policy
+            .catch(|| window_handler.on_event(&mut crate::EventContext::new(window), event));
+        self.handler_active = false;
+        self.window.event_clock.end_event(outer_event_time);
+        if status.is_none() {
+            unsafe { self.handle_handler_panic() };
+            return None;
+        }
+        if is_will_close {
+            self.lifecycle = Lifecycle::Closed;
+        }
+
+        // Events always get to draw at least one frame, even if the window isn't animating. The
+        // timer is stopped again after that frame if the window still isn't animating.
+        if !is_will_close {
+            self.frame_pending = true;
+            unsafe {
+                self.handle_scale_change();
+                self.update_timer();
+            }
+        }
+
+        status
+    }
+
+    /// Send the events that arrived while the handler was running. Returns `false` if the handler
+    /// panicked.
+    fn send_deferred_events(&mut self) -> bool {
+        while let Some(event) = self.deferred_events.pop_front() {
+            if self.dispatch_event(event).is_none() {
+                return false;
+            }
+        }
+
+        true
     }
 
     pub(super) fn trigger_frame(&mut self) {
-        self.window_handler.on_frame(&mut crate::Window::new(&mut self.window));
+        let _span = window_span!(ns_view = ?self.window.ns_view);
+        if self.handler_panicked || !self.lifecycle.can_draw() || self.window.frames_paused {
+            return;
+        }
+
+        // The frame is drawn once the handler has returned, see `handler_active`
+        if self.handler_active {
+            self.frame_pending = true;
+            return;
+        }
+
+        self.frame_pending = false;
+        self.window.damage.begin_frame(self.window_info.logical_size());
+        let (window, window_handler) = (&mut self.window, &mut self.window_handler);
+        self.handler_active = true;
+        let result = self
+            .panic_policy
+            .catch(|| window_handler.on_frame(&mut crate::EventContext::new(window)));
+        self.handler_active = false;
+        if result.is_none() {
+            unsafe { self.handle_handler_panic() };
+            return;
+        }
+
+        // Rectangles invalidated while drawing this frame are drawn in the next one
+        self.frame_pending |= self.window.damage.has_pending();
+        unsafe { self.handle_scale_change() };
+
+        if !self.send_deferred_events() {
+            return;
+        }
+
+        unsafe {
+            self.update_timer();
+            self.handle_close_requests();
+        }
+    }
+
+    /// Close the window if the handler or the parent's [`WindowHandle`] asked for that. This may
+    /// free the window state, so it must not be used after calling this.
+    unsafe fn handle_close_requests(&mut self) {
+        // The handler may be running a nested run loop, for instance for a modal dialog, and closing
+        // the window would free the state it's using
+        if self.handler_active {
+            return;
+        }
 
         let mut do_close = false;
 
-        /* FIXME: Is it even necessary to check if the parent dropped the handle
-        // in MacOS?
-        // Check if the parent handle was dropped
+        // Check if the parent asked the window to close through its handle, which may have
+        // happened on another thread
         if let Some(parent_handle) = &self.parent_handle {
             if parent_handle.parent_did_drop() {
                 do_close = true;
                 self.window.close_requested = false;
             }
         }
-        */
 
         // Check if the user requested the window to close
         if self.window.close_requested {
@@ -576,23 +1599,319 @@ impl WindowState {
         }
 
         if do_close {
-            unsafe {
-                if let Some(ns_window) = self.window.ns_window.take() {
-                    ns_window.close();
-                } else {
-                    // FIXME: How do we close a non-parented window? Is this even
-                    // possible in a DAW host usecase?
-                }
+            if let Some(ns_window) = self.window.ns_window.take() {
+                ns_window.close();
+            } else {
+                // The parent's view holds the last reference baseview doesn't own, so removing the
+                // view from it closes the window once the view gets released. Windows opened with
+                // `open_as_if_parented()` don't have a parent view, and they only close when the
+                // host releases the view.
+                let () = msg_send![self.window.ns_view, removeFromSuperview];
             }
         }
     }
 
+    /// Called after the handler has panicked, since panics cannot unwind through the Objective-C
+    /// callbacks. The handler gets a final `WillClose` event, after which the window is closed.
+    ///
+    /// The callback that called the handler may still use the view and call its superclass after
+    /// this returns, so the window is only closed once control has returned to the run loop.
+    /// Closing it right away could free the view and this state in the middle of that callback.
+    /// Delayed performs retain their target until they've run, which keeps the view alive until
+    /// then.
+    unsafe fn handle_handler_panic(&mut self) {
+        self.handler_panicked = true;
+        self.stop_timer();
+
+        // The handler may also have panicked while handling `WillClose`
+        if self.lifecycle.can_send_events() {
+            self.lifecycle = Lifecycle::Closing;
+
+            #[cfg(feature = "opengl")]
+            self.window.make_gl_context_current();
+
+            let (window, window_handler) = (&mut self.window, &mut self.window_handler);
+            self.panic_policy.catch(|| {
+                window_handler.on_event(
+                    &mut crate::EventContext::new(window),
+                    Event::Window(WindowEvent::WillClose),
+                )
+            });
+        }
+        self.lifecycle = Lifecycle::Closed;
+
+        if let Some(ns_window) = self.window.ns_window.take() {
+            let () = msg_send![ns_window, performSelector: sel!(close)
+                                               withObject: nil
+                                               afterDelay: 0.0f64];
+        } else {
+            let () = msg_send![self.window.ns_view, performSelector: sel!(removeFromSuperview)
+                                                          withObject: nil
+                                                          afterDelay: 0.0f64];
+        }
+    }
+
+    /// Called when the window stops being the key window. Buttons and keys that are still held down
+    /// won't be released in this view, so the handler needs to be told about that now to prevent
+    /// gestures from getting stuck.
+    pub(super) fn handle_focus_lost(&mut self) {
+        #[cfg(feature = "accesskit")]
+        self.update_accesskit_focus(false);
+
+        self.keyboard_state.reset();
+        self.cursor_state.confined = false;
+        for event in self.window.held_inputs.release_all() {
+            self.trigger_event(event);
+        }
+
+        self.trigger_event(Event::Window(WindowEvent::Unfocused));
+    }
+
+    /// Tell assistive technology whether the tree's focused node has the keyboard focus.
+    #[cfg(feature = "accesskit")]
+    pub(super) fn update_accesskit_focus(&mut self, focused: bool) {
+        let events = self
+            .window
+            .accesskit
+            .as_mut()
+            .and_then(|accesskit| accesskit.adapter.update_view_focus_state(focused));
+        if let Some(events) = events {
+            events.raise();
+        }
+    }
+
+    /// Store the view's new size and scale factor, and let the handler know about it.
+    pub(super) fn update_window_info(&mut self, new_window_info: WindowInfo) {
+        let old_window_info = std::mem::replace(&mut self.window_info, new_window_info);
+        if self.lifecycle != Lifecycle::Created {
+            self.trigger_event(Event::Window(WindowEvent::Resized {
+                new: new_window_info,
+                old: old_window_info,
+            }));
+        }
+    }
+
+    /// The window info for the view at the given size in points. This also updates the backing
+    /// scale factor the view's points are converted with.
+    pub(super) unsafe fn view_window_info(&mut self, size: NSSize) -> WindowInfo {
+        let ns_window: id = msg_send![self.window.ns_view, window];
+        self.window.backing_scale =
+            if ns_window == nil { 1.0 } else { NSWindow::backingScaleFactor(ns_window) };
+
+        let window_info = WindowInfo::from_logical_size(
+            Size::new(size.width, size.height),
+            self.window.backing_scale,
+        );
+        let scale = self.window.scale_factor();
+        if scale == self.window.backing_scale {
+            window_info
+        } else {
+            WindowInfo::from_physical_size(window_info.physical_size(), scale)
+        }
+    }
+
+    /// Apply a new scale policy or override after the handler has returned. The view keeps its
+    /// size, so only the logical size changes.
+    unsafe fn handle_scale_change(&mut self) {
+        if !std::mem::take(&mut self.window.scale_changed) {
+            return;
+        }
+
+        let bounds: NSRect = msg_send![self.window.ns_view, bounds];
+        let new_window_info = self.view_window_info(bounds.size);
+        if new_window_info == self.window_info {
+            return;
+        }
+
+        // The event is sent right after the handler's current call, like any other deferred event
+        let old_window_info = std::mem::replace(&mut self.window_info, new_window_info);
+        if self.lifecycle != Lifecycle::Created {
+            self.deferred_events.push_back(Event::Window(WindowEvent::Resized {
+                new: new_window_info,
+                old: old_window_info,
+            }));
+        }
+    }
+
+    /// Send the `Resized` event every window receives right after it has been opened. The
+    /// backing scale factor is only known after the view has been added to a window.
+    unsafe fn send_initial_resized(&mut self) {
+        let bounds: NSRect = msg_send![self.window.ns_view, bounds];
+
+        let window_info = self.view_window_info(bounds.size);
+        self.window_info = window_info;
+        self.lifecycle = Lifecycle::Ready;
+
+        self.trigger_event(Event::Window(WindowEvent::Resized {
+            new: window_info,
+            old: window_info,
+        }));
+    }
+
+    /// Send a `Shown` or `Hidden` event if the view's visibility has changed. See
+    /// [`view_is_visible()`].
+    pub(super) unsafe fn update_visibility(&mut self) {
+        if self.lifecycle == Lifecycle::Created {
+            return;
+        }
+
+        let visible = view_is_visible(self.window.ns_view);
+        if self.visible != visible {
+            self.visible = visible;
+            let event = if visible { WindowEvent::Shown } else { WindowEvent::Hidden };
+            self.trigger_event(Event::Window(event));
+        }
+    }
+
+    /// Show the cursor for the pointer at `position`, resolved from the cursor set by the handler
+    /// and the cursor regions. `position` is `None` while the pointer is outside of the view.
+    pub(super) unsafe fn update_cursor(&mut self, position: Option<Point>) {
+        let cursor_state = &mut self.cursor_state;
+        let mouse_cursor = cursor_state.regions.resolve(cursor_state.mouse_cursor, position);
+        cursor_state.shown = mouse_cursor;
+        if mouse_cursor == MouseCursor::Hidden {
+            if cursor_state.visible {
+                cursor_state.visible = false;
+                let _: id = msg_send![class!(NSCursor), hide];
+            }
+            return;
+        } else if !cursor_state.visible {
+            cursor_state.visible = true;
+            let _: id = msg_send![class!(NSCursor), unhide];
+        }
+
+        let cursor = ns_cursor(mouse_cursor, &cursor_state.custom_cursors);
+        cursor_state.cursor = cursor;
+        let ns_window =
+            self.window.ns_window.or(self.window.parent_ns_window).unwrap_or(ptr::null_mut());
+        let _: id = msg_send![ns_window, invalidateCursorRectsForView: self.window.ns_view];
+        // The cursor rects are only rebuilt when the pointer moves, so the cursor is also set
+        // right away if the pointer is already over the view
+        if position.is_some() {
+            let _: () = msg_send![cursor, set];
+        }
+    }
+
+    /// Update the cursor after the pointer moved, if that changed which cursor should be shown.
+    pub(super) unsafe fn pointer_moved(&mut self, position: Option<Point>) {
+        let cursor_state = &self.cursor_state;
+        if cursor_state.regions.resolve(cursor_state.mouse_cursor, position) != cursor_state.shown {
+            self.update_cursor(position);
+        }
+    }
+
+    /// Send a `CursorEntered` followed by a `CursorMoved` event if the mouse cursor is already
+    /// inside of the view. Hosts often open editors right under the mouse cursor, and the handler
+    /// should know about this before the first frame gets drawn. This only works once the view has
+    /// been added to a window.
+    unsafe fn send_initial_cursor_position(&mut self) {
+        let Some(position) = self.pointer_position_in_view() else {
+            return;
+        };
+
+        let modifiers: NSEventModifierFlags = msg_send![class!(NSEvent), modifierFlags];
+
+        self.trigger_event(Event::Mouse(MouseEvent::CursorEntered));
+        self.trigger_event(Event::Mouse(MouseEvent::CursorMoved {
+            position,
+            delta: PhyPoint::default(),
+            modifiers: make_modifiers(modifiers),
+            buttons: held_mouse_buttons(),
+        }));
+    }
+
+    /// The pointer's position within the view, or `None` if it's outside of the view or the view
+    /// hasn't been added to a window yet.
+    pub(super) unsafe fn pointer_position_in_view(&self) -> Option<Point> {
+        let ns_view = self.window.ns_view;
+        let ns_window: id = msg_send![ns_view, window];
+        if ns_window == nil {
+            return None;
+        }
+
+        let position = self.view_position(msg_send![ns_window, mouseLocationOutsideOfEventStream]);
+        let bounds: NSRect = msg_send![ns_view, bounds];
+        let inside = position.x >= 0.0
+            && position.y >= 0.0
+            && position.x < bounds.size.width
+            && position.y < bounds.size.height;
+
+        inside.then_some(position)
+    }
+
+    /// Convert a point in the window's coordinate system, like an event's `locationInWindow` or a
+    /// drag's `draggingLocation`, to a position within the view. The view is flipped so the origin
+    /// is in its top left corner, just like on the other platforms. The view's size in points is
+    /// the window's logical size, so the result is already in logical coordinates.
+    pub(super) unsafe fn view_position(&self, window_point: NSPoint) -> Point {
+        let point: NSPoint = msg_send![self.window.ns_view, convertPoint:window_point fromView:nil];
+        let points = self.window.logical_per_point();
+
+        Point::new(point.x * points, point.y * points)
+    }
+
     pub(super) fn process_native_key_event(&mut self, event: *mut Object) -> Option<KeyboardEvent> {
         self.keyboard_state.process_native_event(event)
     }
 
-    /// Don't call until WindowState pointer is stored in view
-    unsafe fn setup_timer(window_state_ptr: *mut WindowState) {
+    /// Remember a key equivalent from `performKeyEquivalent:` that the handler ignored, so it isn't
+    /// sent to the handler a second time when it arrives at `keyDown:`.
+    pub(super) fn ignore_key_equivalent(&mut self, event: id) {
+        self.ignored_key_equivalent = Some(unsafe { key_event_identity(event) });
+    }
+
+    /// Whether `event` is the key equivalent the handler already ignored. This only matches once.
+    pub(super) fn take_ignored_key_equivalent(&mut self, event: id) -> bool {
+        match self.ignored_key_equivalent {
+            Some(ignored) if ignored == unsafe { key_event_identity(event) } => {
+                self.ignored_key_equivalent = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Start, stop, or recreate the frame timer so it matches the window's animation state, frame
+    /// interval, and whether its frames are paused. This can also be called from the timer's own
+    /// callback. Don't call until WindowState pointer is stored in view.
+    unsafe fn update_timer(&mut self) {
+        if !self.wants_frames() || self.frame_source == FrameSource::External {
+            self.stop_timer();
+            return;
+        }
+
+        if let Some(display_link) = &mut self.display_link {
+            display_link.start();
+            return;
+        }
+
+        let interval = self.window.frame_interval;
+        match &self.frame_timer {
+            Some((_, timer_interval)) if *timer_interval == interval => return,
+            Some(_) => self.stop_timer(),
+            None => (),
+        }
+
+        Self::setup_timer(self, interval);
+    }
+
+    /// Whether the window should draw frames at all right now.
+    fn wants_frames(&self) -> bool {
+        self.lifecycle.can_draw()
+            && !self.detached
+            && !self.window.frames_paused
+            && (self.window.animating || self.frame_pending)
+    }
+
+    /// A tick from [`crate::WindowHandle::trigger_frame()`]. Like a frame timer tick, this only
+    /// draws a frame if the window wants one.
+    fn trigger_external_frame(&mut self) {
+        if self.frame_source == FrameSource::External && self.wants_frames() {
+            self.trigger_frame();
+        }
+    }
+
+    unsafe fn setup_timer(window_state_ptr: *mut WindowState, interval: Duration) {
         extern "C" fn timer_callback(_: *mut __CFRunLoopTimer, window_state_ptr: *mut c_void) {
             unsafe {
                 let window_state = &mut *(window_state_ptr as *mut WindowState);
@@ -609,13 +1928,32 @@ impl WindowState {
             copyDescription: None,
         };
 
-        let timer = CFRunLoopTimer::new(0.0, 0.015, 0, 0, timer_callback, &mut timer_context);
+        // A timer with a zero interval would only fire once
+        let interval_secs = interval.as_secs_f64().max(0.001);
+        let timer =
+            CFRunLoopTimer::new(0.0, interval_secs, 0, 0, timer_callback, &mut timer_context);
 
         CFRunLoop::get_current().add_timer(&timer, kCFRunLoopDefaultMode);
 
         let window_state = &mut *(window_state_ptr);
 
-        window_state.frame_timer = Some(timer);
+        window_state.frame_timer = Some((timer, interval));
+    }
+
+    /// Stop the frame timer, or the display link if that's used instead.
+    fn stop_timer(&mut self) {
+        if let Some((frame_timer, _)) = self.frame_timer.take() {
+            unsafe { CFRunLoop::get_current().remove_timer(&frame_timer, kCFRunLoopDefaultMode) };
+        }
+        if let Some(display_link) = &mut self.display_link {
+            display_link.stop();
+        }
+    }
+
+    /// Called when the view is about to be removed from its window or added to another one.
+    pub(super) unsafe fn set_detached(&mut self, detached: bool) {
+        self.detached = detached;
+        self.update_timer();
     }
 
     /// Call when freeing view
@@ -625,16 +1963,24 @@ impl WindowState {
         // Take back ownership of Box<WindowState> so that it gets dropped
         // when it goes out of scope
         let mut window_state = Box::from_raw(state_ptr as *mut WindowState);
+        let _span = window_span!(ns_view = ?window_state.window.ns_view);
+        debug!(target: "baseview::lifecycle", "destroyed window");
 
-        if let Some(frame_timer) = window_state.frame_timer.take() {
-            CFRunLoop::get_current().remove_timer(&frame_timer, kCFRunLoopDefaultMode);
-        }
+        window_state.stop_timer();
+        // Frames that are still queued on the main thread must not use the state after this
+        window_state.display_link = None;
 
         // Clear ivar before triggering WindowEvent::WillClose. Otherwise, if the
         // handler of the event causes another call to release, this function could be
         // called again, leading to a double free.
         ns_view_obj.set_ivar(BASEVIEW_STATE_IVAR, ptr::null() as *const c_void);
 
+        // The key window notifications would otherwise still try to access the state
+        let notification_center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+        let () = msg_send![notification_center, removeObserver: &*ns_view_obj];
+
+        #[cfg(feature = "opengl")]
+        window_state.window.make_gl_context_current();
         window_state.trigger_event(Event::Window(WindowEvent::WillClose));
 
         // If in non-parented mode, we want to also quit the app altogether
@@ -644,26 +1990,6 @@ impl WindowState {
     }
 }
 
-unsafe impl HasRawWindowHandle for Window {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        let ns_window = self.ns_window.unwrap_or(ptr::null_mut()) as *mut c_void;
-
-        let mut handle = AppKitWindowHandle::empty();
-        handle.ns_window = ns_window;
-        handle.ns_view = self.ns_view as *mut c_void;
-
-        RawWindowHandle::AppKit(handle)
-    }
-}
-
-unsafe impl HasRawDisplayHandle for Window {
-    fn raw_display_handle(&self) -> RawDisplayHandle {
-        let handle = AppKitDisplayHandle::empty();
-
-        RawDisplayHandle::AppKit(handle)
-    }
-}
-
 pub fn copy_to_clipboard(string: &str) {
     unsafe {
         let pb = NSPasteboard::generalPasteboard(nil);
@@ -674,3 +2000,21 @@ pub fn copy_to_clipboard(string: &str) {
         NSPasteboard::setString_forType(pb, ns_str, cocoa::appkit::NSPasteboardTypeString);
     }
 }
+
+/// The timestamp and key code of a key event, which together identify a key press. An unhandled
+/// key equivalent keeps both when it's sent to `keyDown:`.
+unsafe fn key_event_identity(event: id) -> (f64, u16) {
+    let timestamp: f64 = msg_send![event, timestamp];
+    (timestamp, event.keyCode())
+}
+
+/// The timestamp of the `NSEvent` AppKit is handling, in time since the system booted.
+unsafe fn current_event_time() -> Option<Duration> {
+    let ns_event: id = msg_send![NSApp(), currentEvent];
+    if ns_event == nil {
+        return None;
+    }
+
+    let timestamp: f64 = msg_send![ns_event, timestamp];
+    Duration::try_from_secs_f64(timestamp).ok()
+}
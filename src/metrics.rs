@@ -0,0 +1,233 @@
+//! Optional measurements of how the window's handler is keeping up, enabled with the `metrics`
+//! feature. These answer whether a window that stopped responding is stuck in its own handler or
+//! isn't receiving anything from the host. See [`WindowHandle::metrics()`].
+//!
+//! Like [`crate::trace::instrument_handler()`], this wraps the handler that's returned by the
+//! `build` closure, so every call a backend makes into the handler gets measured: the X11 event
+//! loop, the Windows window procedure, and the macOS view's event and frame callbacks alike.
+//! Without the feature the handler isn't wrapped at all, and the window handles don't store
+//! anything for it.
+//!
+//! [`WindowHandle::metrics()`]: crate::WindowHandle::metrics
+
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+#[cfg(feature = "metrics")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+use crate::{Event, EventContext, EventStatus, WindowHandler};
+
+/// What the window handles store for the metrics.
+#[cfg(feature = "metrics")]
+pub(crate) type HandleMetrics = Arc<Metrics>;
+#[cfg(not(feature = "metrics"))]
+#[derive(Clone)]
+pub(crate) struct HandleMetrics;
+
+/// A snapshot of a window's metrics, as returned by [`WindowHandle::metrics()`]. Everything except
+/// for [`Self::since_last_frame`] counts from when the window was opened, or from the last call
+/// to [`WindowHandle::reset_metrics()`].
+///
+/// The values are read one at a time while the window keeps running, so a snapshot taken while
+/// the handler is being called may be off by that one call.
+///
+/// [`WindowHandle::metrics()`]: crate::WindowHandle::metrics
+/// [`WindowHandle::reset_metrics()`]: crate::WindowHandle::reset_metrics
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowMetrics {
+    /// The number of frames the handler has drawn, through
+    /// [`WindowHandler::on_frame()`](crate::WindowHandler::on_frame).
+    pub frames: u64,
+    /// How long `on_frame()` took on average. This is zero if no frames have been drawn.
+    pub average_frame_duration: Duration,
+    /// The longest `on_frame()` call.
+    pub max_frame_duration: Duration,
+    /// The number of events the handler has received.
+    pub events: EventCounts,
+    /// The longest any single call into the handler took, whether it was for an event or a
+    /// frame. The window can't respond to anything else during that time.
+    pub longest_stall: Duration,
+    /// How long ago the last frame finished, or `None` if no frame has been drawn yet. This is not
+    /// affected by resetting the metrics, since it describes the window's current state. It keeps
+    /// growing when the window isn't animating, or when the host stops driving its frames.
+    pub since_last_frame: Option<Duration>,
+}
+
+/// The number of events of each kind the handler has received, see [`WindowMetrics::events`].
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventCounts {
+    /// [`Event::Mouse`] events.
+    pub mouse: u64,
+    /// [`Event::Keyboard`] events.
+    pub keyboard: u64,
+    /// [`Event::Window`] events.
+    pub window: u64,
+    /// [`Event::Dialog`] events.
+    pub dialog: u64,
+    /// `Event::AccessKit` events. These are only sent with the `accesskit` feature.
+    pub accessibility: u64,
+}
+
+#[cfg(feature = "metrics")]
+impl EventCounts {
+    /// The total number of events.
+    pub fn total(&self) -> u64 {
+        self.mouse + self.keyboard + self.window + self.dialog + self.accessibility
+    }
+}
+
+/// The counters behind [`WindowMetrics`]. The handler's thread updates these with relaxed atomic
+/// operations, and the window handles read them from any thread.
+#[cfg(feature = "metrics")]
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    /// The durations below are stored in nanoseconds, and the last frame's time is relative to
+    /// this.
+    opened_at: Instant,
+    frames: AtomicU64,
+    total_frame_nanos: AtomicU64,
+    max_frame_nanos: AtomicU64,
+    /// Indexed by [`event_category()`].
+    events: [AtomicU64; 5],
+    longest_stall_nanos: AtomicU64,
+    /// One more than the nanoseconds since `opened_at` at which the last frame finished, so zero
+    /// means that there hasn't been a frame yet.
+    last_frame: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    fn new() -> Self {
+        Self {
+            opened_at: Instant::now(),
+            frames: AtomicU64::new(0),
+            total_frame_nanos: AtomicU64::new(0),
+            max_frame_nanos: AtomicU64::new(0),
+            events: Default::default(),
+            longest_stall_nanos: AtomicU64::new(0),
+            last_frame: AtomicU64::new(0),
+        }
+    }
+
+    pub fn snapshot(&self) -> WindowMetrics {
+        let frames = self.frames.load(Ordering::Relaxed);
+        let total_frame_nanos = self.total_frame_nanos.load(Ordering::Relaxed);
+        let [mouse, keyboard, window, dialog, accessibility] =
+            self.events.each_ref().map(|count| count.load(Ordering::Relaxed));
+        let since_last_frame = match self.last_frame.load(Ordering::Relaxed) {
+            0 => None,
+            last_frame => {
+                let last_frame = self.opened_at + Duration::from_nanos(last_frame - 1);
+                Some(last_frame.elapsed())
+            }
+        };
+
+        WindowMetrics {
+            frames,
+            average_frame_duration: Duration::from_nanos(
+                total_frame_nanos.checked_div(frames).unwrap_or(0),
+            ),
+            max_frame_duration: Duration::from_nanos(self.max_frame_nanos.load(Ordering::Relaxed)),
+            events: EventCounts { mouse, keyboard, window, dialog, accessibility },
+            longest_stall: Duration::from_nanos(self.longest_stall_nanos.load(Ordering::Relaxed)),
+            since_last_frame,
+        }
+    }
+
+    pub fn reset(&self) {
+        self.frames.store(0, Ordering::Relaxed);
+        self.total_frame_nanos.store(0, Ordering::Relaxed);
+        self.max_frame_nanos.store(0, Ordering::Relaxed);
+        for count in &self.events {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.longest_stall_nanos.store(0, Ordering::Relaxed);
+    }
+
+    fn record_frame(&self, started_at: Instant) {
+        let finished_at = Instant::now();
+        let duration = nanos(finished_at - started_at);
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.total_frame_nanos.fetch_add(duration, Ordering::Relaxed);
+        self.max_frame_nanos.fetch_max(duration, Ordering::Relaxed);
+        self.longest_stall_nanos.fetch_max(duration, Ordering::Relaxed);
+        self.last_frame.store(nanos(finished_at - self.opened_at) + 1, Ordering::Relaxed);
+    }
+
+    fn record_event(&self, category: usize, started_at: Instant) {
+        self.events[category].fetch_add(1, Ordering::Relaxed);
+        self.longest_stall_nanos.fetch_max(nanos(started_at.elapsed()), Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+/// The index of the event's counter in [`Metrics::events`].
+#[cfg(feature = "metrics")]
+fn event_category(event: &Event) -> usize {
+    match event {
+        Event::Mouse(_) => 0,
+        Event::Keyboard(_) => 1,
+        Event::Window(_) => 2,
+        Event::Dialog(_) => 3,
+        #[cfg(feature = "accesskit")]
+        Event::AccessKit(_) => 4,
+    }
+}
+
+/// Wrap the `build` closure for a window so the handler it returns gets measured. Returns the new
+/// closure along with what the window's handles should store.
+#[cfg(feature = "metrics")]
+pub(crate) fn measure<H, B>(
+    build: B,
+) -> (impl FnOnce(&mut EventContext) -> MeasuredHandler<H>, HandleMetrics)
+where
+    H: WindowHandler,
+    B: FnOnce(&mut EventContext) -> H,
+{
+    let metrics = Arc::new(Metrics::new());
+    let handler_metrics = metrics.clone();
+    let build = move |window: &mut EventContext| MeasuredHandler {
+        handler: build(window),
+        metrics: handler_metrics,
+    };
+
+    (build, metrics)
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn measure<B>(build: B) -> (B, HandleMetrics) {
+    (build, HandleMetrics)
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) struct MeasuredHandler<H> {
+    handler: H,
+    metrics: Arc<Metrics>,
+}
+
+#[cfg(feature = "metrics")]
+impl<H: WindowHandler> WindowHandler for MeasuredHandler<H> {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let started_at = Instant::now();
+        self.handler.on_frame(window);
+        self.metrics.record_frame(started_at);
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let category = event_category(&event);
+        let started_at = Instant::now();
+        let status = self.handler.on_event(window, event);
+        self.metrics.record_event(category, started_at);
+
+        status
+    }
+}
@@ -1,5 +1,11 @@
-#[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Hash)]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Error, Point, Rect};
+
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseCursor {
+    #[default]
     Default,
     Hand,
     HandGrabbing,
@@ -41,10 +47,211 @@ pub enum MouseCursor {
     NeswResize,
     ColResize,
     RowResize,
+
+    /// A cursor created from an image using
+    /// [`EventContext::register_cursor()`](crate::EventContext::register_cursor). Custom cursors
+    /// belong to the window they were registered with. Using one with another window shows the
+    /// default cursor instead.
+    Custom(CursorId),
+}
+
+/// Refers to a cursor registered with
+/// [`EventContext::register_cursor()`](crate::EventContext::register_cursor). IDs are unique for
+/// the entire process and they're never reused.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CursorId(u64);
+
+impl CursorId {
+    pub(crate) fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        CursorId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
-impl Default for MouseCursor {
-    fn default() -> Self {
-        Self::Default
+/// An image for a custom mouse cursor. See
+/// [`EventContext::register_cursor()`](crate::EventContext::register_cursor).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorIcon {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    hotspot_x: u32,
+    hotspot_y: u32,
+    fallback: MouseCursor,
+}
+
+impl CursorIcon {
+    /// Create a cursor image from non-premultiplied RGBA pixels, stored row by row starting at the
+    /// top left. The image is shown at its actual size in physical pixels. The hotspot is the pixel
+    /// that points at the cursor's position.
+    ///
+    /// Returns [`Error::InvalidCursorIcon`] if the image is empty, if the buffer doesn't contain
+    /// exactly `width * height` pixels, or if the hotspot lies outside of the image.
+    pub fn from_rgba(
+        rgba: Vec<u8>, width: u32, height: u32, hotspot_x: u32, hotspot_y: u32,
+    ) -> Result<Self, Error> {
+        let expected_len =
+            (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4));
+        if width == 0
+            || height == 0
+            || expected_len != Some(rgba.len())
+            || hotspot_x >= width
+            || hotspot_y >= height
+        {
+            return Err(Error::InvalidCursorIcon);
+        }
+
+        Ok(Self { rgba, width, height, hotspot_x, hotspot_y, fallback: MouseCursor::Default })
+    }
+
+    /// The built-in cursor to show instead when the platform can't display color cursors. This
+    /// only happens on X11 when the X server doesn't support the RENDER extension. Defaults to
+    /// [`MouseCursor::Default`]. Passing a [`MouseCursor::Custom`] cursor also results in the
+    /// default cursor.
+    pub fn with_fallback(mut self, fallback: MouseCursor) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// The image's pixels, in the format described in [`Self::from_rgba()`].
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The hotspot's `(x, y)` position within the image.
+    pub fn hotspot(&self) -> (u32, u32) {
+        (self.hotspot_x, self.hotspot_y)
+    }
+
+    pub fn fallback(&self) -> MouseCursor {
+        self.fallback
+    }
+}
+
+/// The cursors for areas of the window, see
+/// [`EventContext::set_cursor_regions()`](crate::EventContext::set_cursor_regions). The platforms
+/// resolve the cursor through this whenever the pointer moves, so the handler isn't involved.
+#[derive(Debug, Default)]
+pub(crate) struct CursorRegions {
+    /// In logical coordinates. Later regions take precedence over earlier ones.
+    regions: Vec<(Rect, MouseCursor)>,
+}
+
+impl CursorRegions {
+    pub fn set(&mut self, regions: Vec<(Rect, MouseCursor)>) {
+        self.regions = regions;
+    }
+
+    /// The cursor to show with the pointer at `position`, or with the pointer outside of the window
+    /// if that's `None`. The cursor set through `set_mouse_cursor()` takes precedence over the
+    /// regions unless it's the default cursor.
+    pub fn resolve(&self, mouse_cursor: MouseCursor, position: Option<Point>) -> MouseCursor {
+        if mouse_cursor != MouseCursor::Default {
+            return mouse_cursor;
+        }
+
+        position
+            .and_then(|position| {
+                self.regions.iter().rev().find(|(rect, _)| rect.contains(position))
+            })
+            .map_or(MouseCursor::Default, |&(_, cursor)| cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regions() -> CursorRegions {
+        let mut regions = CursorRegions::default();
+        regions.set(vec![
+            // A text field with a button on top of it
+            (Rect::new(10.0, 10.0, 100.0, 20.0), MouseCursor::Text),
+            (Rect::new(90.0, 10.0, 20.0, 20.0), MouseCursor::PointingHand),
+            // An empty region doesn't cover anything
+            (Rect::new(0.0, 0.0, 0.0, 0.0), MouseCursor::Hidden),
+        ]);
+
+        regions
+    }
+
+    #[test]
+    fn hit_testing() {
+        let regions = regions();
+        let resolve = |x, y| regions.resolve(MouseCursor::Default, Some(Point::new(x, y)));
+
+        assert_eq!(resolve(10.0, 10.0), MouseCursor::Text);
+        assert_eq!(resolve(89.9, 29.9), MouseCursor::Text);
+        // The right and bottom edges are exclusive
+        assert_eq!(resolve(110.0, 15.0), MouseCursor::Default);
+        assert_eq!(resolve(50.0, 30.0), MouseCursor::Default);
+        assert_eq!(resolve(0.0, 0.0), MouseCursor::Default);
+        assert_eq!(resolve(-5.0, 15.0), MouseCursor::Default);
+    }
+
+    #[test]
+    fn later_regions_override_earlier_ones() {
+        let regions = regions();
+        let resolve = |x, y| regions.resolve(MouseCursor::Default, Some(Point::new(x, y)));
+
+        assert_eq!(resolve(90.0, 10.0), MouseCursor::PointingHand);
+        assert_eq!(resolve(100.0, 20.0), MouseCursor::PointingHand);
+        assert_eq!(resolve(89.0, 20.0), MouseCursor::Text);
+    }
+
+    #[test]
+    fn overrides() {
+        let regions = regions();
+        let inside = Some(Point::new(50.0, 15.0));
+
+        // `set_mouse_cursor()` takes precedence unless it's the default cursor
+        assert_eq!(regions.resolve(MouseCursor::Working, inside), MouseCursor::Working);
+        assert_eq!(regions.resolve(MouseCursor::Working, None), MouseCursor::Working);
+        assert_eq!(regions.resolve(MouseCursor::Default, inside), MouseCursor::Text);
+        // The pointer left the window
+        assert_eq!(regions.resolve(MouseCursor::Default, None), MouseCursor::Default);
+    }
+
+    #[test]
+    fn replacing_regions() {
+        let mut regions = regions();
+        let inside = Some(Point::new(50.0, 15.0));
+
+        regions.set(vec![(Rect::new(0.0, 0.0, 60.0, 60.0), MouseCursor::Help)]);
+        assert_eq!(regions.resolve(MouseCursor::Default, inside), MouseCursor::Help);
+        regions.set(Vec::new());
+        assert_eq!(regions.resolve(MouseCursor::Default, inside), MouseCursor::Default);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn mouse_cursor_round_trip() {
+        for cursor in [
+            MouseCursor::Default,
+            MouseCursor::Hand,
+            MouseCursor::HandGrabbing,
+            MouseCursor::PointingHand,
+            MouseCursor::Help,
+            MouseCursor::Hidden,
+            MouseCursor::Text,
+            MouseCursor::VerticalText,
+            MouseCursor::Working,
+            MouseCursor::Custom(CursorId::next()),
+        ] {
+            let json = serde_json::to_string(&cursor).unwrap();
+            assert_eq!(serde_json::from_str::<MouseCursor>(&json).unwrap(), cursor, "{}", json);
+        }
+
+        assert_eq!(serde_json::to_string(&MouseCursor::Hand).unwrap(), r#""Hand""#);
     }
 }
@@ -0,0 +1,11 @@
+//! Access to the native window for making platform calls that baseview doesn't wrap itself, like
+//! embedding handshakes with a host. The native objects are only lent out for the duration of a
+//! closure through `Window::with_x11_connection()` on Linux, `Window::with_hwnd()` on Windows, and
+//! `Window::with_ns_view()` on macOS.
+
+/// The types used by
+/// [`EventContext::with_x11_connection()`](crate::EventContext::with_x11_connection).
+#[cfg(all(feature = "windowing", target_os = "linux"))]
+pub mod x11 {
+    pub use crate::x11::{PropertyFormat, X11Connection};
+}
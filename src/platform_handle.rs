@@ -0,0 +1,407 @@
+//! Baseview's own representation of the native window and display handles. The platform
+//! implementations only ever fill these in, and they are then converted to the types from whichever
+//! `raw-window-handle` versions were enabled through the `rwh_05` and `rwh_06` features.
+
+use std::os::raw::c_void;
+#[cfg(target_os = "linux")]
+use std::os::raw::{c_int, c_ulong};
+
+#[cfg(any(feature = "rwh_05", feature = "rwh_06"))]
+use crate::Error;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PlatformWindowHandle {
+    #[cfg(target_os = "linux")]
+    Xlib { window: c_ulong, visual_id: c_ulong },
+    #[cfg(target_os = "windows")]
+    Win32 { hwnd: *mut c_void },
+    /// `ns_window` may be null, for instance when the handle came from a parent that only told us
+    /// about its view.
+    #[cfg(target_os = "macos")]
+    AppKit { ns_window: *mut c_void, ns_view: *mut c_void },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PlatformDisplayHandle {
+    #[cfg(target_os = "linux")]
+    Xlib { display: *mut c_void, screen: c_int },
+    #[cfg(target_os = "windows")]
+    Windows,
+    #[cfg(target_os = "macos")]
+    AppKit,
+}
+
+#[cfg(feature = "rwh_05")]
+impl PlatformWindowHandle {
+    /// Convert a parent window's handle. Fails if the handle is for a different platform, or if
+    /// it's a null handle.
+    pub(crate) fn from_rwh_05(handle: rwh_05::RawWindowHandle) -> Result<Self, Error> {
+        match handle {
+            #[cfg(target_os = "linux")]
+            rwh_05::RawWindowHandle::Xlib(handle) if handle.window == 0 => {
+                Err(Error::NullParentHandle)
+            }
+            #[cfg(target_os = "linux")]
+            rwh_05::RawWindowHandle::Xlib(handle) => {
+                Ok(Self::Xlib { window: handle.window, visual_id: handle.visual_id })
+            }
+            #[cfg(target_os = "linux")]
+            rwh_05::RawWindowHandle::Xcb(handle) if handle.window == 0 => {
+                Err(Error::NullParentHandle)
+            }
+            #[cfg(target_os = "linux")]
+            rwh_05::RawWindowHandle::Xcb(handle) => Ok(Self::Xlib {
+                window: handle.window as c_ulong,
+                visual_id: handle.visual_id as c_ulong,
+            }),
+            #[cfg(target_os = "linux")]
+            rwh_05::RawWindowHandle::Wayland(_) => Err(Error::WaylandParentUnsupported),
+            // This would otherwise result in a top level window
+            #[cfg(target_os = "windows")]
+            rwh_05::RawWindowHandle::Win32(handle) if handle.hwnd.is_null() => {
+                Err(Error::NullParentHandle)
+            }
+            #[cfg(target_os = "windows")]
+            rwh_05::RawWindowHandle::Win32(handle) => Ok(Self::Win32 { hwnd: handle.hwnd }),
+            #[cfg(target_os = "macos")]
+            rwh_05::RawWindowHandle::AppKit(handle) if handle.ns_view.is_null() => {
+                Err(Error::NullParentHandle)
+            }
+            #[cfg(target_os = "macos")]
+            rwh_05::RawWindowHandle::AppKit(handle) => {
+                Ok(Self::AppKit { ns_window: handle.ns_window, ns_view: handle.ns_view })
+            }
+            handle => Err(Error::UnsupportedParentHandle(format!("{:?}", handle))),
+        }
+    }
+
+    /// Returns an empty handle for this platform if `handle` is `None`, i.e. when the window has
+    /// already been closed.
+    pub(crate) fn to_rwh_05(handle: Option<Self>) -> rwh_05::RawWindowHandle {
+        match handle {
+            #[cfg(target_os = "linux")]
+            Some(Self::Xlib { window, visual_id }) => {
+                let mut handle = rwh_05::XlibWindowHandle::empty();
+                handle.window = window;
+                handle.visual_id = visual_id;
+
+                rwh_05::RawWindowHandle::Xlib(handle)
+            }
+            #[cfg(target_os = "windows")]
+            Some(Self::Win32 { hwnd }) => {
+                let mut handle = rwh_05::Win32WindowHandle::empty();
+                handle.hwnd = hwnd;
+
+                rwh_05::RawWindowHandle::Win32(handle)
+            }
+            #[cfg(target_os = "macos")]
+            Some(Self::AppKit { ns_window, ns_view }) => {
+                let mut handle = rwh_05::AppKitWindowHandle::empty();
+                handle.ns_window = ns_window;
+                handle.ns_view = ns_view;
+
+                rwh_05::RawWindowHandle::AppKit(handle)
+            }
+            #[cfg(target_os = "linux")]
+            None => rwh_05::RawWindowHandle::Xlib(rwh_05::XlibWindowHandle::empty()),
+            #[cfg(target_os = "windows")]
+            None => rwh_05::RawWindowHandle::Win32(rwh_05::Win32WindowHandle::empty()),
+            #[cfg(target_os = "macos")]
+            None => rwh_05::RawWindowHandle::AppKit(rwh_05::AppKitWindowHandle::empty()),
+        }
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl PlatformWindowHandle {
+    /// Convert a parent window's handle. Fails if the handle is for a different platform, or if
+    /// it's a null handle. Most handles in version 0.6 cannot be null.
+    pub(crate) fn from_rwh_06(handle: rwh_06::RawWindowHandle) -> Result<Self, Error> {
+        match handle {
+            #[cfg(target_os = "linux")]
+            rwh_06::RawWindowHandle::Xlib(handle) if handle.window == 0 => {
+                Err(Error::NullParentHandle)
+            }
+            #[cfg(target_os = "linux")]
+            rwh_06::RawWindowHandle::Xlib(handle) => {
+                Ok(Self::Xlib { window: handle.window, visual_id: handle.visual_id })
+            }
+            #[cfg(target_os = "linux")]
+            rwh_06::RawWindowHandle::Xcb(handle) => Ok(Self::Xlib {
+                window: handle.window.get() as c_ulong,
+                visual_id: handle.visual_id.map_or(0, |id| id.get()) as c_ulong,
+            }),
+            #[cfg(target_os = "linux")]
+            rwh_06::RawWindowHandle::Wayland(_) => Err(Error::WaylandParentUnsupported),
+            #[cfg(target_os = "windows")]
+            rwh_06::RawWindowHandle::Win32(handle) => {
+                Ok(Self::Win32 { hwnd: handle.hwnd.get() as *mut c_void })
+            }
+            // Version 0.6 no longer includes the `NSWindow`
+            #[cfg(target_os = "macos")]
+            rwh_06::RawWindowHandle::AppKit(handle) => Ok(Self::AppKit {
+                ns_window: std::ptr::null_mut(),
+                ns_view: handle.ns_view.as_ptr(),
+            }),
+            handle => Err(Error::UnsupportedParentHandle(format!("{:?}", handle))),
+        }
+    }
+
+    /// Fails with [`rwh_06::HandleError::Unavailable`] if `handle` is `None` or if it's a null
+    /// handle.
+    pub(crate) fn to_rwh_06(
+        handle: Option<Self>,
+    ) -> Result<rwh_06::RawWindowHandle, rwh_06::HandleError> {
+        match handle.ok_or(rwh_06::HandleError::Unavailable)? {
+            #[cfg(target_os = "linux")]
+            Self::Xlib { window, visual_id } => {
+                let mut handle = rwh_06::XlibWindowHandle::new(window);
+                handle.visual_id = visual_id;
+
+                Ok(rwh_06::RawWindowHandle::Xlib(handle))
+            }
+            #[cfg(target_os = "windows")]
+            Self::Win32 { hwnd } => {
+                let hwnd = std::num::NonZeroIsize::new(hwnd as isize)
+                    .ok_or(rwh_06::HandleError::Unavailable)?;
+
+                Ok(rwh_06::RawWindowHandle::Win32(rwh_06::Win32WindowHandle::new(hwnd)))
+            }
+            #[cfg(target_os = "macos")]
+            Self::AppKit { ns_view, .. } => {
+                let ns_view =
+                    std::ptr::NonNull::new(ns_view).ok_or(rwh_06::HandleError::Unavailable)?;
+
+                Ok(rwh_06::RawWindowHandle::AppKit(rwh_06::AppKitWindowHandle::new(ns_view)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rwh_05")]
+impl PlatformDisplayHandle {
+    pub(crate) fn to_rwh_05(self) -> rwh_05::RawDisplayHandle {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::Xlib { display, screen } => {
+                let mut handle = rwh_05::XlibDisplayHandle::empty();
+                handle.display = display;
+                handle.screen = screen;
+
+                rwh_05::RawDisplayHandle::Xlib(handle)
+            }
+            #[cfg(target_os = "windows")]
+            Self::Windows => {
+                rwh_05::RawDisplayHandle::Windows(rwh_05::WindowsDisplayHandle::empty())
+            }
+            #[cfg(target_os = "macos")]
+            Self::AppKit => rwh_05::RawDisplayHandle::AppKit(rwh_05::AppKitDisplayHandle::empty()),
+        }
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl PlatformDisplayHandle {
+    pub(crate) fn to_rwh_06(self) -> rwh_06::RawDisplayHandle {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::Xlib { display, screen } => rwh_06::RawDisplayHandle::Xlib(
+                rwh_06::XlibDisplayHandle::new(std::ptr::NonNull::new(display), screen),
+            ),
+            #[cfg(target_os = "windows")]
+            Self::Windows => rwh_06::RawDisplayHandle::Windows(rwh_06::WindowsDisplayHandle::new()),
+            #[cfg(target_os = "macos")]
+            Self::AppKit => rwh_06::RawDisplayHandle::AppKit(rwh_06::AppKitDisplayHandle::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rwh_05")]
+    #[test]
+    fn rwh_05_unsupported_handles() {
+        let handle = rwh_05::RawWindowHandle::Web(rwh_05::WebWindowHandle::empty());
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+
+        let handle = rwh_05::RawWindowHandle::UiKit(rwh_05::UiKitWindowHandle::empty());
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+    }
+
+    #[cfg(all(feature = "rwh_05", target_os = "linux"))]
+    #[test]
+    fn rwh_05_x11_handles() {
+        let mut xlib = rwh_05::XlibWindowHandle::empty();
+        xlib.window = 0x1200003;
+        xlib.visual_id = 0x21;
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::Xlib(xlib)),
+            Ok(PlatformWindowHandle::Xlib { window: 0x1200003, visual_id: 0x21 })
+        ));
+
+        let mut xcb = rwh_05::XcbWindowHandle::empty();
+        xcb.window = 0x1200003;
+        xcb.visual_id = 0x21;
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::Xcb(xcb)),
+            Ok(PlatformWindowHandle::Xlib { window: 0x1200003, visual_id: 0x21 })
+        ));
+    }
+
+    #[cfg(all(feature = "rwh_05", target_os = "linux"))]
+    #[test]
+    fn rwh_05_null_and_wayland_handles() {
+        let xlib = rwh_05::RawWindowHandle::Xlib(rwh_05::XlibWindowHandle::empty());
+        assert_eq!(PlatformWindowHandle::from_rwh_05(xlib).unwrap_err(), Error::NullParentHandle);
+
+        let xcb = rwh_05::RawWindowHandle::Xcb(rwh_05::XcbWindowHandle::empty());
+        assert_eq!(PlatformWindowHandle::from_rwh_05(xcb).unwrap_err(), Error::NullParentHandle);
+
+        let wayland = rwh_05::RawWindowHandle::Wayland(rwh_05::WaylandWindowHandle::empty());
+        assert_eq!(
+            PlatformWindowHandle::from_rwh_05(wayland).unwrap_err(),
+            Error::WaylandParentUnsupported
+        );
+    }
+
+    #[cfg(all(feature = "rwh_05", target_os = "windows"))]
+    #[test]
+    fn rwh_05_win32_handles() {
+        let mut handle = rwh_05::Win32WindowHandle::empty();
+        assert_eq!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::Win32(handle)).unwrap_err(),
+            Error::NullParentHandle
+        );
+
+        handle.hwnd = 0x10 as *mut c_void;
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::Win32(handle)),
+            Ok(PlatformWindowHandle::Win32 { hwnd }) if hwnd as usize == 0x10
+        ));
+
+        let handle = rwh_05::RawWindowHandle::Xlib(rwh_05::XlibWindowHandle::empty());
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+    }
+
+    #[cfg(all(feature = "rwh_05", target_os = "macos"))]
+    #[test]
+    fn rwh_05_appkit_handles() {
+        let mut handle = rwh_05::AppKitWindowHandle::empty();
+        handle.ns_window = 0x20 as *mut c_void;
+        assert_eq!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::AppKit(handle)).unwrap_err(),
+            Error::NullParentHandle
+        );
+
+        // The window is optional
+        handle.ns_window = std::ptr::null_mut();
+        handle.ns_view = 0x10 as *mut c_void;
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::AppKit(handle)),
+            Ok(PlatformWindowHandle::AppKit { ns_window, ns_view })
+                if ns_window.is_null() && ns_view as usize == 0x10
+        ));
+
+        let mut handle = rwh_05::XcbWindowHandle::empty();
+        handle.window = 1;
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_05(rwh_05::RawWindowHandle::Xcb(handle)),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+    }
+
+    #[cfg(feature = "rwh_06")]
+    #[test]
+    fn rwh_06_unsupported_handles() {
+        let handle = rwh_06::RawWindowHandle::Web(rwh_06::WebWindowHandle::new(1));
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+
+        let view = std::ptr::NonNull::<c_void>::dangling();
+        let handle = rwh_06::RawWindowHandle::UiKit(rwh_06::UiKitWindowHandle::new(view));
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+    }
+
+    #[cfg(all(feature = "rwh_06", target_os = "linux"))]
+    #[test]
+    fn rwh_06_x11_handles() {
+        let mut xlib = rwh_06::XlibWindowHandle::new(0x1200003);
+        xlib.visual_id = 0x21;
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(rwh_06::RawWindowHandle::Xlib(xlib)),
+            Ok(PlatformWindowHandle::Xlib { window: 0x1200003, visual_id: 0x21 })
+        ));
+
+        let xcb = rwh_06::XcbWindowHandle::new(std::num::NonZeroU32::new(0x1200003).unwrap());
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(rwh_06::RawWindowHandle::Xcb(xcb)),
+            Ok(PlatformWindowHandle::Xlib { window: 0x1200003, visual_id: 0 })
+        ));
+    }
+
+    #[cfg(all(feature = "rwh_06", target_os = "linux"))]
+    #[test]
+    fn rwh_06_null_and_wayland_handles() {
+        // Only Xlib handles can still be null in version 0.6
+        let xlib = rwh_06::RawWindowHandle::Xlib(rwh_06::XlibWindowHandle::new(0));
+        assert_eq!(PlatformWindowHandle::from_rwh_06(xlib).unwrap_err(), Error::NullParentHandle);
+
+        let surface = std::ptr::NonNull::<c_void>::dangling();
+        let wayland = rwh_06::RawWindowHandle::Wayland(rwh_06::WaylandWindowHandle::new(surface));
+        assert_eq!(
+            PlatformWindowHandle::from_rwh_06(wayland).unwrap_err(),
+            Error::WaylandParentUnsupported
+        );
+    }
+
+    #[cfg(all(feature = "rwh_06", target_os = "windows"))]
+    #[test]
+    fn rwh_06_win32_handles() {
+        let hwnd = std::num::NonZeroIsize::new(0x10).unwrap();
+        let handle = rwh_06::RawWindowHandle::Win32(rwh_06::Win32WindowHandle::new(hwnd));
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(handle),
+            Ok(PlatformWindowHandle::Win32 { hwnd }) if hwnd as usize == 0x10
+        ));
+
+        let handle = rwh_06::RawWindowHandle::Xlib(rwh_06::XlibWindowHandle::new(1));
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+    }
+
+    #[cfg(all(feature = "rwh_06", target_os = "macos"))]
+    #[test]
+    fn rwh_06_appkit_handles() {
+        let view = std::ptr::NonNull::new(0x10 as *mut c_void).unwrap();
+        let handle = rwh_06::RawWindowHandle::AppKit(rwh_06::AppKitWindowHandle::new(view));
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(handle),
+            Ok(PlatformWindowHandle::AppKit { ns_window, ns_view })
+                if ns_window.is_null() && ns_view as usize == 0x10
+        ));
+
+        let handle = rwh_06::RawWindowHandle::Xlib(rwh_06::XlibWindowHandle::new(1));
+        assert!(matches!(
+            PlatformWindowHandle::from_rwh_06(handle),
+            Err(Error::UnsupportedParentHandle(_))
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{Rect, Size};
+
+/// Once a region contains more rectangles than this, they're merged into their bounding box.
+/// Redrawing a slightly larger area is cheaper than clipping to a long list of tiny rectangles.
+const MAX_RECTS: usize = 16;
+
+/// An area of the window made up of rectangles, used to keep track of which parts of the window
+/// need to be redrawn. See [`EventContext::damage()`][crate::EventContext::damage].
+///
+/// The rectangles are simplified while they're added: rectangles that overlap are replaced by
+/// their bounding box, so the rectangles in a region never overlap each other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Region {
+    rects: Vec<Rect>,
+}
+
+impl Region {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rectangle to the region. Empty rectangles are ignored.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        // Merging two rectangles can make the result overlap rectangles that didn't overlap either
+        // of them, so this keeps going until nothing overlaps the merged rectangle anymore
+        let mut merged = rect;
+        while let Some(index) = self.rects.iter().position(|other| other.intersects(&merged)) {
+            merged = merged.union(&self.rects.swap_remove(index));
+        }
+        self.rects.push(merged);
+
+        if self.rects.len() > MAX_RECTS {
+            let bounds = self.bounds().unwrap();
+            self.rects.clear();
+            self.rects.push(bounds);
+        }
+    }
+
+    /// The rectangles that make up the region. These don't overlap, and they're in no particular
+    /// order.
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// The smallest rectangle that contains the whole region, or `None` if the region is empty.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.rects.iter().copied().reduce(|bounds, rect| bounds.union(&rect))
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+}
+
+/// The damage tracking the backends share. Damage that's added while a frame is being drawn
+/// belongs to the next frame, so the damage of the current frame is kept separately.
+#[derive(Debug, Default)]
+pub(crate) struct Damage {
+    /// The damage for the frame that's currently being drawn.
+    current: Region,
+    /// The damage for the next frame.
+    pending: Region,
+    /// The window's logical size during the last frame.
+    last_size: Option<Size>,
+}
+
+impl Damage {
+    pub fn add(&mut self, rect: Rect) {
+        self.pending.add(rect);
+    }
+
+    /// Whether damage was added since the last frame started.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Move the pending damage into the current frame. The entire window is damaged for the first
+    /// frame and after the window has been resized.
+    pub fn begin_frame(&mut self, size: Size) {
+        self.current = std::mem::take(&mut self.pending);
+        if self.last_size != Some(size) {
+            self.current.add(Rect::from_origin_size(crate::Point::new(0.0, 0.0), size));
+            self.last_size = Some(size);
+        }
+    }
+
+    pub fn current(&self) -> &Region {
+        &self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(region: &Region) -> Vec<Rect> {
+        let mut rects = region.rects().to_vec();
+        rects.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        rects
+    }
+
+    #[test]
+    fn empty_rects_are_ignored() {
+        let mut region = Region::new();
+        region.add(Rect::new(10.0, 10.0, 0.0, 5.0));
+        region.add(Rect::new(10.0, 10.0, 5.0, 0.0));
+        region.add(Rect::new(10.0, 10.0, -5.0, 5.0));
+        region.add(Rect::new(10.0, 10.0, f64::NAN, 5.0));
+
+        assert!(region.is_empty());
+        assert_eq!(region.bounds(), None);
+    }
+
+    #[test]
+    fn separate_rects_are_kept() {
+        let mut region = Region::new();
+        region.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        // Sharing an edge doesn't count as overlapping
+        region.add(Rect::new(10.0, 0.0, 10.0, 10.0));
+        region.add(Rect::new(50.0, 50.0, 5.0, 5.0));
+
+        assert_eq!(
+            sorted(&region),
+            [
+                Rect::new(0.0, 0.0, 10.0, 10.0),
+                Rect::new(10.0, 0.0, 10.0, 10.0),
+                Rect::new(50.0, 50.0, 5.0, 5.0),
+            ]
+        );
+        assert_eq!(region.bounds(), Some(Rect::new(0.0, 0.0, 55.0, 55.0)));
+    }
+
+    #[test]
+    fn overlapping_rects_are_merged() {
+        let mut region = Region::new();
+        region.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        region.add(Rect::new(5.0, 5.0, 10.0, 10.0));
+
+        assert_eq!(region.rects(), [Rect::new(0.0, 0.0, 15.0, 15.0)]);
+    }
+
+    #[test]
+    fn contained_rects_are_merged() {
+        let mut region = Region::new();
+        region.add(Rect::new(0.0, 0.0, 100.0, 100.0));
+        region.add(Rect::new(10.0, 10.0, 5.0, 5.0));
+        assert_eq!(region.rects(), [Rect::new(0.0, 0.0, 100.0, 100.0)]);
+
+        // And the other way around
+        let mut region = Region::new();
+        region.add(Rect::new(10.0, 10.0, 5.0, 5.0));
+        region.add(Rect::new(0.0, 0.0, 100.0, 100.0));
+        assert_eq!(region.rects(), [Rect::new(0.0, 0.0, 100.0, 100.0)]);
+    }
+
+    #[test]
+    fn merging_cascades() {
+        let mut region = Region::new();
+        region.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        region.add(Rect::new(20.0, 9.0, 10.0, 10.0));
+        // This only overlaps the first rect, but their bounding box also overlaps the second one
+        region.add(Rect::new(5.0, 5.0, 20.0, 4.0));
+
+        assert_eq!(region.rects(), [Rect::new(0.0, 0.0, 30.0, 19.0)]);
+    }
+
+    #[test]
+    fn rects_never_overlap() {
+        let mut region = Region::new();
+        for i in 0..10 {
+            let i = f64::from(i);
+            region.add(Rect::new(i * 7.0, (i * 13.0) % 40.0, 9.0, 9.0));
+        }
+
+        let rects = region.rects();
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                assert!(!a.intersects(b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn too_many_rects_become_the_bounds() {
+        let mut region = Region::new();
+        for i in 0..=MAX_RECTS {
+            region.add(Rect::new(i as f64 * 10.0, 0.0, 5.0, 5.0));
+        }
+
+        assert_eq!(region.rects(), [Rect::new(0.0, 0.0, MAX_RECTS as f64 * 10.0 + 5.0, 5.0)]);
+    }
+
+    #[test]
+    fn damage_covers_the_window_on_the_first_frame_and_after_resizes() {
+        let size = Size::new(200.0, 100.0);
+        let mut damage = Damage::default();
+
+        damage.begin_frame(size);
+        assert_eq!(damage.current().rects(), [Rect::new(0.0, 0.0, 200.0, 100.0)]);
+        assert!(!damage.has_pending());
+
+        damage.add(Rect::new(10.0, 10.0, 5.0, 5.0));
+        assert!(damage.has_pending());
+        damage.begin_frame(size);
+        assert_eq!(damage.current().rects(), [Rect::new(10.0, 10.0, 5.0, 5.0)]);
+
+        damage.begin_frame(size);
+        assert!(damage.current().is_empty());
+
+        damage.begin_frame(Size::new(300.0, 100.0));
+        assert_eq!(damage.current().rects(), [Rect::new(0.0, 0.0, 300.0, 100.0)]);
+    }
+
+    #[test]
+    fn damage_added_while_drawing_belongs_to_the_next_frame() {
+        let size = Size::new(200.0, 100.0);
+        let mut damage = Damage::default();
+        damage.begin_frame(size);
+
+        damage.add(Rect::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(damage.current().rects(), [Rect::new(0.0, 0.0, 200.0, 100.0)]);
+        damage.begin_frame(size);
+        assert_eq!(damage.current().rects(), [Rect::new(1.0, 2.0, 3.0, 4.0)]);
+    }
+}
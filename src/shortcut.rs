@@ -0,0 +1,304 @@
+//! Matching keyboard events against keyboard shortcuts, without having to deal with the platform
+//! differences in how modifier keys are used and reported.
+
+use keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers};
+
+/// The modifiers a shortcut can consist of. Lock keys like Caps Lock and Num Lock are ignored when
+/// matching shortcuts.
+const SHORTCUT_MODIFIERS: Modifiers =
+    Modifiers::CONTROL.union(Modifiers::ALT).union(Modifiers::SHIFT).union(Modifiers::META);
+
+/// A keyboard shortcut, like Ctrl+S. Use [`Shortcut::command_or_control()`] for shortcuts that
+/// should use the Command key on macOS and the Control key everywhere else, so a Save As shortcut
+/// would be `Shortcut::command_or_control(Key::Character("s".into())).with_shift()`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    key: Key,
+    modifiers: Modifiers,
+}
+
+impl Shortcut {
+    /// A shortcut for the key combined with exactly these modifiers. Only Control, Alt, Shift and
+    /// Meta are used, any other modifiers are removed.
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers: modifiers & SHORTCUT_MODIFIERS }
+    }
+
+    /// A shortcut for the key combined with the Command key on macOS, or the Control key on the
+    /// other platforms.
+    pub fn command_or_control(key: Key) -> Self {
+        let modifier = if cfg!(target_os = "macos") { Modifiers::META } else { Modifiers::CONTROL };
+
+        Self::new(key, modifier)
+    }
+
+    /// Also require the Shift key.
+    pub fn with_shift(mut self) -> Self {
+        self.modifiers |= Modifiers::SHIFT;
+        self
+    }
+
+    /// Also require the Alt key, which is the Option key on macOS.
+    pub fn with_alt(mut self) -> Self {
+        self.modifiers |= Modifiers::ALT;
+        self
+    }
+
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Whether the key press triggers this shortcut. Key releases never match. Key repeats do,
+    /// check [`KeyboardEvent::repeat`] to ignore those.
+    ///
+    /// Characters are compared case insensitively, so `Ctrl+S` matches with Caps Lock enabled.
+    /// For characters without a case, like `+` or `?`, Shift is ignored unless the shortcut
+    /// contains it, since those characters often can only be typed using Shift. Keys are compared
+    /// by what they type rather than by where they are, so keys from the numeric keypad match the
+    /// same shortcuts as their counterparts on the main keyboard.
+    ///
+    /// Events with the AltGr modifier never match, since AltGr is used to type characters. Some
+    /// layouts report AltGr as Ctrl+Alt instead. Those events only match shortcuts that contain
+    /// both Ctrl and Alt and the exact same character.
+    pub fn matches(&self, event: &KeyboardEvent) -> bool {
+        if event.state != KeyState::Down || event.modifiers.contains(Modifiers::ALT_GRAPH) {
+            return false;
+        }
+
+        let mut event_modifiers = event.modifiers & SHORTCUT_MODIFIERS;
+        match (&self.key, &event.key) {
+            (Key::Character(expected), Key::Character(actual)) => {
+                if !has_case(expected) && !self.modifiers.contains(Modifiers::SHIFT) {
+                    event_modifiers.remove(Modifiers::SHIFT);
+                }
+                // The character may have been typed with AltGr reported as Ctrl+Alt, in which
+                // case its case matters
+                let characters_match =
+                    if event_modifiers.contains(Modifiers::CONTROL | Modifiers::ALT) {
+                        expected == actual
+                    } else {
+                        expected.to_lowercase() == actual.to_lowercase()
+                    };
+
+                event_modifiers == self.modifiers && characters_match
+            }
+            (expected, actual) => event_modifiers == self.modifiers && expected == actual,
+        }
+    }
+
+    /// A description of the shortcut for menus and tooltips, following the current platform's
+    /// conventions. This is `⇧⌘S` on macOS and `Ctrl+Shift+S` everywhere else.
+    pub fn display_string(&self) -> String {
+        let macos = cfg!(target_os = "macos");
+        let key = key_name(&self.key, macos);
+        if macos {
+            // The modifier symbols always come in this order, without separators
+            let symbols = [
+                (Modifiers::CONTROL, "⌃"),
+                (Modifiers::ALT, "⌥"),
+                (Modifiers::SHIFT, "⇧"),
+                (Modifiers::META, "⌘"),
+            ];
+
+            symbols
+                .iter()
+                .filter(|(modifier, _)| self.modifiers.contains(*modifier))
+                .map(|(_, symbol)| *symbol)
+                .chain(std::iter::once(key.as_str()))
+                .collect()
+        } else {
+            let meta_name = if cfg!(target_os = "windows") { "Win" } else { "Super" };
+            let names = [
+                (Modifiers::CONTROL, "Ctrl"),
+                (Modifiers::ALT, "Alt"),
+                (Modifiers::SHIFT, "Shift"),
+                (Modifiers::META, meta_name),
+            ];
+
+            names
+                .iter()
+                .filter(|(modifier, _)| self.modifiers.contains(*modifier))
+                .map(|(_, name)| *name)
+                .chain(std::iter::once(key.as_str()))
+                .collect::<Vec<_>>()
+                .join("+")
+        }
+    }
+}
+
+/// Whether Shift changes the characters, e.g. letters.
+fn has_case(characters: &str) -> bool {
+    characters.to_lowercase() != characters.to_uppercase()
+}
+
+fn key_name(key: &Key, macos: bool) -> String {
+    let name = match (key, macos) {
+        (Key::Character(c), _) if c == " " => "Space",
+        (Key::Character(c), _) => return c.to_uppercase(),
+        (Key::Enter, true) => "↩",
+        (Key::Escape, true) => "⎋",
+        (Key::Escape, false) => "Esc",
+        (Key::Backspace, true) => "⌫",
+        (Key::Delete, true) => "⌦",
+        (Key::Delete, false) => "Del",
+        (Key::Tab, true) => "⇥",
+        (Key::ArrowLeft, true) => "←",
+        (Key::ArrowLeft, false) => "Left",
+        (Key::ArrowRight, true) => "→",
+        (Key::ArrowRight, false) => "Right",
+        (Key::ArrowUp, true) => "↑",
+        (Key::ArrowUp, false) => "Up",
+        (Key::ArrowDown, true) => "↓",
+        (Key::ArrowDown, false) => "Down",
+        (Key::PageUp, true) => "⇞",
+        (Key::PageDown, true) => "⇟",
+        (Key::Home, true) => "↖",
+        (Key::End, true) => "↘",
+        (key, _) => return key.to_string(),
+    };
+
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use keyboard_types::{Code, Location};
+
+    use super::*;
+
+    fn press(key: Key, modifiers: Modifiers) -> KeyboardEvent {
+        KeyboardEvent { state: KeyState::Down, key, modifiers, ..Default::default() }
+    }
+
+    fn character(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[test]
+    fn modifiers_must_match_exactly() {
+        let save = Shortcut::new(character("s"), Modifiers::CONTROL);
+
+        assert!(save.matches(&press(character("s"), Modifiers::CONTROL)));
+        assert!(!save.matches(&press(character("s"), Modifiers::empty())));
+        assert!(!save.matches(&press(character("S"), Modifiers::CONTROL | Modifiers::SHIFT)));
+        assert!(!save.matches(&press(character("s"), Modifiers::CONTROL | Modifiers::META)));
+        assert!(!save.matches(&press(character("a"), Modifiers::CONTROL)));
+    }
+
+    #[test]
+    fn key_release_and_repeat() {
+        let save = Shortcut::new(character("s"), Modifiers::CONTROL);
+        let mut event = press(character("s"), Modifiers::CONTROL);
+
+        event.repeat = true;
+        assert!(save.matches(&event));
+        event.state = KeyState::Up;
+        assert!(!save.matches(&event));
+    }
+
+    #[test]
+    fn caps_lock() {
+        let save = Shortcut::new(character("s"), Modifiers::CONTROL);
+        let save_as = save.clone().with_shift();
+        let caps_lock = Modifiers::CONTROL | Modifiers::CAPS_LOCK;
+
+        assert!(save.matches(&press(character("S"), caps_lock)));
+        assert!(!save_as.matches(&press(character("S"), caps_lock)));
+        assert!(save_as.matches(&press(character("s"), caps_lock | Modifiers::SHIFT)));
+        assert!(save_as.matches(&press(character("S"), Modifiers::CONTROL | Modifiers::SHIFT)));
+    }
+
+    #[test]
+    fn shifted_punctuation() {
+        let zoom_in = Shortcut::new(character("+"), Modifiers::CONTROL);
+        // `+` needs Shift on a US layout, but not on a German one
+        assert!(zoom_in.matches(&press(character("+"), Modifiers::CONTROL | Modifiers::SHIFT)));
+        assert!(zoom_in.matches(&press(character("+"), Modifiers::CONTROL)));
+        assert!(!zoom_in.matches(&press(character("="), Modifiers::CONTROL | Modifiers::SHIFT)));
+
+        let help = Shortcut::new(character("?"), Modifiers::CONTROL);
+        assert!(help.matches(&press(character("?"), Modifiers::CONTROL | Modifiers::SHIFT)));
+
+        // A shortcut that contains Shift does require it
+        let shifted_help = help.with_shift();
+        assert!(shifted_help.matches(&press(character("?"), Modifiers::CONTROL | Modifiers::SHIFT)));
+        assert!(!shifted_help.matches(&press(character("?"), Modifiers::CONTROL)));
+    }
+
+    #[test]
+    fn altgr_never_matches() {
+        let at = Shortcut::new(character("@"), Modifiers::CONTROL | Modifiers::ALT);
+        let q = Shortcut::new(character("q"), Modifiers::empty());
+
+        assert!(!at.matches(&press(
+            character("@"),
+            Modifiers::CONTROL | Modifiers::ALT | Modifiers::ALT_GRAPH
+        )));
+        assert!(!q.matches(&press(character("@"), Modifiers::ALT_GRAPH)));
+        assert!(!q.matches(&press(character("q"), Modifiers::ALT_GRAPH)));
+    }
+
+    #[test]
+    fn altgr_as_control_alt() {
+        // AltGr+Q types `@` on a German layout, which Windows reports as Ctrl+Alt
+        let event = press(character("@"), Modifiers::CONTROL | Modifiers::ALT);
+
+        assert!(Shortcut::new(character("@"), Modifiers::CONTROL | Modifiers::ALT).matches(&event));
+        assert!(!Shortcut::new(character("@"), Modifiers::CONTROL).matches(&event));
+        assert!(!Shortcut::new(character("@"), Modifiers::ALT).matches(&event));
+        assert!(!Shortcut::new(character("q"), Modifiers::CONTROL | Modifiers::ALT).matches(&event));
+
+        // The characters must be exactly the same, since the case was chosen by the layout
+        let delete = Shortcut::new(character("d"), Modifiers::CONTROL | Modifiers::ALT);
+        assert!(delete.matches(&press(character("d"), Modifiers::CONTROL | Modifiers::ALT)));
+        assert!(!delete.matches(&press(character("D"), Modifiers::CONTROL | Modifiers::ALT)));
+    }
+
+    #[test]
+    fn numpad() {
+        let zoom_in = Shortcut::new(character("+"), Modifiers::CONTROL);
+        let numpad_add = KeyboardEvent {
+            code: Code::NumpadAdd,
+            location: Location::Numpad,
+            ..press(character("+"), Modifiers::CONTROL | Modifiers::NUM_LOCK)
+        };
+        assert!(zoom_in.matches(&numpad_add));
+
+        let confirm = Shortcut::new(Key::Enter, Modifiers::empty());
+        let numpad_enter = KeyboardEvent {
+            code: Code::NumpadEnter,
+            location: Location::Numpad,
+            ..press(Key::Enter, Modifiers::empty())
+        };
+        assert!(confirm.matches(&numpad_enter));
+
+        // Without Num Lock the digits are navigation keys
+        let numpad_end = KeyboardEvent {
+            code: Code::Numpad1,
+            location: Location::Numpad,
+            ..press(Key::End, Modifiers::empty())
+        };
+        assert!(!Shortcut::new(character("1"), Modifiers::empty()).matches(&numpad_end));
+        assert!(Shortcut::new(Key::End, Modifiers::empty()).matches(&numpad_end));
+    }
+
+    #[test]
+    fn named_keys_ignore_case_rules() {
+        let undo = Shortcut::new(Key::Backspace, Modifiers::ALT);
+
+        assert!(undo.matches(&press(Key::Backspace, Modifiers::ALT)));
+        assert!(!undo.matches(&press(Key::Backspace, Modifiers::ALT | Modifiers::SHIFT)));
+        assert!(!undo.matches(&press(Key::Delete, Modifiers::ALT)));
+    }
+
+    #[test]
+    fn new_removes_other_modifiers() {
+        let shortcut = Shortcut::new(character("s"), Modifiers::CONTROL | Modifiers::CAPS_LOCK);
+
+        assert_eq!(shortcut.modifiers(), Modifiers::CONTROL);
+    }
+}
@@ -0,0 +1,37 @@
+/// What the window's surface was actually created with, as returned by
+/// [`EventContext::surface_info()`][crate::EventContext::surface_info]. Renderers that draw into
+/// the window directly need this to pick a matching format, since a surface with an alpha channel
+/// may be blended with whatever is behind the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceInfo {
+    /// The number of bits per pixel, including the alpha channel if there is one.
+    pub color_depth: u8,
+    /// Whether the surface has an alpha channel.
+    ///
+    /// - On Linux this is the case for windows with a 32-bit visual, which baseview picks when it's
+    ///   available. With a compositing window manager the alpha channel makes the window
+    ///   translucent, so the colors should be premultiplied by their alpha.
+    /// - On Windows regular windows don't have an alpha channel.
+    /// - On macOS the view is layered on top of its window, and transparent pixels show whatever
+    ///   is behind the view.
+    ///
+    /// Windows and macOS windows with an OpenGL context describe the context's pixel format here
+    /// instead. That's the pixel format set on the window's device context on Windows, and the
+    /// format of the `NSOpenGLView` on macOS.
+    pub has_alpha: bool,
+    /// The pixel format of the window's OpenGL context, if it has one.
+    #[cfg(feature = "opengl")]
+    pub gl: Option<crate::gl::GlPixelFormat>,
+}
+
+impl SurfaceInfo {
+    /// The surface of a window that's drawn to through OpenGL is its OpenGL framebuffer.
+    #[cfg(all(feature = "opengl", not(target_os = "linux")))]
+    pub(crate) fn from_gl_pixel_format(format: crate::gl::GlPixelFormat) -> Self {
+        Self {
+            color_depth: format.color_bits + format.alpha_bits,
+            has_alpha: format.alpha_bits > 0,
+            gl: Some(format),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Optional instrumentation through the `tracing` crate, enabled with the `tracing` feature. The
+//! macros in this module forward to their `tracing` counterparts when the feature is enabled, and
+//! expand to nothing otherwise. Arguments are not evaluated at all in that case, so they should
+//! not have any side effects.
+//!
+//! Everything is logged with one of the following targets:
+//!
+//! - `baseview::handler`: every event sent to the window handler, every frame, and the handler's
+//!   panics.
+//! - `baseview::lifecycle`: windows being created, mapped, and destroyed.
+//! - `baseview::platform`: the native messages and events received by the backend. On Windows
+//!   this only includes a curated set of messages.
+//! - `baseview::dnd`: state changes for both incoming and outgoing drag and drop operations.
+//! - `baseview::frame`: frame pacing problems, like late frames or timer jitter.
+//!
+//! All of these are emitted within a `window` span that identifies the window through its X11
+//! window ID, `HWND`, or `NSView` pointer.
+
+#[cfg(feature = "tracing")]
+use crate::{Event, EventContext, EventStatus, MouseEvent, WindowEvent, WindowHandler};
+
+#[cfg(feature = "tracing")]
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($($arg:tt)+) => { ::tracing::trace!($($arg)+) };
+}
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($($arg:tt)+) => {};
+}
+
+#[cfg(feature = "tracing")]
+#[allow(unused_macros)]
+macro_rules! debug {
+    ($($arg:tt)+) => { ::tracing::debug!($($arg)+) };
+}
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_macros)]
+macro_rules! debug {
+    ($($arg:tt)+) => {};
+}
+
+#[cfg(feature = "tracing")]
+#[allow(unused_macros)]
+macro_rules! warn {
+    ($($arg:tt)+) => { ::tracing::warn!($($arg)+) };
+}
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_macros)]
+macro_rules! warn {
+    ($($arg:tt)+) => {};
+}
+
+/// Enter a span for a window. The fields should identify the window. Evaluates to a guard that
+/// leaves the span again when it's dropped.
+#[cfg(feature = "tracing")]
+#[allow(unused_macros)]
+macro_rules! window_span {
+    ($($field:tt)+) => { ::tracing::debug_span!("window", $($field)+).entered() };
+}
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_macros)]
+macro_rules! window_span {
+    ($($field:tt)+) => {
+        ()
+    };
+}
+
+/// Wrap the window handler so every event and frame it receives gets logged. This is the only
+/// place where the handler is called, so the backends don't need to instrument every call site.
+#[cfg(feature = "tracing")]
+pub(crate) fn instrument_handler<H: WindowHandler>(handler: H) -> InstrumentedHandler<H> {
+    InstrumentedHandler(handler)
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn instrument_handler<H>(handler: H) -> H {
+    handler
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) struct InstrumentedHandler<H>(H);
+
+#[cfg(feature = "tracing")]
+impl<H: WindowHandler> WindowHandler for InstrumentedHandler<H> {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        let _span = tracing::trace_span!(target: "baseview::handler", "on_frame").entered();
+
+        self.0.on_frame(window);
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        let _span = tracing::trace_span!(target: "baseview::handler", "on_event", kind = event_kind(&event))
+            .entered();
+        tracing::trace!(target: "baseview::handler", ?event);
+
+        let status = self.0.on_event(window, event);
+        tracing::trace!(target: "baseview::handler", ?status);
+
+        status
+    }
+}
+
+/// The event's variant, without any of the data it contains.
+#[cfg(feature = "tracing")]
+fn event_kind(event: &Event) -> &'static str {
+    match event {
+        Event::Mouse(MouseEvent::CursorMoved { .. }) => "Mouse::CursorMoved",
+        Event::Mouse(MouseEvent::ButtonPressed { .. }) => "Mouse::ButtonPressed",
+        Event::Mouse(MouseEvent::ButtonReleased { .. }) => "Mouse::ButtonReleased",
+        Event::Mouse(MouseEvent::WheelScrolled { .. }) => "Mouse::WheelScrolled",
+        Event::Mouse(MouseEvent::PenMoved { .. }) => "Mouse::PenMoved",
+        Event::Mouse(MouseEvent::CursorEntered) => "Mouse::CursorEntered",
+        Event::Mouse(MouseEvent::CursorLeft) => "Mouse::CursorLeft",
+        Event::Keyboard(_) => "Keyboard",
+        Event::Window(WindowEvent::Resized { .. }) => "Window::Resized",
+        Event::Window(WindowEvent::ResizeStarted) => "Window::ResizeStarted",
+        Event::Window(WindowEvent::ResizeEnded) => "Window::ResizeEnded",
+        Event::Window(WindowEvent::Focused) => "Window::Focused",
+        Event::Window(WindowEvent::Unfocused) => "Window::Unfocused",
+        Event::Window(WindowEvent::Shown) => "Window::Shown",
+        Event::Window(WindowEvent::Hidden) => "Window::Hidden",
+        Event::Window(WindowEvent::WillClose) => "Window::WillClose",
+        Event::Window(WindowEvent::DragEnter { .. }) => "Window::DragEnter",
+        Event::Window(WindowEvent::DragLeave) => "Window::DragLeave",
+        Event::Window(WindowEvent::Dragging) => "Window::Dragging",
+        Event::Window(WindowEvent::Drop(_)) => "Window::Drop",
+        Event::Window(WindowEvent::DropFailed(_)) => "Window::DropFailed",
+        Event::Window(WindowEvent::DragSourceMoved { .. }) => "Window::DragSourceMoved",
+        Event::Window(WindowEvent::DragSourceEnded { .. }) => "Window::DragSourceEnded",
+        Event::Dialog(_) => "Dialog",
+        #[cfg(feature = "accesskit")]
+        Event::AccessKit(crate::AccessKitEvent::InitialTreeRequested) => {
+            "AccessKit::InitialTreeRequested"
+        }
+        #[cfg(feature = "accesskit")]
+        Event::AccessKit(crate::AccessKitEvent::ActionRequested(_)) => "AccessKit::ActionRequested",
+        #[cfg(feature = "accesskit")]
+        Event::AccessKit(crate::AccessKitEvent::AccessibilityDeactivated) => {
+            "AccessKit::AccessibilityDeactivated"
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Storage for the values set through [`crate::EventContext::set_user_data()`]. This holds at most
+//! one value per type. The values are reference counted so they can be handed out without holding
+//! on to a borrow of the window's state, since the handler may set new values at any time.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Default)]
+pub(crate) struct UserData {
+    values: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+}
+
+impl UserData {
+    /// Store `value`, replacing the previous value of the same type.
+    pub(crate) fn set<T: Any>(&self, value: T) {
+        self.values.borrow_mut().insert(TypeId::of::<T>(), Rc::new(value));
+    }
+
+    pub(crate) fn get<T: Any>(&self) -> Option<Rc<T>> {
+        let value = self.values.borrow().get(&TypeId::of::<T>())?.clone();
+
+        // The type ID is the key, so this cannot fail
+        value.downcast().ok()
+    }
+}
@@ -0,0 +1,51 @@
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::windef::HCURSOR;
+use winapi::um::wingdi::{CreateBitmap, DeleteObject};
+use winapi::um::winuser::{CreateIconIndirect, ICONINFO};
+
+use crate::CursorIcon;
+
+/// Create a cursor from the icon's image. Returns a null handle if the cursor could not be created.
+/// The cursor needs to be freed using `DestroyCursor()` once it's no longer used.
+pub(super) unsafe fn create_cursor(icon: &CursorIcon) -> HCURSOR {
+    let width = icon.width() as i32;
+    let height = icon.height() as i32;
+
+    // The color bitmap's alpha channel determines the cursor's shape, but `CreateIconIndirect()`
+    // still needs a monochrome mask. Its rows are padded to 16 bits.
+    let mask_row_bytes = icon.width().div_ceil(16) as usize * 2;
+    let mask = vec![0xffu8; mask_row_bytes * icon.height() as usize];
+    let mask_bitmap = CreateBitmap(width, height, 1, 1, mask.as_ptr() as *const _);
+
+    // 32-bit bitmaps store their pixels as BGRA
+    let bgra: Vec<u8> =
+        icon.rgba().chunks_exact(4).flat_map(|rgba| [rgba[2], rgba[1], rgba[0], rgba[3]]).collect();
+    let color_bitmap = CreateBitmap(width, height, 1, 32, bgra.as_ptr() as *const _);
+
+    let cursor = if mask_bitmap.is_null() || color_bitmap.is_null() {
+        null_mut()
+    } else {
+        let (hotspot_x, hotspot_y) = icon.hotspot();
+        let mut icon_info = ICONINFO {
+            fIcon: FALSE,
+            xHotspot: hotspot_x,
+            yHotspot: hotspot_y,
+            hbmMask: mask_bitmap,
+            hbmColor: color_bitmap,
+        };
+
+        CreateIconIndirect(&mut icon_info)
+    };
+
+    // `CreateIconIndirect()` copies the bitmaps
+    if !mask_bitmap.is_null() {
+        DeleteObject(mask_bitmap as *mut _);
+    }
+    if !color_bitmap.is_null() {
+        DeleteObject(color_bitmap as *mut _);
+    }
+
+    cursor
+}
@@ -9,8 +9,11 @@ use winapi::{
 };
 
 use crate::event::Data;
+use crate::DropError;
 
-pub unsafe fn get_drop_data<F>(data_obj: *const IDataObject, callback: F) -> Option<shellapi::HDROP>
+pub unsafe fn get_drop_data<F>(
+    data_obj: *const IDataObject, callback: F,
+) -> Result<shellapi::HDROP, DropError>
 where
     F: FnMut(Data),
 {
@@ -18,7 +21,7 @@ where
 }
 unsafe fn iterate_filenames<F>(
     data_obj: *const IDataObject, mut callback: F,
-) -> Option<shellapi::HDROP>
+) -> Result<shellapi::HDROP, DropError>
 where
     F: FnMut(Data),
 {
@@ -54,6 +57,9 @@ where
         // The second parameter (0xFFFFFFFF) instructs the function to return the item count
         let item_count = DragQueryFileW(hdrop, 0xFFFFFFFF, ptr::null_mut(), 0);
 
+        // A single buffer is reused for every path, so each path is only allocated once as the
+        // `OsString` it's converted to
+        let mut path_buf: Vec<u16> = Vec::new();
         for i in 0..item_count {
             // Get the length of the path string NOT including the terminating null character.
             // Previously, this was using a fixed size array of MAX_PATH length, but the
@@ -62,7 +68,8 @@ where
             let str_len = character_count + 1;
 
             // Fill path_buf with the null-terminated file name
-            let mut path_buf = Vec::with_capacity(str_len);
+            path_buf.clear();
+            path_buf.reserve(str_len);
             DragQueryFileW(hdrop, i, path_buf.as_mut_ptr(), str_len as UINT);
             path_buf.set_len(str_len);
 
@@ -70,15 +77,13 @@ where
             callback(Data::Filepath(buf));
         }
 
-        Some(hdrop)
+        Ok(hdrop)
     } else if get_data_result == DV_E_FORMATETC {
-        // If the dropped item is not a file this error will occur.
-        // In this case it is OK to return without taking further action.
-        println!("Error occured while processing dropped/hovered item: item is not a file.");
-        None
+        // If the dropped item is not a file this error will occur
+        Err(DropError::NotAFile(None))
     } else {
-        println!("Unexpected error occured while processing dropped/hovered item.");
-        None
+        debug!(target: "baseview::dnd", get_data_result, "could not read the dragged data");
+        Err(DropError::Unavailable)
     }
 }
 
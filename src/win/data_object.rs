@@ -2,39 +2,137 @@
 // used as a reference
 // Perhaps https://github.com/DenisKolodin/nativeshell/blob/bc20a943be05eadb63a7d3323279d95328744e5c/nativeshell/src/shell/platform/win32/drag_context.rs#L83 may provide some ideas as well
 
+use std::cell::RefCell;
+use std::os::windows::ffi::OsStrExt;
+
+use winapi::um::winuser::RegisterClipboardFormatW;
 use windows::{
     core::implement,
     Win32::{
         Foundation::{
-            GlobalFree, DATA_S_SAMEFORMATETC, DV_E_FORMATETC, E_NOTIMPL, E_OUTOFMEMORY, HGLOBAL,
-            OLE_E_ADVISENOTSUPPORTED, POINT, S_FALSE, S_OK,
+            GlobalFree, DATA_S_SAMEFORMATETC, DV_E_FORMATETC, DV_E_TYMED, E_NOTIMPL, E_OUTOFMEMORY,
+            HGLOBAL, OLE_E_ADVISENOTSUPPORTED, POINT, S_FALSE, S_OK,
         },
         System::{
             Com::{
                 IDataObject, IDataObject_Impl, DATADIR_GET, FORMATETC, STGMEDIUM, STGMEDIUM_0,
                 STREAM_SEEK_END, TYMED_HGLOBAL, TYMED_ISTREAM,
             },
-            Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GLOBAL_ALLOC_FLAGS},
-            Ole::CF_HDROP,
+            Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GLOBAL_ALLOC_FLAGS},
+            Ole::{ReleaseStgMedium, CF_HDROP},
+        },
+        UI::Shell::{
+            SHCreateMemStream, SHCreateStdEnumFmtEtc, CFSTR_FILECONTENTS, CFSTR_FILEDESCRIPTORW,
+            DROPFILES, FD_PROGRESSUI, FD_UNICODE, FILEDESCRIPTORW, FILEGROUPDESCRIPTORW, HDROP,
         },
-        UI::Shell::{SHCreateMemStream, SHCreateStdEnumFmtEtc, DROPFILES, HDROP},
     },
 };
 
-use crate::event::Data;
+use crate::event::{Data, VirtualFile};
 
 #[derive(Debug, Clone)]
 #[implement(IDataObject)]
 pub struct DataObject {
     data: Data,
+    /// The registered `CFSTR_FILEDESCRIPTORW` clipboard format, used for virtual files.
+    file_descriptor_format: u16,
+    /// The registered `CFSTR_FILECONTENTS` clipboard format, used for virtual files.
+    file_contents_format: u16,
+    /// Data other objects stored with `SetData()`. The shell's drag image helper stores the drag
+    /// image and its own bookkeeping this way, and reads them back from the drop target's side.
+    stored: RefCell<Vec<(FORMATETC, Vec<u8>)>>,
 }
 
 impl DataObject {
     pub fn create(data: Data) -> IDataObject {
-        let data_object = Self { data };
+        // Registering a format that has already been registered returns the existing ID
+        let (file_descriptor_format, file_contents_format) = unsafe {
+            (
+                RegisterClipboardFormatW(CFSTR_FILEDESCRIPTORW.0) as u16,
+                RegisterClipboardFormatW(CFSTR_FILECONTENTS.0) as u16,
+            )
+        };
+
+        let data_object = Self {
+            data,
+            file_descriptor_format,
+            file_contents_format,
+            stored: RefCell::new(Vec::new()),
+        };
         data_object.into()
     }
 
+    /// The formats offered for `self.data`, in order of preference.
+    fn formats(&self) -> Vec<FORMATETC> {
+        let format = |cf_format, lindex, tymed: i32| FORMATETC {
+            cfFormat: cf_format,
+            ptd: std::ptr::null_mut(),
+            dwAspect: 1, // DVASPECT_CONTENT
+            lindex,
+            tymed: tymed as u32,
+        };
+
+        match &self.data {
+            Data::VirtualFile(_) => vec![
+                format(self.file_descriptor_format, -1, TYMED_HGLOBAL.0),
+                // There is only a single file, and FILECONTENTS uses `lindex` as its index
+                format(self.file_contents_format, 0, TYMED_ISTREAM.0),
+            ],
+            _ => vec![
+                format(CF_HDROP.0, -1, TYMED_HGLOBAL.0), // -1 = all items
+                format(CF_HDROP.0, -1, TYMED_ISTREAM.0),
+            ],
+        }
+    }
+
+    /// Whether `GetData()` can provide the data in `format`.
+    fn supports(&self, format: &FORMATETC) -> bool {
+        match &self.data {
+            Data::VirtualFile(_) => {
+                if format.cfFormat == self.file_descriptor_format {
+                    (format.tymed & TYMED_HGLOBAL.0 as u32) != 0
+                } else if format.cfFormat == self.file_contents_format {
+                    // Some drop targets pass -1 instead of an index when there's only one file
+                    (format.tymed & TYMED_ISTREAM.0 as u32) != 0
+                        && (format.lindex == 0 || format.lindex == -1)
+                } else {
+                    false
+                }
+            }
+            _ => {
+                (format.tymed == TYMED_HGLOBAL.0 as u32 || format.tymed == TYMED_ISTREAM.0 as u32)
+                    && format.cfFormat == CF_HDROP.0
+            }
+        }
+    }
+
+    /// The data stored with `SetData()` in `format`, if any.
+    fn stored_data(&self, format: &FORMATETC) -> Option<Vec<u8>> {
+        if (format.tymed & TYMED_HGLOBAL.0 as u32) == 0 {
+            return None;
+        }
+
+        self.stored
+            .borrow()
+            .iter()
+            .find(|(stored, _)| stored.cfFormat == format.cfFormat)
+            .map(|(_, data)| data.clone())
+    }
+
+    /// Copy the contents of a global memory object.
+    fn data_from_global(global: HGLOBAL) -> windows::core::Result<Vec<u8>> {
+        unsafe {
+            let size = GlobalSize(global);
+            let ptr = GlobalLock(global);
+            if ptr.is_null() {
+                return Err(E_OUTOFMEMORY.into());
+            }
+            let data = std::slice::from_raw_parts(ptr as *const u8, size).to_vec();
+            let _ = GlobalUnlock(global);
+            Ok(data)
+        }
+    }
+
     fn global_from_data(data: &[u8]) -> windows::core::Result<HGLOBAL> {
         unsafe {
             let global =
@@ -69,8 +167,8 @@ impl DataObject {
         };
         res.extend_from_slice(drop_files);
 
-        let mut file_str: Vec<u16> =
-            path.clone().into_os_string().into_string().unwrap().encode_utf16().collect();
+        // Windows paths are UTF-16 that isn't necessarily valid, which `encode_wide()` preserves
+        let mut file_str: Vec<u16> = path.as_os_str().encode_wide().collect();
         // https://learn.microsoft.com/en-us/windows/win32/shell/clipboard#cf_hdrop
         file_str.push(0);
         file_str.push(0); // Double null terminated
@@ -85,11 +183,72 @@ impl DataObject {
 
         res
     }
+
+    /// A `FILEGROUPDESCRIPTORW` describing `file`. The size is left out so the contents don't have
+    /// to be generated before the drop target asks for them.
+    fn data_for_file_descriptor(file: &VirtualFile) -> Vec<u8> {
+        let mut file_name = [0u16; 260];
+        // The name needs to be null terminated, so at most 259 characters fit
+        for (dest, c) in file_name[..259].iter_mut().zip(file.name().encode_utf16()) {
+            *dest = c;
+        }
+
+        let descriptor = FILEGROUPDESCRIPTORW {
+            cItems: 1,
+            fgd: [FILEDESCRIPTORW {
+                dwFlags: (FD_PROGRESSUI.0 | FD_UNICODE.0) as u32,
+                cFileName: file_name,
+                ..Default::default()
+            }],
+        };
+
+        unsafe {
+            ::std::slice::from_raw_parts(
+                (&descriptor as *const FILEGROUPDESCRIPTORW) as *const u8,
+                ::std::mem::size_of::<FILEGROUPDESCRIPTORW>(),
+            )
+        }
+        .to_vec()
+    }
 }
 
 impl IDataObject_Impl for DataObject {
     fn GetData(&self, pformatetcin: *const FORMATETC) -> windows::core::Result<STGMEDIUM> {
+        if let Some(data) = self.stored_data(unsafe { &*pformatetcin }) {
+            let global = Self::global_from_data(&data)?;
+            return Ok(STGMEDIUM {
+                tymed: TYMED_HGLOBAL.0 as u32,
+                u: STGMEDIUM_0 { hGlobal: global },
+                pUnkForRelease: std::mem::ManuallyDrop::new(None),
+            });
+        }
+
         match &self.data {
+            Data::VirtualFile(file) => {
+                let format = unsafe { &*pformatetcin };
+                if !self.supports(format) {
+                    return Err(DV_E_FORMATETC.into());
+                }
+
+                if format.cfFormat == self.file_descriptor_format {
+                    let global = Self::global_from_data(&Self::data_for_file_descriptor(file))?;
+                    Ok(STGMEDIUM {
+                        tymed: TYMED_HGLOBAL.0 as u32,
+                        u: STGMEDIUM_0 { hGlobal: global },
+                        pUnkForRelease: std::mem::ManuallyDrop::new(None),
+                    })
+                } else {
+                    // The stream starts at the beginning, since the drop target reads the file
+                    // from the stream's current position
+                    let stream = unsafe { SHCreateMemStream(Some(&file.contents())) };
+                    let stream = stream.ok_or_else(|| windows::core::Error::from(E_OUTOFMEMORY))?;
+                    Ok(STGMEDIUM {
+                        tymed: TYMED_ISTREAM.0 as u32,
+                        u: STGMEDIUM_0 { pstm: std::mem::ManuallyDrop::new(Some(stream)) },
+                        pUnkForRelease: std::mem::ManuallyDrop::new(None),
+                    })
+                }
+            }
             Data::Filepath(p) => {
                 let format = unsafe { &*pformatetcin };
                 let data = Self::data_for_hdrop(p);
@@ -131,10 +290,7 @@ impl IDataObject_Impl for DataObject {
 
     fn QueryGetData(&self, pformatetc: *const FORMATETC) -> windows::core::HRESULT {
         let format = unsafe { &*pformatetc };
-        // Only supporting file drops
-        if (format.tymed == TYMED_HGLOBAL.0 as u32 || format.tymed == TYMED_ISTREAM.0 as u32)
-            && format.cfFormat == CF_HDROP.0
-        {
+        if self.supports(format) || self.stored_data(format).is_some() {
             S_OK
         } else {
             S_FALSE
@@ -151,35 +307,38 @@ impl IDataObject_Impl for DataObject {
     }
 
     fn SetData(
-        &self, _pformatetc: *const FORMATETC,
-        _pmedium: *const windows::Win32::System::Com::STGMEDIUM,
-        _frelease: windows::Win32::Foundation::BOOL,
+        &self, pformatetc: *const FORMATETC,
+        pmedium: *const windows::Win32::System::Com::STGMEDIUM,
+        frelease: windows::Win32::Foundation::BOOL,
     ) -> windows::core::Result<()> {
-        Err(E_NOTIMPL.into())
+        let format = unsafe { *pformatetc };
+        let medium = unsafe { &*pmedium };
+        if medium.tymed != TYMED_HGLOBAL.0 as u32 {
+            return Err(DV_E_TYMED.into());
+        }
+
+        let data = Self::data_from_global(unsafe { medium.u.hGlobal })?;
+        // The medium is ours to release if the caller handed over ownership
+        if frelease.as_bool() {
+            unsafe { ReleaseStgMedium(pmedium as *mut _) };
+        }
+
+        // The target device belongs to the caller, and only the format itself is needed later
+        let format = FORMATETC { ptd: std::ptr::null_mut(), ..format };
+        let mut stored = self.stored.borrow_mut();
+        stored.retain(|(stored, _)| stored.cfFormat != format.cfFormat);
+        stored.push((format, data));
+
+        Ok(())
     }
 
     fn EnumFormatEtc(
         &self, dwdirection: u32,
     ) -> windows::core::Result<windows::Win32::System::Com::IEnumFORMATETC> {
         if dwdirection == DATADIR_GET.0 as u32 {
-            unsafe {
-                SHCreateStdEnumFmtEtc(&[
-                    FORMATETC {
-                        cfFormat: CF_HDROP.0,
-                        ptd: std::ptr::null_mut(),
-                        dwAspect: 1, // DVASPECT_CONTENT
-                        lindex: -1,  // -1 = all items
-                        tymed: TYMED_HGLOBAL.0 as u32,
-                    },
-                    FORMATETC {
-                        cfFormat: CF_HDROP.0,
-                        ptd: std::ptr::null_mut(),
-                        dwAspect: 1, // DVASPECT_CONTENT
-                        lindex: -1,  // -1 = all items
-                        tymed: TYMED_ISTREAM.0 as u32,
-                    },
-                ])
-            }
+            let mut formats = self.formats();
+            formats.extend(self.stored.borrow().iter().map(|(format, _)| *format));
+            unsafe { SHCreateStdEnumFmtEtc(&formats) }
         } else {
             Err(E_NOTIMPL.into())
         }
@@ -200,3 +359,38 @@ impl IDataObject_Impl for DataObject {
         Err(OLE_E_ADVISENOTSUPPORTED.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// The UTF-16 file list after the `DROPFILES` header.
+    fn file_list(data: &[u8]) -> Vec<u16> {
+        let header = std::mem::size_of::<DROPFILES>();
+        data[header..].chunks_exact(2).map(|unit| u16::from_ne_bytes([unit[0], unit[1]])).collect()
+    }
+
+    #[test]
+    fn hdrop_paths() {
+        let path = PathBuf::from(r"C:\Samples\Grüße.wav");
+        let data = DataObject::data_for_hdrop(&path);
+
+        let expected: Vec<u16> = path.as_os_str().encode_wide().chain([0, 0]).collect();
+        assert_eq!(file_list(&data), expected);
+    }
+
+    #[test]
+    fn hdrop_non_unicode_paths() {
+        // An unpaired surrogate, which isn't valid UTF-16 but is a legal file name
+        let wide = [b'C' as u16, b':' as u16, b'\\' as u16, 0xd800, b'.' as u16, b'w' as u16];
+        let path = PathBuf::from(OsString::from_wide(&wide));
+        assert!(path.to_str().is_none());
+
+        let data = DataObject::data_for_hdrop(&path);
+        assert_eq!(file_list(&data), [&wide[..], &[0, 0]].concat());
+    }
+}
@@ -0,0 +1,273 @@
+//! The per-monitor DPI functions only exist on recent versions of Windows. Linking to them directly
+//! would prevent the library from loading at all on Windows 7 and 8.1, so they're looked up at
+//! runtime instead.
+
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+use winapi::shared::minwindef::{BOOL, HMODULE, UINT};
+use winapi::shared::ntdef::HRESULT;
+use winapi::shared::windef::{
+    DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, HMONITOR, HWND,
+};
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::libloaderapi::{GetModuleHandleA, GetProcAddress, LoadLibraryA};
+use winapi::um::winuser::{MonitorFromWindow, MONITOR_DEFAULTTONEAREST};
+
+/// The DPI Windows uses when there's no scaling.
+pub(super) const DEFAULT_DPI: u32 = 96;
+
+/// `MDT_EFFECTIVE_DPI` from `shellscalingapi.h`, which isn't part of the winapi features we use.
+const MDT_EFFECTIVE_DPI: i32 = 0;
+
+/// Windows 10 1607 and up.
+type GetDpiForWindow = unsafe extern "system" fn(HWND) -> UINT;
+/// Windows 10 1703 and up.
+type SetProcessDpiAwarenessContext = unsafe extern "system" fn(DPI_AWARENESS_CONTEXT) -> BOOL;
+/// Windows Vista and up.
+type SetProcessDpiAware = unsafe extern "system" fn() -> BOOL;
+/// Windows 8.1 and up, in `shcore.dll`.
+type GetDpiForMonitor = unsafe extern "system" fn(HMONITOR, i32, *mut UINT, *mut UINT) -> HRESULT;
+
+/// The functions that could be found on this system.
+struct DpiFunctions {
+    get_dpi_for_window: Option<GetDpiForWindow>,
+    set_process_dpi_awareness_context: Option<SetProcessDpiAwarenessContext>,
+    set_process_dpi_aware: Option<SetProcessDpiAware>,
+    get_dpi_for_monitor: Option<GetDpiForMonitor>,
+}
+
+impl DpiFunctions {
+    fn availability(&self) -> Availability {
+        Availability {
+            get_dpi_for_window: self.get_dpi_for_window.is_some(),
+            set_process_dpi_awareness_context: self.set_process_dpi_awareness_context.is_some(),
+            set_process_dpi_aware: self.set_process_dpi_aware.is_some(),
+            get_dpi_for_monitor: self.get_dpi_for_monitor.is_some(),
+        }
+    }
+}
+
+/// Which of the [`DpiFunctions`] exist, so the fallbacks can be picked without calling them.
+#[derive(Debug, Clone, Copy, Default)]
+struct Availability {
+    get_dpi_for_window: bool,
+    set_process_dpi_awareness_context: bool,
+    set_process_dpi_aware: bool,
+    get_dpi_for_monitor: bool,
+}
+
+/// A process-wide DPI awareness mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DpiAwareness {
+    /// Windows 10 1703 and up. The window receives `WM_DPICHANGED` when it moves to a monitor
+    /// with a different DPI.
+    PerMonitorV2,
+    /// The DPI of the primary monitor at the time the user logged in is used everywhere. Windows
+    /// stretches the window's contents on monitors with a different DPI.
+    System,
+}
+
+/// Where a window's DPI can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DpiSource {
+    Window,
+    Monitor,
+}
+
+fn functions() -> &'static DpiFunctions {
+    static FUNCTIONS: OnceLock<DpiFunctions> = OnceLock::new();
+
+    FUNCTIONS.get_or_init(|| unsafe {
+        // `user32.dll` is always loaded since the rest of the backend links to it. `shcore.dll` is
+        // loaded on demand and never unloaded, so the function pointer stays valid.
+        let user32 = GetModuleHandleA(CString::new("user32.dll").unwrap().as_ptr());
+        let shcore = LoadLibraryA(CString::new("shcore.dll").unwrap().as_ptr());
+
+        DpiFunctions {
+            get_dpi_for_window: load(user32, "GetDpiForWindow"),
+            set_process_dpi_awareness_context: load(user32, "SetProcessDpiAwarenessContext"),
+            set_process_dpi_aware: load(user32, "SetProcessDPIAware"),
+            get_dpi_for_monitor: load(shcore, "GetDpiForMonitor"),
+        }
+    })
+}
+
+/// Look up `symbol` in `module`, which may be null if the module couldn't be loaded. `F` must be
+/// the function pointer type matching the symbol's signature.
+pub(super) unsafe fn load<F>(module: HMODULE, symbol: &str) -> Option<F> {
+    if module.is_null() {
+        return None;
+    }
+
+    let symbol = CString::new(symbol).unwrap();
+    let addr = GetProcAddress(module, symbol.as_ptr());
+    if addr.is_null() {
+        None
+    } else {
+        Some(std::mem::transmute_copy(&addr))
+    }
+}
+
+/// Make the process DPI aware. This uses per-monitor awareness on Windows 10 1703 and up, and
+/// system awareness on older versions or when that fails. The process stays DPI unaware if neither
+/// can be set, for instance because the application's manifest already picked a mode.
+pub(super) fn enable_dpi_awareness() {
+    let functions = functions();
+
+    enable_best_awareness(functions.availability(), |awareness| unsafe {
+        let result = match awareness {
+            DpiAwareness::PerMonitorV2 => (functions.set_process_dpi_awareness_context.unwrap())(
+                DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+            ),
+            DpiAwareness::System => (functions.set_process_dpi_aware.unwrap())(),
+        };
+
+        result != 0
+    });
+}
+
+/// The awareness modes that can be set with the available functions, best first.
+fn awareness_modes(available: Availability) -> impl Iterator<Item = DpiAwareness> {
+    [
+        (available.set_process_dpi_awareness_context, DpiAwareness::PerMonitorV2),
+        (available.set_process_dpi_aware, DpiAwareness::System),
+    ]
+    .into_iter()
+    .filter_map(|(available, awareness)| available.then_some(awareness))
+}
+
+/// Try every available awareness mode with `set` until one of them succeeds, and return that mode.
+/// `None` means the process stays DPI unaware.
+fn enable_best_awareness(
+    available: Availability, mut set: impl FnMut(DpiAwareness) -> bool,
+) -> Option<DpiAwareness> {
+    awareness_modes(available).find(|&awareness| set(awareness))
+}
+
+/// The window's DPI. This uses `GetDpiForWindow()` on Windows 10, the DPI of the monitor the window
+/// is on for Windows 8.1, and [`DEFAULT_DPI`] for anything older than that.
+pub(super) fn dpi_for_window(hwnd: HWND) -> u32 {
+    let functions = functions();
+
+    resolve_dpi(functions.availability(), |source| match source {
+        // This returns 0 for invalid windows
+        DpiSource::Window => unsafe { (functions.get_dpi_for_window.unwrap())(hwnd) },
+        DpiSource::Monitor => {
+            let (mut dpi_x, mut dpi_y) = (0, 0);
+            let result = unsafe {
+                let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+                (functions.get_dpi_for_monitor.unwrap())(
+                    monitor,
+                    MDT_EFFECTIVE_DPI,
+                    &mut dpi_x,
+                    &mut dpi_y,
+                )
+            };
+            // The horizontal and vertical DPI are always the same
+            if SUCCEEDED(result) {
+                dpi_x
+            } else {
+                0
+            }
+        }
+    })
+}
+
+/// The sources the DPI can be read from with the available functions, best first.
+fn dpi_sources(available: Availability) -> impl Iterator<Item = DpiSource> {
+    [
+        (available.get_dpi_for_window, DpiSource::Window),
+        (available.get_dpi_for_monitor, DpiSource::Monitor),
+    ]
+    .into_iter()
+    .filter_map(|(available, source)| available.then_some(source))
+}
+
+/// Query the available sources with `query` until one of them returns a DPI other than 0. Falls
+/// back to [`DEFAULT_DPI`].
+fn resolve_dpi(available: Availability, query: impl FnMut(DpiSource) -> u32) -> u32 {
+    dpi_sources(available).map(query).find(|&dpi| dpi != 0).unwrap_or(DEFAULT_DPI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVERYTHING: Availability = Availability {
+        get_dpi_for_window: true,
+        set_process_dpi_awareness_context: true,
+        set_process_dpi_aware: true,
+        get_dpi_for_monitor: true,
+    };
+    const WINDOWS_8_1: Availability = Availability {
+        get_dpi_for_window: false,
+        set_process_dpi_awareness_context: false,
+        set_process_dpi_aware: true,
+        get_dpi_for_monitor: true,
+    };
+    const WINDOWS_7: Availability = Availability {
+        get_dpi_for_window: false,
+        set_process_dpi_awareness_context: false,
+        set_process_dpi_aware: true,
+        get_dpi_for_monitor: false,
+    };
+
+    #[test]
+    fn awareness_fallbacks() {
+        assert_eq!(enable_best_awareness(EVERYTHING, |_| true), Some(DpiAwareness::PerMonitorV2));
+        assert_eq!(enable_best_awareness(WINDOWS_8_1, |_| true), Some(DpiAwareness::System));
+        assert_eq!(enable_best_awareness(WINDOWS_7, |_| true), Some(DpiAwareness::System));
+        assert_eq!(enable_best_awareness(Availability::default(), |_| true), None);
+    }
+
+    #[test]
+    fn failed_awareness_calls() {
+        let mut attempts = Vec::new();
+        let awareness = enable_best_awareness(EVERYTHING, |awareness| {
+            attempts.push(awareness);
+            awareness == DpiAwareness::System
+        });
+        assert_eq!(awareness, Some(DpiAwareness::System));
+        assert_eq!(attempts, [DpiAwareness::PerMonitorV2, DpiAwareness::System]);
+
+        // The awareness was already set by the manifest or by an earlier call
+        assert_eq!(enable_best_awareness(EVERYTHING, |_| false), None);
+    }
+
+    #[test]
+    fn dpi_fallbacks() {
+        let query = |source| match source {
+            DpiSource::Window => 144,
+            DpiSource::Monitor => 120,
+        };
+
+        assert_eq!(resolve_dpi(EVERYTHING, query), 144);
+        assert_eq!(resolve_dpi(WINDOWS_8_1, query), 120);
+        assert_eq!(resolve_dpi(WINDOWS_7, query), DEFAULT_DPI);
+    }
+
+    #[test]
+    fn failed_dpi_queries() {
+        let mut queried = Vec::new();
+        let dpi = resolve_dpi(EVERYTHING, |source| {
+            queried.push(source);
+            match source {
+                DpiSource::Window => 0,
+                DpiSource::Monitor => 192,
+            }
+        });
+        assert_eq!(dpi, 192);
+        assert_eq!(queried, [DpiSource::Window, DpiSource::Monitor]);
+
+        assert_eq!(resolve_dpi(EVERYTHING, |_| 0), DEFAULT_DPI);
+
+        // The monitor isn't queried once the window's DPI is known
+        let mut queried = Vec::new();
+        resolve_dpi(EVERYTHING, |source| {
+            queried.push(source);
+            96
+        });
+        assert_eq!(queried, [DpiSource::Window]);
+    }
+}
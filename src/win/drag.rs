@@ -5,45 +5,139 @@ use windows::{
     core::{implement, HRESULT},
     Win32::{
         Foundation::{
-            BOOL, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, S_OK,
+            BOOL, COLORREF, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS,
+            POINT, SIZE, S_OK,
+        },
+        Graphics::Gdi::{
+            CreateDIBSection, DeleteObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+            HBITMAP,
         },
         System::{
+            Com::{CoCreateInstance, IDataObject, CLSCTX_INPROC_SERVER},
             Ole::{
                 DoDragDrop, IDropSource, IDropSource_Impl, DROPEFFECT, DROPEFFECT_COPY,
-                DROPEFFECT_NONE,
+                DROPEFFECT_MOVE, DROPEFFECT_NONE,
             },
             SystemServices::{MK_LBUTTON, MODIFIERKEYS_FLAGS},
         },
+        UI::Shell::{CLSID_DragDropHelper, IDragSourceHelper, SHDRAGIMAGE},
     },
 };
 
 use super::data_object::*;
 use crate::event::Data;
+use crate::{DragImage, DropEffect};
 
-pub fn start_drag(data: Data) {
+/// Run the drag until the data is dropped or the drag is cancelled. Returns the effect the target
+/// performed, or `None` if the drag was cancelled or the target rejected the data.
+///
+/// `DoDragDrop()` runs its own modal loop, so `on_feedback` is called with whether the target under
+/// the cursor will accept the drop whenever the cursor moves or the target's answer changes.
+pub fn start_drag(
+    data: Data, effect: DropEffect, image: Option<DragImage>, on_feedback: impl Fn(bool) + 'static,
+) -> Option<DropEffect> {
     // TODO: Why after starting, does event handling seem screwy?
     // TODO implement IDataObjectAsyncCapability for DataObject to be able to start this in a thread?
 
+    debug!(target: "baseview::dnd", ?data, ?effect, "outgoing drag started");
+    // Windows doesn't have an equivalent to asking, so the target picks one of the effects
+    let allowed_effects = match effect {
+        DropEffect::Copy => DROPEFFECT_COPY,
+        DropEffect::Move => DROPEFFECT_MOVE,
+        DropEffect::Ask => DROPEFFECT(DROPEFFECT_COPY.0 | DROPEFFECT_MOVE.0),
+    };
     let data_object = DataObject::create(data);
-    let drop_source = DropSource::create();
+    if let Some(image) = &image {
+        // The drag still works without the image, it just shows the default feedback
+        if let Err(_err) = unsafe { set_drag_image(&data_object, image) } {
+            debug!(target: "baseview::dnd", error = %_err, "could not set the drag image");
+        }
+    }
+    let drop_source = DropSource::create(Box::new(on_feedback));
     let mut effects_out = DROPEFFECT_NONE;
-    unsafe {
-        let _ = DoDragDrop(
-            &data_object,
-            &drop_source,
-            DROPEFFECT_COPY, // DROPEFFECT_MOVE?
-            &mut effects_out as *mut DROPEFFECT,
-        );
+    let result = unsafe {
+        DoDragDrop(&data_object, &drop_source, allowed_effects, &mut effects_out as *mut DROPEFFECT)
+    };
+    debug!(target: "baseview::dnd", ?result, effect = effects_out.0, "outgoing drag ended");
+
+    if result != DRAGDROP_S_DROP {
+        None
+    } else if effects_out.0 & DROPEFFECT_MOVE.0 != 0 {
+        Some(DropEffect::Move)
+    } else if effects_out.0 & DROPEFFECT_COPY.0 != 0 {
+        Some(DropEffect::Copy)
+    } else {
+        None
+    }
+}
+
+/// Attach `image` to the data object through the shell's drag image helper, which shows it during
+/// `DoDragDrop()`. The helper stores the image on the data object with `SetData()`.
+unsafe fn set_drag_image(
+    data_object: &IDataObject, image: &DragImage,
+) -> windows::core::Result<()> {
+    let helper: IDragSourceHelper =
+        CoCreateInstance(&CLSID_DragDropHelper, None, CLSCTX_INPROC_SERVER)?;
+    let bitmap = create_bitmap(image)?;
+
+    let (hotspot_x, hotspot_y) = image.hotspot();
+    let drag_image = SHDRAGIMAGE {
+        sizeDragImage: SIZE { cx: image.width() as i32, cy: image.height() as i32 },
+        ptOffset: POINT { x: hotspot_x as i32, y: hotspot_y as i32 },
+        hbmpDragImage: bitmap,
+        // `CLR_NONE`, the alpha channel determines the image's shape
+        crColorKey: COLORREF(0xffffffff),
+    };
+
+    // The helper only takes ownership of the bitmap when this succeeds
+    helper.InitializeFromBitmap(&drag_image, data_object).inspect_err(|_| {
+        let _ = DeleteObject(bitmap);
+    })
+}
+
+/// A top-down 32-bit DIB section with the image's pixels, which the drag image helper expects to be
+/// premultiplied BGRA.
+unsafe fn create_bitmap(image: &DragImage) -> windows::core::Result<HBITMAP> {
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: image.width() as i32,
+            // A negative height puts the first row at the top
+            biHeight: -(image.height() as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut bits = std::ptr::null_mut();
+    let bitmap = CreateDIBSection(None, &info, DIB_RGB_COLORS, &mut bits, None, 0)?;
+
+    let pixels = std::slice::from_raw_parts_mut(bits as *mut u8, image.rgba().len());
+    for (bgra, rgba) in pixels.chunks_exact_mut(4).zip(image.rgba().chunks_exact(4)) {
+        let alpha = rgba[3] as u32;
+        let premultiply = |channel: u8| ((channel as u32 * alpha + 127) / 255) as u8;
+        bgra.copy_from_slice(&[
+            premultiply(rgba[2]),
+            premultiply(rgba[1]),
+            premultiply(rgba[0]),
+            rgba[3],
+        ]);
     }
+
+    Ok(bitmap)
 }
 
 #[implement(IDropSource)]
-pub struct DropSource {}
+pub struct DropSource {
+    on_feedback: Box<dyn Fn(bool)>,
+}
 
 #[allow(non_snake_case)]
 impl DropSource {
-    pub fn create() -> IDropSource {
-        Self {}.into()
+    pub fn create(on_feedback: Box<dyn Fn(bool)>) -> IDropSource {
+        Self { on_feedback }.into()
     }
 }
 
@@ -59,7 +153,9 @@ impl IDropSource_Impl for DropSource {
         }
     }
 
-    fn GiveFeedback(&self, _dweffect: DROPEFFECT) -> HRESULT {
+    fn GiveFeedback(&self, dweffect: DROPEFFECT) -> HRESULT {
+        (self.on_feedback)(dweffect != DROPEFFECT_NONE);
+
         DRAGDROP_S_USEDEFAULTCURSORS
     }
 }
@@ -1,4 +1,9 @@
 // Taken from https://github.com/rust-windowing/winit/blob/master/src/platform_impl/windows/drop_handler.rs
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use winapi::{
     ctypes::c_void,
@@ -9,26 +14,114 @@ use winapi::{
         winerror::S_OK,
     },
     um::{
+        handleapi::CloseHandle,
         objidl::IDataObject,
-        oleidl::{IDropTarget, IDropTargetVtbl, DROPEFFECT_COPY, DROPEFFECT_NONE},
+        oleidl::{IDropTarget, IDropTargetVtbl, DROPEFFECT_COPY, DROPEFFECT_MOVE, DROPEFFECT_NONE},
+        processthreadsapi::OpenProcess,
         shellapi, unknwnbase,
-        winnt::HRESULT,
+        winbase::QueryFullProcessImageNameW,
+        winnt::{HRESULT, PROCESS_QUERY_LIMITED_INFORMATION},
+        winuser::{GetForegroundWindow, GetWindowThreadProcessId, MK_CONTROL, MK_SHIFT},
     },
 };
 
 use super::data::*;
-use crate::event::{Event, WindowEvent};
+use super::window::screen_to_logical;
+use crate::drop_effect::{DragEventOrder, DropTargetEffect};
+use crate::event::{DropPayload, Event, WindowEvent};
+use crate::{DragSourceInfo, DropEffect, DropError, PhyPoint, Point};
+
+/// The drop effect of the drag that's over the window. This is shared between the drop target
+/// and the window, so the handler can pick the effect while it handles the drag's events.
+pub(super) struct DropTarget {
+    effect: Cell<DropTargetEffect>,
+    /// Callback that determines if the drop target is valid
+    drop_target_valid: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl DropTarget {
+    pub fn new(drop_target_valid: Option<Box<dyn Fn() -> bool + Send + Sync>>) -> Self {
+        Self { effect: Cell::new(DropTargetEffect::default()), drop_target_valid }
+    }
+
+    /// The effect the drop will have, or `None` if it will be rejected.
+    pub fn effect(&self) -> Option<DropEffect> {
+        let drop_target_valid = self.drop_target_valid.as_ref().is_none_or(|f| f());
+
+        if drop_target_valid {
+            self.effect.get().effect()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_chosen_effect(&self, effect: Option<DropEffect>) {
+        self.modify_effect(|target_effect| target_effect.chosen = Some(effect));
+    }
+
+    fn reset(&self) {
+        self.modify_effect(DropTargetEffect::reset);
+    }
+
+    fn modify_effect(&self, f: impl FnOnce(&mut DropTargetEffect)) {
+        let mut effect = self.effect.get();
+        f(&mut effect);
+        self.effect.set(effect);
+    }
+
+    /// Update the allowed and proposed effects from the arguments of an `IDropTarget` callback.
+    /// Like in Explorer, holding Shift moves the data and holding Control copies it. The drag
+    /// source's allowed effects are passed through `pdwEffect`.
+    fn update(&self, key_state: DWORD, allowed_effects: DWORD) {
+        let allowed = match (
+            allowed_effects & DROPEFFECT_COPY != 0,
+            allowed_effects & DROPEFFECT_MOVE != 0,
+        ) {
+            (true, true) => Some(DropEffect::Ask),
+            (true, false) => Some(DropEffect::Copy),
+            (false, true) => Some(DropEffect::Move),
+            (false, false) => None,
+        };
+        let requested =
+            if key_state & MK_SHIFT as DWORD != 0 && key_state & MK_CONTROL as DWORD == 0 {
+                DropEffect::Move
+            } else {
+                DropEffect::Copy
+            };
+
+        self.modify_effect(|effect| {
+            effect.allowed = allowed;
+            effect.proposed =
+                allowed.map(|allowed| if allowed.allows(requested) { requested } else { allowed });
+        });
+    }
+
+    /// The effect for the `pdwEffect` argument of the `IDropTarget` callbacks.
+    fn effect_flags(&self) -> DWORD {
+        match self.effect() {
+            Some(DropEffect::Move) => DROPEFFECT_MOVE,
+            Some(_) => DROPEFFECT_COPY,
+            None => DROPEFFECT_NONE,
+        }
+    }
+}
 
 #[repr(C)]
 pub struct DropHandlerData {
     pub interface: IDropTarget,
     refcount: AtomicUsize,
     window: HWND,
-    send_event: Box<dyn Fn(Event, Option<crate::PhyPoint>)>,
-    // Callback that determines if the drop target is valid
-    drop_target_valid: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+    /// Sends an event to the window handler. If a position is passed, then a `CursorMoved` event is
+    /// sent for that position first. Events that arrive while the handler is busy are buffered by
+    /// the window, so the handler receives them in the order they were sent.
+    send_event: Box<dyn Fn(Event, Option<Point>)>,
+    target: Rc<DropTarget>,
     cursor_effect: DWORD,
-    hovered_is_valid: bool, /* If the currently hovered item is not valid there must not be any `HoveredFileCancelled` emitted */
+    /// The events sent for the current drag. Drags without any files don't send `DragEnter`, so
+    /// they must not send any of the other events either.
+    event_order: DragEventOrder,
+    /// Where the current drag came from, looked up once when it entered the window.
+    source: Option<DragSourceInfo>,
 }
 
 pub struct DropHandler {
@@ -38,17 +131,17 @@ pub struct DropHandler {
 #[allow(non_snake_case)]
 impl DropHandler {
     pub fn new(
-        window: HWND, send_event: Box<dyn Fn(Event, Option<crate::PhyPoint>)>,
-        drop_target_valid: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+        window: HWND, send_event: Box<dyn Fn(Event, Option<Point>)>, target: Rc<DropTarget>,
     ) -> DropHandler {
         let data = Box::new(DropHandlerData {
             interface: IDropTarget { lpVtbl: &DROP_TARGET_VTBL as *const IDropTargetVtbl },
             refcount: AtomicUsize::new(1),
             window,
             send_event,
-            drop_target_valid,
+            target,
             cursor_effect: DROPEFFECT_NONE,
-            hovered_is_valid: false,
+            event_order: DragEventOrder::Idle,
+            source: None,
         });
         DropHandler { data: Box::into_raw(data) }
     }
@@ -79,37 +172,49 @@ impl DropHandler {
 
     // Implement IDropTarget
     pub unsafe extern "system" fn DragEnter(
-        this: *mut IDropTarget, pDataObj: *const IDataObject, _grfKeyState: DWORD,
-        _pt: *const POINTL, pdwEffect: *mut DWORD,
+        this: *mut IDropTarget, pDataObj: *const IDataObject, grfKeyState: DWORD,
+        pt: *const POINTL, pdwEffect: *mut DWORD,
     ) -> HRESULT {
         let drop_handler = Self::from_interface(this);
-        let hdrop = get_drop_data(pDataObj, |data| {
-            drop_handler.send_event(Event::Window(WindowEvent::DragEnter(data)), None);
+        drop_handler.target.reset();
+        drop_handler.target.update(grfKeyState, *pdwEffect);
+        // The handler should know where the drag entered before the first `DragOver`
+        let mut position = Some(drop_handler.position(pt));
+        drop_handler.source = drag_source_info();
+        let result = get_drop_data(pDataObj, |data| {
+            let source = drop_handler.source.clone();
+            drop_handler.send_event(WindowEvent::DragEnter { data, source }, position.take());
         });
-        drop_handler.hovered_is_valid = hdrop.is_some();
+        let entered = drop_handler.event_order.in_drag();
+        match result {
+            Ok(_) if !entered => {
+                drop_handler.send_event(WindowEvent::DropFailed(DropError::Empty), None);
+            }
+            Ok(_) => (),
+            Err(error) => drop_handler.send_event(WindowEvent::DropFailed(error), None),
+        }
         drop_handler.cursor_effect =
-            if drop_handler.hovered_is_valid && drop_handler.drop_target_valid() {
-                DROPEFFECT_COPY
-            } else {
-                DROPEFFECT_NONE
-            };
+            if entered { drop_handler.target.effect_flags() } else { DROPEFFECT_NONE };
+        debug!(
+            target: "baseview::dnd",
+            entered,
+            cursor_effect = drop_handler.cursor_effect,
+            "incoming drag entered"
+        );
         *pdwEffect = drop_handler.cursor_effect;
 
         S_OK
     }
 
     pub unsafe extern "system" fn DragOver(
-        this: *mut IDropTarget, _grfKeyState: DWORD, pt: *const POINTL, pdwEffect: *mut DWORD,
+        this: *mut IDropTarget, grfKeyState: DWORD, pt: *const POINTL, pdwEffect: *mut DWORD,
     ) -> HRESULT {
         let drop_handler = Self::from_interface(this);
-        let pt: POINTL = std::mem::transmute(pt); // Signature is incorrect
-        if drop_handler.hovered_is_valid {
-            drop_handler.send_event(
-                Event::Window(WindowEvent::Dragging),
-                Some(crate::PhyPoint { x: pt.x, y: pt.y }),
-            );
-            drop_handler.cursor_effect =
-                if drop_handler.drop_target_valid() { DROPEFFECT_COPY } else { DROPEFFECT_NONE };
+        if drop_handler.event_order.in_drag() {
+            drop_handler.target.update(grfKeyState, *pdwEffect);
+            let position = drop_handler.position(pt);
+            drop_handler.send_event(WindowEvent::Dragging, Some(position));
+            drop_handler.cursor_effect = drop_handler.target.effect_flags();
         }
         *pdwEffect = drop_handler.cursor_effect;
 
@@ -118,30 +223,45 @@ impl DropHandler {
 
     pub unsafe extern "system" fn DragLeave(this: *mut IDropTarget) -> HRESULT {
         let drop_handler = Self::from_interface(this);
-        drop_handler.send_event(Event::Window(WindowEvent::DragLeave), None);
+        debug!(target: "baseview::dnd", "incoming drag left");
+        if drop_handler.event_order.in_drag() {
+            drop_handler.send_event(WindowEvent::DragLeave, None);
+        }
+        drop_handler.target.reset();
 
         S_OK
     }
 
     pub unsafe extern "system" fn Drop(
-        this: *mut IDropTarget, pDataObj: *const IDataObject, _grfKeyState: DWORD,
-        _pt: *const POINTL, _pdwEffect: *mut DWORD,
+        this: *mut IDropTarget, pDataObj: *const IDataObject, grfKeyState: DWORD,
+        pt: *const POINTL, pdwEffect: *mut DWORD,
     ) -> HRESULT {
         let drop_handler = Self::from_interface(this);
-        let drop_target_valid = drop_handler.drop_target_valid();
+        // The drop doesn't need to be preceded by a `DragOver` at the same position
+        let position = drop_handler.position(pt);
+        drop_handler.target.update(grfKeyState, *pdwEffect);
+        // The source learns which effect the drop had through `pdwEffect`
+        let effect_flags = drop_handler.target.effect_flags();
+        let entered = drop_handler.event_order.in_drag();
         let mut dropped = false;
-        let hdrop = get_drop_data(pDataObj, |data| {
-            if drop_target_valid {
+        // The files are only read if the drop is accepted, and then moved into a single payload
+        if entered && effect_flags != DROPEFFECT_NONE {
+            let mut items = Vec::new();
+            if let Ok(hdrop) = get_drop_data(pDataObj, |data| items.push(data)) {
+                shellapi::DragFinish(hdrop);
+            }
+            if !items.is_empty() {
                 dropped = true;
-                drop_handler.send_event(Event::Window(WindowEvent::Drop(data)), None);
+                let payload = DropPayload { items, position, source: drop_handler.source.clone() };
+                drop_handler.send_event(WindowEvent::Drop(payload), Some(position));
             }
-        });
-        if let Some(hdrop) = hdrop {
-            shellapi::DragFinish(hdrop);
         }
-        if !dropped {
-            drop_handler.send_event(Event::Window(WindowEvent::DragLeave), None);
+        debug!(target: "baseview::dnd", dropped, effect_flags, "incoming drag dropped");
+        if entered && !dropped {
+            drop_handler.send_event(WindowEvent::DragLeave, None);
         }
+        *pdwEffect = if dropped { effect_flags } else { DROPEFFECT_NONE };
+        drop_handler.target.reset();
 
         S_OK
     }
@@ -152,17 +272,45 @@ impl DropHandler {
 }
 
 impl DropHandlerData {
-    fn send_event(&self, event: Event, pt: Option<crate::PhyPoint>) {
-        (self.send_event)(event, pt);
+    fn send_event(&mut self, event: WindowEvent, position: Option<Point>) {
+        self.event_order.track(&event);
+        (self.send_event)(Event::Window(event), position);
     }
 
-    fn drop_target_valid(&self) -> bool {
-        if let Some(f) = &self.drop_target_valid {
-            (f)()
-        } else {
-            true
+    /// Convert a position from the `IDropTarget` callbacks to a logical position within the window.
+    unsafe fn position(&self, pt: *const POINTL) -> Point {
+        let pt: POINTL = std::mem::transmute(pt); // Signature is incorrect
+        screen_to_logical(self.window, PhyPoint { x: pt.x, y: pt.y })
+    }
+}
+
+/// OLE doesn't tell drop targets where a drag came from, but the application that started a drag
+/// is almost always the foreground application, so that's used as the drag's source instead.
+unsafe fn drag_source_info() -> Option<DragSourceInfo> {
+    let window = GetForegroundWindow();
+    if window.is_null() {
+        return None;
+    }
+    let mut process_id = 0;
+    GetWindowThreadProcessId(window, &mut process_id);
+    if process_id == 0 {
+        return None;
+    }
+
+    // Elevated processes can't be opened, in which case only the process ID is known
+    let mut application = None;
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+    if !process.is_null() {
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as DWORD;
+        if QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut len) != 0 {
+            let path = OsString::from_wide(&buffer[..len as usize]);
+            application = Path::new(&path).file_stem().and_then(|s| s.to_str()).map(str::to_owned);
         }
+        CloseHandle(process);
     }
+
+    Some(DragSourceInfo { platform_id: process_id as u64, application })
 }
 
 impl Drop for DropHandler {
@@ -0,0 +1,150 @@
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+
+use winapi::shared::windef::HWND as WinapiHWND;
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::{
+        Foundation::{ERROR_CANCELLED, HWND},
+        System::Com::{
+            CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, CLSCTX_INPROC_SERVER,
+            COINIT_APARTMENTTHREADED,
+        },
+        UI::Shell::{
+            Common::COMDLG_FILTERSPEC, FileOpenDialog, FileSaveDialog, IFileDialog,
+            IFileOpenDialog, IFileSaveDialog, IShellItem, SHCreateItemFromParsingName,
+            FILEOPENDIALOGOPTIONS, FOS_ALLOWMULTISELECT, FOS_FILEMUSTEXIST, FOS_FORCEFILESYSTEM,
+            FOS_OVERWRITEPROMPT, FOS_PICKFOLDERS, SIGDN_FILESYSPATH,
+        },
+    },
+};
+
+use crate::{FileDialogKind, FileDialogOptions};
+
+/// Show the dialog and wait until the user closes it. Returns the paths the user picked, which is
+/// empty if the dialog was cancelled or if it couldn't be shown.
+///
+/// `Show()` runs a modal loop that keeps dispatching the owner's messages, so this must not be
+/// called while the window handler is borrowed.
+pub fn show(owner: WinapiHWND, options: &FileDialogOptions) -> Vec<PathBuf> {
+    unsafe {
+        // COM may already be initialized on this thread, for instance through `OleInitialize()`.
+        // Every successful call needs to be balanced, including the ones that return `S_FALSE`.
+        let com_initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+        let result = match options.kind {
+            FileDialogKind::Save => show_save_dialog(owner, options),
+            FileDialogKind::Open | FileDialogKind::OpenDirectory => {
+                show_open_dialog(owner, options)
+            }
+        };
+        if com_initialized {
+            CoUninitialize();
+        }
+
+        result.unwrap_or_else(|_err| {
+            debug!(target: "baseview::platform", error = %_err, "could not show the file dialog");
+            Vec::new()
+        })
+    }
+}
+
+unsafe fn show_open_dialog(
+    owner: WinapiHWND, options: &FileDialogOptions,
+) -> windows::core::Result<Vec<PathBuf>> {
+    let dialog: IFileOpenDialog = CoCreateInstance(&FileOpenDialog, None, CLSCTX_INPROC_SERVER)?;
+
+    let mut flags = FOS_FILEMUSTEXIST;
+    if options.kind == FileDialogKind::OpenDirectory {
+        flags |= FOS_PICKFOLDERS;
+    }
+    if options.multiple {
+        flags |= FOS_ALLOWMULTISELECT;
+    }
+    configure(&dialog, options, flags)?;
+    if !run(&dialog, owner)? {
+        return Ok(Vec::new());
+    }
+
+    let items = dialog.GetResults()?;
+    (0..items.GetCount()?).map(|index| item_path(&items.GetItemAt(index)?)).collect()
+}
+
+unsafe fn show_save_dialog(
+    owner: WinapiHWND, options: &FileDialogOptions,
+) -> windows::core::Result<Vec<PathBuf>> {
+    let dialog: IFileSaveDialog = CoCreateInstance(&FileSaveDialog, None, CLSCTX_INPROC_SERVER)?;
+
+    configure(&dialog, options, FOS_OVERWRITEPROMPT)?;
+    if let Some(file_name) = &options.file_name {
+        let file_name = HSTRING::from(file_name.as_str());
+        dialog.SetFileName(PCWSTR(file_name.as_ptr()))?;
+    }
+    if !run(&dialog, owner)? {
+        return Ok(Vec::new());
+    }
+
+    Ok(vec![item_path(&dialog.GetResult()?)?])
+}
+
+/// Apply the options both kinds of dialogs share.
+unsafe fn configure(
+    dialog: &IFileDialog, options: &FileDialogOptions, flags: FILEOPENDIALOGOPTIONS,
+) -> windows::core::Result<()> {
+    dialog.SetOptions(dialog.GetOptions()? | FOS_FORCEFILESYSTEM | flags)?;
+
+    if let Some(title) = &options.title {
+        let title = HSTRING::from(title.as_str());
+        dialog.SetTitle(PCWSTR(title.as_ptr()))?;
+    }
+
+    if options.kind != FileDialogKind::OpenDirectory && !options.filters.is_empty() {
+        // The specs point into these strings, so they need to outlive the call
+        let strings: Vec<(HSTRING, HSTRING)> = options
+            .filters
+            .iter()
+            .map(|filter| {
+                let patterns: Vec<String> =
+                    filter.extensions.iter().map(|extension| format!("*.{}", extension)).collect();
+                (HSTRING::from(filter.name.as_str()), HSTRING::from(patterns.join(";")))
+            })
+            .collect();
+        let specs: Vec<COMDLG_FILTERSPEC> = strings
+            .iter()
+            .map(|(name, spec)| COMDLG_FILTERSPEC {
+                pszName: PCWSTR(name.as_ptr()),
+                pszSpec: PCWSTR(spec.as_ptr()),
+            })
+            .collect();
+        dialog.SetFileTypes(&specs)?;
+    }
+
+    // The platform picks a directory instead if this one doesn't exist
+    if let Some(directory) = &options.directory {
+        let directory = HSTRING::from(directory.as_os_str());
+        let folder: windows::core::Result<IShellItem> =
+            SHCreateItemFromParsingName(PCWSTR(directory.as_ptr()), None);
+        if let Ok(folder) = folder {
+            dialog.SetFolder(&folder)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the dialog's modal loop. Returns false if the user cancelled the dialog.
+unsafe fn run(dialog: &IFileDialog, owner: WinapiHWND) -> windows::core::Result<bool> {
+    match dialog.Show(HWND(owner as isize)) {
+        Ok(()) => Ok(true),
+        Err(err) if err.code() == ERROR_CANCELLED.to_hresult() => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+unsafe fn item_path(item: &IShellItem) -> windows::core::Result<PathBuf> {
+    let name = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+    let path = OsString::from_wide(name.as_wide());
+    CoTaskMemFree(Some(name.0 as *const _));
+
+    Ok(path.into())
+}
@@ -0,0 +1,106 @@
+//! Frame timing that keeps working while a modal loop is running. Modal loops, like the ones for
+//! context menus or for scrolling the host's windows, only dispatch `WM_TIMER` messages
+//! sporadically. The frame timer is therefore backed by a thread that posts a registered message
+//! at the same interval, since posted messages are dispatched by those loops much more reliably.
+//! Both the timer and the thread only wake up the window thread, so the handler is always called
+//! from there.
+
+use std::ffi::{CString, OsStr};
+use std::os::windows::ffi::OsStrExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use winapi::shared::basetsd::UINT_PTR;
+use winapi::shared::minwindef::{UINT, ULONG};
+use winapi::shared::windef::HWND;
+use winapi::um::libloaderapi::GetModuleHandleA;
+use winapi::um::winuser::{PostMessageW, RegisterWindowMessageW, SetTimer, TIMERPROC};
+
+use super::dpi::load;
+
+/// `TIMERV_NO_COALESCING` from `winuser.h`. The system may otherwise delay the timer to combine it
+/// with other timers.
+const TIMERV_NO_COALESCING: ULONG = 0xFFFF_FFFF;
+
+/// Windows 8 and up.
+type SetCoalescableTimer =
+    unsafe extern "system" fn(HWND, UINT_PTR, UINT, TIMERPROC, ULONG) -> UINT_PTR;
+
+fn set_coalescable_timer() -> Option<SetCoalescableTimer> {
+    static FUNCTION: OnceLock<Option<SetCoalescableTimer>> = OnceLock::new();
+
+    *FUNCTION.get_or_init(|| unsafe {
+        // `user32.dll` is always loaded since the rest of the backend links to it
+        let user32 = GetModuleHandleA(CString::new("user32.dll").unwrap().as_ptr());
+        load(user32, "SetCoalescableTimer")
+    })
+}
+
+/// The message the frame thread posts to the window. The wnd_proc handles it just like the frame
+/// timer's `WM_TIMER` messages.
+pub(super) fn frame_message() -> UINT {
+    static MESSAGE: OnceLock<UINT> = OnceLock::new();
+
+    *MESSAGE.get_or_init(|| {
+        let name: Vec<u16> = OsStr::new("baseview-frame").encode_wide().chain([0]).collect();
+        unsafe { RegisterWindowMessageW(name.as_ptr()) }
+    })
+}
+
+/// Create or replace the window timer with the ID `id`, opting out of timer coalescing on versions
+/// of Windows that support it.
+pub(super) unsafe fn set_timer(hwnd: HWND, id: UINT_PTR, interval_ms: UINT) {
+    match set_coalescable_timer() {
+        Some(set_coalescable_timer) => {
+            set_coalescable_timer(hwnd, id, interval_ms, None, TIMERV_NO_COALESCING)
+        }
+        None => SetTimer(hwnd, id, interval_ms, None),
+    };
+}
+
+/// A thread that posts [`frame_message()`] to a window at a fixed interval. The thread stops when
+/// this is dropped.
+pub(super) struct FrameThread {
+    /// Dropping this wakes up the thread, which then exits.
+    _stop: Sender<()>,
+    /// Set while a posted message hasn't been handled yet. No new messages are posted until then,
+    /// so they don't pile up in the queue while the window thread is busy.
+    message_pending: Arc<AtomicBool>,
+}
+
+impl FrameThread {
+    pub fn start(hwnd: HWND, interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let message_pending = Arc::new(AtomicBool::new(false));
+        // Window handles can be used from any thread
+        let hwnd = hwnd as usize;
+        let message = frame_message();
+
+        let thread_message_pending = message_pending.clone();
+        thread::Builder::new()
+            .name("baseview-frame".into())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if thread_message_pending.swap(true, Ordering::AcqRel) {
+                        continue;
+                    }
+
+                    // This fails once the window has been destroyed, which also stops the thread
+                    if unsafe { PostMessageW(hwnd as HWND, message, 0, 0) } == 0 {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn the frame thread");
+
+        Self { _stop: stop, message_pending }
+    }
+
+    /// Called when the window receives [`frame_message()`], so the thread can post the next one.
+    pub fn message_received(&self) {
+        self.message_pending.store(false, Ordering::Release);
+    }
+}
@@ -43,7 +43,7 @@ use winapi::um::winuser::{
     VK_PROCESSKEY, VK_RCONTROL, VK_RETURN, VK_RIGHT, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SCROLL,
     VK_SELECT, VK_SHIFT, VK_SLEEP, VK_SNAPSHOT, VK_SUBTRACT, VK_TAB, VK_UP, VK_VOLUME_DOWN,
     VK_VOLUME_MUTE, VK_VOLUME_UP, VK_ZOOM, WM_CHAR, WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP,
-    WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP,
+    WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_UNICHAR,
 };
 
 const VK_ABNT_C2: INT = 0xc2;
@@ -92,14 +92,35 @@ const SCAN_MASK: LPARAM = 0x1ff_0000;
 /// with a matching scan code, therefore it is reasonable to stash the data
 /// from this message and defer til later to actually produce the event.
 unsafe fn is_last_message(hwnd: HWND, msg: UINT, lparam: LPARAM) -> bool {
-    let expected_msg = match msg {
-        WM_KEYDOWN | WM_CHAR => WM_CHAR,
-        WM_SYSKEYDOWN | WM_SYSCHAR => WM_SYSCHAR,
+    let expected_msgs: &[UINT] = match msg {
+        WM_KEYDOWN | WM_CHAR | WM_UNICHAR => &[WM_CHAR, WM_UNICHAR],
+        WM_SYSKEYDOWN | WM_SYSCHAR => &[WM_SYSCHAR],
         _ => unreachable!(),
     };
-    let mut msg = mem::zeroed();
-    let avail = PeekMessageW(&mut msg, hwnd, expected_msg, expected_msg, PM_NOREMOVE);
-    avail == 0 || msg.lParam & SCAN_MASK != lparam & SCAN_MASK
+    expected_msgs.iter().all(|&expected_msg| {
+        let mut msg = mem::zeroed();
+        let avail = PeekMessageW(&mut msg, hwnd, expected_msg, expected_msg, PM_NOREMOVE);
+        avail == 0 || msg.lParam & SCAN_MASK != lparam & SCAN_MASK
+    })
+}
+
+/// The UTF-16 code units a character message carries. `WM_UNICHAR` carries a UTF-32 code point,
+/// which may need two code units. Returns `None` if that isn't a valid code point.
+fn char_message_code_units(msg: UINT, wparam: WPARAM) -> Option<Vec<u16>> {
+    if msg == WM_UNICHAR {
+        let c = char::from_u32(wparam as u32)?;
+        Some(c.encode_utf16(&mut [0; 2]).to_vec())
+    } else {
+        Some(vec![wparam as u16])
+    }
+}
+
+/// Whether the code unit is the first half of a surrogate pair. Characters outside of the Basic
+/// Multilingual Plane, like emoji, arrive as two `WM_CHAR` messages. They can't be told apart from
+/// other characters by their scan codes, since input methods send both halves as `VK_PACKET`
+/// key presses with the code unit in place of the scan code.
+fn is_high_surrogate(code_unit: u16) -> bool {
+    (0xd800..=0xdbff).contains(&code_unit)
 }
 
 const MODIFIER_MAP: &[(INT, Modifiers, SHORT)] = &[
@@ -408,8 +429,9 @@ impl KeyboardState {
     ///
     /// This is the main interface point for generating cooked keyboard events
     /// from raw platform messages. It should be called for each relevant message,
-    /// which comprises: `WM_KEYDOWN`, `WM_KEYUP`, `WM_CHAR`, `WM_SYSKEYDOWN`,
-    /// `WM_SYSKEYUP`, `WM_SYSCHAR`, and `WM_INPUTLANGCHANGE`.
+    /// which comprises: `WM_KEYDOWN`, `WM_KEYUP`, `WM_CHAR`, `WM_UNICHAR`, `WM_SYSKEYDOWN`,
+    /// `WM_SYSKEYUP`, `WM_SYSCHAR`, and `WM_INPUTLANGCHANGE`. `WM_UNICHAR` messages with
+    /// `UNICODE_NOCHAR` are only probing for support, and need to be answered by the window.
     ///
     /// As a general theory, many keyboard events generate a sequence of platform
     /// messages. In these cases, we stash information from all messages but the
@@ -431,6 +453,10 @@ impl KeyboardState {
     /// a key event with `key = Key::Character("´´")`, which also matches browser
     /// behavior.
     ///
+    /// Characters outside of the Basic Multilingual Plane are sent as a surrogate
+    /// pair in two `WM_CHAR` messages. The high surrogate is always stashed, so a
+    /// single event with the whole character is produced for the low surrogate.
+    ///
     /// # Safety
     ///
     /// The `hwnd` argument must be a valid `HWND`. Similarly, the `lparam` must be
@@ -488,9 +514,12 @@ impl KeyboardState {
                 };
                 Some(event)
             }
-            WM_CHAR | WM_SYSCHAR => {
+            WM_CHAR | WM_SYSCHAR | WM_UNICHAR => {
                 //println!("char wparam {:x} lparam {:x}", wparam, lparam);
-                if is_last_message(hwnd, msg, lparam) {
+                let code_units = char_message_code_units(msg, wparam)?;
+                let ends_with_high_surrogate =
+                    code_units.last().is_some_and(|&code_unit| is_high_surrogate(code_unit));
+                if !ends_with_high_surrogate && is_last_message(hwnd, msg, lparam) {
                     let stash_vk = self.stash_vk.take();
                     let modifiers = self.get_modifiers();
                     let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
@@ -499,7 +528,7 @@ impl KeyboardState {
                     let key = if self.stash_utf16.is_empty() && wparam < 0x20 {
                         vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers))
                     } else {
-                        self.stash_utf16.push(wparam as u16);
+                        self.stash_utf16.extend_from_slice(&code_units);
                         if let Ok(s) = String::from_utf16(&self.stash_utf16) {
                             Key::Character(s)
                         } else {
@@ -522,7 +551,7 @@ impl KeyboardState {
                     };
                     Some(event)
                 } else {
-                    self.stash_utf16.push(wparam as u16);
+                    self.stash_utf16.extend_from_slice(&code_units);
                     None
                 }
             }
@@ -535,6 +564,13 @@ impl KeyboardState {
         }
     }
 
+    /// Forget about pending dead keys. The modifiers are always queried from the system, so those
+    /// can't get stuck.
+    pub(crate) fn reset(&mut self) {
+        self.stash_vk = None;
+        self.stash_utf16.clear();
+    }
+
     /// Get the modifier state.
     ///
     /// This function is designed to be called from a message handler, and
@@ -1,8 +1,14 @@
+mod cursor;
 mod data;
 mod data_object;
+mod dpi;
 mod drag;
 mod drop_handler;
+mod file_dialog;
+mod frame_timer;
 mod keyboard;
+mod pen;
+mod popup;
 mod window;
 
 pub use window::*;
@@ -0,0 +1,94 @@
+//! Pressure and tilt for pens. Windows 8 and up send pointer messages for pens, and they're only
+//! turned into regular mouse messages after reaching `DefWindowProc()`. The pointer functions
+//! don't exist on Windows 7, so they're looked up at runtime.
+
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+use winapi::shared::basetsd::UINT32;
+use winapi::shared::minwindef::{BOOL, DWORD, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::libloaderapi::GetModuleHandleA;
+use winapi::um::winuser::{
+    ScreenToClient, PEN_MASK_PRESSURE, PEN_MASK_TILT_X, PEN_MASK_TILT_Y, POINTER_INPUT_TYPE,
+    POINTER_PEN_INFO, PT_PEN,
+};
+
+use super::dpi::load;
+use crate::PhyPoint;
+
+/// Pen pressure is reported from 0 to this value.
+const MAX_PRESSURE: f32 = 1024.0;
+/// Pen tilt is reported in degrees, from minus this value to this value.
+const MAX_TILT: f32 = 90.0;
+
+type GetPointerType = unsafe extern "system" fn(UINT32, *mut POINTER_INPUT_TYPE) -> BOOL;
+type GetPointerPenInfo = unsafe extern "system" fn(UINT32, *mut POINTER_PEN_INFO) -> BOOL;
+
+struct PenFunctions {
+    get_pointer_type: Option<GetPointerType>,
+    get_pointer_pen_info: Option<GetPointerPenInfo>,
+}
+
+fn functions() -> &'static PenFunctions {
+    static FUNCTIONS: OnceLock<PenFunctions> = OnceLock::new();
+
+    FUNCTIONS.get_or_init(|| unsafe {
+        let user32 = GetModuleHandleA(CString::new("user32.dll").unwrap().as_ptr());
+
+        PenFunctions {
+            get_pointer_type: load(user32, "GetPointerType"),
+            get_pointer_pen_info: load(user32, "GetPointerPenInfo"),
+        }
+    })
+}
+
+/// The state of a pen, as reported by a pointer message.
+pub(super) struct PenState {
+    /// The position in the window's client area.
+    pub position: PhyPoint,
+    pub pressure: f32,
+    pub tilt: (f32, f32),
+    /// The `POINTER_MOD_*` flags, which have the same values as the `MK_*` flags in the `WPARAM` of
+    /// mouse messages.
+    pub key_states: WPARAM,
+}
+
+/// Read the pen's state for a `WM_POINTERDOWN` or `WM_POINTERUPDATE` message. Returns `None` if the pointer isn't a
+/// pen, or if the pen doesn't report its pressure.
+pub(super) unsafe fn pen_state(hwnd: HWND, wparam: WPARAM) -> Option<PenState> {
+    let functions = functions();
+    let get_pointer_type = functions.get_pointer_type?;
+    let get_pointer_pen_info = functions.get_pointer_pen_info?;
+
+    // `GET_POINTERID_WPARAM()`
+    let pointer_id = (wparam & 0xFFFF) as UINT32;
+
+    let mut pointer_type = 0;
+    if get_pointer_type(pointer_id, &mut pointer_type) == 0 || pointer_type != PT_PEN {
+        return None;
+    }
+
+    let mut info: POINTER_PEN_INFO = std::mem::zeroed();
+    if get_pointer_pen_info(pointer_id, &mut info) == 0 || info.penMask & PEN_MASK_PRESSURE == 0 {
+        return None;
+    }
+
+    let mut position = info.pointerInfo.ptPixelLocation;
+    ScreenToClient(hwnd, &mut position);
+
+    let tilt = |mask: DWORD, value: i32| {
+        if info.penMask & mask != 0 {
+            (value as f32 / MAX_TILT).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+
+    Some(PenState {
+        position: PhyPoint::new(position.x, position.y),
+        pressure: (info.pressure as f32 / MAX_PRESSURE).clamp(0.0, 1.0),
+        tilt: (tilt(PEN_MASK_TILT_X, info.tiltX), tilt(PEN_MASK_TILT_Y, info.tiltY)),
+        key_states: info.pointerInfo.dwKeyStates as WPARAM,
+    })
+}
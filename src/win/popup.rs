@@ -0,0 +1,56 @@
+//! Window styles for [`WindowKind::Popup`](crate::WindowKind::Popup) windows. The rounded corners
+//! need DWM's corner preference, which only exists on Windows 11, so `DwmSetWindowAttribute()` is
+//! looked up at runtime just like the per-monitor DPI functions.
+
+use std::ffi::{c_void, CString};
+use std::sync::OnceLock;
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::HRESULT;
+use winapi::shared::windef::HWND;
+use winapi::um::libloaderapi::LoadLibraryA;
+use winapi::um::winuser::{
+    WS_CLIPSIBLINGS, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_POPUP, WS_VISIBLE,
+};
+
+use super::dpi::load;
+
+/// `DWMWA_WINDOW_CORNER_PREFERENCE` from `dwmapi.h`.
+const DWMWA_WINDOW_CORNER_PREFERENCE: DWORD = 33;
+/// `DWMWCP_ROUND`, the same corners Windows 11 uses for its own top-level windows.
+const DWMWCP_ROUND: u32 = 2;
+
+type DwmSetWindowAttribute =
+    unsafe extern "system" fn(HWND, DWORD, *const c_void, DWORD) -> HRESULT;
+
+/// The window style and extended window style for a popup. Tool windows don't show up in the
+/// taskbar. Popups that aren't focusable are never activated, not even when they're clicked, and
+/// focusable popups are shown without being activated in [`super::Window::open()`] instead.
+pub(super) fn styles(focusable: bool) -> (DWORD, DWORD) {
+    if focusable {
+        (WS_POPUP | WS_CLIPSIBLINGS, WS_EX_TOOLWINDOW)
+    } else {
+        (WS_POPUP | WS_VISIBLE | WS_CLIPSIBLINGS, WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE)
+    }
+}
+
+/// Ask DWM to round the window's corners. This does nothing before Windows 11.
+pub(super) unsafe fn round_corners(hwnd: HWND) {
+    static FUNCTION: OnceLock<Option<DwmSetWindowAttribute>> = OnceLock::new();
+
+    // `dwmapi.dll` is never unloaded, so the function pointer stays valid
+    let function = *FUNCTION.get_or_init(|| {
+        let dwmapi = LoadLibraryA(CString::new("dwmapi.dll").unwrap().as_ptr());
+        load(dwmapi, "DwmSetWindowAttribute")
+    });
+
+    if let Some(dwm_set_window_attribute) = function {
+        let preference = DWMWCP_ROUND;
+        dwm_set_window_attribute(
+            hwnd,
+            DWMWA_WINDOW_CORNER_PREFERENCE,
+            &preference as *const u32 as *const c_void,
+            std::mem::size_of::<u32>() as DWORD,
+        );
+    }
+}
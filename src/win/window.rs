@@ -1,52 +1,94 @@
 use winapi::shared::guiddef::GUID;
-use winapi::shared::minwindef::{ATOM, FALSE, LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::minwindef::{ATOM, DWORD, FALSE, LOWORD, LPARAM, LRESULT, TRUE, UINT, WPARAM};
 use winapi::shared::ntdef::PCWSTR;
 use winapi::shared::windef::{HCURSOR, HWND, POINT, RECT};
 use winapi::shared::winerror::{OLE_E_WRONGCOMPOBJ, RPC_E_CHANGED_MODE, S_OK};
 use winapi::um::combaseapi::CoCreateGuid;
 use winapi::um::libloaderapi::GetModuleHandleA;
+use winapi::um::wingdi::{GetDeviceCaps, BITSPIXEL};
 use winapi::um::winuser::{
-    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
-    GetDpiForWindow, GetMessageW, GetSystemMetrics, GetWindowLongPtrW, LoadCursorW, LoadIconA,
-    PostMessageW, RegisterClassW, ReleaseCapture, ScreenToClient, SetCapture, SetCursor,
-    SetProcessDpiAwarenessContext, SetTimer, SetWindowLongPtrW, SetWindowPos, TranslateMessage,
-    UnregisterClassW, CS_OWNDC, GET_XBUTTON_WPARAM, GWLP_USERDATA, IDC_ARROW, IDC_CROSS, IDC_HAND,
-    IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE,
-    IDC_WAIT, MAKEINTRESOURCEA, MSG, SM_CXSCREEN, SM_CYSCREEN, SWP_NOMOVE, SWP_NOSIZE,
-    SWP_NOZORDER, WHEEL_DELTA, WM_CHAR, WM_CLOSE, WM_CREATE, WM_DPICHANGED, WM_INPUTLANGCHANGE,
-    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
-    WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_NCDESTROY, WM_RBUTTONDOWN, WM_RBUTTONUP,
-    WM_SETCURSOR, WM_SHOWWINDOW, WM_SIZE, WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_TIMER,
-    WM_USER, WM_XBUTTONDOWN, WM_XBUTTONUP, WNDCLASSW, WS_CAPTION, WS_CHILD, WS_CLIPSIBLINGS,
-    WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_POPUPWINDOW, WS_SIZEBOX, WS_VISIBLE, XBUTTON1, XBUTTON2,
+    AdjustWindowRectEx, ClipCursor, CreateWindowExW, DefWindowProcW, DestroyCursor, DestroyWindow,
+    DispatchMessageW, FlashWindowEx, GetAncestor, GetCapture, GetClientRect, GetCursorPos, GetDC,
+    GetFocus, GetKeyState, GetMessageTime, GetMessageW, GetSystemMetrics, GetWindowLongPtrW,
+    IsIconic, IsWindowVisible, KillTimer, LoadCursorW, LoadIconA, MapWindowPoints, PostMessageW,
+    RegisterClassW, ReleaseCapture, ReleaseDC, ScreenToClient, SendMessageW, SetCapture, SetCursor,
+    SetLayeredWindowAttributes, SetWindowLongPtrW, SetWindowPos, ShowWindow, TranslateMessage,
+    UnregisterClassW, WindowFromPoint, CS_DROPSHADOW, CS_OWNDC, FLASHWINFO, FLASHW_ALL,
+    FLASHW_STOP, FLASHW_TIMERNOFG, FLASHW_TRAY, GA_ROOT, GET_XBUTTON_WPARAM, GWLP_USERDATA,
+    GWLP_WNDPROC, GWL_EXSTYLE, HTCLIENT, IDC_ARROW, IDC_CROSS, IDC_HAND, IDC_HELP, IDC_IBEAM,
+    IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, IDC_WAIT, LWA_ALPHA,
+    MAKEINTRESOURCEA, MK_LBUTTON, MK_MBUTTON, MK_RBUTTON, MK_XBUTTON1, MK_XBUTTON2, MSG,
+    SM_CXSCREEN, SM_CYSCREEN, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER,
+    SW_SHOWNOACTIVATE, UNICODE_NOCHAR, USER_TIMER_MAXIMUM, USER_TIMER_MINIMUM, VK_LBUTTON,
+    VK_MBUTTON, VK_RBUTTON, VK_XBUTTON1, VK_XBUTTON2, WHEEL_DELTA, WM_CAPTURECHANGED, WM_CHAR,
+    WM_CLOSE, WM_DESTROY, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_ENTERSIZEMOVE, WM_EXITSIZEMOVE,
+    WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOVE,
+    WM_NCDESTROY, WM_POINTERDOWN, WM_POINTERUPDATE, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR,
+    WM_SETFOCUS, WM_SHOWWINDOW, WM_SIZE, WM_SIZING, WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP,
+    WM_TIMER, WM_UNICHAR, WM_USER, WM_XBUTTONDOWN, WM_XBUTTONUP, WNDCLASSW, WS_CAPTION, WS_CHILD,
+    WS_CLIPSIBLINGS, WS_EX_LAYERED, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_POPUPWINDOW, WS_SIZEBOX,
+    WS_VISIBLE, XBUTTON1, XBUTTON2,
 };
 use winapi::um::{ole2, oleidl::LPDROPTARGET};
 
 use std::cell::{Cell, RefCell};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::{c_void, OsStr};
 use std::marker::PhantomData;
 use std::os::windows::ffi::OsStrExt;
-use std::ptr::null_mut;
+use std::ptr::{null, null_mut};
 use std::rc::Rc;
-
-use raw_window_handle::{
-    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, Win32WindowHandle,
-    WindowsDisplayHandle,
-};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const BV_WINDOW_MUST_CLOSE: UINT = WM_USER + 1;
+/// Passed as the `WPARAM` for [`BV_WINDOW_MUST_CLOSE`] messages posted by
+/// [`crate::EventContext::close()`]. These only close the window if the request hasn't been
+/// canceled in the meantime.
+const CLOSE_IF_STILL_REQUESTED: WPARAM = 1;
+/// Posted when AccessKit queued requests for the handler in [`WindowState::accesskit_events`].
+#[cfg(feature = "accesskit")]
+const BV_ACCESSKIT_EVENT: UINT = WM_USER + 2;
+/// Sent by [`crate::WindowHandle::trigger_frame()`]. This replaces the frame timer's `WM_TIMER`
+/// messages for windows using [`FrameSource::External`].
+const BV_TRIGGER_FRAME: UINT = WM_USER + 3;
 
 use crate::{
-    Data, Event, MouseButton, MouseCursor, MouseEvent, PhyPoint, PhySize, Point, ScrollDelta, Size,
-    WindowEvent, WindowHandler, WindowInfo, WindowOpenOptions, WindowScalePolicy,
+    AttentionType, CursorIcon, CursorId, Data, DialogId, DialogResult, DragImage, DropEffect,
+    Error, Event, FileDialogOptions, FrameSource, MouseButton, MouseButtonSet, MouseCursor,
+    MouseEvent, PanicPolicy, PhyPoint, PhySize, Point, Rect, Region, ScrollDelta, Size,
+    SurfaceInfo, WindowEvent, WindowHandler, WindowId, WindowInfo, WindowKind, WindowOpenOptions,
+    WindowScalePolicy,
 };
 
-use super::drop_handler::DropHandler;
+use super::cursor;
+use super::dpi;
+use super::drop_handler::{DropHandler, DropTarget};
+use super::frame_timer::{self, FrameThread};
 use super::keyboard::KeyboardState;
+use super::pen;
+use super::popup;
+use crate::event_clock::EventClock;
+use crate::held_inputs::HeldInputs;
+use crate::lifecycle::Lifecycle;
+use crate::mouse_cursor::CursorRegions;
+use crate::platform_handle::{PlatformDisplayHandle, PlatformWindowHandle};
+use crate::region::Damage;
+use crate::user_data::UserData;
 
 #[cfg(feature = "opengl")]
-use crate::{gl::GlContext, window::RawWindowHandleWrapper};
+use crate::gl::{GlContext, GlError};
+
+#[cfg(feature = "accesskit")]
+use crate::accessibility::AccessKitHandler;
+#[cfg(feature = "accesskit")]
+use crate::AccessKitEvent;
+#[cfg(feature = "accesskit")]
+use std::sync::Mutex;
+#[cfg(feature = "accesskit")]
+use winapi::um::winuser::WM_GETOBJECT;
 
 unsafe fn generate_guid() -> String {
     let mut guid: GUID = std::mem::zeroed();
@@ -67,11 +109,53 @@ unsafe fn generate_guid() -> String {
     )
 }
 
+/// The name of the messages that are logged to the `baseview::platform` tracing target. Mouse
+/// movement, painting, and hit testing messages are left out to keep the output readable.
+#[cfg(feature = "tracing")]
+fn message_name(msg: UINT) -> Option<&'static str> {
+    Some(match msg {
+        WM_SHOWWINDOW => "WM_SHOWWINDOW",
+        WM_SIZE => "WM_SIZE",
+        WM_ENTERSIZEMOVE => "WM_ENTERSIZEMOVE",
+        WM_EXITSIZEMOVE => "WM_EXITSIZEMOVE",
+        WM_DPICHANGED => "WM_DPICHANGED",
+        WM_DISPLAYCHANGE => "WM_DISPLAYCHANGE",
+        WM_SETFOCUS => "WM_SETFOCUS",
+        WM_KILLFOCUS => "WM_KILLFOCUS",
+        WM_CAPTURECHANGED => "WM_CAPTURECHANGED",
+        WM_LBUTTONDOWN => "WM_LBUTTONDOWN",
+        WM_LBUTTONUP => "WM_LBUTTONUP",
+        WM_MBUTTONDOWN => "WM_MBUTTONDOWN",
+        WM_MBUTTONUP => "WM_MBUTTONUP",
+        WM_RBUTTONDOWN => "WM_RBUTTONDOWN",
+        WM_RBUTTONUP => "WM_RBUTTONUP",
+        WM_XBUTTONDOWN => "WM_XBUTTONDOWN",
+        WM_XBUTTONUP => "WM_XBUTTONUP",
+        WM_KEYDOWN => "WM_KEYDOWN",
+        WM_KEYUP => "WM_KEYUP",
+        WM_SYSKEYDOWN => "WM_SYSKEYDOWN",
+        WM_SYSKEYUP => "WM_SYSKEYUP",
+        WM_CHAR => "WM_CHAR",
+        WM_SYSCHAR => "WM_SYSCHAR",
+        WM_UNICHAR => "WM_UNICHAR",
+        WM_INPUTLANGCHANGE => "WM_INPUTLANGCHANGE",
+        WM_CLOSE => "WM_CLOSE",
+        WM_DESTROY => "WM_DESTROY",
+        WM_NCDESTROY => "WM_NCDESTROY",
+        BV_WINDOW_MUST_CLOSE => "BV_WINDOW_MUST_CLOSE",
+        BV_TRIGGER_FRAME => "BV_TRIGGER_FRAME",
+        _ => return None,
+    })
+}
+
 const WIN_FRAME_TIMER: usize = 4242;
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(15);
 
 pub struct WindowHandle {
+    id: WindowId,
     hwnd: Option<HWND>,
-    is_open: Rc<Cell<bool>>,
+    /// Shared with [`RemoteWindowHandle`], which may be used from other threads.
+    is_open: Arc<AtomicBool>,
 
     // Ensure handle is !Send
     _phantom: PhantomData<*mut ()>,
@@ -87,35 +171,75 @@ impl WindowHandle {
     }
 
     pub fn is_open(&self) -> bool {
-        self.is_open.get()
+        self.is_open.load(Ordering::Relaxed)
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> Option<PlatformWindowHandle> {
+        self.hwnd
+            .filter(|_| self.is_open())
+            .map(|hwnd| PlatformWindowHandle::Win32 { hwnd: hwnd as *mut c_void })
+    }
+
+    pub fn remote(&self) -> RemoteWindowHandle {
+        RemoteWindowHandle {
+            id: self.id,
+            hwnd: self.hwnd.map_or(0, |hwnd| hwnd as usize),
+            is_open: Arc::clone(&self.is_open),
+        }
+    }
+
+    pub fn trigger_frame(&self) {
+        if let Some(hwnd) = self.hwnd.filter(|_| self.is_open()) {
+            unsafe { SendMessageW(hwnd, BV_TRIGGER_FRAME, 0, 0) };
+        }
     }
 }
 
-unsafe impl HasRawWindowHandle for WindowHandle {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        if let Some(hwnd) = self.hwnd {
-            let mut handle = Win32WindowHandle::empty();
-            handle.hwnd = hwnd as *mut c_void;
+/// Closes the window by posting the same message as [`WindowHandle::close()`]. Unlike most of the
+/// window API, `PostMessageW()` can be called from any thread.
+#[derive(Clone)]
+pub struct RemoteWindowHandle {
+    id: WindowId,
+    /// The window's `HWND` as an integer, since pointers aren't `Send`. This is `0` if the
+    /// [`WindowHandle`] was already closed when this handle was created.
+    hwnd: usize,
+    is_open: Arc<AtomicBool>,
+}
 
-            RawWindowHandle::Win32(handle)
-        } else {
-            RawWindowHandle::Win32(Win32WindowHandle::empty())
+impl RemoteWindowHandle {
+    pub fn close(&self) {
+        // Once the window is closed its `HWND` may be reused for another window
+        if self.hwnd != 0 && self.is_open() {
+            unsafe { PostMessageW(self.hwnd as HWND, BV_WINDOW_MUST_CLOSE, 0, 0) };
         }
     }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open.load(Ordering::Relaxed)
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
 }
 
 struct ParentHandle {
     _hwnd: HWND,
-    is_open: Rc<Cell<bool>>,
+    is_open: Arc<AtomicBool>,
 }
 
 impl ParentHandle {
-    pub fn new(hwnd: HWND) -> (Self, WindowHandle) {
-        let is_open = Rc::new(Cell::new(true));
+    pub fn new(hwnd: HWND, id: WindowId) -> (Self, WindowHandle) {
+        let is_open = Arc::new(AtomicBool::new(true));
 
         let handle = WindowHandle {
+            id,
             hwnd: Some(hwnd),
-            is_open: Rc::clone(&is_open),
+            is_open: Arc::clone(&is_open),
             _phantom: PhantomData::default(),
         };
 
@@ -125,41 +249,72 @@ impl ParentHandle {
 
 impl Drop for ParentHandle {
     fn drop(&mut self) {
-        self.is_open.set(false);
+        self.is_open.store(false, Ordering::Relaxed);
     }
 }
 
 unsafe extern "system" fn wnd_proc(
     hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM,
 ) -> LRESULT {
-    if msg == WM_CREATE {
-        PostMessageW(hwnd, WM_SHOWWINDOW, 0, 0);
-        return 0;
+    let _span = window_span!(hwnd = ?hwnd);
+    #[cfg(feature = "tracing")]
+    if let Some(message) = message_name(msg) {
+        trace!(target: "baseview::platform", message);
     }
 
     let window_state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
     if !window_state_ptr.is_null() {
-        let result = wnd_proc_inner(hwnd, msg, wparam, lparam, &*window_state_ptr);
-
-        // If any of the above event handlers caused tasks to be pushed to the deferred tasks list,
-        // then we'll try to handle them now
-        loop {
-            // NOTE: This is written like this instead of using a `while let` loop to avoid extending
-            //       the borrow of `window_state.deferred_tasks` into the call of
-            //       `window_state.handle_deferred_task()` since that may also generate additional
-            //       messages.
-            let task = match (*window_state_ptr).deferred_tasks.borrow_mut().pop_front() {
-                Some(task) => task,
-                None => break,
-            };
-
-            (*window_state_ptr).handle_deferred_task(task);
-        }
-
-        // NOTE: This is not handled in `wnd_proc_inner` because of the deferred task loop above
+        let window_state = &*window_state_ptr;
+        window_state.message_depth.set(window_state.message_depth.get() + 1);
+
+        // Unwinding into the system's message dispatching code is undefined behavior, so any
+        // panics from the window handler need to be caught here. Once the handler has panicked it
+        // won't receive any more messages, and the window will be destroyed.
+        let result = if window_state.handler_panicked.get() {
+            // Only the message that tears down the window is still handled
+            if msg == BV_WINDOW_MUST_CLOSE {
+                DestroyWindow(hwnd);
+                Some(0)
+            } else {
+                None
+            }
+        } else if !window_state.lifecycle.get().can_send_events() {
+            // The handler has received `WillClose`, so the remaining messages only tear down the
+            // window
+            None
+        } else {
+            window_state
+                .panic_policy
+                .catch(|| handle_message(hwnd, msg, wparam, lparam, window_state_ptr))
+                .unwrap_or_else(|| {
+                    window_state.handle_handler_panic();
+                    Some(0)
+                })
+        };
+        window_state.message_depth.set(window_state.message_depth.get() - 1);
+
+        // NOTE: This is not handled in `wnd_proc_inner` because of the deferred task loop in
+        //       `handle_message()`
+        //
+        // This is also the last message a child window receives when the host destroys the parent
+        // window directly. Dropping the state drops the `ParentHandle`, which marks the window as
+        // closed for `WindowHandle::is_open()`.
         if msg == WM_NCDESTROY {
-            unregister_wnd_class((*window_state_ptr).window_class);
+            debug!(target: "baseview::lifecycle", "destroyed window");
+            window_state.revoke_drop_target();
+            unregister_wnd_class(window_state.window_class);
+            for (_, cursor) in window_state.custom_cursors.borrow_mut().drain() {
+                DestroyCursor(cursor);
+            }
             SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            window_state.destroyed.set(true);
+        }
+
+        // The window can also be destroyed while one of its messages is being handled, for
+        // instance when the handler makes the host destroy the parent window. The state is still
+        // in use by that message then, so it's only freed once the outermost message returns.
+        if window_state.destroyed.get() && window_state.message_depth.get() == 0 {
+            window_state.send_pending_will_close();
             drop(Box::from_raw(window_state_ptr));
         }
 
@@ -173,6 +328,224 @@ unsafe extern "system" fn wnd_proc(
     DefWindowProcW(hwnd, msg, wparam, lparam)
 }
 
+/// Run our custom `wnd_proc_inner` message handler, followed by any tasks it deferred.
+unsafe fn handle_message(
+    hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM, window_state_ptr: *mut WindowState,
+) -> Option<LRESULT> {
+    // Only posted input messages have a meaningful `GetMessageTime()`, everything else that's
+    // sent to the handler gets the current time
+    let event_clock = &(*window_state_ptr).event_clock;
+    let native_time = if is_input_message(msg) {
+        Some(event_clock.extend_millis(GetMessageTime() as u32))
+    } else {
+        None
+    };
+    let outer_event_time = event_clock.begin_event(native_time);
+    let result = wnd_proc_inner(hwnd, msg, wparam, lparam, &*window_state_ptr);
+    event_clock.end_event(outer_event_time);
+
+    // If any of the above event handlers caused tasks to be pushed to the deferred tasks list,
+    // then we'll try to handle them now
+    loop {
+        // NOTE: This is written like this instead of using a `while let` loop to avoid extending
+        //       the borrow of `window_state.deferred_tasks` into the call of
+        //       `window_state.handle_deferred_task()` since that may also generate additional
+        //       messages.
+        let task = match (*window_state_ptr).deferred_tasks.borrow_mut().pop_front() {
+            Some(task) => task,
+            None => break,
+        };
+
+        (*window_state_ptr).handle_deferred_task(task);
+    }
+
+    // Events that arrived while the handler was busy are sent once it's done
+    (*window_state_ptr).send_pending_events();
+    (*window_state_ptr).send_drag_events();
+
+    // Input always gets to draw at least one frame, even if the window isn't animating
+    if matches!(
+        msg,
+        WM_MOUSEMOVE
+            | WM_POINTERDOWN
+            | WM_POINTERUPDATE
+            | WM_MOUSEWHEEL
+            | WM_MOUSEHWHEEL
+            | WM_LBUTTONDOWN
+            | WM_LBUTTONUP
+            | WM_MBUTTONDOWN
+            | WM_MBUTTONUP
+            | WM_RBUTTONDOWN
+            | WM_RBUTTONUP
+            | WM_XBUTTONDOWN
+            | WM_XBUTTONUP
+            | WM_CHAR
+            | WM_SYSCHAR
+            | WM_UNICHAR
+            | WM_KEYDOWN
+            | WM_SYSKEYDOWN
+            | WM_KEYUP
+            | WM_SYSKEYUP
+            | WM_SIZE
+            | WM_SETFOCUS
+            | WM_KILLFOCUS
+            | WM_CAPTURECHANGED
+    ) {
+        (*window_state_ptr).start_frame_timer();
+    }
+
+    result
+}
+
+/// Whether the message is a mouse or keyboard message from the input queue.
+fn is_input_message(msg: UINT) -> bool {
+    matches!(
+        msg,
+        WM_MOUSEMOVE
+            | WM_POINTERDOWN
+            | WM_POINTERUPDATE
+            | WM_MOUSEWHEEL
+            | WM_MOUSEHWHEEL
+            | WM_LBUTTONDOWN
+            | WM_LBUTTONUP
+            | WM_MBUTTONDOWN
+            | WM_MBUTTONUP
+            | WM_RBUTTONDOWN
+            | WM_RBUTTONUP
+            | WM_XBUTTONDOWN
+            | WM_XBUTTONUP
+            | WM_CHAR
+            | WM_SYSCHAR
+            | WM_UNICHAR
+            | WM_KEYDOWN
+            | WM_SYSKEYDOWN
+            | WM_KEYUP
+            | WM_SYSKEYUP
+    )
+}
+
+/// Apply the window's new DPI when using [`WindowScalePolicy::SystemScaleFactor`]. This resizes the
+/// window so it keeps the same logical size, and the handler receives a `Resized` event with the new
+/// scale factor.
+unsafe fn handle_dpi_change(hwnd: HWND, window_state: &WindowState, dpi: u32) {
+    // To avoid weirdness with the realtime borrow checker.
+    let new_rect = {
+        if window_state.scale_policy().follows_system() {
+            let scale_factor = dpi as f64 / dpi::DEFAULT_DPI as f64;
+
+            let mut window_info = window_state.window_info.borrow_mut();
+            window_state.pre_dpi_change_window_info.set(Some(*window_info));
+            *window_info = WindowInfo::from_logical_size(window_info.logical_size(), scale_factor);
+
+            Some((
+                RECT {
+                    left: 0,
+                    top: 0,
+                    right: window_info.physical_size().to_point().x,
+                    bottom: window_info.physical_size().to_point().y,
+                },
+                window_state.dw_style,
+            ))
+        } else {
+            None
+        }
+    };
+    if let Some((mut new_rect, dw_style)) = new_rect {
+        // Convert this desired "client rectangle" size to the actual "window rectangle"
+        // size (Because of course you have to do that).
+        AdjustWindowRectEx(&mut new_rect, dw_style, 0, 0);
+
+        // Windows makes us resize the window manually. This will trigger another `WM_SIZE` event,
+        // which we can then send the user the new scale factor.
+        SetWindowPos(
+            hwnd,
+            hwnd,
+            new_rect.left,
+            new_rect.top,
+            new_rect.right - new_rect.left,
+            new_rect.bottom - new_rect.top,
+            SWP_NOZORDER | SWP_NOMOVE,
+        );
+
+        // If the window's size didn't change then there won't be a `WM_SIZE` message, but
+        // the handler still needs to be told about the new scale factor
+        if let Some(old_window_info) = window_state.pre_dpi_change_window_info.take() {
+            let new_window_info = *window_state.window_info.borrow();
+
+            let mut window = window_state.create_window();
+            let mut window = crate::EventContext::new(&mut window);
+            window_state.handler.borrow_mut().as_mut().unwrap().on_event(
+                &mut window,
+                Event::Window(WindowEvent::Resized { new: new_window_info, old: old_window_info }),
+            );
+        }
+    }
+}
+
+/// The new and old window info for a `WM_SIZE` message, or `None` if nothing changed. If the resize
+/// was caused by a DPI change, then `pre_dpi_change` is the window info from before that change, so
+/// the size and the scale factor changes are reported together in a single `Resized` event.
+fn resized_window_info(
+    window_info: WindowInfo, pre_dpi_change: Option<WindowInfo>, size: PhySize,
+) -> Option<(WindowInfo, WindowInfo)> {
+    let old_window_info = pre_dpi_change.unwrap_or(window_info);
+    let new_window_info = WindowInfo::from_physical_size(size, window_info.scale());
+
+    // Only send the event if anything changed
+    if old_window_info.physical_size() == new_window_info.physical_size()
+        && old_window_info.scale() == new_window_info.scale()
+    {
+        return None;
+    }
+
+    Some((new_window_info, old_window_info))
+}
+
+/// The mouse buttons in the `MK_*` flags of a mouse message's `wParam`. For button messages this
+/// already includes the button that was pressed, and no longer includes the one that was released.
+const fn mouse_buttons(wparam: WPARAM) -> MouseButtonSet {
+    let masks = [
+        (MK_LBUTTON, MouseButtonSet::LEFT),
+        (MK_MBUTTON, MouseButtonSet::MIDDLE),
+        (MK_RBUTTON, MouseButtonSet::RIGHT),
+        (MK_XBUTTON1, MouseButtonSet::BACK),
+        (MK_XBUTTON2, MouseButtonSet::FORWARD),
+    ];
+
+    let mut buttons = MouseButtonSet::empty();
+    let mut i = 0;
+    while i < masks.len() {
+        if wparam & masks[i].0 != 0 {
+            buttons = buttons.union(masks[i].1);
+        }
+        i += 1;
+    }
+
+    buttons
+}
+
+/// The mouse buttons that are held down according to the thread's input state, for when there's no
+/// mouse message to take them from.
+unsafe fn current_mouse_buttons() -> MouseButtonSet {
+    let keys = [
+        (VK_LBUTTON, MK_LBUTTON),
+        (VK_MBUTTON, MK_MBUTTON),
+        (VK_RBUTTON, MK_RBUTTON),
+        (VK_XBUTTON1, MK_XBUTTON1),
+        (VK_XBUTTON2, MK_XBUTTON2),
+    ];
+
+    let mut wparam = 0;
+    for (key, mask) in keys {
+        // The high bit is set while the button is held down
+        if GetKeyState(key) < 0 {
+            wparam |= mask;
+        }
+    }
+
+    mouse_buttons(wparam)
+}
+
 /// Our custom `wnd_proc` handler. If the result contains a value, then this is returned after
 /// handling any deferred tasks. otherwise the default window procedure is invoked.
 unsafe fn wnd_proc_inner(
@@ -181,30 +554,59 @@ unsafe fn wnd_proc_inner(
     match msg {
         WM_MOUSEMOVE => {
             let mut window = window_state.create_window();
-            let mut window = crate::Window::new(&mut window);
-            winapi::um::winuser::SetFocus(hwnd);
+            let mut window = crate::EventContext::new(&mut window);
+            if window_state.takes_focus && !focus_in_nested_window(hwnd) {
+                winapi::um::winuser::SetFocus(hwnd);
+            }
 
             let x = (lparam & 0xFFFF) as i16 as i32;
             let y = ((lparam >> 16) & 0xFFFF) as i16 as i32;
 
             let physical_pos = PhyPoint { x, y };
             let logical_pos = physical_pos.to_logical(&window_state.window_info.borrow());
+            let delta = physical_pos
+                .delta_from(window_state.last_cursor_position.replace(Some(physical_pos)));
             let event = Event::Mouse(MouseEvent::CursorMoved {
                 position: logical_pos,
+                delta,
                 modifiers: window_state
                     .keyboard_state
                     .borrow()
                     .get_modifiers_from_mouse_wparam(wparam),
+                buttons: mouse_buttons(wparam),
             });
 
             window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, event);
 
             Some(0)
         }
+        WM_POINTERDOWN | WM_POINTERUPDATE => {
+            if let Some(pen) = pen::pen_state(hwnd, wparam) {
+                let mut window = window_state.create_window();
+                let mut window = crate::EventContext::new(&mut window);
+
+                let event = Event::Mouse(MouseEvent::PenMoved {
+                    position: pen.position.to_logical(&window_state.window_info.borrow()),
+                    pressure: pen.pressure,
+                    tilt: pen.tilt,
+                    modifiers: window_state
+                        .keyboard_state
+                        .borrow()
+                        .get_modifiers_from_mouse_wparam(pen.key_states),
+                });
+
+                window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, event);
+            }
+
+            // The default window procedure turns this into the mouse messages for the pen
+            None
+        }
         WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
             let mut window = window_state.create_window();
-            let mut window = crate::Window::new(&mut window);
+            let mut window = crate::EventContext::new(&mut window);
 
+            // Rotating the wheel away from the user and tilting it to the right are positive, which
+            // already matches `ScrollDelta`'s signs
             let value = (wparam >> 16) as i16;
             let value = value as i32;
             let value = value as f32 / WHEEL_DELTA as f32;
@@ -228,9 +630,7 @@ unsafe fn wnd_proc_inner(
         WM_LBUTTONDOWN | WM_LBUTTONUP | WM_MBUTTONDOWN | WM_MBUTTONUP | WM_RBUTTONDOWN
         | WM_RBUTTONUP | WM_XBUTTONDOWN | WM_XBUTTONUP => {
             let mut window = window_state.create_window();
-            let mut window = crate::Window::new(&mut window);
-
-            let mut mouse_button_counter = window_state.mouse_button_counter.get();
+            let mut window = crate::EventContext::new(&mut window);
 
             let button = match msg {
                 WM_LBUTTONDOWN | WM_LBUTTONUP => Some(MouseButton::Left),
@@ -239,6 +639,7 @@ unsafe fn wnd_proc_inner(
                 WM_XBUTTONDOWN | WM_XBUTTONUP => match GET_XBUTTON_WPARAM(wparam) {
                     XBUTTON1 => Some(MouseButton::Back),
                     XBUTTON2 => Some(MouseButton::Forward),
+                    // Neither the mouse messages nor Raw Input report more than five buttons
                     _ => None,
                 },
                 _ => None,
@@ -247,30 +648,33 @@ unsafe fn wnd_proc_inner(
             if let Some(button) = button {
                 let event = match msg {
                     WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN | WM_XBUTTONDOWN => {
+                        // Hovering doesn't move the focus between nested windows, clicking does
+                        if window_state.takes_focus && GetFocus() != hwnd {
+                            winapi::um::winuser::SetFocus(hwnd);
+                        }
+
                         // Capture the mouse cursor on button down
-                        mouse_button_counter = mouse_button_counter.saturating_add(1);
-                        SetCapture(hwnd);
+                        if !window_state.held_inputs.borrow().any_mouse_button_held() {
+                            SetCapture(hwnd);
+                        }
+
                         MouseEvent::ButtonPressed {
                             button,
                             modifiers: window_state
                                 .keyboard_state
                                 .borrow()
                                 .get_modifiers_from_mouse_wparam(wparam),
+                            buttons: mouse_buttons(wparam),
                         }
                     }
                     WM_LBUTTONUP | WM_MBUTTONUP | WM_RBUTTONUP | WM_XBUTTONUP => {
-                        // Release the mouse cursor capture when all buttons are released
-                        mouse_button_counter = mouse_button_counter.saturating_sub(1);
-                        if mouse_button_counter == 0 {
-                            ReleaseCapture();
-                        }
-
                         MouseEvent::ButtonReleased {
                             button,
                             modifiers: window_state
                                 .keyboard_state
                                 .borrow()
                                 .get_modifiers_from_mouse_wparam(wparam),
+                            buttons: mouse_buttons(wparam),
                         }
                     }
                     _ => {
@@ -278,65 +682,124 @@ unsafe fn wnd_proc_inner(
                     }
                 };
 
-                window_state.mouse_button_counter.set(mouse_button_counter);
+                let event = Event::Mouse(event);
+                window_state.held_inputs.borrow_mut().track(&event);
+                window_state.event_clock.track(&event);
+
+                // Release the mouse cursor capture when all buttons are released. This sends a
+                // `WM_CAPTURECHANGED` message, but there are no held buttons left to release then.
+                if !window_state.held_inputs.borrow().any_mouse_button_held() {
+                    ReleaseCapture();
+                }
+
+                window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, event);
+            }
+
+            None
+        }
+        WM_CAPTURECHANGED => {
+            // Another window took the mouse capture while buttons were held, for instance because
+            // the host opened a modal dialog. We won't receive the button up messages anymore.
+            let events = window_state.held_inputs.borrow_mut().release_mouse_buttons();
+            if !events.is_empty() {
+                window_state.release_cursor();
+            }
+            window_state.send_events(events);
+
+            Some(0)
+        }
+        WM_SETFOCUS => {
+            #[cfg(feature = "accesskit")]
+            window_state.update_accesskit_focus(true);
+
+            window_state.send_events(vec![Event::Window(WindowEvent::Focused)]);
 
-                window_state
-                    .handler
-                    .borrow_mut()
-                    .as_mut()
-                    .unwrap()
-                    .on_event(&mut window, Event::Mouse(event));
+            Some(0)
+        }
+        WM_KILLFOCUS => {
+            // Buttons and keys that are still held down won't be released while the window
+            // doesn't have focus, so the handler needs to be told about this now to prevent
+            // gestures from getting stuck
+            let mut events = window_state.held_inputs.borrow_mut().release_all();
+            window_state.keyboard_state.borrow_mut().reset();
+            if GetCapture() == hwnd {
+                ReleaseCapture();
             }
+            window_state.release_cursor();
+
+            #[cfg(feature = "accesskit")]
+            window_state.update_accesskit_focus(false);
+
+            events.push(Event::Window(WindowEvent::Unfocused));
+            window_state.send_events(events);
+
+            Some(0)
+        }
+        // This is also sent when the host shows or hides an embedded window. The message sent
+        // while the window is being created arrives before the state exists, so
+        // `send_initial_visibility()` covers that one.
+        WM_SHOWWINDOW => {
+            window_state.set_visible(wparam != 0);
 
             None
         }
         WM_TIMER => {
-            let mut window = window_state.create_window();
-            let mut window = crate::Window::new(&mut window);
-
             if wparam == WIN_FRAME_TIMER {
-                if let Ok(mut h) = window_state.handler.try_borrow_mut() {
-                    h.as_mut().unwrap().on_frame(&mut window);
-                } else {
-                    //println!("Warning: baseview: Can't process frame");
-                }
+                #[cfg(feature = "tracing")]
+                window_state.trace_frame_timer_jitter();
+
+                window_state.handle_frame_timer_tick();
             }
 
             Some(0)
         }
-        WM_CLOSE => {
-            // Make sure to release the borrow before the DefWindowProc call
-            {
-                let mut window = window_state.create_window();
-                let mut window = crate::Window::new(&mut window);
+        // Modal loops dispatch these more reliably than `WM_TIMER` messages. See the `frame_timer`
+        // module.
+        msg if msg == frame_timer::frame_message() => {
+            if let Some(frame_thread) = window_state.frame_thread.borrow().as_ref() {
+                frame_thread.message_received();
+            }
+            if window_state.frame_timer_running.get() {
+                window_state.handle_frame_timer_tick();
+            }
 
-                window_state
-                    .handler
-                    .borrow_mut()
-                    .as_mut()
-                    .unwrap()
-                    .on_event(&mut window, Event::Window(WindowEvent::WillClose));
+            Some(0)
+        }
+        BV_TRIGGER_FRAME => {
+            if window_state.frame_source == FrameSource::External
+                && window_state.frame_timer_running.get()
+            {
+                window_state.handle_frame_tick();
             }
 
-            // DestroyWindow(hwnd);
-            // Some(0)
-            Some(DefWindowProcW(hwnd, msg, wparam, lparam))
+            Some(0)
+        }
+        // `DefWindowProc` destroys the window, and the handler receives `WillClose` from the
+        // `WM_DESTROY` message that follows
+        WM_CLOSE => None,
+        WM_DESTROY => {
+            window_state.release_cursor();
+            window_state.send_will_close();
+
+            None
         }
-        WM_CHAR | WM_SYSCHAR | WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP
+        // Input methods check whether the window understands `WM_UNICHAR` before they send
+        // characters as UTF-32, and otherwise fall back to `WM_CHAR`
+        WM_UNICHAR if wparam == UNICODE_NOCHAR => Some(1),
+        WM_CHAR | WM_SYSCHAR | WM_UNICHAR | WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP
         | WM_INPUTLANGCHANGE => {
             let mut window = window_state.create_window();
-            let mut window = crate::Window::new(&mut window);
+            let mut window = crate::EventContext::new(&mut window);
 
             let opt_event =
                 window_state.keyboard_state.borrow_mut().process_message(hwnd, msg, wparam, lparam);
 
             if let Some(event) = opt_event {
-                window_state
-                    .handler
-                    .borrow_mut()
-                    .as_mut()
-                    .unwrap()
-                    .on_event(&mut window, Event::Keyboard(event));
+                let event = Event::Keyboard(event);
+                window_state.held_inputs.borrow_mut().track(&event);
+                window_state.event_clock.track(&event);
+
+                window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, event);
             }
 
             if msg != WM_SYSKEYDOWN {
@@ -345,87 +808,117 @@ unsafe fn wnd_proc_inner(
                 None
             }
         }
+        WM_MOVE => {
+            window_state.clip_cursor();
+
+            None
+        }
         WM_SIZE => {
+            window_state.clip_cursor();
+
             let mut window = window_state.create_window();
-            let mut window = crate::Window::new(&mut window);
+            let mut window = crate::EventContext::new(&mut window);
 
             let width = (lparam & 0xFFFF) as u16 as u32;
             let height = ((lparam >> 16) & 0xFFFF) as u16 as u32;
 
-            let new_window_info = {
+            let (new_window_info, old_window_info) = {
                 let mut window_info = window_state.window_info.borrow_mut();
-                let new_window_info =
-                    WindowInfo::from_physical_size(PhySize { width, height }, window_info.scale());
-
-                // Only send the event if anything changed
-                if window_info.physical_size() == new_window_info.physical_size() {
-                    return None;
-                }
+                let (new_window_info, old_window_info) = resized_window_info(
+                    *window_info,
+                    window_state.pre_dpi_change_window_info.take(),
+                    PhySize { width, height },
+                )?;
 
                 *window_info = new_window_info;
 
-                new_window_info
+                (new_window_info, old_window_info)
             };
 
-            window_state
-                .handler
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .on_event(&mut window, Event::Window(WindowEvent::Resized(new_window_info)));
+            // While the user is dragging the window's frame these messages arrive much faster than
+            // the handler can draw frames, so only the latest size is sent before the next frame.
+            // `WM_TIMER` messages have the lowest priority and may be starved by the resize, so
+            // the frames are also drawn from here.
+            if window_state.interactive_resize.get() {
+                if window_state.pending_resize.get().is_none() {
+                    window_state.pending_resize.set(Some(old_window_info));
+                }
+
+                let frame_due = window_state.last_frame.get().is_none_or(|last_frame| {
+                    last_frame.elapsed() >= window_state.frame_interval.get()
+                });
+                if frame_due && !window_state.frames_paused.get() {
+                    window_state.draw_frame();
+                }
+
+                return None;
+            }
+
+            window_state.handler.borrow_mut().as_mut().unwrap().on_event(
+                &mut window,
+                Event::Window(WindowEvent::Resized { new: new_window_info, old: old_window_info }),
+            );
 
             None
         }
-        WM_DPICHANGED => {
-            // To avoid weirdness with the realtime borrow checker.
-            let new_rect = {
-                if let WindowScalePolicy::SystemScaleFactor = window_state.scale_policy {
-                    let dpi = (wparam & 0xFFFF) as u16 as u32;
-                    let scale_factor = dpi as f64 / 96.0;
+        WM_ENTERSIZEMOVE => {
+            // This is also sent when the window is only being moved. `WM_SIZING` tells the two
+            // apart.
+            window_state.in_size_move.set(true);
 
-                    let mut window_info = window_state.window_info.borrow_mut();
-                    *window_info =
-                        WindowInfo::from_logical_size(window_info.logical_size(), scale_factor);
+            None
+        }
+        WM_SIZING => {
+            if window_state.in_size_move.get() && !window_state.interactive_resize.replace(true) {
+                window_state.send_events(vec![Event::Window(WindowEvent::ResizeStarted)]);
+            }
 
-                    Some((
-                        RECT {
-                            left: 0,
-                            top: 0,
-                            // todo: check if usize fits into i32
-                            right: window_info.physical_size().width as i32,
-                            bottom: window_info.physical_size().height as i32,
-                        },
-                        window_state.dw_style,
-                    ))
-                } else {
-                    None
+            None
+        }
+        WM_EXITSIZEMOVE => {
+            window_state.in_size_move.set(false);
+            if window_state.interactive_resize.replace(false) {
+                let mut events = Vec::with_capacity(2);
+                if let Some(old_window_info) = window_state.pending_resize.take() {
+                    let new_window_info = *window_state.window_info.borrow();
+                    events.push(Event::Window(WindowEvent::Resized {
+                        new: new_window_info,
+                        old: old_window_info,
+                    }));
                 }
-            };
-            if let Some((mut new_rect, dw_style)) = new_rect {
-                // Convert this desired "client rectangle" size to the actual "window rectangle"
-                // size (Because of course you have to do that).
-                AdjustWindowRectEx(&mut new_rect, dw_style, 0, 0);
+                events.push(Event::Window(WindowEvent::ResizeEnded));
 
-                // Windows makes us resize the window manually. This will trigger another `WM_SIZE` event,
-                // which we can then send the user the new scale factor.
-                SetWindowPos(
-                    hwnd,
-                    hwnd,
-                    new_rect.left,
-                    new_rect.top,
-                    new_rect.right - new_rect.left,
-                    new_rect.bottom - new_rect.top,
-                    SWP_NOZORDER | SWP_NOMOVE,
-                );
+                window_state.send_events(events);
+            }
+
+            None
+        }
+        WM_DPICHANGED => {
+            handle_dpi_change(hwnd, window_state, (wparam & 0xFFFF) as u16 as u32);
+
+            None
+        }
+        WM_DISPLAYCHANGE => {
+            // Changing the resolution or rearranging the monitors can change the window's DPI
+            // without a `WM_DPICHANGED` message, for instance when the window stays on the same
+            // monitor. Only top level windows receive this message.
+            if window_state.scale_policy().follows_system() {
+                let dpi = dpi::dpi_for_window(hwnd);
+                let scale_factor = dpi as f64 / dpi::DEFAULT_DPI as f64;
+                if scale_factor != window_state.window_info.borrow().scale() {
+                    handle_dpi_change(hwnd, window_state, dpi);
+                }
             }
 
             None
         }
         WM_SETCURSOR => {
-            let cursor = *window_state.cursor.borrow();
-            if cursor != LoadCursorW(null_mut(), IDC_ARROW) {
-                SetCursor(cursor);
-                Some(0)
+            // The borders of standalone windows keep their resize cursors
+            let in_client_area = LOWORD(lparam as DWORD) as LRESULT == HTCLIENT;
+            let cursor = window_state.resolved_cursor();
+            if in_client_area && cursor != MouseCursor::Default {
+                SetCursor(window_state.load_cursor(cursor));
+                Some(TRUE as LRESULT)
             } else {
                 None
             }
@@ -433,14 +926,75 @@ unsafe fn wnd_proc_inner(
         // NOTE: `WM_NCDESTROY` is handled in the outer function because this deallocates the window
         //        state
         BV_WINDOW_MUST_CLOSE => {
-            DestroyWindow(hwnd);
+            if wparam != CLOSE_IF_STILL_REQUESTED || window_state.close_requested.replace(false) {
+                DestroyWindow(hwnd);
+            }
+
+            Some(0)
+        }
+        #[cfg(feature = "accesskit")]
+        WM_GETOBJECT => {
+            let mut activation_handler =
+                AccessKitHandler(accesskit_sender(hwnd, &window_state.accesskit_events));
+            let result = window_state.accesskit.borrow_mut().as_mut().and_then(|adapter| {
+                adapter.handle_wm_getobject(
+                    accesskit_windows::WPARAM(wparam),
+                    accesskit_windows::LPARAM(lparam),
+                    &mut activation_handler,
+                )
+            });
+
+            // Turning the result into an `LRESULT` can lead to nested `WM_GETOBJECT` messages, so
+            // the adapter must not be borrowed anymore at this point
+            result.map(|result| {
+                let result: accesskit_windows::LRESULT = result.into();
+                result.0
+            })
+        }
+        #[cfg(feature = "accesskit")]
+        BV_ACCESSKIT_EVENT => {
+            let events: Vec<_> = window_state
+                .accesskit_events
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(Event::AccessKit)
+                .collect();
+            window_state.send_events(events);
+
             Some(0)
         }
         _ => None,
     }
 }
 
-unsafe fn register_wnd_class() -> ATOM {
+/// Convert a position in screen coordinates, as used by OLE drag and drop, to a logical position
+/// relative to the window's client area. The window state must already have been stored in the
+/// window.
+pub(super) unsafe fn screen_to_logical(hwnd: HWND, position: PhyPoint) -> Point {
+    let window_state = &*(GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const WindowState);
+
+    let mut point = POINT { x: position.x, y: position.y };
+    ScreenToClient(hwnd, &mut point);
+
+    let physical_pos = PhyPoint { x: point.x, y: point.y };
+    physical_pos.to_logical(&window_state.window_info.borrow())
+}
+
+/// Whether another baseview window that's part of the same top-level window has the keyboard
+/// focus, like a window nested in this one or the window this one is nested in. Windows take the
+/// focus when the cursor moves over them, but between nested windows that would move the focus
+/// back and forth every time the cursor crosses the border between them. They only take the focus
+/// from each other when they're clicked instead.
+unsafe fn focus_in_nested_window(hwnd: HWND) -> bool {
+    let focus = GetFocus();
+    !focus.is_null()
+        && focus != hwnd
+        && GetWindowLongPtrW(focus, GWLP_WNDPROC) as usize == wnd_proc as usize
+        && GetAncestor(focus, GA_ROOT) == GetAncestor(hwnd, GA_ROOT)
+}
+
+unsafe fn register_wnd_class(drop_shadow: bool) -> ATOM {
     // We generate a unique name for the new window class to prevent name collisions
     let class_name_str = format!("Baseview-{}", generate_guid());
     let mut class_name: Vec<u16> = OsStr::new(&class_name_str).encode_wide().collect();
@@ -448,7 +1002,7 @@ unsafe fn register_wnd_class() -> ATOM {
     let icon = LoadIconA(GetModuleHandleA(null_mut()), MAKEINTRESOURCEA(1));
 
     let wnd_class = WNDCLASSW {
-        style: CS_OWNDC,
+        style: if drop_shadow { CS_OWNDC | CS_DROPSHADOW } else { CS_OWNDC },
         lpfnWndProc: Some(wnd_proc),
         hInstance: null_mut(),
         lpszClassName: class_name.as_ptr(),
@@ -467,27 +1021,149 @@ unsafe fn unregister_wnd_class(wnd_class: ATOM) {
     UnregisterClassW(wnd_class as _, null_mut());
 }
 
+/// Queue AccessKit's requests for the window's handler. AccessKit may call its handlers on other
+/// threads, so the window is woken up through a posted message.
+#[cfg(feature = "accesskit")]
+fn accesskit_sender(
+    hwnd: HWND, events: &Arc<Mutex<VecDeque<AccessKitEvent>>>,
+) -> impl FnMut(AccessKitEvent) + Send + 'static {
+    // Window handles can be used from any thread
+    let hwnd = hwnd as usize;
+    let events = Arc::clone(events);
+
+    move |event| {
+        events.lock().unwrap().push_back(event);
+        unsafe { PostMessageW(hwnd as HWND, BV_ACCESSKIT_EVENT, 0, 0) };
+    }
+}
+
 /// All data associated with the window. This uses internal mutability so the outer struct doesn't
 /// need to be mutably borrowed. Mutably borrowing the entire `WindowState` can be problematic
 /// because of the Windows message loops' reentrant nature. Care still needs to be taken to prevent
 /// `handler` from indirectly triggering other events that would also need to be handled using
 /// `handler`.
 struct WindowState {
+    id: WindowId,
     /// The HWND belonging to this window. The window's actual state is stored in the `WindowState`
     /// struct associated with this HWND through `unsafe { GetWindowLongPtrW(self.hwnd,
     /// GWLP_USERDATA) } as *const WindowState`.
     pub hwnd: HWND,
     window_class: ATOM,
     window_info: RefCell<WindowInfo>,
-    parent_handle: Option<ParentHandle>,
+    /// Marks the window as closed for its [`WindowHandle`] once this state is dropped.
+    parent_handle: ParentHandle,
     drop_handler: DropHandler,
+    /// See [`crate::EventContext::set_drop_effect`].
+    drop_target: Rc<DropTarget>,
+    /// Events from the `IDropTarget` callbacks, along with the position for the `CursorMoved` event
+    /// sent before them. These callbacks can also run while the handler is busy, so the events are
+    /// queued and sent by [`Self::send_drag_events()`] once the handler is available.
+    drag_events: RefCell<VecDeque<(Event, Option<Point>)>>,
+    /// Events passed to [`Self::send_events()`] while the handler was busy, for instance because
+    /// it's running a modal loop. These include the releases for held buttons and keys when the
+    /// window loses the focus or the mouse capture, so they're sent once the handler is available
+    /// instead of being dropped.
+    pending_events: RefCell<VecDeque<Event>>,
+    /// Whether OLE could be initialized and the window was registered as a drop target. See
+    /// [`crate::EventContext::drag_and_drop_available`].
+    drag_and_drop_available: Cell<bool>,
     keyboard_state: RefCell<KeyboardState>,
-    mouse_button_counter: Cell<usize>,
+    /// Used to synthesize release events when the window loses focus or its mouse capture.
+    held_inputs: RefCell<HeldInputs>,
+    event_clock: EventClock,
+    user_data: UserData,
     // Initialized late so the `Window` can hold a reference to this `WindowState`
     handler: Rc<RefCell<Option<Box<dyn WindowHandler>>>>,
-    scale_policy: WindowScalePolicy,
+    /// See [`crate::EventContext::set_scale_policy`]. Use [`Self::scale_policy()`] to also take the
+    /// override into account.
+    scale_policy: Cell<WindowScalePolicy>,
+    /// See [`crate::EventContext::set_scale_override`].
+    scale_override: Cell<Option<f64>>,
     dw_style: u32,
-    cursor: RefCell<HCURSOR>,
+    /// Whether the window takes the keyboard focus when the cursor moves over it. This is false
+    /// for [`WindowKind::Popup`]s that aren't focusable.
+    takes_focus: bool,
+    /// The cursor set through [`crate::EventContext::set_mouse_cursor()`].
+    mouse_cursor: Cell<MouseCursor>,
+    cursor_regions: RefCell<CursorRegions>,
+    /// The last cursor that was loaded, so `WM_SETCURSOR` doesn't load it again every time the
+    /// mouse moves.
+    loaded_cursor: Cell<Option<(MouseCursor, HCURSOR)>>,
+    /// Set by [`crate::EventContext::close()`] and cleared by
+    /// [`crate::EventContext::cancel_close()`]. The `BV_WINDOW_MUST_CLOSE` message posted by
+    /// `close()` only destroys the window if this is still set when it arrives.
+    close_requested: Cell<bool>,
+    /// The cursors created through [`crate::EventContext::register_cursor()`]. These are destroyed
+    /// together with the window.
+    custom_cursors: RefCell<HashMap<CursorId, HCURSOR>>,
+    /// Whether `on_frame` should be called continuously. See
+    /// [`crate::EventContext::set_animating`].
+    animating: Cell<bool>,
+    /// See [`crate::EventContext::set_frame_interval`].
+    frame_interval: Cell<Duration>,
+    /// See [`WindowOpenOptions::frame_source`]. No frame timer is created for external frame
+    /// sources.
+    frame_source: FrameSource,
+    /// Whether the window wants frames right now. With [`FrameSource::External`] there's no
+    /// timer, and this only decides whether the host's next tick draws a frame.
+    frame_timer_running: Cell<bool>,
+    /// See [`crate::EventContext::pause_frames`].
+    frames_paused: Cell<bool>,
+    /// Set when a frame was requested while the frames were paused, so it can be drawn after
+    /// unpausing.
+    frame_pending_while_paused: Cell<bool>,
+    /// Posts frame messages alongside the frame timer, since those still arrive during modal loops.
+    /// This runs whenever the frame timer does.
+    frame_thread: RefCell<Option<FrameThread>>,
+    /// When the frame timer or the frame thread last drew a frame. Both of them usually fire during
+    /// every interval, so only the first one draws a frame.
+    last_frame_timer_frame: Cell<Option<Instant>>,
+    /// Used to detect timer jitter. Reset whenever the timer is stopped.
+    #[cfg(feature = "tracing")]
+    last_frame_timer_tick: Cell<Option<Instant>>,
+    panic_policy: PanicPolicy,
+    /// Set once the handler has panicked. From that point on the handler doesn't receive any more
+    /// events, and the window is destroyed.
+    handler_panicked: Cell<bool>,
+    /// Frames are only drawn after the initial `Resized` event, and the handler isn't called at
+    /// all anymore after `WillClose`.
+    lifecycle: Cell<Lifecycle>,
+    /// How many of the window's messages are being handled right now. Messages can be nested, for
+    /// instance when the handler runs a modal loop.
+    message_depth: Cell<u32>,
+    /// Set by `WM_NCDESTROY`. The window state is freed once no more messages are being handled.
+    destroyed: Cell<bool>,
+    /// Whether the handler was last told that the window is visible. See [`WindowEvent::Shown`].
+    visible: Cell<bool>,
+    /// The physical position of the last `WM_MOUSEMOVE` message, used to compute the `delta` of
+    /// `CursorMoved` events.
+    last_cursor_position: Cell<Option<PhyPoint>>,
+    /// Whether the cursor is clipped to the window. See
+    /// [`crate::EventContext::set_cursor_confined`].
+    cursor_confined: Cell<bool>,
+    /// The window info from before a `WM_DPICHANGED` message. This is used so that the `WM_SIZE`
+    /// message that follows it results in a single `Resized` event containing both changes.
+    pre_dpi_change_window_info: Cell<Option<WindowInfo>>,
+    /// Set between `WM_ENTERSIZEMOVE` and `WM_EXITSIZEMOVE`, while the window is being moved or
+    /// resized by the user.
+    in_size_move: Cell<bool>,
+    /// Set once a `WM_SIZING` message arrives while `in_size_move` is set. See
+    /// [`WindowEvent::ResizeStarted`].
+    interactive_resize: Cell<bool>,
+    /// The window info from before the first resize that hasn't been sent to the handler yet during
+    /// an interactive resize. The `Resized` event is sent before the next frame.
+    pending_resize: Cell<Option<WindowInfo>>,
+    /// When the handler last drew a frame. Used to keep drawing frames during interactive resizes.
+    last_frame: Cell<Option<Instant>>,
+    /// See [`crate::EventContext::damage`].
+    damage: RefCell<Damage>,
+    /// Answers `WM_GETOBJECT` messages if the window was opened with AccessKit enabled.
+    #[cfg(feature = "accesskit")]
+    accesskit: RefCell<Option<accesskit_windows::Adapter>>,
+    /// Requests from AccessKit's handlers, which may be called on other threads. A
+    /// `BV_ACCESSKIT_EVENT` message is posted along with every request.
+    #[cfg(feature = "accesskit")]
+    accesskit_events: Arc<Mutex<VecDeque<AccessKitEvent>>>,
 
     /// Tasks that should be executed at the end of `wnd_proc`. This is needed to avoid mutably
     /// borrowing the fields from `WindowState` more than once. For instance, when the window
@@ -498,6 +1174,9 @@ struct WindowState {
 
     #[cfg(feature = "opengl")]
     pub gl_context: Option<GlContext>,
+    /// See [`crate::EventContext::gl_context_error()`].
+    #[cfg(feature = "opengl")]
+    pub gl_context_error: Option<GlError>,
 }
 
 impl WindowState {
@@ -505,25 +1184,497 @@ impl WindowState {
         Window { state: self }
     }
 
+    /// Initialize OLE for this thread and register the window as a drop target. Returns `false`
+    /// if either of these failed, for instance because something else already initialized COM
+    /// using the multithreaded apartment model on this thread.
+    ///
+    /// Both are undone by [`Self::revoke_drop_target()`] if this returns `true`.
+    unsafe fn register_drop_target(&self) -> bool {
+        let ole_init_result = ole2::OleInitialize(null_mut());
+        // It is ok if the initialize result is `S_FALSE` because it might happen that
+        // multiple windows are created on the same thread.
+        if ole_init_result == OLE_E_WRONGCOMPOBJ || ole_init_result == RPC_E_CHANGED_MODE {
+            debug!(
+                target: "baseview::dnd",
+                result = ole_init_result,
+                "OleInitialize failed, drag and drop is disabled"
+            );
+            return false;
+        }
+
+        let handler_interface_ptr = &mut (*self.drop_handler.data).interface as LPDROPTARGET;
+        let register_result = ole2::RegisterDragDrop(self.hwnd, handler_interface_ptr);
+        if register_result != S_OK {
+            debug!(
+                target: "baseview::dnd",
+                result = register_result,
+                "RegisterDragDrop failed, drag and drop is disabled"
+            );
+            ole2::OleUninitialize();
+            return false;
+        }
+
+        true
+    }
+
+    /// Undo [`Self::register_drop_target()`]. This needs to happen before the window is gone, and
+    /// it releases OLE's reference to the drop handler.
+    unsafe fn revoke_drop_target(&self) {
+        if self.drag_and_drop_available.replace(false) {
+            ole2::RevokeDragDrop(self.hwnd);
+            ole2::OleUninitialize();
+        }
+    }
+
+    /// Start the frame timer if it isn't already running. If the window isn't animating, the timer
+    /// is stopped again after the next frame. While the frames are paused this only remembers that
+    /// a frame should be drawn after unpausing.
+    fn start_frame_timer(&self) {
+        if self.frames_paused.get() {
+            self.frame_pending_while_paused.set(true);
+        } else if !self.frame_timer_running.replace(true) {
+            self.set_frame_timer();
+        }
+    }
+
+    /// Create the frame timer using the current frame interval. Calling this while the timer is
+    /// already running replaces that timer.
+    fn set_frame_timer(&self) {
+        if self.frame_source == FrameSource::External {
+            return;
+        }
+
+        let interval_ms =
+            self.frame_interval
+                .get()
+                .as_millis()
+                .clamp(USER_TIMER_MINIMUM as u128, USER_TIMER_MAXIMUM as u128) as UINT;
+
+        unsafe { frame_timer::set_timer(self.hwnd, WIN_FRAME_TIMER, interval_ms) };
+        let interval = Duration::from_millis(interval_ms as u64);
+        *self.frame_thread.borrow_mut() = Some(FrameThread::start(self.hwnd, interval));
+    }
+
+    fn stop_frame_timer(&self) {
+        if self.frame_timer_running.replace(false) {
+            unsafe { KillTimer(self.hwnd, WIN_FRAME_TIMER) };
+            self.frame_thread.borrow_mut().take();
+
+            #[cfg(feature = "tracing")]
+            self.last_frame_timer_tick.set(None);
+        }
+    }
+
+    /// Draw a frame in response to a frame timer tick, or to a tick from the host with
+    /// [`FrameSource::External`].
+    fn handle_frame_tick(&self) {
+        // When the window isn't animating the timer was only started to draw a single frame in
+        // response to an event, or to redraw an invalidated rectangle
+        if self.draw_frame() && !self.animating.get() && !self.damage.borrow().has_pending() {
+            self.stop_frame_timer();
+        }
+    }
+
+    /// Draw a frame in response to the frame timer or the frame thread, unless the other one
+    /// already drew a frame during this interval.
+    fn handle_frame_timer_tick(&self) {
+        let min_elapsed = self.frame_interval.get() * 3 / 4;
+        let now = Instant::now();
+        if self.last_frame_timer_frame.get().is_some_and(|last| now - last < min_elapsed) {
+            return;
+        }
+
+        self.last_frame_timer_frame.set(Some(now));
+        self.handle_frame_tick();
+    }
+
+    /// Log frame timer ticks that arrive much later than the timer's interval. `WM_TIMER` messages
+    /// have a low priority, so this happens when the message queue is flooded.
+    #[cfg(feature = "tracing")]
+    fn trace_frame_timer_jitter(&self) {
+        let now = Instant::now();
+        if let Some(last_tick) = self.last_frame_timer_tick.replace(Some(now)) {
+            let elapsed = now - last_tick;
+            if elapsed >= self.frame_interval.get() * 2 {
+                debug!(target: "baseview::frame", ?elapsed, "frame timer tick arrived late");
+            }
+        }
+    }
+
+    /// Send the handler its final `WillClose` event. Every way of closing the window ends up in
+    /// `WM_DESTROY`, including the parent window being destroyed, so this is called from there. The
+    /// OpenGL context is only destroyed together with the window state after `WM_NCDESTROY`.
+    fn send_will_close(&self) {
+        if !self.lifecycle.get().can_send_events() {
+            return;
+        }
+        self.lifecycle.set(Lifecycle::Closing);
+
+        // The handler is still borrowed if it destroyed the window's parent itself. It receives
+        // `WillClose` from `send_pending_will_close()` once it has returned.
+        if self.handler.try_borrow_mut().is_ok() {
+            self.deliver_will_close();
+        }
+    }
+
+    /// Send the `WillClose` event [`Self::send_will_close()`] couldn't send because the handler was
+    /// busy. This happens right before the window state is freed, when the window itself has
+    /// already been destroyed, but the OpenGL context is still alive.
+    fn send_pending_will_close(&self) {
+        if self.lifecycle.get() == Lifecycle::Closing {
+            // This runs outside of the message handling code that catches the handler's panics
+            self.panic_policy.catch(|| self.deliver_will_close());
+            self.lifecycle.set(Lifecycle::Closed);
+        }
+    }
+
+    fn deliver_will_close(&self) {
+        #[cfg(feature = "opengl")]
+        self.make_gl_context_current();
+
+        let mut window = self.create_window();
+        let mut window = crate::EventContext::new(&mut window);
+        if let Some(handler) = self.handler.borrow_mut().as_mut() {
+            handler.on_event(&mut window, Event::Window(WindowEvent::WillClose));
+        }
+        self.lifecycle.set(Lifecycle::Closed);
+    }
+
+    /// Make the OpenGL context current before the handler receives `WillClose`, so the handler can
+    /// still free its GPU resources.
+    #[cfg(feature = "opengl")]
+    fn make_gl_context_current(&self) {
+        if let Some(gl_context) = &self.gl_context {
+            unsafe { gl_context.make_current() };
+        }
+    }
+
+    /// Called after the handler has panicked. The handler gets a final `WillClose` event, after
+    /// which the window is destroyed.
+    fn handle_handler_panic(&self) {
+        self.handler_panicked.set(true);
+        self.stop_frame_timer();
+
+        // The handler may also have panicked while handling `WillClose`
+        if self.lifecycle.get().can_send_events() {
+            self.lifecycle.set(Lifecycle::Closing);
+
+            #[cfg(feature = "opengl")]
+            self.make_gl_context_current();
+            let mut window = self.create_window();
+            let mut window = crate::EventContext::new(&mut window);
+            self.panic_policy.catch(|| {
+                if let Ok(Some(handler)) = self.handler.try_borrow_mut().as_deref_mut() {
+                    handler.on_event(&mut window, Event::Window(WindowEvent::WillClose));
+                }
+            });
+        }
+        self.lifecycle.set(Lifecycle::Closed);
+
+        // We may be deep inside of a message handler that still uses this window state, so the
+        // window cannot be destroyed right away
+        unsafe { PostMessageW(self.hwnd, BV_WINDOW_MUST_CLOSE, 0, 0) };
+    }
+
+    /// Send events that weren't directly caused by input. These messages can also arrive while
+    /// the handler is running, for instance when it opens a modal dialog. The handler initiated
+    /// that itself, so the events are dropped in that case.
+    /// Draw a frame, sending any pending `Resized` event first. Returns `false` if the handler
+    /// was already borrowed or if the window can't draw frames yet or anymore, in which case
+    /// nothing happened.
+    fn draw_frame(&self) -> bool {
+        if !self.lifecycle.get().can_draw() {
+            return false;
+        }
+
+        let mut window = self.create_window();
+        let mut window = crate::EventContext::new(&mut window);
+        let Ok(mut handler) = self.handler.try_borrow_mut() else {
+            return false;
+        };
+        let handler = handler.as_mut().unwrap();
+
+        if let Some(old_window_info) = self.pending_resize.take() {
+            let new_window_info = *self.window_info.borrow();
+            handler.on_event(
+                &mut window,
+                Event::Window(WindowEvent::Resized { new: new_window_info, old: old_window_info }),
+            );
+        }
+
+        let logical_size = self.window_info.borrow().logical_size();
+        self.damage.borrow_mut().begin_frame(logical_size);
+        handler.on_frame(&mut window);
+        self.last_frame.set(Some(Instant::now()));
+
+        true
+    }
+
+    /// Clip the cursor to the window's client area if it's confined. The clip rectangle is in
+    /// screen coordinates, so this is repeated whenever the window moves or is resized.
+    fn clip_cursor(&self) {
+        if !self.cursor_confined.get() {
+            return;
+        }
+
+        unsafe {
+            let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            GetClientRect(self.hwnd, &mut rect);
+            MapWindowPoints(self.hwnd, null_mut(), &mut rect as *mut RECT as *mut POINT, 2);
+            ClipCursor(&rect);
+        }
+    }
+
+    /// Stop confining the cursor to the window, if it's confined.
+    fn release_cursor(&self) {
+        if self.cursor_confined.replace(false) {
+            unsafe { ClipCursor(null()) };
+        }
+    }
+
+    fn send_events(&self, events: Vec<Event>) {
+        // Deferred tasks still run after the handler has received `WillClose`
+        if events.is_empty() || !self.lifecycle.get().can_send_events() {
+            return;
+        }
+
+        self.pending_events.borrow_mut().extend(events);
+        self.send_pending_events();
+    }
+
+    /// Send the events queued by [`Self::send_events()`] in order. If the handler is busy, then
+    /// they're sent after the message that's being handled instead. These can't be deferred tasks,
+    /// since those also run at the end of the nested messages the handler is busy during.
+    fn send_pending_events(&self) {
+        if self.handler_panicked.get() || !self.lifecycle.get().can_send_events() {
+            self.pending_events.borrow_mut().clear();
+            return;
+        }
+
+        let Ok(mut handler) = self.handler.try_borrow_mut() else { return };
+        let Some(handler) = handler.as_mut() else { return };
+        let mut window = self.create_window();
+        let mut window = crate::EventContext::new(&mut window);
+        loop {
+            let Some(event) = self.pending_events.borrow_mut().pop_front() else { break };
+            handler.on_event(&mut window, event);
+        }
+    }
+
+    /// Send the queued drag events to the handler in order. If the handler is busy, then they're
+    /// sent after the message that's being handled instead.
+    fn send_drag_events(&self) {
+        loop {
+            if self.handler_panicked.get() || !self.lifecycle.get().can_send_events() {
+                self.drag_events.borrow_mut().clear();
+                return;
+            }
+
+            let result = {
+                let Ok(mut handler) = self.handler.try_borrow_mut() else { return };
+                let Some(handler) = handler.as_mut() else { return };
+                let Some((event, position)) = self.drag_events.borrow_mut().pop_front() else {
+                    return;
+                };
+
+                let mut window = self.create_window();
+                let mut window = crate::EventContext::new(&mut window);
+                self.panic_policy.catch(|| {
+                    if let Some(position) = position {
+                        let event = Event::Mouse(MouseEvent::CursorMoved {
+                            position,
+                            delta: PhyPoint::default(),
+                            modifiers: keyboard_types::Modifiers::empty(),
+                            buttons: MouseButtonSet::empty(),
+                        });
+                        handler.on_event(&mut window, event);
+                    }
+                    handler.on_event(&mut window, event);
+                })
+            };
+            if result.is_none() {
+                self.handle_handler_panic();
+                return;
+            }
+
+            self.start_frame_timer();
+        }
+    }
+
+    /// Call into the handler from outside of `wnd_proc()`, closing the window if the handler
+    /// panics.
+    fn catch_handler_panic(&self, f: impl FnOnce()) {
+        if !self.handler_panicked.get() && self.panic_policy.catch(f).is_none() {
+            self.handle_handler_panic();
+        }
+    }
+
+    #[cfg(feature = "accesskit")]
+    fn update_accesskit_focus(&self, focused: bool) {
+        let events = self
+            .accesskit
+            .borrow_mut()
+            .as_mut()
+            .and_then(|adapter| adapter.update_window_focus_state(focused));
+
+        // Raising the events can lead to nested `WM_GETOBJECT` messages
+        if let Some(events) = events {
+            events.raise();
+        }
+    }
+
+    fn send_initial_resized(&self) {
+        let window_info = *self.window_info.borrow();
+
+        let mut window = self.create_window();
+        let mut window = crate::EventContext::new(&mut window);
+        self.lifecycle.set(Lifecycle::Ready);
+        self.handler.borrow_mut().as_mut().unwrap().on_event(
+            &mut window,
+            Event::Window(WindowEvent::Resized { new: window_info, old: window_info }),
+        );
+    }
+
+    /// Send a `Shown` event if the window is already visible. Windows created with `WS_VISIBLE`
+    /// receive their first `WM_SHOWWINDOW` message before the handler has been installed.
+    unsafe fn send_initial_visibility(&self) {
+        self.set_visible(IsWindowVisible(self.hwnd) != 0);
+    }
+
+    /// Let the handler know that the window has been shown or hidden, if that changed its
+    /// visibility.
+    fn set_visible(&self, visible: bool) {
+        if self.visible.replace(visible) != visible {
+            let event = if visible { WindowEvent::Shown } else { WindowEvent::Hidden };
+            self.send_events(vec![Event::Window(event)]);
+        }
+    }
+
+    /// The cursor for the pointer's current position, from the cursor set by the handler and the
+    /// cursor regions.
+    unsafe fn resolved_cursor(&self) -> MouseCursor {
+        let mut point = POINT { x: 0, y: 0 };
+        let position = (GetCursorPos(&mut point) != 0
+            && ScreenToClient(self.hwnd, &mut point) != 0)
+            .then(|| PhyPoint::new(point.x, point.y).to_logical(&self.window_info.borrow()));
+
+        self.cursor_regions.borrow().resolve(self.mouse_cursor.get(), position)
+    }
+
+    unsafe fn load_cursor(&self, mouse_cursor: MouseCursor) -> HCURSOR {
+        if let Some((loaded, cursor)) = self.loaded_cursor.get() {
+            if loaded == mouse_cursor {
+                return cursor;
+            }
+        }
+
+        let custom_cursor = match mouse_cursor {
+            MouseCursor::Custom(id) => self.custom_cursors.borrow().get(&id).copied(),
+            _ => None,
+        };
+        let cursor = custom_cursor
+            .unwrap_or_else(|| LoadCursorW(null_mut(), cursor_to_windows_cursor(mouse_cursor)));
+        self.loaded_cursor.set(Some((mouse_cursor, cursor)));
+
+        cursor
+    }
+
+    /// Show the cursor for the pointer's position after the cursor or the cursor regions changed.
+    /// `SetCursor()` changes the cursor no matter which window the pointer is over, so it's only
+    /// called when the pointer is over this window. Otherwise the cursor is set by `WM_SETCURSOR`
+    /// once the pointer enters the window.
+    unsafe fn refresh_cursor(&self) {
+        if self.owns_cursor() {
+            SetCursor(self.load_cursor(self.resolved_cursor()));
+        }
+    }
+
+    /// Whether the pointer's cursor is currently this window's to change. That's the case when the
+    /// pointer is over the window's client area and not over another window that covers it, or
+    /// when the window has captured the mouse.
+    unsafe fn owns_cursor(&self) -> bool {
+        if GetCapture() == self.hwnd {
+            return true;
+        }
+
+        let mut point = POINT { x: 0, y: 0 };
+        if GetCursorPos(&mut point) == 0 || WindowFromPoint(point) != self.hwnd {
+            return false;
+        }
+        ScreenToClient(self.hwnd, &mut point);
+
+        let mut client_rect: RECT = std::mem::zeroed();
+        GetClientRect(self.hwnd, &mut client_rect);
+        point.x >= client_rect.left
+            && point.y >= client_rect.top
+            && point.x < client_rect.right
+            && point.y < client_rect.bottom
+    }
+
+    /// Send a `CursorEntered` followed by a `CursorMoved` event if the mouse cursor is already
+    /// inside of the window.
+    unsafe fn send_initial_cursor_position(&self) {
+        let mut point = POINT { x: 0, y: 0 };
+        if GetCursorPos(&mut point) == 0 {
+            return;
+        }
+        ScreenToClient(self.hwnd, &mut point);
+
+        let mut client_rect: RECT = std::mem::zeroed();
+        GetClientRect(self.hwnd, &mut client_rect);
+        if point.x < client_rect.left
+            || point.y < client_rect.top
+            || point.x >= client_rect.right
+            || point.y >= client_rect.bottom
+        {
+            return;
+        }
+
+        let physical_pos = PhyPoint { x: point.x, y: point.y };
+        let logical_pos = physical_pos.to_logical(&self.window_info.borrow());
+        let modifiers = self.keyboard_state.borrow().get_modifiers();
+        let buttons = current_mouse_buttons();
+        self.last_cursor_position.set(Some(physical_pos));
+
+        let mut window = self.create_window();
+        let mut window = crate::EventContext::new(&mut window);
+        let mut handler = self.handler.borrow_mut();
+        let handler = handler.as_mut().unwrap();
+
+        handler.on_event(&mut window, Event::Mouse(MouseEvent::CursorEntered));
+        handler.on_event(
+            &mut window,
+            Event::Mouse(MouseEvent::CursorMoved {
+                position: logical_pos,
+                delta: PhyPoint::default(),
+                modifiers,
+                buttons,
+            }),
+        );
+    }
+
+    /// The scale policy that's in effect, which is the override's scale factor if there is one.
+    fn scale_policy(&self) -> WindowScalePolicy {
+        self.scale_policy.get().with_override(self.scale_override.get())
+    }
+
     /// Handle a deferred task as described in [`Self::deferred_tasks
     pub(self) fn handle_deferred_task(&self, task: WindowTask) {
         match task {
             WindowTask::Resize(size) => {
-                let window_info = {
-                    let mut window_info = self.window_info.borrow_mut();
-                    let scaling = window_info.scale();
-                    *window_info = WindowInfo::from_logical_size(size, scaling);
-
-                    *window_info
-                };
+                // The stored window info is only updated by the `WM_SIZE` message `SetWindowPos()`
+                // sends, so the handler receives a single `Resized` event with the size the window
+                // actually ended up with. The window's style doesn't matter here, so this also
+                // works for windows the user can't resize.
+                let window_info = self.window_info.borrow().with_logical_size(size);
 
                 // If the window is a standalone window then the size needs to include the window
                 // decorations
                 let mut rect = RECT {
                     left: 0,
                     top: 0,
-                    right: window_info.physical_size().width as i32,
-                    bottom: window_info.physical_size().height as i32,
+                    right: window_info.physical_size().to_point().x,
+                    bottom: window_info.physical_size().to_point().y,
                 };
                 unsafe {
                     AdjustWindowRectEx(&mut rect, self.dw_style, 0, 0);
@@ -553,8 +1704,91 @@ impl WindowState {
                     )
                 };
             }
-            WindowTask::Drag(data) => {
-                super::drag::start_drag(data);
+            WindowTask::Drag(data, effect, image) => {
+                let hwnd = self.hwnd;
+                let last_feedback = Cell::new(None);
+                let on_feedback = move |will_accept| unsafe {
+                    // `GiveFeedback()` is also called when nothing changed
+                    let mut point = POINT { x: 0, y: 0 };
+                    GetCursorPos(&mut point);
+                    if last_feedback.replace(Some((point.x, point.y, will_accept)))
+                        == Some((point.x, point.y, will_accept))
+                    {
+                        return;
+                    }
+
+                    // The window may have been destroyed during the drag
+                    let window_state_ptr =
+                        GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const WindowState;
+                    if window_state_ptr.is_null() {
+                        return;
+                    }
+
+                    let window_state = &*window_state_ptr;
+                    let position = screen_to_logical(hwnd, PhyPoint { x: point.x, y: point.y });
+                    window_state.send_events(vec![Event::Window(WindowEvent::DragSourceMoved {
+                        position,
+                        will_accept,
+                    })]);
+                };
+                let effect = super::drag::start_drag(data, effect, image, on_feedback);
+                self.send_events(vec![Event::Window(WindowEvent::DragSourceEnded { effect })]);
+            }
+            WindowTask::FileDialog(id, options) => {
+                let paths = super::file_dialog::show(self.hwnd, &options);
+                self.send_events(vec![Event::Dialog(DialogResult { id, paths })]);
+            }
+            WindowTask::SetFrameInterval(interval) => {
+                self.frame_interval.set(interval);
+                if self.frame_timer_running.get() {
+                    self.set_frame_timer();
+                }
+            }
+            WindowTask::PauseFrames(true) => {
+                if self.frame_timer_running.get() {
+                    self.frame_pending_while_paused.set(true);
+                }
+
+                self.stop_frame_timer();
+                self.frames_paused.set(true);
+            }
+            WindowTask::SetOpacity(opacity) => unsafe {
+                // Only windows that are actually made transparent become layered windows, since
+                // those are composited differently
+                let ex_style = GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE);
+                if ex_style & WS_EX_LAYERED as isize == 0 {
+                    SetWindowLongPtrW(self.hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED as isize);
+                }
+
+                let alpha = (opacity * 255.0).round() as u8;
+                SetLayeredWindowAttributes(self.hwnd, 0, alpha, LWA_ALPHA);
+            },
+            WindowTask::UpdateScale => {
+                // The window keeps its physical size, so there's no `WM_SIZE` message to wait for
+                let scale = match self.scale_policy() {
+                    WindowScalePolicy::ScaleFactor(scale) => scale,
+                    _ => dpi::dpi_for_window(self.hwnd) as f64 / dpi::DEFAULT_DPI as f64,
+                };
+                let old_window_info = *self.window_info.borrow();
+                if scale == old_window_info.scale() {
+                    return;
+                }
+
+                let new_window_info =
+                    WindowInfo::from_physical_size(old_window_info.physical_size(), scale);
+                *self.window_info.borrow_mut() = new_window_info;
+                self.send_events(vec![Event::Window(WindowEvent::Resized {
+                    new: new_window_info,
+                    old: old_window_info,
+                })]);
+            }
+            WindowTask::PauseFrames(false) => {
+                if self.frames_paused.replace(false) {
+                    let frame_pending = self.frame_pending_while_paused.take();
+                    if frame_pending || self.animating.get() {
+                        self.start_frame_timer();
+                    }
+                }
             }
         }
     }
@@ -571,7 +1805,17 @@ enum WindowTask {
     /// automatically.
     SetPosition(Point),
     /// Start a drag event
-    Drag(Data),
+    Drag(Data, DropEffect, Option<DragImage>),
+    /// Change the frame timer's interval, replacing the timer if it's currently running.
+    SetFrameInterval(Duration),
+    /// Stop or restart the frame timer. See [`crate::EventContext::pause_frames`].
+    PauseFrames(bool),
+    /// Change the opacity of a top-level window. See [`crate::EventContext::set_opacity`].
+    SetOpacity(f64),
+    /// Apply a new scale policy or override. See [`crate::EventContext::set_scale_policy`].
+    UpdateScale,
+    /// Show a file dialog. See [`crate::EventContext::open_file_dialog`].
+    FileDialog(DialogId, FileDialogOptions),
 }
 
 pub struct Window<'a> {
@@ -579,30 +1823,30 @@ pub struct Window<'a> {
 }
 
 impl Window<'_> {
-    pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
+    pub fn open_parented<H, B>(
+        parent: PlatformWindowHandle, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, Error>
     where
-        P: HasRawWindowHandle,
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
+        B: FnOnce(&mut crate::EventContext) -> H,
         B: Send + 'static,
     {
-        let parent = match parent.raw_window_handle() {
-            RawWindowHandle::Win32(h) => h.hwnd as HWND,
-            h => panic!("unsupported parent handle {:?}", h),
-        };
+        let PlatformWindowHandle::Win32 { hwnd } = parent;
+        let parent = hwnd as HWND;
 
-        let (window_handle, _) = Self::open(true, parent, options, build);
+        let (window_handle, _) = Self::open(true, parent, options, build)?;
 
-        window_handle
+        Ok(window_handle)
     }
 
     pub fn open_as_if_parented<H, B>(options: WindowOpenOptions, build: B) -> WindowHandle
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
+        B: FnOnce(&mut crate::EventContext) -> H,
         B: Send + 'static,
     {
-        let (window_handle, _) = Self::open(true, null_mut(), options, build);
+        let (window_handle, _) =
+            Self::open(true, null_mut(), options, build).unwrap_or_else(|err| panic!("{}", err));
 
         window_handle
     }
@@ -610,10 +1854,11 @@ impl Window<'_> {
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: 'static,
     {
-        let (_, hwnd) = Self::open(false, null_mut(), options, build);
+        let (_, hwnd) =
+            Self::open(false, null_mut(), options, build).unwrap_or_else(|err| panic!("{}", err));
 
         unsafe {
             let mut msg: MSG = std::mem::zeroed();
@@ -635,24 +1880,41 @@ impl Window<'_> {
         }
     }
 
+    /// `parented` is set for the windows that run on the host's message loop. Those don't need to
+    /// have a parent window: `open_as_if_parented()` creates a top level window.
+    ///
+    /// Nothing has been allocated for the window yet when this returns an error, and `build` has
+    /// not been called.
     fn open<H, B>(
         parented: bool, parent: HWND, mut options: WindowOpenOptions, build: B,
-    ) -> (WindowHandle, HWND)
+    ) -> Result<(WindowHandle, HWND), Error>
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: 'static,
     {
         unsafe {
             let mut title: Vec<u16> = OsStr::new(&options.title[..]).encode_wide().collect();
             title.push(0);
 
-            let window_class = register_wnd_class();
-            // todo: manage error ^
+            // `open_as_if_parented()` also creates a top level window
+            let popup = match options.window_kind {
+                WindowKind::Popup { shadow, rounded_corners, focusable } if parent.is_null() => {
+                    Some((shadow, rounded_corners, focusable))
+                }
+                _ => None,
+            };
+
+            let window_class = register_wnd_class(matches!(popup, Some((true, _, _))));
+            if window_class == 0 {
+                let error = std::io::Error::last_os_error();
+                warn!(target: "baseview::lifecycle", %error, "could not register the window class");
+                return Err(Error::WindowCreationFailed(error.to_string()));
+            }
 
             let scaling = match options.scale {
-                WindowScalePolicy::SystemScaleFactor => 1.0,
                 WindowScalePolicy::ScaleFactor(scale) => scale,
+                _ => 1.0,
             };
 
             let window_info = WindowInfo::from_logical_size(options.size, scaling);
@@ -660,22 +1922,25 @@ impl Window<'_> {
             let mut rect = RECT {
                 left: 0,
                 top: 0,
-                // todo: check if usize fits into i32
-                right: window_info.physical_size().width as i32,
-                bottom: window_info.physical_size().height as i32,
+                right: window_info.physical_size().to_point().x,
+                bottom: window_info.physical_size().to_point().y,
             };
 
-            let mut flags = if parented {
-                WS_CHILD | WS_VISIBLE
+            // Creating a child window without a parent fails
+            let child = !parent.is_null();
+            let (mut flags, ex_flags) = if let Some((_, _, focusable)) = popup {
+                popup::styles(focusable)
+            } else if child {
+                (WS_CHILD | WS_VISIBLE, 0)
             } else {
-                WS_POPUPWINDOW | WS_CAPTION | WS_VISIBLE | WS_MINIMIZEBOX | WS_CLIPSIBLINGS
+                (WS_POPUPWINDOW | WS_CAPTION | WS_VISIBLE | WS_MINIMIZEBOX | WS_CLIPSIBLINGS, 0)
             };
 
-            if !parented {
-                if options.resizable {
+            if !child {
+                if options.resizable && popup.is_none() {
                     flags |= WS_SIZEBOX | WS_MAXIMIZEBOX;
                 }
-                AdjustWindowRectEx(&mut rect, flags, FALSE, 0);
+                AdjustWindowRectEx(&mut rect, flags, FALSE, ex_flags);
             }
 
             // Center the window on the screen
@@ -686,7 +1951,7 @@ impl Window<'_> {
 
             // Create window at (0, 0) initially - we'll center it after DPI awareness is set
             let hwnd = CreateWindowExW(
-                0,
+                ex_flags,
                 window_class as _,
                 title.as_ptr(),
                 flags,
@@ -699,112 +1964,161 @@ impl Window<'_> {
                 null_mut(),
                 null_mut(),
             );
-            // todo: manage error ^
+            // This happens for instance when the parent handle doesn't refer to a window (anymore).
+            // The OpenGL context and the window state are only created after this, so the class is
+            // all there is to clean up.
+            if hwnd.is_null() {
+                let error = std::io::Error::last_os_error();
+                unregister_wnd_class(window_class);
+                warn!(
+                    target: "baseview::lifecycle",
+                    ?parent,
+                    %error,
+                    "could not create the window"
+                );
+                return Err(Error::WindowCreationFailed(error.to_string()));
+            }
+            let _span = window_span!(hwnd = ?hwnd);
+            debug!(target: "baseview::lifecycle", parented, child, "created window");
 
             #[cfg(feature = "opengl")]
-            let gl_context: Option<GlContext> = options.gl_config.map(|gl_config| {
-                let mut handle = Win32WindowHandle::empty();
-                handle.hwnd = hwnd as *mut c_void;
-                let handle = RawWindowHandleWrapper { handle: RawWindowHandle::Win32(handle) };
-
-                GlContext::create(&handle, gl_config).expect("Could not create OpenGL context")
-            });
+            let (gl_context, gl_context_error) = match options.gl_config {
+                Some(gl_config) => {
+                    let handle = PlatformWindowHandle::Win32 { hwnd: hwnd as *mut c_void };
+                    let fallback_to_no_gl = gl_config.fallback_to_no_gl;
+
+                    match GlContext::create(handle, gl_config) {
+                        Ok(context) => (Some(context), None),
+                        Err(err) if fallback_to_no_gl => (None, Some(err)),
+                        Err(err) => panic!("Could not create OpenGL context: {}", err),
+                    }
+                }
+                None => (None, None),
+            };
             // The Window refers to this `WindowState`, so this `handler` needs to be
             // initialized later
             let handler: Rc<RefCell<Option<Box<dyn WindowHandler>>>> = Rc::new(RefCell::new(None));
 
-            let (parent_handle, window_handle) = ParentHandle::new(hwnd);
-            let parent_handle = if parented { Some(parent_handle) } else { None };
+            let id = WindowId::next();
+            // This is also kept for `open_blocking()`, even though nothing observes its handle
+            let (parent_handle, window_handle) = ParentHandle::new(hwnd, id);
 
-            let drop_handler_window_handler = handler.clone();
+            let drop_target = Rc::new(DropTarget::new(options.drop_target_valid.take()));
             let drop_handler = DropHandler::new(
                 hwnd,
-                Box::new(move |e, p| {
-                    let window_state_ptr =
-                        GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-                    let mut window = (*window_state_ptr).create_window();
-                    let mut window = crate::Window::new(&mut window);
-                    if let Some(p) = p {
-                        // Convert from screen coordinates (from Windows OLE drag) to client coordinates
-                        // ScreenToClient converts screen coordinates to client-relative coordinates
-                        let mut point = POINT { x: p.x, y: p.y };
-                        ScreenToClient(window.window.state.hwnd, &mut point);
-                        let physical_pos = PhyPoint { x: point.x, y: point.y };
-                        let logical_pos =
-                            physical_pos.to_logical(&window.window.state.window_info.borrow());
-                        let event = Event::Mouse(MouseEvent::CursorMoved {
-                            position: logical_pos,
-                            modifiers: keyboard_types::Modifiers::empty(),
-                        });
-
-                        drop_handler_window_handler
-                            .borrow_mut()
-                            .as_mut()
-                            .unwrap()
-                            .on_event(&mut window, event);
-                    }
-                    drop_handler_window_handler
-                        .borrow_mut()
-                        .as_mut()
-                        .unwrap()
-                        .on_event(&mut window, e);
+                Box::new(move |event, position| {
+                    let window_state =
+                        &*(GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const WindowState);
+                    window_state.drag_events.borrow_mut().push_back((event, position));
+                    window_state.send_drag_events();
                 }),
-                options.drop_target_valid.take(),
+                drop_target.clone(),
             );
 
             let window_state = Box::new(WindowState {
+                id,
                 hwnd,
                 window_class,
                 window_info: RefCell::new(window_info),
                 parent_handle,
                 drop_handler,
+                drop_target,
+                drag_events: RefCell::new(VecDeque::new()),
+                pending_events: RefCell::new(VecDeque::new()),
+                drag_and_drop_available: Cell::new(false),
                 keyboard_state: RefCell::new(KeyboardState::new()),
-                mouse_button_counter: Cell::new(0),
+                held_inputs: RefCell::new(HeldInputs::default()),
+                event_clock: EventClock::new(),
+                user_data: UserData::default(),
                 handler,
-                scale_policy: options.scale,
+                scale_policy: Cell::new(options.scale),
+                scale_override: Cell::new(None),
+                panic_policy: options.on_handler_panic,
+                handler_panicked: Cell::new(false),
+                lifecycle: Cell::new(Lifecycle::Created),
+                message_depth: Cell::new(0),
+                destroyed: Cell::new(false),
+                visible: Cell::new(false),
+                last_cursor_position: Cell::new(None),
+                cursor_confined: Cell::new(false),
                 dw_style: flags,
-                cursor: RefCell::new(LoadCursorW(null_mut(), IDC_ARROW)),
+                takes_focus: !matches!(popup, Some((_, _, false))),
+                mouse_cursor: Cell::new(MouseCursor::Default),
+                cursor_regions: RefCell::new(CursorRegions::default()),
+                loaded_cursor: Cell::new(None),
+                custom_cursors: RefCell::new(HashMap::new()),
+                close_requested: Cell::new(false),
+                animating: Cell::new(true),
+                frame_interval: Cell::new(DEFAULT_FRAME_INTERVAL),
+                frame_source: options.frame_source,
+                frame_timer_running: Cell::new(false),
+                frames_paused: Cell::new(false),
+                frame_pending_while_paused: Cell::new(false),
+                frame_thread: RefCell::new(None),
+                last_frame_timer_frame: Cell::new(None),
+                #[cfg(feature = "tracing")]
+                last_frame_timer_tick: Cell::new(None),
+                pre_dpi_change_window_info: Cell::new(None),
+                in_size_move: Cell::new(false),
+                interactive_resize: Cell::new(false),
+                pending_resize: Cell::new(None),
+                last_frame: Cell::new(None),
+                damage: RefCell::new(Damage::default()),
+                #[cfg(feature = "accesskit")]
+                accesskit: RefCell::new(None),
+                #[cfg(feature = "accesskit")]
+                accesskit_events: Arc::new(Mutex::new(VecDeque::new())),
 
                 deferred_tasks: RefCell::new(VecDeque::with_capacity(4)),
 
                 #[cfg(feature = "opengl")]
                 gl_context,
+                #[cfg(feature = "opengl")]
+                gl_context_error,
             });
 
+            // UI Automation needs to be initialized by the adapter before the first `WM_GETOBJECT`
+            // message arrives
+            #[cfg(feature = "accesskit")]
+            if options.accesskit {
+                let action_handler =
+                    AccessKitHandler(accesskit_sender(hwnd, &window_state.accesskit_events));
+                *window_state.accesskit.borrow_mut() = Some(accesskit_windows::Adapter::new(
+                    accesskit_windows::HWND(hwnd as *mut c_void),
+                    false,
+                    action_handler,
+                ));
+            }
+
+            // This happens before the handler is built so it can check whether drag and drop works
+            if options.enable_drag_and_drop {
+                window_state.drag_and_drop_available.set(window_state.register_drop_target());
+            }
+
             let handler = {
+                #[cfg(feature = "opengl")]
+                let _gl_guard = window_state.gl_context.as_ref().map(|gl_context| unsafe {
+                    gl_context.make_current_for_build(window_info.physical_size())
+                });
                 let mut window = window_state.create_window();
-                let mut window = crate::Window::new(&mut window);
+                let mut window = crate::EventContext::new(&mut window);
 
                 build(&mut window)
             };
-            *window_state.handler.borrow_mut() = Some(Box::new(handler));
-
-            let ole_init_result = ole2::OleInitialize(null_mut());
-            // It is ok if the initialize result is `S_FALSE` because it might happen that
-            // multiple windows are created on the same thread.
-            if ole_init_result == OLE_E_WRONGCOMPOBJ {
-                panic!("OleInitialize failed! Result was: `OLE_E_WRONGCOMPOBJ`");
-            } else if ole_init_result == RPC_E_CHANGED_MODE {
-                panic!(
-                    "OleInitialize failed! Result was: `RPC_E_CHANGED_MODE`. \
-                     Make sure other crates are not using multithreaded COM library \
-                     on the same thread or disable drag and drop support."
-                );
-            }
-            let handler_interface_ptr =
-                &mut (*window_state.drop_handler.data).interface as LPDROPTARGET;
-            assert_eq!(ole2::RegisterDragDrop(hwnd, handler_interface_ptr), S_OK);
+            *window_state.handler.borrow_mut() = Some(Box::new(crate::lifecycle::check_lifecycle(
+                crate::trace::instrument_handler(handler),
+            )));
 
-            // Only works on Windows 10 unfortunately.
-            SetProcessDpiAwarenessContext(
-                winapi::shared::windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
-            );
+            // The DPI awareness applies to the entire process, so embedded windows leave it up to
+            // the host.
+            if !parented {
+                dpi::enable_dpi_awareness();
+            }
 
             // Now we can get the actual dpi of the window.
-            let new_rect = if let WindowScalePolicy::SystemScaleFactor = options.scale {
-                // Only works on Windows 10 unfortunately.
-                let dpi = GetDpiForWindow(hwnd);
-                let scale_factor = dpi as f64 / 96.0;
+            let new_rect = if options.scale.follows_system() {
+                let dpi = dpi::dpi_for_window(hwnd);
+                let scale_factor = dpi as f64 / dpi::DEFAULT_DPI as f64;
 
                 let mut window_info = window_state.window_info.borrow_mut();
                 if window_info.scale() != scale_factor {
@@ -814,9 +2128,8 @@ impl Window<'_> {
                     Some(RECT {
                         left: 0,
                         top: 0,
-                        // todo: check if usize fits into i32
-                        right: window_info.physical_size().width as i32,
-                        bottom: window_info.physical_size().height as i32,
+                        right: window_info.physical_size().to_point().x,
+                        bottom: window_info.physical_size().to_point().y,
                     })
                 } else {
                     None
@@ -824,19 +2137,27 @@ impl Window<'_> {
             } else {
                 None
             };
-            SetWindowLongPtrW(hwnd, GWLP_USERDATA, Box::into_raw(window_state) as *const _ as _);
-            SetTimer(hwnd, WIN_FRAME_TIMER, 15, None);
+            let window_state_ptr = Box::into_raw(window_state);
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, window_state_ptr as *const _ as _);
+            (*window_state_ptr).start_frame_timer();
+
+            // Send an initial window resized event so the user is alerted of the correct dpi
+            // scaling. Unlike on the other platforms, no `WM_SIZE` message is guaranteed to arrive
+            // after the handler has been installed.
+            let window_state = &*window_state_ptr;
+            window_state.catch_handler_panic(|| window_state.send_initial_resized());
+            window_state.catch_handler_panic(|| window_state.send_initial_visibility());
 
             if let Some(mut new_rect) = new_rect {
                 // Recalculate the window position to center it on the screen
                 let screen_width = GetSystemMetrics(SM_CXSCREEN);
                 let screen_height = GetSystemMetrics(SM_CYSCREEN);
-                AdjustWindowRectEx(&mut new_rect, flags, FALSE, 0);
+                AdjustWindowRectEx(&mut new_rect, flags, FALSE, ex_flags);
                 let x = (screen_width - (new_rect.right - new_rect.left)) / 2;
                 let y = (screen_height - (new_rect.bottom - new_rect.top)) / 2;
 
-                // Windows makes us resize the window manually. This will trigger another `WM_SIZE` event,
-                // which we can then send the user the new scale factor.
+                // Windows makes us resize the window manually. This will trigger another `WM_SIZE`
+                // event, which we can then send the user the new scale factor.
                 SetWindowPos(
                     hwnd,
                     hwnd,
@@ -844,20 +2165,42 @@ impl Window<'_> {
                     y,
                     new_rect.right - new_rect.left,
                     new_rect.bottom - new_rect.top,
-                    SWP_NOZORDER,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
                 );
             }
 
-            (window_handle, hwnd)
+            if let Some((_, rounded_corners, focusable)) = popup {
+                if rounded_corners {
+                    popup::round_corners(hwnd);
+                }
+                // Focusable popups aren't created with `WS_VISIBLE`, since that would activate them
+                if focusable {
+                    ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+                }
+            }
+
+            // Hosts often open editors right under the mouse cursor. The handler should know about
+            // this before the first frame gets drawn instead of when the mouse is moved for the
+            // first time.
+            window_state.catch_handler_panic(|| window_state.send_initial_cursor_position());
+
+            Ok((window_handle, hwnd))
         }
     }
 
     pub fn close(&mut self) {
-        unsafe {
-            PostMessageW(self.state.hwnd, BV_WINDOW_MUST_CLOSE, 0, 0);
+        // Only a single message is needed for any number of calls
+        if !self.state.close_requested.replace(true) {
+            unsafe {
+                PostMessageW(self.state.hwnd, BV_WINDOW_MUST_CLOSE, CLOSE_IF_STILL_REQUESTED, 0);
+            }
         }
     }
 
+    pub fn cancel_close(&mut self) {
+        self.state.close_requested.set(false);
+    }
+
     pub fn resize(&mut self, size: Size) {
         // To avoid reentrant event handler calls we'll defer the actual resizing until after the
         // event has been handled
@@ -865,6 +2208,16 @@ impl Window<'_> {
         self.state.deferred_tasks.borrow_mut().push_back(task);
     }
 
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.state.scale_policy.set(policy);
+        self.state.deferred_tasks.borrow_mut().push_back(WindowTask::UpdateScale);
+    }
+
+    pub fn set_scale_override(&mut self, scale: Option<f64>) {
+        self.state.scale_override.set(scale);
+        self.state.deferred_tasks.borrow_mut().push_back(WindowTask::UpdateScale);
+    }
+
     pub fn set_position(&mut self, position: Point) {
         // To avoid reentrant event handler calls we'll defer the actual positioning until after the
         // event has been handled
@@ -872,18 +2225,214 @@ impl Window<'_> {
         self.state.deferred_tasks.borrow_mut().push_back(task);
     }
 
-    pub fn start_drag(&self, data: Data) {
+    pub fn start_drag(&self, data: Data, effect: DropEffect, image: Option<DragImage>) {
         // To avoid reentrant event handler calls we'll defer the actual resizing until after the
         // event has been handled
-        let task = WindowTask::Drag(data);
+        // `DoDragDrop()` needs OLE
+        if !self.state.drag_and_drop_available.get() {
+            return;
+        }
+
+        let task = WindowTask::Drag(data, effect, image);
         self.state.deferred_tasks.borrow_mut().push_back(task);
     }
 
-    pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
+    pub fn drag_and_drop_available(&self) -> bool {
+        self.state.drag_and_drop_available.get()
+    }
+
+    pub fn is_visible(&self) -> bool {
+        // `WM_SHOWWINDOW` is sent before the visibility changes, so `IsWindowVisible()` would
+        // disagree with the `Shown` and `Hidden` events while they're being handled
+        self.state.visible.get()
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        // Child windows aren't minimized themselves when the window they're part of is
+        unsafe { IsIconic(GetAncestor(self.state.hwnd, GA_ROOT)) != 0 }
+    }
+
+    pub fn has_focus(&self) -> bool {
+        unsafe { GetFocus() == self.state.hwnd }
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        self.state.dw_style & WS_SIZEBOX != 0
+    }
+
+    pub fn open_file_dialog(&mut self, options: FileDialogOptions) -> Result<DialogId, Error> {
+        // Windows resolves child windows to their top-level window when they're used as the owner
+        let id = DialogId::next();
+        self.state.deferred_tasks.borrow_mut().push_back(WindowTask::FileDialog(id, options));
+
+        Ok(id)
+    }
+
+    pub fn set_drop_effect(&mut self, effect: Option<DropEffect>) {
+        self.state.drop_target.set_chosen_effect(effect);
+    }
+
+    pub fn drop_effect(&self) -> Option<DropEffect> {
+        self.state.drop_target.effect()
+    }
+
+    #[cfg(feature = "accesskit")]
+    pub fn update_accesskit_tree(&mut self, update: impl FnOnce() -> accesskit::TreeUpdate) {
+        let events = self
+            .state
+            .accesskit
+            .borrow_mut()
+            .as_mut()
+            .and_then(|adapter| adapter.update_if_active(update));
+
+        // Raising the events can lead to nested `WM_GETOBJECT` messages
+        if let Some(events) = events {
+            events.raise();
+        }
+    }
+
+    pub fn set_animating(&mut self, animating: bool) {
+        self.state.animating.set(animating);
+        if animating {
+            self.state.start_frame_timer();
+        }
+    }
+
+    pub fn invalidate_rect(&mut self, rect: Rect) {
+        self.state.damage.borrow_mut().add(rect);
+        self.state.start_frame_timer();
+    }
+
+    pub fn damage(&self) -> Region {
+        self.state.damage.borrow().current().clone()
+    }
+
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        // This is deferred so the frame timer isn't replaced from within its own `WM_TIMER` handler
+        let task = WindowTask::SetFrameInterval(interval);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn pause_frames(&mut self, paused: bool) {
+        let task = WindowTask::PauseFrames(paused);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn set_opacity(&mut self, opacity: f64) -> Result<(), Error> {
+        // Layered child windows require Windows 8, and even then only work when the host
+        // application declares Windows 8 support in its manifest
+        if self.state.dw_style & WS_CHILD != 0 {
+            return Err(Error::Unsupported);
+        }
+
+        // Changing the extended window style sends messages to `wnd_proc`
+        let task = WindowTask::SetOpacity(opacity);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+
+        Ok(())
+    }
+
+    pub fn request_attention(&mut self, attention: AttentionType) {
+        let (flags, count) = match attention {
+            AttentionType::Informational => (FLASHW_TRAY, 3),
+            AttentionType::Critical => (FLASHW_ALL | FLASHW_TIMERNOFG, 0),
+        };
+
+        self.flash_window(flags, count);
+    }
+
+    pub fn stop_attention(&mut self) {
+        self.flash_window(FLASHW_STOP, 0);
+    }
+
+    /// Flash the top-level window this window belongs to. Child windows don't have a taskbar
+    /// button of their own.
+    fn flash_window(&self, flags: DWORD, count: UINT) {
         unsafe {
-            let cursor = LoadCursorW(null_mut(), cursor_to_windows_cursor(mouse_cursor));
-            *self.state.cursor.borrow_mut() = cursor;
-            SetCursor(cursor);
+            let mut flash_info = FLASHWINFO {
+                cbSize: std::mem::size_of::<FLASHWINFO>() as UINT,
+                hwnd: GetAncestor(self.state.hwnd, GA_ROOT),
+                dwFlags: flags,
+                uCount: count,
+                dwTimeout: 0,
+            };
+
+            FlashWindowEx(&mut flash_info);
+        }
+    }
+
+    pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
+        self.state.mouse_cursor.set(mouse_cursor);
+        unsafe { self.state.refresh_cursor() };
+    }
+
+    pub fn set_cursor_regions(&mut self, regions: Vec<(Rect, MouseCursor)>) {
+        self.state.cursor_regions.borrow_mut().set(regions);
+        unsafe { self.state.refresh_cursor() };
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        if confined {
+            self.state.cursor_confined.set(true);
+            self.state.clip_cursor();
+        } else {
+            self.state.release_cursor();
+        }
+    }
+
+    pub fn register_cursor(&mut self, icon: CursorIcon) -> CursorId {
+        let id = CursorId::next();
+        let cursor = unsafe { cursor::create_cursor(&icon) };
+        // Unknown IDs result in the default cursor, which is the closest thing to a fallback here
+        if !cursor.is_null() {
+            self.state.custom_cursors.borrow_mut().insert(id, cursor);
+        }
+
+        id
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.state.id
+    }
+
+    pub fn pressed_keys(&self) -> Vec<keyboard_types::Code> {
+        self.state.held_inputs.borrow().pressed_keys()
+    }
+
+    pub(crate) fn event_clock(&self) -> &EventClock {
+        &self.state.event_clock
+    }
+
+    /// See [`crate::EventContext::set_user_data()`].
+    pub(crate) fn user_data(&self) -> &UserData {
+        &self.state.user_data
+    }
+
+    pub fn with_hwnd<R>(&mut self, f: impl FnOnce(*mut c_void) -> R) -> R {
+        f(self.state.hwnd as *mut c_void)
+    }
+
+    pub fn surface_info(&self) -> SurfaceInfo {
+        #[cfg(feature = "opengl")]
+        if let Some(gl_context) = &self.state.gl_context {
+            return SurfaceInfo::from_gl_pixel_format(gl_context.pixel_format());
+        }
+
+        // Windows that aren't drawn to with OpenGL are drawn through GDI, which doesn't have an
+        // alpha channel
+        let color_depth = unsafe {
+            let hdc = GetDC(self.state.hwnd);
+            let bits_per_pixel = GetDeviceCaps(hdc, BITSPIXEL);
+            ReleaseDC(self.state.hwnd, hdc);
+
+            bits_per_pixel
+        };
+
+        SurfaceInfo {
+            color_depth: color_depth as u8,
+            has_alpha: false,
+            #[cfg(feature = "opengl")]
+            gl: None,
         }
     }
 
@@ -891,21 +2440,18 @@ impl Window<'_> {
     pub fn gl_context(&self) -> Option<&GlContext> {
         self.state.gl_context.as_ref()
     }
-}
 
-unsafe impl HasRawWindowHandle for Window<'_> {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        let mut handle = Win32WindowHandle::empty();
-        handle.hwnd = self.state.hwnd as *mut c_void;
+    #[cfg(feature = "opengl")]
+    pub fn gl_context_error(&self) -> Option<&GlError> {
+        self.state.gl_context_error.as_ref()
+    }
 
-        RawWindowHandle::Win32(handle)
+    pub(crate) fn platform_window_handle(&self) -> PlatformWindowHandle {
+        PlatformWindowHandle::Win32 { hwnd: self.state.hwnd as *mut c_void }
     }
-}
 
-unsafe impl HasRawDisplayHandle for Window<'_> {
-    fn raw_display_handle(&self) -> RawDisplayHandle {
-        let handle = WindowsDisplayHandle::empty();
-        RawDisplayHandle::Windows(handle)
+    pub(crate) fn platform_display_handle(&self) -> PlatformDisplayHandle {
+        PlatformDisplayHandle::Windows
     }
 }
 
@@ -937,3 +2483,68 @@ pub fn cursor_to_windows_cursor(mouse_cursor: MouseCursor) -> PCWSTR {
         _ => IDC_ARROW, // use arrow for the missing cases.
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use winapi::um::winuser::{MK_CONTROL, MK_SHIFT};
+
+    use super::*;
+
+    #[test]
+    fn plain_resize() {
+        let window_info = WindowInfo::from_logical_size(Size::new(400.0, 300.0), 1.5);
+
+        let (new, old) = resized_window_info(window_info, None, PhySize::new(900, 600)).unwrap();
+        assert_eq!(old, window_info);
+        assert_eq!(new.physical_size(), PhySize::new(900, 600));
+        assert_eq!(new.scale(), 1.5);
+
+        assert_eq!(resized_window_info(window_info, None, window_info.physical_size()), None);
+    }
+
+    #[test]
+    fn dpi_change_and_resize_are_combined() {
+        let before = WindowInfo::from_logical_size(Size::new(400.0, 300.0), 1.0);
+        // `handle_dpi_change()` has already applied the new scale factor when `WM_SIZE` arrives
+        let after = WindowInfo::from_logical_size(before.logical_size(), 2.0);
+
+        let (new, old) = resized_window_info(after, Some(before), PhySize::new(800, 600)).unwrap();
+        assert_eq!(old, before);
+        assert_eq!(new, after);
+        assert_eq!(new.logical_size(), old.logical_size());
+    }
+
+    #[test]
+    fn dpi_change_without_a_size_change() {
+        // The window's physical size can stay the same, for instance when the window is resized
+        // at the same time as it's moved to another monitor
+        let before = WindowInfo::from_logical_size(Size::new(400.0, 300.0), 2.0);
+        let after = WindowInfo::from_physical_size(before.physical_size(), 1.0);
+
+        let (new, old) = resized_window_info(after, Some(before), before.physical_size()).unwrap();
+        assert_eq!(old, before);
+        assert_eq!(new, after);
+    }
+
+    #[test]
+    fn wparam_button_masks() {
+        let table = [
+            (MK_LBUTTON, MouseButtonSet::LEFT),
+            (MK_MBUTTON, MouseButtonSet::MIDDLE),
+            (MK_RBUTTON, MouseButtonSet::RIGHT),
+            (MK_XBUTTON1, MouseButtonSet::BACK),
+            (MK_XBUTTON2, MouseButtonSet::FORWARD),
+        ];
+        for (mask, buttons) in table {
+            assert_eq!(mouse_buttons(mask), buttons, "mask {:#x}", mask);
+        }
+
+        assert_eq!(mouse_buttons(0), MouseButtonSet::empty());
+        assert_eq!(
+            mouse_buttons(MK_LBUTTON | MK_XBUTTON2),
+            MouseButtonSet::LEFT | MouseButtonSet::FORWARD
+        );
+        // The modifier flags are ignored
+        assert_eq!(mouse_buttons(MK_SHIFT | MK_CONTROL | MK_RBUTTON), MouseButtonSet::RIGHT);
+    }
+}
@@ -1,29 +1,62 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
 use std::marker::PhantomData;
-
-use raw_window_handle::{
-    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
-};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use crate::event::{Data, Event, EventStatus};
-use crate::window_open_options::WindowOpenOptions;
-use crate::{MouseCursor, Point, Size};
+use crate::metrics::HandleMetrics;
+use crate::platform_handle::PlatformWindowHandle;
+use crate::window_open_options::{is_valid_scale, WindowOpenOptions, WindowScalePolicy};
+use crate::{
+    CursorIcon, CursorId, DialogId, DragImage, DropEffect, Error, FileDialogOptions, MouseCursor,
+    Point, Rect, Region, Size, SurfaceInfo,
+};
 
-#[cfg(target_os = "macos")]
+#[cfg(not(feature = "windowing"))]
+use crate::headless as platform;
+#[cfg(all(feature = "windowing", target_os = "macos"))]
 use crate::macos as platform;
-#[cfg(target_os = "windows")]
+#[cfg(all(feature = "windowing", target_os = "windows"))]
 use crate::win as platform;
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "windowing", target_os = "linux"))]
 use crate::x11 as platform;
 
+/// Identifies a window. Every window opened by the process gets its own ID, which stays the same
+/// for the window's entire lifetime and is never reused. This can be used to tell windows apart
+/// when handlers for several windows feed into shared state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(u64);
+
+impl WindowId {
+    pub(crate) fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        WindowId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// How urgently [`EventContext::request_attention()`] should get the user's attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttentionType {
+    /// Something the user may want to look at, like a render that finished in the background.
+    Informational,
+    /// Something the user needs to act on.
+    Critical,
+}
+
 pub struct WindowHandle {
     window_handle: platform::WindowHandle,
+    metrics: HandleMetrics,
     // so that WindowHandle is !Send on all platforms
     phantom: PhantomData<*mut ()>,
 }
 
 impl WindowHandle {
-    fn new(window_handle: platform::WindowHandle) -> Self {
-        Self { window_handle, phantom: PhantomData::default() }
+    fn new(window_handle: platform::WindowHandle, metrics: HandleMetrics) -> Self {
+        Self { window_handle, metrics, phantom: PhantomData }
     }
 
     /// Close the window
@@ -33,82 +66,439 @@ impl WindowHandle {
 
     /// Returns `true` if the window is still open, and returns `false`
     /// if the window was closed/dropped.
+    ///
+    /// This also returns `false` once a parented window notices that it was destroyed along with
+    /// its parent, for instance when the host destroyed the parent window without closing this
+    /// window first. That happens within a frame interval on every platform.
     pub fn is_open(&self) -> bool {
         self.window_handle.is_open()
     }
+
+    /// The window's ID. This is the same as [`EventContext::id()`] for this window.
+    pub fn id(&self) -> WindowId {
+        self.window_handle.id()
+    }
+
+    /// A handle that can close the window and check whether it's open from any thread. This
+    /// handle can't be sent to other threads since it carries the window's raw window handle,
+    /// which may only be used on the thread that opened the window.
+    pub fn remote(&self) -> RemoteWindowHandle {
+        RemoteWindowHandle {
+            window_handle: self.window_handle.remote(),
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    /// How the window's handler has been keeping up, for instance to find out whether a window
+    /// that stopped responding is stuck in its handler. This keeps working after the window has
+    /// closed. [`RemoteWindowHandle::metrics()`] returns the same from other threads.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> crate::WindowMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Start counting the [`Self::metrics()`] from zero again.
+    #[cfg(feature = "metrics")]
+    pub fn reset_metrics(&self) {
+        self.metrics.reset();
+    }
+
+    /// Draw a frame for a window that was opened with [`FrameSource::External`], if the window
+    /// wants one. This does everything baseview's own frame timer does on every tick, including
+    /// closing the window if that was requested. Nothing happens for windows that use
+    /// [`FrameSource::Internal`], or once the window has been closed.
+    ///
+    /// This has to be called on the thread that opened the window. On Linux the windows run their
+    /// event loops on threads of their own, so there the frame is drawn on the window's thread
+    /// shortly after this returns. On Windows and macOS the frame is drawn before this returns,
+    /// unless the window's handler is currently running.
+    ///
+    /// [`FrameSource::External`]: crate::FrameSource::External
+    /// [`FrameSource::Internal`]: crate::FrameSource::Internal
+    pub fn trigger_frame(&self) {
+        self.window_handle.trigger_frame();
+    }
 }
 
-unsafe impl HasRawWindowHandle for WindowHandle {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        self.window_handle.raw_window_handle()
+impl fmt::Debug for WindowHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowHandle")
+            .field("id", &self.id())
+            .field("handle", &self.window_handle.platform_window_handle())
+            .field("is_open", &self.is_open())
+            .finish()
     }
 }
 
+/// The parts of a [`WindowHandle`] that can be used from any thread, as returned by
+/// [`WindowHandle::remote()`]. This can be cloned, and it stays valid after the window has closed.
+///
+/// How these operations reach the window differs per platform:
+///
+/// - On Linux every window already runs its event loop on a thread of its own. Closing the window
+///   sets a flag that the event loop checks on every iteration, which happens at least every
+///   frame interval.
+/// - On Windows closing the window posts a message to it, which the window handles on the thread
+///   that opened it. Posting messages to a window is allowed from any thread.
+/// - On macOS closing the window sets a flag, and schedules a check of that flag on the main
+///   thread's run loop. The window is closed by removing its view from the parent's view, so it
+///   receives [`WindowEvent::WillClose`](crate::WindowEvent::WillClose) once the host has
+///   released the view as well.
+///
+/// Whether the window is open is tracked through an atomic flag on every platform. Apart from the
+/// metrics with the `metrics` feature, nothing else about the window is available off the
+/// window's thread. Use a [`crate::ChannelHandler`] to send other messages to the window's
+/// handler.
+#[derive(Clone)]
+pub struct RemoteWindowHandle {
+    window_handle: platform::RemoteWindowHandle,
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    metrics: HandleMetrics,
+}
+
+impl RemoteWindowHandle {
+    /// Close the window, like [`WindowHandle::close()`]. This does nothing if the window was
+    /// already closed, and calling it more than once has the same effect as calling it once.
+    pub fn close(&self) {
+        self.window_handle.close();
+    }
+
+    /// Returns `true` until the window has been closed, like [`WindowHandle::is_open()`].
+    pub fn is_open(&self) -> bool {
+        self.window_handle.is_open()
+    }
+
+    /// The window's ID. This is the same as [`EventContext::id()`] for this window.
+    pub fn id(&self) -> WindowId {
+        self.window_handle.id()
+    }
+
+    /// The window's metrics, like [`WindowHandle::metrics()`].
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> crate::WindowMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Start counting the metrics from zero again, like [`WindowHandle::reset_metrics()`].
+    #[cfg(feature = "metrics")]
+    pub fn reset_metrics(&self) {
+        self.metrics.reset();
+    }
+}
+
+impl fmt::Debug for RemoteWindowHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteWindowHandle")
+            .field("id", &self.id())
+            .field("is_open", &self.is_open())
+            .finish()
+    }
+}
+
+// Every platform's handle needs to stay `Send` and `Sync`, since that's the point of this type
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RemoteWindowHandle>();
+};
+
+#[cfg(feature = "rwh_05")]
+unsafe impl rwh_05::HasRawWindowHandle for WindowHandle {
+    fn raw_window_handle(&self) -> rwh_05::RawWindowHandle {
+        PlatformWindowHandle::to_rwh_05(self.window_handle.platform_window_handle())
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl rwh_06::HasWindowHandle for WindowHandle {
+    fn window_handle(&self) -> Result<rwh_06::WindowHandle<'_>, rwh_06::HandleError> {
+        let handle = PlatformWindowHandle::to_rwh_06(self.window_handle.platform_window_handle())?;
+
+        Ok(unsafe { rwh_06::WindowHandle::borrow_raw(handle) })
+    }
+}
+
+/// Receives the window's events and draws its frames.
+///
+/// The handler is never re-entered. While one of these functions is running, baseview won't call
+/// into the same handler again, even if the handler runs a nested native event loop like a modal
+/// file dialog. Frames that would have been drawn in the meantime are skipped. On macOS events that
+/// arrive in the meantime are sent right after the outer call returns, and they're reported to the
+/// platform as [`EventStatus::Ignored`].
+///
+/// Every handler receives at least one [`WindowEvent::Resized`](crate::WindowEvent::Resized) event
+/// before `on_frame` is called for the first time, and
+/// [`WindowEvent::WillClose`](crate::WindowEvent::WillClose) is always the last call. Nothing is
+/// called after it, not even when the window is closed in several ways at once. Debug builds check
+/// this.
 pub trait WindowHandler {
-    fn on_frame(&mut self, window: &mut Window);
-    fn on_event(&mut self, window: &mut Window, event: Event) -> EventStatus;
+    fn on_frame(&mut self, window: &mut EventContext);
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus;
+}
+
+/// A [`WindowHandler`] that produces a value once its window has closed, like the settings the user
+/// picked in a standalone tool. See [`EventContext::open_blocking_with_result()`].
+pub trait WindowHandlerWithResult: WindowHandler {
+    type Output;
+
+    /// Turn the handler into its result. This is called on the thread that opened the window after
+    /// the event loop has exited, so the handler has already received
+    /// [`WindowEvent::WillClose`](crate::WindowEvent::WillClose).
+    fn on_close(self: Box<Self>) -> Self::Output;
 }
 
-pub struct Window<'a> {
-    #[cfg(target_os = "windows")]
+/// The window as seen by the [`WindowHandler`] during a single call. A new context is passed to
+/// every call, and to the `build` closure passed to the `open_*()` functions.
+///
+/// Requests that change the window, [`close()`](Self::close),
+/// [`cancel_close()`](Self::cancel_close), [`resize()`](Self::resize),
+/// [`set_mouse_cursor()`](Self::set_mouse_cursor),
+/// [`set_cursor_confined()`](Self::set_cursor_confined), and
+/// [`start_drag()`](Self::start_drag), are queued and carried out in order once the handler
+/// returns. This way they behave the same on every platform, and the handler is never called again
+/// from within one of these functions. Requests made by a handler that panics are dropped.
+pub struct EventContext<'a> {
+    #[cfg(all(feature = "windowing", target_os = "windows"))]
     pub(crate) window: &'a mut platform::Window<'a>,
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(not(all(feature = "windowing", target_os = "windows")))]
     pub(crate) window: &'a mut platform::Window,
 
-    // so that Window is !Send on all platforms
+    /// The queued requests, see above. This is a `RefCell` because `start_drag()` takes `&self`.
+    tasks: RefCell<Vec<ContextTask>>,
+
+    // so that EventContext is !Send on all platforms
     phantom: PhantomData<*mut ()>,
 }
 
-impl<'a> Window<'a> {
-    #[cfg(target_os = "windows")]
-    pub(crate) fn new(window: &'a mut platform::Window<'a>) -> Window<'a> {
-        Window { window, phantom: PhantomData }
+/// The name [`EventContext`] had before requests were queued.
+#[deprecated(note = "renamed to `EventContext`")]
+pub type Window<'a> = EventContext<'a>;
+
+/// A request queued by an [`EventContext`].
+enum ContextTask {
+    Close,
+    CancelClose,
+    Resize(Size),
+    SetMouseCursor(MouseCursor),
+    SetCursorRegions(Vec<(Rect, MouseCursor)>),
+    SetCursorConfined(bool),
+    SetScalePolicy(WindowScalePolicy),
+    SetScaleOverride(Option<f64>),
+    StartDrag(Data, DropEffect, Option<DragImage>),
+}
+
+impl<'a> EventContext<'a> {
+    #[cfg(all(feature = "windowing", target_os = "windows"))]
+    pub(crate) fn new(window: &'a mut platform::Window<'a>) -> EventContext<'a> {
+        EventContext { window, tasks: RefCell::new(Vec::new()), phantom: PhantomData }
     }
 
-    #[cfg(not(target_os = "windows"))]
-    pub(crate) fn new(window: &mut platform::Window) -> Window {
-        Window { window, phantom: PhantomData }
+    #[cfg(not(all(feature = "windowing", target_os = "windows")))]
+    pub(crate) fn new(window: &mut platform::Window) -> EventContext<'_> {
+        EventContext { window, tasks: RefCell::new(Vec::new()), phantom: PhantomData }
     }
 
+    /// Open a window as a child of `parent`, for instance a plugin host's editor window. This
+    /// returns right away, and the window is driven by its own event loop.
+    ///
+    /// On Windows and macOS `build` is called on the calling thread, and the window's events are
+    /// dispatched by the parent's event loop on that same thread. On Linux the window runs its own
+    /// X11 event loop on a new thread, and `build` is called on that thread. This is why `build`
+    /// needs to be `Send`, even though the handler it returns does not.
+    ///
+    /// The parent can also be another baseview window, by passing its `EventContext`, for
+    /// instance for a panel inside of a plugin's editor. The parent's handler owns the nested
+    /// window's [`WindowHandle`], and it should close the nested window at the latest while
+    /// handling its own [`WindowEvent::WillClose`](crate::WindowEvent::WillClose). Nested windows
+    /// that are still open after that are closed when the parent's native window is destroyed, so
+    /// they receive their `WillClose` event after the parent is already gone. Between the parent
+    /// and the windows nested in it, key presses go to the one that was clicked last, regardless
+    /// of where the mouse cursor is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parent's window handle cannot be used on this platform, or if the window could
+    /// not be created. Use [`EventContext::try_open_parented()`] to handle those cases.
+    #[cfg(feature = "rwh_05")]
     pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
     where
-        P: HasRawWindowHandle,
+        P: rwh_05::HasRawWindowHandle,
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut EventContext) -> H,
+        B: Send + 'static,
+    {
+        Self::try_open_parented(parent, options, build).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// The same as [`EventContext::open_parented()`], but this returns an error instead of
+    /// panicking when the parent's window handle is for another platform or windowing system, or
+    /// when it's a null handle. On Windows this also returns an error if the window could not be
+    /// created, for instance because the parent window no longer exists. `build` is not called in
+    /// those cases. On Linux `build` runs on the window's own thread, so if it panics while
+    /// [`WindowOpenOptions::on_handler_panic`] is [`PanicPolicy::CloseWindow`], the window is
+    /// closed again and this returns [`Error::WindowCreationFailed`].
+    ///
+    /// [`PanicPolicy::CloseWindow`]: crate::PanicPolicy::CloseWindow
+    #[cfg(feature = "rwh_05")]
+    pub fn try_open_parented<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, Error>
+    where
+        P: rwh_05::HasRawWindowHandle,
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut EventContext) -> H,
+        B: Send + 'static,
+    {
+        let parent = PlatformWindowHandle::from_rwh_05(parent.raw_window_handle())?;
+
+        let (build, metrics) = crate::metrics::measure(build);
+        let window_handle = platform::Window::open_parented(parent, options, build)?;
+        Ok(WindowHandle::new(window_handle, metrics))
+    }
+
+    /// The same as [`EventContext::open_parented()`], but for parents that implement the traits
+    /// from version 0.6 of `raw-window-handle`.
+    #[cfg(feature = "rwh_06")]
+    pub fn open_parented_rwh_06<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> WindowHandle
+    where
+        P: rwh_06::HasWindowHandle,
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut EventContext) -> H,
+        B: Send + 'static,
+    {
+        Self::try_open_parented_rwh_06(parent, options, build)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// The same as [`EventContext::try_open_parented()`], but for parents that implement the traits
+    /// from version 0.6 of `raw-window-handle`.
+    #[cfg(feature = "rwh_06")]
+    pub fn try_open_parented_rwh_06<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, Error>
+    where
+        P: rwh_06::HasWindowHandle,
         H: WindowHandler + 'static,
-        B: FnOnce(&mut Window) -> H,
+        B: FnOnce(&mut EventContext) -> H,
         B: Send + 'static,
     {
-        let window_handle = platform::Window::open_parented::<P, H, B>(parent, options, build);
-        WindowHandle::new(window_handle)
+        let raw_handle =
+            parent.window_handle().map_err(|_| Error::ParentHandleUnavailable)?.as_raw();
+        let parent = PlatformWindowHandle::from_rwh_06(raw_handle)?;
+
+        let (build, metrics) = crate::metrics::measure(build);
+        let window_handle = platform::Window::open_parented(parent, options, build)?;
+        Ok(WindowHandle::new(window_handle, metrics))
     }
 
+    /// Open a window that behaves like a parented window without having a parent. The threading
+    /// model is the same as for [`EventContext::open_parented()`]. Panics if the window could not
+    /// be created.
     pub fn open_as_if_parented<H, B>(options: WindowOpenOptions, build: B) -> WindowHandle
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut Window) -> H,
+        B: FnOnce(&mut EventContext) -> H,
         B: Send + 'static,
     {
-        let window_handle = platform::Window::open_as_if_parented::<H, B>(options, build);
-        WindowHandle::new(window_handle)
+        let (build, metrics) = crate::metrics::measure(build);
+        let window_handle = platform::Window::open_as_if_parented(options, build);
+        WindowHandle::new(window_handle, metrics)
     }
 
+    /// Open a standalone window and run its event loop until the window is closed. On every
+    /// platform both `build` and the handler run on the calling thread, so neither of them needs to
+    /// be `Send`. On macOS this needs to be called from the main thread. Panics if the window could
+    /// not be created.
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut EventContext) -> H,
+        B: 'static,
     {
         platform::Window::open_blocking::<H, B>(options, build)
     }
 
-    /// Close the window
+    /// The same as [`Self::open_blocking()`], but once the window has closed the handler is turned
+    /// into a value through [`WindowHandlerWithResult::on_close()`], which is then returned.
+    /// Returns `None` if the handler panicked, since its state can't be trusted anymore at that
+    /// point.
+    pub fn open_blocking_with_result<H, B>(
+        options: WindowOpenOptions, build: B,
+    ) -> Option<H::Output>
+    where
+        H: WindowHandlerWithResult + 'static,
+        B: FnOnce(&mut EventContext) -> H,
+        B: 'static,
+    {
+        let closed_handler = Rc::new(RefCell::new(None));
+        let slot = closed_handler.clone();
+        Self::open_blocking(options, move |window| ResultHandler {
+            handler: Some(Box::new(build(window))),
+            handler_active: false,
+            closed_handler: slot,
+        });
+
+        let handler = closed_handler.borrow_mut().take()?;
+        Some(handler.on_close())
+    }
+
+    /// Close the window. The window isn't closed right away. This happens once the handler returns
+    /// control to the event loop, at which point the handler receives a final
+    /// [`WindowEvent::WillClose`](crate::WindowEvent::WillClose) event. Until then the request can
+    /// still be withdrawn with [`Self::cancel_close()`].
     pub fn close(&mut self) {
-        self.window.close();
+        self.tasks.get_mut().push(ContextTask::Close);
+    }
+
+    /// Withdraw an earlier call to [`Self::close()`] that hasn't been acted on yet. This does
+    /// nothing if the window wasn't about to close. Only the last of these two calls counts, so
+    /// calling `close()` again afterwards closes the window after all. Once the handler has
+    /// received [`WindowEvent::WillClose`](crate::WindowEvent::WillClose) the window will be
+    /// closed regardless. This also can't cancel closes that weren't requested through
+    /// [`Self::close()`], like [`WindowHandle::close()`] or the parent dropping the handle.
+    pub fn cancel_close(&mut self) {
+        self.tasks.get_mut().push(ContextTask::CancelClose);
     }
 
     /// Resize the window to the given size. The size is always in logical pixels. DPI scaling will
     /// automatically be accounted for.
+    ///
+    /// This also works for windows that were opened with
+    /// [`WindowOpenOptions::resizable`](crate::WindowOpenOptions::resizable) disabled. The user
+    /// still can't resize those, and on Linux their size hints are updated to pin them to the new
+    /// size. If the size changes, the handler receives exactly one
+    /// [`WindowEvent::Resized`](crate::WindowEvent::Resized) event for it. On Linux the window
+    /// manager has the final say, so the window may end up with a different size or not be
+    /// resized at all.
     pub fn resize(&mut self, size: Size) {
-        self.window.resize(size);
+        self.tasks.get_mut().push(ContextTask::Resize(size));
+    }
+
+    /// Change the window's dpi scaling policy, see
+    /// [`WindowOpenOptions::scale`](crate::WindowOpenOptions::scale). The window keeps its physical
+    /// size, so its logical size changes along with the scale factor. Once the handler returns it
+    /// receives a [`WindowEvent::Resized`](crate::WindowEvent::Resized) event with the new window
+    /// info, and every logical position and size after that uses the new scale factor. Nothing
+    /// happens if the scale factor stays the same. While an override set with
+    /// [`Self::set_scale_override()`] is active the new policy only takes effect once the override
+    /// is removed.
+    ///
+    /// Switching back to [`WindowScalePolicy::SystemScaleFactor`] picks up the system's current
+    /// scale factor, and the window follows the system's scale factor changes again from then on.
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.tasks.get_mut().push(ContextTask::SetScalePolicy(policy.sanitized()));
+    }
+
+    /// Use `scale` as the window's scale factor regardless of its scaling policy, or go back to the
+    /// policy's scale factor with `None`. This is meant for UI scale settings that should apply on
+    /// top of the system's dpi scaling, like a plugin editor's zoom slider. The override is applied
+    /// the same way as [`Self::set_scale_policy()`], and the window doesn't follow the system's
+    /// scale factor while it's active. Overrides that aren't positive finite numbers are treated as
+    /// `None`.
+    pub fn set_scale_override(&mut self, scale: Option<f64>) {
+        let scale = scale.filter(|&scale| is_valid_scale(scale));
+        self.tasks.get_mut().push(ContextTask::SetScaleOverride(scale));
     }
 
     /// Set the position of the window. The position is always in logical pixels. DPI scaling will
@@ -117,30 +507,494 @@ impl<'a> Window<'a> {
         self.window.set_position(position);
     }
 
-    pub fn start_drag(&self, data: Data) {
-        self.window.start_drag(data);
+    /// Start dragging `data` out of the window. This should be called while a mouse button is held
+    /// down, and the drag ends when the button is released. `effect` tells the target whether the
+    /// data should be copied or moved. While the drag is in progress the handler receives
+    /// [`WindowEvent::DragSourceMoved`][crate::WindowEvent::DragSourceMoved] events instead of
+    /// mouse events, and a [`WindowEvent::DragSourceEnded`][crate::WindowEvent::DragSourceEnded]
+    /// event with the effect the target chose once the drag is over.
+    ///
+    /// `image` is shown under the cursor during the drag, for instance a thumbnail of what's being
+    /// dragged. Without an image, the platform's default drag feedback is shown instead.
+    pub fn start_drag(&self, data: Data, effect: DropEffect, image: Option<DragImage>) {
+        self.tasks.borrow_mut().push(ContextTask::StartDrag(data, effect, image));
+    }
+
+    /// Choose what happens when the data that's being dragged over the window is dropped, or reject
+    /// the drop by passing `None`. This is meant to be called in response to
+    /// [`WindowEvent::DragEnter`][crate::WindowEvent::DragEnter] and
+    /// [`WindowEvent::Dragging`][crate::WindowEvent::Dragging] events, and the choice is shown to
+    /// the user through the cursor. It's kept until the drag leaves the window or is dropped.
+    ///
+    /// Effects the drag source doesn't allow reject the drop. Drops are also rejected while
+    /// [`WindowOpenOptions::drop_target_valid`] returns `false`.
+    pub fn set_drop_effect(&mut self, effect: Option<DropEffect>) {
+        self.window.set_drop_effect(effect);
+    }
+
+    /// The effect that dropping the data that's being dragged over the window will have, or `None`
+    /// if the drop will be rejected. Unless it's changed with [`Self::set_drop_effect()`], this is
+    /// what the drag source asked for. On Windows the modifier keys can change that, with Control
+    /// meaning copy and Shift meaning move. During [`WindowEvent::Drop`][crate::WindowEvent::Drop]
+    /// events this is the effect the drop has.
+    pub fn drop_effect(&self) -> Option<DropEffect> {
+        self.window.drop_effect()
+    }
+
+    /// Whether the window supports drag and drop. This is `false` if it was disabled through
+    /// [`WindowOpenOptions::enable_drag_and_drop`], or if the platform's drag and drop support
+    /// could not be initialized. On Windows that happens when another library already initialized
+    /// COM on the window's thread using the multithreaded apartment model. On Linux it happens when
+    /// the X server doesn't support the atoms needed for XDND. Everything else keeps working
+    /// normally in that case, but the window won't receive drops and
+    /// [`Self::start_drag()`] does nothing.
+    pub fn drag_and_drop_available(&self) -> bool {
+        self.window.drag_and_drop_available()
+    }
+
+    /// Whether the window is visible right now, as described by
+    /// [`WindowEvent::Shown`](crate::WindowEvent::Shown). Unlike those events this is also
+    /// correct for state changes that happened before the handler was created.
+    ///
+    /// On Linux and Windows this is tracked from the platform's events, so it always agrees with
+    /// the last `Shown` or `Hidden` event, and it can lag slightly behind the window's actual
+    /// state until the next event is handled. macOS queries the view directly, so while a change's
+    /// event is still on its way this can already differ from the last event.
+    pub fn is_visible(&self) -> bool {
+        self.window.is_visible()
+    }
+
+    /// Whether the window is minimized. Parented windows are considered minimized when the
+    /// top-level window they're part of is minimized.
+    ///
+    /// On Linux this checks for the window manager's `_NET_WM_STATE_HIDDEN` state, which is
+    /// tracked from the X server's events for windows that aren't parented. For parented windows
+    /// this takes a few round trips to the X server to find and query the host's window, so it
+    /// shouldn't be called on every frame. This is always `false` with window managers that don't
+    /// set that state. Windows and macOS query the window directly.
+    pub fn is_minimized(&self) -> bool {
+        self.window.is_minimized()
+    }
+
+    /// Whether the window has the keyboard focus, as described by
+    /// [`WindowEvent::Focused`](crate::WindowEvent::Focused). On Linux this is tracked from the X
+    /// server's focus events and has the same staleness as [`Self::is_visible()`]. Windows and
+    /// macOS query the focus directly. On macOS the view has the focus while its window is the key
+    /// window.
+    pub fn has_focus(&self) -> bool {
+        self.window.has_focus()
+    }
+
+    /// Whether the user can resize the window, as set through
+    /// [`WindowOpenOptions::resizable`]. This is always `false` for parented windows and popups,
+    /// whose size is controlled by the host and the handler. The window can still be resized
+    /// through [`Self::resize()`] either way.
+    pub fn is_resizable(&self) -> bool {
+        self.window.is_resizable()
     }
 
     pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
-        self.window.set_mouse_cursor(mouse_cursor);
+        self.tasks.get_mut().push(ContextTask::SetMouseCursor(mouse_cursor));
+    }
+
+    /// Show different cursors over different areas of the window, for instance a text cursor over
+    /// a text field and resize cursors over a splitter, without calling
+    /// [`Self::set_mouse_cursor()`] every time the pointer moves. The regions are in logical
+    /// coordinates, and where they overlap the later ones take precedence. The default cursor is
+    /// shown outside of all regions. baseview updates the cursor as the pointer moves, enters, and
+    /// leaves the window, without involving the handler.
+    ///
+    /// A cursor set through [`Self::set_mouse_cursor()`] takes precedence over the regions until
+    /// it's set back to [`MouseCursor::Default`]. Calling this again replaces all regions, and an
+    /// empty slice removes them.
+    pub fn set_cursor_regions(&mut self, regions: &[(Rect, MouseCursor)]) {
+        self.tasks.get_mut().push(ContextTask::SetCursorRegions(regions.to_vec()));
+    }
+
+    /// Keep the mouse cursor inside the window, for instance while the user drags the handle of an
+    /// XY pad. Unlike a pointer lock the cursor stays visible and keeps moving, it just can't leave
+    /// the window. The cursor is released again when this is called with `false`, when the window
+    /// loses the focus or closes, and when a drag is started with [`Self::start_drag()`].
+    ///
+    /// - On Windows this uses `ClipCursor()`, and the clip is updated when the window moves or is
+    ///   resized.
+    /// - On Linux this grabs the pointer, which fails if another client has already grabbed it. The
+    ///   cursor isn't confined in that case.
+    /// - On macOS there's no way to confine the cursor, so it's moved back into the window when it
+    ///   leaves. This is best-effort. AppKit only reports mouse movement outside of the window
+    ///   while a mouse button is held down, so a fast movement without a button held down can
+    ///   still escape.
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        self.tasks.get_mut().push(ContextTask::SetCursorConfined(confined));
+    }
+
+    /// Create a custom cursor from an image. The returned ID can be passed to
+    /// [`Self::set_mouse_cursor()`] through [`MouseCursor::Custom`] as often as needed. The
+    /// cursor stays valid until the window is closed, at which point its resources are freed.
+    ///
+    /// On macOS the image is scaled down by the window's scale factor at the time the cursor is
+    /// registered, so it has the same size in physical pixels as on the other platforms.
+    pub fn register_cursor(&mut self, icon: CursorIcon) -> CursorId {
+        self.window.register_cursor(icon)
+    }
+
+    /// Control whether [`WindowHandler::on_frame`] should be called continuously. Windows are
+    /// animating by default. When this is set to `false` the frame timer is stopped and the window
+    /// becomes purely event driven: `on_frame` will only be called once after events have been
+    /// delivered to the handler so the UI can respond to them. Set this back to `true` to resume
+    /// continuous frames.
+    pub fn set_animating(&mut self, animating: bool) {
+        self.window.set_animating(animating);
+    }
+
+    /// Change the time between two calls to [`WindowHandler::on_frame`]. This defaults to 15
+    /// milliseconds, and it can be changed at any time, including from within `on_event` and
+    /// `on_frame`. The new interval is used starting from the next frame. Windows does not support
+    /// intervals shorter than 10 milliseconds, and intervals are rounded down to whole milliseconds
+    /// there.
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.window.set_frame_interval(interval);
+    }
+
+    /// Stop calling [`WindowHandler::on_frame`] altogether, for instance while the handler has a
+    /// modal dialog open. Unlike [`EventContext::set_animating`], this also suppresses the frame
+    /// that's normally drawn after handling events. Events are still delivered while frames are
+    /// paused.
+    ///
+    /// Pausing frames doesn't drop a pending frame. If a frame was requested before or while the
+    /// frames were paused, either because the window is animating or because events were handled,
+    /// then the next frame is drawn right after unpausing. Interval changes made while paused take
+    /// effect as soon as the frames are unpaused.
+    pub fn pause_frames(&mut self, paused: bool) {
+        self.window.pause_frames(paused);
+    }
+
+    /// Mark part of the window as needing to be redrawn. The rectangle is in logical coordinates.
+    /// This makes sure a frame gets drawn, even if the window isn't animating. The rectangle is
+    /// included in the next frame's [`Self::damage()`], so rectangles that are invalidated while
+    /// drawing a frame belong to the frame after it.
+    pub fn invalidate_rect(&mut self, rect: Rect) {
+        self.window.invalidate_rect(rect);
+    }
+
+    /// The parts of the window that need to be redrawn in the frame that's currently being drawn,
+    /// meant to be called from [`WindowHandler::on_frame()`]. Renderers that support partial
+    /// redraws can limit drawing to this region.
+    ///
+    /// This combines the rectangles passed to [`Self::invalidate_rect()`] since the last frame with
+    /// the areas the system asked to redraw. On Linux those come from `Expose` events. The entire
+    /// window is damaged in the first frame and after the window has been resized.
+    ///
+    /// The region is empty when nothing was invalidated, for instance when the frame is only drawn
+    /// because the window is animating or because input arrived. Handlers that don't keep track of
+    /// what changed should simply keep redrawing everything.
+    pub fn damage(&self) -> Region {
+        self.window.damage()
+    }
+
+    /// This window's ID. Every call to the [`WindowHandler`] receives the `EventContext` it belongs
+    /// to, so this identifies the window any event came from.
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// The physical keys that are held down, in the order they were pressed in. These are the keys
+    /// the handler received key down events for without receiving the matching key up events yet,
+    /// so together with the `buttons` of a [`MouseEvent`](crate::MouseEvent) this describes the
+    /// full chord at any event. Keys that were held down when the window got the focus aren't
+    /// included.
+    pub fn pressed_keys(&self) -> Vec<keyboard_types::Code> {
+        self.window.pressed_keys()
+    }
+
+    /// When the event that's currently being handled happened. Timestamps are measured from when
+    /// the window was opened, they use the platform's own timestamps for native input events, and
+    /// they never go backwards. Events that don't come from a native event, like the ones baseview
+    /// synthesizes itself, get the current time. Outside of the handler's `on_event()` this is
+    /// the current time.
+    pub fn event_time(&self) -> Duration {
+        self.window.event_clock().event_time()
+    }
+
+    /// The current time on the same clock as [`Self::event_time()`]. This can be slightly earlier
+    /// than the last event's timestamp.
+    pub fn now(&self) -> Duration {
+        self.window.event_clock().now()
+    }
+
+    /// The [`Self::event_time()`] of the last
+    /// [`MouseEvent::ButtonPressed`](crate::MouseEvent::ButtonPressed) the handler received, for
+    /// instance to detect double clicks.
+    pub fn last_mouse_down_time(&self) -> Option<Duration> {
+        self.window.event_clock().last_mouse_down()
+    }
+
+    /// Ask for the user's attention in the platform's usual way, for instance after finishing a long
+    /// task while the window is in the background. This is ignored if the window already has the
+    /// user's attention. Parented windows don't show up in the taskbar on their own, so the request
+    /// applies to the top-level window they're part of instead.
+    ///
+    /// - On Linux this sets `_NET_WM_STATE_DEMANDS_ATTENTION`, which most window managers show by
+    ///   highlighting the window in the taskbar until it gets focused. Critical requests also ring
+    ///   the X server's bell.
+    /// - On Windows the taskbar button flashes. Informational requests flash it three times,
+    ///   critical requests flash both the button and the title bar until the window comes to the
+    ///   foreground.
+    /// - On macOS the application's Dock icon bounces. Informational requests bounce it once,
+    ///   critical requests keep bouncing until the application becomes active. For plugins this is
+    ///   the host's Dock icon.
+    pub fn request_attention(&mut self, attention: AttentionType) {
+        self.window.request_attention(attention);
+    }
+
+    /// Cancel the last [`Self::request_attention()`] request, if it's still active.
+    pub fn stop_attention(&mut self) {
+        self.window.stop_attention();
+    }
+
+    /// Set the opacity of the entire window, from fully transparent at `0.0` to fully opaque at
+    /// `1.0`. Values outside of that range are clamped. This applies on top of the alpha channel of
+    /// whatever is drawn in the window.
+    ///
+    /// Parented windows can only be made transparent on macOS, where the opacity is applied to the
+    /// view. The other platforms return [`Error::Unsupported`] for parented windows. On Linux the
+    /// opacity requires a compositing window manager, and [`Error::Unsupported`] is also returned
+    /// if the X server doesn't know about the `_NET_WM_WINDOW_OPACITY` property.
+    pub fn set_opacity(&mut self, opacity: f64) -> Result<(), Error> {
+        self.window.set_opacity(opacity.clamp(0.0, 1.0))
+    }
+
+    /// Show the platform's dialog for opening or saving files, with this window as its owner. This
+    /// returns right away, and the handler receives an [`Event::Dialog`](crate::Event::Dialog)
+    /// with the returned ID and the paths the user picked once the dialog has been closed.
+    ///
+    /// - On Windows this uses `IFileOpenDialog` and `IFileSaveDialog`. The dialog is shown after
+    ///   the handler returns, and it runs a modal loop that blocks the window's owner until it's
+    ///   closed. The window keeps receiving events and frames in the meantime.
+    /// - On macOS this uses `NSOpenPanel` and `NSSavePanel`. Windows opened with
+    ///   [`EventContext::open_blocking()`] get the panel as a sheet. Parented windows live in the
+    ///   host's window, so they get a separate panel instead.
+    /// - Linux doesn't have a native file dialog, so this returns [`Error::Unsupported`].
+    pub fn open_file_dialog(&mut self, options: FileDialogOptions) -> Result<DialogId, Error> {
+        self.window.open_file_dialog(options)
+    }
+
+    /// Attach a value to this window, for instance the application's shared state, so it can be
+    /// accessed from anywhere the window is available. A window stores at most one value of every
+    /// type, and setting a value replaces the previous value of that type.
+    ///
+    /// The values live as long as the native window does. They are dropped when the window is
+    /// destroyed, which happens after the handler has received [`WindowEvent::WillClose`].
+    ///
+    /// [`WindowEvent::WillClose`]: crate::WindowEvent::WillClose
+    pub fn set_user_data<T: Any>(&mut self, value: T) {
+        self.window.user_data().set(value);
+    }
+
+    /// Get the value of type `T` attached through [`EventContext::set_user_data()`], if there is
+    /// one. The value is reference counted, so the window can still be used while holding on to it.
+    pub fn user_data<T: Any>(&self) -> Option<Rc<T>> {
+        self.window.user_data().get()
+    }
+
+    /// Describe the surface the window was created with, so a renderer can match its color format
+    /// and alpha handling. This is already available in the `build` closure passed to the
+    /// `open_*()` functions, and it doesn't change while the window is open.
+    pub fn surface_info(&self) -> SurfaceInfo {
+        self.window.surface_info()
+    }
+
+    /// Call `f` with the X11 connection the window uses and the window's X11 ID, for making
+    /// requests baseview doesn't make itself. The closure runs right away on the window's thread.
+    ///
+    /// The connection must not be used to wait for or poll events, and `f` must not open or close
+    /// windows. Don't hold on to the connection's raw pointers after `f` returns.
+    #[cfg(all(feature = "windowing", target_os = "linux"))]
+    pub fn with_x11_connection<R>(
+        &mut self, f: impl FnOnce(&crate::platform::x11::X11Connection, u32) -> R,
+    ) -> R {
+        self.window.with_x11_connection(f)
+    }
+
+    /// Set a custom property on the window's X11 window, or replace its value. The atoms for the
+    /// name and the value are created if they don't exist yet. See
+    /// [`WindowOpenOptions::x11_properties`](crate::WindowOpenOptions::x11_properties) for setting
+    /// properties before the window is mapped.
+    #[cfg(all(feature = "windowing", target_os = "linux"))]
+    pub fn set_x11_property(&mut self, name: &str, value: crate::X11PropertyValue) {
+        self.window.set_x11_property(name, value);
+    }
+
+    /// Call `f` with the window's `HWND`, for making calls baseview doesn't make itself. The
+    /// closure runs right away on the window's thread.
+    ///
+    /// `f` must not destroy the window, run a message loop that dispatches the window's messages,
+    /// or replace its window procedure. Don't hold on to the handle after `f` returns, since it's
+    /// reused once the window has been destroyed.
+    #[cfg(all(feature = "windowing", target_os = "windows"))]
+    pub fn with_hwnd<R>(&mut self, f: impl FnOnce(*mut std::ffi::c_void) -> R) -> R {
+        self.window.with_hwnd(f)
+    }
+
+    /// Call `f` with the window's `NSView`, for making calls baseview doesn't make itself. The
+    /// closure runs right away on the main thread.
+    ///
+    /// `f` must not release the view or remove it from its superview. Don't hold on to the pointer
+    /// after `f` returns without retaining the view first.
+    #[cfg(all(feature = "windowing", target_os = "macos"))]
+    pub fn with_ns_view<R>(&mut self, f: impl FnOnce(*mut std::ffi::c_void) -> R) -> R {
+        self.window.with_ns_view(f)
     }
 
     /// If provided, then an OpenGL context will be created for this window. You'll be able to
-    /// access this context through [crate::Window::gl_context].
+    /// access this context through [crate::EventContext::gl_context].
+    ///
+    /// The context already exists in the `build` closure passed to the `open_*()` functions, and
+    /// it's current while `build` runs, with a viewport that covers the window's physical size.
+    /// This way the handler can compile its shaders while it's being built. The context that was
+    /// current before, like a host's own context, is made current again once `build` returns. On
+    /// macOS the view is only attached to its window after `build`, so resources can be created
+    /// during `build`, but drawing to the default framebuffer only works from the first frame on.
+    ///
+    /// The context stays valid until the handler has received
+    /// [`WindowEvent::WillClose`](crate::WindowEvent::WillClose), and it's current while that
+    /// event is handled.
     #[cfg(feature = "opengl")]
     pub fn gl_context(&self) -> Option<&crate::gl::GlContext> {
         self.window.gl_context()
     }
+
+    /// Why the window doesn't have an OpenGL context even though
+    /// [`WindowOpenOptions::gl_config`](crate::WindowOpenOptions::gl_config) asked for one. This is
+    /// only possible when [`GlConfig::fallback_to_no_gl`](crate::gl::GlConfig::fallback_to_no_gl)
+    /// is enabled, otherwise failing to create the context panics.
+    #[cfg(feature = "opengl")]
+    pub fn gl_context_error(&self) -> Option<&crate::gl::GlError> {
+        self.window.gl_context_error()
+    }
+
+    /// Update the accessibility tree of a window opened with [`WindowOpenOptions::accesskit()`].
+    /// The closure is only called while assistive technology is inspecting the window, so there's
+    /// no cost to building the update otherwise. The first update after an
+    /// [`AccessKitEvent::InitialTreeRequested`] event must contain the full tree.
+    ///
+    /// This does nothing for windows that were opened without AccessKit.
+    ///
+    /// [`AccessKitEvent::InitialTreeRequested`]: crate::AccessKitEvent::InitialTreeRequested
+    #[cfg(feature = "accesskit")]
+    pub fn update_accesskit_tree(&mut self, update: impl FnOnce() -> accesskit::TreeUpdate) {
+        self.window.update_accesskit_tree(update);
+    }
+}
+
+impl<'a> fmt::Debug for EventContext<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventContext")
+            .field("id", &self.id())
+            .field("handle", &self.window.platform_window_handle())
+            .finish()
+    }
+}
+
+impl<'a> Drop for EventContext<'a> {
+    fn drop(&mut self) {
+        // The handler didn't finish what it was doing, so its requests may not make sense
+        if std::thread::panicking() {
+            return;
+        }
+
+        for task in self.tasks.get_mut().drain(..) {
+            match task {
+                ContextTask::Close => self.window.close(),
+                ContextTask::CancelClose => self.window.cancel_close(),
+                ContextTask::Resize(size) => self.window.resize(size),
+                ContextTask::SetMouseCursor(cursor) => self.window.set_mouse_cursor(cursor),
+                ContextTask::SetCursorRegions(regions) => self.window.set_cursor_regions(regions),
+                ContextTask::SetCursorConfined(confined) => {
+                    self.window.set_cursor_confined(confined)
+                }
+                ContextTask::SetScalePolicy(policy) => self.window.set_scale_policy(policy),
+                ContextTask::SetScaleOverride(scale) => self.window.set_scale_override(scale),
+                ContextTask::StartDrag(data, effect, image) => {
+                    // The drag takes over the cursor
+                    self.window.set_cursor_confined(false);
+                    self.window.start_drag(data, effect, image);
+                }
+            }
+        }
+    }
 }
 
-unsafe impl<'a> HasRawWindowHandle for Window<'a> {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        self.window.raw_window_handle()
+#[cfg(feature = "rwh_05")]
+unsafe impl<'a> rwh_05::HasRawWindowHandle for EventContext<'a> {
+    fn raw_window_handle(&self) -> rwh_05::RawWindowHandle {
+        PlatformWindowHandle::to_rwh_05(Some(self.window.platform_window_handle()))
+    }
+}
+
+#[cfg(feature = "rwh_05")]
+unsafe impl<'a> rwh_05::HasRawDisplayHandle for EventContext<'a> {
+    fn raw_display_handle(&self) -> rwh_05::RawDisplayHandle {
+        self.window.platform_display_handle().to_rwh_05()
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl<'a> rwh_06::HasWindowHandle for EventContext<'a> {
+    fn window_handle(&self) -> Result<rwh_06::WindowHandle<'_>, rwh_06::HandleError> {
+        let handle = PlatformWindowHandle::to_rwh_06(Some(self.window.platform_window_handle()))?;
+
+        Ok(unsafe { rwh_06::WindowHandle::borrow_raw(handle) })
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl<'a> rwh_06::HasDisplayHandle for EventContext<'a> {
+    fn display_handle(&self) -> Result<rwh_06::DisplayHandle<'_>, rwh_06::HandleError> {
+        let handle = self.window.platform_display_handle().to_rwh_06();
+
+        Ok(unsafe { rwh_06::DisplayHandle::borrow_raw(handle) })
+    }
+}
+
+/// Wraps the handler for [`EventContext::open_blocking_with_result()`]. Every platform drops the
+/// handler on the calling thread before its blocking event loop returns, at which point the handler
+/// is moved to `closed_handler` so the result can be computed outside of the event loop.
+struct ResultHandler<H> {
+    handler: Option<Box<H>>,
+    /// Set while the handler is being called. If this is still set when the wrapper is dropped, the
+    /// handler panicked.
+    handler_active: bool,
+    closed_handler: Rc<RefCell<Option<Box<H>>>>,
+}
+
+impl<H> ResultHandler<H> {
+    fn call<R>(&mut self, f: impl FnOnce(&mut H) -> R) -> R {
+        // The handler is only taken out when the wrapper is dropped
+        let handler = self.handler.as_mut().unwrap();
+
+        self.handler_active = true;
+        let result = f(handler);
+        self.handler_active = false;
+
+        result
+    }
+}
+
+impl<H: WindowHandler> WindowHandler for ResultHandler<H> {
+    fn on_frame(&mut self, window: &mut EventContext) {
+        self.call(|handler| handler.on_frame(window))
+    }
+
+    fn on_event(&mut self, window: &mut EventContext, event: Event) -> EventStatus {
+        self.call(|handler| handler.on_event(window, event))
     }
 }
 
-unsafe impl<'a> HasRawDisplayHandle for Window<'a> {
-    fn raw_display_handle(&self) -> RawDisplayHandle {
-        self.window.raw_display_handle()
+impl<H> Drop for ResultHandler<H> {
+    fn drop(&mut self) {
+        if !self.handler_active {
+            *self.closed_handler.borrow_mut() = self.handler.take();
+        }
     }
 }
@@ -1,33 +1,84 @@
+use std::fmt;
+use std::num::TryFromIntError;
+
 /// The info about the window
-#[derive(Debug, Copy, Clone)]
+///
+/// With the `serde` feature this is serialized as its `logical_size`, `physical_size`, `scale_x`
+/// and `scale_y`. Deserializing fails if either scale factor isn't positive and finite.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerializedWindowInfo", try_from = "SerializedWindowInfo")
+)]
 pub struct WindowInfo {
     logical_size: Size,
     physical_size: PhySize,
-    scale: f64,
-    scale_recip: f64,
+    scale_x: f64,
+    scale_y: f64,
+    scale_x_recip: f64,
+    scale_y_recip: f64,
 }
 
 impl WindowInfo {
+    /// Create the window info from a logical size and a scale factor that applies to both axes.
+    ///
+    /// The scale factor needs to be positive and finite. In debug builds any other value causes a
+    /// panic, and in release builds it is replaced by a scale factor of 1.0.
     pub fn from_logical_size(logical_size: Size, scale: f64) -> Self {
-        let scale_recip = if scale == 1.0 { 1.0 } else { 1.0 / scale };
+        Self::from_logical_size_non_uniform(logical_size, scale, scale)
+    }
+
+    /// The same as [`Self::from_logical_size()`], but with separate horizontal and vertical scale
+    /// factors. This is only needed for displays that don't have square pixels.
+    pub fn from_logical_size_non_uniform(logical_size: Size, scale_x: f64, scale_y: f64) -> Self {
+        let (scale_x, scale_y) = (validate_scale(scale_x), validate_scale(scale_y));
 
         let physical_size = PhySize {
-            width: (logical_size.width * scale).round() as u32,
-            height: (logical_size.height * scale).round() as u32,
+            width: (logical_size.width * scale_x).round() as u32,
+            height: (logical_size.height * scale_y).round() as u32,
         };
 
-        Self { logical_size, physical_size, scale, scale_recip }
+        Self {
+            logical_size,
+            physical_size,
+            scale_x,
+            scale_y,
+            scale_x_recip: recip(scale_x),
+            scale_y_recip: recip(scale_y),
+        }
     }
 
+    /// Create the window info from a physical size and a scale factor that applies to both axes.
+    /// The scale factor is validated the same way as in [`Self::from_logical_size()`].
     pub fn from_physical_size(physical_size: PhySize, scale: f64) -> Self {
-        let scale_recip = if scale == 1.0 { 1.0 } else { 1.0 / scale };
+        Self::from_physical_size_non_uniform(physical_size, scale, scale)
+    }
+
+    /// The same as [`Self::from_physical_size()`], but with separate horizontal and vertical scale
+    /// factors.
+    pub fn from_physical_size_non_uniform(
+        physical_size: PhySize, scale_x: f64, scale_y: f64,
+    ) -> Self {
+        let (scale_x, scale_y) = (validate_scale(scale_x), validate_scale(scale_y));
+        let (scale_x_recip, scale_y_recip) = (recip(scale_x), recip(scale_y));
 
         let logical_size = Size {
-            width: f64::from(physical_size.width) * scale_recip,
-            height: f64::from(physical_size.height) * scale_recip,
+            width: f64::from(physical_size.width) * scale_x_recip,
+            height: f64::from(physical_size.height) * scale_y_recip,
         };
 
-        Self { logical_size, physical_size, scale, scale_recip }
+        Self { logical_size, physical_size, scale_x, scale_y, scale_x_recip, scale_y_recip }
+    }
+
+    /// The same window info with a new logical size, keeping the current scale factors.
+    pub fn with_logical_size(&self, logical_size: Size) -> Self {
+        Self::from_logical_size_non_uniform(logical_size, self.scale_x, self.scale_y)
+    }
+
+    /// The same window info with a new physical size, keeping the current scale factors.
+    pub fn with_physical_size(&self, physical_size: PhySize) -> Self {
+        Self::from_physical_size_non_uniform(physical_size, self.scale_x, self.scale_y)
     }
 
     /// The logical size of the window
@@ -40,19 +91,121 @@ impl WindowInfo {
         self.physical_size
     }
 
-    /// The scale factor of the window
+    /// The scale factor of the window. If the horizontal and vertical scale factors differ, then
+    /// this is their geometric mean.
     pub fn scale(&self) -> f64 {
-        self.scale
+        if self.scale_x == self.scale_y {
+            self.scale_x
+        } else {
+            (self.scale_x * self.scale_y).sqrt()
+        }
     }
 
     /// The reciprocal of the scale factor of the window
     pub fn scale_recip(&self) -> f64 {
-        self.scale_recip
+        recip(self.scale())
+    }
+
+    /// The horizontal scale factor of the window. This is the same as [`Self::scale()`] unless the
+    /// display has non-square pixels.
+    pub fn scale_x(&self) -> f64 {
+        self.scale_x
+    }
+
+    /// The vertical scale factor of the window. This is the same as [`Self::scale()`] unless the
+    /// display has non-square pixels.
+    pub fn scale_y(&self) -> f64 {
+        self.scale_y
+    }
+}
+
+/// Scale factors of zero, negative scale factors, and non-finite scale factors would result in
+/// invalid physical sizes.
+fn validate_scale(scale: f64) -> f64 {
+    debug_assert!(scale.is_finite() && scale > 0.0, "Invalid scale factor: {}", scale);
+
+    if scale.is_finite() && scale > 0.0 {
+        scale
+    } else {
+        1.0
+    }
+}
+
+/// Avoids rounding errors for the common case where there is no scaling.
+fn recip(scale: f64) -> f64 {
+    if scale == 1.0 {
+        1.0
+    } else {
+        1.0 / scale
+    }
+}
+
+/// The serialized form of [`WindowInfo`]. The reciprocals are recomputed when deserializing.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedWindowInfo {
+    logical_size: Size,
+    physical_size: PhySize,
+    scale_x: f64,
+    scale_y: f64,
+}
+
+#[cfg(feature = "serde")]
+impl From<WindowInfo> for SerializedWindowInfo {
+    fn from(info: WindowInfo) -> Self {
+        Self {
+            logical_size: info.logical_size,
+            physical_size: info.physical_size,
+            scale_x: info.scale_x,
+            scale_y: info.scale_y,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedWindowInfo> for WindowInfo {
+    type Error = String;
+
+    fn try_from(info: SerializedWindowInfo) -> Result<Self, Self::Error> {
+        for scale in [info.scale_x, info.scale_y] {
+            if !(scale.is_finite() && scale > 0.0) {
+                return Err(format!("Invalid scale factor: {}", scale));
+            }
+        }
+
+        // The sizes are stored as is, since recomputing one from the other may round differently
+        // than the platform did
+        Ok(Self {
+            logical_size: info.logical_size,
+            physical_size: info.physical_size,
+            scale_x: info.scale_x,
+            scale_y: info.scale_y,
+            scale_x_recip: recip(info.scale_x),
+            scale_y_recip: recip(info.scale_y),
+        })
+    }
+}
+
+/// Prints the physical size and the scale factor, like `1024x768 @2.0`. Non-uniform scale factors
+/// are printed per axis, like `1024x768 @2.0x1.5`.
+impl fmt::Display for WindowInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} @{:?}",
+            self.physical_size.width, self.physical_size.height, self.scale_x
+        )?;
+        if self.scale_x != self.scale_y {
+            write!(f, "x{:?}", self.scale_y)?;
+        }
+
+        Ok(())
     }
 }
 
 /// A point in logical coordinates
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -60,7 +213,7 @@ pub struct Point {
 
 impl Point {
     /// Create a new point in logical coordinates
-    pub fn new(x: f64, y: f64) -> Self {
+    pub const fn new(x: f64, y: f64) -> Self {
         Self { x, y }
     }
 
@@ -68,14 +221,22 @@ impl Point {
     #[inline]
     pub fn to_physical(&self, window_info: &WindowInfo) -> PhyPoint {
         PhyPoint {
-            x: (self.x * window_info.scale()).round() as i32,
-            y: (self.y * window_info.scale()).round() as i32,
+            x: (self.x * window_info.scale_x()).round() as i32,
+            y: (self.y * window_info.scale_y()).round() as i32,
         }
     }
 }
 
+/// Prints the coordinates as `x,y`.
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?},{:?}", self.x, self.y)
+    }
+}
+
 /// A point in actual physical coordinates
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PhyPoint {
     pub x: i32,
     pub y: i32,
@@ -83,7 +244,7 @@ pub struct PhyPoint {
 
 impl PhyPoint {
     /// Create a new point in actual physical coordinates
-    pub fn new(x: i32, y: i32) -> Self {
+    pub const fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
 
@@ -91,14 +252,34 @@ impl PhyPoint {
     #[inline]
     pub fn to_logical(&self, window_info: &WindowInfo) -> Point {
         Point {
-            x: f64::from(self.x) * window_info.scale_recip(),
-            y: f64::from(self.y) * window_info.scale_recip(),
+            x: f64::from(self.x) * window_info.scale_x_recip,
+            y: f64::from(self.y) * window_info.scale_y_recip,
+        }
+    }
+
+    /// The closest point inside of a rectangle of `size` at the origin, so `x` lies between `0`
+    /// and `size.width - 1`, and the same for `y`. Points outside of an empty size are moved to
+    /// the origin. Useful for cursor positions reported while the cursor is outside of the window.
+    pub fn clamp_to(self, size: PhySize) -> PhyPoint {
+        let max = size.to_point();
+
+        PhyPoint::new(self.x.min(max.x - 1).max(0), self.y.min(max.y - 1).max(0))
+    }
+
+    /// How far this point is from `previous`, or zero if there is no previous point. Used for the
+    /// `delta` of [`MouseEvent::CursorMoved`](crate::MouseEvent::CursorMoved).
+    #[cfg_attr(not(feature = "windowing"), allow(dead_code))]
+    pub(crate) fn delta_from(self, previous: Option<PhyPoint>) -> PhyPoint {
+        match previous {
+            Some(previous) => PhyPoint::new(self.x - previous.x, self.y - previous.y),
+            None => PhyPoint::default(),
         }
     }
 }
 
 /// A size in logical coordinates
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Size {
     pub width: f64,
     pub height: f64,
@@ -106,7 +287,7 @@ pub struct Size {
 
 impl Size {
     /// Create a new size in logical coordinates
-    pub fn new(width: f64, height: f64) -> Self {
+    pub const fn new(width: f64, height: f64) -> Self {
         Self { width, height }
     }
 
@@ -114,14 +295,15 @@ impl Size {
     #[inline]
     pub fn to_physical(&self, window_info: &WindowInfo) -> PhySize {
         PhySize {
-            width: (self.width * window_info.scale()).round() as u32,
-            height: (self.height * window_info.scale()).round() as u32,
+            width: (self.width * window_info.scale_x()).round() as u32,
+            height: (self.height * window_info.scale_y()).round() as u32,
         }
     }
 }
 
 /// An actual size in physical coordinates
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PhySize {
     pub width: u32,
     pub height: u32,
@@ -129,16 +311,439 @@ pub struct PhySize {
 
 impl PhySize {
     /// Create a new size in actual physical coordinates
-    pub fn new(width: u32, height: u32) -> Self {
+    pub const fn new(width: u32, height: u32) -> Self {
         Self { width, height }
     }
 
+    /// The point at the size's bottom right corner, relative to the origin. Sizes that don't fit
+    /// in an `i32` are saturated to `i32::MAX`. See the `TryFrom` implementation for a checked
+    /// conversion.
+    pub fn to_point(self) -> PhyPoint {
+        PhyPoint::new(
+            i32::try_from(self.width).unwrap_or(i32::MAX),
+            i32::try_from(self.height).unwrap_or(i32::MAX),
+        )
+    }
+
     /// Convert to logical size
     #[inline]
     pub fn to_logical(&self, window_info: &WindowInfo) -> Size {
         Size {
-            width: f64::from(self.width) * window_info.scale_recip(),
-            height: f64::from(self.height) * window_info.scale_recip(),
+            width: f64::from(self.width) * window_info.scale_x_recip,
+            height: f64::from(self.height) * window_info.scale_y_recip,
+        }
+    }
+}
+
+/// A rectangle in logical coordinates, relative to the window's top left corner
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    /// Create a new rectangle in logical coordinates
+    pub const fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Create a rectangle at the given position with the given size
+    pub fn from_origin_size(origin: Point, size: Size) -> Self {
+        Self::new(origin.x, origin.y, size.width, size.height)
+    }
+
+    pub fn origin(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Whether the rectangle doesn't cover any area.
+    pub fn is_empty(&self) -> bool {
+        !(self.width > 0.0 && self.height > 0.0)
+    }
+
+    /// Whether the point lies inside of the rectangle. The right and bottom edges are exclusive.
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.x
+            && point.y >= self.y
+            && point.x < self.x + self.width
+            && point.y < self.y + self.height
+    }
+
+    /// Whether the rectangles overlap. Rectangles that only share an edge don't overlap.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// The area covered by both rectangles, or `None` if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+
+        Some(Rect::new(x, y, right - x, bottom - y))
+    }
+
+    /// The smallest rectangle that contains both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    /// Convert to actual physical coordinates. The result covers every physical pixel the
+    /// rectangle touches, so it can be slightly larger than the rectangle itself.
+    #[inline]
+    pub fn to_physical(&self, window_info: &WindowInfo) -> PhyRect {
+        let (scale_x, scale_y) = (window_info.scale_x(), window_info.scale_y());
+        let x = (self.x * scale_x).floor();
+        let y = (self.y * scale_y).floor();
+        let right = ((self.x + self.width) * scale_x).ceil();
+        let bottom = ((self.y + self.height) * scale_y).ceil();
+
+        PhyRect {
+            x: x as i32,
+            y: y as i32,
+            width: (right - x).max(0.0) as u32,
+            height: (bottom - y).max(0.0) as u32,
+        }
+    }
+}
+
+/// A rectangle in actual physical coordinates, relative to the window's top left corner
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhyRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PhyRect {
+    /// Create a new rectangle in actual physical coordinates
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Create a rectangle at the given position with the given size
+    pub const fn from_origin_size(origin: PhyPoint, size: PhySize) -> Self {
+        Self::new(origin.x, origin.y, size.width, size.height)
+    }
+
+    pub fn origin(&self) -> PhyPoint {
+        PhyPoint::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> PhySize {
+        PhySize::new(self.width, self.height)
+    }
+
+    /// Whether the point lies inside of the rectangle. The right and bottom edges are exclusive.
+    pub fn contains(&self, point: PhyPoint) -> bool {
+        let (x, y) =
+            (i64::from(point.x) - i64::from(self.x), i64::from(point.y) - i64::from(self.y));
+
+        (0..i64::from(self.width)).contains(&x) && (0..i64::from(self.height)).contains(&y)
+    }
+
+    /// Convert to logical coordinates
+    #[inline]
+    pub fn to_logical(&self, window_info: &WindowInfo) -> Rect {
+        Rect {
+            x: f64::from(self.x) * window_info.scale_x_recip,
+            y: f64::from(self.y) * window_info.scale_y_recip,
+            width: f64::from(self.width) * window_info.scale_x_recip,
+            height: f64::from(self.height) * window_info.scale_y_recip,
+        }
+    }
+}
+
+impl From<(f64, f64)> for Point {
+    fn from((x, y): (f64, f64)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl From<Point> for (f64, f64) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl From<(i32, i32)> for PhyPoint {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl From<PhyPoint> for (i32, i32) {
+    fn from(point: PhyPoint) -> Self {
+        (point.x, point.y)
+    }
+}
+
+/// Fails if either coordinate is negative.
+impl TryFrom<PhyPoint> for PhySize {
+    type Error = TryFromIntError;
+
+    fn try_from(point: PhyPoint) -> Result<Self, Self::Error> {
+        Ok(Self::new(u32::try_from(point.x)?, u32::try_from(point.y)?))
+    }
+}
+
+impl From<(f64, f64)> for Size {
+    fn from((width, height): (f64, f64)) -> Self {
+        Self::new(width, height)
+    }
+}
+
+impl From<Size> for (f64, f64) {
+    fn from(size: Size) -> Self {
+        (size.width, size.height)
+    }
+}
+
+impl From<(u32, u32)> for PhySize {
+    fn from((width, height): (u32, u32)) -> Self {
+        Self::new(width, height)
+    }
+}
+
+impl From<PhySize> for (u32, u32) {
+    fn from(size: PhySize) -> Self {
+        (size.width, size.height)
+    }
+}
+
+/// Fails if either dimension doesn't fit in an `i32`. [`PhySize::to_point()`] saturates instead.
+impl TryFrom<PhySize> for PhyPoint {
+    type Error = TryFromIntError;
+
+    fn try_from(size: PhySize) -> Result<Self, Self::Error> {
+        Ok(Self::new(i32::try_from(size.width)?, i32::try_from(size.height)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The scale factors the conversions are checked with, including non-uniform combinations.
+    const SCALES: [f64; 7] = [0.5, 1.0, 1.25, 4.0 / 3.0, 1.5, 2.0, 3.0];
+
+    fn window_infos() -> impl Iterator<Item = WindowInfo> {
+        SCALES.into_iter().flat_map(|scale_x| {
+            SCALES.into_iter().map(move |scale_y| {
+                WindowInfo::from_logical_size_non_uniform(Size::new(100.0, 100.0), scale_x, scale_y)
+            })
+        })
+    }
+
+    #[test]
+    fn physical_points_round_trip() {
+        for window_info in window_infos() {
+            for x in -50..50 {
+                for y in [-7, 0, 13] {
+                    let point = PhyPoint::new(x, y);
+                    assert_eq!(
+                        point.to_logical(&window_info).to_physical(&window_info),
+                        point,
+                        "{}",
+                        window_info
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn logical_points_round_to_the_nearest_pixel() {
+        for window_info in window_infos() {
+            for i in -100..100 {
+                let point = Point::new(i as f64 * 0.37, i as f64 * -0.53);
+                let rounded = point.to_physical(&window_info).to_logical(&window_info);
+
+                // Half a physical pixel, in logical pixels
+                let max_x = 0.5 / window_info.scale_x() + 1e-9;
+                let max_y = 0.5 / window_info.scale_y() + 1e-9;
+                assert!((rounded.x - point.x).abs() <= max_x, "{} {:?}", window_info, point);
+                assert!((rounded.y - point.y).abs() <= max_y, "{} {:?}", window_info, point);
+            }
+        }
+    }
+
+    #[test]
+    fn sizes_match_the_window_info() {
+        for (scale_x, scale_y) in SCALES.into_iter().zip(SCALES.into_iter().rev()) {
+            for width in [0.0, 1.0, 99.5, 333.0, 1920.0] {
+                let size = Size::new(width, width / 2.0);
+                let from_logical =
+                    WindowInfo::from_logical_size_non_uniform(size, scale_x, scale_y);
+                assert_eq!(size.to_physical(&from_logical), from_logical.physical_size());
+
+                let physical_size = from_logical.physical_size();
+                let from_physical =
+                    WindowInfo::from_physical_size_non_uniform(physical_size, scale_x, scale_y);
+                assert_eq!(physical_size.to_logical(&from_physical), from_physical.logical_size());
+                assert_eq!(from_physical.logical_size().to_physical(&from_physical), physical_size);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_and_non_uniform_scales() {
+        let uniform = WindowInfo::from_logical_size(Size::new(200.0, 100.0), 1.5);
+        assert_eq!((uniform.scale(), uniform.scale_x(), uniform.scale_y()), (1.5, 1.5, 1.5));
+        assert_eq!(uniform.physical_size(), PhySize::new(300, 150));
+        assert_eq!(uniform.scale_recip(), 1.0 / 1.5);
+
+        let non_uniform =
+            WindowInfo::from_logical_size_non_uniform(Size::new(200.0, 100.0), 1.0, 4.0);
+        assert_eq!((non_uniform.scale_x(), non_uniform.scale_y()), (1.0, 4.0));
+        // The geometric mean
+        assert_eq!(non_uniform.scale(), 2.0);
+        assert_eq!(non_uniform.physical_size(), PhySize::new(200, 400));
+        assert_eq!(PhyPoint::new(10, 10).to_logical(&non_uniform), Point::new(10.0, 2.5));
+
+        let resized = non_uniform.with_physical_size(PhySize::new(100, 100));
+        assert_eq!(resized.logical_size(), Size::new(100.0, 25.0));
+        assert_eq!((resized.scale_x(), resized.scale_y()), (1.0, 4.0));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Invalid scale factor")]
+    fn invalid_scale() {
+        WindowInfo::from_logical_size(Size::new(100.0, 100.0), 0.0);
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn invalid_scale() {
+        for scale in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let window_info = WindowInfo::from_logical_size(Size::new(100.0, 100.0), scale);
+            assert_eq!(window_info.scale(), 1.0);
+            assert_eq!(window_info.physical_size(), PhySize::new(100, 100));
+        }
+    }
+
+    #[test]
+    fn rect_contains() {
+        let rect = Rect::new(10.0, 20.0, 30.0, 40.0);
+
+        assert!(rect.contains(Point::new(10.0, 20.0)));
+        assert!(rect.contains(Point::new(39.9, 59.9)));
+        // The right and bottom edges are exclusive
+        assert!(!rect.contains(Point::new(40.0, 30.0)));
+        assert!(!rect.contains(Point::new(20.0, 60.0)));
+        assert!(!rect.contains(Point::new(9.9, 30.0)));
+        assert!(!Rect::new(10.0, 10.0, 0.0, 0.0).contains(Point::new(10.0, 10.0)));
+    }
+
+    #[test]
+    fn rect_intersection_and_union() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let b = Rect::new(5.0, 5.0, 10.0, 10.0);
+
+        assert_eq!(a.intersection(&b), Some(Rect::new(5.0, 5.0, 5.0, 5.0)));
+        assert_eq!(a.union(&b), Rect::new(0.0, 0.0, 15.0, 15.0));
+        assert_eq!(a.intersection(&Rect::new(10.0, 0.0, 5.0, 5.0)), None);
+        assert_eq!(
+            a.intersection(&Rect::new(2.0, 2.0, 1.0, 1.0)),
+            Some(Rect::new(2.0, 2.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn rect_to_physical_covers_every_touched_pixel() {
+        let window_info = WindowInfo::from_logical_size(Size::new(100.0, 100.0), 1.5);
+
+        // 10.0 * 1.5 = 15 and 20.5 * 1.5 = 30.75, so the last pixel is only partially covered
+        assert_eq!(
+            Rect::new(10.0, 10.0, 10.5, 10.5).to_physical(&window_info),
+            PhyRect::new(15, 15, 16, 16)
+        );
+        // 0.5 * 1.5 = 0.75 starts inside of the first pixel
+        assert_eq!(
+            Rect::new(0.5, 0.5, 1.0, 1.0).to_physical(&window_info),
+            PhyRect::new(0, 0, 3, 3)
+        );
+        assert_eq!(
+            Rect::new(10.0, 10.0, 0.0, 0.0).to_physical(&window_info),
+            PhyRect::new(15, 15, 0, 0)
+        );
+        assert_eq!(
+            Rect::new(-1.0, -1.0, 2.0, 2.0).to_physical(&window_info),
+            PhyRect::new(-2, -2, 4, 4)
+        );
+    }
+
+    #[test]
+    fn rect_round_trip() {
+        let window_info = WindowInfo::from_logical_size(Size::new(100.0, 100.0), 2.0);
+        let physical = PhyRect::new(3, 5, 7, 9);
+
+        assert_eq!(physical.to_logical(&window_info), Rect::new(1.5, 2.5, 3.5, 4.5));
+        assert_eq!(physical.to_logical(&window_info).to_physical(&window_info), physical);
+
+        // A logical rect that doesn't line up with the pixels only grows
+        let logical = Rect::new(1.2, 1.2, 1.2, 1.2);
+        let rounded = logical.to_physical(&window_info).to_logical(&window_info);
+        assert_eq!(rounded, Rect::new(1.0, 1.0, 1.5, 1.5));
+        assert_eq!(rounded.union(&logical), rounded);
+    }
+
+    #[test]
+    fn phy_rect_contains() {
+        let rect = PhyRect::new(-5, -5, 10, 10);
+
+        assert!(rect.contains(PhyPoint::new(-5, -5)));
+        assert!(rect.contains(PhyPoint::new(4, 4)));
+        assert!(!rect.contains(PhyPoint::new(5, 0)));
+        assert!(!PhyRect::new(i32::MAX, 0, u32::MAX, 1).contains(PhyPoint::new(i32::MIN, 0)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn window_info_round_trip() {
+        let info = WindowInfo::from_logical_size_non_uniform(Size::new(333.0, 200.0), 1.5, 1.75);
+        let json = serde_json::to_string(&info).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"logical_size":{"width":333.0,"height":200.0},"physical_size":{"width":500,"height":350},"scale_x":1.5,"scale_y":1.75}"#
+        );
+        assert_eq!(serde_json::from_str::<WindowInfo>(&json).unwrap(), info);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn window_info_invalid_scale() {
+        for scale in ["0.0", "-1.0", "1e400"] {
+            let json = format!(
+                r#"{{"logical_size":{{"width":1.0,"height":1.0}},"physical_size":{{"width":1,"height":1}},"scale_x":1.0,"scale_y":{}}}"#,
+                scale
+            );
+            assert!(serde_json::from_str::<WindowInfo>(&json).is_err(), "{}", json);
         }
     }
 }
@@ -1,4 +1,8 @@
-use crate::Size;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{Size, X11Error, X11PropertyValue};
 
 /// The dpi scaling policy of the window
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -7,9 +11,157 @@ pub enum WindowScalePolicy {
     SystemScaleFactor,
     /// Use the given dpi scale factor (e.g. `1.0` = 96 dpi)
     ScaleFactor(f64),
+    /// Use the system's dpi scale factor, unless it can't be trusted. In that case the given scale
+    /// factor is used instead. See [`WindowOpenOptions::system_scale_range`] for when that happens.
+    /// This is the same as [`SystemScaleFactor`](Self::SystemScaleFactor) on Windows and macOS,
+    /// where the system's scale factor is always reliable.
+    SystemScaleFactorWithFallback(f64),
+}
+
+impl WindowScalePolicy {
+    /// Whether the window follows the system's scale factor.
+    #[cfg_attr(not(feature = "windowing"), allow(dead_code))]
+    pub(crate) fn follows_system(&self) -> bool {
+        matches!(self, Self::SystemScaleFactor | Self::SystemScaleFactorWithFallback(_))
+    }
+
+    /// The policy that's in effect while the scale factor is replaced by an override, see
+    /// [`EventContext::set_scale_override()`](crate::EventContext::set_scale_override).
+    #[cfg_attr(not(feature = "windowing"), allow(dead_code))]
+    pub(crate) fn with_override(self, scale_override: Option<f64>) -> Self {
+        scale_override.map_or(self, Self::ScaleFactor)
+    }
+
+    /// Replace scale factors that aren't positive finite numbers by `1.0`.
+    pub(crate) fn sanitized(self) -> Self {
+        match self {
+            Self::ScaleFactor(factor) if !is_valid_scale(factor) => Self::ScaleFactor(1.0),
+            Self::SystemScaleFactorWithFallback(factor) if !is_valid_scale(factor) => {
+                Self::SystemScaleFactorWithFallback(1.0)
+            }
+            policy => policy,
+        }
+    }
+}
+
+pub(crate) fn is_valid_scale(factor: f64) -> bool {
+    factor.is_finite() && factor > 0.0
+}
+
+/// See [`WindowOpenOptions::system_scale_range`].
+pub const DEFAULT_SYSTEM_SCALE_RANGE: RangeInclusive<f64> = 0.5..=4.0;
+
+/// What should happen when the [`WindowHandler`](crate::WindowHandler) panics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// Log the panic through the `tracing` feature, send a
+    /// [`WindowEvent::WillClose`](crate::WindowEvent::WillClose) event to the handler, and close
+    /// the window. The rest of the application keeps running.
+    #[default]
+    CloseWindow,
+    /// Abort the process
+    Abort,
+}
+
+impl PanicPolicy {
+    /// Run `f`, which calls into the window handler. Returns `None` if the handler panicked, in
+    /// which case the window should be closed.
+    pub(crate) fn catch<R>(self, f: impl FnOnce() -> R) -> Option<R> {
+        let payload = match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(result) => return Some(result),
+            Err(payload) => payload,
+        };
+
+        let _message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.as_str()
+        } else {
+            "<non-string panic payload>"
+        };
+        warn!(target: "baseview::handler", message = _message, "window handler panicked");
+
+        match self {
+            PanicPolicy::CloseWindow => None,
+            PanicPolicy::Abort => std::process::abort(),
+        }
+    }
+}
+
+/// What drives the calls to [`WindowHandler::on_frame`](crate::WindowHandler::on_frame).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameSource {
+    /// Baseview draws the frames from a timer of its own, or from the display link when
+    /// [`WindowOpenOptions::vsync_frames`] is set.
+    #[default]
+    Internal,
+    /// The host draws the frames by calling
+    /// [`WindowHandle::trigger_frame()`](crate::WindowHandle::trigger_frame), for instance from a
+    /// host-driven timer or idle callback. Baseview doesn't create any timers for the window in
+    /// this mode, so [`WindowOpenOptions::vsync_frames`] and
+    /// [`EventContext::set_frame_interval()`](crate::EventContext::set_frame_interval) have no
+    /// effect. Events are still handled as they arrive, and the frames they cause are drawn on the
+    /// next tick.
+    External,
+}
+
+/// What kind of window [`EventContext::open_blocking()`](crate::EventContext::open_blocking) and
+/// [`EventContext::open_as_if_parented()`](crate::EventContext::open_as_if_parented) create.
+/// Parented windows are always embedded in their parent, so they ignore this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowKind {
+    /// A regular top-level window with the platform's title bar and borders.
+    #[default]
+    Normal,
+    /// A borderless window for dropdown lists, tooltips, and other popups, which is usually placed
+    /// next to another window with
+    /// [`EventContext::set_position()`](crate::EventContext::set_position). It doesn't show up in
+    /// the taskbar, and opening or clicking it doesn't take the activation away from the window
+    /// that opened it.
+    ///
+    /// On Linux the window bypasses the window manager and is marked as a popup menu, so the
+    /// compositor decides whether it gets a shadow. Rounded corners aren't supported there. On
+    /// Windows, rounded corners need Windows 11. On macOS `open_as_if_parented()` only creates a
+    /// view for the host to put in its own window, so there only `open_blocking()` creates popups.
+    Popup {
+        /// Draw a drop shadow around the window.
+        shadow: bool,
+        /// Round the window's corners.
+        rounded_corners: bool,
+        /// Let the window receive keyboard input, like a dropdown with a search field. Clicking a
+        /// focusable popup gives it the keyboard focus, and on macOS it becomes the key window
+        /// without activating it. Windows has no such distinction, so there clicking a focusable
+        /// popup also activates it.
+        focusable: bool,
+    },
+}
+
+/// How a macOS app shows up in the Dock and the menu bar, see
+/// [`WindowOpenOptions::macos_activation_policy`]. This mirrors AppKit's
+/// `NSApplicationActivationPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationPolicy {
+    /// An ordinary app with a Dock icon that can own the menu bar.
+    Regular,
+    /// An app without a Dock icon that doesn't own the menu bar, but whose windows can still be
+    /// activated, like a helper utility.
+    Accessory,
+    /// An app without a Dock icon that can't be activated at all.
+    Prohibited,
 }
 
-/// The options for opening a new window
+/// See [`WindowOpenOptions::x11_error_handler`].
+pub type X11ErrorHandler = Box<dyn Fn(&X11Error) + Send + Sync>;
+
+/// The options for opening a new window. Start with [`WindowOpenOptions::new()`] or
+/// [`WindowOpenOptions::default()`], and change the options that should differ from their defaults
+/// through the builder methods, which are named after the fields they set.
+///
+/// New options are added over time, so the struct can't be created through a struct literal
+/// outside of this crate. Code that used to list every field can call `WindowOpenOptions::new()`
+/// with the title and the size instead, followed by a builder method for every other field that
+/// didn't have its default value. The fields can still be read and assigned directly.
+#[non_exhaustive]
 pub struct WindowOpenOptions {
     pub title: String,
 
@@ -25,11 +177,348 @@ pub struct WindowOpenOptions {
     /// Callback that determines if the drop target is valid
     pub drop_target_valid: Option<Box<dyn Fn() -> bool + Send + Sync>>,
 
-    /// Should this window be resizable?
+    /// Whether the window should support drag and drop. When this is disabled the window doesn't
+    /// accept drops, [`EventContext::start_drag`](crate::EventContext::start_drag) does nothing,
+    /// and on Windows OLE isn't initialized for the window's thread. That last part is useful when
+    /// something else in the process has already initialized COM in a way that's incompatible with
+    /// OLE.
+    ///
+    /// Even when this is enabled, drag and drop may not be available. See
+    /// [`EventContext::drag_and_drop_available`](crate::EventContext::drag_and_drop_available).
+    pub enable_drag_and_drop: bool,
+
+    /// Should the user be able to resize this window? The window can still be resized through
+    /// [`EventContext::resize`](crate::EventContext::resize) when this is disabled.
     pub resizable: bool,
 
+    /// Whether a standalone window is a regular window or a popup. See [`WindowKind`].
+    pub window_kind: WindowKind,
+
+    /// The range of system scale factors that are considered plausible. Misconfigured Linux
+    /// systems can report absurd scale factors, for instance virtual displays that claim to be a
+    /// few millimeters wide or an `Xft.dpi` of 0 or 960. `Xft.dpi` is only used when it results in
+    /// a scale factor within this range, and the screen's dimensions are used otherwise. If neither
+    /// is within the range, the fallback from
+    /// [`WindowScalePolicy::SystemScaleFactorWithFallback`] is used, or the scale factor is clamped
+    /// to this range if there is no fallback. Defaults to [`DEFAULT_SYSTEM_SCALE_RANGE`].
+    ///
+    /// Only used on Linux.
+    pub system_scale_range: RangeInclusive<f64>,
+
+    /// What to do when the window handler panics. Panics are never allowed to unwind into the
+    /// platform's event loop.
+    pub on_handler_panic: PanicPolicy,
+
+    /// Align the calls to [`WindowHandler::on_frame`](crate::WindowHandler::on_frame) with the
+    /// display's refresh rate instead of using a timer. This avoids the stutter caused by the timer
+    /// drifting against the display's refresh rate. The frame interval set through
+    /// [`EventContext::set_frame_interval`](crate::EventContext::set_frame_interval) is not used in
+    /// this mode, and an OpenGL context's swap interval is set to 1.
+    ///
+    /// This is currently only supported on macOS, where it uses a `CVDisplayLink`. If the display
+    /// link cannot be created the window silently falls back to the timer. Other platforms ignore
+    /// this option.
+    pub vsync_frames: bool,
+
+    /// Whether baseview or the host decides when frames are drawn. See [`FrameSource`].
+    pub frame_source: FrameSource,
+
+    /// After a call to [`WindowHandler::on_frame`](crate::WindowHandler::on_frame) that took at
+    /// least two frame intervals, wait a full frame interval before drawing the next frame instead
+    /// of drawing it right away. This gives the events that queued up during the slow frame a
+    /// chance to be handled, at the cost of a lower frame rate while frames stay slow.
+    ///
+    /// This is currently only used on Linux.
+    pub skip_late_frames: bool,
+
+    /// Called with the errors the X server reports for the window's requests. When this is not set
+    /// the errors are logged as warnings through the `tracing` feature instead. If the error means that the window itself no
+    /// longer exists, the handler receives a
+    /// [`WindowEvent::WillClose`](crate::WindowEvent::WillClose) event and the window is closed
+    /// afterwards.
+    ///
+    /// All windows share a single X11 connection. Errors that can't be traced back to a specific
+    /// window, for instance because they're about a resource other than a window, are reported to
+    /// every open window.
+    ///
+    /// Only used on Linux. The callback is called on the window's thread.
+    pub x11_error_handler: Option<X11ErrorHandler>,
+    /// Custom properties to set on the X11 window before it's mapped, as pairs of property names
+    /// and values, for instance to advertise capabilities to the host during an embedding
+    /// handshake. The atoms for the names are created if they don't exist yet. Use
+    /// [`EventContext::set_x11_property()`](crate::EventContext::set_x11_property) to change them
+    /// while the window is open.
+    ///
+    /// Only used on Linux.
+    pub x11_properties: Vec<(String, X11PropertyValue)>,
+
+    /// The activation policy [`EventContext::open_blocking()`](crate::EventContext::open_blocking)
+    /// gives the app. `None` leaves the policy alone. Popups never get a Dock icon, so they use
+    /// [`ActivationPolicy::Accessory`] when this is [`ActivationPolicy::Regular`].
+    ///
+    /// Nothing is changed if the app has already finished launching or has a delegate, which means
+    /// that a larger application configured it before opening the window.
+    ///
+    /// Only used on macOS.
+    pub macos_activation_policy: Option<ActivationPolicy>,
+    /// Let [`EventContext::open_blocking()`](crate::EventContext::open_blocking) install a main
+    /// menu with a Quit item. Like [`Self::macos_activation_policy`], this is skipped if the app
+    /// has already finished launching or has a delegate.
+    ///
+    /// Only used on macOS.
+    pub macos_install_menu: bool,
+
     /// If provided, then an OpenGL context will be created for this window. You'll be able to
-    /// access this context through [crate::Window::gl_context].
+    /// access this context through [crate::EventContext::gl_context].
     #[cfg(feature = "opengl")]
     pub gl_config: Option<crate::gl::GlConfig>,
+
+    /// Expose the window to assistive technology like screen readers through AccessKit. The
+    /// platform's adapter is created along with the window. Once assistive technology starts
+    /// inspecting the window the handler receives an
+    /// [`AccessKitEvent::InitialTreeRequested`](crate::AccessKitEvent::InitialTreeRequested) event,
+    /// and it should respond with a full tree through
+    /// [`EventContext::update_accesskit_tree()`](crate::EventContext::update_accesskit_tree).
+    #[cfg(feature = "accesskit")]
+    pub accesskit: bool,
+}
+
+impl WindowOpenOptions {
+    /// Options for a window with the given title and logical size. All other options have their
+    /// default values. See [`Self::size()`] for how invalid sizes are handled.
+    pub fn new(title: impl Into<String>, size: Size) -> Self {
+        Self { title: title.into(), ..Self::default() }.size(size)
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Set the window's logical size. No platform can create an empty window, so dimensions
+    /// smaller than one logical pixel are raised to one pixel. The same goes for dimensions that
+    /// aren't finite.
+    pub fn size(mut self, size: Size) -> Self {
+        let clamp = |value: f64| if value.is_finite() { value.max(1.0) } else { 1.0 };
+        self.size = Size::new(clamp(size.width), clamp(size.height));
+        self
+    }
+
+    /// Set the dpi scaling policy. Scale factors that aren't positive finite numbers are replaced
+    /// by `1.0`.
+    pub fn scale(mut self, scale: WindowScalePolicy) -> Self {
+        self.scale = scale.sanitized();
+        self
+    }
+
+    /// Set the range of plausible system scale factors. Ranges that are empty or that contain
+    /// anything other than positive finite numbers are replaced by [`DEFAULT_SYSTEM_SCALE_RANGE`].
+    pub fn system_scale_range(mut self, range: RangeInclusive<f64>) -> Self {
+        let is_valid = |factor: &f64| factor.is_finite() && *factor > 0.0;
+        self.system_scale_range =
+            if is_valid(range.start()) && is_valid(range.end()) && !range.is_empty() {
+                range
+            } else {
+                DEFAULT_SYSTEM_SCALE_RANGE
+            };
+        self
+    }
+
+    pub fn drop_target_valid(mut self, f: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.drop_target_valid = Some(Box::new(f));
+        self
+    }
+
+    pub fn enable_drag_and_drop(mut self, enable: bool) -> Self {
+        self.enable_drag_and_drop = enable;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn window_kind(mut self, window_kind: WindowKind) -> Self {
+        self.window_kind = window_kind;
+        self
+    }
+
+    pub fn on_handler_panic(mut self, policy: PanicPolicy) -> Self {
+        self.on_handler_panic = policy;
+        self
+    }
+
+    pub fn vsync_frames(mut self, vsync_frames: bool) -> Self {
+        self.vsync_frames = vsync_frames;
+        self
+    }
+
+    pub fn frame_source(mut self, frame_source: FrameSource) -> Self {
+        self.frame_source = frame_source;
+        self
+    }
+
+    pub fn skip_late_frames(mut self, skip_late_frames: bool) -> Self {
+        self.skip_late_frames = skip_late_frames;
+        self
+    }
+
+    pub fn x11_error_handler(mut self, f: impl Fn(&X11Error) + Send + Sync + 'static) -> Self {
+        self.x11_error_handler = Some(Box::new(f));
+        self
+    }
+
+    pub fn x11_properties(mut self, properties: Vec<(String, X11PropertyValue)>) -> Self {
+        self.x11_properties = properties;
+        self
+    }
+
+    pub fn macos_activation_policy(mut self, policy: Option<ActivationPolicy>) -> Self {
+        self.macos_activation_policy = policy;
+        self
+    }
+
+    pub fn macos_install_menu(mut self, install_menu: bool) -> Self {
+        self.macos_install_menu = install_menu;
+        self
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn gl_config(mut self, gl_config: crate::gl::GlConfig) -> Self {
+        self.gl_config = Some(gl_config);
+        self
+    }
+
+    #[cfg(feature = "accesskit")]
+    pub fn accesskit(mut self, accesskit: bool) -> Self {
+        self.accesskit = accesskit;
+        self
+    }
+}
+
+/// A normal, resizable 800 by 600 window without a title that uses the system's scale factor, with
+/// drag and drop enabled. On macOS the app gets a Dock icon and a main menu. Everything else is
+/// turned off.
+impl Default for WindowOpenOptions {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            size: Size::new(800.0, 600.0),
+            scale: WindowScalePolicy::SystemScaleFactor,
+            drop_target_valid: None,
+            enable_drag_and_drop: true,
+            resizable: true,
+            window_kind: WindowKind::Normal,
+            system_scale_range: DEFAULT_SYSTEM_SCALE_RANGE,
+            on_handler_panic: PanicPolicy::default(),
+            vsync_frames: false,
+            frame_source: FrameSource::Internal,
+            skip_late_frames: false,
+            x11_error_handler: None,
+            x11_properties: Vec::new(),
+            macos_activation_policy: Some(ActivationPolicy::Regular),
+            macos_install_menu: true,
+            #[cfg(feature = "opengl")]
+            gl_config: None,
+            #[cfg(feature = "accesskit")]
+            accesskit: false,
+        }
+    }
+}
+
+impl fmt::Debug for WindowOpenOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Stands in for the callbacks, which can't be printed.
+        struct Callback;
+
+        impl fmt::Debug for Callback {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "<fn>")
+            }
+        }
+
+        let mut debug = f.debug_struct("WindowOpenOptions");
+        debug
+            .field("title", &self.title)
+            .field("size", &self.size)
+            .field("scale", &self.scale)
+            .field("drop_target_valid", &self.drop_target_valid.as_ref().map(|_| Callback))
+            .field("enable_drag_and_drop", &self.enable_drag_and_drop)
+            .field("resizable", &self.resizable)
+            .field("window_kind", &self.window_kind)
+            .field("system_scale_range", &self.system_scale_range)
+            .field("on_handler_panic", &self.on_handler_panic)
+            .field("vsync_frames", &self.vsync_frames)
+            .field("frame_source", &self.frame_source)
+            .field("skip_late_frames", &self.skip_late_frames)
+            .field("x11_error_handler", &self.x11_error_handler.as_ref().map(|_| Callback))
+            .field("x11_properties", &self.x11_properties)
+            .field("macos_activation_policy", &self.macos_activation_policy)
+            .field("macos_install_menu", &self.macos_install_menu);
+        #[cfg(feature = "opengl")]
+        debug.field("gl_config", &self.gl_config);
+        #[cfg(feature = "accesskit")]
+        debug.field("accesskit", &self.accesskit);
+
+        debug.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Event, MouseEvent, WindowEvent};
+
+    /// A handler that panics while handling mouse events, like a handler with a bug would.
+    #[derive(Default)]
+    struct Handler {
+        received: Vec<Event>,
+    }
+
+    impl Handler {
+        fn on_event(&mut self, event: Event) {
+            if let Event::Mouse(_) = event {
+                panic!("the handler has a bug");
+            }
+
+            self.received.push(event);
+        }
+    }
+
+    #[test]
+    fn catch_returns_the_result() {
+        assert_eq!(PanicPolicy::CloseWindow.catch(|| 42), Some(42));
+        assert_eq!(PanicPolicy::Abort.catch(|| 42), Some(42));
+    }
+
+    #[test]
+    fn catch_close_window() {
+        let policy = PanicPolicy::CloseWindow;
+
+        assert_eq!(policy.catch(|| panic!("a string literal")), None::<()>);
+        assert_eq!(policy.catch(|| panic!("a formatted {}", "string")), None::<()>);
+        assert_eq!(policy.catch(|| std::panic::panic_any(42)), None::<()>);
+    }
+
+    /// The backends send `WillClose` to a handler that panicked with `CloseWindow`, and they don't
+    /// call it again after that. The handler's state survives the panic, so it can still clean up
+    /// after itself.
+    #[test]
+    fn close_window_delivers_will_close() {
+        let policy = PanicPolicy::CloseWindow;
+        let mut handler = Handler::default();
+
+        assert!(policy.catch(|| handler.on_event(Event::Window(WindowEvent::Focused))).is_some());
+        let panicked =
+            policy.catch(|| handler.on_event(Event::Mouse(MouseEvent::CursorLeft))).is_none();
+        assert!(panicked);
+
+        let will_close = Event::Window(WindowEvent::WillClose);
+        assert!(policy.catch(|| handler.on_event(will_close)).is_some());
+        assert_eq!(
+            handler.received,
+            [Event::Window(WindowEvent::Focused), Event::Window(WindowEvent::WillClose)]
+        );
+    }
 }
@@ -0,0 +1,527 @@
+//! A compatibility layer for code that was written against `winit`'s window and event types, like
+//! the glue between a renderer or a GUI library and `winit`. This doesn't depend on `winit`.
+//! Instead it contains copies of the parts of `winit`'s API that those integrations tend to use,
+//! built on top of the regular baseview API, so porting them mostly comes down to changing the
+//! imports.
+//!
+//! A handler keeps a [`WinitCompat`] around, passes every event it receives through
+//! [`WinitCompat::translate()`], and wraps the [`EventContext`] with [`WinitCompat::window()`]
+//! wherever the glue code expects a `winit` window. There's no `RedrawRequested` event, since
+//! [`WindowHandler::on_frame()`][crate::WindowHandler::on_frame] is called for every frame
+//! instead. See the `winit_compat` example.
+//!
+//! This is only a compatibility surface. Everything that isn't covered here is still available
+//! through the regular baseview API.
+
+use std::path::PathBuf;
+
+use keyboard_types::{Code, Key, KeyState, KeyboardEvent, Location, Modifiers};
+
+use crate::{
+    Data, Event, EventContext, MouseCursor, MouseEvent, Point, Rect, ScrollDelta, Size, WindowId,
+    WindowInfo,
+};
+
+/// A size in physical pixels, like `winit`'s `PhysicalSize`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhysicalSize<P> {
+    pub width: P,
+    pub height: P,
+}
+
+impl<P> PhysicalSize<P> {
+    pub const fn new(width: P, height: P) -> Self {
+        Self { width, height }
+    }
+}
+
+/// A position in physical pixels relative to the window's top left corner, like `winit`'s
+/// `PhysicalPosition`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhysicalPosition<P> {
+    pub x: P,
+    pub y: P,
+}
+
+impl<P> PhysicalPosition<P> {
+    pub const fn new(x: P, y: P) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Whether a key or a mouse button was pressed or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementState {
+    Pressed,
+    Released,
+}
+
+impl ElementState {
+    pub fn is_pressed(self) -> bool {
+        self == ElementState::Pressed
+    }
+}
+
+/// A mouse button, using `winit`'s names for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl From<crate::MouseButton> for MouseButton {
+    fn from(button: crate::MouseButton) -> Self {
+        match button {
+            crate::MouseButton::Left => MouseButton::Left,
+            crate::MouseButton::Middle => MouseButton::Middle,
+            crate::MouseButton::Right => MouseButton::Right,
+            crate::MouseButton::Back => MouseButton::Back,
+            crate::MouseButton::Forward => MouseButton::Forward,
+            crate::MouseButton::Other(button) => MouseButton::Other(button as u16),
+        }
+    }
+}
+
+/// How far the mouse wheel was scrolled. Just like in `winit`, positive values mean that the
+/// content should move right and down, so a positive `x` means scrolling left. baseview's own
+/// [`ScrollDelta`] uses the opposite horizontal direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseScrollDelta {
+    LineDelta(f32, f32),
+    PixelDelta(PhysicalPosition<f64>),
+}
+
+/// A key press or release. The keys and their locations use the types from `keyboard-types`,
+/// which `winit`'s own keyboard types are modeled after.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub physical_key: Code,
+    pub logical_key: Key,
+    /// The text the key press produced, if any. This is always `None` for releases.
+    pub text: Option<String>,
+    pub location: Location,
+    pub state: ElementState,
+    pub repeat: bool,
+}
+
+/// The cursor icons `winit` supports, with the same names. These are mapped to the closest
+/// [`MouseCursor`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    #[default]
+    Default,
+    ContextMenu,
+    Help,
+    Pointer,
+    Progress,
+    Wait,
+    Cell,
+    Crosshair,
+    Text,
+    VerticalText,
+    Alias,
+    Copy,
+    Move,
+    NoDrop,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    EResize,
+    NResize,
+    NeResize,
+    NwResize,
+    SResize,
+    SeResize,
+    SwResize,
+    WResize,
+    EwResize,
+    NsResize,
+    NeswResize,
+    NwseResize,
+    ColResize,
+    RowResize,
+    AllScroll,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl From<CursorIcon> for MouseCursor {
+    fn from(icon: CursorIcon) -> Self {
+        match icon {
+            CursorIcon::Default | CursorIcon::ContextMenu => MouseCursor::Default,
+            CursorIcon::Help => MouseCursor::Help,
+            CursorIcon::Pointer => MouseCursor::PointingHand,
+            CursorIcon::Progress => MouseCursor::PtrWorking,
+            CursorIcon::Wait => MouseCursor::Working,
+            CursorIcon::Cell => MouseCursor::Cell,
+            CursorIcon::Crosshair => MouseCursor::Crosshair,
+            CursorIcon::Text => MouseCursor::Text,
+            CursorIcon::VerticalText => MouseCursor::VerticalText,
+            CursorIcon::Alias => MouseCursor::Alias,
+            CursorIcon::Copy => MouseCursor::Copy,
+            CursorIcon::Move => MouseCursor::Move,
+            CursorIcon::NoDrop => MouseCursor::PtrNotAllowed,
+            CursorIcon::NotAllowed => MouseCursor::NotAllowed,
+            CursorIcon::Grab => MouseCursor::Hand,
+            CursorIcon::Grabbing => MouseCursor::HandGrabbing,
+            CursorIcon::EResize => MouseCursor::EResize,
+            CursorIcon::NResize => MouseCursor::NResize,
+            CursorIcon::NeResize => MouseCursor::NeResize,
+            CursorIcon::NwResize => MouseCursor::NwResize,
+            CursorIcon::SResize => MouseCursor::SResize,
+            CursorIcon::SeResize => MouseCursor::SeResize,
+            CursorIcon::SwResize => MouseCursor::SwResize,
+            CursorIcon::WResize => MouseCursor::WResize,
+            CursorIcon::EwResize => MouseCursor::EwResize,
+            CursorIcon::NsResize => MouseCursor::NsResize,
+            CursorIcon::NeswResize => MouseCursor::NeswResize,
+            CursorIcon::NwseResize => MouseCursor::NwseResize,
+            CursorIcon::ColResize => MouseCursor::ColResize,
+            CursorIcon::RowResize => MouseCursor::RowResize,
+            CursorIcon::AllScroll => MouseCursor::AllScroll,
+            CursorIcon::ZoomIn => MouseCursor::ZoomIn,
+            CursorIcon::ZoomOut => MouseCursor::ZoomOut,
+        }
+    }
+}
+
+/// The subset of `winit`'s window events that can be produced from baseview's events. Positions
+/// and sizes are in physical pixels, like in `winit`.
+///
+/// baseview doesn't ask the handler before closing a window, so there's no `CloseRequested`
+/// event. [`WindowEvent::Destroyed`] is sent right before the window closes instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    Resized(PhysicalSize<u32>),
+    /// Sent right before the [`WindowEvent::Resized`] event for the window's new size. Windows with
+    /// a different scale factor for each axis report the horizontal one.
+    ScaleFactorChanged {
+        scale_factor: f64,
+    },
+    Focused(bool),
+    /// Sent with `true` when the window is hidden, and with `false` when it's shown again.
+    Occluded(bool),
+    Destroyed,
+    HoveredFile(PathBuf),
+    HoveredFileCancelled,
+    DroppedFile(PathBuf),
+    ModifiersChanged(Modifiers),
+    KeyboardInput {
+        event: KeyEvent,
+    },
+    CursorMoved {
+        position: PhysicalPosition<f64>,
+    },
+    CursorEntered,
+    CursorLeft,
+    MouseWheel {
+        delta: MouseScrollDelta,
+    },
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+}
+
+/// Translates baseview's events into [`WindowEvent`]s, and keeps track of the state that's needed
+/// for that, like the window's size and the modifiers. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct WinitCompat {
+    window_info: WindowInfo,
+    modifiers: Modifiers,
+    cursor_icon: CursorIcon,
+    cursor_visible: bool,
+}
+
+impl Default for WinitCompat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WinitCompat {
+    /// The window's size is only known once the first
+    /// [`WindowEvent::Resized`](crate::WindowEvent::Resized) event has been translated, which
+    /// baseview sends before anything else.
+    pub fn new() -> Self {
+        Self {
+            window_info: WindowInfo::from_logical_size(Size::new(0.0, 0.0), 1.0),
+            modifiers: Modifiers::empty(),
+            cursor_icon: CursorIcon::Default,
+            cursor_visible: true,
+        }
+    }
+
+    /// Wrap the context passed to the handler, so it can be used like a `winit` window for the
+    /// rest of the call.
+    pub fn window<'a, 'b>(&'a mut self, context: &'a mut EventContext<'b>) -> CompatWindow<'a, 'b> {
+        CompatWindow { context, compat: self }
+    }
+
+    /// Translate one of baseview's events into the matching `winit` events. Most events turn into
+    /// a single event, and events that baseview has no counterpart for in `winit` are dropped.
+    /// A [`WindowEvent::ModifiersChanged`] event is added in front of input events whose
+    /// modifiers differ from the previous ones, and a drop is split into a
+    /// [`WindowEvent::DroppedFile`] event for every dropped file.
+    pub fn translate(&mut self, event: Event) -> Translated {
+        let mut translated =
+            Translated { first: None, event: None, dropped: Vec::new().into_iter() };
+        match event {
+            Event::Mouse(event) => {
+                if let Some(modifiers) = mouse_event_modifiers(&event) {
+                    translated.first = self.update_modifiers(modifiers);
+                }
+                translated.event = self.translate_mouse_event(event);
+            }
+            Event::Keyboard(event) => {
+                translated.first = self.update_modifiers(event.modifiers);
+                translated.event = Some(WindowEvent::KeyboardInput { event: key_event(event) });
+            }
+            Event::Window(event) => self.translate_window_event(event, &mut translated),
+            _ => (),
+        }
+
+        translated
+    }
+
+    fn translate_mouse_event(&self, event: MouseEvent) -> Option<WindowEvent> {
+        let event = match event {
+            MouseEvent::CursorMoved { position, .. } => {
+                WindowEvent::CursorMoved { position: self.physical_position(position) }
+            }
+            MouseEvent::ButtonPressed { button, .. } => {
+                WindowEvent::MouseInput { state: ElementState::Pressed, button: button.into() }
+            }
+            MouseEvent::ButtonReleased { button, .. } => {
+                WindowEvent::MouseInput { state: ElementState::Released, button: button.into() }
+            }
+            MouseEvent::WheelScrolled { delta, .. } => {
+                let delta = match delta {
+                    ScrollDelta::Lines { x, y } => MouseScrollDelta::LineDelta(-x, y),
+                    ScrollDelta::Pixels { x, y } => {
+                        let delta = self.physical_position(Point::new(-x as f64, y as f64));
+                        MouseScrollDelta::PixelDelta(delta)
+                    }
+                };
+                WindowEvent::MouseWheel { delta }
+            }
+            MouseEvent::CursorEntered => WindowEvent::CursorEntered,
+            MouseEvent::CursorLeft => WindowEvent::CursorLeft,
+            MouseEvent::PenMoved { .. } => return None,
+        };
+
+        Some(event)
+    }
+
+    fn translate_window_event(&mut self, event: crate::WindowEvent, translated: &mut Translated) {
+        use crate::WindowEvent as BaseviewEvent;
+
+        translated.event = match event {
+            BaseviewEvent::Resized { new, old } => {
+                self.window_info = new;
+                if new.scale_x() != old.scale_x() {
+                    translated.first =
+                        Some(WindowEvent::ScaleFactorChanged { scale_factor: new.scale_x() });
+                }
+                let size = new.physical_size();
+                Some(WindowEvent::Resized(PhysicalSize::new(size.width, size.height)))
+            }
+            BaseviewEvent::Focused => Some(WindowEvent::Focused(true)),
+            BaseviewEvent::Unfocused => Some(WindowEvent::Focused(false)),
+            BaseviewEvent::Shown => Some(WindowEvent::Occluded(false)),
+            BaseviewEvent::Hidden => Some(WindowEvent::Occluded(true)),
+            BaseviewEvent::WillClose => Some(WindowEvent::Destroyed),
+            BaseviewEvent::DragEnter { data: Data::Filepath(path), .. } => {
+                Some(WindowEvent::HoveredFile(path))
+            }
+            BaseviewEvent::DragLeave => Some(WindowEvent::HoveredFileCancelled),
+            BaseviewEvent::Drop(payload) => {
+                translated.dropped = payload.items.into_iter();
+                None
+            }
+            _ => None,
+        };
+    }
+
+    /// Returns a [`WindowEvent::ModifiersChanged`] event if the modifiers have changed.
+    fn update_modifiers(&mut self, modifiers: Modifiers) -> Option<WindowEvent> {
+        if modifiers == self.modifiers {
+            return None;
+        }
+
+        self.modifiers = modifiers;
+        Some(WindowEvent::ModifiersChanged(modifiers))
+    }
+
+    fn physical_position(&self, position: Point) -> PhysicalPosition<f64> {
+        PhysicalPosition::new(
+            position.x * self.window_info.scale_x(),
+            position.y * self.window_info.scale_y(),
+        )
+    }
+}
+
+/// The events a single baseview event was translated into, see [`WinitCompat::translate()`].
+#[derive(Debug)]
+pub struct Translated {
+    first: Option<WindowEvent>,
+    event: Option<WindowEvent>,
+    /// The items of a drop, which are turned into events one at a time so their paths don't have to
+    /// be copied.
+    dropped: std::vec::IntoIter<Data>,
+}
+
+impl Iterator for Translated {
+    type Item = WindowEvent;
+
+    fn next(&mut self) -> Option<WindowEvent> {
+        if let Some(event) = self.first.take().or_else(|| self.event.take()) {
+            return Some(event);
+        }
+
+        // Only files can be dropped in `winit`
+        self.dropped.find_map(|data| match data {
+            Data::Filepath(path) => Some(WindowEvent::DroppedFile(path)),
+            _ => None,
+        })
+    }
+}
+
+/// The modifiers of the mouse events that have any.
+fn mouse_event_modifiers(event: &MouseEvent) -> Option<Modifiers> {
+    match event {
+        MouseEvent::CursorMoved { modifiers, .. }
+        | MouseEvent::ButtonPressed { modifiers, .. }
+        | MouseEvent::ButtonReleased { modifiers, .. }
+        | MouseEvent::WheelScrolled { modifiers, .. }
+        | MouseEvent::PenMoved { modifiers, .. } => Some(*modifiers),
+        MouseEvent::CursorEntered | MouseEvent::CursorLeft => None,
+    }
+}
+
+fn key_event(event: KeyboardEvent) -> KeyEvent {
+    let state = match event.state {
+        KeyState::Down => ElementState::Pressed,
+        KeyState::Up => ElementState::Released,
+    };
+    let text = match &event.key {
+        Key::Character(text) if state.is_pressed() => Some(text.clone()),
+        _ => None,
+    };
+
+    KeyEvent {
+        physical_key: event.code,
+        logical_key: event.key,
+        text,
+        location: event.location,
+        state,
+        repeat: event.repeat,
+    }
+}
+
+/// The window as seen through `winit`'s `Window` API, see [`WinitCompat::window()`]. Changes to
+/// the window are queued just like with the [`EventContext`] it wraps, so they're only carried out
+/// once the handler returns.
+pub struct CompatWindow<'a, 'b> {
+    context: &'a mut EventContext<'b>,
+    compat: &'a mut WinitCompat,
+}
+
+impl<'b> CompatWindow<'_, 'b> {
+    /// The wrapped context, for everything that isn't part of `winit`'s API.
+    pub fn context(&mut self) -> &mut EventContext<'b> {
+        self.context
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.context.id()
+    }
+
+    /// The window's size in physical pixels, as of the last translated
+    /// [`WindowEvent::Resized`](crate::WindowEvent::Resized) event.
+    pub fn inner_size(&self) -> PhysicalSize<u32> {
+        let size = self.compat.window_info.physical_size();
+        PhysicalSize::new(size.width, size.height)
+    }
+
+    /// The window's scale factor. Windows with a different scale factor for each axis report the
+    /// horizontal one.
+    pub fn scale_factor(&self) -> f64 {
+        self.compat.window_info.scale_x()
+    }
+
+    /// Resize the window. This always returns `None`, since the window is only resized once the
+    /// handler returns. A [`WindowEvent::Resized`] event follows if the size changed.
+    pub fn request_inner_size(&mut self, size: PhysicalSize<u32>) -> Option<PhysicalSize<u32>> {
+        let info = &self.compat.window_info;
+        let size =
+            Size::new(size.width as f64 / info.scale_x(), size.height as f64 / info.scale_y());
+        self.context.resize(size);
+
+        None
+    }
+
+    /// Make sure the handler's [`on_frame()`][crate::WindowHandler::on_frame] gets called, even
+    /// if the window isn't animating. The whole window is invalidated.
+    pub fn request_redraw(&mut self) {
+        let size = self.compat.window_info.logical_size();
+        self.context.invalidate_rect(Rect::new(0.0, 0.0, size.width, size.height));
+    }
+
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.compat.cursor_icon = icon;
+        if self.compat.cursor_visible {
+            self.context.set_mouse_cursor(icon.into());
+        }
+    }
+
+    /// Hide the cursor while it's over the window. The last icon is restored when it's shown
+    /// again.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.compat.cursor_visible = visible;
+        let cursor = if visible { self.compat.cursor_icon.into() } else { MouseCursor::Hidden };
+        self.context.set_mouse_cursor(cursor);
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.context.has_focus()
+    }
+
+    /// Always returns `Some`, since baseview knows whether its windows are visible on every
+    /// platform.
+    pub fn is_visible(&self) -> Option<bool> {
+        Some(self.context.is_visible())
+    }
+}
+
+#[cfg(feature = "rwh_05")]
+unsafe impl rwh_05::HasRawWindowHandle for CompatWindow<'_, '_> {
+    fn raw_window_handle(&self) -> rwh_05::RawWindowHandle {
+        rwh_05::HasRawWindowHandle::raw_window_handle(&*self.context)
+    }
+}
+
+#[cfg(feature = "rwh_05")]
+unsafe impl rwh_05::HasRawDisplayHandle for CompatWindow<'_, '_> {
+    fn raw_display_handle(&self) -> rwh_05::RawDisplayHandle {
+        rwh_05::HasRawDisplayHandle::raw_display_handle(&*self.context)
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl rwh_06::HasWindowHandle for CompatWindow<'_, '_> {
+    fn window_handle(&self) -> Result<rwh_06::WindowHandle<'_>, rwh_06::HandleError> {
+        rwh_06::HasWindowHandle::window_handle(&*self.context)
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl rwh_06::HasDisplayHandle for CompatWindow<'_, '_> {
+    fn display_handle(&self) -> Result<rwh_06::DisplayHandle<'_>, rwh_06::HandleError> {
+        rwh_06::HasDisplayHandle::display_handle(&*self.context)
+    }
+}
@@ -1,6 +1,6 @@
 use std::os::raw::c_char;
 
-use crate::MouseCursor;
+use crate::{CursorIcon, MouseCursor};
 
 fn create_empty_cursor(display: *mut x11::xlib::Display) -> Option<u32> {
     let data = 0;
@@ -100,7 +100,49 @@ pub(super) fn get_xcursor(display: *mut x11::xlib::Display, cursor: MouseCursor)
         MouseCursor::NeswResize => loadn(&[b"fd_double_arrow\0", b"size_fdiag\0"]),
         MouseCursor::ColResize => loadn(&[b"split_h\0", b"h_double_arrow\0"]),
         MouseCursor::RowResize => loadn(&[b"split_v\0", b"v_double_arrow\0"]),
+
+        // Custom cursors are created up front by `create_image_cursor()`, so this one belongs to
+        // another window
+        MouseCursor::Custom(_) => None,
     };
 
     cursor.or_else(|| load(b"left_ptr\0")).unwrap_or(0)
 }
+
+/// Create a cursor from the icon's image. This needs the RENDER extension, so the icon's fallback
+/// cursor is loaded instead if the X server doesn't support it.
+pub(super) fn create_image_cursor(display: *mut x11::xlib::Display, icon: &CursorIcon) -> u32 {
+    if unsafe { x11::xcursor::XcursorSupportsARGB(display) } == 0 {
+        return get_xcursor(display, icon.fallback());
+    }
+
+    unsafe {
+        let image = x11::xcursor::XcursorImageCreate(icon.width() as i32, icon.height() as i32);
+        if image.is_null() {
+            return get_xcursor(display, icon.fallback());
+        }
+
+        let (hotspot_x, hotspot_y) = icon.hotspot();
+        (*image).xhot = hotspot_x;
+        (*image).yhot = hotspot_y;
+
+        // Xcursor expects premultiplied ARGB pixels
+        let pixels = std::slice::from_raw_parts_mut(
+            (*image).pixels,
+            icon.width() as usize * icon.height() as usize,
+        );
+        for (pixel, rgba) in pixels.iter_mut().zip(icon.rgba().chunks_exact(4)) {
+            let alpha = rgba[3] as u32;
+            let premultiply = |channel: u8| (channel as u32 * alpha + 127) / 255;
+            *pixel = (alpha << 24)
+                | (premultiply(rgba[0]) << 16)
+                | (premultiply(rgba[1]) << 8)
+                | premultiply(rgba[2]);
+        }
+
+        let cursor = x11::xcursor::XcursorImageLoadCursor(display, image);
+        x11::xcursor::XcursorImageDestroy(image);
+
+        cursor as u32
+    }
+}
@@ -1,15 +1,200 @@
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use percent_encoding::{percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+use super::window::Window;
+use super::xcb_connection::DndAtoms;
 use super::XcbConnection;
 use crate::event::Data;
-use xcb::{self, ffi, GenericError};
+use crate::{DragImage, DropEffect, MouseCursor};
+use xcb::{self, ffi, Atom, GenericError};
+
+/// The pointer events the window receives while the pointer is grabbed for a drag. The crossing
+/// events are needed to notice when the grab gets broken.
+const DRAG_EVENT_MASK: u32 = xcb::EVENT_MASK_BUTTON_RELEASE
+    | xcb::EVENT_MASK_POINTER_MOTION
+    | xcb::EVENT_MASK_ENTER_WINDOW
+    | xcb::EVENT_MASK_LEAVE_WINDOW;
+
+/// The largest chunk of pixel data sent in a single `PutImage` request, which stays well below the
+/// core protocol's request size limit.
+const PUT_IMAGE_CHUNK_SIZE: usize = 1 << 16;
+
+/// An override-redirect window showing the drag image, which is moved along with the cursor. X11
+/// doesn't have drag images of its own, so toolkits draw them in a window like this.
+struct DragImageWindow {
+    window: u32,
+    pixmap: u32,
+    colormap: u32,
+    hotspot: (i16, i16),
+}
+
+impl DragImageWindow {
+    /// Show `image` with its hotspot at the given root window coordinates. Returns `None` if the
+    /// screen doesn't have a 32-bit visual. Without a compositing manager, the image's transparent
+    /// parts are shown as black.
+    fn create(conn: &XcbConnection, image: &DragImage, (x, y): (i16, i16)) -> Option<Self> {
+        let setup = conn.conn.get_setup();
+        let screen = setup.roots().nth(conn.xlib_display as usize).unwrap();
+        let visual = Window::find_visual_for_depth(&screen, 32)?;
+        let width = u16::try_from(image.width()).ok()?;
+        let height = u16::try_from(image.height()).ok()?;
+        let hotspot = (image.hotspot().0 as i16, image.hotspot().1 as i16);
+
+        // The pixels are premultiplied ARGB, in the X server's byte order
+        let big_endian = setup.image_byte_order() == xcb::IMAGE_ORDER_MSB_FIRST as u8;
+        let pixels: Vec<u8> = image
+            .rgba()
+            .chunks_exact(4)
+            .flat_map(|rgba| {
+                let alpha = rgba[3] as u32;
+                let premultiply = |channel: u8| (channel as u32 * alpha + 127) / 255;
+                let pixel = (alpha << 24)
+                    | (premultiply(rgba[0]) << 16)
+                    | (premultiply(rgba[1]) << 8)
+                    | premultiply(rgba[2]);
+                if big_endian {
+                    pixel.to_be_bytes()
+                } else {
+                    pixel.to_le_bytes()
+                }
+            })
+            .collect();
+
+        let pixmap = conn.conn.generate_id();
+        xcb::create_pixmap(&conn.conn, 32, pixmap, screen.root(), width, height);
+        let gc = conn.conn.generate_id();
+        xcb::create_gc(&conn.conn, gc, pixmap, &[]);
+        let row_len = width as usize * 4;
+        let rows_per_chunk = (PUT_IMAGE_CHUNK_SIZE / row_len).max(1);
+        for (i, chunk) in pixels.chunks(rows_per_chunk * row_len).enumerate() {
+            xcb::put_image(
+                &conn.conn,
+                xcb::IMAGE_FORMAT_Z_PIXMAP as u8,
+                pixmap,
+                gc,
+                width,
+                (chunk.len() / row_len) as u16,
+                0,
+                (i * rows_per_chunk) as i16,
+                0,
+                32,
+                chunk,
+            );
+        }
+        xcb::free_gc(&conn.conn, gc);
+
+        // The window uses the image as its background, so the X server redraws it by itself
+        let colormap = conn.conn.generate_id();
+        xcb::create_colormap(
+            &conn.conn,
+            xcb::COLORMAP_ALLOC_NONE as u8,
+            colormap,
+            screen.root(),
+            visual,
+        );
+        let window = conn.conn.generate_id();
+        xcb::create_window(
+            &conn.conn,
+            32,
+            window,
+            screen.root(),
+            x - hotspot.0,
+            y - hotspot.1,
+            width,
+            height,
+            0,
+            xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+            visual,
+            &[
+                (xcb::CW_BACK_PIXMAP, pixmap),
+                (xcb::CW_BORDER_PIXEL, 0),
+                (xcb::CW_OVERRIDE_REDIRECT, 1),
+                (xcb::CW_COLORMAP, colormap),
+            ],
+        );
+        xcb::map_window(&conn.conn, window);
+        conn.conn.flush();
+
+        Some(Self { window, pixmap, colormap, hotspot })
+    }
+
+    fn move_to(&self, conn: &XcbConnection, x: i16, y: i16) {
+        xcb::configure_window(
+            &conn.conn,
+            self.window,
+            &[
+                (xcb::CONFIG_WINDOW_X as u16, (x - self.hotspot.0) as i32 as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, (y - self.hotspot.1) as i32 as u32),
+            ],
+        );
+    }
+
+    fn destroy(self, conn: &XcbConnection) {
+        xcb::destroy_window(&conn.conn, self.window);
+        xcb::free_pixmap(&conn.conn, self.pixmap);
+        xcb::free_colormap(&conn.conn, self.colormap);
+    }
+}
+
+/// The topmost mapped child of the root window at the given position, ignoring `skip`. Returns the
+/// root window if there is none.
+fn top_level_window_at(conn: &XcbConnection, root: u32, skip: u32, x: i16, y: i16) -> u32 {
+    let Ok(tree) = xcb::query_tree(&conn.conn, root).get_reply() else {
+        return root;
+    };
+
+    // All requests are sent before waiting for the first reply
+    let candidates: Vec<_> = tree
+        .children()
+        .iter()
+        .filter(|&&window| window != skip)
+        .map(|&window| {
+            let attributes = xcb::get_window_attributes(&conn.conn, window);
+            let geometry = xcb::get_geometry(&conn.conn, window);
+            (window, attributes, geometry)
+        })
+        .collect();
+
+    // The children are listed from the bottom of the stacking order to the top
+    candidates
+        .into_iter()
+        .rev()
+        .find_map(|(window, attributes, geometry)| {
+            let viewable = attributes
+                .get_reply()
+                .is_ok_and(|reply| reply.map_state() == xcb::MAP_STATE_VIEWABLE as u8);
+            let contains_cursor = viewable
+                && geometry.get_reply().is_ok_and(|geometry| {
+                    let border = geometry.border_width() as i32 * 2;
+                    let (x, y) = (x as i32, y as i32);
+                    let (left, top) = (geometry.x() as i32, geometry.y() as i32);
+                    x >= left
+                        && y >= top
+                        && x < left + geometry.width() as i32 + border
+                        && y < top + geometry.height() as i32 + border
+                });
+
+            contains_cursor.then_some(window)
+        })
+        .unwrap_or(root)
+}
 
 #[derive(Default)]
 pub(crate) struct DragHandler {
     /// The data we're dragging
     data: Option<Data>,
+    /// What the window asked the target to do with the data
+    effect: DropEffect,
     /// Are we dragging something right now?
     active: bool,
-    /// Are we over a drag target that will accept the drop?
-    accept: bool,
+    /// The action the target under the cursor will perform if the data is dropped, or `None` if
+    /// it won't accept the drop
+    accepted_effect: Option<DropEffect>,
+    /// Was the data dropped with `XdndActionAsk`? The target then only tells us what it did in
+    /// its `XdndFinished` message.
+    waiting_for_finished: bool,
     /// Are we waiting for a XdndStatus message?
     waiting_for_status: bool,
     /// Have we deferred sending a XdndPosition message because we're waiting for a status?
@@ -18,37 +203,158 @@ pub(crate) struct DragHandler {
     target_window: Option<u32>,
     /// Where is our cursor?
     position: (u32, u32),
+    /// Where is our cursor relative to our own window, in physical pixels?
+    window_position: (i16, i16),
+    /// Has the cursor moved, or has the target changed its mind about accepting the drop, since
+    /// this was last reported to the window handler?
+    moved: bool,
+    /// The timestamp of the last pointer event, which is sent along with the XDND messages.
+    time: u32,
+    /// The action the target performed with the last drag's data, or `None` if the drag was
+    /// cancelled or rejected, if that hasn't been reported to the window handler yet.
+    outcome: Option<Option<DropEffect>>,
+    /// The window showing the drag image, if the drag has one.
+    image_window: Option<DragImageWindow>,
+    /// The cursor currently set on the pointer grab, if the pointer is grabbed.
+    grab_cursor: Option<MouseCursor>,
 }
 
 impl DragHandler {
-    pub fn activate(&mut self, data: Data) {
+    pub fn activate(&mut self, data: Data, effect: DropEffect) {
+        debug!(target: "baseview::dnd", ?data, ?effect, "outgoing drag started");
         self.data = Some(data);
+        self.effect = effect;
         self.active = true;
-        self.accept = true;
+        self.accepted_effect = Some(effect);
+        self.waiting_for_finished = false;
         self.waiting_for_status = false;
         self.deferred_position_message = false;
         self.target_window = None;
+        self.moved = false;
+        self.time = xcb::CURRENT_TIME;
+        self.outcome = None;
     }
 
     pub fn is_active(&self) -> bool {
         self.active
     }
 
-    pub fn will_accept(&self) -> bool {
-        self.accept
+    /// Whether the last drag ended, and what the target did with the data. This only returns the
+    /// outcome once.
+    pub fn take_outcome(&mut self) -> Option<Option<DropEffect>> {
+        self.outcome.take()
+    }
+
+    /// The cursor's position relative to this window and whether the target under it will accept
+    /// the drop, if either changed since the last call and the drag is still going on.
+    pub fn take_moved(&mut self) -> Option<((i16, i16), bool)> {
+        if !self.active || !std::mem::take(&mut self.moved) {
+            return None;
+        }
+
+        Some((self.window_position, self.accepted_effect.is_some()))
     }
 
-    pub fn start(&self, conn: &XcbConnection, this_window: u32) {
-        xcb::set_selection_owner_checked(&conn.conn, this_window, conn.atoms.dnd_selection, 0);
+    /// Take ownership of the XDND selection and grab the pointer. Without the grab, the motion
+    /// and button release events would stop arriving as soon as the cursor leaves the window. The
+    /// drag is cancelled right away if the pointer can't be grabbed.
+    pub fn start(&mut self, conn: &XcbConnection, this_window: u32, image: Option<&DragImage>) {
+        xcb::set_selection_owner_checked(&conn.conn, this_window, conn.atoms.dnd.selection, 0);
         xcb::change_property_checked(
             &conn.conn,
             ffi::XCB_PROP_MODE_REPLACE as u8,
             this_window,
-            conn.atoms.dnd_type_list,
+            conn.atoms.dnd.type_list,
             ffi::XCB_ATOM_ATOM,
             32,
-            &[conn.atoms.dnd_uri_list],
+            &[conn.atoms.dnd.uri_list],
         );
+        if self.effect == DropEffect::Ask {
+            // Targets show these actions to the user when the data is dropped
+            let atoms = &conn.atoms.dnd;
+            xcb::change_property(
+                &conn.conn,
+                ffi::XCB_PROP_MODE_REPLACE as u8,
+                this_window,
+                atoms.action_list,
+                ffi::XCB_ATOM_ATOM,
+                32,
+                &[atoms.action_copy, atoms.action_move],
+            );
+            xcb::change_property(
+                &conn.conn,
+                ffi::XCB_PROP_MODE_REPLACE as u8,
+                this_window,
+                atoms.action_description,
+                ffi::XCB_ATOM_STRING,
+                8,
+                b"Copy\0Move\0",
+            );
+        }
+
+        let grabbed = xcb::grab_pointer(
+            &conn.conn,
+            false,
+            this_window,
+            DRAG_EVENT_MASK as u16,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::NONE,
+            conn.get_cursor_xid(MouseCursor::HandGrabbing),
+            xcb::CURRENT_TIME,
+        )
+        .get_reply()
+        .is_ok_and(|reply| reply.status() == xcb::GRAB_STATUS_SUCCESS as u8);
+        if !grabbed {
+            debug!(target: "baseview::dnd", "could not grab the pointer for the outgoing drag");
+            let _ = self.cancel(conn, this_window);
+            return;
+        }
+        self.grab_cursor = Some(MouseCursor::HandGrabbing);
+
+        if let Some(image) = image {
+            let root =
+                conn.conn.get_setup().roots().nth(conn.xlib_display as usize).unwrap().root();
+            if let Ok(pointer) = xcb::query_pointer(&conn.conn, root).get_reply() {
+                self.image_window =
+                    DragImageWindow::create(conn, image, (pointer.root_x(), pointer.root_y()));
+            }
+        }
+    }
+
+    /// Show whether the target under the cursor will accept the drop. While the pointer is
+    /// grabbed, the cursor is set on the grab instead of on the window. Targets send a status for
+    /// every position message, so the grab is only changed when the cursor actually changes.
+    pub fn update_cursor(&mut self, conn: &XcbConnection) {
+        let cursor = if self.accepted_effect.is_some() {
+            MouseCursor::HandGrabbing
+        } else {
+            MouseCursor::NotAllowed
+        };
+        if self.grab_cursor.is_none() || self.grab_cursor == Some(cursor) {
+            return;
+        }
+
+        // The event loop flushes this before it waits for the next event
+        xcb::change_active_pointer_grab(
+            &conn.conn,
+            conn.get_cursor_xid(cursor),
+            xcb::CURRENT_TIME,
+            DRAG_EVENT_MASK as u16,
+        );
+        self.grab_cursor = Some(cursor);
+    }
+
+    /// Release the pointer grab. The drag's outcome is set separately, since it may only be known
+    /// once the target has finished.
+    fn end(&mut self, conn: &XcbConnection) {
+        self.active = false;
+        self.grab_cursor = None;
+        if let Some(image_window) = self.image_window.take() {
+            image_window.destroy(conn);
+        }
+        xcb::ungrab_pointer(&conn.conn, self.time);
+        conn.conn.flush();
     }
 
     pub fn motion(
@@ -57,33 +363,42 @@ impl DragHandler {
         let setup = conn.conn.get_setup();
         let screen = setup.roots().nth(conn.xlib_display as usize).unwrap();
 
-        let mut target_window = screen.root();
+        let root = screen.root();
         let abs_x = event.root_x() as i16;
         let abs_y = event.root_y() as i16;
-        loop {
+        let mut target_window = match &self.image_window {
+            // The drag image is right under the cursor, so the top-level window below it is found
+            // by skipping it
+            Some(image_window) => {
+                image_window.move_to(conn, abs_x, abs_y);
+                top_level_window_at(conn, root, image_window.window, abs_x, abs_y)
+            }
+            None => root,
+        };
+        if target_window != root || self.image_window.is_none() {
             // Find the (target) window under the cursor
-            let r =
-                xcb::translate_coordinates(&conn.conn, screen.root(), target_window, abs_x, abs_y)
-                    .get_reply();
-            if let Ok(r) = r {
+            while let Ok(r) =
+                xcb::translate_coordinates(&conn.conn, root, target_window, abs_x, abs_y)
+                    .get_reply()
+            {
                 if r.child() == 0 {
                     break;
-                } else {
-                    target_window = r.child()
                 }
+                target_window = r.child();
             }
         }
         if Some(target_window) != self.target_window {
             // Enter window
+            debug!(target: "baseview::dnd", target_window, "outgoing drag entered window");
             self.target_window = Some(target_window);
             conn.send_client_message(
                 target_window,
-                conn.atoms.dnd_enter,
+                conn.atoms.dnd.enter,
                 [
                     this_window,
                     (5 << 24) // Version
                     | 0, // All types supported listed in the rest of this data (no need to fetch more types)
-                    conn.atoms.dnd_uri_list,
+                    conn.atoms.dnd.uri_list,
                     0,
                     0,
                 ],
@@ -93,19 +408,16 @@ impl DragHandler {
         let x = event.root_x() as u32;
         let y = event.root_y() as u32;
         self.position = (x, y);
+        self.window_position = (event.event_x(), event.event_y());
+        self.moved = true;
+        self.time = event.time();
         if !self.waiting_for_status {
             self.waiting_for_status = true;
             self.deferred_position_message = false;
             conn.send_client_message(
                 target_window,
-                conn.atoms.dnd_position,
-                [
-                    this_window,
-                    0,
-                    (x << 16) | y,
-                    99, // TODO set some actual time?
-                    conn.atoms.dnd_action_copy,
-                ],
+                conn.atoms.dnd.position,
+                [this_window, 0, (x << 16) | y, self.time, conn.atoms.dnd.action(self.effect)],
             )
         } else {
             self.deferred_position_message = true;
@@ -116,18 +428,27 @@ impl DragHandler {
     pub fn handle_status(
         &mut self, status: &[u32], conn: &XcbConnection, this_window: u32,
     ) -> Result<(), GenericError> {
-        self.accept = status[1] & 1 == 1;
+        // The target echoes the action it will perform, which may not be the requested one. Targets
+        // using a version of the protocol before 2 don't include an action.
+        let accepted_effect = if status[1] & 1 == 1 {
+            Some(conn.atoms.dnd.effect(status[4]).unwrap_or(DropEffect::Copy))
+        } else {
+            None
+        };
+        self.moved |= accepted_effect.is_some() != self.accepted_effect.is_some();
+        self.accepted_effect = accepted_effect;
+        trace!(target: "baseview::dnd", accepted_effect = ?self.accepted_effect, "received XdndStatus");
         self.waiting_for_status = false;
         if self.deferred_position_message && self.target_window.is_some() {
             conn.send_client_message(
                 self.target_window.unwrap(),
-                conn.atoms.dnd_position,
+                conn.atoms.dnd.position,
                 [
                     this_window,
                     0,
                     (self.position.0 << 16) | self.position.1,
-                    99, // TODO set to some actual time?
-                    conn.atoms.dnd_action_copy,
+                    self.time,
+                    conn.atoms.dnd.action(self.effect),
                 ],
             )
         } else {
@@ -135,81 +456,247 @@ impl DragHandler {
         }
     }
 
+    /// Answer a drop target's request for the dragged data. Targets may first ask for `TARGETS` to
+    /// find out which formats are offered. Requests that can't be answered are refused by replying
+    /// with a `None` property, so the target doesn't have to wait for a reply that never comes.
     pub fn selection_request(
         &mut self, event: &xcb::SelectionRequestEvent, conn: &XcbConnection,
     ) -> Result<(), GenericError> {
-        match self.data.as_ref() {
-            Some(Data::Filepath(p)) => unsafe {
-                let property =
-                    if event.property() == 0 { event.selection() } else { event.property() };
-                let path = format!("file://{}", p.clone().into_os_string().into_string().unwrap());
+        let reply = selection_reply(&conn.atoms.dnd, event.target(), self.data.as_ref());
+        trace!(
+            target: "baseview::dnd",
+            target_atom = event.target(),
+            refused = reply.is_none(),
+            "outgoing drag selection requested"
+        );
 
+        let property = match reply {
+            Some(reply) => unsafe {
+                // Obsolete clients don't pass a property, in which case the target is used instead
+                let property =
+                    if event.property() == xcb::NONE { event.target() } else { event.property() };
+                let element_size = reply.format as usize / 8;
                 let cookie = ffi::xcb_change_property_checked(
                     conn.conn.get_raw_conn(),
                     ffi::XCB_PROP_MODE_REPLACE as _,
                     event.requestor(),
                     property,
-                    conn.atoms.dnd_uri_list,
-                    8,
-                    path.len() as _,
-                    path.as_ptr() as _,
+                    reply.property_type,
+                    reply.format,
+                    (reply.data.len() / element_size) as _,
+                    reply.data.as_ptr() as _,
                 );
                 xcb::base::VoidCookie { cookie, conn: &conn.conn, checked: true }
                     .request_check()?;
 
-                let msg = ffi::xcb_selection_notify_event_t {
-                    response_type: ffi::XCB_SELECTION_NOTIFY,
-                    requestor: event.requestor(),
-                    selection: event.selection(),
-                    target: event.target(),
-                    time: event.time(),
-                    property,
-                    sequence: 0,
-                    pad0: 0,
-                };
-                let cookie = ffi::xcb_send_event_checked(
-                    conn.conn.get_raw_conn(),
-                    0,
-                    event.requestor(),
-                    0,
-                    &msg as *const _ as _,
-                );
-                xcb::base::VoidCookie { cookie, conn: &conn.conn, checked: true }.request_check()
+                property
             },
-            _ => Ok(()),
+            None => xcb::NONE,
+        };
+
+        let msg = ffi::xcb_selection_notify_event_t {
+            response_type: ffi::XCB_SELECTION_NOTIFY,
+            requestor: event.requestor(),
+            selection: event.selection(),
+            target: event.target(),
+            time: event.time(),
+            property,
+            sequence: 0,
+            pad0: 0,
+        };
+        unsafe {
+            let cookie = ffi::xcb_send_event_checked(
+                conn.conn.get_raw_conn(),
+                0,
+                event.requestor(),
+                0,
+                &msg as *const _ as _,
+            );
+            xcb::base::VoidCookie { cookie, conn: &conn.conn, checked: true }.request_check()
         }
     }
 
-    pub fn do_drop(&mut self, conn: &XcbConnection, this_window: u32) -> Result<(), GenericError> {
-        if !self.accept {
-            return self.cancel(conn, this_window);
-        }
-        self.active = false;
+    /// Drop the data onto the target under the cursor. `time` is the timestamp of the button
+    /// release that ended the drag.
+    pub fn do_drop(
+        &mut self, conn: &XcbConnection, this_window: u32, time: u32,
+    ) -> Result<(), GenericError> {
+        self.time = time;
+        let (target_window, effect) = match (self.target_window, self.accepted_effect) {
+            (Some(target_window), Some(effect)) => (target_window, effect),
+            _ => return self.cancel(conn, this_window),
+        };
+        debug!(target: "baseview::dnd", target_window, ?effect, "outgoing drag dropped");
         // We don't set self.data to None because we still need to handle the selection_request
-        if let Some(target_window) = self.target_window {
-            conn.send_client_message(
-                target_window,
-                conn.atoms.dnd_drop,
-                [
-                    this_window,
-                    0,
-                    99, // TODO set to some actual time?
-                    0,
-                    0,
-                ],
-            )
+        self.end(conn);
+        if effect == DropEffect::Ask {
+            self.waiting_for_finished = true;
         } else {
-            Ok(())
+            self.outcome = Some(Some(effect));
+        }
+        conn.send_client_message(target_window, conn.atoms.dnd.drop, [this_window, 0, time, 0, 0])
+    }
+
+    /// Handle the target's `XdndFinished` message. This only matters for drops that were accepted
+    /// with `XdndActionAsk`, since the action the user picked is only known at this point.
+    pub fn handle_finished(&mut self, conn: &XcbConnection, finished: &[u32]) {
+        if !self.waiting_for_finished {
+            return;
         }
+
+        self.waiting_for_finished = false;
+        let effect = if finished[1] & 1 == 1 {
+            // Targets using a version of the protocol before 5 don't report the action
+            Some(conn.atoms.dnd.effect(finished[2]).unwrap_or(DropEffect::Copy).resolved())
+        } else {
+            None
+        };
+        trace!(target: "baseview::dnd", ?effect, "received XdndFinished");
+        self.outcome = Some(effect);
     }
 
     pub fn cancel(&mut self, conn: &XcbConnection, this_window: u32) -> Result<(), GenericError> {
-        self.active = false;
+        debug!(target: "baseview::dnd", target_window = ?self.target_window, "outgoing drag cancelled");
+        self.end(conn);
+        self.outcome = Some(None);
         self.data = None;
         if let Some(target_window) = self.target_window {
-            conn.send_client_message(target_window, conn.atoms.dnd_leave, [this_window, 0, 0, 0, 0])
+            conn.send_client_message(target_window, conn.atoms.dnd.leave, [this_window, 0, 0, 0, 0])
         } else {
             Ok(())
         }
     }
 }
+
+/// The property to write in reply to a request for the dragged data, see [`selection_reply()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SelectionReply {
+    property_type: Atom,
+    /// The size of the property's elements in bits, either 8 or 32.
+    format: u8,
+    data: Vec<u8>,
+}
+
+/// The reply to a drop target's request for `target`, or `None` if the request should be refused.
+/// Only file paths can be dragged out of the window, so the data is offered as a
+/// `text/uri-list`. `MULTIPLE` requests are refused, since a drop target has no reason to ask for
+/// more than one format at once.
+fn selection_reply(atoms: &DndAtoms, target: Atom, data: Option<&Data>) -> Option<SelectionReply> {
+    let path = match data {
+        Some(Data::Filepath(path)) => path,
+        _ => return None,
+    };
+
+    if target == atoms.targets {
+        let data = [atoms.targets, atoms.uri_list].into_iter().flat_map(u32::to_ne_bytes);
+        Some(SelectionReply { property_type: xcb::ATOM_ATOM, format: 32, data: data.collect() })
+    } else if target == atoms.uri_list {
+        Some(SelectionReply { property_type: atoms.uri_list, format: 8, data: file_uri(path) })
+    } else {
+        None
+    }
+}
+
+/// Build a `file://` URI for `path`. Paths on Linux are arbitrary bytes, so every segment's bytes
+/// are percent-encoded as is instead of requiring the path to be valid UTF-8.
+fn file_uri(path: &Path) -> Vec<u8> {
+    let mut uri = b"file://".to_vec();
+    for (i, segment) in path.as_os_str().as_bytes().split(|&b| b == b'/').enumerate() {
+        if i > 0 {
+            uri.push(b'/');
+        }
+        uri.extend(percent_encode(segment, PATH_SEGMENT_ENCODE_SET).flat_map(str::bytes));
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::x11::drop_handler::DropHandler;
+
+    /// Stands in for the `MULTIPLE` atom, which isn't one of the DND atoms.
+    const MULTIPLE: Atom = 500;
+
+    fn atoms() -> DndAtoms {
+        DndAtoms::from_atoms(std::array::from_fn(|i| 100 + i as Atom))
+    }
+
+    fn file(path: &str) -> Data {
+        Data::Filepath(path.into())
+    }
+
+    #[test]
+    fn targets() {
+        let atoms = atoms();
+        let reply = selection_reply(&atoms, atoms.targets, Some(&file("/tmp/a.wav"))).unwrap();
+
+        assert_eq!(reply.property_type, xcb::ATOM_ATOM);
+        assert_eq!(reply.format, 32);
+        let offered: Vec<Atom> = reply
+            .data
+            .chunks_exact(4)
+            .map(|atom| u32::from_ne_bytes(atom.try_into().unwrap()))
+            .collect();
+        assert_eq!(offered, [atoms.targets, atoms.uri_list]);
+    }
+
+    #[test]
+    fn uri_list() {
+        let atoms = atoms();
+        let reply = selection_reply(&atoms, atoms.uri_list, Some(&file("/tmp/a b.wav"))).unwrap();
+
+        assert_eq!(
+            reply,
+            SelectionReply {
+                property_type: atoms.uri_list,
+                format: 8,
+                data: b"file:///tmp/a%20b.wav".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn refused_requests() {
+        let atoms = atoms();
+        let data = file("/tmp/a.wav");
+
+        assert_eq!(selection_reply(&atoms, MULTIPLE, Some(&data)), None);
+        assert_eq!(selection_reply(&atoms, xcb::ATOM_STRING, Some(&data)), None);
+        assert_eq!(selection_reply(&atoms, xcb::NONE, Some(&data)), None);
+        // After the drag was cancelled, or for data that isn't a file
+        assert_eq!(selection_reply(&atoms, atoms.uri_list, None), None);
+        assert_eq!(selection_reply(&atoms, atoms.targets, None), None);
+        assert_eq!(
+            selection_reply(&atoms, atoms.uri_list, Some(&Data::String("text".into()))),
+            None
+        );
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(file_uri(Path::new("/")), b"file:///");
+        assert_eq!(file_uri(Path::new("/tmp/50% #1?.wav")), b"file:///tmp/50%25%20%231%3F.wav");
+        assert_eq!(file_uri(Path::new("/tmp/Grüße.wav")), b"file:///tmp/Gr%C3%BC%C3%9Fe.wav");
+        assert_eq!(
+            file_uri(Path::new(OsStr::from_bytes(b"/tmp/\xff.wav"))),
+            b"file:///tmp/%FF.wav"
+        );
+    }
+
+    #[test]
+    fn file_uris_round_trip() {
+        for path in [
+            PathBuf::from("/tmp/a b/c.wav"),
+            PathBuf::from("/tmp/50% #1?.wav"),
+            PathBuf::from("/tmp/Grüße.wav"),
+            PathBuf::from(OsStr::from_bytes(b"/tmp/\xff\xfe.wav")),
+        ] {
+            assert_eq!(DropHandler::parse_data(&file_uri(&path)), Ok(vec![path.clone()]));
+        }
+    }
+}
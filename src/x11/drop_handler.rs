@@ -1,39 +1,34 @@
 // Adapted from https://github.com/rust-windowing/winit/blob/master/src/platform_impl/linux/x11/dnd.rs
-use std::{
-    io,
-    path::{Path, PathBuf},
-    str::Utf8Error,
-};
+use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
 
 use percent_encoding::percent_decode;
 use xcb::{Atom, GenericError};
+use xcb_util::icccm::GetTextPropertyReply;
 
+#[cfg(feature = "portal")]
+use super::portal::FileTransferPortal;
+use super::xcb_connection::DndAtoms;
 use super::XcbConnection;
+use crate::drop_effect::DropTargetEffect;
+use crate::{DragSourceInfo, DropEffect, DropError, PhyPoint};
 
-#[derive(Debug, Clone, Copy)]
-pub enum DndState {
-    Accepted,
-    Rejected,
+/// The types the drag's data can be requested as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransferType {
+    /// A `text/uri-list` with `file://` URIs.
+    UriList,
+    /// A key for the document portal's file transfer, see the `portal` module.
+    #[cfg(feature = "portal")]
+    PortalFileTransfer,
 }
 
-#[derive(Debug)]
-pub enum DndDataParseError {
-    EmptyData,
-    InvalidUtf8(Utf8Error),
-    HostnameSpecified(String),
-    UnexpectedProtocol(String),
-    UnresolvablePath(io::Error),
-}
-
-impl From<Utf8Error> for DndDataParseError {
-    fn from(e: Utf8Error) -> Self {
-        DndDataParseError::InvalidUtf8(e)
-    }
-}
-
-impl From<io::Error> for DndDataParseError {
-    fn from(e: io::Error) -> Self {
-        DndDataParseError::UnresolvablePath(e)
+impl TransferType {
+    fn atom(self, atoms: &DndAtoms) -> u32 {
+        match self {
+            TransferType::UriList => atoms.uri_list,
+            #[cfg(feature = "portal")]
+            TransferType::PortalFileTransfer => atoms.portal_file_transfer,
+        }
     }
 }
 
@@ -43,10 +38,28 @@ pub(crate) struct DropHandler {
     // Populated by XdndEnter event handler
     pub version: Option<u32>,
     pub type_list: Option<Vec<u32>>,
-    // Populated by XdndPosition event handler
+    /// The type the drag's data is requested as, picked from the type list by
+    /// [`Self::select_target()`]
+    pub target: Option<TransferType>,
+    /// Queried from the source window once per drag, since it's sent with every drag event.
+    pub source_info: Option<DragSourceInfo>,
+    // Populated by XdndPosition event handler. `XdndDrop` doesn't contain a position, so the drop
+    // happens at the last position received.
     pub source_window: Option<u32>,
+    pub position: Option<PhyPoint>,
+    /// The time of the last `XdndPosition` message, which the selection is converted for.
+    pub time: u32,
+    /// The root window's origin relative to this window, so the positions don't each need a
+    /// round-trip to translate them. Embedded windows aren't told when their host moves, so this
+    /// is only cached for the current drag.
+    pub root_offset: Option<PhyPoint>,
+    /// The source's requested action is also part of `XdndPosition`
+    pub effect: DropTargetEffect,
     // Populated by SelectionNotify event handler (triggered by XdndPosition event handler)
-    pub result: Option<Result<Vec<PathBuf>, DndDataParseError>>,
+    pub result: Option<Result<Vec<PathBuf>, DropError>>,
+    /// Kept across drags, so the session bus is only connected to once.
+    #[cfg(feature = "portal")]
+    portal: FileTransferPortal,
 }
 
 impl std::fmt::Debug for DropHandler {
@@ -54,7 +67,13 @@ impl std::fmt::Debug for DropHandler {
         f.debug_struct("DropHandler")
             .field("version", &self.version)
             .field("type_list", &self.type_list)
+            .field("target", &self.target)
+            .field("source_info", &self.source_info)
             .field("source_window", &self.source_window)
+            .field("position", &self.position)
+            .field("time", &self.time)
+            .field("root_offset", &self.root_offset)
+            .field("effect", &self.effect)
             .field("result", &self.result)
             .finish()
     }
@@ -62,40 +81,112 @@ impl std::fmt::Debug for DropHandler {
 
 impl DropHandler {
     pub fn reset(&mut self) {
+        trace!(target: "baseview::dnd", "incoming drag reset");
         self.version = None;
         self.type_list = None;
+        self.target = None;
+        self.source_info = None;
         self.source_window = None;
+        self.position = None;
+        self.time = xcb::base::CURRENT_TIME;
+        self.root_offset = None;
+        self.effect.reset();
         self.result = None;
     }
 
+    /// Pick the type to request the drag's data as from the source's type list, or `None` if none
+    /// of them can be turned into files. The paths in a sandboxed source's URI list may only exist
+    /// inside of its sandbox, so the document portal's file transfer is preferred.
+    pub fn select_target(&mut self, atoms: &DndAtoms) {
+        let type_list = self.type_list.as_deref().unwrap_or_default();
+        #[cfg(feature = "portal")]
+        let preferred = [TransferType::PortalFileTransfer, TransferType::UriList];
+        #[cfg(not(feature = "portal"))]
+        let preferred = [TransferType::UriList];
+
+        self.target = preferred.into_iter().find(|target| type_list.contains(&target.atom(atoms)));
+        debug!(target: "baseview::dnd", target = ?self.target, "selected the drag's data type");
+    }
+
+    /// Request the URI list instead after the portal failed to provide the files. Returns `false`
+    /// if the data wasn't requested from the portal, or if the source doesn't offer a URI list.
+    #[cfg(feature = "portal")]
+    pub fn fall_back_to_uri_list(&mut self, atoms: &DndAtoms) -> bool {
+        let offers_uri_list =
+            self.type_list.as_ref().is_some_and(|type_list| type_list.contains(&atoms.uri_list));
+        if self.target != Some(TransferType::PortalFileTransfer) || !offers_uri_list {
+            return false;
+        }
+
+        debug!(target: "baseview::dnd", "falling back to the URI list");
+        self.target = Some(TransferType::UriList);
+        true
+    }
+
+    /// Store the action the source requested in an `XdndPosition` message. Before version 2 of
+    /// the protocol the action was always `XdndActionCopy`.
+    pub fn update_requested_action(&mut self, conn: &XcbConnection, action: u32) {
+        let allowed = match self.version {
+            Some(version) if version >= 2 => conn.atoms.dnd.effect(action),
+            _ => Some(DropEffect::Copy),
+        };
+        self.effect.allowed = allowed;
+        self.effect.proposed = allowed.map(DropEffect::resolved);
+    }
+
+    /// The effect to report to the source in an `XdndStatus` message. Once the drag's data failed
+    /// to parse the drop is rejected, since the handler will never receive it.
+    pub fn status_effect(&self, effect: Option<DropEffect>) -> Option<DropEffect> {
+        match self.result {
+            Some(Err(_)) => None,
+            _ => effect,
+        }
+    }
+
+    /// Whether the handler receives the drop once it happens, which requires the drag's data to
+    /// have arrived and to contain at least one file. Other drops are rejected in the
+    /// `XdndFinished` message.
+    pub fn drop_received(&self) -> bool {
+        matches!(&self.result, Some(Ok(paths)) if !paths.is_empty())
+    }
+
+    /// Tell the source whether the drop would be accepted, and with which action. `None` rejects
+    /// the drop.
     pub fn send_status(
-        &self, conn: &XcbConnection, this_window: u32, target_window: u32, state: DndState,
+        &self, conn: &XcbConnection, this_window: u32, target_window: u32,
+        effect: Option<DropEffect>,
     ) -> Result<(), GenericError> {
-        let (accepted, action) = match state {
-            DndState::Accepted => (1, conn.atoms.dnd_action_private),
-            DndState::Rejected => (0, xcb::ATOM_NONE),
-        };
+        trace!(target: "baseview::dnd", target_window, ?effect, "sending XdndStatus");
+        let (accepted, action) = Self::accepted_action(conn, effect);
         conn.send_client_message(
             target_window,
-            conn.atoms.dnd_status,
-            [this_window, accepted, 0, 0, action.into()],
+            conn.atoms.dnd.status,
+            [this_window, accepted, 0, 0, action],
         )
     }
 
+    /// Tell the source that the drop has been handled, and which action was performed. `None`
+    /// means that the drop was rejected.
     pub fn send_finished(
-        &self, conn: &XcbConnection, this_window: u32, target_window: u32, state: DndState,
+        &self, conn: &XcbConnection, this_window: u32, target_window: u32,
+        effect: Option<DropEffect>,
     ) -> Result<(), GenericError> {
-        let (accepted, action) = match state {
-            DndState::Accepted => (1, conn.atoms.dnd_action_private),
-            DndState::Rejected => (0, xcb::ATOM_NONE),
-        };
+        debug!(target: "baseview::dnd", target_window, ?effect, "sending XdndFinished");
+        let (accepted, action) = Self::accepted_action(conn, effect);
         conn.send_client_message(
             target_window,
-            conn.atoms.dnd_finished,
+            conn.atoms.dnd.finished,
             [this_window, accepted, action, 0, 0],
         )
     }
 
+    fn accepted_action(conn: &XcbConnection, effect: Option<DropEffect>) -> (u32, u32) {
+        match effect {
+            Some(effect) => (1, conn.atoms.dnd.action(effect)),
+            None => (0, xcb::ATOM_NONE),
+        }
+    }
+
     pub fn get_type_list(
         &self, conn: &XcbConnection, source_window: u32,
     ) -> Result<Vec<Atom>, GenericError> {
@@ -103,7 +194,7 @@ impl DropHandler {
             &conn.conn,
             false,
             source_window,
-            conn.atoms.dnd_type_list,
+            conn.atoms.dnd.type_list,
             xcb::ATOM_ATOM,
             0,
             0,
@@ -112,48 +203,214 @@ impl DropHandler {
         .map(|r| r.value::<Atom>().to_vec())
     }
 
-    pub fn convert_selection(&self, conn: &XcbConnection, window: u32, time: u32) {
+    /// Find out which application the source window belongs to. The executable is looked up
+    /// through the window's `_NET_WM_PID`, which is only meaningful if the source runs on this
+    /// machine, and the instance name from `WM_CLASS` is used otherwise. Toolkits usually set that
+    /// to the name of the executable as well.
+    pub fn query_source_info(conn: &XcbConnection, source_window: u32) -> DragSourceInfo {
+        // Both requests are sent before waiting on either reply
+        let pid_cookie = xcb::get_property(
+            &conn.conn,
+            false,
+            source_window,
+            conn.atoms.net_wm_pid,
+            xcb::ATOM_CARDINAL,
+            0,
+            1,
+        );
+        let class_cookie = xcb_util::icccm::get_wm_class(&conn.conn, source_window);
+
+        let executable = pid_cookie
+            .get_reply()
+            .ok()
+            .and_then(|reply| reply.value::<u32>().first().copied())
+            .and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok())
+            .and_then(|path| Some(path.file_name()?.to_str()?.to_owned()));
+        let application = executable.or_else(|| {
+            let reply = class_cookie.get_reply().ok()?;
+            Some(reply.instance().to_owned()).filter(|instance| !instance.is_empty())
+        });
+
+        DragSourceInfo { platform_id: source_window as u64, application }
+    }
+
+    /// Request the drag's data as [`Self::target`].
+    pub fn convert_selection(&self, conn: &XcbConnection, window: u32) {
         xcb::convert_selection(
             &conn.conn,
             window,
-            conn.atoms.dnd_selection,
-            conn.atoms.dnd_uri_list,
-            conn.atoms.dnd_baseview_transfer,
-            time,
+            conn.atoms.dnd.selection,
+            self.target.unwrap_or(TransferType::UriList).atom(&conn.atoms.dnd),
+            conn.atoms.dnd.baseview_transfer,
+            self.time,
         );
     }
 
-    pub fn read_data(&self, conn: &XcbConnection, window: u32) -> Result<Vec<u8>, GenericError> {
-        xcb_util::icccm::get_text_property(&conn.conn, window, conn.atoms.dnd_baseview_transfer)
+    pub fn read_data(
+        &self, conn: &XcbConnection, window: u32,
+    ) -> Result<GetTextPropertyReply, GenericError> {
+        xcb_util::icccm::get_text_property(&conn.conn, window, conn.atoms.dnd.baseview_transfer)
             .get_reply()
-            .map(|r| r.name().as_bytes().to_vec())
     }
 
-    pub fn parse_data(&self, data: &mut [u8]) -> Result<Vec<PathBuf>, DndDataParseError> {
+    /// Whether a `SelectionNotify` reply with data of type `target` still needs to be handled.
+    /// Every `XdndPosition` message before the data arrived requested it again, so only the first
+    /// reply is used. After falling back to the URI list the portal's replies are ignored, since
+    /// its keys only work once.
+    pub fn wants_reply(&self, atoms: &DndAtoms, target: u32) -> bool {
+        self.result.is_none() && self.target.is_some_and(|own| own.atom(atoms) == target)
+    }
+
+    /// Turn the data received for [`Self::target`] into paths. For the portal's file transfer this
+    /// blocks until the portal has replied.
+    pub fn parse_target_data(&mut self, data: &[u8]) -> Result<Vec<PathBuf>, DropError> {
+        match self.target {
+            #[cfg(feature = "portal")]
+            Some(TransferType::PortalFileTransfer) => self.portal.retrieve_files(data),
+            _ => Self::parse_data(data),
+        }
+    }
+
+    /// Parse a `text/uri-list`. Every path is only copied once, when it's percent-decoded.
+    pub fn parse_data(data: &[u8]) -> Result<Vec<PathBuf>, DropError> {
         if !data.is_empty() {
-            let mut path_list = Vec::new();
-            let decoded = percent_decode(data).decode_utf8()?.into_owned();
-            for uri in decoded.split("\r\n").filter(|u| !u.is_empty()) {
+            let mut path_list = Vec::with_capacity(data.split(|&b| b == b'\n').count());
+            let uris = data
+                .split(|&b| b == b'\n')
+                .map(|uri| uri.strip_suffix(b"\r").unwrap_or(uri))
+                .filter(|uri| !uri.is_empty());
+            for uri in uris {
                 // The format is specified as protocol://host/path
                 // However, it's typically simply protocol:///path
-                let path_str = if uri.starts_with("file://") {
-                    let path_str = uri.replace("file://", "");
-                    if !path_str.starts_with('/') {
+                let path = if let Some(path) = uri.strip_prefix(b"file://") {
+                    if !path.starts_with(b"/") {
                         // A hostname is specified
                         // Supporting this case is beyond the scope of my mental health
-                        return Err(DndDataParseError::HostnameSpecified(path_str));
+                        return Err(DropError::RemoteFile(
+                            String::from_utf8_lossy(uri).into_owned(),
+                        ));
                     }
-                    path_str
+                    path
                 } else {
                     // Only the file protocol is supported
-                    return Err(DndDataParseError::UnexpectedProtocol(uri.to_owned()));
+                    return Err(DropError::NotAFile(Some(
+                        String::from_utf8_lossy(uri).into_owned(),
+                    )));
                 };
 
-                path_list.push(Path::new(&path_str).to_path_buf());
+                // File URIs encode the path's bytes, which don't need to be valid UTF-8
+                let path: Vec<u8> = percent_decode(path).collect();
+                path_list.push(OsString::from_vec(path).into());
+            }
+            if path_list.is_empty() {
+                Err(DropError::Empty)
+            } else {
+                Ok(path_list)
             }
-            Ok(path_list)
         } else {
-            Err(DndDataParseError::EmptyData)
+            Err(DropError::Empty)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atoms() -> DndAtoms {
+        DndAtoms::from_atoms(std::array::from_fn(|i| 100 + i as Atom))
+    }
+
+    #[test]
+    fn rejected_after_failed_parse() {
+        let mut handler = DropHandler::default();
+        let effect = Some(DropEffect::Copy);
+
+        // The data hasn't arrived yet
+        assert_eq!(handler.status_effect(effect), effect);
+        handler.result = Some(Ok(vec!["/tmp/a.wav".into()]));
+        assert_eq!(handler.status_effect(effect), effect);
+
+        handler.result = Some(Err(DropError::NotAFile(Some("https://example.com".into()))));
+        assert_eq!(handler.status_effect(effect), None);
+        assert_eq!(handler.status_effect(Some(DropEffect::Move)), None);
+
+        // The next drag starts over
+        handler.reset();
+        assert_eq!(handler.status_effect(effect), effect);
+        assert_eq!(handler.status_effect(None), None);
+    }
+
+    #[test]
+    fn drop_received() {
+        let mut handler = DropHandler::default();
+
+        // The data never arrived, so the handler never saw the drop
+        assert!(!handler.drop_received());
+        handler.result = Some(Err(DropError::RemoteFile("file://host/a.wav".into())));
+        assert!(!handler.drop_received());
+        handler.result = Some(Ok(Vec::new()));
+        assert!(!handler.drop_received());
+
+        handler.result = Some(Ok(vec!["/tmp/a.wav".into()]));
+        assert!(handler.drop_received());
+        handler.reset();
+        assert!(!handler.drop_received());
+    }
+
+    #[test]
+    fn only_the_first_reply_is_used() {
+        let atoms = atoms();
+        let mut handler = DropHandler::default();
+        assert!(!handler.wants_reply(&atoms, atoms.uri_list));
+
+        handler.type_list = Some(vec![atoms.action_copy, atoms.uri_list]);
+        handler.select_target(&atoms);
+        assert_eq!(handler.target, Some(TransferType::UriList));
+        assert!(handler.wants_reply(&atoms, atoms.uri_list));
+        assert!(!handler.wants_reply(&atoms, atoms.targets));
+
+        handler.result = Some(Err(DropError::Empty));
+        assert!(!handler.wants_reply(&atoms, atoms.uri_list));
+    }
+
+    #[test]
+    fn no_target_without_uri_list() {
+        let atoms = atoms();
+        let mut handler = DropHandler::default();
+
+        handler.type_list = Some(vec![atoms.action_copy, 42]);
+        handler.select_target(&atoms);
+        assert_eq!(handler.target, None);
+    }
+
+    #[test]
+    fn uri_lists() {
+        assert_eq!(
+            DropHandler::parse_data(b"file:///tmp/a.wav\r\nfile:///tmp/b%20c.wav\r\n"),
+            Ok(vec![PathBuf::from("/tmp/a.wav"), PathBuf::from("/tmp/b c.wav")])
+        );
+        assert_eq!(
+            DropHandler::parse_data(b"file:///tmp/a.wav\n\nfile:///b"),
+            Ok(vec![PathBuf::from("/tmp/a.wav"), PathBuf::from("/b")])
+        );
+        assert_eq!(
+            DropHandler::parse_data(b"file:///tmp/%FF.wav"),
+            Ok(vec![PathBuf::from(OsString::from_vec(b"/tmp/\xff.wav".to_vec()))])
+        );
+    }
+
+    #[test]
+    fn invalid_uri_lists() {
+        assert_eq!(DropHandler::parse_data(b""), Err(DropError::Empty));
+        assert_eq!(DropHandler::parse_data(b"\r\n\n"), Err(DropError::Empty));
+        assert_eq!(
+            DropHandler::parse_data(b"file:///tmp/a.wav\nfile://host/b.wav"),
+            Err(DropError::RemoteFile("file://host/b.wav".into()))
+        );
+        assert_eq!(
+            DropHandler::parse_data(b"https://example.com/a.wav"),
+            Err(DropError::NotAFile(Some("https://example.com/a.wav".into())))
+        );
+    }
+}
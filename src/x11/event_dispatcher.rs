@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use super::pen::MotionEvent;
+use super::xcb_connection::XcbConnection;
+use crate::X11Error;
+
+/// What the dispatcher sends to a window's event loop.
+pub(super) enum WindowMessage {
+    Event(XcbEvent),
+    Error(X11Error),
+    /// The resolution or the monitor configuration of a screen changed, or the `Xft.dpi` resource
+    /// was changed. This is sent to every window, since these events are about the root windows.
+    ScreenChanged,
+    /// A frame tick from [`crate::WindowHandle::trigger_frame()`].
+    Frame,
+    /// A request from assistive technology. AccessKit's adapter handles these on its own thread.
+    #[cfg(feature = "accesskit")]
+    AccessKit(crate::AccessKitEvent),
+}
+
+/// A screen's size as reported by the last RandR screen change notification. The connection's setup
+/// data still contains the size from when the connection was made.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ScreenSize {
+    pub width_px: u16,
+    pub height_px: u16,
+    pub width_mm: u16,
+    pub height_mm: u16,
+}
+
+/// An event read from the shared connection.
+pub(super) struct XcbEvent(pub xcb::GenericEvent);
+
+// SAFETY: The event owns its heap allocation, and it's only ever used by the thread that received
+//         it from the channel
+unsafe impl Send for XcbEvent {}
+
+#[cfg(test)]
+impl XcbEvent {
+    /// Wrap an event built with one of the typed events' `new()` functions.
+    pub fn new<T>(event: xcb::base::Event<T>) -> Self {
+        let ptr = event.ptr as *mut xcb::ffi::xcb_generic_event_t;
+        std::mem::forget(event);
+
+        Self(xcb::GenericEvent { ptr })
+    }
+}
+
+/// Every window shares the same X11 connection, but each window runs its own event loop on its own
+/// thread. The dispatcher reads all of the connection's events on a separate thread and forwards
+/// them to the event loop of the window they belong to. Events for a single window always arrive in
+/// the order the X server sent them.
+pub(super) struct EventDispatcher {
+    /// Channels to the event loops, indexed by X11 window ID.
+    routes: Mutex<HashMap<u32, Sender<WindowMessage>>>,
+    /// Needed to route the XInput 2 events for tablets.
+    xinput_opcode: Option<u8>,
+    /// The RandR extension's first event code, if the connection selected its screen change
+    /// notifications.
+    randr_first_event: Option<u8>,
+    /// The screens' sizes by root window, for the screens that have changed since connecting.
+    screen_sizes: Mutex<HashMap<u32, ScreenSize>>,
+}
+
+impl EventDispatcher {
+    pub fn new(xinput_opcode: Option<u8>, randr_first_event: Option<u8>) -> Self {
+        Self {
+            routes: Mutex::new(HashMap::new()),
+            xinput_opcode,
+            randr_first_event,
+            screen_sizes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The screen's current size, if it has changed since connecting.
+    pub fn screen_size(&self, root: u32) -> Option<ScreenSize> {
+        self.screen_sizes.lock().unwrap().get(&root).copied()
+    }
+
+    /// Start forwarding the window's events. This should be called before the window is created so
+    /// none of its events get lost.
+    pub fn register_window(&self, window_id: u32) -> Receiver<WindowMessage> {
+        let (tx, rx) = mpsc::channel();
+        self.routes.lock().unwrap().insert(window_id, tx);
+
+        rx
+    }
+
+    /// Send a message to a window's event loop from another thread. The message is dropped if the
+    /// window has already been unregistered.
+    pub fn send(&self, window_id: u32, message: WindowMessage) {
+        if let Some(route) = self.routes.lock().unwrap().get(&window_id) {
+            let _ = route.send(message);
+        }
+    }
+
+    /// Stop forwarding events to the window. Events that still arrive for the window are dropped.
+    pub fn unregister_window(&self, window_id: u32) {
+        self.routes.lock().unwrap().remove(&window_id);
+    }
+
+    /// Whether `window_id` belongs to one of this process's baseview windows.
+    pub fn is_registered(&self, window_id: u32) -> bool {
+        self.routes.lock().unwrap().contains_key(&window_id)
+    }
+
+    /// Forward the connection's events until the connection breaks. The routes are dropped at that
+    /// point, so the windows' event loops find out about it through their disconnected channels.
+    pub fn run(&self, connection: &XcbConnection) {
+        while let Some(event) = connection.conn.wait_for_event() {
+            self.dispatch(connection, event);
+        }
+
+        debug!(target: "baseview::platform", "X11 connection lost");
+        self.routes.lock().unwrap().clear();
+    }
+
+    fn dispatch(&self, connection: &XcbConnection, event: xcb::GenericEvent) {
+        let routes = self.routes.lock().unwrap();
+
+        // Errors for requests that weren't checked when they were made
+        if event.response_type() == 0 {
+            let error = unsafe { &*(event.ptr as *const xcb::ffi::xcb_generic_error_t) };
+            let error = X11Error {
+                error_code: error.error_code,
+                major_opcode: error.major_code,
+                minor_opcode: error.minor_code,
+                resource_id: error.resource_id,
+                sequence: error.sequence,
+            };
+
+            route_error(&routes, error);
+
+            return;
+        }
+
+        if self.randr_first_event.is_some_and(|first_event| {
+            event.response_type() & !0x80 == first_event + xcb::randr::SCREEN_CHANGE_NOTIFY
+        }) {
+            let event = unsafe { xcb::cast_event::<xcb::randr::ScreenChangeNotifyEvent>(&event) };
+            self.screen_sizes.lock().unwrap().insert(
+                event.root(),
+                ScreenSize {
+                    width_px: event.width(),
+                    height_px: event.height(),
+                    width_mm: event.mwidth(),
+                    height_mm: event.mheight(),
+                },
+            );
+
+            connection.invalidate_scale();
+            for route in routes.values() {
+                let _ = route.send(WindowMessage::ScreenChanged);
+            }
+
+            return;
+        }
+
+        // These are sent to every client when the keyboard layout is changed, for instance with
+        // `setxkbmap`, and they don't belong to any window
+        if event.response_type() & !0x80 == xcb::MAPPING_NOTIFY {
+            let mapping_event = unsafe { xcb::cast_event::<xcb::MappingNotifyEvent>(&event) };
+            if mapping_event.request() as u32 == xcb::MAPPING_KEYBOARD {
+                connection.invalidate_keymap();
+            }
+
+            return;
+        }
+
+        // `xrdb` replaces the whole `RESOURCE_MANAGER` property on a root window, and the scale
+        // factor is read from its `Xft.dpi` entry
+        if event.response_type() & !0x80 == xcb::PROPERTY_NOTIFY {
+            let property_event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+            if property_event.atom() == xcb::ATOM_RESOURCE_MANAGER {
+                connection.invalidate_scale();
+                for route in routes.values() {
+                    let _ = route.send(WindowMessage::ScreenChanged);
+                }
+
+                return;
+            }
+        }
+
+        let window_id = event_window(&event).or_else(|| {
+            let opcode = self.xinput_opcode?;
+            MotionEvent::parse(&event, opcode).map(|event| event.window())
+        });
+        if let Some(route) = window_id.and_then(|window_id| routes.get(&window_id)) {
+            let _ = route.send(WindowMessage::Event(XcbEvent(event)));
+        } else {
+            trace!(
+                target: "baseview::platform",
+                response_type = event.response_type(),
+                "dropped event without a window"
+            );
+        }
+    }
+}
+
+/// Send an error to the window it's about. Errors can only be traced back to a window if they're
+/// about the window itself. Any other error could have been caused by any of the windows, so they
+/// all get to see it.
+fn route_error(routes: &HashMap<u32, Sender<WindowMessage>>, error: X11Error) {
+    match routes.get(&error.resource_id) {
+        Some(route) => {
+            let _ = route.send(WindowMessage::Error(error));
+        }
+        None => {
+            for route in routes.values() {
+                let _ = route.send(WindowMessage::Error(error));
+            }
+        }
+    }
+}
+
+/// The window an event was sent to, for the event types baseview selects.
+fn event_window(event: &xcb::GenericEvent) -> Option<u32> {
+    let window = unsafe {
+        match event.response_type() & !0x80 {
+            xcb::KEY_PRESS | xcb::KEY_RELEASE => {
+                xcb::cast_event::<xcb::KeyPressEvent>(event).event()
+            }
+            xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
+                xcb::cast_event::<xcb::ButtonPressEvent>(event).event()
+            }
+            xcb::MOTION_NOTIFY => xcb::cast_event::<xcb::MotionNotifyEvent>(event).event(),
+            xcb::ENTER_NOTIFY | xcb::LEAVE_NOTIFY => {
+                xcb::cast_event::<xcb::EnterNotifyEvent>(event).event()
+            }
+            xcb::FOCUS_IN | xcb::FOCUS_OUT => xcb::cast_event::<xcb::FocusInEvent>(event).event(),
+            xcb::EXPOSE => xcb::cast_event::<xcb::ExposeEvent>(event).window(),
+            // With `STRUCTURE_NOTIFY` the event window is the window that changed
+            xcb::CONFIGURE_NOTIFY => xcb::cast_event::<xcb::ConfigureNotifyEvent>(event).event(),
+            xcb::MAP_NOTIFY => xcb::cast_event::<xcb::MapNotifyEvent>(event).event(),
+            xcb::UNMAP_NOTIFY => xcb::cast_event::<xcb::UnmapNotifyEvent>(event).event(),
+            xcb::DESTROY_NOTIFY => xcb::cast_event::<xcb::DestroyNotifyEvent>(event).event(),
+            xcb::REPARENT_NOTIFY => xcb::cast_event::<xcb::ReparentNotifyEvent>(event).event(),
+            xcb::CLIENT_MESSAGE => xcb::cast_event::<xcb::ClientMessageEvent>(event).window(),
+            xcb::SELECTION_NOTIFY => {
+                xcb::cast_event::<xcb::SelectionNotifyEvent>(event).requestor()
+            }
+            xcb::SELECTION_REQUEST => xcb::cast_event::<xcb::SelectionRequestEvent>(event).owner(),
+            xcb::SELECTION_CLEAR => xcb::cast_event::<xcb::SelectionClearEvent>(event).owner(),
+            xcb::PROPERTY_NOTIFY => xcb::cast_event::<xcb::PropertyNotifyEvent>(event).window(),
+            _ => return None,
+        }
+    };
+
+    Some(window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Turn a typed event into the generic event the dispatcher receives.
+    fn generic<T>(event: xcb::base::Event<T>) -> xcb::GenericEvent {
+        XcbEvent::new(event).0
+    }
+
+    /// Change the event's response type, for the event types that share a struct.
+    fn with_response_type(event: xcb::GenericEvent, response_type: u8) -> xcb::GenericEvent {
+        unsafe { (*event.ptr).response_type = response_type };
+
+        event
+    }
+
+    #[test]
+    fn input_events() {
+        const WINDOW: u32 = 10;
+        const OTHER: u32 = 20;
+
+        let key_press = generic(xcb::KeyPressEvent::new(
+            xcb::KEY_PRESS,
+            38,
+            0,
+            OTHER,
+            WINDOW,
+            OTHER,
+            0,
+            0,
+            0,
+            0,
+            0,
+            true,
+        ));
+        let button_press = generic(xcb::ButtonPressEvent::new(
+            xcb::BUTTON_PRESS,
+            1,
+            0,
+            OTHER,
+            WINDOW,
+            OTHER,
+            0,
+            0,
+            0,
+            0,
+            0,
+            true,
+        ));
+        let motion =
+            generic(xcb::MotionNotifyEvent::new(0, 0, OTHER, WINDOW, OTHER, 0, 0, 0, 0, 0, true));
+        let enter = generic(xcb::EnterNotifyEvent::new(
+            xcb::ENTER_NOTIFY,
+            0,
+            0,
+            OTHER,
+            WINDOW,
+            OTHER,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ));
+        let focus_in = generic(xcb::FocusInEvent::new(xcb::FOCUS_IN, 0, WINDOW, 0));
+
+        assert_eq!(event_window(&key_press), Some(WINDOW));
+        assert_eq!(event_window(&button_press), Some(WINDOW));
+        assert_eq!(event_window(&motion), Some(WINDOW));
+        assert_eq!(event_window(&enter), Some(WINDOW));
+        assert_eq!(event_window(&focus_in), Some(WINDOW));
+
+        let key_release = with_response_type(key_press, xcb::KEY_RELEASE);
+        let button_release = with_response_type(button_press, xcb::BUTTON_RELEASE);
+        let leave = with_response_type(enter, xcb::LEAVE_NOTIFY);
+        let focus_out = with_response_type(focus_in, xcb::FOCUS_OUT);
+        assert_eq!(event_window(&key_release), Some(WINDOW));
+        assert_eq!(event_window(&button_release), Some(WINDOW));
+        assert_eq!(event_window(&leave), Some(WINDOW));
+        assert_eq!(event_window(&focus_out), Some(WINDOW));
+
+        // Events sent with `SendEvent` have the high bit set
+        let sent = with_response_type(key_release, xcb::KEY_PRESS | 0x80);
+        assert_eq!(event_window(&sent), Some(WINDOW));
+    }
+
+    #[test]
+    fn structure_events() {
+        const WINDOW: u32 = 10;
+        const OTHER: u32 = 20;
+
+        let events = [
+            generic(xcb::ExposeEvent::new(WINDOW, 0, 0, 100, 100, 0)),
+            generic(xcb::ConfigureNotifyEvent::new(
+                WINDOW, WINDOW, OTHER, 0, 0, 100, 100, 0, false,
+            )),
+            generic(xcb::MapNotifyEvent::new(WINDOW, WINDOW, false)),
+            generic(xcb::UnmapNotifyEvent::new(WINDOW, WINDOW, false)),
+            generic(xcb::DestroyNotifyEvent::new(WINDOW, WINDOW)),
+            generic(xcb::ReparentNotifyEvent::new(WINDOW, WINDOW, OTHER, 0, 0, false)),
+            generic(xcb::ClientMessageEvent::new(
+                32,
+                WINDOW,
+                1,
+                xcb::ClientMessageData::from_data32([OTHER, 0, 0, 0, 0]),
+            )),
+            generic(xcb::PropertyNotifyEvent::new(WINDOW, xcb::ATOM_WM_NAME, 0, 0)),
+        ];
+        for event in &events {
+            assert_eq!(event_window(event), Some(WINDOW), "{}", event.response_type());
+        }
+    }
+
+    #[test]
+    fn selection_events() {
+        const OWNER: u32 = 10;
+        const REQUESTOR: u32 = 20;
+
+        // Other clients ask the selection's owner for its contents
+        let request = generic(xcb::SelectionRequestEvent::new(0, OWNER, REQUESTOR, 1, 2, 3));
+        assert_eq!(event_window(&request), Some(OWNER));
+
+        // And the reply is sent to the window that asked for it
+        let notify = generic(xcb::SelectionNotifyEvent::new(0, REQUESTOR, 1, 2, 3));
+        assert_eq!(event_window(&notify), Some(REQUESTOR));
+
+        let clear = generic(xcb::SelectionClearEvent::new(0, OWNER, 1));
+        assert_eq!(event_window(&clear), Some(OWNER));
+    }
+
+    #[test]
+    fn unknown_events() {
+        let event = with_response_type(generic(xcb::DestroyNotifyEvent::new(10, 10)), 0x7f);
+        assert_eq!(event_window(&event), None);
+    }
+
+    #[test]
+    fn errors() {
+        let (tx_1, rx_1) = mpsc::channel();
+        let (tx_2, rx_2) = mpsc::channel();
+        let routes = HashMap::from([(10, tx_1), (20, tx_2)]);
+
+        let error = |resource_id| X11Error {
+            error_code: xcb::WINDOW,
+            major_opcode: xcb::CONFIGURE_WINDOW,
+            minor_opcode: 0,
+            resource_id,
+            sequence: 1,
+        };
+        let received = |rx: &Receiver<WindowMessage>| {
+            rx.try_iter()
+                .map(|message| match message {
+                    WindowMessage::Error(error) => error.resource_id,
+                    _ => panic!("expected an error"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Errors about a window only go to that window
+        route_error(&routes, error(20));
+        assert_eq!(received(&rx_1), []);
+        assert_eq!(received(&rx_2), [20]);
+
+        // Errors about anything else could have been caused by any of the windows
+        route_error(&routes, error(30));
+        assert_eq!(received(&rx_1), [30]);
+        assert_eq!(received(&rx_2), [30]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::{Duration, Instant};
+
+/// A frame that takes at least this many frame intervals counts as a long frame for
+/// [`crate::WindowOpenOptions::skip_late_frames`].
+const LONG_FRAME_INTERVALS: u32 = 2;
+
+/// Decides when the event loop should draw the next frame and how long it may sleep in between.
+/// This doesn't read the clock itself. The current time is always passed in by the event loop, so
+/// the decisions only depend on the arguments.
+pub(super) struct FramePacer {
+    interval: Duration,
+    /// The time the last frame was scheduled for. The next frame is due one interval later.
+    last_frame: Instant,
+    skip_late_frames: bool,
+}
+
+impl FramePacer {
+    pub(super) fn new(now: Instant, interval: Duration, skip_late_frames: bool) -> Self {
+        Self { interval, last_frame: now, skip_late_frames }
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(super) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub(super) fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// The time the next frame should be drawn at.
+    pub(super) fn next_frame(&self) -> Instant {
+        self.last_frame + self.interval
+    }
+
+    pub(super) fn is_frame_due(&self, now: Instant) -> bool {
+        now >= self.next_frame()
+    }
+
+    /// How long the event loop may wait for events before the next frame is due, rounded up to
+    /// whole milliseconds for `poll()`. Rounding down would wake the loop up before the frame is
+    /// due, after which it would spin with a zero timeout until it is.
+    pub(super) fn poll_timeout_ms(&self, now: Instant) -> i32 {
+        let remaining = self.next_frame().saturating_duration_since(now);
+        let millis = remaining.as_micros().div_ceil(1000);
+
+        millis.min(i32::MAX as u128) as i32
+    }
+
+    /// Record a frame that was started at `start` and that returned at `end`.
+    ///
+    /// If handling events or drawing the frame took longer than the frame interval, the next frame
+    /// is due right away, but frames never queue up: there is at most a single frame to catch up
+    /// on. With `skip_late_frames` enabled a long frame instead pushes the next frame back by a
+    /// full interval, so the events that arrived in the meantime are handled before the handler
+    /// gets to draw again.
+    pub(super) fn frame_finished(&mut self, start: Instant, end: Instant) {
+        if self.skip_late_frames
+            && end.saturating_duration_since(start) >= self.interval * LONG_FRAME_INTERVALS
+        {
+            self.last_frame = end;
+        } else {
+            self.last_frame = Instant::max(self.next_frame(), end - self.interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn is_frame_due() {
+        let start = Instant::now();
+        let pacer = FramePacer::new(start, INTERVAL, false);
+
+        assert!(!pacer.is_frame_due(start));
+        assert!(!pacer.is_frame_due(start + ms(10) - Duration::from_nanos(1)));
+        assert!(pacer.is_frame_due(start + ms(10)));
+        assert!(pacer.is_frame_due(start + ms(25)));
+    }
+
+    #[test]
+    fn poll_timeout_rounds_up() {
+        let start = Instant::now();
+        let pacer = FramePacer::new(start, INTERVAL, false);
+
+        assert_eq!(pacer.poll_timeout_ms(start), 10);
+        assert_eq!(pacer.poll_timeout_ms(start + Duration::from_micros(9_500)), 1);
+        assert_eq!(pacer.poll_timeout_ms(start + Duration::from_micros(9_999)), 1);
+        assert_eq!(pacer.poll_timeout_ms(start + ms(10)), 0);
+        assert_eq!(pacer.poll_timeout_ms(start + ms(50)), 0);
+    }
+
+    #[test]
+    fn poll_timeout_saturates() {
+        let start = Instant::now();
+        let pacer = FramePacer::new(start, Duration::from_secs(u32::MAX as u64), false);
+
+        assert_eq!(pacer.poll_timeout_ms(start), i32::MAX);
+    }
+
+    #[test]
+    fn set_interval() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(start, INTERVAL, false);
+
+        pacer.set_interval(ms(20));
+        assert_eq!(pacer.next_frame(), start + ms(20));
+        assert!(!pacer.is_frame_due(start + ms(15)));
+        assert_eq!(pacer.poll_timeout_ms(start + ms(15)), 5);
+
+        // The new interval also applies to the frames after the next one
+        pacer.frame_finished(start + ms(20), start + ms(22));
+        assert_eq!(pacer.next_frame(), start + ms(40));
+    }
+
+    #[test]
+    fn on_time_frames_keep_the_schedule() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(start, INTERVAL, false);
+
+        // Frames are scheduled relative to when they were due, not when they were drawn
+        pacer.frame_finished(start + ms(11), start + ms(14));
+        assert_eq!(pacer.next_frame(), start + ms(20));
+        pacer.frame_finished(start + ms(20), start + ms(21));
+        assert_eq!(pacer.next_frame(), start + ms(30));
+    }
+
+    #[test]
+    fn late_frames_catch_up_once() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(start, INTERVAL, false);
+
+        // A frame that took three and a half intervals only makes the next frame due right away
+        pacer.frame_finished(start + ms(10), start + ms(45));
+        assert!(pacer.is_frame_due(start + ms(45)));
+        assert_eq!(pacer.next_frame(), start + ms(45));
+
+        // After catching up on that one frame the regular interval applies again
+        pacer.frame_finished(start + ms(45), start + ms(46));
+        assert_eq!(pacer.next_frame(), start + ms(55));
+        assert!(!pacer.is_frame_due(start + ms(46)));
+    }
+
+    #[test]
+    fn skip_late_frames_pushes_back_long_frames() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(start, INTERVAL, true);
+
+        pacer.frame_finished(start + ms(10), start + ms(45));
+        assert!(!pacer.is_frame_due(start + ms(45)));
+        assert_eq!(pacer.next_frame(), start + ms(55));
+        assert_eq!(pacer.poll_timeout_ms(start + ms(45)), 10);
+    }
+
+    #[test]
+    fn skip_late_frames_ignores_short_overruns() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(start, INTERVAL, true);
+
+        // Less than two intervals isn't a long frame, so this catches up like without the option
+        pacer.frame_finished(start + ms(10), start + ms(25));
+        assert!(pacer.is_frame_due(start + ms(25)));
+        pacer.frame_finished(start + ms(25), start + ms(26));
+        assert_eq!(pacer.next_frame(), start + ms(35));
+
+        pacer.frame_finished(start + ms(35), start + ms(36));
+        assert_eq!(pacer.next_frame(), start + ms(45));
+    }
+}
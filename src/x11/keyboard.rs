@@ -18,16 +18,110 @@
 
 //! X11 keyboard handling
 
+use std::sync::Mutex;
+
 use xcb::xproto;
 
 use keyboard_types::*;
 
 use crate::keyboard::code_to_location;
 
+use super::keysyms::keysym_to_char;
+
+/// The keysym for keycodes and levels without a symbol.
+const NO_SYMBOL: u32 = 0;
+
+/// The keyboard mapping from the X server, which translates keycodes to the keysyms of the user's
+/// keyboard layout. The server sends a `MappingNotify` event when the layout changes, after which
+/// the mapping needs to be fetched again.
+#[derive(Debug, Default)]
+pub(super) struct Keymap {
+    min_keycode: u8,
+    keysyms_per_keycode: usize,
+    /// `keysyms_per_keycode` keysyms for every keycode, starting at `min_keycode`.
+    keysyms: Vec<u32>,
+}
+
+impl Keymap {
+    /// Fetch the current mapping from the server. The mapping is left empty if that fails, in
+    /// which case keys are translated as if the layout was US English.
+    pub fn fetch(conn: &xcb::Connection) -> Self {
+        let setup = conn.get_setup();
+        let min_keycode = setup.min_keycode();
+        let count = setup.max_keycode() - min_keycode + 1;
+
+        match xcb::get_keyboard_mapping(conn, min_keycode, count).get_reply() {
+            Ok(reply) => Self::new(
+                min_keycode,
+                reply.keysyms_per_keycode() as usize,
+                reply.keysyms().to_vec(),
+            ),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// A mapping in the format of a `GetKeyboardMapping` reply.
+    fn new(min_keycode: u8, keysyms_per_keycode: usize, keysyms: Vec<u32>) -> Self {
+        Self { min_keycode, keysyms_per_keycode, keysyms }
+    }
+
+    /// The key a keycode produces with the modifiers and the keyboard group in an event's
+    /// `state`, or `None` if it doesn't produce a character. This follows the core protocol's rules
+    /// for picking one of the keycode's keysyms.
+    fn key(&self, keycode: u8, state: u16) -> Option<Key> {
+        let start = usize::from(keycode.checked_sub(self.min_keycode)?) * self.keysyms_per_keycode;
+        let keysyms = self.keysyms.get(start..start + self.keysyms_per_keycode)?;
+
+        // XKB reports the active group in bits 13 and 14. The first two keysyms belong to the first
+        // group and the next two to the second group. Keys without symbols for the second group,
+        // and the rarely used third and fourth groups, use the first group's symbols instead.
+        let group = match keysyms.get(2..4) {
+            Some(second_group) if (state >> 13) & 0b11 == 1 && second_group[0] != NO_SYMBOL => {
+                second_group
+            }
+            _ => &keysyms[..keysyms.len().min(2)],
+        };
+        let base = group.first().copied().unwrap_or(NO_SYMBOL);
+        let shifted = group.get(1).copied().unwrap_or(NO_SYMBOL);
+
+        let shift = state & xproto::MOD_MASK_SHIFT as u16 != 0;
+        let caps_lock = state & xproto::MOD_MASK_LOCK as u16 != 0;
+        let keysym = if shift && shifted != NO_SYMBOL { shifted } else { base };
+        if (0xfe50..=0xfe93).contains(&keysym) {
+            return Some(Key::Dead);
+        }
+
+        // Letters only have a single keysym, and they're capitalized by both Shift and Caps Lock
+        let character = keysym_to_char(keysym)?;
+        if (caps_lock || (shift && shifted == NO_SYMBOL)) && character.is_lowercase() {
+            Some(Key::Character(character.to_uppercase().collect()))
+        } else {
+            Some(Key::Character(character.into()))
+        }
+    }
+}
+
+/// The [`Keymap`] that's currently in use. It's only fetched once it's needed, and it's fetched
+/// again after the layout changed.
+#[derive(Debug, Default)]
+pub(super) struct KeymapCache(Mutex<Option<Keymap>>);
+
+impl KeymapCache {
+    /// Call `f` with the cached keymap, calling `fetch` first if there is none.
+    pub fn with<R>(&self, fetch: impl FnOnce() -> Keymap, f: impl FnOnce(&Keymap) -> R) -> R {
+        f(self.0.lock().unwrap().get_or_insert_with(fetch))
+    }
+
+    /// Forget the keymap, so the next call to [`Self::with()`] fetches it again.
+    pub fn invalidate(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
 /// Convert a hardware scan code to a key.
 ///
-/// Note: this is a hardcoded layout. We need to detect the user's
-/// layout from the system and apply it.
+/// Note: this is a hardcoded US layout. It's only used for keys the [`Keymap`] doesn't produce a
+/// character for, like the named keys.
 fn code_to_key(code: Code, m: Modifiers) -> Key {
     fn a(s: &str) -> Key {
         Key::Character(s.into())
@@ -383,24 +477,114 @@ pub(super) fn key_mods(mods: u16) -> Modifiers {
     ret
 }
 
-pub(super) fn convert_key_press_event(key_press: &xcb::KeyPressEvent) -> KeyboardEvent {
+pub(super) fn convert_key_press_event(
+    key_press: &xcb::KeyPressEvent, keymap: &Keymap,
+) -> KeyboardEvent {
     let hw_keycode = key_press.detail();
     let code = hardware_keycode_to_code(hw_keycode.into());
     let modifiers = key_mods(key_press.state());
-    let key = code_to_key(code, modifiers);
+    let key =
+        keymap.key(hw_keycode, key_press.state()).unwrap_or_else(|| code_to_key(code, modifiers));
     let location = code_to_location(code);
     let state = KeyState::Down;
 
     KeyboardEvent { code, key, modifiers, location, state, repeat: false, is_composing: false }
 }
 
-pub(super) fn convert_key_release_event(key_release: &xcb::KeyReleaseEvent) -> KeyboardEvent {
+pub(super) fn convert_key_release_event(
+    key_release: &xcb::KeyReleaseEvent, keymap: &Keymap,
+) -> KeyboardEvent {
     let hw_keycode = key_release.detail();
     let code = hardware_keycode_to_code(hw_keycode.into());
     let modifiers = key_mods(key_release.state());
-    let key = code_to_key(code, modifiers);
+    let key =
+        keymap.key(hw_keycode, key_release.state()).unwrap_or_else(|| code_to_key(code, modifiers));
     let location = code_to_location(code);
     let state = KeyState::Up;
 
     KeyboardEvent { code, key, modifiers, location, state, repeat: false, is_composing: false }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYCODE_Q: u8 = 24;
+    const KEYCODE_A: u8 = 38;
+    const KEYCODE_2: u8 = 11;
+
+    /// A keymap with two keysyms per keycode for keycodes 8 and up, like the server sends.
+    fn keymap(keys: &[(u8, [u32; 2])]) -> Keymap {
+        let mut keysyms = vec![NO_SYMBOL; 2 * 248];
+        for &(keycode, symbols) in keys {
+            let start = usize::from(keycode - 8) * 2;
+            keysyms[start..start + 2].copy_from_slice(&symbols);
+        }
+
+        Keymap::new(8, 2, keysyms)
+    }
+
+    fn us() -> Keymap {
+        keymap(&[(KEYCODE_Q, [0x71, 0x51]), (KEYCODE_A, [0x61, 0x41]), (KEYCODE_2, [0x32, 0x40])])
+    }
+
+    fn azerty() -> Keymap {
+        // `eacute` and `2`
+        keymap(&[(KEYCODE_Q, [0x61, 0x41]), (KEYCODE_A, [0x71, 0x51]), (KEYCODE_2, [0xe9, 0x32])])
+    }
+
+    fn character(s: &str) -> Option<Key> {
+        Some(Key::Character(s.into()))
+    }
+
+    #[test]
+    fn translation() {
+        let shift = xproto::MOD_MASK_SHIFT as u16;
+        let caps_lock = xproto::MOD_MASK_LOCK as u16;
+
+        assert_eq!(us().key(KEYCODE_Q, 0), character("q"));
+        assert_eq!(us().key(KEYCODE_Q, shift), character("Q"));
+        assert_eq!(us().key(KEYCODE_Q, caps_lock), character("Q"));
+        assert_eq!(us().key(KEYCODE_2, shift), character("@"));
+        assert_eq!(azerty().key(KEYCODE_2, 0), character("é"));
+        assert_eq!(azerty().key(KEYCODE_2, caps_lock), character("É"));
+
+        // Keycodes outside of the mapping, and an empty mapping after a failed request
+        assert_eq!(us().key(7, 0), None);
+        assert_eq!(Keymap::default().key(KEYCODE_Q, 0), None);
+    }
+
+    #[test]
+    fn second_group() {
+        // `a` and `A`, followed by Cyrillic `ф` and `Ф`
+        let keymap = Keymap::new(KEYCODE_A, 4, vec![0x61, 0x41, 0x6c6, 0x6e6]);
+        let second_group = 1 << 13;
+
+        assert_eq!(keymap.key(KEYCODE_A, 0), character("a"));
+        assert_eq!(keymap.key(KEYCODE_A, second_group), character("ф"));
+        assert_eq!(
+            keymap.key(KEYCODE_A, second_group | xproto::MOD_MASK_SHIFT as u16),
+            character("Ф")
+        );
+
+        // Without symbols for the second group, the first group is used
+        let keymap = Keymap::new(KEYCODE_A, 4, vec![0x61, 0x41, NO_SYMBOL, NO_SYMBOL]);
+        assert_eq!(keymap.key(KEYCODE_A, second_group), character("a"));
+    }
+
+    #[test]
+    fn refresh_after_layout_change() {
+        let cache = KeymapCache::default();
+        let mut layouts = vec![azerty(), us()];
+        let mut fetch = || layouts.pop().unwrap();
+
+        assert_eq!(cache.with(&mut fetch, |keymap| keymap.key(KEYCODE_Q, 0)), character("q"));
+        // The keymap is only fetched once until the layout changes
+        assert_eq!(cache.with(&mut fetch, |keymap| keymap.key(KEYCODE_A, 0)), character("a"));
+
+        cache.invalidate();
+        assert_eq!(cache.with(&mut fetch, |keymap| keymap.key(KEYCODE_Q, 0)), character("a"));
+        assert_eq!(cache.with(&mut fetch, |keymap| keymap.key(KEYCODE_A, 0)), character("q"));
+        assert!(layouts.is_empty());
+    }
+}
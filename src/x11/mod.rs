@@ -4,8 +4,19 @@ use xcb_connection::XcbConnection;
 mod window;
 pub use window::*;
 
+mod x11_connection;
+pub use x11_connection::{PropertyFormat, X11Connection};
+
 mod cursor;
 mod keyboard;
+mod keysyms;
 
 mod drag_handler;
 mod drop_handler;
+mod event_dispatcher;
+mod frame_pacer;
+mod pen;
+#[cfg(feature = "portal")]
+mod portal;
+mod properties;
+mod scaling;
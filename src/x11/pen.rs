@@ -0,0 +1,272 @@
+//! Pressure and tilt for graphics tablets. X11 only reports these through the valuators of the
+//! tablet's XInput 2 devices, so the window selects XInput 2 motion events for every pointer device
+//! that has a pressure axis, next to the core pointer events it already receives.
+
+use std::collections::HashMap;
+
+use keyboard_types::Modifiers;
+use x11::xinput2;
+
+use super::keyboard::key_mods;
+use super::XcbConnection;
+use crate::{MouseEvent, Point, WindowInfo};
+
+/// The size of an XInput 2 device event up to the button mask. This is the protocol's 80 bytes plus
+/// the `full_sequence` field XCB adds after the first 32 bytes of every event.
+const DEVICE_EVENT_SIZE: usize = 84;
+
+/// An XInput 2 motion event, read directly from its wire format since the xcb crate's XInput
+/// bindings don't compile. Because of the `full_sequence` field XCB inserts, every field after the
+/// first 32 bytes is 4 bytes further along than in the XInput 2 protocol specification.
+pub(super) struct MotionEvent<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> MotionEvent<'a> {
+    /// Interpret the event as an XInput 2 motion event. Returns `None` for any other event.
+    pub fn parse(event: &'a xcb::GenericEvent, xinput_opcode: u8) -> Option<Self> {
+        if event.response_type() & !0x80 != xcb::GE_GENERIC {
+            return None;
+        }
+
+        let bytes = unsafe {
+            let header = std::slice::from_raw_parts(event.ptr as *const u8, 36);
+            let length = 36 + u32::from_ne_bytes(header[4..8].try_into().unwrap()) as usize * 4;
+
+            std::slice::from_raw_parts(event.ptr as *const u8, length)
+        };
+        let event = Self { bytes };
+        if bytes[1] != xinput_opcode
+            || bytes.len() < DEVICE_EVENT_SIZE
+            || event.u16(8) != xinput2::XI_Motion as u16
+        {
+            return None;
+        }
+
+        Some(event)
+    }
+
+    /// The physical device that caused the event.
+    pub fn source_id(&self) -> u16 {
+        self.u16(56)
+    }
+
+    pub fn window(&self) -> u32 {
+        self.u32(24)
+    }
+
+    /// The position relative to the window, in physical pixels.
+    pub fn position(&self) -> (f64, f64) {
+        (self.fp1616(44), self.fp1616(48))
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        // The effective modifiers, which are in the same format as the core events' state
+        key_mods(self.u32(76) as u16)
+    }
+
+    /// The value of the valuator with the given number, if the event contains it. Events only
+    /// contain the valuators that changed.
+    pub fn valuator(&self, number: u16) -> Option<f64> {
+        let mask_start = DEVICE_EVENT_SIZE + self.u16(52) as usize * 4;
+        let mask = self.bytes.get(mask_start..mask_start + self.u16(54) as usize * 4)?;
+        let is_set = |i: usize| mask[i / 8] & (1 << (i % 8)) != 0;
+
+        let number = number as usize;
+        if number >= mask.len() * 8 || !is_set(number) {
+            return None;
+        }
+
+        // The values are only included for the valuators in the mask, as 32.32 fixed point numbers
+        let index = (0..number).filter(|&i| is_set(i)).count();
+        let offset = mask_start + mask.len() + index * 8;
+        let value = self.bytes.get(offset..offset + 8)?;
+        let integral = i32::from_ne_bytes(value[..4].try_into().unwrap());
+        let fraction = u32::from_ne_bytes(value[4..].try_into().unwrap());
+
+        Some(integral as f64 + fraction as f64 / 4_294_967_296.0)
+    }
+
+    fn u16(&self, offset: usize) -> u16 {
+        u16::from_ne_bytes(self.bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        u32::from_ne_bytes(self.bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn fp1616(&self, offset: usize) -> f64 {
+        self.u32(offset) as i32 as f64 / 65536.0
+    }
+}
+
+/// A valuator and the range of values it reports.
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    number: u16,
+    min: f64,
+    max: f64,
+}
+
+impl Axis {
+    /// Read the axis' value from the event, mapped to `0.0..=1.0`.
+    fn read(&self, event: &MotionEvent) -> Option<f32> {
+        let value = event.valuator(self.number)?;
+        if self.max <= self.min {
+            return Some(0.0);
+        }
+
+        Some(((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0) as f32)
+    }
+
+    /// Read the axis' value from the event, mapped to `-1.0..=1.0`.
+    fn read_centered(&self, event: &MotionEvent) -> Option<f32> {
+        self.read(event).map(|value| value * 2.0 - 1.0)
+    }
+}
+
+#[derive(Debug)]
+struct PenDevice {
+    pressure: Axis,
+    tilt_x: Option<Axis>,
+    tilt_y: Option<Axis>,
+    /// Events only contain the valuators that changed, so the last known values are needed to
+    /// report the full state.
+    last_pressure: f32,
+    last_tilt: (f32, f32),
+}
+
+/// The pen devices of the connected graphics tablets, by XInput 2 device ID.
+#[derive(Debug, Default)]
+pub(super) struct PenDevices {
+    devices: HashMap<u16, PenDevice>,
+}
+
+impl PenDevices {
+    /// Find the devices that report their pressure. This finds nothing if the X server doesn't
+    /// support XInput 2, or if no tablet is connected.
+    pub fn query(conn: &XcbConnection) -> Self {
+        let atoms = &conn.atoms;
+        if conn.xinput_opcode.is_none() || atoms.abs_pressure == xcb::ATOM_NONE {
+            return Self::default();
+        }
+
+        let mut devices = HashMap::new();
+        unsafe {
+            let mut count = 0;
+            let infos =
+                xinput2::XIQueryDevice(conn.conn.get_raw_dpy(), xinput2::XIAllDevices, &mut count);
+            if infos.is_null() {
+                return Self::default();
+            }
+
+            for info in std::slice::from_raw_parts(infos, count as usize) {
+                // Only the physical pointer devices attached to the core pointer move the cursor
+                if info._use != xinput2::XISlavePointer || info.enabled == 0 {
+                    continue;
+                }
+
+                let (mut pressure, mut tilt_x, mut tilt_y) = (None, None, None);
+                for &class in std::slice::from_raw_parts(info.classes, info.num_classes as usize) {
+                    if (*class)._type != xinput2::XIValuatorClass {
+                        continue;
+                    }
+
+                    let valuator = &*(class as *const xinput2::XIValuatorClassInfo);
+                    let axis = Some(Axis {
+                        number: valuator.number as u16,
+                        min: valuator.min,
+                        max: valuator.max,
+                    });
+                    let label = valuator.label as u32;
+                    if label == xcb::ATOM_NONE {
+                        continue;
+                    } else if label == atoms.abs_pressure {
+                        pressure = axis;
+                    } else if label == atoms.abs_tilt_x {
+                        tilt_x = axis;
+                    } else if label == atoms.abs_tilt_y {
+                        tilt_y = axis;
+                    }
+                }
+
+                if let Some(pressure) = pressure {
+                    devices.insert(
+                        info.deviceid as u16,
+                        PenDevice {
+                            pressure,
+                            tilt_x,
+                            tilt_y,
+                            last_pressure: 0.0,
+                            last_tilt: (0.0, 0.0),
+                        },
+                    );
+                }
+            }
+
+            xinput2::XIFreeDeviceInfo(infos);
+        }
+
+        Self { devices }
+    }
+
+    /// Select the XInput 2 motion events of the pen devices on the window. The core pointer
+    /// events are still delivered since only the physical devices are selected.
+    pub fn select_events(&self, conn: &XcbConnection, window_id: u32) {
+        if self.devices.is_empty() {
+            return;
+        }
+
+        let mut mask = [0u8; 4];
+        xinput2::XISetMask(&mut mask, xinput2::XI_Motion);
+
+        let mut masks: Vec<_> = self
+            .devices
+            .keys()
+            .map(|&device_id| xinput2::XIEventMask {
+                deviceid: device_id as i32,
+                mask_len: mask.len() as i32,
+                mask: mask.as_mut_ptr(),
+            })
+            .collect();
+
+        unsafe {
+            let display = conn.conn.get_raw_dpy();
+            xinput2::XISelectEvents(
+                display,
+                window_id as _,
+                masks.as_mut_ptr(),
+                masks.len() as i32,
+            );
+            // Xlib buffers its requests separately from XCB
+            x11::xlib::XFlush(display);
+        }
+    }
+
+    /// Turn a motion event from one of the pens into a [`MouseEvent::PenMoved`] event. Returns
+    /// `None` for events from other devices.
+    pub fn pen_moved(
+        &mut self, event: &MotionEvent, window_info: &WindowInfo,
+    ) -> Option<MouseEvent> {
+        let device = self.devices.get_mut(&event.source_id())?;
+
+        if let Some(pressure) = device.pressure.read(event) {
+            device.last_pressure = pressure;
+        }
+        if let Some(tilt_x) = device.tilt_x.and_then(|axis| axis.read_centered(event)) {
+            device.last_tilt.0 = tilt_x;
+        }
+        if let Some(tilt_y) = device.tilt_y.and_then(|axis| axis.read_centered(event)) {
+            device.last_tilt.1 = tilt_y;
+        }
+
+        let (x, y) = event.position();
+
+        Some(MouseEvent::PenMoved {
+            position: Point::new(x / window_info.scale_x(), y / window_info.scale_y()),
+            pressure: device.last_pressure,
+            tilt: device.last_tilt,
+            modifiers: event.modifiers(),
+        })
+    }
+}
@@ -0,0 +1,56 @@
+//! Receiving files from sandboxed applications through the document portal. Sandboxed sources
+//! can't share their own paths, so instead of a URI list they offer a key under the
+//! `application/vnd.portal.filetransfer` target. The portal turns that key into paths this
+//! application can read, using the portal's `org.freedesktop.portal.FileTransfer` interface.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::Value;
+
+use crate::DropError;
+
+const DESTINATION: &str = "org.freedesktop.portal.Documents";
+const PATH: &str = "/org/freedesktop/portal/documents";
+const INTERFACE: &str = "org.freedesktop.portal.FileTransfer";
+
+/// A connection to the session bus, which is only opened once the first file transfer arrives.
+#[derive(Default)]
+pub(crate) struct FileTransferPortal {
+    connection: Option<Connection>,
+}
+
+impl FileTransferPortal {
+    /// Retrieve the files for the key a source sent for the file transfer target. This blocks
+    /// until the portal has replied. A key can only be used once.
+    pub fn retrieve_files(&mut self, data: &[u8]) -> Result<Vec<PathBuf>, DropError> {
+        // The key is a string, which some toolkits send with a null terminator
+        let key = std::str::from_utf8(data).map_err(|_| DropError::Unavailable)?;
+        let key = key.trim_end_matches('\0');
+        if key.is_empty() {
+            return Err(DropError::Empty);
+        }
+
+        let paths = self.call_retrieve_files(key).map_err(|_err| {
+            debug!(target: "baseview::dnd", error = %_err, "could not retrieve the portal's files");
+            DropError::Unavailable
+        })?;
+        if paths.is_empty() {
+            return Err(DropError::Empty);
+        }
+
+        Ok(paths.into_iter().map(PathBuf::from).collect())
+    }
+
+    fn call_retrieve_files(&mut self, key: &str) -> zbus::Result<Vec<String>> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Connection::session()?),
+        };
+
+        let proxy = Proxy::new(connection, DESTINATION, PATH, INTERFACE)?;
+        let options: HashMap<&str, Value> = HashMap::new();
+        proxy.call("RetrieveFiles", &(key, options))
+    }
+}
@@ -0,0 +1,208 @@
+//! Custom window properties, see
+//! [`WindowOpenOptions::x11_properties`](crate::WindowOpenOptions::x11_properties).
+
+use xcb::{Atom, GenericError};
+
+use crate::X11PropertyValue;
+
+use super::xcb_connection::XcbConnection;
+
+/// A property value in the format it's sent to the X server in.
+#[derive(Debug, PartialEq, Eq)]
+enum PropertyData {
+    Format8(Vec<u8>),
+    Format32(Vec<u32>),
+}
+
+/// A `ChangeProperty` request for one of the properties.
+#[derive(Debug, PartialEq, Eq)]
+struct PropertyChange {
+    property: Atom,
+    type_: Atom,
+    data: PropertyData,
+}
+
+/// Set the properties on `window_id`. The atoms for all property names, atom values, and types are
+/// interned in a single round-trip. Errors from the `ChangeProperty` requests are reported by the
+/// event loop like any other X11 error.
+pub(super) fn set_properties(
+    conn: &XcbConnection, window_id: u32, properties: &[(String, X11PropertyValue)],
+) -> Result<(), GenericError> {
+    let atoms = conn.atoms.intern_custom(&conn.conn, &atom_names(properties))?;
+
+    let mode = xcb::PROP_MODE_REPLACE as u8;
+    for PropertyChange { property, type_, data } in property_changes(properties, &atoms) {
+        match data {
+            PropertyData::Format8(data) => {
+                xcb::change_property(&conn.conn, mode, window_id, property, type_, 8, &data);
+            }
+            PropertyData::Format32(data) => {
+                xcb::change_property(&conn.conn, mode, window_id, property, type_, 32, &data);
+            }
+        }
+    }
+    conn.conn.flush();
+
+    Ok(())
+}
+
+/// The names of all atoms needed for the properties. Every property needs its name and its type,
+/// followed by the names in an `Atom` value.
+fn atom_names(properties: &[(String, X11PropertyValue)]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for (name, value) in properties {
+        names.push(name);
+        names.push(type_name(value));
+        if let X11PropertyValue::Atom(atom_names) = value {
+            names.extend(atom_names.iter().map(String::as_str));
+        }
+    }
+
+    names
+}
+
+/// The requests for setting the properties. `atoms` contains the interned atoms for the names from
+/// [`atom_names()`], in the same order.
+fn property_changes(
+    properties: &[(String, X11PropertyValue)], atoms: &[Atom],
+) -> Vec<PropertyChange> {
+    let mut atoms = atoms.iter().copied();
+    properties
+        .iter()
+        .map(|(_, value)| {
+            let (property, type_) = (atoms.next().unwrap(), atoms.next().unwrap());
+            let value_atoms: Vec<Atom> = match value {
+                X11PropertyValue::Atom(atom_names) => {
+                    atoms.by_ref().take(atom_names.len()).collect()
+                }
+                _ => Vec::new(),
+            };
+
+            PropertyChange { property, type_, data: encode(value, &value_atoms) }
+        })
+        .collect()
+}
+
+/// The name of the value's property type.
+fn type_name(value: &X11PropertyValue) -> &'static str {
+    match value {
+        X11PropertyValue::Cardinal(_) => "CARDINAL",
+        X11PropertyValue::Atom(_) => "ATOM",
+        X11PropertyValue::String(_) => "STRING",
+        X11PropertyValue::Utf8String(_) => "UTF8_STRING",
+    }
+}
+
+/// Encode the value. `value_atoms` contains the interned atoms for an `Atom` value's names, in
+/// the same order.
+fn encode(value: &X11PropertyValue, value_atoms: &[Atom]) -> PropertyData {
+    match value {
+        X11PropertyValue::Cardinal(numbers) => PropertyData::Format32(numbers.clone()),
+        X11PropertyValue::Atom(_) => PropertyData::Format32(value_atoms.to_vec()),
+        // Latin-1 is the first 256 code points of Unicode
+        X11PropertyValue::String(text) => {
+            PropertyData::Format8(text.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect())
+        }
+        X11PropertyValue::Utf8String(text) => PropertyData::Format8(text.as_bytes().to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        assert_eq!(
+            encode(&X11PropertyValue::Cardinal(vec![0, 1, u32::MAX]), &[]),
+            PropertyData::Format32(vec![0, 1, u32::MAX])
+        );
+        assert_eq!(
+            encode(&X11PropertyValue::Atom(vec!["A".into(), "B".into()]), &[300, 301]),
+            PropertyData::Format32(vec![300, 301])
+        );
+        assert_eq!(
+            encode(&X11PropertyValue::Utf8String("Grüße ✓".into()), &[]),
+            PropertyData::Format8("Grüße ✓".as_bytes().to_vec())
+        );
+        assert_eq!(
+            encode(&X11PropertyValue::Cardinal(Vec::new()), &[]),
+            PropertyData::Format32(Vec::new())
+        );
+        assert_eq!(
+            encode(&X11PropertyValue::Utf8String(String::new()), &[]),
+            PropertyData::Format8(Vec::new())
+        );
+    }
+
+    #[test]
+    fn latin1_strings() {
+        // Characters outside of Latin-1 can't be represented
+        assert_eq!(
+            encode(&X11PropertyValue::String("Grüße ✓".into()), &[]),
+            PropertyData::Format8(vec![b'G', b'r', 0xfc, 0xdf, b'e', b' ', b'?'])
+        );
+        assert_eq!(
+            encode(&X11PropertyValue::String("\u{ff}\u{100}".into()), &[]),
+            PropertyData::Format8(vec![0xff, b'?'])
+        );
+    }
+
+    #[test]
+    fn atoms_for_properties() {
+        let properties = vec![
+            ("_HOST_SHM_ID".to_string(), X11PropertyValue::Cardinal(vec![42])),
+            (
+                "_HOST_CAPABILITIES".to_string(),
+                X11PropertyValue::Atom(vec!["_HOST_RESIZE".into(), "_HOST_FOCUS".into()]),
+            ),
+            ("_HOST_NAME".to_string(), X11PropertyValue::Utf8String("host".into())),
+        ];
+
+        assert_eq!(
+            atom_names(&properties),
+            [
+                "_HOST_SHM_ID",
+                "CARDINAL",
+                "_HOST_CAPABILITIES",
+                "ATOM",
+                "_HOST_RESIZE",
+                "_HOST_FOCUS",
+                "_HOST_NAME",
+                "UTF8_STRING",
+            ]
+        );
+
+        let atoms = [300, 6, 301, 4, 302, 303, 304, 305];
+        assert_eq!(
+            property_changes(&properties, &atoms),
+            [
+                PropertyChange { property: 300, type_: 6, data: PropertyData::Format32(vec![42]) },
+                PropertyChange {
+                    property: 301,
+                    type_: 4,
+                    data: PropertyData::Format32(vec![302, 303]),
+                },
+                PropertyChange {
+                    property: 304,
+                    type_: 305,
+                    data: PropertyData::Format8(b"host".to_vec()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn latin1_and_empty_atom_properties() {
+        let properties = vec![
+            ("A".to_string(), X11PropertyValue::String("a".into())),
+            ("B".to_string(), X11PropertyValue::Atom(Vec::new())),
+        ];
+
+        assert_eq!(atom_names(&properties), ["A", "STRING", "B", "ATOM"]);
+        assert_eq!(
+            property_changes(&properties, &[1, 2, 3, 4])[1],
+            PropertyChange { property: 3, type_: 4, data: PropertyData::Format32(Vec::new()) }
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Picks the system scale factor from the values the X server reports. Misconfigured systems are
+//! common enough that these can't be trusted blindly: virtual displays report physical sizes of a
+//! few millimeters, and `Xft.dpi` is sometimes set to 0 or to ten times the actual DPI.
+
+use std::ops::RangeInclusive;
+
+/// The scale factors that could be read from the X server. Either of them may be missing.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DetectedScale {
+    /// `Xft.dpi` divided by 96.
+    pub xft: Option<f64>,
+    /// The horizontal and vertical scale factors computed from the screen's size in pixels and
+    /// millimeters.
+    pub screen_dimensions: Option<(f64, f64)>,
+}
+
+/// Pick the system's horizontal and vertical scale factors. `Xft.dpi` is preferred as long as it's
+/// within `range`, and the screen's dimensions are used otherwise. When neither is within the
+/// range, `fallback` is used if it's set, and otherwise the detected scale factor is clamped to the
+/// range. If nothing was detected at all the scale factor is the fallback or 1.0.
+pub fn resolve_system_scale(
+    detected: DetectedScale, range: &RangeInclusive<f64>, fallback: Option<f64>,
+) -> (f64, f64) {
+    let (min, max) = (*range.start(), *range.end());
+    let is_plausible = |scale: f64| scale >= min && scale <= max;
+
+    if let Some(xft) = detected.xft.filter(|&scale| is_plausible(scale)) {
+        return (xft, xft);
+    }
+    if let Some((scale_x, scale_y)) = detected
+        .screen_dimensions
+        .filter(|&(scale_x, scale_y)| is_plausible(scale_x) && is_plausible(scale_y))
+    {
+        if detected.xft.is_some() {
+            debug!(
+                target: "baseview::platform",
+                xft = ?detected.xft,
+                "ignoring the implausible Xft.dpi scale factor"
+            );
+        }
+        return (scale_x, scale_y);
+    }
+
+    let Some((scale_x, scale_y)) =
+        detected.xft.map(|scale| (scale, scale)).or(detected.screen_dimensions)
+    else {
+        let scale = fallback.unwrap_or(1.0);
+        return (scale, scale);
+    };
+
+    match fallback {
+        Some(scale) => {
+            warn!(
+                target: "baseview::platform",
+                scale_x, scale_y, fallback = scale,
+                "the system scale factor is implausible, using the fallback instead"
+            );
+            (scale, scale)
+        }
+        None => {
+            // This doesn't use `f64::clamp()` since that panics on invalid ranges
+            let clamp = |scale: f64| scale.max(min).min(max);
+            let clamped = (clamp(scale_x), clamp(scale_y));
+            warn!(
+                target: "baseview::platform",
+                scale_x, scale_y, clamped_x = clamped.0, clamped_y = clamped.1,
+                "the system scale factor is implausible, clamping it"
+            );
+            clamped
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_SYSTEM_SCALE_RANGE;
+
+    fn xft(dpi: f64) -> Option<f64> {
+        Some(dpi / 96.0)
+    }
+
+    /// The scale factors computed from a screen's size in pixels and millimeters, like
+    /// `get_scaling_screen_dimensions()` does.
+    fn screen(width_px: f64, height_px: f64, width_mm: f64, height_mm: f64) -> Option<(f64, f64)> {
+        let dpi = |px: f64, mm: f64| px / (mm / 25.4);
+        Some((dpi(width_px, width_mm) / 96.0, dpi(height_px, height_mm) / 96.0))
+    }
+
+    #[test]
+    fn pathological_inputs() {
+        #[rustfmt::skip]
+        let cases = [
+            // A regular 1080p monitor with Xft.dpi set to 144
+            ("Xft.dpi is preferred", xft(144.0), screen(1920.0, 1080.0, 508.0, 286.0), None, (1.5, 1.5)),
+            ("no Xft.dpi", None, screen(1920.0, 1080.0, 508.0, 286.0), None, (0.9999, 0.9999)),
+            // A VNC or Xvfb display reporting a 10 mm wide screen, without Xft.dpi
+            ("tiny virtual screen", None, screen(1920.0, 1080.0, 10.0, 10.0), None, (4.0, 4.0)),
+            ("tiny virtual screen with a fallback", None, screen(1920.0, 1080.0, 10.0, 10.0), Some(1.25), (1.25, 1.25)),
+            // A desktop environment writing Xft.dpi as 0 before the first login
+            ("Xft.dpi of 0", xft(0.0), screen(1920.0, 1080.0, 508.0, 286.0), None, (0.9999, 0.9999)),
+            // Xft.dpi accidentally set to ten times the actual DPI
+            ("Xft.dpi of 960", xft(960.0), screen(1920.0, 1080.0, 508.0, 286.0), None, (0.9999, 0.9999)),
+            ("Xft.dpi of 960 on a tiny virtual screen", xft(960.0), screen(1920.0, 1080.0, 10.0, 10.0), None, (4.0, 4.0)),
+            ("Xft.dpi of 9.6", xft(9.6), None, None, (0.5, 0.5)),
+            ("Xft.dpi of 9.6 with a fallback", xft(9.6), None, Some(2.0), (2.0, 2.0)),
+            // A projector reporting a screen that's several meters wide
+            ("huge screen", None, screen(1024.0, 768.0, 3000.0, 2250.0), None, (0.5, 0.5)),
+            ("nothing detected", None, None, None, (1.0, 1.0)),
+            ("nothing detected with a fallback", None, None, Some(1.5), (1.5, 1.5)),
+        ];
+
+        for (description, xft, screen_dimensions, fallback, expected) in cases {
+            let detected = DetectedScale { xft, screen_dimensions };
+            let (scale_x, scale_y) =
+                resolve_system_scale(detected, &DEFAULT_SYSTEM_SCALE_RANGE, fallback);
+
+            assert!(
+                (scale_x - expected.0).abs() < 0.001 && (scale_y - expected.1).abs() < 0.001,
+                "{}: expected {:?}, got {:?}",
+                description,
+                expected,
+                (scale_x, scale_y)
+            );
+        }
+    }
+
+    #[test]
+    fn mismatched_screen_axes() {
+        // Only one of the axes is plausible, so neither is used
+        let detected = DetectedScale { xft: None, screen_dimensions: Some((1.0, 20.0)) };
+        assert_eq!(resolve_system_scale(detected, &DEFAULT_SYSTEM_SCALE_RANGE, None), (1.0, 4.0));
+
+        let detected = DetectedScale { xft: None, screen_dimensions: Some((1.25, 1.5)) };
+        assert_eq!(resolve_system_scale(detected, &DEFAULT_SYSTEM_SCALE_RANGE, None), (1.25, 1.5));
+    }
+
+    #[test]
+    fn custom_ranges() {
+        let detected = DetectedScale { xft: Some(5.0), screen_dimensions: None };
+        assert_eq!(resolve_system_scale(detected, &(1.0..=8.0), None), (5.0, 5.0));
+        assert_eq!(resolve_system_scale(detected, &(1.0..=2.0), None), (2.0, 2.0));
+
+        // The range boundaries are inclusive
+        let detected = DetectedScale { xft: Some(0.5), screen_dimensions: Some((3.0, 3.0)) };
+        assert_eq!(resolve_system_scale(detected, &DEFAULT_SYSTEM_SCALE_RANGE, None), (0.5, 0.5));
+    }
+}
@@ -1,36 +1,56 @@
+use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::ops::RangeInclusive;
 use std::os::raw::{c_ulong, c_void};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{mpsc, Arc, RwLock};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::*;
 
-use keyboard_types::Modifiers;
-use raw_window_handle::{
-    HasRawDisplayHandle, HasRawWindowHandle, RawWindowHandle, XlibDisplayHandle, XlibWindowHandle,
-};
+use keyboard_types::{Code, Modifiers};
 use xcb::ffi::xcb_screen_t;
 use xcb::StructPtr;
 use xcb_util::icccm;
 
 use super::drag_handler::DragHandler;
-use super::drop_handler::{DndState, DropHandler};
-use super::XcbConnection;
+use super::drop_handler::DropHandler;
+use super::event_dispatcher::{WindowMessage, XcbEvent};
+use super::frame_pacer::FramePacer;
+use super::pen::{MotionEvent, PenDevices};
+use super::properties;
+use super::scaling::resolve_system_scale;
+use super::{X11Connection, XcbConnection};
+use crate::drop_effect::DragEventOrder;
+use crate::event_clock::EventClock;
+use crate::held_inputs::HeldInputs;
+use crate::lifecycle::Lifecycle;
+use crate::mouse_cursor::CursorRegions;
+use crate::platform_handle::{PlatformDisplayHandle, PlatformWindowHandle};
+use crate::region::Damage;
+use crate::user_data::UserData;
 use crate::{
-    Data, Event, MouseButton, MouseCursor, MouseEvent, PhyPoint, PhySize, Point, ScrollDelta, Size,
-    WindowEvent, WindowHandler, WindowInfo, WindowOpenOptions, WindowScalePolicy,
+    AttentionType, CursorIcon, CursorId, Data, DialogId, DragImage, DropEffect, DropError,
+    DropPayload, Error, Event, FileDialogOptions, FrameSource, MouseButton, MouseButtonSet,
+    MouseCursor, MouseEvent, PhyPoint, PhyRect, PhySize, Point, Rect, Region, ScrollDelta, Size,
+    SurfaceInfo, WindowEvent, WindowHandler, WindowId, WindowInfo, WindowKind, WindowOpenOptions,
+    WindowScalePolicy, X11Error, X11ErrorHandler, X11PropertyValue,
 };
 
 use super::keyboard::{convert_key_press_event, convert_key_release_event, key_mods};
 
+#[cfg(feature = "accesskit")]
+use crate::accessibility::AccessKitHandler;
+
 #[cfg(feature = "opengl")]
-use crate::{
-    gl::{platform, GlContext},
-    window::RawWindowHandleWrapper,
-};
+use crate::gl::{platform, GlContext, GlError};
+
+#[cfg(feature = "tracing")]
+use super::xcb_connection::RequestCount;
 
 pub struct WindowHandle {
-    raw_window_handle: Option<RawWindowHandle>,
+    id: WindowId,
+    raw_window_handle: Option<PlatformWindowHandle>,
     close_requested: Arc<AtomicBool>,
     is_open: Arc<AtomicBool>,
 
@@ -52,38 +72,77 @@ impl WindowHandle {
     pub fn is_open(&self) -> bool {
         self.is_open.load(Ordering::Relaxed)
     }
-}
 
-unsafe impl HasRawWindowHandle for WindowHandle {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        if let Some(raw_window_handle) = self.raw_window_handle {
-            if self.is_open.load(Ordering::Relaxed) {
-                return raw_window_handle;
-            }
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> Option<PlatformWindowHandle> {
+        self.raw_window_handle.filter(|_| self.is_open.load(Ordering::Relaxed))
+    }
+
+    pub fn remote(&self) -> RemoteWindowHandle {
+        RemoteWindowHandle {
+            id: self.id,
+            close_requested: Arc::clone(&self.close_requested),
+            is_open: Arc::clone(&self.is_open),
+        }
+    }
+
+    /// The window runs its event loop on another thread, so the tick is forwarded to it.
+    pub fn trigger_frame(&self) {
+        if let Some(PlatformWindowHandle::Xlib { window, .. }) = self.platform_window_handle() {
+            XcbConnection::shared().dispatcher.send(window as u32, WindowMessage::Frame);
         }
+    }
+}
 
-        RawWindowHandle::Xlib(XlibWindowHandle::empty())
+/// Shares the atomics of a [`WindowHandle`]. The window's event loop checks the close request on
+/// every iteration, so it doesn't matter which thread sets it.
+#[derive(Clone)]
+pub struct RemoteWindowHandle {
+    id: WindowId,
+    close_requested: Arc<AtomicBool>,
+    is_open: Arc<AtomicBool>,
+}
+
+impl RemoteWindowHandle {
+    pub fn close(&self) {
+        self.close_requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open.load(Ordering::Relaxed)
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
     }
 }
 
 struct ParentHandle {
+    /// The ID of the window this handle belongs to. The window is created on another thread, so
+    /// the ID is assigned here.
+    window_id: WindowId,
     close_requested: Arc<AtomicBool>,
     is_open: Arc<AtomicBool>,
 }
 
 impl ParentHandle {
     pub fn new() -> (Self, WindowHandle) {
+        let window_id = WindowId::next();
         let close_requested = Arc::new(AtomicBool::new(false));
         let is_open = Arc::new(AtomicBool::new(true));
 
         let handle = WindowHandle {
+            id: window_id,
             raw_window_handle: None,
             close_requested: Arc::clone(&close_requested),
             is_open: Arc::clone(&is_open),
             _phantom: PhantomData::default(),
         };
 
-        (Self { close_requested, is_open }, handle)
+        (Self { window_id, close_requested, is_open }, handle)
     }
 
     pub fn parent_did_drop(&self) -> bool {
@@ -98,75 +157,196 @@ impl Drop for ParentHandle {
 }
 
 pub struct Window {
-    xcb_connection: Option<XcbConnection>,
+    id: WindowId,
+    xcb_connection: &'static XcbConnection,
     window_id: u32,
     window_info: WindowInfo,
-    // FIXME: There's all this mouse cursor logic but it's never actually used, is this correct?
+    /// Needed to tell whether the scale factor should follow the system's after a screen change.
+    scale_policy: WindowScalePolicy,
+    /// See [`crate::EventContext::set_scale_override`]. This takes precedence over `scale_policy`.
+    scale_override: Option<f64>,
+    /// Set when the scale policy or the override changed, so the window info is updated once the
+    /// handler returns.
+    scale_changed: bool,
+    /// See [`WindowOpenOptions::system_scale_range`].
+    system_scale_range: RangeInclusive<f64>,
+    /// Whether the user can resize the window. Otherwise the size hints pin the window to its
+    /// current size, and they're updated whenever the window is resized programmatically.
+    resizable: bool,
+    /// The cursor set through [`crate::EventContext::set_mouse_cursor`].
     mouse_cursor: MouseCursor,
-
-    frame_interval: Duration,
+    cursor_regions: CursorRegions,
+    /// The cursor that's actually set on the window, resolved from the two fields above.
+    shown_cursor: MouseCursor,
+    /// The cursors registered through [`crate::EventContext::register_cursor`]. Unlike the built-in
+    /// cursors these belong to the window, so they're freed when the window is dropped.
+    custom_cursors: HashMap<CursorId, u32>,
+    /// Whether the window grabbed the pointer for [`crate::EventContext::set_cursor_confined`].
+    cursor_confined: bool,
+    /// Tracked from `MapNotify` and `UnmapNotify` events. See [`crate::EventContext::is_visible`].
+    mapped: bool,
+    /// Tracked from `FocusIn` and `FocusOut` events. See [`crate::EventContext::has_focus`].
+    focused: bool,
+    /// Whether the window manager set `_NET_WM_STATE_HIDDEN` on the window, tracked from
+    /// `PropertyNotify` events. This is only used for windows that aren't parented, since the
+    /// property is set on the host's top-level window otherwise.
+    minimized: bool,
+
+    /// The window's events and errors, as forwarded by the connection's event dispatcher.
+    events: Receiver<WindowMessage>,
+    /// A message that was received while waiting for the next event, and that hasn't been
+    /// handled yet.
+    next_message: Option<WindowMessage>,
+
+    frame_pacer: FramePacer,
+    /// With [`FrameSource::External`] the frame pacer isn't used, and frames are only drawn after
+    /// `frame_triggered` has been set by a [`WindowMessage::Frame`] message.
+    frame_source: FrameSource,
+    frame_triggered: bool,
     event_loop_running: bool,
     close_requested: bool,
-
+    /// Frames are only drawn after the initial `Resized` event, and the handler isn't called at
+    /// all anymore after `WillClose`.
+    lifecycle: Lifecycle,
+    /// Whether `on_frame` should be called continuously. See
+    /// [`crate::EventContext::set_animating`].
+    animating: bool,
+    /// Set when events were delivered to the handler so a single frame gets drawn even when the
+    /// window isn't animating.
+    frame_pending: bool,
+    /// See [`crate::EventContext::pause_frames`]. `frame_pending` is left untouched while paused.
+    frames_paused: bool,
+    /// See [`crate::EventContext::damage`].
+    damage: Damage,
+
+    /// Whether drag and drop was enabled in the options and the DND atoms could be interned. See
+    /// [`crate::EventContext::drag_and_drop_available`].
+    dnd_enabled: bool,
     drag_handler: Arc<RwLock<DragHandler>>,
     drop_handler: DropHandler,
+    /// The incoming drag events the handler has received for the drag that's over the window.
+    drag_event_order: DragEventOrder,
+    /// The graphics tablets' pens, whose pressure and tilt are reported through
+    /// [`MouseEvent::PenMoved`] events.
+    pen_devices: PenDevices,
+    /// The physical position of the last `CursorMoved` event, used to compute its `delta`.
+    last_cursor_position: Option<PhyPoint>,
 
     new_physical_size: Option<PhySize>,
+    /// Whether the window was embedded in another window.
+    parented: bool,
+    /// Whether the window was embedded in another baseview window. See [`KEY_EVENT_MASK`].
+    nested: bool,
+    /// Whether the window is a popup. Popups are override-redirect windows, which the window
+    /// manager doesn't manage.
+    popup: bool,
+    /// Set for parented windows and focusable popups, which nothing else gives the focus.
+    focus_on_click: bool,
+    /// Set while the focus is on one of the window's children, like a window nested in this one.
+    /// The window manager doesn't move the focus back when this window is clicked, since the
+    /// focus is already inside of its top-level window, so the window takes it back itself.
+    focus_in_child: bool,
     parent_handle: Option<ParentHandle>,
+    /// Used to synthesize release events when the window loses focus.
+    held_inputs: HeldInputs,
+    event_clock: EventClock,
+    user_data: UserData,
+    x11_error_handler: Option<X11ErrorHandler>,
+    /// Set when the X server reported that the window no longer exists.
+    window_destroyed: bool,
+    /// The depth of the window's visual.
+    depth: u8,
 
     #[cfg(feature = "opengl")]
     gl_context: Option<GlContext>,
+    /// See [`crate::EventContext::gl_context_error()`].
+    #[cfg(feature = "opengl")]
+    gl_context_error: Option<GlError>,
+    /// Talks to AT-SPI on a thread of its own, and sends the requests it receives to this window's
+    /// event loop.
+    #[cfg(feature = "accesskit")]
+    accesskit: Option<accesskit_unix::Adapter>,
 }
 
 impl Drop for Window {
     fn drop(&mut self) {
-        let conn = self.xcb_connection.take().unwrap();
-        xcb::destroy_window_checked(&conn.conn, self.window_id).request_check().unwrap();
-        // Don't actually trigger the drop because this will cause a segfault
-        std::mem::forget(conn);
+        let conn = self.xcb_connection;
+        for (_, xid) in self.custom_cursors.drain() {
+            if xid != 0 {
+                xcb::free_cursor(&conn.conn, xid);
+            }
+        }
+        if self.cursor_confined {
+            xcb::ungrab_pointer(&conn.conn, xcb::CURRENT_TIME);
+        }
+        if !self.window_destroyed {
+            xcb::destroy_window_checked(&conn.conn, self.window_id).request_check().unwrap();
+        }
+        conn.dispatcher.unregister_window(self.window_id);
+        debug!(target: "baseview::lifecycle", "destroyed window");
     }
 }
 
-// Hack to allow sending a RawWindowHandle between threads. Do not make public
-struct SendableRwh(RawWindowHandle);
+/// How often the event loop checks whether the parent's handle was dropped while the window isn't
+/// animating.
+const IDLE_PARENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The events every window selects, except for the key events. See [`KEY_EVENT_MASK`].
+const WINDOW_EVENT_MASK: u32 = xcb::EVENT_MASK_EXPOSURE
+    | xcb::EVENT_MASK_POINTER_MOTION
+    | xcb::EVENT_MASK_BUTTON_PRESS
+    | xcb::EVENT_MASK_BUTTON_RELEASE
+    | xcb::EVENT_MASK_STRUCTURE_NOTIFY
+    | xcb::EVENT_MASK_FOCUS_CHANGE
+    | xcb::EVENT_MASK_PROPERTY_CHANGE;
+
+/// The X server sends key events to the window under the pointer when that's inside of the
+/// focused window. A window nested in another baseview window would then receive the key presses
+/// meant for the focused parent just because the pointer is over it, so nested windows only select
+/// these while they have the focus. Without a selection the events go to the parent instead.
+const KEY_EVENT_MASK: u32 = xcb::EVENT_MASK_KEY_PRESS | xcb::EVENT_MASK_KEY_RELEASE;
+
+/// The pointer events the window receives while the cursor is confined to it.
+const CONFINED_EVENT_MASK: u32 =
+    xcb::EVENT_MASK_POINTER_MOTION | xcb::EVENT_MASK_BUTTON_PRESS | xcb::EVENT_MASK_BUTTON_RELEASE;
 
-unsafe impl Send for SendableRwh {}
+/// The line deltas for the scroll wheel buttons 4 to 7, which scroll up, down, left, and right.
+/// See [`ScrollDelta`] for the signs.
+const SCROLL_BUTTON_DELTAS: [(f32, f32); 4] = [(0.0, 1.0), (0.0, -1.0), (-1.0, 0.0), (1.0, 0.0)];
+const _: () = assert!(SCROLL_BUTTON_DELTAS[0].1 > 0.0 && SCROLL_BUTTON_DELTAS[3].0 > 0.0);
 
-type WindowOpenResult = Result<SendableRwh, ()>;
+type WindowOpenResult = Result<PlatformWindowHandle, Error>;
 
 impl Window {
-    pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
+    pub fn open_parented<H, B>(
+        parent: PlatformWindowHandle, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, Error>
     where
-        P: HasRawWindowHandle,
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
+        B: FnOnce(&mut crate::EventContext) -> H,
         B: Send + 'static,
     {
-        // Convert parent into something that X understands
-        let parent_id = match parent.raw_window_handle() {
-            RawWindowHandle::Xlib(h) => h.window as u32,
-            RawWindowHandle::Xcb(h) => h.window,
-            h => panic!("unsupported parent handle type {:?}", h),
-        };
+        let PlatformWindowHandle::Xlib { window, .. } = parent;
+        let parent_id = window as u32;
 
         let (tx, rx) = mpsc::sync_channel::<WindowOpenResult>(1);
 
         let (parent_handle, mut window_handle) = ParentHandle::new();
 
         thread::spawn(move || {
-            Self::window_thread(Some(parent_id), options, build, tx.clone(), Some(parent_handle));
+            Self::window_thread(Some(parent_id), options, build, Some(tx), Some(parent_handle));
         });
 
-        let raw_window_handle = rx.recv().unwrap().unwrap();
-        window_handle.raw_window_handle = Some(raw_window_handle.0);
+        let raw_window_handle = rx.recv().unwrap()?;
+        window_handle.raw_window_handle = Some(raw_window_handle);
 
-        window_handle
+        Ok(window_handle)
     }
 
     pub fn open_as_if_parented<H, B>(options: WindowOpenOptions, build: B) -> WindowHandle
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
+        B: FnOnce(&mut crate::EventContext) -> H,
         B: Send + 'static,
     {
         let (tx, rx) = mpsc::sync_channel::<WindowOpenResult>(1);
@@ -174,11 +354,11 @@ impl Window {
         let (parent_handle, mut window_handle) = ParentHandle::new();
 
         thread::spawn(move || {
-            Self::window_thread(None, options, build, tx.clone(), Some(parent_handle));
+            Self::window_thread(None, options, build, Some(tx), Some(parent_handle));
         });
 
-        let raw_window_handle = rx.recv().unwrap().unwrap();
-        window_handle.raw_window_handle = Some(raw_window_handle.0);
+        let raw_window_handle = rx.recv().unwrap().unwrap_or_else(|err| panic!("{}", err));
+        window_handle.raw_window_handle = Some(raw_window_handle);
 
         window_handle
     }
@@ -186,37 +366,29 @@ impl Window {
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
     where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
+        B: 'static,
     {
-        let (tx, rx) = mpsc::sync_channel::<WindowOpenResult>(1);
-
-        let thread = thread::spawn(move || {
-            Self::window_thread(None, options, build, tx, None);
-        });
-
-        let _ = rx.recv().unwrap().unwrap();
-
-        thread.join().unwrap_or_else(|err| {
-            eprintln!("Window thread panicked: {:#?}", err);
-        });
+        // There's no need for a separate thread when blocking anyways, and running the event loop
+        // on the calling thread means that `build` doesn't need to be `Send`
+        Self::window_thread(None, options, build, None, None);
     }
 
     fn conn(&self) -> &XcbConnection {
-        self.xcb_connection.as_ref().unwrap()
+        self.xcb_connection
     }
 
+    /// Create the window and run its event loop on the current thread. If `tx` is set, the
+    /// window's handle is sent through it once the window has been created.
     fn window_thread<H, B>(
         parent: Option<u32>, mut options: WindowOpenOptions, build: B,
-        tx: mpsc::SyncSender<WindowOpenResult>, parent_handle: Option<ParentHandle>,
+        tx: Option<mpsc::SyncSender<WindowOpenResult>>, parent_handle: Option<ParentHandle>,
     ) where
         H: WindowHandler + 'static,
-        B: FnOnce(&mut crate::Window) -> H,
-        B: Send + 'static,
+        B: FnOnce(&mut crate::EventContext) -> H,
     {
-        // Connect to the X server
-        // FIXME: baseview error type instead of unwrap()
-        let xcb_connection = XcbConnection::new().unwrap();
+        // All windows share the same connection to the X server
+        let xcb_connection = XcbConnection::shared();
 
         // Get screen information (?)
         let setup = xcb_connection.conn.get_setup();
@@ -233,12 +405,12 @@ impl Window {
             &[(xcb::GC_FOREGROUND, screen.black_pixel()), (xcb::GC_GRAPHICS_EXPOSURES, 0)],
         );
 
-        let scaling = match options.scale {
-            WindowScalePolicy::SystemScaleFactor => xcb_connection.get_scaling().unwrap_or(1.0),
-            WindowScalePolicy::ScaleFactor(scale) => scale,
+        let (scale_x, scale_y) = match options.scale {
+            WindowScalePolicy::ScaleFactor(scale) => (scale, scale),
+            policy => system_scale(xcb_connection, policy, &options.system_scale_range),
         };
 
-        let window_info = WindowInfo::from_logical_size(options.size, scaling);
+        let window_info = WindowInfo::from_logical_size_non_uniform(options.size, scale_x, scale_y);
 
         // Now it starts becoming fun. If we're creating an OpenGL context, then we need to create
         // the window with a visual that matches the framebuffer used for the OpenGL context. So the
@@ -251,18 +423,28 @@ impl Window {
                 .map(|visual| (32, visual))
                 .unwrap_or((xcb::COPY_FROM_PARENT as u8, xcb::COPY_FROM_PARENT as u32))
         };
+        // Unless the OpenGL configuration allows falling back to a window without OpenGL, failing
+        // to create the context is fatal
         #[cfg(feature = "opengl")]
-        let (fb_config, (depth, visual)) = match options.gl_config {
-            Some(gl_config) => unsafe {
-                platform::GlContext::get_fb_config_and_visual(
-                    xcb_connection.conn.get_raw_dpy(),
-                    gl_config,
-                )
-                .map(|(fb_config, window_config)| {
-                    (Some(fb_config), (window_config.depth, window_config.visual))
-                })
-                .expect("Could not fetch framebuffer config")
-            },
+        let fallback_to_no_gl =
+            options.gl_config.as_ref().is_some_and(|gl_config| gl_config.fallback_to_no_gl);
+        #[cfg(feature = "opengl")]
+        let mut gl_context_error = None;
+        #[cfg(feature = "opengl")]
+        let (fb_config, (depth, visual)) = match options.gl_config.take().map(|gl_config| unsafe {
+            platform::GlContext::get_fb_config_and_visual(
+                xcb_connection.conn.get_raw_dpy(),
+                gl_config,
+            )
+        }) {
+            Some(Ok((fb_config, window_config))) => {
+                (Some(fb_config), (window_config.depth, window_config.visual))
+            }
+            Some(Err(err)) if fallback_to_no_gl => {
+                gl_context_error = Some(err);
+                (None, create_default_config())
+            }
+            Some(Err(err)) => panic!("Could not fetch framebuffer config: {}", err),
             None => (None, create_default_config()),
         };
         #[cfg(not(feature = "opengl"))]
@@ -281,54 +463,88 @@ impl Window {
 
         // Calculate physical size from logical size using the actual scaling factor
         // This ensures we're using the same coordinate system as the screen dimensions
-        let width = (options.size.width * scaling).round() as u32;
-        let height = (options.size.height * scaling).round() as u32;
+        let physical_size = window_info.physical_size();
 
         // Center the window on the screen if not parented
         let (x, y) = if parent.is_none() {
-            let screen_width = screen.width_in_pixels();
-            let screen_height = screen.height_in_pixels();
-            let x = (screen_width as i32 - width as i32) / 2;
-            let y = (screen_height as i32 - height as i32) / 2;
+            let screen_size =
+                PhySize::new(screen.width_in_pixels().into(), screen.height_in_pixels().into());
+            let (screen_corner, corner) = (screen_size.to_point(), physical_size.to_point());
+            let x = (screen_corner.x - corner.x) / 2;
+            let y = (screen_corner.y - corner.y) / 2;
+            // The screen is at most 65535 pixels wide, so half of that fits in an `i16`
             (x.max(0) as i16, y.max(0) as i16)
         } else {
             (0, 0)
         };
 
+        // Popups bypass the window manager, so they don't get decorations, a taskbar entry, or the
+        // focus. Only focusable popups take the focus when they're clicked.
+        let popup_focusable = match options.window_kind {
+            WindowKind::Popup { focusable, .. } if parent.is_none() => Some(focusable),
+            _ => None,
+        };
+
+        let (width, height) =
+            (x11_dimension(physical_size.width), x11_dimension(physical_size.height));
         let window_id = xcb_connection.conn.generate_id();
-        xcb::create_window_checked(
+        let _span = window_span!(window_id);
+        // This needs to happen before the window is created, or its first events could get lost
+        let nested = parent.is_some_and(|parent| xcb_connection.dispatcher.is_registered(parent));
+        let events = xcb_connection.dispatcher.register_window(window_id);
+        // Checking this request separately would cost a round-trip of its own. If it fails, the
+        // geometry request below fails as well.
+        xcb::create_window(
             &xcb_connection.conn,
             depth,
             window_id,
             parent_id,
-            x,             // x coordinate of the new window
-            y,             // y coordinate of the new window
-            width as u16,  // window width
-            height as u16, // window height
-            0,             // window border
+            x,      // x coordinate of the new window
+            y,      // y coordinate of the new window
+            width,  // window width
+            height, // window height
+            0,      // window border
             xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
             visual,
             &[
                 (
                     xcb::CW_EVENT_MASK,
-                    xcb::EVENT_MASK_EXPOSURE
-                        | xcb::EVENT_MASK_POINTER_MOTION
-                        | xcb::EVENT_MASK_BUTTON_PRESS
-                        | xcb::EVENT_MASK_BUTTON_RELEASE
-                        | xcb::EVENT_MASK_KEY_PRESS
-                        | xcb::EVENT_MASK_KEY_RELEASE
-                        | xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+                    if nested { WINDOW_EVENT_MASK } else { WINDOW_EVENT_MASK | KEY_EVENT_MASK },
                 ),
                 // As mentioned above, these two values are needed to be able to create a window
                 // with a dpeth of 32-bits when the parent window has a different depth
                 (xcb::CW_COLORMAP, colormap),
                 (xcb::CW_BORDER_PIXEL, 0),
+                (xcb::CW_OVERRIDE_REDIRECT, popup_focusable.is_some() as u32),
             ],
-        )
-        .request_check()
-        .unwrap();
+        );
 
+        // The window inherits its parent's depth if there's no 32-bit visual
+        let depth = xcb::get_geometry(&xcb_connection.conn, window_id)
+            .get_reply()
+            .expect("Could not create the window")
+            .depth();
+        debug!(target: "baseview::lifecycle", ?parent, ?physical_size, "created window");
+
+        let pen_devices = PenDevices::query(xcb_connection);
+        pen_devices.select_events(xcb_connection, window_id);
+
+        // This and the requests below only happen once, but nothing can be done about them failing
+        // at this point. Their errors are reported by the event loop instead of blocking here.
+        if popup_focusable.is_some() {
+            set_popup_properties(xcb_connection, window_id);
+        }
+        if let Err(_err) =
+            properties::set_properties(xcb_connection, window_id, &options.x11_properties)
+        {
+            warn!(
+                target: "baseview::platform",
+                error_code = _err.error_code(),
+                "could not set the X11 properties"
+            );
+        }
         xcb::map_window(&xcb_connection.conn, window_id);
+        debug!(target: "baseview::lifecycle", "mapped window");
 
         // Change window title
         let title = options.title;
@@ -342,315 +558,1148 @@ impl Window {
             title.as_bytes(),
         );
         // Allow window to be a drop target
-        let atom = xcb_connection.get_atom("XdndAware");
-        let version = &[5];
-        xcb::change_property(
-            &xcb_connection.conn,
-            xcb::PROP_MODE_REPLACE as u8,
-            window_id,
-            atom,
-            xcb::ATOM_ATOM,
-            32, // view data as 8-bit
-            version,
-        );
+        let dnd_enabled = options.enable_drag_and_drop && xcb_connection.atoms.dnd_enabled;
+        if dnd_enabled {
+            let version = &[5];
+            xcb::change_property(
+                &xcb_connection.conn,
+                xcb::PROP_MODE_REPLACE as u8,
+                window_id,
+                xcb_connection.atoms.dnd.aware,
+                xcb::ATOM_ATOM,
+                32, // view data as 8-bit
+                version,
+            );
+        }
 
+        // See `take_focus()` for how the focus is handled
         icccm::set_wm_protocols(
             &xcb_connection.conn,
             window_id,
             xcb_connection.atoms.wm_protocols,
-            &[xcb_connection.atoms.wm_delete_window],
+            &[xcb_connection.atoms.wm_delete_window, xcb_connection.atoms.wm_take_focus],
+        );
+        icccm::set_wm_hints(
+            &xcb_connection.conn,
+            window_id,
+            &icccm::WmHints::empty()
+                .input(parent.is_none() && popup_focusable != Some(false))
+                .build(),
         );
 
         if !options.resizable {
-            icccm::set_wm_size_hints(
-                &xcb_connection.conn,
-                window_id,
-                xcb::ATOM_WM_NORMAL_HINTS,
-                &icccm::SizeHints::empty()
-                    .min_size(width as i32, height as i32)
-                    .max_size(width as i32, height as i32)
-                    .build(),
-            );
+            set_fixed_size_hints(xcb_connection, window_id, window_info.physical_size());
         }
 
         xcb_connection.conn.flush();
 
+        let panic_policy = options.on_handler_panic;
+
         let mut drop_handler = DropHandler::default();
         drop_handler.drop_target_valid = options.drop_target_valid.take();
 
-        // TODO: These APIs could use a couple tweaks now that everything is internal and there is
-        //       no error handling anymore at this point. Everything is more or less unchanged
-        //       compared to when raw-gl-context was a separate crate.
+        // TODO: These APIs could use a couple tweaks now that everything is internal. Everything
+        //       is more or less unchanged compared to when raw-gl-context was a separate crate.
         #[cfg(feature = "opengl")]
-        let gl_context = fb_config.map(|fb_config| {
-            let mut handle = XlibWindowHandle::empty();
-            handle.window = window_id as c_ulong;
-            //handle.display = xcb_connection.conn.get_raw_dpy() as *mut c_void;
-            let mut display = XlibDisplayHandle::empty();
-            display.display = xcb_connection.conn.get_raw_dpy() as *mut c_void;
-            let handle = RawWindowHandleWrapper { handle: RawWindowHandle::Xlib(handle) };
-            let display = RawDisplayHandle::Xlib(display);
-
-            // Because of the visual negotiation we had to take some extra steps to create this context
-            let context = unsafe { platform::GlContext::create(&handle, fb_config, &display) }
-                .expect("Could not create OpenGL context");
-            GlContext::new(context)
+        let gl_context = fb_config.and_then(|fb_config| {
+            let handle = PlatformWindowHandle::Xlib { window: window_id as c_ulong, visual_id: 0 };
+            let display = PlatformDisplayHandle::Xlib {
+                display: xcb_connection.conn.get_raw_dpy() as *mut c_void,
+                screen: xcb_connection.xlib_display,
+            };
+
+            // Because of the visual negotiation we had to take some extra steps to create this
+            // context. If this fails, the window keeps the visual that was picked for OpenGL.
+            match unsafe { platform::GlContext::create(handle, fb_config, display) } {
+                Ok(context) => Some(GlContext::new(context)),
+                Err(err) if fallback_to_no_gl => {
+                    gl_context_error = Some(err);
+                    None
+                }
+                Err(err) => panic!("Could not create OpenGL context: {}", err),
+            }
         });
 
         let mut window = Self {
-            xcb_connection: Some(xcb_connection),
+            id: parent_handle.as_ref().map_or_else(WindowId::next, |handle| handle.window_id),
+            xcb_connection,
             window_id,
             window_info,
+            scale_policy: options.scale,
+            scale_override: None,
+            scale_changed: false,
+            system_scale_range: options.system_scale_range.clone(),
+            resizable: options.resizable,
             mouse_cursor: MouseCursor::default(),
-
-            frame_interval: Duration::from_millis(15),
+            cursor_regions: CursorRegions::default(),
+            shown_cursor: MouseCursor::default(),
+            custom_cursors: HashMap::new(),
+            cursor_confined: false,
+            mapped: false,
+            focused: false,
+            minimized: false,
+
+            events,
+            next_message: None,
+
+            frame_pacer: FramePacer::new(
+                Instant::now(),
+                Duration::from_millis(15),
+                options.skip_late_frames,
+            ),
+            frame_source: options.frame_source,
+            frame_triggered: false,
             event_loop_running: false,
             close_requested: false,
-
+            lifecycle: Lifecycle::Created,
+            animating: true,
+            // Always draw the first frame, even if the handler disables animation right away
+            frame_pending: true,
+            frames_paused: false,
+            damage: Damage::default(),
+
+            dnd_enabled,
             drag_handler: Arc::new(RwLock::new(DragHandler::default())),
             drop_handler,
+            drag_event_order: DragEventOrder::Idle,
+            pen_devices,
+            last_cursor_position: None,
 
             new_physical_size: None,
+            parented: parent.is_some(),
+            nested,
+            popup: popup_focusable.is_some(),
+            focus_on_click: parent.is_some() || popup_focusable == Some(true),
+            focus_in_child: false,
             parent_handle,
+            held_inputs: HeldInputs::default(),
+            event_clock: EventClock::new(),
+            user_data: UserData::default(),
+            x11_error_handler: options.x11_error_handler.take(),
+            window_destroyed: false,
+            depth,
 
             #[cfg(feature = "opengl")]
             gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+            #[cfg(feature = "accesskit")]
+            accesskit: options.accesskit.then(|| {
+                let send = move |event| {
+                    xcb_connection.dispatcher.send(window_id, WindowMessage::AccessKit(event))
+                };
+
+                accesskit_unix::Adapter::new(
+                    AccessKitHandler(send),
+                    AccessKitHandler(send),
+                    AccessKitHandler(send),
+                )
+            }),
         };
+        #[cfg(feature = "accesskit")]
+        window.update_accesskit_bounds();
 
-        let mut handler = build(&mut crate::Window::new(&mut window));
+        #[cfg(feature = "opengl")]
+        let gl_guard = window.gl_context.as_ref().map(|gl_context| unsafe {
+            gl_context.make_current_for_build(window_info.physical_size())
+        });
+        let handler = panic_policy.catch(|| build(&mut crate::EventContext::new(&mut window)));
+        #[cfg(feature = "opengl")]
+        drop(gl_guard);
+        let Some(handler) = handler else {
+            // There's no handler to send `WillClose` to yet. The window is destroyed and the
+            // parent's handle is notified when `window` gets dropped.
+            if let Some(tx) = tx {
+                let _ = tx.send(Err(Error::WindowCreationFailed(
+                    "the window handler panicked while it was being built".into(),
+                )));
+            }
+            return;
+        };
+        let mut handler =
+            crate::lifecycle::check_lifecycle(crate::trace::instrument_handler(handler));
+
+        let initialized = panic_policy
+            .catch(|| {
+                // Send an initial window resized event so the user is alerted of
+                // the correct dpi scaling.
+                window.lifecycle = Lifecycle::Ready;
+                handler.on_event(
+                    &mut crate::EventContext::new(&mut window),
+                    Event::Window(WindowEvent::Resized { new: window_info, old: window_info }),
+                );
 
-        // Send an initial window resized event so the user is alerted of
-        // the correct dpi scaling.
-        handler.on_event(
-            &mut crate::Window::new(&mut window),
-            Event::Window(WindowEvent::Resized(window_info)),
-        );
+                // Hosts often open editors right under the mouse cursor. The handler should know
+                // about this before the first frame gets drawn instead of when the mouse is moved
+                // for the first time.
+                window.send_initial_cursor_position(&mut handler);
+            })
+            .is_some();
 
-        let _ = tx.send(Ok(SendableRwh(window.raw_window_handle())));
+        if let Some(tx) = tx {
+            let _ = tx.send(Ok(window.platform_window_handle()));
+        }
 
-        window.run_event_loop(&mut handler);
+        if !initialized || panic_policy.catch(|| window.run_event_loop(&mut handler)).is_none() {
+            // The handler still gets to clean up after itself. The window itself is destroyed and
+            // the parent's handle is notified when `window` gets dropped at the end of this
+            // function.
+            panic_policy.catch(|| window.handle_must_close(&mut handler));
+        }
     }
 
     pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
-        if self.mouse_cursor == mouse_cursor {
+        self.mouse_cursor = mouse_cursor;
+        self.update_cursor();
+    }
+
+    pub fn set_cursor_regions(&mut self, regions: Vec<(Rect, MouseCursor)>) {
+        self.cursor_regions.set(regions);
+        self.update_cursor();
+    }
+
+    /// Show the cursor for the pointer's last known position. The window's cursor is only shown
+    /// while the pointer is over the window, so the position being outdated after the pointer left
+    /// doesn't matter.
+    fn update_cursor(&mut self) {
+        let position = self.last_cursor_position.map(|pos| pos.to_logical(&self.window_info));
+        let mouse_cursor = self.cursor_regions.resolve(self.mouse_cursor, position);
+        if self.shown_cursor == mouse_cursor {
             return;
         }
 
-        let xid = self.xcb_connection.as_mut().unwrap().get_cursor_xid(mouse_cursor);
+        // Setting the cursor to `None` makes the window inherit its parent's cursor
+        let xid = match mouse_cursor {
+            MouseCursor::Default => xcb::NONE,
+            cursor => {
+                let xid = match cursor {
+                    MouseCursor::Custom(id) => self.custom_cursors.get(&id).copied().unwrap_or(0),
+                    cursor => self.conn().get_cursor_xid(cursor),
+                };
+
+                // Unknown cursors and cursors that couldn't be loaded are ignored
+                if xid == 0 {
+                    return;
+                }
 
-        if xid != 0 {
-            xcb::change_window_attributes(
-                &self.conn().conn,
-                self.window_id,
-                &[(xcb::CW_CURSOR, xid)],
-            );
+                xid
+            }
+        };
 
-            self.conn().conn.flush();
+        // Unchecked since this is called from the handler. Errors are reported by the event loop.
+        // The server shows the new cursor right away if the pointer is already over the window, so
+        // unlike on the other platforms nothing needs to be refreshed here.
+        xcb::change_window_attributes(&self.conn().conn, self.window_id, &[(xcb::CW_CURSOR, xid)]);
+
+        self.shown_cursor = mouse_cursor;
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        if !confined {
+            self.release_cursor();
+            return;
+        }
+        if self.cursor_confined {
+            return;
         }
 
-        self.mouse_cursor = mouse_cursor;
+        // The grab confines the pointer to the window, and with `owner_events` the window keeps
+        // receiving its pointer events as usual
+        let reply = xcb::grab_pointer(
+            &self.conn().conn,
+            true,
+            self.window_id,
+            CONFINED_EVENT_MASK as u16,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+            self.window_id,
+            xcb::NONE,
+            xcb::CURRENT_TIME,
+        )
+        .get_reply();
+        match reply {
+            Ok(reply) if reply.status() == xcb::GRAB_STATUS_SUCCESS as u8 => {
+                self.cursor_confined = true;
+            }
+            // For instance when another client already grabbed the pointer, or when the window
+            // isn't mapped
+            Ok(_reply) => {
+                warn!(
+                    target: "baseview::platform",
+                    status = _reply.status(),
+                    "could not grab the pointer to confine the cursor"
+                );
+            }
+            Err(_err) => {
+                warn!(
+                    target: "baseview::platform",
+                    error_code = _err.error_code(),
+                    "could not grab the pointer to confine the cursor"
+                );
+            }
+        }
     }
 
-    pub fn close(&mut self) {
-        self.close_requested = true;
+    /// Release the pointer grab from [`Self::set_cursor_confined()`], if there is one.
+    fn release_cursor(&mut self) {
+        if std::mem::take(&mut self.cursor_confined) {
+            xcb::ungrab_pointer(&self.conn().conn, xcb::CURRENT_TIME);
+        }
     }
 
-    pub fn resize(&mut self, size: Size) {
-        let scaling = self.window_info.scale();
-        let new_window_info = WindowInfo::from_logical_size(size, scaling);
+    pub fn register_cursor(&mut self, icon: CursorIcon) -> CursorId {
+        let id = CursorId::next();
+        let xid = self.conn().create_image_cursor(&icon);
+        self.custom_cursors.insert(id, xid);
 
-        xcb::configure_window(
-            &self.conn().conn,
-            self.window_id,
-            &[
-                (xcb::CONFIG_WINDOW_WIDTH as u16, new_window_info.physical_size().width),
-                (xcb::CONFIG_WINDOW_HEIGHT as u16, new_window_info.physical_size().height),
-            ],
-        );
-        self.conn().conn.flush();
+        id
+    }
 
-        // This will trigger a `ConfigureNotify` event which will in turn change `self.window_info`
-        // and notify the window handler about it
+    pub fn close(&mut self) {
+        self.close_requested = true;
     }
 
-    pub fn set_position(&mut self, position: Point) {
-        let window_info = self.window_info;
-        let physical_pos = position.to_physical(&window_info);
+    pub fn cancel_close(&mut self) {
+        self.close_requested = false;
+    }
 
-        xcb::configure_window(
+    pub fn set_opacity(&mut self, opacity: f64) -> Result<(), Error> {
+        // Compositors only look at this property on top-level windows
+        let opacity_atom = self.conn().atoms.net_wm_window_opacity;
+        if self.parented || opacity_atom == xcb::ATOM_NONE {
+            return Err(Error::Unsupported);
+        }
+
+        let opacity = (opacity * f64::from(u32::MAX)).round() as u32;
+        xcb::change_property(
             &self.conn().conn,
+            xcb::PROP_MODE_REPLACE as u8,
             self.window_id,
-            &[
-                (xcb::CONFIG_WINDOW_X as u16, physical_pos.x as u32),
-                (xcb::CONFIG_WINDOW_Y as u16, physical_pos.y as u32),
-            ],
+            opacity_atom,
+            xcb::ATOM_CARDINAL,
+            32,
+            &[opacity],
         );
-        self.conn().conn.flush();
+
+        Ok(())
     }
 
-    #[cfg(feature = "opengl")]
-    pub fn gl_context(&self) -> Option<&crate::gl::GlContext> {
-        self.gl_context.as_ref()
+    pub fn open_file_dialog(&mut self, _options: FileDialogOptions) -> Result<DialogId, Error> {
+        // X11 has no file dialogs of its own
+        Err(Error::Unsupported)
     }
 
-    pub fn start_drag(&self, data: Data) {
-        self.drag_handler.write().unwrap().activate(data);
-        self.drag_handler.read().unwrap().start(&self.conn(), self.window_id);
+    pub fn request_attention(&mut self, attention: AttentionType) {
+        self.set_demands_attention(true);
+        // Window managers usually only highlight the window, so critical requests also ring the
+        // bell
+        if attention == AttentionType::Critical {
+            xcb::bell(&self.conn().conn, 0);
+        }
     }
 
-    fn is_dragging(&self) -> bool {
-        self.drag_handler.read().unwrap().is_active()
+    pub fn stop_attention(&mut self) {
+        self.set_demands_attention(false);
     }
 
-    fn drop_target_valid(&self) -> bool {
-        if let Some(f) = &self.drop_handler.drop_target_valid {
-            (f)()
-        } else {
-            true
+    /// Ask the window manager to add or remove `_NET_WM_STATE_DEMANDS_ATTENTION` for the top-level
+    /// window this window belongs to.
+    fn set_demands_attention(&self, demands_attention: bool) {
+        let atoms = &self.conn().atoms;
+        if atoms.net_wm_state == xcb::ATOM_NONE
+            || atoms.net_wm_state_demands_attention == xcb::ATOM_NONE
+        {
+            return;
         }
+
+        let setup = self.conn().conn.get_setup();
+        let root = setup.roots().nth(self.conn().xlib_display as usize).unwrap().root();
+
+        // The action is either `_NET_WM_STATE_REMOVE` (0) or `_NET_WM_STATE_ADD` (1), and the
+        // fourth item indicates that the request comes from a normal application
+        let data = [demands_attention as u32, atoms.net_wm_state_demands_attention, 0, 1, 0];
+        let event = xcb::ClientMessageEvent::new(
+            32,
+            self.top_level_window(),
+            atoms.net_wm_state,
+            xcb::ClientMessageData::from_data32(data),
+        );
+        xcb::send_event(
+            &self.conn().conn,
+            false,
+            root,
+            xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT | xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+            &event,
+        );
     }
 
-    fn find_visual_for_depth(screen: &StructPtr<xcb_screen_t>, depth: u8) -> Option<u32> {
-        for candidate_depth in screen.allowed_depths() {
-            if candidate_depth.depth() != depth {
-                continue;
+    /// The window the window manager manages for this window. Parented windows don't show up in
+    /// the taskbar themselves, so this is the outermost ancestor with a `WM_STATE` property, which
+    /// the window manager sets on the host's top-level window. The window manager's own frame
+    /// windows don't have that property.
+    fn top_level_window(&self) -> u32 {
+        if !self.parented || self.conn().atoms.wm_state == xcb::ATOM_NONE {
+            return self.window_id;
+        }
+
+        let conn = &self.conn().conn;
+        let mut window = self.window_id;
+        let mut top_level = self.window_id;
+        while let Ok(tree) = xcb::query_tree(conn, window).get_reply() {
+            if tree.parent() == tree.root() || tree.parent() == xcb::NONE {
+                break;
             }
 
-            for candidate_visual in candidate_depth.visuals() {
-                if candidate_visual.class() == xcb::VISUAL_CLASS_TRUE_COLOR as u8 {
-                    return Some(candidate_visual.visual_id());
-                }
+            window = tree.parent();
+            let wm_state = xcb::get_property(
+                conn,
+                false,
+                window,
+                self.conn().atoms.wm_state,
+                xcb::ATOM_ANY,
+                0,
+                0,
+            )
+            .get_reply();
+            if matches!(wm_state, Ok(reply) if reply.type_() != xcb::NONE) {
+                top_level = window;
             }
         }
 
-        None
+        top_level
     }
 
-    #[inline]
-    fn drain_xcb_events(&mut self, handler: &mut dyn WindowHandler) {
-        // the X server has a tendency to send spurious/extraneous configure notify events when a
-        // window is resized, and we need to batch those together and just send one resize event
-        // when they've all been coalesced.
-        self.new_physical_size = None;
+    pub fn is_visible(&self) -> bool {
+        self.mapped
+    }
 
-        while let Some(event) = self.conn().conn.poll_for_event() {
-            if self.is_dragging() {
-                if !self.handle_dragging_event(&event) {
-                    self.handle_xcb_event(handler, event);
-                }
-            } else {
-                self.handle_xcb_event(handler, event);
-            }
+    pub fn is_minimized(&self) -> bool {
+        if self.parented {
+            // The host doesn't tell embedded windows about its own window's state
+            self.has_hidden_state(self.top_level_window())
+        } else {
+            self.minimized
         }
+    }
 
-        if let Some(size) = self.new_physical_size.take() {
-            self.window_info = WindowInfo::from_physical_size(size, self.window_info.scale());
+    pub fn has_focus(&self) -> bool {
+        self.focused
+    }
 
-            let window_info = self.window_info;
+    pub fn is_resizable(&self) -> bool {
+        self.resizable && !self.parented && !self.popup
+    }
 
-            handler.on_event(
-                &mut crate::Window::new(self),
-                Event::Window(WindowEvent::Resized(window_info)),
-            );
+    /// Whether `window`'s `_NET_WM_STATE` property contains `_NET_WM_STATE_HIDDEN`, which window
+    /// managers set while the window is minimized.
+    fn has_hidden_state(&self, window: u32) -> bool {
+        let atoms = &self.conn().atoms;
+        if atoms.net_wm_state == xcb::ATOM_NONE || atoms.net_wm_state_hidden == xcb::ATOM_NONE {
+            return false;
+        }
+
+        let reply = xcb::get_property(
+            &self.conn().conn,
+            false,
+            window,
+            atoms.net_wm_state,
+            xcb::ATOM_ATOM,
+            0,
+            u32::MAX,
+        )
+        .get_reply();
+        match reply {
+            Ok(reply) => reply.value::<u32>().contains(&atoms.net_wm_state_hidden),
+            Err(_) => false,
         }
     }
 
-    // Event loop
-    // FIXME: poll() acts fine on linux, sometimes funky on *BSD. XCB upstream uses a define to
-    // switch between poll() and select() (the latter of which is fine on *BSD), and we should do
-    // the same.
-    fn run_event_loop(&mut self, handler: &mut dyn WindowHandler) {
-        use nix::poll::*;
+    pub fn set_animating(&mut self, animating: bool) {
+        self.animating = animating;
+    }
+
+    #[cfg(feature = "accesskit")]
+    pub fn update_accesskit_tree(&mut self, update: impl FnOnce() -> accesskit::TreeUpdate) {
+        if let Some(adapter) = &mut self.accesskit {
+            adapter.update_if_active(update);
+        }
+    }
 
-        let xcb_fd = unsafe {
-            let raw_conn = self.conn().conn.get_raw_conn();
-            xcb::ffi::xcb_get_file_descriptor(raw_conn)
+    /// AT-SPI places the tree's nodes on the screen, so the adapter needs to know where the window
+    /// is. Embedded windows aren't told when the host's window moves, so this is only updated when
+    /// the window itself is moved or resized.
+    #[cfg(feature = "accesskit")]
+    fn update_accesskit_bounds(&mut self) {
+        let Some(adapter) = &mut self.accesskit else { return };
+
+        let conn = self.xcb_connection;
+        let root = conn.conn.get_setup().roots().nth(conn.xlib_display as usize).unwrap().root();
+        let Ok(position) =
+            xcb::translate_coordinates(&conn.conn, self.window_id, root, 0, 0).get_reply()
+        else {
+            return;
         };
 
-        let mut last_frame = Instant::now();
-        self.event_loop_running = true;
+        let x = position.dst_x() as f64;
+        let y = position.dst_y() as f64;
+        let size = self.window_info.physical_size();
+        let bounds = accesskit::Rect::new(x, y, x + size.width as f64, y + size.height as f64);
+        adapter.set_root_window_bounds(bounds, bounds);
+    }
 
-        while self.event_loop_running {
-            // We'll try to keep a consistent frame pace. If the last frame couldn't be processed in
-            // the expected frame time, this will throttle down to prevent multiple frames from
-            // being queued up. The conditional here is needed because event handling and frame
-            // drawing is interleaved. The `poll()` function below will wait until the next frame
-            // can be drawn, or until the window receives an event. We thus need to manually check
-            // if it's already time to draw a new frame.
-            let next_frame = last_frame + self.frame_interval;
-            if Instant::now() >= next_frame {
-                handler.on_frame(&mut crate::Window::new(self));
-                last_frame = Instant::max(next_frame, Instant::now() - self.frame_interval);
-            }
+    pub fn invalidate_rect(&mut self, rect: Rect) {
+        self.damage.add(rect);
+    }
 
-            let mut fds = [PollFd::new(xcb_fd, PollFlags::POLLIN)];
+    pub fn damage(&self) -> Region {
+        self.damage.current().clone()
+    }
 
-            // Check for any events in the internal buffers
-            // before going to sleep:
-            self.drain_xcb_events(handler);
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.frame_pacer.set_interval(interval);
+    }
 
-            // FIXME: handle errors
-            poll(&mut fds, next_frame.duration_since(Instant::now()).subsec_millis() as i32)
-                .unwrap();
+    pub fn pause_frames(&mut self, paused: bool) {
+        self.frames_paused = paused;
+    }
 
-            if let Some(revents) = fds[0].revents() {
-                if revents.contains(PollFlags::POLLERR) {
-                    panic!("xcb connection poll error");
-                }
+    /// Whether the event loop should draw frames at all right now.
+    fn wants_frames(&self) -> bool {
+        self.lifecycle.can_draw()
+            && !self.window_destroyed
+            && !self.frames_paused
+            && (self.animating || self.frame_pending || self.damage.has_pending())
+    }
 
-                if revents.contains(PollFlags::POLLIN) {
-                    self.drain_xcb_events(handler);
-                }
-            }
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.scale_policy = policy;
+        self.scale_changed = true;
+    }
 
-            // Check if the parents's handle was dropped (such as when the host
-            // requested the window to close)
-            //
-            // FIXME: This will need to be changed from just setting an atomic to somehow
-            // synchronizing with the window being closed (using a synchronous channel, or
-            // by joining on the event loop thread).
-            if let Some(parent_handle) = &self.parent_handle {
-                if parent_handle.parent_did_drop() {
-                    self.handle_must_close(handler);
-                    self.close_requested = false;
-                }
-            }
+    pub fn set_scale_override(&mut self, scale: Option<f64>) {
+        self.scale_override = scale;
+        self.scale_changed = true;
+    }
 
-            // Check if the user has requested the window to close
-            if self.close_requested {
-                self.handle_must_close(handler);
-                self.close_requested = false;
-            }
+    /// The scale factors the window should use according to its scale policy and override.
+    fn scale_factors(&self) -> (f64, f64) {
+        match self.scale_policy.with_override(self.scale_override) {
+            WindowScalePolicy::ScaleFactor(scale) => (scale, scale),
+            policy => system_scale(self.conn(), policy, &self.system_scale_range),
         }
     }
 
-    fn handle_close_requested(&mut self, handler: &mut dyn WindowHandler) {
-        handler.on_event(&mut crate::Window::new(self), Event::Window(WindowEvent::WillClose));
+    pub fn resize(&mut self, size: Size) {
+        let new_window_info = self.window_info.with_logical_size(size);
 
-        // FIXME: handler should decide whether window stays open or not
-        self.event_loop_running = false;
+        self.request_size(new_window_info.physical_size());
+
+        // This will trigger a `ConfigureNotify` event which will in turn change `self.window_info`
+        // and notify the window handler about it
     }
 
-    fn handle_must_close(&mut self, handler: &mut dyn WindowHandler) {
-        handler.on_event(&mut crate::Window::new(self), Event::Window(WindowEvent::WillClose));
+    /// Ask the X server to resize the window. Window managers ignore requests that don't match
+    /// the window's size hints, so the hints of a window the user can't resize are moved to the
+    /// new size first.
+    fn request_size(&self, size: PhySize) {
+        if !self.resizable {
+            set_fixed_size_hints(self.conn(), self.window_id, size);
+        }
 
-        self.event_loop_running = false;
+        // Unchecked since this is called from the handler. Errors are reported by the event loop,
+        // and the resize itself is confirmed through a `ConfigureNotify` event.
+        xcb::configure_window(
+            &self.conn().conn,
+            self.window_id,
+            &[
+                (xcb::CONFIG_WINDOW_WIDTH as u16, size.width),
+                (xcb::CONFIG_WINDOW_HEIGHT as u16, size.height),
+            ],
+        );
     }
 
-    // Return whether we have actual handled anything. If not, we'll handle it as a normal event
-    fn handle_dragging_event(&mut self, event: &xcb::GenericEvent) -> bool {
-        let event_type = event.response_type() & !0x80;
+    pub fn set_position(&mut self, position: Point) {
+        let window_info = self.window_info;
+        let physical_pos = position.to_physical(&window_info);
+
+        // Unchecked for the same reason as in `resize()`
+        xcb::configure_window(
+            &self.conn().conn,
+            self.window_id,
+            &[
+                (xcb::CONFIG_WINDOW_X as u16, physical_pos.x as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, physical_pos.y as u32),
+            ],
+        );
+    }
+
+    pub(crate) fn platform_window_handle(&self) -> PlatformWindowHandle {
+        let setup = self.conn().conn.get_setup();
+        let screen = setup.roots().nth(self.conn().xlib_display as usize).unwrap();
+
+        PlatformWindowHandle::Xlib {
+            window: self.window_id as c_ulong,
+            visual_id: screen.root_visual() as c_ulong,
+        }
+    }
+
+    pub(crate) fn platform_display_handle(&self) -> PlatformDisplayHandle {
+        PlatformDisplayHandle::Xlib {
+            display: self.conn().conn.get_raw_dpy() as *mut c_void,
+            screen: self.conn().xlib_display,
+        }
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        self.held_inputs.pressed_keys()
+    }
+
+    pub(crate) fn event_clock(&self) -> &EventClock {
+        &self.event_clock
+    }
+
+    /// See [`crate::EventContext::set_user_data()`].
+    pub(crate) fn user_data(&self) -> &UserData {
+        &self.user_data
+    }
+
+    pub fn with_x11_connection<R>(&mut self, f: impl FnOnce(&X11Connection, u32) -> R) -> R {
+        f(&X11Connection::new(self.conn()), self.window_id)
+    }
+
+    pub fn set_x11_property(&mut self, name: &str, value: X11PropertyValue) {
+        let properties = [(name.to_string(), value)];
+        if let Err(_err) = properties::set_properties(self.conn(), self.window_id, &properties) {
+            warn!(
+                target: "baseview::platform",
+                name,
+                error_code = _err.error_code(),
+                "could not set the X11 property"
+            );
+        }
+    }
+
+    pub fn surface_info(&self) -> SurfaceInfo {
+        SurfaceInfo {
+            color_depth: self.depth,
+            // 32-bit visuals are always ARGB visuals
+            has_alpha: self.depth == 32,
+            #[cfg(feature = "opengl")]
+            gl: self.gl_context.as_ref().map(|context| context.pixel_format()),
+        }
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn gl_context(&self) -> Option<&crate::gl::GlContext> {
+        self.gl_context.as_ref()
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn gl_context_error(&self) -> Option<&GlError> {
+        self.gl_context_error.as_ref()
+    }
+
+    pub fn start_drag(&self, data: Data, effect: DropEffect, image: Option<DragImage>) {
+        if !self.dnd_enabled {
+            return;
+        }
+
+        // TODO: Use the XDS protocol for virtual files instead of writing them to disk
+        let data = match data.with_virtual_file_on_disk() {
+            Ok(data) => data,
+            Err(_err) => {
+                debug!(target: "baseview::dnd", error = %_err, "could not write virtual file");
+                return;
+            }
+        };
+
+        let mut drag_handler = self.drag_handler.write().unwrap();
+        drag_handler.activate(data, effect);
+        drag_handler.start(self.conn(), self.window_id, image.as_ref());
+    }
+
+    pub fn drag_and_drop_available(&self) -> bool {
+        self.dnd_enabled
+    }
+
+    /// Send a `CursorEntered` followed by a `CursorMoved` event if the mouse cursor is already
+    /// inside of the window.
+    fn send_initial_cursor_position(&mut self, handler: &mut dyn WindowHandler) {
+        let reply = match xcb::query_pointer(&self.conn().conn, self.window_id).get_reply() {
+            Ok(reply) => reply,
+            Err(_) => return,
+        };
+
+        let physical_pos = PhyPoint::new(reply.win_x().into(), reply.win_y().into());
+        let bounds =
+            PhyRect::from_origin_size(PhyPoint::default(), self.window_info.physical_size());
+        if !reply.same_screen() || !bounds.contains(physical_pos) {
+            return;
+        }
+
+        let logical_pos = physical_pos.to_logical(&self.window_info);
+        let buttons = self.held_mouse_buttons(reply.mask());
+        self.last_cursor_position = Some(physical_pos);
+        self.update_cursor();
+        handler
+            .on_event(&mut crate::EventContext::new(self), Event::Mouse(MouseEvent::CursorEntered));
+        handler.on_event(
+            &mut crate::EventContext::new(self),
+            Event::Mouse(MouseEvent::CursorMoved {
+                position: logical_pos,
+                delta: PhyPoint::default(),
+                modifiers: key_mods(reply.mask()),
+                buttons,
+            }),
+        );
+    }
+
+    fn is_dragging(&self) -> bool {
+        self.drag_handler.read().unwrap().is_active()
+    }
+
+    fn drop_target_valid(&self) -> bool {
+        if let Some(f) = &self.drop_handler.drop_target_valid {
+            (f)()
+        } else {
+            true
+        }
+    }
+
+    pub fn set_drop_effect(&mut self, effect: Option<DropEffect>) {
+        self.drop_handler.effect.chosen = Some(effect);
+    }
+
+    pub fn drop_effect(&self) -> Option<DropEffect> {
+        if self.drop_target_valid() {
+            self.drop_handler.effect.effect()
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn find_visual_for_depth(
+        screen: &StructPtr<xcb_screen_t>, depth: u8,
+    ) -> Option<u32> {
+        for candidate_depth in screen.allowed_depths() {
+            if candidate_depth.depth() != depth {
+                continue;
+            }
+
+            for candidate_visual in candidate_depth.visuals() {
+                if candidate_visual.class() == xcb::VISUAL_CLASS_TRUE_COLOR as u8 {
+                    return Some(candidate_visual.visual_id());
+                }
+            }
+        }
+
+        None
+    }
+
+    #[inline]
+    fn drain_xcb_events(&mut self, handler: &mut dyn WindowHandler) {
+        // the X server has a tendency to send spurious/extraneous configure notify events when a
+        // window is resized, and we need to batch those together and just send one resize event
+        // when they've all been coalesced.
+        self.new_physical_size = None;
+
+        // Whatever is still queued after `WillClose` was sent is of no interest to the handler. The
+        // handle is checked before every event, so nothing is sent after it was closed.
+        loop {
+            self.check_handle_closed();
+            if !self.lifecycle.can_send_events() {
+                break;
+            }
+
+            let Some(message) = self.next_message.take().or_else(|| self.events.try_recv().ok())
+            else {
+                break;
+            };
+
+            // Any event we receive may change what the handler wants to draw
+            if !matches!(message, WindowMessage::Frame) {
+                self.frame_pending = true;
+            }
+
+            match message {
+                WindowMessage::Event(XcbEvent(event)) => {
+                    trace!(
+                        target: "baseview::platform",
+                        event = xcb_event_name(event.response_type())
+                    );
+
+                    let native_time =
+                        xcb_event_time(&event).map(|time| self.event_clock.extend_millis(time));
+                    let outer_event_time = self.event_clock.begin_event(native_time);
+                    if self.is_dragging() {
+                        let handled = self.handle_dragging_event(&event);
+                        self.send_drag_source_moved(handler);
+                        self.send_drag_source_ended(handler);
+                        if !handled {
+                            self.handle_xcb_event(handler, event);
+                        }
+                    } else {
+                        self.handle_xcb_event(handler, event);
+                    }
+                    self.event_clock.end_event(outer_event_time);
+                }
+                WindowMessage::Error(error) => self.handle_x11_error(handler, error),
+                WindowMessage::ScreenChanged => self.handle_screen_changed(handler),
+                WindowMessage::Frame => {
+                    self.frame_triggered = self.frame_source == FrameSource::External;
+                }
+                #[cfg(feature = "accesskit")]
+                WindowMessage::AccessKit(event) => {
+                    handler.on_event(&mut crate::EventContext::new(self), Event::AccessKit(event));
+                }
+            }
+        }
+
+        if self.lifecycle == Lifecycle::ClosePending {
+            self.discard_queued_messages();
+            self.handle_must_close(handler);
+        }
+        if !self.lifecycle.can_send_events() {
+            return;
+        }
+
+        // The drag can also end while starting it, if the pointer couldn't be grabbed
+        self.send_drag_source_ended(handler);
+
+        if let Some(size) = self.new_physical_size.take() {
+            let old_window_info = self.window_info;
+            self.window_info = self.window_info.with_physical_size(size);
+
+            let window_info = self.window_info;
+
+            handler.on_event(
+                &mut crate::EventContext::new(self),
+                Event::Window(WindowEvent::Resized { new: window_info, old: old_window_info }),
+            );
+        }
+
+        if std::mem::take(&mut self.scale_changed) {
+            self.handle_scale_changed(handler);
+        }
+    }
+
+    // Event loop
+    // The handler is only ever reachable through the `&mut` reference passed down from here, so
+    // the borrow checker already guarantees that it cannot be re-entered. Keep it that way instead
+    // of storing the handler somewhere the `Window` can reach it.
+    fn run_event_loop(&mut self, handler: &mut dyn WindowHandler) {
+        self.event_loop_running = true;
+
+        while self.event_loop_running {
+            #[cfg(feature = "tracing")]
+            let request_count = RequestCount::begin(self.xcb_connection);
+
+            // Handle everything that arrived while sleeping first, so the next frame can respond to
+            // it
+            self.drain_xcb_events(handler);
+
+            // When the window isn't animating we'll only draw a single frame after receiving events
+            // so the UI can respond to them. No frames are drawn at all while they're paused.
+            let frame_start = Instant::now();
+            let frame_due = match self.frame_source {
+                FrameSource::Internal => self.frame_pacer.is_frame_due(frame_start),
+                FrameSource::External => std::mem::take(&mut self.frame_triggered),
+            };
+            if self.wants_frames() && frame_due {
+                // Handling events or drawing the last frame took longer than a full frame
+                #[cfg(feature = "tracing")]
+                if self.frame_source == FrameSource::Internal {
+                    let late_by = frame_start - self.frame_pacer.next_frame();
+                    if self.animating && late_by >= self.frame_pacer.interval() {
+                        debug!(target: "baseview::frame", ?late_by, "late frame");
+                    }
+                }
+
+                self.frame_pending = false;
+                self.damage.begin_frame(self.window_info.logical_size());
+                handler.on_frame(&mut crate::EventContext::new(self));
+                self.frame_pacer.frame_finished(frame_start, Instant::now());
+
+                // Input that arrived during a slow frame is handled right away instead of only
+                // after the sleep below
+                self.drain_xcb_events(handler);
+            }
+
+            // Requests made while handling the events and drawing the frame are only sent here,
+            // instead of flushing the connection after every single one of them
+            self.conn().conn.flush();
+            #[cfg(feature = "tracing")]
+            if let Some(requests) = request_count.requests() {
+                trace!(target: "baseview::platform", requests, "sent requests");
+            }
+
+            // When idle we can sleep until the next event arrives. Parented windows still need to
+            // wake up every now and then to check whether the parent's handle has been dropped.
+            // The timeout is based on the current time, so the time spent drawing the frame and
+            // handling events is already taken into account.
+            let timeout =
+                if !self.event_loop_running || self.close_requested || self.frame_triggered {
+                    0
+                } else if self.frame_source == FrameSource::Internal && self.wants_frames() {
+                    self.frame_pacer.poll_timeout_ms(Instant::now())
+                } else if self.parent_handle.is_some() {
+                    IDLE_PARENT_POLL_INTERVAL.as_millis() as i32
+                } else {
+                    -1
+                };
+
+            // The events themselves are handled at the start of the next iteration. The channel
+            // only disconnects when the dispatcher lost the connection to the X server.
+            let message = if timeout < 0 {
+                self.events.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                self.events.recv_timeout(Duration::from_millis(timeout as u64))
+            };
+            match message {
+                Ok(message) => self.next_message = Some(message),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => panic!("xcb connection error"),
+            }
+
+            // Check if the parents's handle was dropped (such as when the host requested the
+            // window to close) before handling the message that woke the loop up
+            //
+            // FIXME: This will need to be changed from just setting an atomic to somehow
+            // synchronizing with the window being closed (using a synchronous channel, or
+            // by joining on the event loop thread).
+            self.check_handle_closed();
+            if self.lifecycle == Lifecycle::ClosePending {
+                self.discard_queued_messages();
+                self.handle_must_close(handler);
+                self.close_requested = false;
+            }
+
+            // Check if the user has requested the window to close
+            if self.close_requested {
+                self.handle_must_close(handler);
+                self.close_requested = false;
+            }
+        }
+    }
+
+    /// Give the keyboard focus to this window. `time` is the timestamp of the event that caused
+    /// the focus change, which lets the X server ignore this request if the focus has changed again
+    /// since then.
+    ///
+    /// Windows use the ICCCM's Locally Active input model when they're standalone, and the Globally
+    /// Active model when they're embedded in another window:
+    ///
+    /// - Standalone windows set the `input` hint, so the window manager gives them the focus
+    ///   directly. They also support `WM_TAKE_FOCUS`, which window managers may send instead.
+    /// - Embedded windows clear the `input` hint so the host's window manager doesn't try to
+    ///   focus them, and take the focus themselves when they're clicked or when the host sends
+    ///   them a `WM_TAKE_FOCUS` message. The focus reverts to the parent window when the window is
+    ///   closed, so the host gets it back.
+    ///
+    /// The [`WindowEvent::Focused`] and [`WindowEvent::Unfocused`] events are sent once the X
+    /// server reports the focus change, regardless of how it came about.
+    fn take_focus(&self, time: u32) {
+        // Unchecked since this is called while handling events. Errors are reported by the event
+        // loop.
+        xcb::set_input_focus(
+            &self.conn().conn,
+            xcb::INPUT_FOCUS_PARENT as u8,
+            self.window_id,
+            time,
+        );
+    }
+
+    /// Replace the window's event mask. This is unchecked since it's called while handling events.
+    fn select_events(&self, event_mask: u32) {
+        xcb::change_window_attributes(
+            &self.conn().conn,
+            self.window_id,
+            &[(xcb::CW_EVENT_MASK, event_mask)],
+        );
+    }
+
+    /// Stop sending events and frames to the handler once the window's handle has been closed.
+    /// See [`Lifecycle::ClosePending`].
+    fn check_handle_closed(&mut self) {
+        let handle_closed = self
+            .parent_handle
+            .as_ref()
+            .is_some_and(|parent_handle| parent_handle.parent_did_drop());
+        if handle_closed && self.lifecycle.handle_closed() {
+            debug!(target: "baseview::lifecycle", "window handle closed");
+        }
+    }
+
+    /// Throw away the messages that are still queued when the window is about to close without
+    /// handling them. Only the window's destruction is still noted, so the OpenGL context isn't
+    /// made current on a window that no longer exists.
+    fn discard_queued_messages(&mut self) {
+        let messages = self.next_message.take().into_iter().chain(self.events.try_iter());
+        let destroyed = discard_messages(messages, self.window_id);
+        self.window_destroyed |= destroyed;
+    }
+
+    /// Send the handler its final `WillClose` event. The OpenGL context is made current first so the
+    /// handler can free its GPU resources. The context is only destroyed together with the window,
+    /// after the event loop has exited.
+    fn send_will_close(&mut self, handler: &mut dyn WindowHandler) {
+        // The parent's handle can be dropped in the same iteration of the event loop as the window
+        // was closed, and a panicking handler is closed again after the event loop has exited
+        if !self.lifecycle.can_send_will_close() {
+            return;
+        }
+        self.lifecycle = Lifecycle::Closing;
+
+        // A window that was destroyed by its parent can't be made current anymore
+        #[cfg(feature = "opengl")]
+        if let Some(gl_context) = &self.gl_context {
+            if !self.window_destroyed {
+                unsafe { gl_context.make_current() };
+            }
+        }
+
+        handler
+            .on_event(&mut crate::EventContext::new(self), Event::Window(WindowEvent::WillClose));
+        self.lifecycle = Lifecycle::Closed;
+    }
+
+    /// Send an event for the drag that's over the window. Debug builds check that these events
+    /// are sent in order.
+    fn send_drag_event(&mut self, handler: &mut dyn WindowHandler, event: WindowEvent) {
+        self.drag_event_order.track(&event);
+        handler.on_event(&mut crate::EventContext::new(self), Event::Window(event));
+    }
+
+    fn handle_close_requested(&mut self, handler: &mut dyn WindowHandler) {
+        self.send_will_close(handler);
+
+        // FIXME: handler should decide whether window stays open or not
+        self.event_loop_running = false;
+    }
+
+    fn handle_must_close(&mut self, handler: &mut dyn WindowHandler) {
+        self.send_will_close(handler);
+
+        self.event_loop_running = false;
+    }
+
+    fn handle_x11_error(&mut self, handler: &mut dyn WindowHandler, error: X11Error) {
+        debug!(target: "baseview::platform", %error, "X11 error");
+        if let Some(error_handler) = &self.x11_error_handler {
+            error_handler(&error);
+        } else {
+            warn!(target: "baseview::platform", %error, "unhandled X11 error");
+        }
+
+        // There's nothing left to do if the window itself is gone, for instance because the parent
+        // window destroyed it before the `DestroyNotify` event arrived
+        if error.error_code == xcb::WINDOW
+            && error.resource_id == self.window_id
+            && !self.window_destroyed
+        {
+            self.window_destroyed = true;
+            self.handle_must_close(handler);
+        }
+    }
+
+    /// Changing the resolution or connecting a monitor can change the system's scale factor. When
+    /// following the system's scale factor, the window is resized so it keeps its logical size.
+    fn handle_screen_changed(&mut self, handler: &mut dyn WindowHandler) {
+        if !self.scale_policy.with_override(self.scale_override).follows_system() {
+            return;
+        }
+
+        let (scale_x, scale_y) = self.scale_factors();
+        if scale_x == self.window_info.scale_x() && scale_y == self.window_info.scale_y() {
+            return;
+        }
+        debug!(target: "baseview::platform", scale_x, scale_y, "system scale factor changed");
+
+        let old_window_info = self.window_info;
+        self.window_info = WindowInfo::from_logical_size_non_uniform(
+            old_window_info.logical_size(),
+            scale_x,
+            scale_y,
+        );
+        let window_info = self.window_info;
+
+        // The `ConfigureNotify` event for this resize matches the new window info, so it doesn't
+        // cause a second `Resized` event
+        self.request_size(window_info.physical_size());
+
+        handler.on_event(
+            &mut crate::EventContext::new(self),
+            Event::Window(WindowEvent::Resized { new: window_info, old: old_window_info }),
+        );
+    }
+
+    /// Apply a new scale policy or override. Unlike a system scale change the window keeps its
+    /// physical size here, and its logical size changes instead.
+    fn handle_scale_changed(&mut self, handler: &mut dyn WindowHandler) {
+        let (scale_x, scale_y) = self.scale_factors();
+        if scale_x == self.window_info.scale_x() && scale_y == self.window_info.scale_y() {
+            return;
+        }
+        debug!(target: "baseview::platform", scale_x, scale_y, "scale factor changed");
+
+        let old_window_info = self.window_info;
+        self.window_info = WindowInfo::from_physical_size_non_uniform(
+            old_window_info.physical_size(),
+            scale_x,
+            scale_y,
+        );
+        let window_info = self.window_info;
+
+        handler.on_event(
+            &mut crate::EventContext::new(self),
+            Event::Window(WindowEvent::Resized { new: window_info, old: old_window_info }),
+        );
+    }
+
+    /// Let the handler know where an outgoing drag is, if it moved or the target under the cursor
+    /// changed whether it will accept the drop.
+    fn send_drag_source_moved(&mut self, handler: &mut dyn WindowHandler) {
+        let moved = self.drag_handler.write().unwrap().take_moved();
+        if let Some(((x, y), will_accept)) = moved {
+            let position = PhyPoint::new(x as i32, y as i32).to_logical(&self.window_info);
+            handler.on_event(
+                &mut crate::EventContext::new(self),
+                Event::Window(WindowEvent::DragSourceMoved { position, will_accept }),
+            );
+        }
+    }
+
+    /// Let the handler know that an outgoing drag has ended, if it did.
+    fn send_drag_source_ended(&mut self, handler: &mut dyn WindowHandler) {
+        let outcome = self.drag_handler.write().unwrap().take_outcome();
+        if let Some(effect) = outcome {
+            handler.on_event(
+                &mut crate::EventContext::new(self),
+                Event::Window(WindowEvent::DragSourceEnded { effect }),
+            );
+        }
+    }
+
+    fn cancel_drag(&mut self) {
+        self.drag_handler
+            .write()
+            .unwrap()
+            .cancel(self.conn(), self.window_id)
+            .expect("Couldn't cancel DND drag");
+    }
+
+    // Return whether we have actual handled anything. If not, we'll handle it as a normal event
+    fn handle_dragging_event(&mut self, event: &xcb::GenericEvent) -> bool {
+        let event_type = event.response_type() & !0x80;
         match event_type {
             xcb::MOTION_NOTIFY => {
                 let event = unsafe { xcb::cast_event::<xcb::MotionNotifyEvent>(&event) };
                 let detail = event.detail();
 
-                if self.drag_handler.read().unwrap().will_accept() {
-                    self.set_mouse_cursor(MouseCursor::HandGrabbing);
-                } else {
-                    self.set_mouse_cursor(MouseCursor::NotAllowed);
-                }
-
                 if detail != 4 && detail != 5 {
                     if let Err(e) = self.drag_handler.write().unwrap().motion(
                         event,
@@ -670,47 +1719,59 @@ impl Window {
                     self.drag_handler
                         .write()
                         .unwrap()
-                        .do_drop(&self.conn(), self.window_id)
+                        .do_drop(self.conn(), self.window_id, event.time())
                         .expect("Couldn't drop DND element");
-                    self.set_mouse_cursor(MouseCursor::Default);
                 }
                 false // we still want to do the default release action
             }
             xcb::KEY_PRESS => {
                 let event = unsafe { xcb::cast_event::<xcb::KeyPressEvent>(&event) };
-                match convert_key_press_event(event).key {
+                let key =
+                    self.conn().with_keymap(|keymap| convert_key_press_event(event, keymap).key);
+                match key {
                     // Abort
-                    keyboard_types::Key::Escape => {
-                        self.drag_handler
-                            .write()
-                            .unwrap()
-                            .cancel(&self.conn(), self.window_id)
-                            .expect("Couldn't cancel DND drag");
-                        self.set_mouse_cursor(MouseCursor::Default);
-                    }
+                    keyboard_types::Key::Escape => self.cancel_drag(),
                     _ => (),
                 }
                 true
             }
+            // The server generates these crossing events when the pointer grab ends. Since the
+            // drag releases the grab itself after it has ended, this means that the grab was
+            // broken, for instance because the window was hidden.
+            xcb::ENTER_NOTIFY | xcb::LEAVE_NOTIFY => {
+                let event = unsafe { xcb::cast_event::<xcb::EnterNotifyEvent>(&event) };
+                if event.mode() as u32 == xcb::NOTIFY_MODE_UNGRAB {
+                    self.cancel_drag();
+                }
+                true
+            }
+            // Another client grabbing the keyboard also takes away the Escape key
+            xcb::FOCUS_OUT => {
+                let event = unsafe { xcb::cast_event::<xcb::FocusOutEvent>(&event) };
+                if event.mode() as u32 == xcb::NOTIFY_MODE_GRAB {
+                    self.cancel_drag();
+                }
+                false
+            }
+            xcb::UNMAP_NOTIFY => {
+                self.cancel_drag();
+                false
+            }
             xcb::CLIENT_MESSAGE => {
                 let event = unsafe { xcb::cast_event::<xcb::ClientMessageEvent>(&event) };
                 let atoms = &self.conn().atoms;
                 let data = event.data().data32();
                 let event_type = event.type_();
 
-                if event_type == self.conn().atoms.dnd_status {
+                if event_type == self.conn().atoms.dnd.status {
                     self.drag_handler
                         .write()
                         .unwrap()
                         .handle_status(data, &self.conn(), self.window_id)
                         .expect("Couldn't cancel DND drag");
-                    if self.drag_handler.read().unwrap().will_accept() {
-                        self.set_mouse_cursor(MouseCursor::HandGrabbing);
-                    } else {
-                        self.set_mouse_cursor(MouseCursor::NotAllowed);
-                    }
+                    self.drag_handler.write().unwrap().update_cursor(self.conn());
                     true
-                } else if event_type == atoms.dnd_finished {
+                } else if event_type == atoms.dnd.finished {
                     // We don't really need to do anything here.
                     true
                 } else {
@@ -720,8 +1781,7 @@ impl Window {
             xcb::SELECTION_REQUEST => {
                 let event = unsafe { xcb::cast_event::<xcb::SelectionRequestEvent>(&event) };
                 if event.owner() == self.window_id
-                    && event.selection() == self.conn().atoms.dnd_selection
-                    && event.target() == self.conn().atoms.dnd_uri_list
+                    && event.selection() == self.conn().atoms.dnd.selection
                 {
                     self.drag_handler
                         .write()
@@ -735,6 +1795,24 @@ impl Window {
         }
     }
 
+    /// The mouse buttons that are held down according to the `state` of a core input event, which
+    /// is the state from right before that event. The core protocol only has masks for the first
+    /// five buttons, and buttons 4 and 5 are the scroll wheel, so the other buttons come from the
+    /// presses the window received itself.
+    fn held_mouse_buttons(&self, state: u16) -> MouseButtonSet {
+        let other_buttons = self.held_inputs.mouse_buttons() - STATE_MOUSE_BUTTONS;
+
+        mouse_buttons(state) | other_buttons
+    }
+
+    /// Send a mouse button or keyboard event to the handler while keeping track of which buttons
+    /// and keys are held down.
+    fn send_input_event(&mut self, handler: &mut dyn WindowHandler, event: Event) {
+        self.held_inputs.track(&event);
+        self.event_clock.track(&event);
+        handler.on_event(&mut crate::EventContext::new(self), event);
+    }
+
     fn handle_xcb_event(&mut self, handler: &mut dyn WindowHandler, event: xcb::GenericEvent) {
         let event_type = event.response_type() & !0x80;
 
@@ -770,11 +1848,25 @@ impl Window {
 
                 if data[0] == atoms.wm_delete_window {
                     self.handle_close_requested(handler);
-                } else if event_type == atoms.dnd_enter {
+                } else if event_type == atoms.wm_protocols && data[0] == atoms.wm_take_focus {
+                    // The second value is the timestamp of the event that caused the focus change
+                    self.take_focus(data[1]);
+                } else if !self.dnd_enabled {
+                    // Drag and drop is disabled, so none of the messages below can be for us
+                } else if event_type == atoms.dnd.enter {
                     let source_window = data[0];
                     let flags = data[1];
                     let version = flags >> 24;
+                    debug!(target: "baseview::dnd", source_window, version, "incoming drag entered");
+                    // The previous drag should have left the window first, but its source may
+                    // have crashed before it could send `XdndLeave`
+                    if self.drag_event_order.in_drag() {
+                        self.send_drag_event(handler, WindowEvent::DragLeave);
+                    }
+                    self.drop_handler.reset();
                     self.drop_handler.version = Some(version);
+                    self.drop_handler.source_info =
+                        Some(DropHandler::query_source_info(&self.conn(), source_window));
                     let has_more_types = (flags & 0b1) == 1;
                     if !has_more_types {
                         let type_list = vec![data[2], data[3], data[4]];
@@ -784,9 +1876,17 @@ impl Window {
                     {
                         self.drop_handler.type_list = Some(more_types);
                     }
-                } else if event_type == atoms.dnd_position {
+                    self.drop_handler.select_target(&self.xcb_connection.atoms.dnd);
+
+                    // Without a URI list or a portal file transfer there are no files, and
+                    // `XdndPosition` rejects the drag
+                    if self.drop_handler.target.is_none() {
+                        let error = DropError::NotAFile(None);
+                        self.send_drag_event(handler, WindowEvent::DropFailed(error));
+                    }
+                } else if event_type == atoms.dnd.position {
                     // This event is send when a DND cursor moves
-                    // over our window. `send_status` with `DndState::Accepted`
+                    // over our window. `send_status` with a drop effect
                     // informs sources that we're interested in this selection
 
                     // When we reply with an accepted status, we will keep getting these events whenever there is movement
@@ -795,160 +1895,253 @@ impl Window {
 
                     // // By our own state flow, `version` should never be `None` at this point.
                     let version = self.drop_handler.version.unwrap_or(5);
-                    let accepted = if let Some(ref type_list) = self.drop_handler.type_list {
-                        type_list.contains(&self.conn().atoms.dnd_uri_list)
-                    } else {
-                        false
-                    };
 
-                    if accepted {
+                    if self.drop_handler.target.is_some() {
                         self.drop_handler.source_window = Some(source_window);
+                        // In version 0, time isn't specified
+                        if version >= 1 {
+                            self.drop_handler.time = data[3];
+                        }
                         if self.drop_handler.result.is_none() {
-                            let time = if version >= 1 {
-                                data[3]
-                            } else {
-                                // In version 0, time isn't specified
-                                xcb::base::CURRENT_TIME
-                            };
                             // This results in the `SelectionNotify` event below
-                            self.drop_handler.convert_selection(&self.conn(), self.window_id, time);
+                            self.drop_handler.convert_selection(self.conn(), self.window_id);
                         }
-                        let accept = if self.drop_target_valid() {
-                            DndState::Accepted
-                        } else {
-                            DndState::Rejected
-                        };
-                        self.drop_handler
-                            .send_status(&self.conn(), self.window_id, source_window, accept)
-                            .expect("Failed to send `XdndStatus` message.");
+                        self.drop_handler.update_requested_action(self.xcb_connection, data[4]);
 
                         // Send mouse motion and dragging events
-                        let x = data[2] >> 16;
-                        let y = data[2] & 0xFFFF;
-                        let setup = self.conn().conn.get_setup();
-                        let screen = setup.roots().nth(self.conn().xlib_display as usize).unwrap();
-                        let r = xcb::translate_coordinates(
-                            &self.conn().conn,
-                            screen.root(),
-                            self.window_id,
-                            x as i16,
-                            y as i16,
-                        )
-                        .get_reply()
-                        .expect("Could not translate coordinates");
-                        let physical_pos = PhyPoint::new(r.dst_x().into(), r.dst_y().into());
-                        let logical_pos = physical_pos.to_logical(&self.window_info);
-                        handler.on_event(
-                            &mut crate::Window::new(self),
-                            Event::Mouse(MouseEvent::CursorMoved {
-                                position: logical_pos,
-                                modifiers: Modifiers::empty(),
-                            }),
-                        );
-                        handler.on_event(
-                            &mut crate::Window::new(self),
-                            Event::Window(WindowEvent::Dragging),
+                        let x = (data[2] >> 16) as i16;
+                        let y = (data[2] & 0xFFFF) as i16;
+                        let root_offset = match self.drop_handler.root_offset {
+                            Some(root_offset) => root_offset,
+                            None => {
+                                let setup = self.conn().conn.get_setup();
+                                let screen =
+                                    setup.roots().nth(self.conn().xlib_display as usize).unwrap();
+                                let r = xcb::translate_coordinates(
+                                    &self.conn().conn,
+                                    screen.root(),
+                                    self.window_id,
+                                    0,
+                                    0,
+                                )
+                                .get_reply()
+                                .expect("Could not translate coordinates");
+                                let root_offset = PhyPoint::new(r.dst_x().into(), r.dst_y().into());
+                                self.drop_handler.root_offset = Some(root_offset);
+                                root_offset
+                            }
+                        };
+                        let physical_pos = PhyPoint::new(
+                            i32::from(x) + root_offset.x,
+                            i32::from(y) + root_offset.y,
                         );
+                        self.drop_handler.position = Some(physical_pos);
+
+                        // The first positions arrive before the data, in which case the position
+                        // is sent along with the `DragEnter` events instead
+                        if self.drag_event_order.in_drag() {
+                            let logical_pos = physical_pos.to_logical(&self.window_info);
+                            handler.on_event(
+                                &mut crate::EventContext::new(self),
+                                Event::Mouse(MouseEvent::CursorMoved {
+                                    position: logical_pos,
+                                    delta: PhyPoint::default(),
+                                    modifiers: Modifiers::empty(),
+                                    buttons: MouseButtonSet::empty(),
+                                }),
+                            );
+                            self.send_drag_event(handler, WindowEvent::Dragging);
+                        }
+
+                        // The handler may have picked a different drop effect in response to the
+                        // events above. Drags whose data couldn't be read are rejected.
+                        let effect = self.drop_handler.status_effect(self.drop_effect());
+                        self.drop_handler
+                            .send_status(&self.conn(), self.window_id, source_window, effect)
+                            .expect("Failed to send `XdndStatus` message.");
                     } else {
                         self.drop_handler
-                            .send_status(
-                                &self.conn(),
-                                self.window_id,
-                                source_window,
-                                DndState::Rejected,
-                            )
+                            .send_status(&self.conn(), self.window_id, source_window, None)
                             .expect("Failed to send `XdndStatus` message.");
                         self.drop_handler.reset()
                     }
-                } else if event_type == atoms.dnd_drop {
-                    let (source_window, state) =
-                        if let Some(source_window) = self.drop_handler.source_window {
-                            if self.drop_handler.result.is_some()
-                                && self.drop_handler.result.as_ref().unwrap().is_ok()
-                            {
-                                let paths = self.drop_handler.result.take().unwrap().unwrap();
-                                if paths.is_empty() {
-                                    handler.on_event(
-                                        &mut crate::Window::new(self),
-                                        Event::Window(WindowEvent::DragLeave),
-                                    );
-                                } else {
-                                    for path in paths.iter() {
-                                        // println!("Dropped {path:?}");
-                                        handler.on_event(
-                                            &mut crate::Window::new(self),
-                                            Event::Window(WindowEvent::Drop(Data::Filepath(
-                                                path.to_path_buf(),
-                                            ))),
-                                        );
-                                    }
+                } else if event_type == atoms.dnd.drop {
+                    let (source_window, effect) = if let (Some(source_window), Some(position)) =
+                        (self.drop_handler.source_window, self.drop_handler.position)
+                    {
+                        let received = self.drop_handler.drop_received();
+                        // The handler only knows about the drag if its data has arrived
+                        if self.drag_event_order.in_drag() {
+                            match self.drop_handler.result.take() {
+                                Some(Ok(paths)) if !paths.is_empty() => {
+                                    let payload = DropPayload {
+                                        items: paths.into_iter().map(Data::Filepath).collect(),
+                                        position: position.to_logical(&self.window_info),
+                                        source: self.drop_handler.source_info.clone(),
+                                    };
+                                    self.send_drag_event(handler, WindowEvent::Drop(payload));
                                 }
-                            } else {
-                                handler.on_event(
-                                    &mut crate::Window::new(self),
-                                    Event::Window(WindowEvent::DragLeave),
-                                );
+                                _ => self.send_drag_event(handler, WindowEvent::DragLeave),
                             }
-                            (source_window, DndState::Accepted)
-                        } else {
-                            // `source_window` won't be part of our DND state if we already rejected the drop in our
-                            // `XdndPosition` handler.
-                            let source_window = data[0];
-                            (source_window, DndState::Rejected)
-                        };
+                        }
+                        // The handler may have picked a different drop effect in response to the
+                        // drop. Drops it never received are rejected.
+                        (source_window, if received { self.drop_effect() } else { None })
+                    } else {
+                        // `source_window` won't be part of our DND state if we already rejected the drop in our
+                        // `XdndPosition` handler.
+                        let source_window = data[0];
+                        (source_window, None)
+                    };
                     self.drop_handler
-                        .send_finished(&self.conn(), self.window_id, source_window, state)
+                        .send_finished(&self.conn(), self.window_id, source_window, effect)
                         .expect("Failed to send `XdndFinished` message.");
                     self.drop_handler.reset();
-                } else if event_type == atoms.dnd_leave {
+                } else if event_type == atoms.dnd.finished {
+                    // Drops that were accepted with `XdndActionAsk` only report the action that was
+                    // performed once the target has finished
+                    self.drag_handler.write().unwrap().handle_finished(self.conn(), data);
+                } else if event_type == atoms.dnd.leave {
                     self.drop_handler.reset();
-                    handler.on_event(
-                        &mut crate::Window::new(self),
-                        Event::Window(WindowEvent::DragLeave),
-                    );
+                    if self.drag_event_order.in_drag() {
+                        self.send_drag_event(handler, WindowEvent::DragLeave);
+                    }
+                }
+            }
+
+            xcb::PROPERTY_NOTIFY => {
+                let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+                let atoms = &self.conn().atoms;
+                if event.atom() == atoms.net_wm_state && atoms.net_wm_state != xcb::ATOM_NONE {
+                    self.minimized = self.has_hidden_state(self.window_id);
                 }
             }
 
             xcb::SELECTION_NOTIFY => {
                 let event = unsafe { xcb::cast_event::<xcb::SelectionNotifyEvent>(&event) };
-                if event.property() == self.conn().atoms.dnd_baseview_transfer {
+                let atoms = &self.xcb_connection.atoms.dnd;
+                if self.dnd_enabled
+                    && event.property() == atoms.baseview_transfer
+                    && self.drop_handler.wants_reply(atoms, event.target())
+                {
                     let window = event.requestor();
 
                     // This is where we receive data from drag and drop
-                    match self.drop_handler.read_data(&self.conn(), window) {
-                        Ok(mut data) => {
-                            let parse_result = self.drop_handler.parse_data(&mut data);
-                            if let Ok(ref path_list) = parse_result {
-                                for path in path_list {
-                                    // println!("Got dnd path: {path:?}");
-                                    handler.on_event(
-                                        &mut crate::Window::new(self),
-                                        Event::Window(WindowEvent::DragEnter(Data::Filepath(
-                                            path.to_path_buf(),
-                                        ))),
-                                    );
-                                }
-                            }
+                    let parse_result = match self.drop_handler.read_data(&self.conn(), window) {
+                        // The paths are parsed straight from the reply's buffer
+                        Ok(reply) => self.drop_handler.parse_target_data(reply.name().as_bytes()),
+                        Err(_err) => {
+                            debug!(
+                                target: "baseview::dnd",
+                                error_code = _err.error_code(),
+                                "could not read drag data"
+                            );
+                            Err(DropError::Unavailable)
+                        }
+                    };
 
-                            self.drop_handler.result = Some(parse_result);
+                    // If the portal couldn't provide the files, the source may still offer them as
+                    // a URI list. The handler only hears about the drag once that has arrived.
+                    #[cfg(feature = "portal")]
+                    if parse_result.is_err()
+                        && self.drop_handler.fall_back_to_uri_list(&self.xcb_connection.atoms.dnd)
+                    {
+                        self.drop_handler.convert_selection(self.conn(), self.window_id);
+                        return;
+                    }
+                    debug!(target: "baseview::dnd", ?parse_result, "received drag data");
+                    match &parse_result {
+                        Ok(path_list) => {
+                            // The handler should know where the drag is before it entered
+                            if let Some(position) = self.drop_handler.position {
+                                let position = position.to_logical(&self.window_info);
+                                handler.on_event(
+                                    &mut crate::EventContext::new(self),
+                                    Event::Mouse(MouseEvent::CursorMoved {
+                                        position,
+                                        delta: PhyPoint::default(),
+                                        modifiers: Modifiers::empty(),
+                                        buttons: MouseButtonSet::empty(),
+                                    }),
+                                );
+                            }
+                            for path in path_list {
+                                let event = WindowEvent::DragEnter {
+                                    data: Data::Filepath(path.to_path_buf()),
+                                    source: self.drop_handler.source_info.clone(),
+                                };
+                                self.send_drag_event(handler, event);
+                            }
                         }
-                        Err(e) => {
-                            dbg!(e);
+                        // The next `XdndStatus` message rejects the drag
+                        Err(error) => {
+                            self.send_drag_event(handler, WindowEvent::DropFailed(error.clone()));
                         }
                     }
+
+                    self.drop_handler.result = Some(parse_result);
+                }
+            }
+
+            xcb::EXPOSE => {
+                let event = unsafe { xcb::cast_event::<xcb::ExposeEvent>(&event) };
+                let rect = PhyRect::new(
+                    event.x() as i32,
+                    event.y() as i32,
+                    event.width() as u32,
+                    event.height() as u32,
+                );
+
+                self.damage.add(rect.to_logical(&self.window_info));
+            }
+
+            // Someone else destroyed the window. Destroying a window also destroys its children
+            // before the window itself, so this is how the window finds out that the host
+            // destroyed the parent window without closing the window first.
+            xcb::DESTROY_NOTIFY => {
+                let event = unsafe { xcb::cast_event::<xcb::DestroyNotifyEvent>(&event) };
+                if event.window() == self.window_id && !self.window_destroyed {
+                    debug!(target: "baseview::lifecycle", "window destroyed externally");
+                    self.window_destroyed = true;
+                    self.handle_must_close(handler);
                 }
             }
 
             xcb::CONFIGURE_NOTIFY => {
                 let event = unsafe { xcb::cast_event::<xcb::ConfigureNotifyEvent>(&event) };
 
-                let new_physical_size = PhySize::new(event.width() as u32, event.height() as u32);
+                let new_physical_size = PhySize::new(event.width().into(), event.height().into());
 
                 if self.new_physical_size.is_some()
                     || new_physical_size != self.window_info.physical_size()
                 {
                     self.new_physical_size = Some(new_physical_size);
                 }
+                // The window may have moved, so drag positions need to be translated again
+                self.drop_handler.root_offset = None;
+
+                #[cfg(feature = "accesskit")]
+                self.update_accesskit_bounds();
+            }
+
+            // The window is mapped right before the event loop starts, so this also delivers the
+            // first `Shown` event
+            xcb::MAP_NOTIFY => {
+                self.mapped = true;
+                handler.on_event(
+                    &mut crate::EventContext::new(self),
+                    Event::Window(WindowEvent::Shown),
+                );
+            }
+
+            xcb::UNMAP_NOTIFY => {
+                self.mapped = false;
+                // The server releases the grab when the window stops being viewable
+                self.cursor_confined = false;
+                handler.on_event(
+                    &mut crate::EventContext::new(self),
+                    Event::Window(WindowEvent::Hidden),
+                );
             }
 
             ////
@@ -960,14 +2153,20 @@ impl Window {
 
                 if detail != 4 && detail != 5 {
                     let physical_pos =
-                        PhyPoint::new(event.event_x() as i32, event.event_y() as i32);
+                        PhyPoint::new(event.event_x().into(), event.event_y().into());
                     let logical_pos = physical_pos.to_logical(&self.window_info);
+                    let delta =
+                        physical_pos.delta_from(self.last_cursor_position.replace(physical_pos));
+                    let buttons = self.held_mouse_buttons(event.state());
+                    self.update_cursor();
 
                     handler.on_event(
-                        &mut crate::Window::new(self),
+                        &mut crate::EventContext::new(self),
                         Event::Mouse(MouseEvent::CursorMoved {
                             position: logical_pos,
+                            delta,
                             modifiers: key_mods(event.state()),
+                            buttons,
                         }),
                     );
                 }
@@ -979,27 +2178,30 @@ impl Window {
 
                 match detail {
                     4..=7 => {
+                        let (x, y) = SCROLL_BUTTON_DELTAS[(detail - 4) as usize];
                         handler.on_event(
-                            &mut crate::Window::new(self),
+                            &mut crate::EventContext::new(self),
                             Event::Mouse(MouseEvent::WheelScrolled {
-                                delta: match detail {
-                                    4 => ScrollDelta::Lines { x: 0.0, y: 1.0 },
-                                    5 => ScrollDelta::Lines { x: 0.0, y: -1.0 },
-                                    6 => ScrollDelta::Lines { x: -1.0, y: 0.0 },
-                                    7 => ScrollDelta::Lines { x: 1.0, y: 0.0 },
-                                    _ => unreachable!(),
-                                },
+                                delta: ScrollDelta::Lines { x, y },
                                 modifiers: key_mods(event.state()),
                             }),
                         );
                     }
                     detail => {
+                        // Nothing else will give an embedded window or a popup the focus
+                        if self.focus_on_click || self.focus_in_child {
+                            self.take_focus(event.time());
+                        }
+
                         let button_id = mouse_id(detail);
-                        handler.on_event(
-                            &mut crate::Window::new(self),
+                        let buttons = self.held_mouse_buttons(event.state())
+                            | MouseButtonSet::from_button(button_id);
+                        self.send_input_event(
+                            handler,
                             Event::Mouse(MouseEvent::ButtonPressed {
                                 button: button_id,
                                 modifiers: key_mods(event.state()),
+                                buttons,
                             }),
                         );
                     }
@@ -1012,11 +2214,14 @@ impl Window {
 
                 if !(4..=7).contains(&detail) {
                     let button_id = mouse_id(detail);
-                    handler.on_event(
-                        &mut crate::Window::new(self),
+                    let buttons = self.held_mouse_buttons(event.state())
+                        - MouseButtonSet::from_button(button_id);
+                    self.send_input_event(
+                        handler,
                         Event::Mouse(MouseEvent::ButtonReleased {
                             button: button_id,
                             modifiers: key_mods(event.state()),
+                            buttons,
                         }),
                     );
                 }
@@ -1028,19 +2233,86 @@ impl Window {
             xcb::KEY_PRESS => {
                 let event = unsafe { xcb::cast_event::<xcb::KeyPressEvent>(&event) };
 
-                handler.on_event(
-                    &mut crate::Window::new(self),
-                    Event::Keyboard(convert_key_press_event(event)),
-                );
+                let key_event =
+                    self.conn().with_keymap(|keymap| convert_key_press_event(event, keymap));
+                self.send_input_event(handler, Event::Keyboard(key_event));
             }
 
             xcb::KEY_RELEASE => {
                 let event = unsafe { xcb::cast_event::<xcb::KeyReleaseEvent>(&event) };
 
-                handler.on_event(
-                    &mut crate::Window::new(self),
-                    Event::Keyboard(convert_key_release_event(event)),
-                );
+                let key_event =
+                    self.conn().with_keymap(|keymap| convert_key_release_event(event, keymap));
+                self.send_input_event(handler, Event::Keyboard(key_event));
+            }
+
+            ////
+            // focus
+            ////
+            xcb::FOCUS_IN => {
+                let event = unsafe { xcb::cast_event::<xcb::FocusInEvent>(&event) };
+                self.focus_in_child = false;
+
+                if !is_focus_change_for_other_window(event.detail()) {
+                    self.focused = true;
+                    if self.nested {
+                        self.select_events(WINDOW_EVENT_MASK | KEY_EVENT_MASK);
+                    }
+                    #[cfg(feature = "accesskit")]
+                    if let Some(adapter) = &mut self.accesskit {
+                        adapter.update_window_focus_state(true);
+                    }
+
+                    handler.on_event(
+                        &mut crate::EventContext::new(self),
+                        Event::Window(WindowEvent::Focused),
+                    );
+                }
+            }
+
+            xcb::FOCUS_OUT => {
+                let event = unsafe { xcb::cast_event::<xcb::FocusOutEvent>(&event) };
+                self.focus_in_child = event.detail() as u32 == xcb::NOTIFY_DETAIL_INFERIOR;
+
+                if !is_focus_change_for_other_window(event.detail()) {
+                    self.focused = false;
+                    if self.nested {
+                        self.select_events(WINDOW_EVENT_MASK);
+                    }
+                    #[cfg(feature = "accesskit")]
+                    if let Some(adapter) = &mut self.accesskit {
+                        adapter.update_window_focus_state(false);
+                    }
+                    self.release_cursor();
+
+                    // We won't receive key release events without the focus. When another client
+                    // grabbed the keyboard, it likely took the pointer grab as well so the mouse
+                    // buttons can also no longer be released.
+                    let mut events = if event.mode() as u32 == xcb::NOTIFY_MODE_GRAB {
+                        self.held_inputs.release_all()
+                    } else {
+                        self.held_inputs.release_keys()
+                    };
+                    events.push(Event::Window(WindowEvent::Unfocused));
+
+                    for event in events {
+                        handler.on_event(&mut crate::EventContext::new(self), event);
+                    }
+                }
+            }
+
+            ////
+            // tablets
+            ////
+            xcb::GE_GENERIC => {
+                let pen_moved = self.conn().xinput_opcode.and_then(|opcode| {
+                    let event = MotionEvent::parse(&event, opcode)?;
+                    self.pen_devices.pen_moved(&event, &self.window_info)
+                });
+
+                if let Some(pen_moved) = pen_moved {
+                    handler.on_event(&mut crate::EventContext::new(self), Event::Mouse(pen_moved));
+                }
             }
 
             _ => {}
@@ -1048,28 +2320,164 @@ impl Window {
     }
 }
 
-unsafe impl HasRawWindowHandle for Window {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        let mut handle = XlibWindowHandle::empty();
-        handle.window = self.window_id as c_ulong;
+/// The name of an X11 event type, or `"other"` for events baseview doesn't handle.
+#[cfg(feature = "tracing")]
+fn xcb_event_name(response_type: u8) -> &'static str {
+    match response_type & !0x80 {
+        xcb::EXPOSE => "Expose",
+        xcb::CLIENT_MESSAGE => "ClientMessage",
+        xcb::SELECTION_NOTIFY => "SelectionNotify",
+        xcb::PROPERTY_NOTIFY => "PropertyNotify",
+        xcb::SELECTION_REQUEST => "SelectionRequest",
+        xcb::CONFIGURE_NOTIFY => "ConfigureNotify",
+        xcb::MAP_NOTIFY => "MapNotify",
+        xcb::UNMAP_NOTIFY => "UnmapNotify",
+        xcb::DESTROY_NOTIFY => "DestroyNotify",
+        xcb::MOTION_NOTIFY => "MotionNotify",
+        xcb::BUTTON_PRESS => "ButtonPress",
+        xcb::BUTTON_RELEASE => "ButtonRelease",
+        xcb::KEY_PRESS => "KeyPress",
+        xcb::KEY_RELEASE => "KeyRelease",
+        xcb::FOCUS_IN => "FocusIn",
+        xcb::FOCUS_OUT => "FocusOut",
+        xcb::GE_GENERIC => "GenericEvent",
+        0 => "Error",
+        _ => "other",
+    }
+}
 
-        let setup = self.conn().conn.get_setup();
-        let screen = setup.roots().nth(self.conn().xlib_display as usize).unwrap();
-        handle.visual_id = screen.root_visual() as u64;
+/// The server timestamp of an input event in milliseconds, or `None` for events without one.
+fn xcb_event_time(event: &xcb::GenericEvent) -> Option<u32> {
+    let time = unsafe {
+        match event.response_type() & !0x80 {
+            xcb::KEY_PRESS | xcb::KEY_RELEASE => {
+                xcb::cast_event::<xcb::KeyPressEvent>(event).time()
+            }
+            xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
+                xcb::cast_event::<xcb::ButtonPressEvent>(event).time()
+            }
+            xcb::MOTION_NOTIFY => xcb::cast_event::<xcb::MotionNotifyEvent>(event).time(),
+            xcb::ENTER_NOTIFY | xcb::LEAVE_NOTIFY => {
+                xcb::cast_event::<xcb::EnterNotifyEvent>(event).time()
+            }
+            _ => return None,
+        }
+    };
+
+    Some(time)
+}
 
-        RawWindowHandle::Xlib(handle)
+/// Drop `messages` without handling them. Returns whether one of them was the `DestroyNotify` event
+/// for `window_id`.
+fn discard_messages(messages: impl Iterator<Item = WindowMessage>, window_id: u32) -> bool {
+    let mut destroyed = false;
+    for message in messages {
+        let WindowMessage::Event(XcbEvent(event)) = message else { continue };
+        if event.response_type() & !0x80 == xcb::DESTROY_NOTIFY {
+            let event = unsafe { xcb::cast_event::<xcb::DestroyNotifyEvent>(&event) };
+            destroyed |= event.window() == window_id;
+        }
     }
+
+    destroyed
 }
 
-unsafe impl HasRawDisplayHandle for Window {
-    fn raw_display_handle(&self) -> raw_window_handle::RawDisplayHandle {
-        let mut handle = XlibDisplayHandle::empty();
-        handle.display = self.conn().conn.get_raw_dpy() as *mut c_void;
+/// Focus events with these details are about the window's children or the window under the
+/// pointer, and don't change whether this window has the focus.
+fn is_focus_change_for_other_window(detail: u8) -> bool {
+    let detail = detail as u32;
+
+    detail == xcb::NOTIFY_DETAIL_INFERIOR || detail == xcb::NOTIFY_DETAIL_POINTER
+}
 
-        raw_window_handle::RawDisplayHandle::Xlib(handle)
+/// The buttons that are included in the button mask of an input event's `state`.
+const STATE_MOUSE_BUTTONS: MouseButtonSet =
+    MouseButtonSet::LEFT.union(MouseButtonSet::MIDDLE).union(MouseButtonSet::RIGHT);
+
+/// The buttons in the button mask of an input event's `state`. The masks for buttons 4 and 5 belong
+/// to the scroll wheel, and there are no masks for the other buttons.
+const fn mouse_buttons(state: u16) -> MouseButtonSet {
+    let masks = [
+        (xcb::BUTTON_MASK_1, MouseButtonSet::LEFT),
+        (xcb::BUTTON_MASK_2, MouseButtonSet::MIDDLE),
+        (xcb::BUTTON_MASK_3, MouseButtonSet::RIGHT),
+    ];
+
+    let mut buttons = MouseButtonSet::empty();
+    let mut i = 0;
+    while i < masks.len() {
+        if state & masks[i].0 as u16 != 0 {
+            buttons = buttons.union(masks[i].1);
+        }
+        i += 1;
     }
+
+    buttons
+}
+
+/// The system's scale factors for a window that follows them. See
+/// [`WindowOpenOptions::system_scale_range`].
+fn system_scale(
+    conn: &XcbConnection, policy: WindowScalePolicy, range: &RangeInclusive<f64>,
+) -> (f64, f64) {
+    let fallback = match policy {
+        WindowScalePolicy::SystemScaleFactorWithFallback(scale) => Some(scale),
+        _ => None,
+    };
+
+    resolve_system_scale(conn.cached_scale(), range, fallback)
+}
+
+/// Mark a popup window as a popup menu, which compositors use to pick its shadow and animations,
+/// and keep it out of the taskbar in case the window manager does look at it.
+fn set_popup_properties(conn: &XcbConnection, window_id: u32) {
+    let atoms = &conn.atoms;
+    if atoms.net_wm_window_type != xcb::ATOM_NONE {
+        xcb::change_property(
+            &conn.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window_id,
+            atoms.net_wm_window_type,
+            xcb::ATOM_ATOM,
+            32,
+            &[atoms.net_wm_window_type_popup_menu],
+        );
+    }
+    if atoms.net_wm_state != xcb::ATOM_NONE && atoms.net_wm_state_skip_taskbar != xcb::ATOM_NONE {
+        xcb::change_property(
+            &conn.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window_id,
+            atoms.net_wm_state,
+            xcb::ATOM_ATOM,
+            32,
+            &[atoms.net_wm_state_skip_taskbar],
+        );
+    }
+}
+
+/// X11 window sizes are 16-bit. Larger sizes are clamped, and debug builds catch them.
+fn x11_dimension(value: u32) -> u16 {
+    debug_assert!(value <= u32::from(u16::MAX), "{} doesn't fit in an X11 window size", value);
+
+    u16::try_from(value).unwrap_or(u16::MAX)
+}
+
+/// Pin the window's minimum and maximum size to `size`, so the user can't resize it.
+fn set_fixed_size_hints(conn: &XcbConnection, window_id: u32, size: PhySize) {
+    icccm::set_wm_size_hints(
+        &conn.conn,
+        window_id,
+        xcb::ATOM_WM_NORMAL_HINTS,
+        &icccm::SizeHints::empty()
+            .min_size(size.to_point().x, size.to_point().y)
+            .max_size(size.to_point().x, size.to_point().y)
+            .build(),
+    );
 }
 
+/// Buttons 4 to 7 are the scroll wheel's, so they're never passed to this function. See
+/// [`MouseButton::Other`] for how the other buttons are numbered.
 fn mouse_id(id: u8) -> MouseButton {
     match id {
         1 => MouseButton::Left,
@@ -1077,10 +2485,132 @@ fn mouse_id(id: u8) -> MouseButton {
         3 => MouseButton::Right,
         8 => MouseButton::Back,
         9 => MouseButton::Forward,
-        id => MouseButton::Other(id),
+        id => MouseButton::Other(id.saturating_sub(10)),
     }
 }
 
 pub fn copy_to_clipboard(_data: &str) {
     todo!()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closing_the_handle_between_events() {
+        const WINDOW: u32 = 10;
+        const CHILD: u32 = 20;
+
+        let (parent_handle, handle) = ParentHandle::new();
+        let remote = handle.remote();
+        let mut lifecycle = Lifecycle::Ready;
+        assert!(!parent_handle.parent_did_drop());
+
+        let (tx, rx) = mpsc::channel();
+        tx.send(WindowMessage::Event(XcbEvent::new(xcb::ExposeEvent::new(
+            WINDOW, 0, 0, 10, 10, 0,
+        ))))
+        .unwrap();
+
+        // The handle is closed after the first event was handled
+        let first_message = rx.try_recv().unwrap();
+        assert!(matches!(first_message, WindowMessage::Event(_)));
+        remote.close();
+        assert!(parent_handle.parent_did_drop());
+        assert!(lifecycle.handle_closed());
+        assert!(!lifecycle.can_send_events());
+
+        // So the events that arrive after that are dropped, but the window's destruction is noted
+        let queued = [
+            WindowMessage::Event(XcbEvent::new(xcb::DestroyNotifyEvent::new(WINDOW, CHILD))),
+            WindowMessage::Frame,
+            WindowMessage::Event(XcbEvent::new(xcb::DestroyNotifyEvent::new(WINDOW, WINDOW))),
+            WindowMessage::Event(XcbEvent::new(xcb::ExposeEvent::new(WINDOW, 0, 0, 10, 10, 0))),
+        ];
+        for message in queued {
+            tx.send(message).unwrap();
+        }
+        assert!(discard_messages(rx.try_iter(), WINDOW));
+        assert!(rx.try_recv().is_err());
+
+        // And the handler only receives `WillClose` once
+        assert!(lifecycle.can_send_will_close());
+        lifecycle = Lifecycle::Closing;
+        assert!(!lifecycle.can_send_will_close());
+        assert!(!lifecycle.handle_closed());
+    }
+
+    #[test]
+    fn discarded_messages() {
+        const WINDOW: u32 = 10;
+        const OTHER: u32 = 20;
+
+        let messages = || {
+            [
+                WindowMessage::Frame,
+                WindowMessage::ScreenChanged,
+                WindowMessage::Event(XcbEvent::new(xcb::ExposeEvent::new(WINDOW, 0, 0, 10, 10, 0))),
+                // A child window was destroyed
+                WindowMessage::Event(XcbEvent::new(xcb::DestroyNotifyEvent::new(WINDOW, OTHER))),
+            ]
+        };
+        assert!(!discard_messages(messages().into_iter(), WINDOW));
+        assert!(!discard_messages(std::iter::empty(), WINDOW));
+
+        let destroyed =
+            WindowMessage::Event(XcbEvent::new(xcb::DestroyNotifyEvent::new(WINDOW, WINDOW)));
+        assert!(discard_messages(messages().into_iter().chain([destroyed]), WINDOW));
+    }
+
+    #[test]
+    fn mouse_button_details() {
+        // Buttons 4 to 7 are the scroll wheel, which isn't sent through `mouse_id()`
+        let table = [
+            (1, MouseButton::Left),
+            (2, MouseButton::Middle),
+            (3, MouseButton::Right),
+            (8, MouseButton::Back),
+            (9, MouseButton::Forward),
+            (10, MouseButton::Other(0)),
+            (11, MouseButton::Other(1)),
+            (20, MouseButton::Other(10)),
+            (255, MouseButton::Other(245)),
+        ];
+
+        for (detail, button) in table {
+            assert_eq!(mouse_id(detail), button, "button {}", detail);
+        }
+    }
+
+    #[test]
+    fn state_button_masks() {
+        assert_eq!(mouse_buttons(0), MouseButtonSet::empty());
+        assert_eq!(mouse_buttons(xcb::BUTTON_MASK_1 as u16), MouseButtonSet::LEFT);
+        assert_eq!(mouse_buttons(xcb::BUTTON_MASK_2 as u16), MouseButtonSet::MIDDLE);
+        assert_eq!(mouse_buttons(xcb::BUTTON_MASK_3 as u16), MouseButtonSet::RIGHT);
+        assert_eq!(
+            mouse_buttons((xcb::BUTTON_MASK_1 | xcb::BUTTON_MASK_3) as u16),
+            MouseButtonSet::LEFT | MouseButtonSet::RIGHT
+        );
+
+        // The scroll wheel's buttons and the modifiers are ignored
+        let ignored = xcb::BUTTON_MASK_4 | xcb::BUTTON_MASK_5 | xcb::MOD_MASK_SHIFT;
+        assert_eq!(mouse_buttons((ignored | xcb::BUTTON_MASK_2) as u16), MouseButtonSet::MIDDLE);
+        assert_eq!(mouse_buttons(ignored as u16), MouseButtonSet::empty());
+    }
+
+    #[test]
+    fn state_masks_match_button_details() {
+        let masks = [xcb::BUTTON_MASK_1, xcb::BUTTON_MASK_2, xcb::BUTTON_MASK_3];
+        for (detail, mask) in (1..).zip(masks) {
+            assert_eq!(
+                mouse_buttons(mask as u16),
+                MouseButtonSet::from_button(mouse_id(detail)),
+                "button {}",
+                detail
+            );
+        }
+        assert_eq!(STATE_MOUSE_BUTTONS, mouse_buttons(u16::MAX));
+    }
+}
@@ -0,0 +1,118 @@
+use std::ffi::c_void;
+
+use super::xcb_connection::XcbConnection;
+
+/// The X11 connection a window uses, for making requests that baseview doesn't make itself, like
+/// setting the properties for an embedding handshake with the host. See
+/// [`EventContext::with_x11_connection()`](crate::EventContext::with_x11_connection).
+///
+/// Every window in the process shares this connection, and baseview reads all of its events on a
+/// separate thread. Requests that fail are reported to the window's
+/// [`WindowOpenOptions::x11_error_handler`](crate::WindowOpenOptions::x11_error_handler) like any
+/// other X11 error.
+pub struct X11Connection<'a> {
+    conn: &'a XcbConnection,
+}
+
+impl<'a> X11Connection<'a> {
+    pub(super) fn new(conn: &'a XcbConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Get the atom with this name, creating it if it doesn't exist yet. This waits for the X
+    /// server's reply. Returns `None` if the request failed.
+    pub fn get_atom(&self, name: &str) -> Option<u32> {
+        let reply = xcb::intern_atom(&self.conn.conn, false, name).get_reply().ok()?;
+
+        Some(reply.atom())
+    }
+
+    /// Replace the value of `property` on `window`. The item type decides the property's format,
+    /// so use `u8` for strings, and `u32` for atoms, windows, and cardinals.
+    pub fn change_property<T: PropertyFormat>(
+        &self, window: u32, property: u32, type_: u32, data: &[T],
+    ) {
+        xcb::change_property(
+            &self.conn.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            property,
+            type_,
+            T::FORMAT,
+            data,
+        );
+        self.conn.conn.flush();
+    }
+
+    /// Send a client message with 32-bit data to `destination`. The message's window field is set
+    /// to `window`. With an empty `event_mask` the message is sent to the client that created
+    /// `destination`, which is what most embedding protocols expect. Messages for the window manager
+    /// are sent to the root window with `SubstructureRedirect` and `SubstructureNotify` instead.
+    pub fn send_client_message(
+        &self, destination: u32, window: u32, message_type: u32, data: [u32; 5], event_mask: u32,
+    ) {
+        let event = xcb::ClientMessageEvent::new(
+            32,
+            window,
+            message_type,
+            xcb::ClientMessageData::from_data32(data),
+        );
+        xcb::send_event(&self.conn.conn, false, destination, event_mask, &event);
+        self.conn.conn.flush();
+    }
+
+    /// The root window of the screen the windows are created on.
+    pub fn root_window(&self) -> u32 {
+        let setup = self.conn.conn.get_setup();
+
+        setup.roots().nth(self.conn.xlib_display as usize).unwrap().root()
+    }
+
+    /// The connection's `xcb_connection_t` pointer, for making requests this type doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// The connection must not be closed, and it must not be used to wait for or poll events.
+    /// Baseview's own event thread needs to receive every event on the connection. The pointer
+    /// stays valid for the lifetime of the process.
+    pub unsafe fn raw_connection(&self) -> *mut c_void {
+        self.conn.conn.get_raw_conn() as *mut c_void
+    }
+
+    /// The Xlib `Display` pointer the connection was made with. The same rules as for
+    /// [`X11Connection::raw_connection()`] apply.
+    ///
+    /// # Safety
+    ///
+    /// See [`X11Connection::raw_connection()`].
+    pub unsafe fn raw_display(&self) -> *mut c_void {
+        self.conn.conn.get_raw_dpy() as *mut c_void
+    }
+}
+
+/// The item types [`X11Connection::change_property()`] accepts. This can't be implemented outside
+/// of baseview.
+pub trait PropertyFormat: Copy + sealed::Sealed {
+    /// The property format in bits, which is the item's size.
+    const FORMAT: u8;
+}
+
+impl PropertyFormat for u8 {
+    const FORMAT: u8 = 8;
+}
+
+impl PropertyFormat for u16 {
+    const FORMAT: u8 = 16;
+}
+
+impl PropertyFormat for u32 {
+    const FORMAT: u8 = 32;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+}
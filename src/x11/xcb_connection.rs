@@ -2,31 +2,236 @@ use std::collections::HashMap;
 /// A very light abstraction around the XCB connection.
 ///
 /// Keeps track of the xcb connection itself and the xlib display ID that was used to connect.
-use std::ffi::{CStr, CString};
+use std::sync::{Mutex, Once, OnceLock};
+use std::thread;
 
 use xcb::{ffi, Atom, GenericError};
 
-use crate::MouseCursor;
+use crate::{CursorIcon, DropEffect, MouseCursor};
 
 use super::cursor;
+use super::event_dispatcher::{EventDispatcher, ScreenSize};
+use super::keyboard::{Keymap, KeymapCache};
+use super::scaling::DetectedScale;
 
 #[derive(Debug)]
 pub(crate) struct Atoms {
     pub wm_protocols: u32,
     pub wm_delete_window: u32,
-    // DND
-    pub dnd_enter: u32,
-    pub dnd_leave: u32,
-    pub dnd_drop: u32,
-    pub dnd_position: u32,
-    pub dnd_status: u32,
-    pub dnd_action_private: u32,
-    pub dnd_action_copy: u32,
-    pub dnd_selection: u32,
-    pub dnd_finished: u32,
-    pub dnd_type_list: u32,
-    pub dnd_uri_list: u32,
-    pub dnd_baseview_transfer: u32,
+    pub wm_take_focus: u32,
+    /// `ATOM_NONE` if the atom could not be interned. The same goes for the other optional atoms
+    /// below.
+    pub net_wm_window_opacity: u32,
+    pub net_wm_state: u32,
+    pub net_wm_state_demands_attention: u32,
+    pub net_wm_state_hidden: u32,
+    pub net_wm_state_skip_taskbar: u32,
+    pub net_wm_window_type: u32,
+    pub net_wm_window_type_popup_menu: u32,
+    /// Only used to find out which application an incoming drag came from.
+    pub net_wm_pid: u32,
+    pub wm_state: u32,
+    /// The labels of the XInput 2 valuators graphics tablets use for pressure and tilt. These only
+    /// exist once a tablet driver has created them, so they're `ATOM_NONE` if no tablet has been
+    /// connected since the X server started.
+    pub abs_pressure: u32,
+    pub abs_tilt_x: u32,
+    pub abs_tilt_y: u32,
+    /// Set to `false` if the DND atoms could not be interned. Drag and drop is then disabled for
+    /// windows on this connection, and `dnd` only contains `ATOM_NONE`s.
+    pub dnd_enabled: bool,
+    pub dnd: DndAtoms,
+    /// The atoms interned through [`Self::intern_custom()`], by name.
+    custom: Mutex<HashMap<String, Atom>>,
+}
+
+/// The replies to the groups of atoms [`XcbConnection::new()`] interns, or `None` for the groups
+/// the server returned an error for. These are kept apart from the requests so the way missing
+/// atoms are handled can be tested without an X server.
+struct AtomReplies {
+    wm_protocols: Option<[Atom; 3]>,
+    wm_state: Option<[Atom; 4]>,
+    net_wm_state_hidden: Option<[Atom; 1]>,
+    popup: Option<[Atom; 3]>,
+    net_wm_pid: Option<[Atom; 1]>,
+    tablet: Option<[Atom; 3]>,
+    dnd: Option<[Atom; DND_ATOM_NAMES.len()]>,
+}
+
+impl Atoms {
+    /// Build the atoms from the interned groups. The `WM_PROTOCOLS` atoms are predefined by every
+    /// X server, so there's no sensible way to continue without them and this returns `None` if
+    /// they're missing. Every other missing atom becomes `ATOM_NONE`, and drag and drop is
+    /// disabled if the DND atoms are missing.
+    fn from_replies(replies: AtomReplies) -> Option<Self> {
+        let [wm_protocols, wm_delete_window, wm_take_focus] = replies.wm_protocols?;
+        // These are only needed for `Window::set_opacity()` and `Window::request_attention()`,
+        // which don't do anything without them
+        let [net_wm_window_opacity, net_wm_state, net_wm_state_demands_attention, wm_state] =
+            replies.wm_state.unwrap_or([xcb::ATOM_NONE; 4]);
+        // Without this `Window::is_minimized()` always returns `false`
+        let [net_wm_state_hidden] = replies.net_wm_state_hidden.unwrap_or([xcb::ATOM_NONE]);
+        // Popups still work without these, they're only hints for the compositor
+        let [net_wm_state_skip_taskbar, net_wm_window_type, net_wm_window_type_popup_menu] =
+            replies.popup.unwrap_or([xcb::ATOM_NONE; 3]);
+        // Incoming drags just won't have an application name without this
+        let [net_wm_pid] = replies.net_wm_pid.unwrap_or([xcb::ATOM_NONE]);
+        let [abs_pressure, abs_tilt_x, abs_tilt_y] = replies.tablet.unwrap_or([xcb::ATOM_NONE; 3]);
+        // Drag and drop support shouldn't be able to prevent a window from opening
+        let (dnd_enabled, dnd) = match replies.dnd {
+            Some(atoms) => (true, DndAtoms::from_atoms(atoms)),
+            None => (false, DndAtoms::default()),
+        };
+
+        Some(Self {
+            wm_protocols,
+            wm_delete_window,
+            wm_take_focus,
+            net_wm_window_opacity,
+            net_wm_state,
+            net_wm_state_demands_attention,
+            net_wm_state_hidden,
+            net_wm_state_skip_taskbar,
+            net_wm_window_type,
+            net_wm_window_type_popup_menu,
+            net_wm_pid,
+            wm_state,
+            abs_pressure,
+            abs_tilt_x,
+            abs_tilt_y,
+            dnd_enabled,
+            dnd,
+            custom: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Get the atoms with these names, creating them if they don't exist yet. This is for atoms
+    /// that aren't known up front, like the names of the application's own properties. Atoms are
+    /// never freed, so they're cached for the lifetime of the connection, and only the names that
+    /// weren't interned before cost a round-trip.
+    pub fn intern_custom(
+        &self, conn: &xcb::Connection, names: &[&str],
+    ) -> Result<Vec<Atom>, GenericError> {
+        let mut custom = self.custom.lock().unwrap();
+
+        // splitting request and reply to improve throughput
+        let cookies: Vec<_> = names
+            .iter()
+            .filter(|name| !custom.contains_key(**name))
+            .map(|name| (name, xcb::intern_atom(conn, false, name)))
+            .collect();
+        for (name, cookie) in cookies {
+            custom.insert(name.to_string(), cookie.get_reply()?.atom());
+        }
+
+        Ok(names.iter().map(|name| custom[*name]).collect())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DndAtoms {
+    pub aware: u32,
+    pub enter: u32,
+    pub leave: u32,
+    pub drop: u32,
+    pub position: u32,
+    pub status: u32,
+    pub action_copy: u32,
+    pub action_move: u32,
+    pub action_ask: u32,
+    pub action_list: u32,
+    pub action_description: u32,
+    pub selection: u32,
+    pub finished: u32,
+    pub type_list: u32,
+    pub uri_list: u32,
+    /// The document portal's file transfer, which sandboxed sources offer their files through.
+    #[cfg_attr(not(feature = "portal"), allow(dead_code))]
+    pub portal_file_transfer: u32,
+    pub baseview_transfer: u32,
+    /// The ICCCM `TARGETS` target, which drop targets can ask for to find out what the data is.
+    pub targets: u32,
+}
+
+/// The names of the DND atoms, in the order [`DndAtoms::from_atoms()`] expects them in.
+const DND_ATOM_NAMES: [&str; 18] = [
+    "XdndAware",
+    "XdndEnter",
+    "XdndLeave",
+    "XdndDrop",
+    "XdndPosition",
+    "XdndStatus",
+    "XdndActionCopy",
+    "XdndActionMove",
+    "XdndActionAsk",
+    "XdndActionList",
+    "XdndActionDescription",
+    "XdndSelection",
+    "XdndFinished",
+    "XdndTypeList",
+    "text/uri-list",
+    "application/vnd.portal.filetransfer",
+    "BaseviewDND",
+    "TARGETS",
+];
+
+impl DndAtoms {
+    /// Intern all of the DND atoms in a single round-trip. These are created if they don't exist
+    /// yet, since a bare X server won't have them until some client has used them.
+    fn intern(conn: &xcb::Connection) -> Result<[Atom; DND_ATOM_NAMES.len()], GenericError> {
+        intern_atoms(conn, false, DND_ATOM_NAMES)
+    }
+
+    /// The DND atoms for the atoms interned by [`Self::intern()`].
+    pub(super) fn from_atoms(atoms: [Atom; DND_ATOM_NAMES.len()]) -> Self {
+        let [aware, enter, leave, drop, position, status, action_copy, action_move, action_ask, action_list, action_description, selection, finished, type_list, uri_list, portal_file_transfer, baseview_transfer, targets] =
+            atoms;
+
+        Self {
+            aware,
+            enter,
+            leave,
+            drop,
+            position,
+            status,
+            action_copy,
+            action_move,
+            action_ask,
+            action_list,
+            action_description,
+            selection,
+            finished,
+            type_list,
+            uri_list,
+            portal_file_transfer,
+            baseview_transfer,
+            targets,
+        }
+    }
+
+    /// The XDND action for a drop effect.
+    pub fn action(&self, effect: DropEffect) -> u32 {
+        match effect {
+            DropEffect::Copy => self.action_copy,
+            DropEffect::Move => self.action_move,
+            DropEffect::Ask => self.action_ask,
+        }
+    }
+
+    /// The drop effect for an XDND action, or `None` for `None`. Actions baseview doesn't know
+    /// about, like `XdndActionLink` and `XdndActionPrivate`, don't affect the source's copy of the
+    /// data, so they count as copies.
+    pub fn effect(&self, action: u32) -> Option<DropEffect> {
+        if action == xcb::ATOM_NONE {
+            None
+        } else if action == self.action_move {
+            Some(DropEffect::Move)
+        } else if action == self.action_ask {
+            Some(DropEffect::Ask)
+        } else {
+            Some(DropEffect::Copy)
+        }
+    }
 }
 
 pub struct XcbConnection {
@@ -34,162 +239,308 @@ pub struct XcbConnection {
     pub xlib_display: i32,
 
     pub(crate) atoms: Atoms,
+    /// The XInput extension's major opcode, if the server supports XInput 2. This is used to
+    /// recognize the events for [`super::pen`].
+    pub(super) xinput_opcode: Option<u8>,
+
+    /// The built-in cursors created for `Window::set_mouse_cursor()`. These are shared by all
+    /// windows, and they're never freed since the connection is never closed.
+    cursor_cache: Mutex<HashMap<MouseCursor, u32>>,
+    /// The result of the last [`Self::cached_scale()`] call. The dispatcher clears this when the X
+    /// resources or the screen configuration change.
+    scale_cache: Mutex<Option<DetectedScale>>,
+    /// The keyboard mapping used to translate key events. The dispatcher clears this when the
+    /// server sends a `MappingNotify` event, so it's fetched again for the next key event.
+    keymap: KeymapCache,
+    pub(super) dispatcher: EventDispatcher,
+    /// Used to tell which requests were sent by a single window. See [`RequestCount`].
+    #[cfg(feature = "tracing")]
+    iterations: Mutex<ActiveIterations>,
+}
 
-    // FIXME: Same here, there's a ton of unused cursor machinery in here
-    pub(super) cursor_cache: HashMap<MouseCursor, u32>,
+/// The event loop iterations of all windows. The connection is shared, so the requests sent during
+/// an iteration only all belong to that window if no other window ran an iteration at the same time.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Default)]
+struct ActiveIterations {
+    /// The number of iterations that haven't ended yet.
+    active: usize,
+    /// The number of iterations that have been started.
+    started: u64,
 }
 
-macro_rules! intern_atoms {
-    ($conn:expr, $( $name:ident ),+ ) => {{
-        $(
-            #[allow(non_snake_case)]
-            let $name = xcb::intern_atom($conn, true, stringify!($name));
-        )+
+#[cfg(feature = "tracing")]
+impl ActiveIterations {
+    /// Start an iteration. Returns the iteration's number if no other iteration is running.
+    fn begin(&mut self) -> Option<u64> {
+        self.started += 1;
+        self.active += 1;
 
-        // splitting request and reply to improve throughput
-        //
+        (self.active == 1).then_some(self.started)
+    }
 
-        (
-            $( $name.get_reply()
-                .map(|r| r.atom())
-                .unwrap()),+
-        )
-    }};
+    /// Whether no other iteration was running since `begin()` returned `iteration`.
+    fn ran_alone(&self, iteration: Option<u64>) -> bool {
+        iteration == Some(self.started)
+    }
+
+    fn end(&mut self) {
+        self.active -= 1;
+    }
+}
+
+/// Counts the requests sent during one of a window's event loop iterations. This only reads the
+/// connection's request counter, so it doesn't send any requests of its own. The iteration ends when
+/// this is dropped, which also happens when the handler panics.
+#[cfg(feature = "tracing")]
+pub(super) struct RequestCount {
+    conn: &'static XcbConnection,
+    iteration: Option<u64>,
+    first_request: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl RequestCount {
+    pub fn begin(conn: &'static XcbConnection) -> Self {
+        let iteration = conn.iterations.lock().unwrap().begin();
+
+        Self { conn, iteration, first_request: conn.next_request() }
+    }
+
+    /// The number of requests sent since [`Self::begin()`], or `None` if another window may have
+    /// sent some of them.
+    pub fn requests(self) -> Option<u64> {
+        let requests = self.conn.next_request().wrapping_sub(self.first_request);
+
+        self.conn.iterations.lock().unwrap().ran_alone(self.iteration).then_some(requests)
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for RequestCount {
+    fn drop(&mut self) {
+        self.conn.iterations.lock().unwrap().end();
+    }
+}
+
+/// Intern `names`, returning the atoms in the same order. All requests are sent before waiting on
+/// any of the replies, so this only costs a single round-trip.
+fn intern_atoms<const N: usize>(
+    conn: &xcb::Connection, only_if_exists: bool, names: [&str; N],
+) -> Result<[Atom; N], GenericError> {
+    // splitting request and reply to improve throughput
+    let cookies = names.map(|name| xcb::intern_atom(conn, only_if_exists, name));
+
+    let mut atoms = [xcb::ATOM_NONE; N];
+    for (atom, cookie) in atoms.iter_mut().zip(cookies) {
+        *atom = cookie.get_reply()?.atom();
+    }
+
+    Ok(atoms)
+}
+
+/// Check whether the server supports XInput 2.0, and return the extension's major opcode if it does.
+/// This also tells the server that we understand XInput 2, which is needed before selecting any of
+/// its events.
+fn query_xinput2(conn: &xcb::Connection) -> Option<u8> {
+    let reply = xcb::query_extension(conn, "XInputExtension").get_reply().ok()?;
+    if !reply.present() {
+        return None;
+    }
+
+    let (mut major, mut minor) = (2, 0);
+    let status =
+        unsafe { x11::xinput2::XIQueryVersion(conn.get_raw_dpy(), &mut major, &mut minor) };
+
+    (status == x11::xlib::Success as i32).then_some(reply.major_opcode())
+}
+
+/// Ask the server to send `PropertyNotify` events for the root windows, so the scale factor can be
+/// updated when `xrdb` changes the `RESOURCE_MANAGER` property. Baseview doesn't select any other
+/// events on the root windows, so this doesn't replace an existing event mask.
+fn select_resource_changes(conn: &xcb::Connection) {
+    for screen in conn.get_setup().roots() {
+        xcb::change_window_attributes(
+            conn,
+            screen.root(),
+            &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)],
+        );
+    }
+    conn.flush();
+}
+
+/// Parse the `Xft.dpi` entry from the contents of the `RESOURCE_MANAGER` property. `xrdb` stores
+/// one `name:\tvalue` entry per line. Later entries take precedence, like they would with Xrm.
+fn parse_xft_dpi(resource_string: &str) -> Option<f64> {
+    resource_string
+        .lines()
+        .rev()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim() == "Xft.dpi")
+        .filter_map(|(_, value)| value.trim().parse::<f64>().ok())
+        .find(|dpi| dpi.is_finite() && *dpi > 0.0)
+}
+
+/// Ask the server to send RandR screen change notifications for every screen, and return the
+/// extension's first event code if it does. These are sent when the resolution changes or when a
+/// monitor is added or removed, which can change the system's scale factor.
+fn select_randr_screen_changes(conn: &xcb::Connection) -> Option<u8> {
+    let extension = conn.get_extension_data(xcb::randr::id())?;
+    if !extension.present() {
+        return None;
+    }
+    let first_event = extension.first_event();
+
+    xcb::randr::query_version(conn, 1, 2).get_reply().ok()?;
+    for screen in conn.get_setup().roots() {
+        xcb::randr::select_input(conn, screen.root(), xcb::randr::NOTIFY_MASK_SCREEN_CHANGE as u16);
+    }
+    conn.flush();
+
+    Some(first_event)
 }
 
 impl XcbConnection {
-    #![allow(non_snake_case)]
-    pub fn new() -> Result<Self, xcb::base::ConnError> {
+    /// The connection used by every window in this process. This connects to the X server when it's
+    /// called for the first time, and it starts the thread that dispatches the events to the
+    /// windows' event loops. The connection stays open until the process exits.
+    pub fn shared() -> &'static Self {
+        static CONNECTION: OnceLock<XcbConnection> = OnceLock::new();
+        static DISPATCHER_STARTED: Once = Once::new();
+
+        let connection = CONNECTION.get_or_init(|| {
+            // The windows use the Xlib display from multiple threads, for instance for loading
+            // cursors and for OpenGL. This only has an effect if nothing else in the process has
+            // used Xlib yet, recent versions of Xlib always enable this.
+            unsafe { x11::xlib::XInitThreads() };
+
+            Self::new().expect("Could not connect to the X server")
+        });
+        DISPATCHER_STARTED.call_once(|| {
+            thread::Builder::new()
+                .name("baseview-x11-events".into())
+                .spawn(|| connection.dispatcher.run(connection))
+                .expect("Could not start the X11 event thread");
+        });
+
+        connection
+    }
+
+    fn new() -> Result<Self, xcb::base::ConnError> {
         let (conn, xlib_display) = xcb::Connection::connect_with_xlib_display()?;
 
         conn.set_event_queue_owner(xcb::base::EventQueueOwner::Xcb);
 
-        let (wm_protocols, wm_delete_window) = intern_atoms!(&conn, WM_PROTOCOLS, WM_DELETE_WINDOW);
-        let (
-            dnd_enter,
-            dnd_leave,
-            dnd_drop,
-            dnd_position,
-            dnd_status,
-            dnd_action_private,
-            dnd_action_copy,
-            dnd_selection,
-            dnd_finished,
-            dnd_type_list,
-        ) = intern_atoms!(
-            &conn,
-            XdndEnter,
-            XdndLeave,
-            XdndDrop,
-            XdndPosition,
-            XdndStatus,
-            XdndActionPrivate,
-            XdndActionCopy,
-            XdndSelection,
-            XdndFinished,
-            XdndTypeList
-        );
-        let dnd_uri_list = Self::_get_atom(&conn, "text/uri-list");
-        let dnd_baseview_transfer = Self::_create_atom(&conn, "BaseviewDND");
+        // `WM_TAKE_FOCUS` is only created once a window manager or another client uses it, so most
+        // atoms are created if they don't exist yet. The tablet valuator labels are only looked
+        // up, since they're only useful once a tablet driver has created them.
+        let replies = AtomReplies {
+            wm_protocols: intern_atoms(
+                &conn,
+                false,
+                ["WM_PROTOCOLS", "WM_DELETE_WINDOW", "WM_TAKE_FOCUS"],
+            )
+            .ok(),
+            wm_state: intern_atoms(
+                &conn,
+                false,
+                [
+                    "_NET_WM_WINDOW_OPACITY",
+                    "_NET_WM_STATE",
+                    "_NET_WM_STATE_DEMANDS_ATTENTION",
+                    "WM_STATE",
+                ],
+            )
+            .ok(),
+            net_wm_state_hidden: intern_atoms(&conn, false, ["_NET_WM_STATE_HIDDEN"]).ok(),
+            popup: intern_atoms(
+                &conn,
+                false,
+                [
+                    "_NET_WM_STATE_SKIP_TASKBAR",
+                    "_NET_WM_WINDOW_TYPE",
+                    "_NET_WM_WINDOW_TYPE_POPUP_MENU",
+                ],
+            )
+            .ok(),
+            net_wm_pid: intern_atoms(&conn, false, ["_NET_WM_PID"]).ok(),
+            tablet: intern_atoms(&conn, true, ["Abs Pressure", "Abs Tilt X", "Abs Tilt Y"]).ok(),
+            dnd: DndAtoms::intern(&conn).ok(),
+        };
+        let atoms = Atoms::from_replies(replies).expect("Could not intern the WM_PROTOCOLS atoms");
+
+        let xinput_opcode = query_xinput2(&conn);
+        let randr_first_event = select_randr_screen_changes(&conn);
+        select_resource_changes(&conn);
 
         Ok(Self {
             conn,
             xlib_display,
 
-            atoms: Atoms {
-                wm_protocols,
-                wm_delete_window,
-                dnd_enter,
-                dnd_leave,
-                dnd_drop,
-                dnd_position,
-                dnd_status,
-                dnd_action_private,
-                dnd_action_copy,
-                dnd_selection,
-                dnd_finished,
-                dnd_type_list,
-                dnd_uri_list,
-                dnd_baseview_transfer,
-            },
-
-            cursor_cache: HashMap::new(),
-        })
-    }
-
-    fn _get_atom(conn: &xcb::Connection, name: &str) -> Atom {
-        xcb::intern_atom(conn, true, name)
-            .get_reply()
-            .expect(&format!("Error getting atom {name}"))
-            .atom()
-    }
+            atoms,
 
-    fn _create_atom(conn: &xcb::Connection, name: &str) -> Atom {
-        xcb::intern_atom(conn, false, name)
-            .get_reply()
-            .expect(&format!("Error getting atom {name}"))
-            .atom()
-    }
+            xinput_opcode,
 
-    pub fn get_atom(&self, name: &str) -> Atom {
-        Self::_get_atom(&self.conn, name)
+            cursor_cache: Mutex::new(HashMap::new()),
+            scale_cache: Mutex::new(None),
+            keymap: KeymapCache::default(),
+            dispatcher: EventDispatcher::new(xinput_opcode, randr_first_event),
+            #[cfg(feature = "tracing")]
+            iterations: Mutex::new(ActiveIterations::default()),
+        })
     }
 
     // Try to get the scaling with this function first.
     // If this gives you `None`, fall back to `get_scaling_screen_dimensions`.
     // If neither work, I guess just assume 96.0 and don't do any scaling.
     fn get_scaling_xft(&self) -> Option<f64> {
-        use x11::xlib::{
-            XResourceManagerString, XrmDestroyDatabase, XrmGetResource, XrmGetStringDatabase,
-            XrmValue,
-        };
+        // Xlib only reads the resources once when connecting, so `XResourceManagerString()` would
+        // keep returning the old DPI after it has been changed
+        let resources = self.get_resource_manager_string()?;
 
-        let display = self.conn.get_raw_dpy();
-        unsafe {
-            let rms = XResourceManagerString(display);
-            if !rms.is_null() {
-                let db = XrmGetStringDatabase(rms);
-                if !db.is_null() {
-                    let mut value = XrmValue { size: 0, addr: std::ptr::null_mut() };
-
-                    let mut value_type: *mut std::os::raw::c_char = std::ptr::null_mut();
-                    let name_c_str = CString::new("Xft.dpi").unwrap();
-                    let c_str = CString::new("Xft.Dpi").unwrap();
-
-                    let dpi = if XrmGetResource(
-                        db,
-                        name_c_str.as_ptr(),
-                        c_str.as_ptr(),
-                        &mut value_type,
-                        &mut value,
-                    ) != 0
-                        && !value.addr.is_null()
-                    {
-                        let value_addr: &CStr = CStr::from_ptr(value.addr);
-                        value_addr.to_str().ok();
-                        let value_str = value_addr.to_str().ok()?;
-                        let value_f64: f64 = value_str.parse().ok()?;
-                        let dpi_to_scale = value_f64 / 96.0;
-                        Some(dpi_to_scale)
-                    } else {
-                        None
-                    };
-                    XrmDestroyDatabase(db);
-
-                    return dpi;
-                }
-            }
+        parse_xft_dpi(&resources).map(|dpi| dpi / 96.0)
+    }
+
+    /// The current contents of the first screen's `RESOURCE_MANAGER` property, which is where
+    /// `xrdb` stores the X resources.
+    fn get_resource_manager_string(&self) -> Option<String> {
+        let setup = self.conn.get_setup();
+        let root = setup.roots().next()?.root();
+        let reply = xcb::get_property(
+            &self.conn,
+            false,
+            root,
+            xcb::ATOM_RESOURCE_MANAGER,
+            xcb::ATOM_STRING,
+            0,
+            u32::MAX,
+        )
+        .get_reply()
+        .ok()?;
+        if reply.value_len() == 0 {
+            return None;
         }
-        None
+
+        Some(String::from_utf8_lossy(reply.value::<u8>()).into_owned())
     }
 
     // Try to get the scaling with `get_scaling_xft` first.
     // Only use this function as a fallback.
     // If neither work, I guess just assume 96.0 and don't do any scaling.
-    fn get_scaling_screen_dimensions(&self) -> Option<f64> {
-        // Figure out screen information
+    //
+    // This returns separate horizontal and vertical scale factors, since screens with non-square
+    // pixels have a different DPI in either direction.
+    fn get_scaling_screen_dimensions(&self) -> Option<(f64, f64)> {
+        // Figure out screen information. The setup data isn't updated when the screen changes, so
+        // the size from the last RandR notification takes precedence.
         let setup = self.conn.get_setup();
         let screen = setup.roots().nth(self.xlib_display as usize).unwrap();
+        let size = self.dispatcher.screen_size(screen.root()).unwrap_or(ScreenSize {
+            width_px: screen.width_in_pixels(),
+            height_px: screen.height_in_pixels(),
+            width_mm: screen.width_in_millimeters(),
+            height_mm: screen.height_in_millimeters(),
+        });
 
         // Get the DPI from the screen struct
         //
@@ -197,29 +548,79 @@ impl XcbConnection {
         // dpi = N pixels / (M millimeters / (25.4 millimeters / 1 inch))
         //     = N pixels / (M inch / 25.4)
         //     = N * 25.4 pixels / M inch
-        let width_px = screen.width_in_pixels() as f64;
-        let width_mm = screen.width_in_millimeters() as f64;
-        let height_px = screen.height_in_pixels() as f64;
-        let height_mm = screen.height_in_millimeters() as f64;
-        let _xres = width_px * 25.4 / width_mm;
+        let width_px = size.width_px as f64;
+        let width_mm = size.width_mm as f64;
+        let height_px = size.height_px as f64;
+        let height_mm = size.height_mm as f64;
+        let xres = width_px * 25.4 / width_mm;
         let yres = height_px * 25.4 / height_mm;
 
+        let xscale = xres / 96.0;
         let yscale = yres / 96.0;
 
-        // TODO: choose between `xres` and `yres`? (probably both are the same?)
-        Some(yscale)
+        // Some X servers, for instance virtual ones, report a physical size of zero millimeters
+        let is_valid = |scale: f64| scale.is_finite() && scale > 0.0;
+        if is_valid(xscale) && is_valid(yscale) {
+            Some((xscale, yscale))
+        } else {
+            None
+        }
     }
 
-    #[inline]
-    pub fn get_scaling(&self) -> Option<f64> {
-        self.get_scaling_xft().or_else(|| self.get_scaling_screen_dimensions())
+    /// The scale factors the X server reports. These may not be plausible, so
+    /// [`resolve_system_scale()`](super::scaling::resolve_system_scale) decides which to use.
+    fn get_scaling(&self) -> DetectedScale {
+        DetectedScale {
+            xft: self.get_scaling_xft(),
+            screen_dimensions: self.get_scaling_screen_dimensions(),
+        }
+    }
+
+    /// The scale factors the X server reports. Reading them requires a round-trip to the server,
+    /// so the result is reused until the X resources or the screen configuration change.
+    pub fn cached_scale(&self) -> DetectedScale {
+        *self.scale_cache.lock().unwrap().get_or_insert_with(|| self.get_scaling())
+    }
+
+    /// Forget the cached scale factors. Called by the dispatcher when they may have changed.
+    pub(super) fn invalidate_scale(&self) {
+        *self.scale_cache.lock().unwrap() = None;
+    }
+
+    /// Call `f` with the current keyboard mapping. This fetches the mapping from the server if it
+    /// changed since it was last used, which is a single round-trip.
+    pub(super) fn with_keymap<R>(&self, f: impl FnOnce(&Keymap) -> R) -> R {
+        self.keymap.with(|| Keymap::fetch(&self.conn), f)
+    }
+
+    /// Forget the keyboard mapping. Called by the dispatcher when the keyboard layout changed.
+    pub(super) fn invalidate_keymap(&self) {
+        self.keymap.invalidate();
+    }
+
+    /// The sequence number the next request will get. Xlib syncs its own request counter with
+    /// XCB's for this, which flushes XCB's output buffer but doesn't send a request.
+    #[cfg(feature = "tracing")]
+    fn next_request(&self) -> u64 {
+        unsafe { x11::xlib::XNextRequest(self.conn.get_raw_dpy()) as u64 }
     }
 
     #[inline]
-    pub fn get_cursor_xid(&mut self, cursor: MouseCursor) -> u32 {
+    pub fn get_cursor_xid(&self, cursor: MouseCursor) -> u32 {
         let dpy = self.conn.get_raw_dpy();
 
-        *self.cursor_cache.entry(cursor).or_insert_with(|| cursor::get_xcursor(dpy, cursor))
+        *self
+            .cursor_cache
+            .lock()
+            .unwrap()
+            .entry(cursor)
+            .or_insert_with(|| cursor::get_xcursor(dpy, cursor))
+    }
+
+    /// Create a cursor from an image. The window that registered the cursor owns it, and it needs
+    /// to free the cursor again when it's closed.
+    pub fn create_image_cursor(&self, icon: &CursorIcon) -> u32 {
+        cursor::create_image_cursor(self.conn.get_raw_dpy(), icon)
     }
 
     pub fn send_client_message(
@@ -265,3 +666,187 @@ impl XcbConnection {
         dbg!(window_map);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn iterations_running_alone() {
+        let mut iterations = ActiveIterations::default();
+        for _ in 0..3 {
+            let iteration = iterations.begin();
+            assert!(iteration.is_some());
+            assert!(iterations.ran_alone(iteration));
+            iterations.end();
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn overlapping_iterations() {
+        let mut iterations = ActiveIterations::default();
+
+        // Another window's iteration is still running
+        let first = iterations.begin();
+        let second = iterations.begin();
+        assert_eq!(second, None);
+        iterations.end();
+        assert!(!iterations.ran_alone(first));
+        iterations.end();
+
+        // Another window's iteration starts and ends in the middle of this one
+        let first = iterations.begin();
+        let second = iterations.begin();
+        iterations.end();
+        assert!(!iterations.ran_alone(first));
+        assert!(!iterations.ran_alone(second));
+        iterations.end();
+
+        // And once they're done, iterations are counted again
+        let iteration = iterations.begin();
+        assert!(iterations.ran_alone(iteration));
+        iterations.end();
+    }
+
+    /// Replies for every group, where each atom is one more than the atom before it.
+    fn replies() -> AtomReplies {
+        let mut next = 0;
+        let mut atoms = || {
+            next += 1;
+            next
+        };
+
+        AtomReplies {
+            wm_protocols: Some([(); 3].map(|_| atoms())),
+            wm_state: Some([(); 4].map(|_| atoms())),
+            net_wm_state_hidden: Some([(); 1].map(|_| atoms())),
+            popup: Some([(); 3].map(|_| atoms())),
+            net_wm_pid: Some([(); 1].map(|_| atoms())),
+            tablet: Some([(); 3].map(|_| atoms())),
+            dnd: Some([(); DND_ATOM_NAMES.len()].map(|_| atoms())),
+        }
+    }
+
+    #[test]
+    fn all_replies() {
+        let atoms = Atoms::from_replies(replies()).unwrap();
+
+        assert_eq!([atoms.wm_protocols, atoms.wm_delete_window, atoms.wm_take_focus], [1, 2, 3]);
+        assert_eq!(atoms.net_wm_window_opacity, 4);
+        assert_eq!(atoms.wm_state, 7);
+        assert_eq!(atoms.net_wm_state_hidden, 8);
+        assert_eq!(atoms.net_wm_window_type_popup_menu, 11);
+        assert_eq!(atoms.net_wm_pid, 12);
+        assert_eq!([atoms.abs_pressure, atoms.abs_tilt_x, atoms.abs_tilt_y], [13, 14, 15]);
+        assert!(atoms.dnd_enabled);
+        assert_eq!(atoms.dnd.aware, 16);
+        assert_eq!(atoms.dnd.targets, 15 + DND_ATOM_NAMES.len() as Atom);
+    }
+
+    #[test]
+    fn dnd_atoms_match_their_names() {
+        let dnd = DndAtoms::from_atoms(std::array::from_fn(|i| i as Atom));
+        let name = |atom: Atom| DND_ATOM_NAMES[atom as usize];
+
+        assert_eq!(name(dnd.aware), "XdndAware");
+        assert_eq!(name(dnd.enter), "XdndEnter");
+        assert_eq!(name(dnd.leave), "XdndLeave");
+        assert_eq!(name(dnd.drop), "XdndDrop");
+        assert_eq!(name(dnd.position), "XdndPosition");
+        assert_eq!(name(dnd.status), "XdndStatus");
+        assert_eq!(name(dnd.action_copy), "XdndActionCopy");
+        assert_eq!(name(dnd.action_move), "XdndActionMove");
+        assert_eq!(name(dnd.action_ask), "XdndActionAsk");
+        assert_eq!(name(dnd.action_list), "XdndActionList");
+        assert_eq!(name(dnd.action_description), "XdndActionDescription");
+        assert_eq!(name(dnd.selection), "XdndSelection");
+        assert_eq!(name(dnd.finished), "XdndFinished");
+        assert_eq!(name(dnd.type_list), "XdndTypeList");
+        assert_eq!(name(dnd.uri_list), "text/uri-list");
+        assert_eq!(name(dnd.portal_file_transfer), "application/vnd.portal.filetransfer");
+        assert_eq!(name(dnd.baseview_transfer), "BaseviewDND");
+        assert_eq!(name(dnd.targets), "TARGETS");
+    }
+
+    #[test]
+    fn missing_wm_protocols() {
+        let replies = AtomReplies { wm_protocols: None, ..replies() };
+
+        assert!(Atoms::from_replies(replies).is_none());
+    }
+
+    #[test]
+    fn missing_dnd_atoms_disable_dnd() {
+        let replies = AtomReplies { dnd: None, ..replies() };
+        let atoms = Atoms::from_replies(replies).unwrap();
+
+        assert!(!atoms.dnd_enabled);
+        assert_eq!(atoms.dnd.aware, xcb::ATOM_NONE);
+        assert_eq!(atoms.dnd.uri_list, xcb::ATOM_NONE);
+        assert_eq!(atoms.dnd.selection, xcb::ATOM_NONE);
+        // The other atoms aren't affected
+        assert_eq!(atoms.wm_protocols, 1);
+        assert_eq!(atoms.net_wm_pid, 12);
+    }
+
+    #[test]
+    fn xft_dpi() {
+        // As written by `xrdb`, with a tab after the colon
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t144\n"), Some(144.0));
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t120.5"), Some(120.5));
+        assert_eq!(parse_xft_dpi("  Xft.dpi :  96  \t\n"), Some(96.0));
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t192\r\n"), Some(192.0));
+
+        let resources = "Xcursor.size:\t24\nXft.antialias:\t1\nXft.dpi:\t168\nXft.hinting:\t1\n";
+        assert_eq!(parse_xft_dpi(resources), Some(168.0));
+    }
+
+    #[test]
+    fn xft_dpi_later_entries_win() {
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t96\nXft.dpi:\t144\n"), Some(144.0));
+        // Unless the later entries are invalid
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t144\nXft.dpi:\tlarge\n"), Some(144.0));
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t144\nXft.dpi:\t0\n"), Some(144.0));
+    }
+
+    #[test]
+    fn xft_dpi_missing_or_malformed() {
+        assert_eq!(parse_xft_dpi(""), None);
+        assert_eq!(parse_xft_dpi("Xcursor.size:\t24\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi\t96\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi:\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t96dpi\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t0\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi:\t-96\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi:\tinf\n"), None);
+        assert_eq!(parse_xft_dpi("Xft.dpi:\tNaN\n"), None);
+        // Other resources that happen to end in the same name
+        assert_eq!(parse_xft_dpi("*Xft.dpi.backup:\t96\nMyApp.Xft.dpi:\t96\n"), None);
+    }
+
+    #[test]
+    fn missing_optional_atoms() {
+        let replies = AtomReplies {
+            wm_state: None,
+            net_wm_state_hidden: None,
+            popup: None,
+            net_wm_pid: None,
+            tablet: None,
+            ..replies()
+        };
+        let atoms = Atoms::from_replies(replies).unwrap();
+
+        assert_eq!(atoms.wm_take_focus, 3);
+        assert_eq!(atoms.net_wm_window_opacity, xcb::ATOM_NONE);
+        assert_eq!(atoms.net_wm_state_demands_attention, xcb::ATOM_NONE);
+        assert_eq!(atoms.wm_state, xcb::ATOM_NONE);
+        assert_eq!(atoms.net_wm_state_hidden, xcb::ATOM_NONE);
+        assert_eq!(atoms.net_wm_state_skip_taskbar, xcb::ATOM_NONE);
+        assert_eq!(atoms.net_wm_window_type_popup_menu, xcb::ATOM_NONE);
+        assert_eq!(atoms.net_wm_pid, xcb::ATOM_NONE);
+        assert_eq!(atoms.abs_pressure, xcb::ATOM_NONE);
+        assert!(atoms.dnd_enabled);
+    }
+}
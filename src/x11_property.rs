@@ -0,0 +1,16 @@
+/// The value of a custom X11 window property, for handshakes with a host or another process
+/// through the window's properties. See
+/// [`WindowOpenOptions::x11_properties`](crate::WindowOpenOptions::x11_properties).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum X11PropertyValue {
+    /// A list of 32-bit numbers with the `CARDINAL` type.
+    Cardinal(Vec<u32>),
+    /// A list of atoms with the `ATOM` type, given by their names. The atoms are created if they
+    /// don't exist yet.
+    Atom(Vec<String>),
+    /// Text with the `STRING` type, which is encoded as Latin-1. Characters that aren't part of
+    /// Latin-1 are replaced by a question mark.
+    String(String),
+    /// Text with the `UTF8_STRING` type.
+    Utf8String(String),
+}